@@ -0,0 +1,153 @@
+// benches/corpus/mod.rs
+//! Synthetic projects for the benchmarks
+//!
+//! Every corpus is generated into a temporary directory from its size alone,
+//! so runs on different machines and branches measure the same input.
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const WORDS: &[&str] = &[
+    "agent",
+    "workflow",
+    "context",
+    "phase",
+    "feature",
+    "review",
+    "spec",
+    "task",
+    "module",
+    "orchestrator",
+    "parallel",
+    "index",
+    "cache",
+    "report",
+    "quality",
+    "design",
+];
+
+/// `count` words picked from `seed`, deterministically
+fn sentence(seed: usize, count: usize) -> String {
+    (0..count)
+        .map(|i| WORDS[(seed * 7 + i * 13) % WORDS.len()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Markdown document `i`: YAML frontmatter, sections, links and a horizontal
+/// rule in the body
+pub fn markdown_document(i: usize) -> String {
+    let mut doc = format!(
+        "---\ntitle: \"Document {i}\"\ndescription: \"{}\"\ntype: \"{}\"\nstatus: \"active\"\n\
+         created: \"2024-01-{:02}\"\nupdated: \"2024-02-{:02}\"\nauthor: \"Bench\"\n\
+         tags:\n  - {}\n  - {}\n---\n\n# Document {i}\n\n",
+        sentence(i, 8),
+        ["design", "guide", "feature", "session"][i % 4],
+        i % 28 + 1,
+        i % 28 + 1,
+        WORDS[i % WORDS.len()],
+        WORDS[(i + 3) % WORDS.len()],
+    );
+    for section in 0..6 {
+        doc.push_str(&format!("## Section {section}\n\n"));
+        for paragraph in 0..3 {
+            doc.push_str(&sentence(i + section * 3 + paragraph, 40));
+            doc.push_str(&format!(
+                " See [document {}](../section-{}/doc-{}.md).\n\n",
+                i + paragraph + 1,
+                (i + 1) % 10,
+                i + paragraph + 1
+            ));
+        }
+        if section == 2 {
+            doc.push_str("---\n\n");
+        }
+    }
+    doc
+}
+
+/// A project with `files` Markdown documents spread over ten directories
+pub fn markdown_project(files: usize) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for i in 0..files {
+        let path = dir
+            .path()
+            .join("docs")
+            .join(format!("section-{}", i % 10))
+            .join(format!("doc-{i}.md"));
+        write(&path, &markdown_document(i));
+    }
+    dir
+}
+
+/// A project with `workflows` CDE workflow files of three to six phases
+pub fn workflow_project(workflows: usize) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for i in 0..workflows {
+        let mut workflow = format!(
+            "name: \"Workflow {i}\"\nversion: \"1.{}\"\nphases:\n",
+            i % 10
+        );
+        for phase in 0..3 + i % 4 {
+            workflow.push_str(&format!(
+                "  - id: phase-{phase}\n    name: \"{}\"\n    description: \"{}\"\n\
+                 \x20   inputs: [\"spec.md\", \"context.json\"]\n    outputs: [\"phase-{phase}.md\"]\n\
+                 \x20   prompt_template: \"prompts/phase-{phase}.poml\"\n",
+                sentence(i + phase, 2),
+                sentence(i * phase + 1, 12),
+            ));
+        }
+        write(
+            &dir.path()
+                .join(".cde")
+                .join("workflows")
+                .join(format!("workflow-{i}.yml")),
+            &workflow,
+        );
+    }
+    dir
+}
+
+/// A tree `depth` directories deep where every directory has `fanout`
+/// subdirectories and a Rust, a Python and a Markdown file
+pub fn deep_tree(depth: usize, fanout: usize) -> TempDir {
+    fn fill(dir: &Path, level: usize, depth: usize, fanout: usize, seed: &mut usize) {
+        *seed += 1;
+        let n = *seed;
+        write(
+            &dir.join(format!("module_{n}.rs")),
+            &format!(
+                "// TODO: {}\npub fn f{n}() -> usize {{\n    {n}\n}}\n",
+                sentence(n, 6)
+            ),
+        );
+        write(
+            &dir.join(format!("script_{n}.py")),
+            &format!(
+                "def handler_{n}():\n    \"\"\"{}\"\"\"\n    return {n}\n",
+                sentence(n, 10)
+            ),
+        );
+        write(&dir.join("README.md"), &markdown_document(n));
+        if level < depth {
+            for child in 0..fanout {
+                fill(
+                    &dir.join(format!("d{child}")),
+                    level + 1,
+                    depth,
+                    fanout,
+                    seed,
+                );
+            }
+        }
+    }
+    let dir = TempDir::new().unwrap();
+    fill(dir.path(), 1, depth, fanout, &mut 0);
+    dir
+}
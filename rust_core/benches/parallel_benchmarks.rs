@@ -1,62 +1,146 @@
 // benches/parallel_benchmarks.rs
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use std::path::Path;
+//! Benchmarks of the core functions on synthetic projects
+//!
+//! The functions are called through `cde_rust_core::api`, without Python. To
+//! check a branch for regressions, record a baseline on main and compare:
+//!
+//! ```text
+//! git checkout main
+//! cargo bench --bench parallel_benchmarks -- --save-baseline main
+//! git checkout my-branch
+//! cargo bench --bench parallel_benchmarks -- --baseline main
+//! python scripts/bench/check_regressions.py --threshold 10
+//! ```
+//!
+//! The `rust-benchmarks` workflow does the same on pull requests.
 
-// Import functions from the library
-// Note: This requires the library to expose these functions publicly
-// For now, we'll benchmark at the Python interface level
+mod corpus;
+
+use cde_rust_core::api::{
+    analyze_documentation_quality, extract_metadata, grep_project, scan_documentation,
+    scan_project, validate_workflows, CancellationToken, GrepOptions, ProgressSink, ScanHandle,
+    ScanOptions,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// Markdown files per project
+const MARKDOWN_FILES: &[usize] = &[100, 1_000];
+
+/// Workflow files per project
+const WORKFLOWS: &[usize] = &[50, 500];
+
+/// Depths of binary trees: 63 and 1023 directories, three files each
+const TREE_DEPTHS: &[usize] = &[6, 10];
 
 fn benchmark_scan_documentation(c: &mut Criterion) {
-    let project_path = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .to_string_lossy()
-        .to_string();
-
-    c.bench_function("scan_documentation", |b| {
-        b.iter(|| {
-            // This would call the actual Rust function
-            // For now, we'll measure the full Python interface
-            black_box(&project_path);
+    let mut group = c.benchmark_group("scan_documentation");
+    for &files in MARKDOWN_FILES {
+        let project = corpus::markdown_project(files);
+        let root = project.path().to_str().unwrap();
+        group.throughput(Throughput::Elements(files as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(files), root, |b, root| {
+            b.iter(|| black_box(scan_documentation(root).unwrap()));
         });
-    });
+    }
+    group.finish();
 }
 
 fn benchmark_analyze_quality(c: &mut Criterion) {
-    let project_path = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .to_string_lossy()
-        .to_string();
-
-    c.bench_function("analyze_documentation_quality", |b| {
-        b.iter(|| {
-            black_box(&project_path);
+    let mut group = c.benchmark_group("analyze_documentation_quality");
+    for &files in MARKDOWN_FILES {
+        let project = corpus::markdown_project(files);
+        let root = project.path().to_str().unwrap();
+        group.throughput(Throughput::Elements(files as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(files), root, |b, root| {
+            b.iter(|| {
+                black_box(
+                    analyze_documentation_quality(
+                        root,
+                        &CancellationToken::default(),
+                        &ProgressSink::default(),
+                    )
+                    .unwrap(),
+                )
+            });
         });
-    });
+    }
+    group.finish();
 }
 
 fn benchmark_validate_workflows(c: &mut Criterion) {
-    let project_path = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .to_string_lossy()
-        .to_string();
-
-    c.bench_function("validate_workflows", |b| {
+    let mut group = c.benchmark_group("validate_workflows");
+    for &workflows in WORKFLOWS {
+        let project = corpus::workflow_project(workflows);
+        let root = project.path().to_str().unwrap();
+        group.throughput(Throughput::Elements(workflows as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(workflows), root, |b, root| {
+            b.iter(|| black_box(validate_workflows(root).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_scan_project(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan_project");
+    for &depth in TREE_DEPTHS {
+        let project = corpus::deep_tree(depth, 2);
+        let root = project.path().to_str().unwrap();
+        group.bench_with_input(BenchmarkId::new("depth", depth), root, |b, root| {
+            b.iter(|| {
+                black_box(
+                    scan_project(
+                        root,
+                        Vec::new(),
+                        Vec::new(),
+                        &ScanOptions::default(),
+                        &ScanHandle::default(),
+                        &ProgressSink::default(),
+                    )
+                    .unwrap(),
+                )
+            });
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_grep_project(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grep_project");
+    for &depth in TREE_DEPTHS {
+        let project = corpus::deep_tree(depth, 2);
+        let root = project.path().to_str().unwrap();
+        group.bench_with_input(BenchmarkId::new("depth", depth), root, |b, root| {
+            b.iter(|| {
+                black_box(grep_project(root, r"TODO: \w+", &GrepOptions::default()).unwrap())
+            });
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_extract_metadata(c: &mut Criterion) {
+    let documents: Vec<String> = (0..100).map(corpus::markdown_document).collect();
+    let bytes: usize = documents.iter().map(String::len).sum();
+    let mut group = c.benchmark_group("extract_metadata");
+    group.throughput(Throughput::Bytes(bytes as u64));
+    group.bench_function("100_documents", |b| {
         b.iter(|| {
-            black_box(&project_path);
+            for document in &documents {
+                black_box(extract_metadata(document));
+            }
         });
     });
+    group.finish();
 }
 
 criterion_group!(
     benches,
     benchmark_scan_documentation,
     benchmark_analyze_quality,
-    benchmark_validate_workflows
+    benchmark_validate_workflows,
+    benchmark_scan_project,
+    benchmark_grep_project,
+    benchmark_extract_metadata
 );
 criterion_main!(benches);
@@ -0,0 +1,533 @@
+// src/agent_registry.rs
+//! Registry of the CLI agents a project can route work to
+//!
+//! Each agent is described by a YAML file under `.cde/agents/`: its `name`,
+//! the `command` that runs it, the `capabilities` it offers, a relative `cost`
+//! and the `platforms` it runs on. Definitions are checked the way config files
+//! are, with issues the SARIF reporting understands, and the program of every
+//! valid definition is looked up on `PATH` in parallel. An agent is available
+//! when its definition is valid, it supports this platform and its program is
+//! installed; the capability matrix lists the available agents per capability,
+//! cheapest first.
+
+use crate::config_validator::ValidationIssue;
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use crate::process_manager::executable;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the definitions, relative to the project root
+pub const DEFAULT_AGENTS_DIR: &str = ".cde/agents";
+
+/// Platform names a definition may list, besides "unix"
+const PLATFORMS: [&str; 3] = ["linux", "macos", "windows"];
+
+/// `command` as one line ("gemini -p") or as argv
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommandSpec {
+    Line(String),
+    Argv(Vec<String>),
+}
+
+impl CommandSpec {
+    pub fn argv(&self) -> Vec<String> {
+        match self {
+            CommandSpec::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+            CommandSpec::Argv(argv) => argv.clone(),
+        }
+    }
+}
+
+/// Content of a definition file
+#[derive(Debug, Clone, Deserialize)]
+struct AgentFile {
+    name: Option<String>,
+    command: CommandSpec,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    cost: Option<f64>,
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentDefinition {
+    /// File name without extension when the file has no `name`
+    pub name: String,
+    pub command: Vec<String>,
+    pub description: Option<String>,
+    pub capabilities: Vec<String>,
+    /// Relative cost of a run, for routing; unknown when absent
+    pub cost: Option<f64>,
+    /// Platforms it runs on, every one when empty
+    pub platforms: Vec<String>,
+    /// Definition file, relative to the root
+    pub file: String,
+}
+
+impl AgentDefinition {
+    /// Whether the agent runs on the platform `os` (as in `std::env::consts::OS`)
+    pub fn supports(&self, os: &str) -> bool {
+        self.platforms.is_empty()
+            || self
+                .platforms
+                .iter()
+                .any(|platform| platform == os || (platform == "unix" && os != "windows"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentStatus {
+    #[serde(flatten)]
+    pub definition: AgentDefinition,
+    /// Whether the program of `command` is on `PATH`
+    pub installed: bool,
+    /// Resolved program
+    pub executable: Option<String>,
+    pub platform_supported: bool,
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentRegistry {
+    pub agents: Vec<AgentStatus>,
+    /// Available agents per capability, cheapest first
+    pub capabilities: BTreeMap<String, Vec<String>>,
+    pub available: Vec<String>,
+    pub issues: Vec<ValidationIssue>,
+    /// Whether every definition is valid
+    pub valid: bool,
+    pub platform: String,
+    pub summary: String,
+}
+
+fn warning(file: &str, rule: &str, message: String) -> ValidationIssue {
+    ValidationIssue {
+        severity: "warning".to_string(),
+        ..ValidationIssue::error(file, None, rule, message)
+    }
+}
+
+/// Definition in `content` of the file `relative`, with the issues found in it;
+/// None when it cannot be used
+pub fn parse_definition(
+    content: &str,
+    relative: &str,
+) -> (Option<AgentDefinition>, Vec<ValidationIssue>) {
+    let value: serde_yaml::Value = match serde_yaml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            let line = e.location().map(|location| location.line());
+            let message = format!("Invalid YAML syntax: {}", e);
+            return (
+                None,
+                vec![ValidationIssue::error(
+                    relative,
+                    line,
+                    "agent-syntax",
+                    message,
+                )],
+            );
+        }
+    };
+    let file: AgentFile = match serde_yaml::from_value(value) {
+        Ok(file) => file,
+        Err(e) => {
+            let message = format!("Invalid agent definition: {}", e);
+            return (
+                None,
+                vec![ValidationIssue::error(
+                    relative,
+                    None,
+                    "agent-field",
+                    message,
+                )],
+            );
+        }
+    };
+
+    let mut issues = Vec::new();
+    let stem = Path::new(relative)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = file
+        .name
+        .map(|name| name.trim().to_string())
+        .unwrap_or(stem);
+    if name.is_empty() {
+        issues.push(ValidationIssue::error(
+            relative,
+            None,
+            "agent-field",
+            "Agent name is empty".to_string(),
+        ));
+    }
+    let command = file.command.argv();
+    if command
+        .first()
+        .is_none_or(|program| program.trim().is_empty())
+    {
+        issues.push(ValidationIssue::error(
+            relative,
+            None,
+            "agent-field",
+            "Agent command is empty".to_string(),
+        ));
+    }
+    if file
+        .cost
+        .is_some_and(|cost| !cost.is_finite() || cost < 0.0)
+    {
+        issues.push(ValidationIssue::error(
+            relative,
+            None,
+            "agent-field",
+            "Agent cost must be a non-negative number".to_string(),
+        ));
+    }
+    let platforms: Vec<String> = file
+        .platforms
+        .iter()
+        .map(|platform| platform.trim().to_ascii_lowercase())
+        .map(|platform| {
+            if platform == "darwin" {
+                "macos".to_string()
+            } else {
+                platform
+            }
+        })
+        .collect();
+    for platform in &platforms {
+        if platform != "unix" && !PLATFORMS.contains(&platform.as_str()) {
+            issues.push(warning(
+                relative,
+                "agent-platform",
+                format!(
+                    "Unknown platform: {} (expected linux, macos, windows or unix)",
+                    platform
+                ),
+            ));
+        }
+    }
+    for key in file.extra.keys() {
+        issues.push(warning(
+            relative,
+            "agent-unknown-key",
+            format!("Unknown key: {}", key),
+        ));
+    }
+    let mut capabilities: Vec<String> = file
+        .capabilities
+        .iter()
+        .map(|capability| capability.trim().to_string())
+        .filter(|capability| !capability.is_empty())
+        .collect();
+    capabilities.sort();
+    capabilities.dedup();
+
+    let usable = issues.iter().all(|issue| issue.severity != "error");
+    let definition = usable.then(|| AgentDefinition {
+        name,
+        command,
+        description: file.description,
+        capabilities,
+        cost: file.cost,
+        platforms,
+        file: relative.to_string(),
+    });
+    (definition, issues)
+}
+
+/// Definition files in `dir`, sorted by name
+fn definition_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml")
+                })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Registry of `definitions`, with `locate` resolving programs and `os` the platform
+pub fn build_registry(
+    definitions: Vec<AgentDefinition>,
+    mut issues: Vec<ValidationIssue>,
+    os: &str,
+    locate: impl Fn(&str) -> Option<PathBuf> + Sync,
+) -> AgentRegistry {
+    let mut seen = BTreeSet::new();
+    let definitions: Vec<AgentDefinition> = definitions
+        .into_iter()
+        .filter(|definition| {
+            let first = seen.insert(definition.name.clone());
+            if !first {
+                issues.push(ValidationIssue::error(
+                    &definition.file,
+                    None,
+                    "agent-duplicate",
+                    format!("Duplicate agent name: {}", definition.name),
+                ));
+            }
+            first
+        })
+        .collect();
+
+    let agents: Vec<AgentStatus> = definitions
+        .into_par_iter()
+        .map(|definition| {
+            let platform_supported = definition.supports(os);
+            let executable = definition
+                .command
+                .first()
+                .and_then(|program| locate(program));
+            let installed = executable.is_some();
+            AgentStatus {
+                definition,
+                installed,
+                executable: executable.map(|path| path.to_string_lossy().into_owned()),
+                platform_supported,
+                available: installed && platform_supported,
+            }
+        })
+        .collect();
+
+    let mut ranked: Vec<&AgentStatus> = agents.iter().filter(|agent| agent.available).collect();
+    ranked.sort_by(|a, b| {
+        let cost = |agent: &AgentStatus| agent.definition.cost.unwrap_or(f64::INFINITY);
+        cost(a)
+            .total_cmp(&cost(b))
+            .then_with(|| a.definition.name.cmp(&b.definition.name))
+    });
+    let mut capabilities: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for agent in &ranked {
+        for capability in &agent.definition.capabilities {
+            capabilities
+                .entry(capability.clone())
+                .or_default()
+                .push(agent.definition.name.clone());
+        }
+    }
+    let available: Vec<String> = ranked
+        .iter()
+        .map(|agent| agent.definition.name.clone())
+        .collect();
+
+    let valid = issues.iter().all(|issue| issue.severity != "error");
+    let summary = format!(
+        "{} of {} agents available{}.",
+        available.len(),
+        agents.len(),
+        if valid {
+            ""
+        } else {
+            " (some definitions are invalid)"
+        }
+    );
+    AgentRegistry {
+        agents,
+        capabilities,
+        available,
+        issues,
+        valid,
+        platform: os.to_string(),
+        summary,
+    }
+}
+
+/// Loads the agent definitions in `agents_dir` under `root` and probes which are installed
+pub fn load_registry(root: &str, agents_dir: &str) -> Result<AgentRegistry, CdeError> {
+    metrics::timed("load_agent_registry", || {
+        let root_path = Path::new(root);
+        path_policy::check(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let parsed: Vec<_> = definition_files(&root_path.join(agents_dir))
+            .par_iter()
+            .map(|path| {
+                let relative = path
+                    .strip_prefix(root_path)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                match fs::read_to_string(path) {
+                    Ok(content) => parse_definition(&content, &relative),
+                    Err(e) => {
+                        let message = format!("Failed to read file: {}", e);
+                        (
+                            None,
+                            vec![ValidationIssue::error(
+                                &relative,
+                                None,
+                                "agent-read",
+                                message,
+                            )],
+                        )
+                    }
+                }
+            })
+            .collect();
+
+        let mut definitions = Vec::new();
+        let mut issues = Vec::new();
+        for (definition, file_issues) in parsed {
+            definitions.extend(definition);
+            issues.extend(file_issues);
+        }
+        Ok(build_registry(
+            definitions,
+            issues,
+            std::env::consts::OS,
+            executable::which,
+        ))
+    })
+}
+
+/// Loads the agent definitions of a project and returns their availability
+///
+/// Reads every `*.yaml`/`*.yml` in `agents_dir` (relative to `root`): `name`
+/// (the file name by default), `command` (a line or a list), `description`,
+/// `capabilities`, `cost` (relative, non-negative) and `platforms` ("linux",
+/// "macos", "windows", "unix"; all when absent). Programs are looked up on
+/// `PATH` in parallel. Returns `agents` (each definition with `installed`,
+/// `executable`, `platform_supported`, `available`), `capabilities` (available
+/// agents per capability, cheapest first), `available`, `issues` (as in
+/// `validate_configs_py`), `valid` and `summary`.
+#[pyfunction]
+#[pyo3(signature = (root, agents_dir=DEFAULT_AGENTS_DIR))]
+fn load_agent_registry_py(py: Python<'_>, root: &str, agents_dir: &str) -> PyResult<String> {
+    let registry = py.detach(|| load_registry(root, agents_dir))?;
+    Ok(to_json(&registry)?)
+}
+
+/// Adds the agent registry functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load_agent_registry_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_validates_and_ranks_agents() {
+        let dir = tempfile::tempdir().unwrap();
+        let agents = dir.path().join(DEFAULT_AGENTS_DIR);
+        fs::create_dir_all(&agents).unwrap();
+        fs::write(
+            agents.join("gemini.yaml"),
+            "command: gemini -p\ncapabilities: [documentation, analysis]\ncost: 1\n",
+        )
+        .unwrap();
+        fs::write(
+            agents.join("codex.yml"),
+            "name: codex\ncommand: [codex, exec]\ncapabilities: [analysis]\ncost: 3\nplatforms: [linux, darwin]\nretries: 2\n",
+        )
+        .unwrap();
+        fs::write(
+            agents.join("copilot.yaml"),
+            "command: gh copilot\nplatforms: [windows]\n",
+        )
+        .unwrap();
+        fs::write(
+            agents.join("missing.yaml"),
+            "command: not-installed\ncapabilities: [analysis]\n",
+        )
+        .unwrap();
+        fs::write(agents.join("broken.yaml"), "command: [a,\n").unwrap();
+        fs::write(
+            agents.join("dupe.yaml"),
+            "name: gemini\ncommand: gemini\ncost: -1\n",
+        )
+        .unwrap();
+        fs::write(agents.join("notes.txt"), "ignored").unwrap();
+
+        let mut definitions = Vec::new();
+        let mut issues = Vec::new();
+        for path in definition_files(&agents) {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let (definition, file_issues) =
+                parse_definition(&fs::read_to_string(&path).unwrap(), &name);
+            definitions.extend(definition);
+            issues.extend(file_issues);
+        }
+        let installed = ["gemini", "codex", "gh"];
+        let registry = build_registry(definitions, issues, "linux", |program| {
+            installed
+                .contains(&program)
+                .then(|| PathBuf::from("/usr/bin").join(program))
+        });
+
+        let mut rules: Vec<(&str, &str)> = registry
+            .issues
+            .iter()
+            .map(|issue| (issue.file.as_str(), issue.rule.as_deref().unwrap()))
+            .collect();
+        rules.sort();
+        assert_eq!(
+            rules,
+            [
+                ("broken.yaml", "agent-syntax"),
+                ("codex.yml", "agent-unknown-key"),
+                ("dupe.yaml", "agent-field"),
+            ]
+        );
+        assert!(!registry.valid);
+
+        let status = |name: &str| {
+            registry
+                .agents
+                .iter()
+                .find(|a| a.definition.name == name)
+                .unwrap()
+        };
+        assert_eq!(status("gemini").definition.command, ["gemini", "-p"]);
+        assert_eq!(status("codex").definition.platforms, ["linux", "macos"]);
+        assert!(status("copilot").installed && !status("copilot").platform_supported);
+        assert!(!status("missing").installed);
+        assert_eq!(registry.available, ["gemini", "codex"]);
+        assert_eq!(registry.capabilities["analysis"], ["gemini", "codex"]);
+        assert_eq!(registry.capabilities["documentation"], ["gemini"]);
+    }
+
+    #[test]
+    fn test_duplicate_names_are_reported() {
+        let (first, _) = parse_definition("name: a\ncommand: x\n", "a.yaml");
+        let (second, _) = parse_definition("name: a\ncommand: y\n", "b.yaml");
+        let registry = build_registry(
+            vec![first.unwrap(), second.unwrap()],
+            Vec::new(),
+            "windows",
+            |_| None,
+        );
+        assert_eq!(registry.agents.len(), 1);
+        assert_eq!(registry.issues[0].rule.as_deref(), Some("agent-duplicate"));
+        assert_eq!(registry.issues[0].file, "b.yaml");
+        assert!(registry.available.is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+// src/api.rs
+//! Rust entry points of the crate, callable without a Python interpreter
+//!
+//! The `*_py` functions only convert their arguments and serialize results
+//! around these. The benchmarks in `benches/` link the crate as an rlib and
+//! call them directly, so what they measure is the work itself rather than the
+//! PyO3 boundary.
+
+pub use crate::cancel::CancellationToken;
+pub use crate::documentation::{
+    analyze_documentation_quality, scan_documentation, Document, QualityReport,
+};
+pub use crate::error::CdeError;
+pub use crate::filesystem::{find_files, find_markdown_files};
+pub use crate::grep::{grep_project, GrepOptions, GrepResult};
+pub use crate::metadata::{extract_metadata, Metadata};
+pub use crate::progress::ProgressSink;
+pub use crate::project_scanner::{scan_project, ProjectAnalysisResult, ScanHandle, ScanOptions};
+pub use crate::text::{text_quality, text_structure};
+pub use crate::workflow_validator::{validate_workflows, WorkflowValidationReport};
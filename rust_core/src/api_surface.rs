@@ -0,0 +1,515 @@
+// src/api_surface.rs
+//! HTTP endpoints a service exposes, read from its code and OpenAPI files
+//!
+//! FastAPI and Flask decorators (`@app.get("/users/{id}")`,
+//! `@bp.route("/x", methods=["POST"])`), Express route calls
+//! (`router.post("/users", create)`, `app.route("/x").get(...)`) and the
+//! `paths` of OpenAPI/Swagger documents all become the same `ApiEndpoint`:
+//! method, path, handler and where it is declared. Path parameters are written
+//! `{name}` whatever the framework (`<int:id>` and `:id` included), so
+//! endpoints from code and from a spec can be compared. The prefix of an
+//! `APIRouter(prefix=...)` or `Blueprint(url_prefix=...)` declared in the same
+//! file is applied; prefixes given where a router is mounted in another file
+//! are not followed.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Files larger than this are not read for endpoints
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+const HTTP_METHODS: &[&str] = &[
+    "get", "post", "put", "patch", "delete", "options", "head", "trace",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiEndpoint {
+    /// Upper case; "ANY" for routes answering every method
+    pub method: String,
+    /// Parameters written `{name}`
+    pub path: String,
+    /// Handling function, or the `operationId` of a spec
+    pub handler: Option<String>,
+    /// Relative to the root
+    pub file: String,
+    pub line: usize,
+    pub framework: String, // "fastapi", "flask", "express", "openapi"
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiSurface {
+    pub files_scanned: usize,
+    pub endpoints: Vec<ApiEndpoint>,
+    /// Endpoint count per framework
+    pub frameworks: BTreeMap<String, usize>,
+    pub truncated: bool,
+}
+
+impl CapLists for ApiSurface {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.endpoints, max_items);
+    }
+}
+
+static PY_ROUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*@(?P<object>\w+)\.(?P<method>get|post|put|patch|delete|options|head|trace|route|api_route|websocket)\(\s*(?:path\s*=\s*)?[rfb]?["'](?P<path>[^"']*)["'](?P<rest>.*)$"#,
+    )
+    .unwrap()
+});
+
+static PY_METHODS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"methods\s*=\s*[\[(]([^\])]*)[\])]").unwrap());
+
+static PY_DEF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:async\s+)?def\s+(\w+)").unwrap());
+
+static PY_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(\w+)\s*=\s*(?:\w+\.)?(?:APIRouter|Blueprint)\((?:[^)]*?\b(?:url_)?prefix\s*=\s*["']([^"']*)["'])?"#,
+    )
+    .unwrap()
+});
+
+static JS_ROUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(?P<object>\w+)\.(?P<method>get|post|put|patch|delete|options|head|all)\(\s*["'`](?P<path>/[^"'`]*)["'`](?P<rest>[^;]*)"#,
+    )
+    .unwrap()
+});
+
+static JS_CHAINED_ROUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\b\w+\.route\(\s*["'`](?P<path>/[^"'`]*)["'`]\s*\)(?P<chain>[^;]*)"#).unwrap()
+});
+
+static JS_CHAINED_METHOD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\.\s*(get|post|put|patch|delete|options|head|all)\s*\(([^()]*)").unwrap()
+});
+
+static FLASK_PARAM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(?:[^:<>]+:)?([^<>]+)>").unwrap());
+
+static EXPRESS_PARAM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(^|/):(\w+)\??").unwrap());
+
+/// FastAPI/Starlette converters: `{name:path}`
+static CONVERTER_PARAM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+):[^}]*\}").unwrap());
+
+/// `path` with its parameters written `{name}` and a leading slash
+pub fn normalize_path(path: &str) -> String {
+    let path = CONVERTER_PARAM.replace_all(path.trim(), "{${1}}");
+    let path = FLASK_PARAM.replace_all(&path, "{${1}}");
+    let path = EXPRESS_PARAM.replace_all(&path, "${1}{${2}}");
+    if path.starts_with('/') {
+        path.into_owned()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+fn join_prefix(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    match (prefix.is_empty(), path) {
+        (true, _) => path.to_string(),
+        (false, "" | "/") => prefix.to_string(),
+        (false, path) if path.starts_with('/') => format!("{}{}", prefix, path),
+        (false, path) => format!("{}/{}", prefix, path),
+    }
+}
+
+/// Endpoints of a FastAPI or Flask module
+fn python_endpoints(content: &str, file: &str) -> Vec<ApiEndpoint> {
+    let framework = if content.contains("fastapi") {
+        "fastapi"
+    } else if content.contains("flask") {
+        "flask"
+    } else {
+        return Vec::new();
+    };
+    let prefixes: HashMap<String, String> = PY_PREFIX
+        .captures_iter(content)
+        .map(|caps| {
+            let prefix = caps.get(2).map_or("", |p| p.as_str());
+            (caps[1].to_string(), prefix.to_string())
+        })
+        .collect();
+
+    let mut endpoints = Vec::new();
+    // Decorated endpoints waiting for the function they decorate
+    let mut pending = 0;
+    for (i, line) in content.lines().enumerate() {
+        if let Some(caps) = PY_ROUTE.captures(line) {
+            let object = &caps["object"];
+            let methods: Vec<String> = match &caps["method"] {
+                "route" | "api_route" => match PY_METHODS.captures(&caps["rest"]) {
+                    Some(listed) => listed[1]
+                        .split(',')
+                        .map(|method| method.trim().trim_matches(['"', '\'']).to_uppercase())
+                        .filter(|method| !method.is_empty())
+                        .collect(),
+                    None => vec!["GET".to_string()],
+                },
+                "websocket" => vec!["WEBSOCKET".to_string()],
+                method => vec![method.to_uppercase()],
+            };
+            let prefix = prefixes.get(object).map_or("", String::as_str);
+            let path = normalize_path(&join_prefix(prefix, &caps["path"]));
+            for method in methods {
+                endpoints.push(ApiEndpoint {
+                    method,
+                    path: path.clone(),
+                    handler: None,
+                    file: file.to_string(),
+                    line: i + 1,
+                    framework: framework.to_string(),
+                });
+                pending += 1;
+            }
+        } else if let Some(caps) = PY_DEF.captures(line) {
+            let count = endpoints.len();
+            for endpoint in &mut endpoints[count - pending..] {
+                endpoint.handler = Some(caps[1].to_string());
+            }
+            pending = 0;
+        }
+    }
+    endpoints
+}
+
+/// Name of the last handler argument of an Express route, unless it is an
+/// inline function
+fn js_handler(arguments: &str) -> Option<String> {
+    let last = arguments
+        .trim()
+        .trim_end_matches(')')
+        .trim()
+        .trim_end_matches(',');
+    let last = last.rsplit(',').next()?.trim();
+    let is_name = !last.is_empty()
+        && last
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.');
+    (is_name && !last.starts_with('.')).then(|| last.to_string())
+}
+
+/// Endpoints of an Express application or router module
+fn express_endpoints(content: &str, file: &str) -> Vec<ApiEndpoint> {
+    if !content.contains("express") {
+        return Vec::new();
+    }
+    let line_of = |offset: usize| content[..offset].matches('\n').count() + 1;
+    let method_of = |method: &str| match method {
+        "all" => "ANY".to_string(),
+        method => method.to_uppercase(),
+    };
+    let mut endpoints = Vec::new();
+    for caps in JS_ROUTE.captures_iter(content) {
+        endpoints.push(ApiEndpoint {
+            method: method_of(&caps["method"]),
+            path: normalize_path(&caps["path"]),
+            handler: js_handler(&caps["rest"]),
+            file: file.to_string(),
+            line: line_of(caps.get(0).unwrap().start()),
+            framework: "express".to_string(),
+        });
+    }
+    for caps in JS_CHAINED_ROUTE.captures_iter(content) {
+        let path = normalize_path(&caps["path"]);
+        let line = line_of(caps.get(0).unwrap().start());
+        for chained in JS_CHAINED_METHOD.captures_iter(&caps["chain"]) {
+            endpoints.push(ApiEndpoint {
+                method: method_of(&chained[1]),
+                path: path.clone(),
+                handler: js_handler(&chained[2]),
+                file: file.to_string(),
+                line,
+                framework: "express".to_string(),
+            });
+        }
+    }
+    endpoints
+}
+
+/// Line of the key `path` in the spec `content`, 1 when not found
+pub(crate) fn spec_line(content: &str, path: &str) -> usize {
+    let keys = [
+        format!("{}:", path),
+        format!("\"{}\"", path),
+        format!("'{}'", path),
+    ];
+    content
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            keys.iter().any(|key| line.starts_with(key.as_str()))
+        })
+        .map_or(1, |i| i + 1)
+}
+
+/// Endpoints of an OpenAPI 3 or Swagger 2 document; None when `content` is
+/// not one
+pub fn openapi_endpoints(content: &str, file: &str) -> Option<Vec<ApiEndpoint>> {
+    let spec: serde_yaml::Value = serde_yaml::from_str(content).ok()?;
+    if spec.get("openapi").is_none() && spec.get("swagger").is_none() {
+        return None;
+    }
+    let base_path = spec
+        .get("basePath")
+        .and_then(|base| base.as_str())
+        .unwrap_or("");
+    let mut endpoints = Vec::new();
+    for (path, item) in spec.get("paths")?.as_mapping()? {
+        let Some(path) = path.as_str() else {
+            continue;
+        };
+        let Some(item) = item.as_mapping() else {
+            continue;
+        };
+        let line = spec_line(content, path);
+        for (method, operation) in item {
+            let Some(method) = method.as_str().filter(|m| HTTP_METHODS.contains(m)) else {
+                continue;
+            };
+            endpoints.push(ApiEndpoint {
+                method: method.to_uppercase(),
+                path: normalize_path(&join_prefix(base_path, path)),
+                handler: operation
+                    .get("operationId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string),
+                file: file.to_string(),
+                line,
+                framework: "openapi".to_string(),
+            });
+        }
+    }
+    Some(endpoints)
+}
+
+/// Whether `name` is the file name of an API description
+fn is_spec_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let stem = name.split('.').next().unwrap_or("");
+    (stem.contains("openapi") || stem.contains("swagger"))
+        && [".json", ".yaml", ".yml"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+/// Endpoints declared by the file at `path`
+fn file_endpoints(root: &Path, path: &Path) -> Vec<ApiEndpoint> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let is_spec = is_spec_name(name);
+    let is_code = matches!(
+        extension.as_str(),
+        "py" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx"
+    );
+    if !is_spec && !is_code {
+        return Vec::new();
+    }
+    if !fs::metadata(path).is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE) {
+        return Vec::new();
+    }
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let file = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if is_spec {
+        openapi_endpoints(&content, &file).unwrap_or_default()
+    } else if extension == "py" {
+        python_endpoints(&content, &file)
+    } else {
+        express_endpoints(&content, &file)
+    }
+}
+
+/// Endpoints declared by `files` (paths under `root`), in parallel, sorted by
+/// path then method
+pub fn endpoints_in_files(root: &Path, files: &[PathBuf]) -> Vec<ApiEndpoint> {
+    let mut endpoints: Vec<ApiEndpoint> = files
+        .par_iter()
+        .flat_map_iter(|path| file_endpoints(root, path))
+        .collect();
+    endpoints.sort_by(|a, b| {
+        (&a.path, &a.method, &a.file, a.line).cmp(&(&b.path, &b.method, &b.file, b.line))
+    });
+    endpoints
+}
+
+/// Endpoints of the project under `root`, honoring `.gitignore`
+pub fn extract_api_endpoints(root: &str) -> Result<ApiSurface, CdeError> {
+    metrics::timed("extract_api_endpoints", || {
+        let root_path = Path::new(root);
+        path_policy::check(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let guard = path_policy::walk_guard(root_path)?;
+        let walker = WalkBuilder::new(root_path)
+            .require_git(false)
+            .max_filesize(Some(MAX_FILE_SIZE))
+            .max_depth(Some(guard.max_depth()))
+            .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+            .build();
+        let files: Vec<PathBuf> = walker
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .collect();
+        let endpoints = endpoints_in_files(root_path, &files);
+        let mut frameworks = BTreeMap::new();
+        for endpoint in &endpoints {
+            *frameworks.entry(endpoint.framework.clone()).or_insert(0) += 1;
+        }
+        Ok(ApiSurface {
+            files_scanned: files.len(),
+            endpoints,
+            frameworks,
+            truncated: false,
+        })
+    })
+}
+
+/// HTTP endpoints of the project under `root`, from code and API documents
+///
+/// Reads FastAPI/Flask route decorators, Express route calls and the `paths`
+/// of OpenAPI/Swagger files (`openapi.yaml`, `swagger.json`, ...). Every
+/// endpoint has `method`, `path` (parameters as `{name}`), `handler` (function
+/// or operationId), `file`, `line` and `framework`; `frameworks` counts them.
+/// `scan_project_py` reports the same list as `api_endpoints`.
+#[pyfunction]
+#[pyo3(signature = (root, max_list_items=None))]
+fn extract_api_endpoints_py(
+    py: Python<'_>,
+    root: &str,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let mut surface = py.detach(|| extract_api_endpoints(root))?;
+    if let Some(max_items) = max_list_items {
+        surface.cap_lists(max_items);
+    }
+    Ok(to_json(&surface)?)
+}
+
+/// Adds the API surface functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(extract_api_endpoints_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes(endpoints: &[ApiEndpoint]) -> Vec<(String, String, Option<String>, usize)> {
+        endpoints
+            .iter()
+            .map(|e| (e.method.clone(), e.path.clone(), e.handler.clone(), e.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_fastapi_and_flask_decorators() {
+        let fastapi = r#"from fastapi import APIRouter
+router = APIRouter(prefix="/users", tags=["users"])
+
+@router.get("/{user_id}")
+async def read_user(user_id: int):
+    ...
+
+@router.api_route("", methods=["POST", "PUT"])
+@requires_auth
+def save_user():
+    ...
+"#;
+        assert_eq!(
+            routes(&python_endpoints(fastapi, "users.py")),
+            vec![
+                (
+                    "GET".into(),
+                    "/users/{user_id}".into(),
+                    Some("read_user".into()),
+                    4
+                ),
+                ("POST".into(), "/users".into(), Some("save_user".into()), 8),
+                ("PUT".into(), "/users".into(), Some("save_user".into()), 8),
+            ]
+        );
+
+        let flask = "from flask import Blueprint\nbp = Blueprint('items', __name__, url_prefix='/items')\n\n@bp.route('/<int:item_id>', methods=['DELETE'])\ndef remove(item_id):\n    pass\n";
+        let endpoints = python_endpoints(flask, "items.py");
+        assert_eq!(
+            routes(&endpoints),
+            vec![(
+                "DELETE".into(),
+                "/items/{item_id}".into(),
+                Some("remove".into()),
+                4
+            )]
+        );
+        assert_eq!(endpoints[0].framework, "flask");
+        assert!(python_endpoints("@app.get('/x')\ndef x(): pass\n", "plain.py").is_empty());
+    }
+
+    #[test]
+    fn test_express_routes() {
+        let js = "const express = require('express');\nconst router = express.Router();\nrouter.get('/users/:id', auth, users.show);\nrouter.route('/items')\n  .get(listItems)\n  .post((req, res) => res.send());\napp.all('/health', health);\nmap.get('key');\n";
+        let mut endpoints = express_endpoints(js, "routes.js");
+        endpoints.sort_by_key(|e| e.line);
+        assert_eq!(
+            routes(&endpoints),
+            vec![
+                (
+                    "GET".into(),
+                    "/users/{id}".into(),
+                    Some("users.show".into()),
+                    3
+                ),
+                ("GET".into(), "/items".into(), Some("listItems".into()), 4),
+                ("POST".into(), "/items".into(), None, 4),
+                ("ANY".into(), "/health".into(), Some("health".into()), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_openapi_paths() {
+        let spec = "swagger: '2.0'\nbasePath: /v1\npaths:\n  /pets/{petId}:\n    get:\n      operationId: showPet\n    parameters: []\n";
+        assert_eq!(
+            routes(&openapi_endpoints(spec, "swagger.yaml").unwrap()),
+            vec![(
+                "GET".into(),
+                "/v1/pets/{petId}".into(),
+                Some("showPet".into()),
+                4
+            )]
+        );
+        assert!(openapi_endpoints("name: not a spec\n", "x.yaml").is_none());
+        assert!(is_spec_name("openapi.v2.yml"));
+        assert_eq!(
+            normalize_path("files/{file_path:path}"),
+            "/files/{file_path}"
+        );
+        assert_eq!(normalize_path("/a/:id?/b"), "/a/{id}/b");
+        assert!(!is_spec_name("openapi.md"));
+    }
+}
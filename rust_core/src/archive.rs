@@ -0,0 +1,635 @@
+// src/archive.rs
+//! Listing and safe extraction of zip, tar and tar.gz archives
+//!
+//! Extraction never writes outside the destination: entry names that are
+//! absolute or climb out with `..` are skipped, and so are symlinks, hard links
+//! and device entries. Archive bombs are stopped by counting the bytes actually
+//! written, not the sizes the archive declares: extraction fails past
+//! `max_total_bytes` or `max_entries`, or when an entry inflates more than
+//! `max_ratio` times its compressed size. Entries are written to a staging
+//! directory next to the destination, renamed into place only when everything
+//! was extracted, so a failed extraction leaves nothing behind.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use flate2::read::GzDecoder;
+use glob::Pattern;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+const DEFAULT_MAX_TOTAL_BYTES: u64 = 1024 * 1024 * 1024;
+const DEFAULT_MAX_ENTRIES: usize = 100_000;
+const DEFAULT_MAX_RATIO: u64 = 200;
+/// Entries smaller than this are not held to the compression ratio
+const RATIO_MIN_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// As stored in the archive
+    pub name: String,
+    /// "file", "dir", "symlink" or "other" (hard links, devices, ...)
+    pub kind: String,
+    /// Uncompressed size
+    pub size: u64,
+    /// Zip only
+    pub compressed_size: Option<u64>,
+    /// Why extraction would skip the entry
+    pub unsafe_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    pub path: String,
+    pub format: String,
+    pub entries: Vec<ArchiveEntry>,
+    pub file_count: usize,
+    /// Declared uncompressed size of the files
+    pub total_size: u64,
+    /// Whether the listing stopped at the entry limit
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractLimits {
+    pub max_total_bytes: u64,
+    pub max_entries: usize,
+    pub max_ratio: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_ratio: DEFAULT_MAX_RATIO,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractReport {
+    pub dest: String,
+    pub format: String,
+    /// Files written, relative to `dest`
+    pub extracted: Vec<String>,
+    pub skipped: Vec<SkippedEntry>,
+    pub total_bytes: u64,
+}
+
+/// What the walk over an archive knows of an entry
+struct EntryMeta {
+    name: String,
+    kind: &'static str,
+    size: u64,
+    compressed_size: Option<u64>,
+    mode: Option<u32>,
+}
+
+fn detect(path: &Path) -> Result<Format, CdeError> {
+    let mut header = Vec::with_capacity(512);
+    fs::File::open(path)
+        .and_then(|file| file.take(512).read_to_end(&mut header))
+        .map_err(|e| {
+            CdeError::io("Failed to read archive")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        Ok(Format::Zip)
+    } else if header.starts_with(&[0x1f, 0x8b]) {
+        Ok(Format::TarGz)
+    } else if header.get(257..262) == Some(b"ustar".as_slice()) {
+        Ok(Format::Tar)
+    } else {
+        Err(CdeError::invalid_input("Not a zip, tar or tar.gz archive").with_path(path))
+    }
+}
+
+/// `name` as a relative path below the destination, or why it is not one
+fn safe_relative(name: &str) -> Result<String, String> {
+    let name = name.replace('\\', "/");
+    let mut parts = Vec::new();
+    for component in Path::new(&name).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => return Err("Path escapes the destination".to_string()),
+            Component::RootDir | Component::Prefix(_) => return Err("Absolute path".to_string()),
+        }
+    }
+    // "C:foo" is not a prefix on Unix but is one on Windows
+    if parts.is_empty() || parts[0].contains(':') {
+        return Err("Invalid entry name".to_string());
+    }
+    Ok(parts.join("/"))
+}
+
+fn read_error(path: &Path) -> impl Fn(io::Error) -> CdeError + '_ {
+    move |e| {
+        CdeError::io("Failed to read archive")
+            .with_path(path)
+            .caused_by(&e)
+    }
+}
+
+/// Calls `visit` with each entry of the archive and a reader of its content
+fn for_each_entry(
+    path: &Path,
+    format: Format,
+    visit: &mut dyn FnMut(EntryMeta, &mut dyn Read) -> Result<bool, CdeError>,
+) -> Result<(), CdeError> {
+    let file = fs::File::open(path).map_err(read_error(path))?;
+    if format == Format::Zip {
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| {
+            CdeError::parse("Invalid zip archive")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).map_err(|e| {
+                CdeError::parse("Invalid zip entry")
+                    .with_path(path)
+                    .caused_by(&e)
+            })?;
+            let kind = if entry.is_symlink() {
+                "symlink"
+            } else if entry.is_dir() {
+                "dir"
+            } else {
+                "file"
+            };
+            let meta = EntryMeta {
+                name: entry.name().to_string(),
+                kind,
+                size: entry.size(),
+                compressed_size: Some(entry.compressed_size()),
+                mode: entry.unix_mode(),
+            };
+            if !visit(meta, &mut entry)? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+
+    let reader: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(read_error(path))? {
+        let mut entry = entry.map_err(read_error(path))?;
+        let header = entry.header();
+        let kind = match header.entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => "file",
+            tar::EntryType::Directory => "dir",
+            tar::EntryType::Symlink => "symlink",
+            _ => "other",
+        };
+        let meta = EntryMeta {
+            name: String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+            kind,
+            size: header.size().unwrap_or(0),
+            compressed_size: None,
+            mode: header.mode().ok(),
+        };
+        if !visit(meta, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the entries of the archive at `path`, at most `max_entries`
+pub fn inspect_archive(path: &str, max_entries: usize) -> Result<ArchiveInfo, CdeError> {
+    path_policy::check(path)?;
+    let archive_path = Path::new(path);
+    let format = detect(archive_path)?;
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for_each_entry(archive_path, format, &mut |meta, _| {
+        if entries.len() == max_entries {
+            truncated = true;
+            return Ok(false);
+        }
+        let unsafe_reason = match (meta.kind, safe_relative(&meta.name)) {
+            (_, Err(reason)) => Some(reason),
+            ("symlink", _) => Some("Symbolic link".to_string()),
+            ("other", _) => Some("Not a regular file or directory".to_string()),
+            _ => None,
+        };
+        entries.push(ArchiveEntry {
+            name: meta.name,
+            kind: meta.kind.to_string(),
+            size: meta.size,
+            compressed_size: meta.compressed_size,
+            unsafe_reason,
+        });
+        Ok(true)
+    })?;
+    let files = entries.iter().filter(|entry| entry.kind == "file");
+    Ok(ArchiveInfo {
+        path: path.to_string(),
+        format: format.name().to_string(),
+        file_count: files.clone().count(),
+        total_size: files.map(|entry| entry.size).sum(),
+        entries,
+        truncated,
+    })
+}
+
+fn bomb(message: String, path: &Path) -> CdeError {
+    CdeError::invalid_input(message).with_path(path)
+}
+
+/// Writes the entries of `archive_path` under `staging`, see the module docs
+fn extract_into(
+    archive_path: &Path,
+    format: Format,
+    staging: &Path,
+    allowlist: &[Pattern],
+    limits: &ExtractLimits,
+) -> Result<(Vec<String>, Vec<SkippedEntry>, u64), CdeError> {
+    let archive_size = fs::metadata(archive_path).map(|m| m.len()).unwrap_or(0);
+    let mut extracted = Vec::new();
+    let mut skipped = Vec::new();
+    let mut total_bytes = 0u64;
+    let mut entries = 0usize;
+    for_each_entry(archive_path, format, &mut |meta, reader| {
+        entries += 1;
+        if entries > limits.max_entries {
+            return Err(bomb(
+                format!("Archive has more than {} entries", limits.max_entries),
+                archive_path,
+            ));
+        }
+        let mut skip = |reason: &str| -> Result<bool, CdeError> {
+            skipped.push(SkippedEntry {
+                name: meta.name.clone(),
+                reason: reason.to_string(),
+            });
+            Ok(true)
+        };
+        let relative = match safe_relative(&meta.name) {
+            Ok(relative) => relative,
+            Err(reason) => return skip(&reason),
+        };
+        match meta.kind {
+            "symlink" => return skip("Symbolic link"),
+            "other" => return skip("Not a regular file or directory"),
+            "dir" if allowlist.is_empty() => {
+                let dir = staging.join(&relative);
+                fs::create_dir_all(&dir).map_err(|e| {
+                    CdeError::io("Failed to create directory")
+                        .with_path(&dir)
+                        .caused_by(&e)
+                })?;
+                return Ok(true);
+            }
+            "dir" => return Ok(true),
+            _ => {}
+        }
+        if !allowlist.is_empty() && !allowlist.iter().any(|p| p.matches(&relative)) {
+            return skip("Not in the allowlist");
+        }
+        if let Some(compressed) = meta.compressed_size.filter(|&c| c > 0) {
+            if meta.size > RATIO_MIN_BYTES && meta.size / compressed > limits.max_ratio {
+                return Err(bomb(
+                    format!(
+                        "Entry {} inflates more than {} times",
+                        meta.name, limits.max_ratio
+                    ),
+                    archive_path,
+                ));
+            }
+        }
+
+        let target = staging.join(&relative);
+        let write_error = |e: io::Error| {
+            CdeError::io("Failed to write extracted file")
+                .with_path(&target)
+                .caused_by(&e)
+        };
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let mut file = fs::File::create(&target).map_err(write_error)?;
+        let budget = limits.max_total_bytes - total_bytes;
+        let written =
+            io::copy(&mut (&mut *reader).take(budget + 1), &mut file).map_err(write_error)?;
+        if written > budget {
+            return Err(bomb(
+                format!("Archive inflates past {} bytes", limits.max_total_bytes),
+                archive_path,
+            ));
+        }
+        total_bytes += written;
+        let inflated = match meta.compressed_size {
+            Some(compressed) => {
+                written > RATIO_MIN_BYTES && written / compressed.max(1) > limits.max_ratio
+            }
+            None => {
+                total_bytes > RATIO_MIN_BYTES
+                    && total_bytes / archive_size.max(1) > limits.max_ratio
+            }
+        };
+        if inflated {
+            return Err(bomb(
+                format!("Archive inflates more than {} times", limits.max_ratio),
+                archive_path,
+            ));
+        }
+        #[cfg(unix)]
+        if meta.mode.is_some_and(|mode| mode & 0o111 != 0) {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&target, fs::Permissions::from_mode(0o755));
+        }
+        extracted.push(relative);
+        Ok(true)
+    })?;
+    Ok((extracted, skipped, total_bytes))
+}
+
+/// Extracts the archive at `path` into `dest`, which must not exist or be empty.
+/// With an `allowlist` of globs, only the files whose path matches one are
+/// extracted.
+pub fn extract_archive(
+    path: &str,
+    dest: &str,
+    allowlist: &[String],
+    limits: &ExtractLimits,
+) -> Result<ExtractReport, CdeError> {
+    metrics::timed("extract_archive", || {
+        path_policy::check(path)?;
+        path_policy::check(dest)?;
+        let archive_path = Path::new(path);
+        let format = detect(archive_path)?;
+        let allowlist = allowlist
+            .iter()
+            .map(|glob| {
+                Pattern::new(glob).map_err(|e| {
+                    CdeError::invalid_input(format!("Invalid allowlist glob: {}", glob))
+                        .caused_by(&e)
+                })
+            })
+            .collect::<Result<Vec<Pattern>, CdeError>>()?;
+
+        let dest_path = Path::new(dest);
+        if dest_path.exists() {
+            let empty = fs::read_dir(dest_path).is_ok_and(|mut entries| entries.next().is_none());
+            if !empty {
+                return Err(
+                    CdeError::invalid_input("Destination is not an empty directory")
+                        .with_path(dest),
+                );
+            }
+        }
+        let name = dest_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| CdeError::invalid_input("Invalid destination").with_path(dest))?;
+        let staging =
+            dest_path.with_file_name(format!(".{}.cde-extract-{}", name, std::process::id()));
+        fs::create_dir_all(&staging).map_err(|e| {
+            CdeError::io("Failed to create staging directory")
+                .with_path(&staging)
+                .caused_by(&e)
+        })?;
+
+        let result =
+            extract_into(archive_path, format, &staging, &allowlist, limits).and_then(|done| {
+                if dest_path.exists() {
+                    let _ = fs::remove_dir(dest_path);
+                }
+                fs::rename(&staging, dest_path).map_err(|e| {
+                    CdeError::io("Failed to move extracted files into place")
+                        .with_path(dest)
+                        .caused_by(&e)
+                })?;
+                Ok(done)
+            });
+        let (mut extracted, skipped, total_bytes) = match result {
+            Ok(done) => done,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+        metrics::add_bytes("extract_archive", total_bytes);
+        extracted.sort();
+        extracted.dedup();
+        Ok(ExtractReport {
+            dest: dest.to_string(),
+            format: format.name().to_string(),
+            extracted,
+            skipped,
+            total_bytes,
+        })
+    })
+}
+
+/// Entries of a zip, tar or tar.gz archive as JSON, without extracting it
+///
+/// Returns the `format`, the `entries` (`name`, `kind`, `size`, zip
+/// `compressed_size`, and the `unsafe_reason` extraction would skip them for),
+/// `file_count` and `total_size`. Listing stops after `max_entries` with
+/// `truncated` set.
+#[pyfunction]
+#[pyo3(signature = (path, max_entries=DEFAULT_MAX_ENTRIES))]
+fn inspect_archive_py(py: Python<'_>, path: &str, max_entries: usize) -> PyResult<String> {
+    let info = py.detach(|| inspect_archive(path, max_entries))?;
+    Ok(to_json(&info)?)
+}
+
+/// Safely extracts a zip, tar or tar.gz archive into `dest`, as JSON
+///
+/// `dest` must not exist or be empty. Entries escaping it, links and devices are
+/// skipped; with `allowlist` globs, only matching files are extracted. Fails,
+/// leaving `dest` untouched, when the archive inflates past `max_total_bytes`
+/// (1 GiB), has more than `max_entries` entries or inflates more than
+/// `max_ratio` times. Returns the `extracted` files, the `skipped` entries with
+/// their `reason`, and `total_bytes`.
+#[pyfunction]
+#[pyo3(signature = (path, dest, allowlist=None, max_total_bytes=DEFAULT_MAX_TOTAL_BYTES, max_entries=DEFAULT_MAX_ENTRIES, max_ratio=DEFAULT_MAX_RATIO))]
+fn extract_archive_py(
+    py: Python<'_>,
+    path: &str,
+    dest: &str,
+    allowlist: Option<Vec<String>>,
+    max_total_bytes: u64,
+    max_entries: usize,
+    max_ratio: u64,
+) -> PyResult<String> {
+    let limits = ExtractLimits {
+        max_total_bytes,
+        max_entries,
+        max_ratio,
+    };
+    let allowlist = allowlist.unwrap_or_default();
+    let report = py.detach(|| extract_archive(path, dest, &allowlist, &limits))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the archive functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(inspect_archive_py, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_archive_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extracts_safely_and_stops_bombs() {
+        let dir = tempfile::tempdir().unwrap();
+        let upload = dir.path().join("upload.zip");
+        write_zip(
+            &upload,
+            &[
+                ("project/src/main.py", b"print()"),
+                ("project/README.md", b"# P"),
+                ("../evil.sh", b"rm -rf"),
+                ("/etc/cron.d/x", b"x"),
+            ],
+        );
+        let upload = upload.to_str().unwrap();
+
+        let info = inspect_archive(upload, 100).unwrap();
+        assert_eq!(
+            (info.format.as_str(), info.file_count, info.total_size),
+            ("zip", 4, 17)
+        );
+        let flagged: Vec<&str> = info
+            .entries
+            .iter()
+            .filter(|entry| entry.unsafe_reason.is_some())
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(flagged, vec!["../evil.sh", "/etc/cron.d/x"]);
+
+        let dest = dir.path().join("out");
+        let report = extract_archive(
+            upload,
+            dest.to_str().unwrap(),
+            &[],
+            &ExtractLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.extracted,
+            vec!["project/README.md", "project/src/main.py"]
+        );
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dest.join("project/src/main.py")).unwrap(),
+            "print()"
+        );
+        assert!(!dir.path().join("evil.sh").exists());
+        // The destination must be empty
+        assert!(extract_archive(
+            upload,
+            dest.to_str().unwrap(),
+            &[],
+            &ExtractLimits::default()
+        )
+        .is_err());
+
+        let only_docs = dir.path().join("docs");
+        let report = extract_archive(
+            upload,
+            only_docs.to_str().unwrap(),
+            &["**/*.md".to_string()],
+            &ExtractLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(report.extracted, vec!["project/README.md"]);
+
+        // A tar.gz with a symlink, and zeros that inflate far past the limit
+        let bomb_path = dir.path().join("bomb.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&bomb_path).unwrap(),
+            flate2::Compression::best(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "passwd", "/etc/passwd")
+            .unwrap();
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let mut header = tar::Header::new_gnu();
+        header.set_size(zeros.len() as u64);
+        builder
+            .append_data(&mut header, "zeros.bin", zeros.as_slice())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        let bomb_path = bomb_path.to_str().unwrap();
+
+        let info = inspect_archive(bomb_path, 100).unwrap();
+        assert_eq!(info.format, "tar.gz");
+        assert_eq!(
+            info.entries[0].unsafe_reason.as_deref(),
+            Some("Symbolic link")
+        );
+        let target = dir.path().join("bombed");
+        let small = ExtractLimits {
+            max_total_bytes: 1024 * 1024,
+            ..ExtractLimits::default()
+        };
+        assert!(extract_archive(bomb_path, target.to_str().unwrap(), &[], &small).is_err());
+        assert!(extract_archive(
+            bomb_path,
+            target.to_str().unwrap(),
+            &[],
+            &ExtractLimits::default()
+        )
+        .is_err());
+        // Nothing is left behind: no destination, no staging directory
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains("bombed"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+}
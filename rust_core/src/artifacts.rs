@@ -0,0 +1,441 @@
+// src/artifacts.rs
+//! Content-addressed store of generated outputs
+//!
+//! Agent outputs, compiled workflows and reports are stored once per content,
+//! under the blake3 hash of their bytes, with a JSON metadata file next to them
+//! (`<root>/<first two hex digits>/<hash>` and `<hash>.json`). The root is
+//! `CDE_ARTIFACT_DIR` when set, `~/.cde/artifacts` otherwise, and must be allowed
+//! by the path policy. Storing content that is already there only merges the new
+//! metadata and marks it used, so repeated runs share their artifacts; `gc` drops
+//! the ones unused for too long or beyond a size budget, least recently used
+//! first.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::path_policy;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable moving the shared store to another directory
+pub const ARTIFACT_DIR_ENV: &str = "CDE_ARTIFACT_DIR";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactInfo {
+    /// Hex blake3 hash of the content
+    pub hash: String,
+    pub size: u64,
+    /// RFC 3339
+    pub created_at: String,
+    /// RFC 3339, updated when the content is stored again or read
+    pub last_used_at: String,
+    /// Object given by the callers that stored the content, null without one
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GcPolicy {
+    /// Artifacts unused for longer are removed
+    pub max_age_secs: Option<u64>,
+    /// Least recently used artifacts are removed until the rest fits
+    pub max_total_bytes: Option<u64>,
+    /// Hashes never removed
+    pub keep: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GcReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub remaining: usize,
+    pub remaining_bytes: u64,
+}
+
+pub struct ArtifactStore {
+    root: PathBuf,
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+fn io_error(message: &str, path: &Path, e: &io::Error) -> CdeError {
+    CdeError::io(message).with_path(path).caused_by(e)
+}
+
+/// Writes through a temporary file so readers never see a partial file
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path)
+}
+
+fn validate_hash(hash: &str) -> Result<(), CdeError> {
+    if hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        Ok(())
+    } else {
+        Err(CdeError::invalid_input(format!(
+            "Invalid artifact hash: {} (expected 64 lowercase hex digits)",
+            hash
+        )))
+    }
+}
+
+/// Shallow merge; keys of `update` win, and a null update changes nothing
+fn merge_metadata(current: &mut serde_json::Value, update: serde_json::Value) {
+    match (current, update) {
+        (_, serde_json::Value::Null) => {}
+        (serde_json::Value::Object(current), serde_json::Value::Object(update)) => {
+            current.extend(update);
+        }
+        (current, update) => *current = update,
+    }
+}
+
+impl ArtifactStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn content_path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+
+    fn info_path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(format!("{}.json", hash))
+    }
+
+    fn load_info(&self, hash: &str) -> Option<ArtifactInfo> {
+        let content = fs::read_to_string(self.info_path(hash)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn store_info(&self, info: &ArtifactInfo) -> Result<(), CdeError> {
+        let path = self.info_path(&info.hash);
+        write_atomically(&path, to_json(info)?.as_bytes())
+            .map_err(|e| io_error("Failed to write artifact metadata", &path, &e))
+    }
+
+    /// Stores `content` unless already there; `metadata` is merged into the
+    /// metadata of stored content
+    pub fn put(
+        &self,
+        content: &[u8],
+        metadata: serde_json::Value,
+    ) -> Result<ArtifactInfo, CdeError> {
+        path_policy::check(&self.root)?;
+        let hash = blake3::hash(content).to_hex().to_string();
+        let content_path = self.content_path(&hash);
+        let stored = content_path
+            .is_file()
+            .then(|| self.load_info(&hash))
+            .flatten();
+        let info = match stored {
+            Some(mut info) => {
+                merge_metadata(&mut info.metadata, metadata);
+                info.last_used_at = now();
+                info
+            }
+            None => {
+                let dir = self.root.join(&hash[..2]);
+                fs::create_dir_all(&dir)
+                    .map_err(|e| io_error("Failed to create artifact directory", &dir, &e))?;
+                write_atomically(&content_path, content)
+                    .map_err(|e| io_error("Failed to write artifact", &content_path, &e))?;
+                let created_at = now();
+                ArtifactInfo {
+                    hash,
+                    size: content.len() as u64,
+                    last_used_at: created_at.clone(),
+                    created_at,
+                    metadata,
+                }
+            }
+        };
+        self.store_info(&info)?;
+        Ok(info)
+    }
+
+    /// Content and metadata stored under `hash`, None if missing; content that no
+    /// longer matches its hash is an error
+    pub fn get(&self, hash: &str) -> Result<Option<(Vec<u8>, ArtifactInfo)>, CdeError> {
+        validate_hash(hash)?;
+        path_policy::check(&self.root)?;
+        let content_path = self.content_path(hash);
+        let content = match fs::read(&content_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error("Failed to read artifact", &content_path, &e)),
+        };
+        if blake3::hash(&content).to_hex().as_str() != hash {
+            return Err(CdeError::parse("Artifact content does not match its hash")
+                .with_path(&content_path));
+        }
+        let Some(mut info) = self.load_info(hash) else {
+            return Ok(None);
+        };
+        info.last_used_at = now();
+        // Only costs the artifact its place in the LRU order
+        let _ = self.store_info(&info);
+        Ok(Some((content, info)))
+    }
+
+    /// Every artifact with readable metadata; metadata whose content is gone is
+    /// removed along the way
+    fn list(&self) -> Result<Vec<ArtifactInfo>, CdeError> {
+        let mut artifacts = Vec::new();
+        let shards = match fs::read_dir(&self.root) {
+            Ok(shards) => shards,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(artifacts),
+            Err(e) => return Err(io_error("Failed to list artifacts", &self.root, &e)),
+        };
+        for shard in shards.flatten() {
+            let Ok(entries) = fs::read_dir(shard.path()) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Some(hash) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".json"))
+                    .filter(|hash| validate_hash(hash).is_ok())
+                else {
+                    continue;
+                };
+                match self.load_info(hash) {
+                    Some(info) if self.content_path(hash).is_file() => artifacts.push(info),
+                    Some(_) => {
+                        let _ = fs::remove_file(&path);
+                    }
+                    None => {}
+                }
+            }
+        }
+        Ok(artifacts)
+    }
+
+    fn remove(&self, hash: &str) -> Result<(), CdeError> {
+        for path in [self.content_path(hash), self.info_path(hash)] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(io_error("Failed to remove artifact", &path, &e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the artifacts `policy` does not keep, least recently used first
+    pub fn gc(&self, policy: &GcPolicy) -> Result<GcReport, CdeError> {
+        path_policy::check(&self.root)?;
+        let mut artifacts = self.list()?;
+        let used_at = |info: &ArtifactInfo| {
+            chrono::DateTime::parse_from_rfc3339(&info.last_used_at)
+                .ok()
+                .and_then(|at| at.timestamp_nanos_opt())
+                .unwrap_or(0)
+        };
+        artifacts.sort_by_key(|info| (used_at(info), info.hash.clone()));
+
+        let cutoff = policy.max_age_secs.map(|secs| {
+            chrono::Local::now()
+                .timestamp_nanos_opt()
+                .unwrap_or(i64::MAX)
+                - (secs as i64).saturating_mul(1_000_000_000)
+        });
+        let mut remaining_bytes: u64 = artifacts.iter().map(|info| info.size).sum();
+        let mut report = GcReport::default();
+        for info in &artifacts {
+            if policy.keep.contains(&info.hash) {
+                continue;
+            }
+            let expired = cutoff.is_some_and(|cutoff| used_at(info) < cutoff);
+            let over_budget = policy
+                .max_total_bytes
+                .is_some_and(|budget| remaining_bytes > budget);
+            if expired || over_budget {
+                self.remove(&info.hash)?;
+                report.removed += 1;
+                report.freed_bytes += info.size;
+                remaining_bytes -= info.size;
+            }
+        }
+        report.remaining = artifacts.len() - report.removed;
+        report.remaining_bytes = remaining_bytes;
+        Ok(report)
+    }
+}
+
+/// Store of the process, under `CDE_ARTIFACT_DIR` or `~/.cde/artifacts`
+pub(crate) fn shared() -> ArtifactStore {
+    let root = std::env::var_os(ARTIFACT_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map_or_else(std::env::temp_dir, PathBuf::from)
+                .join(".cde")
+                .join("artifacts")
+        });
+    ArtifactStore::new(root)
+}
+
+/// Content given as bytes or as text (stored as UTF-8)
+#[derive(FromPyObject)]
+enum ArtifactContent {
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+/// Stores `content` (bytes or str) under its blake3 hash; returns its metadata as
+/// JSON (`hash`, `size`, `created_at`, `last_used_at`, `metadata`). Content
+/// already stored is not written again; `metadata_json` is merged into its
+/// metadata.
+#[pyfunction]
+#[pyo3(signature = (content, metadata_json=None))]
+fn put_artifact_py(
+    py: Python<'_>,
+    content: ArtifactContent,
+    metadata_json: Option<String>,
+) -> PyResult<String> {
+    let metadata: serde_json::Value = match metadata_json {
+        Some(json) => from_json("metadata", &json)?,
+        None => serde_json::Value::Null,
+    };
+    let info = py.detach(|| {
+        let content = match &content {
+            ArtifactContent::Bytes(bytes) => bytes.as_slice(),
+            ArtifactContent::Text(text) => text.as_bytes(),
+        };
+        shared().put(content, metadata)
+    })?;
+    Ok(to_json(&info)?)
+}
+
+/// Content stored under `hash` and its metadata as JSON, None if not stored
+#[pyfunction]
+fn get_artifact_py<'py>(
+    py: Python<'py>,
+    hash: &str,
+) -> PyResult<Option<(Bound<'py, PyBytes>, String)>> {
+    let Some((content, info)) = py.detach(|| shared().get(hash))? else {
+        return Ok(None);
+    };
+    Ok(Some((PyBytes::new(py, &content), to_json(&info)?)))
+}
+
+/// Removes artifacts unused for more than `max_age_secs`, then least recently used
+/// ones until the rest fits in `max_total_bytes`; hashes in `keep` stay. Returns
+/// `removed`, `freed_bytes`, `remaining` and `remaining_bytes` as JSON.
+#[pyfunction]
+#[pyo3(signature = (max_age_secs=None, max_total_bytes=None, keep=Vec::new()))]
+fn gc_artifacts_py(
+    py: Python<'_>,
+    max_age_secs: Option<u64>,
+    max_total_bytes: Option<u64>,
+    keep: Vec<String>,
+) -> PyResult<String> {
+    let policy = GcPolicy {
+        max_age_secs,
+        max_total_bytes,
+        keep,
+    };
+    let report = py.detach(|| shared().gc(&policy))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the artifact store functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(put_artifact_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_artifact_py, m)?)?;
+    m.add_function(wrap_pyfunction!(gc_artifacts_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_put_deduplicates_and_gc_keeps_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new(dir.path());
+
+        let first = store.put(b"report", json!({"kind": "report"})).unwrap();
+        assert_eq!(first.hash, blake3::hash(b"report").to_hex().as_str());
+        let again = store.put(b"report", json!({"run": 2})).unwrap();
+        assert_eq!(again.created_at, first.created_at);
+        assert_eq!(again.metadata, json!({"kind": "report", "run": 2}));
+
+        let (content, info) = store.get(&first.hash).unwrap().unwrap();
+        assert_eq!((content.as_slice(), info.size), (&b"report"[..], 6));
+        let missing = blake3::hash(b"missing").to_hex().to_string();
+        assert!(store.get(&missing).unwrap().is_none());
+        assert!(store.get("../escape").is_err());
+
+        let workflow = store
+            .put(b"compiled workflow", serde_json::Value::Null)
+            .unwrap();
+        fs::write(store.content_path(&workflow.hash), b"tampered").unwrap();
+        assert!(store.get(&workflow.hash).is_err());
+        store.get(&first.hash).unwrap();
+
+        // "report" was used last, so the budget evicts the workflow first
+        let report = store
+            .gc(&GcPolicy {
+                max_total_bytes: Some(10),
+                ..GcPolicy::default()
+            })
+            .unwrap();
+        assert_eq!(
+            (report.removed, report.remaining, report.remaining_bytes),
+            (1, 1, 6)
+        );
+        assert!(store.get(&first.hash).unwrap().is_some());
+
+        let report = store
+            .gc(&GcPolicy {
+                max_total_bytes: Some(0),
+                keep: vec![first.hash.clone()],
+                ..GcPolicy::default()
+            })
+            .unwrap();
+        assert_eq!(report.removed, 0);
+        let report = store
+            .gc(&GcPolicy {
+                max_age_secs: Some(0),
+                ..GcPolicy::default()
+            })
+            .unwrap();
+        assert_eq!(
+            (report.removed, report.freed_bytes, report.remaining),
+            (1, 6, 0)
+        );
+    }
+
+    #[test]
+    fn test_policy_confines_store_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new(dir.path().join("denied-artifacts"));
+        let hash = blake3::hash(b"report").to_hex().to_string();
+        let denied = path_policy::with_denied("denied-artifacts", || {
+            [
+                store.put(b"report", json!({})).err(),
+                store.get(&hash).err(),
+                store.gc(&GcPolicy::default()).err(),
+            ]
+        });
+        for error in denied {
+            assert!(
+                matches!(error, Some(CdeError::InvalidInput(_))),
+                "{error:?}"
+            );
+        }
+        assert!(!store.root.exists());
+    }
+}
@@ -0,0 +1,245 @@
+// src/async_bindings.rs
+//! Awaitable variants of the long-running bindings
+//!
+//! Each `*_async` function takes the same arguments as its `*_py` counterpart and
+//! returns an asyncio awaitable resolving to the same JSON string. The work runs
+//! on the blocking pool of the shared Tokio runtime without the GIL, so the MCP
+//! event loop keeps serving requests meanwhile. They must be called while an
+//! asyncio event loop is running. Cancelling the asyncio task does not stop the
+//! work; pass a `cancel_token` and cancel it for that.
+
+use crate::cancel::CancellationToken;
+use crate::error::{from_json, to_json, CdeError};
+use crate::paging::capped;
+use crate::progress::ProgressSink;
+use crate::{
+    documentation, git_analyzer, grep, import_graph, process_manager, project_scanner, trend,
+    workflow_validator,
+};
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Runs `work` on the shared runtime's blocking pool; the awaitable resolves to
+/// its result as JSON
+fn spawn_json<T, F>(py: Python<'_>, work: F) -> PyResult<Bound<'_, PyAny>>
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce() -> Result<T, CdeError> + Send + 'static,
+{
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let result = tokio::task::spawn_blocking(work)
+            .await
+            .map_err(|e| CdeError::io("Background task failed").caused_by(&e))?;
+        Ok(to_json(&result?)?)
+    })
+}
+
+/// Awaitable `scan_documentation_py`
+#[pyfunction]
+#[pyo3(signature = (root_path, offset=0, limit=None, include_content=true, cancel_token=None, progress_callback=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+fn scan_documentation_async(
+    py: Python<'_>,
+    root_path: String,
+    offset: usize,
+    limit: Option<usize>,
+    include_content: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    spawn_json(py, move || {
+        documentation::scan_documentation_page(
+            &root_path,
+            offset,
+            limit,
+            include_content,
+            &cancel,
+            &progress,
+        )
+    })
+}
+
+/// Awaitable `analyze_documentation_quality_py`
+#[pyfunction]
+#[pyo3(signature = (root_path, max_list_items=Some(20), cancel_token=None, progress_callback=None, op_id=None, record_trend=false))]
+fn analyze_documentation_quality_async(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+    record_trend: bool,
+) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    spawn_json(py, move || {
+        let report = documentation::analyze_documentation_quality(&root_path, &cancel, &progress)?;
+        if record_trend {
+            trend::record_quality_run(&root_path, &report);
+        }
+        Ok(capped(report, max_list_items))
+    })
+}
+
+/// Awaitable `validate_workflows_py`
+#[pyfunction]
+#[pyo3(signature = (root_path, max_list_items=None))]
+fn validate_workflows_async(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+) -> PyResult<Bound<'_, PyAny>> {
+    spawn_json(py, move || {
+        let report = workflow_validator::validate_workflows(&root_path)?;
+        Ok(capped(report, max_list_items))
+    })
+}
+
+/// Awaitable `scan_project_py`; `progress_callback` is called from a worker thread
+#[pyfunction]
+#[pyo3(signature = (root_path, excluded_dirs, excluded_patterns, follow_symlinks=false, detect_cycles=true, dedupe_hardlinks=false, large_file_threshold_bytes=5242880, max_path_length=260, handle=None, progress_callback=None, max_list_items=None, cancel_token=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+fn scan_project_async(
+    py: Python<'_>,
+    root_path: String,
+    excluded_dirs: Vec<String>,
+    excluded_patterns: Vec<String>,
+    follow_symlinks: bool,
+    detect_cycles: bool,
+    dedupe_hardlinks: bool,
+    large_file_threshold_bytes: u64,
+    max_path_length: usize,
+    handle: Option<project_scanner::ScanHandle>,
+    progress_callback: Option<Py<PyAny>>,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let options = project_scanner::ScanOptions {
+        follow_symlinks,
+        detect_cycles,
+        dedupe_hardlinks,
+        large_file_threshold_bytes,
+        max_path_length,
+    };
+    spawn_json(py, move || {
+        let result = crate::scan_project(
+            &root_path,
+            excluded_dirs,
+            excluded_patterns,
+            &options,
+            handle,
+            progress_callback,
+            cancel_token,
+            op_id,
+        )?;
+        Ok(capped(result, max_list_items))
+    })
+}
+
+/// Awaitable `analyze_git_repository_py`
+#[pyfunction]
+#[pyo3(signature = (repo_path, days, use_cache=true, cancel_token=None, progress_callback=None, op_id=None))]
+fn analyze_git_repository_async(
+    py: Python<'_>,
+    repo_path: String,
+    days: i64,
+    use_cache: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    spawn_json(py, move || {
+        if use_cache {
+            git_analyzer::analyze_git_repository_cached(&repo_path, days, &cancel, &progress)
+        } else {
+            git_analyzer::analyze_git_repository(&repo_path, days, &cancel, &progress)
+        }
+    })
+}
+
+/// Awaitable `analyze_repositories_py`
+#[pyfunction]
+#[pyo3(signature = (paths_json, days, use_cache=true, cancel_token=None, progress_callback=None, op_id=None))]
+fn analyze_repositories_async(
+    py: Python<'_>,
+    paths_json: String,
+    days: i64,
+    use_cache: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let paths: Vec<String> = from_json("paths", &paths_json)?;
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    spawn_json(py, move || {
+        Ok(git_analyzer::analyze_repositories(
+            &paths, days, use_cache, &cancel, &progress,
+        ))
+    })
+}
+
+/// Awaitable `grep_project_py`
+#[pyfunction]
+#[pyo3(signature = (root_path, pattern, options_json=None))]
+fn grep_project_async(
+    py: Python<'_>,
+    root_path: String,
+    pattern: String,
+    options_json: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let options: grep::GrepOptions = match options_json {
+        Some(json) => from_json("options", &json)?,
+        None => grep::GrepOptions::default(),
+    };
+    spawn_json(py, move || {
+        grep::grep_project(&root_path, &pattern, &options)
+    })
+}
+
+/// Awaitable `build_import_graph_py`
+#[pyfunction]
+#[pyo3(signature = (root_path, focus_files=Vec::new()))]
+fn build_import_graph_async(
+    py: Python<'_>,
+    root_path: String,
+    focus_files: Vec<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    spawn_json(py, move || {
+        import_graph::build_import_graph(&root_path, &focus_files)
+    })
+}
+
+/// Awaitable `wait_process_py`
+#[pyfunction]
+#[pyo3(signature = (pid, timeout_secs=None))]
+fn wait_process_async(
+    py: Python<'_>,
+    pid: u32,
+    timeout_secs: Option<f64>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let timeout = timeout_secs.map(Duration::from_secs_f64);
+    spawn_json(py, move || process_manager::wait_for(pid, timeout))
+}
+
+/// Adds the awaitable functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(scan_documentation_async, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_documentation_quality_async, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_workflows_async, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_project_async, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_git_repository_async, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_repositories_async, m)?)?;
+    m.add_function(wrap_pyfunction!(grep_project_async, m)?)?;
+    m.add_function(wrap_pyfunction!(build_import_graph_async, m)?)?;
+    m.add_function(wrap_pyfunction!(wait_process_async, m)?)?;
+    Ok(())
+}
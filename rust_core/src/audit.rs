@@ -0,0 +1,482 @@
+// src/audit.rs
+//! Governance audit of a project: one report, one score, one verdict
+//!
+//! Four checks run over the project and each scores 0-100: documentation
+//! quality (links, layout, size), workflow validation of `.cde/`, frontmatter
+//! metadata against the conventions' vocabulary, and repository health from a
+//! project scan. Their findings share one shape (`check`, `severity`, `path`,
+//! `message`) and are grouped by check; the overall score weighs the checks.
+//! The audit fails, with exit code 1, when a finding reaches `fail_on` or the
+//! score is below `min_score`, so CI can gate on `exit_code` alone.
+
+use crate::cancel::CancellationToken;
+use crate::conventions::{relative_to, Conventions};
+use crate::documentation;
+use crate::error::{from_json, to_json, CdeError};
+use crate::metadata::validate_metadata;
+use crate::metrics;
+use crate::path_policy;
+use crate::progress::ProgressSink;
+use crate::project_scanner::{scan_project, ScanHandle, ScanOptions};
+use crate::repo_health::DEFAULT_MAX_PATH_LENGTH;
+use crate::trend;
+use crate::workflow_validator::validate_workflows;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Weight of each check in the overall score
+const WEIGHTS: [(&str, f32); 4] = [
+    ("documentation", 0.35),
+    ("metadata", 0.25),
+    ("workflows", 0.2),
+    ("repo_health", 0.2),
+];
+
+/// Score lost per finding of each severity in the workflow and health checks
+const ERROR_PENALTY: f32 = 20.0;
+const WARNING_PENALTY: f32 = 5.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditOptions {
+    /// The audit fails below this overall score
+    pub min_score: f32,
+    /// Lowest severity failing the audit: "error", "warning" or "none"
+    pub fail_on: String,
+    /// Directory of the workflows, relative to the root
+    pub workflows_dir: String,
+    /// Directories the repository health scan skips
+    pub excluded_dirs: Vec<String>,
+    pub large_file_threshold_bytes: u64,
+    pub max_path_length: usize,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        Self {
+            min_score: 70.0,
+            fail_on: "error".to_string(),
+            workflows_dir: ".cde".to_string(),
+            excluded_dirs: [
+                ".git",
+                ".venv",
+                "node_modules",
+                "venv",
+                "__pycache__",
+                ".pytest_cache",
+                "target",
+            ]
+            .iter()
+            .map(|dir| dir.to_string())
+            .collect(),
+            large_file_threshold_bytes: 5 * 1024 * 1024,
+            max_path_length: DEFAULT_MAX_PATH_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditFinding {
+    pub check: String,
+    /// "error", "warning" or "info"
+    pub severity: String,
+    /// Relative to the root, when the finding is about one file
+    pub path: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditCheck {
+    pub check: String,
+    pub score: f32,
+    pub errors: usize,
+    pub warnings: usize,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub root: String,
+    pub score: f32,
+    pub passed: bool,
+    /// 0 when the audit passed, 1 when it failed
+    pub exit_code: i32,
+    pub checks: Vec<AuditCheck>,
+    /// Findings by check, errors first
+    pub findings: BTreeMap<String, Vec<AuditFinding>>,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+fn finding(check: &str, severity: &str, path: Option<String>, message: String) -> AuditFinding {
+    AuditFinding {
+        check: check.to_string(),
+        severity: severity.to_string(),
+        path,
+        message,
+    }
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "error" => 2,
+        "warning" => 1,
+        _ => 0,
+    }
+}
+
+fn count(findings: &[AuditFinding], severity: &str) -> usize {
+    findings.iter().filter(|f| f.severity == severity).count()
+}
+
+/// Score of a check graded by its findings alone
+fn penalized(findings: &[AuditFinding]) -> f32 {
+    let lost = count(findings, "error") as f32 * ERROR_PENALTY
+        + count(findings, "warning") as f32 * WARNING_PENALTY;
+    (100.0 - lost).max(0.0)
+}
+
+fn documentation_check(root: &str) -> Result<(f32, String, Vec<AuditFinding>), CdeError> {
+    let report = documentation::analyze_documentation_quality(
+        root,
+        &CancellationToken::default(),
+        &ProgressSink::default(),
+    )?;
+    let root_path = Path::new(root);
+    let mut findings = Vec::new();
+    for link in &report.broken_internal_links {
+        let (path, target) = link.split_once(" -> ").unwrap_or((link, ""));
+        findings.push(finding(
+            "documentation",
+            "error",
+            Some(relative_to(root_path, path)),
+            format!("Broken internal link: {}", target),
+        ));
+    }
+    for path in &report.orphaned_docs {
+        findings.push(finding(
+            "documentation",
+            "warning",
+            Some(relative_to(root_path, path)),
+            "Document outside the documentation layout".to_string(),
+        ));
+    }
+    for path in &report.misplaced_docs {
+        findings.push(finding(
+            "documentation",
+            "warning",
+            Some(relative_to(root_path, path)),
+            "Document type not allowed in its directory".to_string(),
+        ));
+    }
+    for path in &report.large_files {
+        findings.push(finding(
+            "documentation",
+            "info",
+            Some(relative_to(root_path, path)),
+            "Document exceeds 1000 lines".to_string(),
+        ));
+    }
+    let summary = format!("{} documents", report.total_docs);
+    Ok((report.quality_score, summary, findings))
+}
+
+fn workflows_check(
+    root: &str,
+    options: &AuditOptions,
+) -> Result<(f32, String, Vec<AuditFinding>), CdeError> {
+    let dir = Path::new(root).join(&options.workflows_dir);
+    if !dir.is_dir() {
+        return Ok((
+            100.0,
+            format!("No {} directory", options.workflows_dir),
+            Vec::new(),
+        ));
+    }
+    let report = validate_workflows(&dir.to_string_lossy())?;
+    let findings: Vec<AuditFinding> = report
+        .issues
+        .iter()
+        .map(|issue| {
+            let message = match issue.line {
+                Some(line) => format!("{} (phase {})", issue.message, line),
+                None => issue.message.clone(),
+            };
+            finding(
+                "workflows",
+                &issue.severity,
+                Some(relative_to(Path::new(root), &issue.file)),
+                message,
+            )
+        })
+        .collect();
+    Ok((penalized(&findings), report.summary, findings))
+}
+
+/// Frontmatter of every document: validated where present, and required in the
+/// directories restricted to some types
+fn metadata_check(root: &str) -> Result<(f32, String, Vec<AuditFinding>), CdeError> {
+    let root_path = Path::new(root);
+    let conventions = Conventions::load(root_path)?;
+    let documents = documentation::scan_documentation(root)?;
+    let mut findings = Vec::new();
+    let mut valid = 0;
+    for document in &documents {
+        let relative = relative_to(root_path, &document.path);
+        let issues = validate_metadata(document.metadata.as_ref(), &relative, &conventions);
+        if !issues.iter().any(|issue| issue.severity == "error") {
+            valid += 1;
+        }
+        findings.extend(issues.into_iter().map(|issue| {
+            finding(
+                "metadata",
+                issue.severity,
+                Some(relative.clone()),
+                issue.message,
+            )
+        }));
+    }
+    let score = if documents.is_empty() {
+        100.0
+    } else {
+        valid as f32 / documents.len() as f32 * 100.0
+    };
+    let summary = format!(
+        "{} of {} documents with valid metadata",
+        valid,
+        documents.len()
+    );
+    Ok((score, summary, findings))
+}
+
+fn repo_health_check(
+    root: &str,
+    options: &AuditOptions,
+) -> Result<(f32, String, Vec<AuditFinding>), CdeError> {
+    let scan_options = ScanOptions {
+        large_file_threshold_bytes: options.large_file_threshold_bytes,
+        max_path_length: options.max_path_length,
+        ..ScanOptions::default()
+    };
+    let result = scan_project(
+        root,
+        options.excluded_dirs.clone(),
+        Vec::new(),
+        &scan_options,
+        &ScanHandle::default(),
+        &ProgressSink::default(),
+    )?;
+    let findings: Vec<AuditFinding> = result
+        .health_findings
+        .into_iter()
+        .map(|health| {
+            finding(
+                "repo_health",
+                &health.severity,
+                Some(health.path),
+                health.message,
+            )
+        })
+        .collect();
+    Ok((
+        penalized(&findings),
+        format!("{} files", result.file_count),
+        findings,
+    ))
+}
+
+/// Runs the four governance checks over `root` and consolidates them
+pub fn audit_project(root: &str, options: &AuditOptions) -> Result<AuditReport, CdeError> {
+    metrics::timed("audit_project", || {
+        path_policy::check(root)?;
+        if !Path::new(root).is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let fail_rank = match options.fail_on.as_str() {
+            "error" => Some(2),
+            "warning" => Some(1),
+            "none" => None,
+            other => {
+                return Err(CdeError::invalid_input(format!(
+                    "Unknown fail_on severity: {} (expected error, warning or none)",
+                    other
+                )))
+            }
+        };
+        let (documentation, (metadata, (workflows, health))) = rayon::join(
+            || documentation_check(root),
+            || {
+                rayon::join(
+                    || metadata_check(root),
+                    || {
+                        rayon::join(
+                            || workflows_check(root, options),
+                            || repo_health_check(root, options),
+                        )
+                    },
+                )
+            },
+        );
+        let results = [
+            ("documentation", documentation?),
+            ("metadata", metadata?),
+            ("workflows", workflows?),
+            ("repo_health", health?),
+        ];
+
+        let mut checks = Vec::new();
+        let mut grouped = BTreeMap::new();
+        let mut score = 0.0;
+        for ((check, (check_score, summary, mut findings)), (_, weight)) in
+            results.into_iter().zip(WEIGHTS)
+        {
+            findings.sort_by(|a, b| {
+                severity_rank(&b.severity)
+                    .cmp(&severity_rank(&a.severity))
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            score += check_score * weight;
+            checks.push(AuditCheck {
+                check: check.to_string(),
+                score: check_score,
+                errors: count(&findings, "error"),
+                warnings: count(&findings, "warning"),
+                summary,
+            });
+            grouped.insert(check.to_string(), findings);
+        }
+        let errors = checks.iter().map(|check| check.errors).sum();
+        let warnings = checks.iter().map(|check| check.warnings).sum();
+        let failing = fail_rank.is_some_and(|rank| {
+            grouped
+                .values()
+                .flatten()
+                .any(|finding| severity_rank(&finding.severity) >= rank)
+        });
+        let passed = !failing && score >= options.min_score;
+        Ok(AuditReport {
+            root: root.to_string(),
+            score,
+            passed,
+            exit_code: if passed { 0 } else { 1 },
+            checks,
+            findings: grouped,
+            errors,
+            warnings,
+        })
+    })
+}
+
+/// Audits the governance of the project at `root` in one report, as JSON
+///
+/// Composes documentation quality, workflow validation (`.cde/`), frontmatter
+/// metadata validation and repository health. Each check has a 0-100 `score`,
+/// and `score` weighs them; `findings` holds every finding by check. Options
+/// (JSON): `min_score` (70), `fail_on` ("error", "warning" or "none"),
+/// `workflows_dir`, `excluded_dirs`, `large_file_threshold_bytes` and
+/// `max_path_length`. `exit_code` is 1 when the audit failed, for CI. With
+/// `record_trend` the scores are recorded for `get_quality_trend_py`; otherwise
+/// nothing is written.
+#[pyfunction]
+#[pyo3(signature = (root, options_json=None, record_trend=false))]
+fn audit_project_py(
+    py: Python<'_>,
+    root: &str,
+    options_json: Option<&str>,
+    record_trend: bool,
+) -> PyResult<String> {
+    let options: AuditOptions = match options_json {
+        Some(json) => from_json("audit options", json)?,
+        None => AuditOptions::default(),
+    };
+    let report = py.detach(|| {
+        let report = audit_project(root, &options)?;
+        if record_trend {
+            let mut scores = BTreeMap::from([("score".to_string(), f64::from(report.score))]);
+            for check in &report.checks {
+                scores.insert(check.check.clone(), f64::from(check.score));
+            }
+            trend::record_run(root, "audit", scores);
+        }
+        Ok::<_, CdeError>(report)
+    })?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the governance audit functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(audit_project_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_audit_consolidates_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("specs/design")).unwrap();
+        fs::create_dir_all(root.join(".cde")).unwrap();
+        fs::write(
+            root.join("specs/design/cache.md"),
+            "---\ntitle: Cache\ndescription: How the cache works\ntype: design\nstatus: active\ncreated: 2025-01-01\nupdated: 2025-01-02\nauthor: team\n---\n# Cache\n",
+        )
+        .unwrap();
+        fs::write(root.join("README.md"), "# Demo\n").unwrap();
+        fs::write(
+            root.join(".cde/workflow.yml"),
+            "name: default\nphases:\n  - id: define\n    description: Define\n",
+        )
+        .unwrap();
+        let root_str = root.to_str().unwrap();
+
+        let report = audit_project(root_str, &AuditOptions::default()).unwrap();
+        let names: Vec<&str> = report.checks.iter().map(|c| c.check.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["documentation", "metadata", "workflows", "repo_health"]
+        );
+        assert_eq!(report.errors, 0);
+        assert_eq!(
+            report.findings["metadata"][0].path.as_deref(),
+            Some("README.md")
+        );
+        assert!(report.passed, "{:?}", report);
+        assert_eq!(report.exit_code, 0);
+
+        fs::write(
+            root.join("specs/design/plan.md"),
+            "---\ntitle: Plan\ntype: roadmap\nstatus: done\n---\n[gone](missing.md)\n",
+        )
+        .unwrap();
+        let report = audit_project(root_str, &AuditOptions::default()).unwrap();
+        assert!(!report.passed);
+        assert_eq!(report.exit_code, 1);
+        let metadata: Vec<&str> = report.findings["metadata"]
+            .iter()
+            .filter(|f| f.severity == "error")
+            .map(|f| f.message.split(['\'', ':']).next().unwrap())
+            .collect();
+        assert_eq!(
+            metadata,
+            vec![
+                "Missing required fields",
+                "Invalid type ",
+                "Invalid status "
+            ]
+        );
+        assert_eq!(report.findings["documentation"][0].severity, "error");
+
+        let lenient = AuditOptions {
+            fail_on: "none".to_string(),
+            min_score: 0.0,
+            ..AuditOptions::default()
+        };
+        assert!(audit_project(root_str, &lenient).unwrap().passed);
+    }
+}
@@ -0,0 +1,35 @@
+// src/cancel.rs
+//! Cooperative cancellation of long-running operations
+//!
+//! A `CancellationToken` is created in Python, passed to any operation taking a
+//! `cancel_token` and cancelled from another thread or task. Operations check it
+//! between units of work (files, commits, repositories, agents) and return what
+//! they produced so far with their `cancelled` flag set.
+
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared cancellation flag; clones observe the same state
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl CancellationToken {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every operation holding the token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,535 @@
+// src/checkpoint.rs
+//! Checkpoints of long-running operations, for resuming them after a restart
+//!
+//! An operation started with an `op_id` keeps what it needs to start over
+//! (`params`) and how far it got (`state`) in the shared state store, written at
+//! most every `CHECKPOINT_INTERVAL` while it runs and once more when it ends.
+//! Checkpoints share one namespace for the whole machine: the server resuming an
+//! operation after a restart does not know which project it belonged to. An
+//! operation left "running" by a process that no longer executes it reads as
+//! "interrupted"; interrupted, failed and cancelled operations can be resumed.
+//! Agent batches are resumed by the core, which runs again the commands that did
+//! not complete. Other kinds, such as workflow simulations checkpointed from
+//! Python, are claimed and handed back to their owner to go on from `state`.
+
+use crate::cancel::CancellationToken;
+use crate::error::{from_json, to_json, CdeError};
+use crate::process_manager::{self, tree};
+use crate::state::{self, StateStore};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// State store namespace of every checkpoint
+pub const CHECKPOINT_NAMESPACE: &str = "cde/checkpoints";
+
+const OP_PREFIX: &str = "ops/";
+
+/// Shortest time between two writes of a running operation's checkpoint
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Kind of the agent batches run by `run_agent_batch_py`
+pub const AGENT_BATCH_KIND: &str = "agent_batch";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Left running by a process that no longer executes it
+    Interrupted,
+}
+
+impl OpStatus {
+    pub fn parse(status: &str) -> Result<Self, CdeError> {
+        match status {
+            "running" => Ok(Self::Running),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "cancelled" => Ok(Self::Cancelled),
+            "interrupted" => Ok(Self::Interrupted),
+            other => Err(CdeError::invalid_input(format!(
+                "Invalid operation status '{}': expected running, completed, failed, cancelled or interrupted",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::Interrupted => "interrupted",
+        }
+    }
+
+    pub fn is_resumable(self) -> bool {
+        matches!(self, Self::Failed | Self::Cancelled | Self::Interrupted)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub op_id: String,
+    /// What the operation is, such as "agent_batch" or "workflow_simulation"
+    pub kind: String,
+    /// Project the operation works on, when it has one
+    pub root: Option<String>,
+    pub status: OpStatus,
+    /// Process executing the operation
+    pub pid: u32,
+    /// RFC 3339
+    pub created_at: String,
+    pub updated_at: String,
+    /// Name of the current phase, for operations made of several
+    pub phase: Option<String>,
+    /// Units of work done so far, out of `total` when known
+    pub processed: usize,
+    pub total: Option<usize>,
+    /// What the operation needs to start over
+    pub params: Value,
+    /// Progress to resume from
+    pub state: Value,
+    /// Times the operation was resumed
+    pub resumes: u32,
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+impl Checkpoint {
+    /// A running operation of this process
+    pub fn new(op_id: &str, kind: &str, root: Option<String>, params: Value, state: Value) -> Self {
+        let created_at = now();
+        Self {
+            op_id: op_id.to_string(),
+            kind: kind.to_string(),
+            root,
+            status: OpStatus::Running,
+            pid: std::process::id(),
+            updated_at: created_at.clone(),
+            created_at,
+            phase: None,
+            processed: 0,
+            total: None,
+            params,
+            state,
+            resumes: 0,
+        }
+    }
+
+    /// Takes the operation over to run it again from its state
+    pub fn claim(&mut self) -> Result<(), CdeError> {
+        if !self.status.is_resumable() {
+            return Err(CdeError::invalid_input(format!(
+                "Operation {} is {} and cannot be resumed",
+                self.op_id,
+                self.status.as_str()
+            )));
+        }
+        self.status = OpStatus::Running;
+        self.pid = std::process::id();
+        self.resumes += 1;
+        self.updated_at = now();
+        Ok(())
+    }
+}
+
+/// Operations this process is executing
+static ACTIVE: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn op_key(op_id: &str) -> String {
+    format!("{}{}", OP_PREFIX, op_id)
+}
+
+pub fn save(store: &StateStore, checkpoint: &Checkpoint) -> Result<(), CdeError> {
+    store.set(
+        CHECKPOINT_NAMESPACE,
+        &op_key(&checkpoint.op_id),
+        &to_json(checkpoint)?,
+        None,
+    )
+}
+
+/// An operation left "running" by a process that no longer executes it reads as interrupted
+fn with_liveness(mut checkpoint: Checkpoint) -> Checkpoint {
+    if checkpoint.status == OpStatus::Running && !ACTIVE.lock().unwrap().contains(&checkpoint.op_id)
+    {
+        let own = checkpoint.pid == std::process::id();
+        if own || !tree::is_alive(checkpoint.pid) {
+            checkpoint.status = OpStatus::Interrupted;
+        }
+    }
+    checkpoint
+}
+
+pub fn load(store: &StateStore, op_id: &str) -> Result<Option<Checkpoint>, CdeError> {
+    store
+        .get(CHECKPOINT_NAMESPACE, &op_key(op_id))?
+        .map(|json| from_json::<Checkpoint>("checkpoint", &json).map(with_liveness))
+        .transpose()
+}
+
+/// Checkpoints of `root`'s and `kind`'s operations (all when None), most recently
+/// updated first; only resumable ones unless `include_finished`
+pub fn list(
+    store: &StateStore,
+    root: Option<&str>,
+    kind: Option<&str>,
+    include_finished: bool,
+) -> Result<Vec<Checkpoint>, CdeError> {
+    let root = root.map(state::namespace);
+    let mut checkpoints = Vec::new();
+    for entry in store.scan(CHECKPOINT_NAMESPACE, OP_PREFIX, None)? {
+        let checkpoint = with_liveness(from_json::<Checkpoint>("checkpoint", &entry.value)?);
+        let in_root = root.as_ref().is_none_or(|root| {
+            checkpoint.root.as_deref().map(state::namespace).as_ref() == Some(root)
+        });
+        if in_root
+            && kind.is_none_or(|kind| checkpoint.kind == kind)
+            && (include_finished || checkpoint.status.is_resumable())
+        {
+            checkpoints.push(checkpoint);
+        }
+    }
+    checkpoints.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(checkpoints)
+}
+
+pub fn delete(store: &StateStore, op_id: &str) -> Result<bool, CdeError> {
+    store.delete(CHECKPOINT_NAMESPACE, &op_key(op_id))
+}
+
+/// Writes `checkpoint` to the shared state store; a failure is only logged, the
+/// operation goes on
+fn persist_shared(checkpoint: &Checkpoint) {
+    if let Err(e) = state::with_shared(|store| save(store, checkpoint)) {
+        tracing::warn!(op_id = %checkpoint.op_id, error = %e, "Failed to persist checkpoint");
+    }
+}
+
+type Persist = Box<dyn Fn(&Checkpoint) + Send + Sync>;
+
+struct Progress {
+    checkpoint: Checkpoint,
+    last_saved: Instant,
+    dirty: bool,
+}
+
+/// Keeps the checkpoint of an operation this process executes
+///
+/// Updates are written at most every `CHECKPOINT_INTERVAL`; `finish` and drop
+/// write whatever is left.
+pub struct Checkpointer {
+    progress: Mutex<Progress>,
+    persist: Persist,
+}
+
+impl Checkpointer {
+    /// Starts checkpointing `checkpoint`, writing it right away; fails when this
+    /// process already executes an operation with the same id
+    pub fn new(mut checkpoint: Checkpoint, persist: Persist) -> Result<Self, CdeError> {
+        if !ACTIVE.lock().unwrap().insert(checkpoint.op_id.clone()) {
+            return Err(CdeError::invalid_input(format!(
+                "Operation {} is already running",
+                checkpoint.op_id
+            )));
+        }
+        checkpoint.status = OpStatus::Running;
+        checkpoint.pid = std::process::id();
+        persist(&checkpoint);
+        Ok(Self {
+            progress: Mutex::new(Progress {
+                checkpoint,
+                last_saved: Instant::now(),
+                dirty: false,
+            }),
+            persist,
+        })
+    }
+
+    /// Checkpointer writing to the shared state store
+    pub fn shared(checkpoint: Checkpoint) -> Result<Self, CdeError> {
+        Self::new(checkpoint, Box::new(persist_shared))
+    }
+
+    /// Current checkpoint, unsaved changes included
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.progress.lock().unwrap().checkpoint.clone()
+    }
+
+    /// Applies `change` to the checkpoint, writing it if the last write is
+    /// `CHECKPOINT_INTERVAL` old
+    pub fn update(&self, change: impl FnOnce(&mut Checkpoint)) {
+        let mut progress = self.progress.lock().unwrap();
+        change(&mut progress.checkpoint);
+        progress.checkpoint.updated_at = now();
+        progress.dirty = true;
+        if progress.last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+            (self.persist)(&progress.checkpoint);
+            progress.last_saved = Instant::now();
+            progress.dirty = false;
+        }
+    }
+
+    /// Records the outcome of the operation and writes the checkpoint
+    pub fn finish(self, status: OpStatus) -> Checkpoint {
+        let mut progress = self.progress.lock().unwrap();
+        progress.checkpoint.status = status;
+        progress.checkpoint.updated_at = now();
+        (self.persist)(&progress.checkpoint);
+        progress.dirty = false;
+        progress.checkpoint.clone()
+    }
+}
+
+impl Drop for Checkpointer {
+    fn drop(&mut self) {
+        let progress = self
+            .progress
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if progress.dirty {
+            (self.persist)(&progress.checkpoint);
+        }
+        ACTIVE.lock().unwrap().remove(&progress.checkpoint.op_id);
+    }
+}
+
+/// Record the progress of an operation run from Python, so it can be resumed
+///
+/// Call it with `status="running"` as the operation goes (the checkpoint is
+/// written on every call) and once more with its final status: "completed",
+/// "failed" or "cancelled". `state_json` is what the operation resumes from;
+/// `params_json`, what it needs to start over, is kept from the previous call
+/// when None. An operation this process stops checkpointing while "running" is
+/// listed as "interrupted" by `list_resumable_ops_py`.
+///
+/// Returns the checkpoint as JSON.
+#[pyfunction]
+#[pyo3(signature = (op_id, kind, state_json, params_json=None, root=None, phase=None, processed=0, total=None, status="running"))]
+#[allow(clippy::too_many_arguments)]
+fn checkpoint_op_py(
+    py: Python<'_>,
+    op_id: &str,
+    kind: &str,
+    state_json: &str,
+    params_json: Option<&str>,
+    root: Option<String>,
+    phase: Option<String>,
+    processed: usize,
+    total: Option<usize>,
+    status: &str,
+) -> PyResult<String> {
+    let status = OpStatus::parse(status)?;
+    let op_state: Value = from_json("operation state", state_json)?;
+    let params: Option<Value> = params_json
+        .map(|json| from_json("operation params", json))
+        .transpose()?;
+
+    let checkpoint = py.detach(|| {
+        state::with_shared(|store| {
+            let mut checkpoint = match load(store, op_id)? {
+                Some(previous) => Checkpoint {
+                    kind: kind.to_string(),
+                    root: root.or(previous.root),
+                    pid: std::process::id(),
+                    updated_at: now(),
+                    state: op_state,
+                    params: params.unwrap_or(previous.params),
+                    ..previous
+                },
+                None => Checkpoint::new(op_id, kind, root, params.unwrap_or(Value::Null), op_state),
+            };
+            checkpoint.status = status;
+            checkpoint.phase = phase;
+            checkpoint.processed = processed;
+            checkpoint.total = total;
+            save(store, &checkpoint)?;
+
+            let mut active = ACTIVE.lock().unwrap();
+            if status == OpStatus::Running {
+                active.insert(op_id.to_string());
+            } else {
+                active.remove(op_id);
+            }
+            Ok(checkpoint)
+        })
+    })?;
+    Ok(to_json(&checkpoint)?)
+}
+
+/// List checkpointed operations that can be resumed
+///
+/// Filters by project `root` and `kind` when given. Returns a JSON list of
+/// checkpoints (`op_id`, `kind`, `root`, `status`, `pid`, `created_at`,
+/// `updated_at`, `phase`, `processed`, `total`, `params`, `state`, `resumes`),
+/// most recently updated first. Only "failed", "cancelled" and "interrupted"
+/// operations are listed unless `include_finished`, which adds running and
+/// completed ones.
+#[pyfunction]
+#[pyo3(signature = (root=None, kind=None, include_finished=false))]
+fn list_resumable_ops_py(
+    py: Python<'_>,
+    root: Option<&str>,
+    kind: Option<&str>,
+    include_finished: bool,
+) -> PyResult<String> {
+    let checkpoints =
+        py.detach(|| state::with_shared(|store| list(store, root, kind, include_finished)))?;
+    Ok(to_json(&checkpoints)?)
+}
+
+/// Resume a failed, cancelled or interrupted operation
+///
+/// Agent batches ("agent_batch", started by `run_agent_batch_py` with an
+/// `op_id`) run again, to completion, the commands that did not complete under
+/// the batch's policy, and the report of the whole batch is returned as JSON.
+/// Secret values are never checkpointed: a batch that had secrets needs
+/// `options_json` again. `on_output` and `cancel_token` work as for
+/// `run_agent_batch_py`.
+///
+/// Any other operation is claimed for this process (status "running",
+/// `resumes` incremented) and its checkpoint returned as JSON, for its owner to
+/// go on from `state` and keep calling `checkpoint_op_py`.
+#[pyfunction]
+#[pyo3(signature = (op_id, options_json=None, on_output=None, cancel_token=None))]
+fn resume_op_py(
+    py: Python<'_>,
+    op_id: &str,
+    options_json: Option<String>,
+    on_output: Option<Py<PyAny>>,
+    cancel_token: Option<CancellationToken>,
+) -> PyResult<String> {
+    let mut checkpoint = py.detach(|| {
+        state::with_shared(|store| {
+            load(store, op_id)?
+                .ok_or_else(|| CdeError::not_found(format!("Unknown operation {}", op_id)))
+        })
+    })?;
+    checkpoint.claim()?;
+
+    if checkpoint.kind == AGENT_BATCH_KIND {
+        let cancel = cancel_token.unwrap_or_default();
+        let report = py.detach(|| {
+            process_manager::resume_agent_batch(
+                checkpoint,
+                options_json.as_deref(),
+                on_output,
+                &cancel,
+            )
+        })?;
+        return Ok(report);
+    }
+
+    py.detach(|| state::with_shared(|store| save(store, &checkpoint)))?;
+    ACTIVE.lock().unwrap().insert(checkpoint.op_id.clone());
+    Ok(to_json(&checkpoint)?)
+}
+
+/// Delete the checkpoint of an operation; returns whether there was one
+#[pyfunction]
+fn discard_op_py(py: Python<'_>, op_id: &str) -> PyResult<bool> {
+    Ok(py.detach(|| state::with_shared(|store| delete(store, op_id)))?)
+}
+
+/// Adds the checkpoint functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(checkpoint_op_py, m)?)?;
+    m.add_function(wrap_pyfunction!(list_resumable_ops_py, m)?)?;
+    m.add_function(wrap_pyfunction!(resume_op_py, m)?)?;
+    m.add_function(wrap_pyfunction!(discard_op_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_checkpoints_list_by_liveness() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+
+        let mut stale = Checkpoint::new("stale", "scan", None, json!({}), json!({}));
+        stale.pid = u32::MAX - 1;
+        save(&store, &stale).unwrap();
+        let mut done = Checkpoint::new("done", "scan", None, json!({}), json!({}));
+        done.status = OpStatus::Completed;
+        save(&store, &done).unwrap();
+        save(
+            &store,
+            &Checkpoint::new(
+                "orphan",
+                "agent_batch",
+                Some("/p".into()),
+                json!({}),
+                json!({}),
+            ),
+        )
+        .unwrap();
+
+        let resumable = list(&store, None, None, false).unwrap();
+        let ids: Vec<_> = resumable.iter().map(|cp| cp.op_id.as_str()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"stale") && ids.contains(&"orphan"));
+        assert!(resumable
+            .iter()
+            .all(|cp| cp.status == OpStatus::Interrupted));
+        assert_eq!(list(&store, None, None, true).unwrap().len(), 3);
+        assert_eq!(list(&store, None, Some("scan"), false).unwrap().len(), 1);
+        assert_eq!(
+            list(&store, Some("/p"), None, false).unwrap()[0].op_id,
+            "orphan"
+        );
+
+        let mut interrupted = load(&store, "orphan").unwrap().unwrap();
+        interrupted.claim().unwrap();
+        assert_eq!(
+            (interrupted.status, interrupted.resumes),
+            (OpStatus::Running, 1)
+        );
+        assert!(done.claim().is_err());
+        assert!(delete(&store, "done").unwrap());
+        assert_eq!(load(&store, "done").unwrap(), None);
+    }
+
+    #[test]
+    fn test_checkpointer_throttles_writes() {
+        let writes = Arc::new(Mutex::new(Vec::<Checkpoint>::new()));
+        let sink = writes.clone();
+        let checkpointer = Checkpointer::new(
+            Checkpoint::new("throttled", "scan", None, json!({}), json!({"done": 0})),
+            Box::new(move |cp| sink.lock().unwrap().push(cp.clone())),
+        )
+        .unwrap();
+        assert!(Checkpointer::new(
+            Checkpoint::new("throttled", "scan", None, json!({}), json!({})),
+            Box::new(|_| {}),
+        )
+        .is_err());
+
+        for done in 1..=3 {
+            checkpointer.update(|cp| cp.state["done"] = json!(done));
+        }
+        assert_eq!(writes.lock().unwrap().len(), 1);
+        assert_eq!(checkpointer.checkpoint().state["done"], 3);
+
+        let finished = checkpointer.finish(OpStatus::Completed);
+        let writes = writes.lock().unwrap();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[1], finished);
+        assert_eq!(finished.state["done"], 3);
+        assert!(!ACTIVE.lock().unwrap().contains("throttled"));
+    }
+}
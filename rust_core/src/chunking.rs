@@ -0,0 +1,452 @@
+// src/chunking.rs
+//! Splitting documents into token-bounded chunks for retrieval
+//!
+//! A document is cut into units first, then units are packed into chunks of at
+//! most `max_tokens` tokens (counted like `tokens::count_tokens`):
+//!
+//! - "markdown": headings, fenced code blocks and paragraphs are units, and every
+//!   heading starts a new chunk, so a chunk never spans two sections and carries
+//!   the breadcrumb of headings it sits under;
+//! - "paragraph": paragraphs (separated by blank lines) are units;
+//! - "fixed": the text is cut wherever needed to fill chunks.
+//!
+//! Units larger than `max_tokens` are split at the line break, then the
+//! whitespace, closest to their middle until they fit. Each chunk after the first
+//! of a section starts with the last units of the previous chunk, up to `overlap`
+//! tokens. Chunks keep their byte and line span in the source.
+
+use crate::error::{to_json, CdeError};
+use crate::path_policy;
+use crate::tokens::count_tokens;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Markdown,
+    Paragraph,
+    Fixed,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Result<Self, CdeError> {
+        match name {
+            "markdown" => Ok(Self::Markdown),
+            "paragraph" => Ok(Self::Paragraph),
+            "fixed" => Ok(Self::Fixed),
+            _ => Err(CdeError::invalid_input(format!(
+                "Unknown chunking strategy: {} (expected markdown, paragraph or fixed)",
+                name
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub index: usize,
+    pub text: String,
+    pub tokens: usize,
+    /// Byte offsets in the source, end exclusive
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Headings enclosing the chunk, outermost first (markdown only)
+    pub headings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkedDocument {
+    /// File the text was read from, None for text given directly
+    pub path: Option<String>,
+    pub chunks: Vec<Chunk>,
+}
+
+/// Span of the source packed as a whole unless too large
+#[derive(Debug, Clone)]
+struct Unit {
+    start: usize,
+    end: usize,
+    /// Starts a new chunk, without overlap
+    section_start: bool,
+    headings: Vec<String>,
+}
+
+/// Lines of `text` with the byte offset they start at, line breaks included
+fn lines_with_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+/// Level and text of an ATX heading line
+pub(crate) fn heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+    let rest = &trimmed[level..];
+    let valid =
+        (1..=6).contains(&level) && (rest.trim().is_empty() || rest.starts_with([' ', '\t']));
+    valid.then(|| {
+        (
+            level,
+            rest.trim().trim_end_matches('#').trim_end().to_string(),
+        )
+    })
+}
+
+/// Fence character and length of a code fence line
+pub(crate) fn fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.chars().take_while(|&c| c == marker).count();
+    (length >= 3).then_some((marker, length))
+}
+
+fn markdown_units(text: &str) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let mut headings: Vec<String> = Vec::new();
+    // Open paragraph or fence: start offset, and the fence marker for a fence
+    let mut open: Option<(usize, Option<(char, usize)>)> = None;
+    let close = |units: &mut Vec<Unit>, start: usize, end: usize, headings: &[String]| {
+        units.push(Unit {
+            start,
+            end,
+            section_start: false,
+            headings: headings.to_vec(),
+        });
+    };
+
+    for (offset, line) in lines_with_offsets(text) {
+        let end = offset + line.len();
+        if let Some((start, Some((marker, length)))) = open {
+            let closing = fence(line).is_some_and(|(m, l)| {
+                m == marker && l >= length && line.trim().chars().all(|c| c == marker)
+            });
+            if closing {
+                close(&mut units, start, end, &headings);
+                open = None;
+            }
+            continue;
+        }
+        if let Some((level, title)) = heading(line) {
+            if let Some((start, _)) = open.take() {
+                close(&mut units, start, offset, &headings);
+            }
+            headings.truncate(level - 1);
+            headings.push(title);
+            units.push(Unit {
+                start: offset,
+                end,
+                section_start: true,
+                headings: headings.clone(),
+            });
+        } else if let Some(marker) = fence(line) {
+            if let Some((start, _)) = open.take() {
+                close(&mut units, start, offset, &headings);
+            }
+            open = Some((offset, Some(marker)));
+        } else if line.trim().is_empty() {
+            if let Some((start, _)) = open.take() {
+                close(&mut units, start, offset, &headings);
+            }
+        } else if open.is_none() {
+            open = Some((offset, None));
+        }
+    }
+    // An unclosed fence runs to the end, as in CommonMark
+    if let Some((start, _)) = open {
+        close(&mut units, start, text.len(), &headings);
+    }
+    units
+}
+
+fn paragraph_units(text: &str) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let mut open = None;
+    for (offset, line) in lines_with_offsets(text) {
+        match (line.trim().is_empty(), open) {
+            (true, Some(start)) => {
+                units.push(Unit {
+                    start,
+                    end: offset,
+                    section_start: false,
+                    headings: Vec::new(),
+                });
+                open = None;
+            }
+            (false, None) => open = Some(offset),
+            _ => {}
+        }
+    }
+    if let Some(start) = open {
+        units.push(Unit {
+            start,
+            end: text.len(),
+            section_start: false,
+            headings: Vec::new(),
+        });
+    }
+    units
+}
+
+/// Byte offset in `text[start..end]` to cut at: after the line break, else at
+/// the whitespace, else at the character boundary closest to the middle
+fn cut_point(text: &str, start: usize, end: usize) -> Option<usize> {
+    fn closest(cuts: impl Iterator<Item = usize>, start: usize, end: usize) -> Option<usize> {
+        let middle = start + (end - start) / 2;
+        cuts.filter(|&i| i > start && i < end)
+            .min_by_key(|&i| i.abs_diff(middle))
+    }
+    let span = &text[start..end];
+    let offsets = span.char_indices();
+    closest(
+        span.match_indices('\n').map(|(i, _)| start + i + 1),
+        start,
+        end,
+    )
+    .or_else(|| {
+        let spaces = offsets.clone().filter(|(_, c)| c.is_whitespace());
+        closest(spaces.map(|(i, _)| start + i), start, end)
+    })
+    .or_else(|| closest(offsets.map(|(i, _)| start + i), start, end))
+}
+
+/// `unit` cut in pieces of at most `max_tokens` tokens
+fn split_to_fit(text: &str, unit: Unit, max_tokens: usize, model: &str, pieces: &mut Vec<Unit>) {
+    let cut = (count_tokens(&text[unit.start..unit.end], model) > max_tokens)
+        .then(|| cut_point(text, unit.start, unit.end))
+        .flatten();
+    let Some(cut) = cut else {
+        pieces.push(unit);
+        return;
+    };
+    let second = Unit {
+        start: cut,
+        section_start: false,
+        ..unit.clone()
+    };
+    split_to_fit(text, Unit { end: cut, ..unit }, max_tokens, model, pieces);
+    split_to_fit(text, second, max_tokens, model, pieces);
+}
+
+/// 1-based line of each byte offset
+struct LineIndex(Vec<usize>);
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self(starts)
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.0.partition_point(|&start| start <= offset)
+    }
+}
+
+/// Splits `text` into chunks of at most `max_tokens` tokens of `model`
+pub fn chunk_text(
+    text: &str,
+    max_tokens: usize,
+    overlap: usize,
+    strategy: Strategy,
+    model: &str,
+) -> Result<Vec<Chunk>, CdeError> {
+    if max_tokens == 0 || overlap >= max_tokens {
+        return Err(CdeError::invalid_input(format!(
+            "Invalid chunk sizes: max_tokens={} overlap={} (expected overlap < max_tokens)",
+            max_tokens, overlap
+        )));
+    }
+    let units = match strategy {
+        Strategy::Markdown => markdown_units(text),
+        Strategy::Paragraph => paragraph_units(text),
+        Strategy::Fixed if text.trim().is_empty() => Vec::new(),
+        Strategy::Fixed => vec![Unit {
+            start: 0,
+            end: text.len(),
+            section_start: false,
+            headings: Vec::new(),
+        }],
+    };
+    let mut pieces = Vec::new();
+    for unit in units {
+        split_to_fit(text, unit, max_tokens, model, &mut pieces);
+    }
+
+    let lines = LineIndex::new(text);
+    let mut chunks = Vec::new();
+    // Pieces of the chunk being filled
+    let mut current: Vec<&Unit> = Vec::new();
+    let span_tokens = |units: &[&Unit]| match (units.first(), units.last()) {
+        (Some(first), Some(last)) => count_tokens(&text[first.start..last.end], model),
+        _ => 0,
+    };
+    let flush = |units: &[&Unit], chunks: &mut Vec<Chunk>| {
+        let (Some(first), Some(last)) = (units.first(), units.last()) else {
+            return;
+        };
+        let chunk_text = &text[first.start..last.end];
+        chunks.push(Chunk {
+            index: chunks.len(),
+            text: chunk_text.to_string(),
+            tokens: count_tokens(chunk_text, model),
+            start_byte: first.start,
+            end_byte: last.end,
+            start_line: lines.line(first.start),
+            end_line: lines.line(last.end.saturating_sub(1).max(first.start)),
+            headings: first.headings.clone(),
+        });
+    };
+
+    for piece in &pieces {
+        if piece.section_start && !current.is_empty() {
+            flush(&current, &mut chunks);
+            current.clear();
+        }
+        current.push(piece);
+        if current.len() > 1 && span_tokens(&current) > max_tokens {
+            current.pop();
+            flush(&current, &mut chunks);
+            // Carry the longest tail of whole pieces within `overlap`, if the next
+            // piece still fits after it
+            let mut carried = current.len();
+            while carried > 0 && span_tokens(&current[carried - 1..]) <= overlap {
+                carried -= 1;
+            }
+            let mut next: Vec<&Unit> = current.split_off(carried);
+            next.push(piece);
+            if next.len() > 1 && span_tokens(&next) > max_tokens {
+                next.drain(..next.len() - 1);
+            }
+            current = next;
+        }
+    }
+    flush(&current, &mut chunks);
+    Ok(chunks)
+}
+
+/// Chunks `path_or_text`: the file at that path when it names one, else the text
+/// itself
+pub fn chunk_document(
+    path_or_text: &str,
+    max_tokens: usize,
+    overlap: usize,
+    strategy: Strategy,
+    model: &str,
+) -> Result<ChunkedDocument, CdeError> {
+    let is_file = !path_or_text.contains('\n') && Path::new(path_or_text).is_file();
+    let (path, chunks) = if is_file {
+        path_policy::check(path_or_text)?;
+        let text = std::fs::read_to_string(path_or_text).map_err(|e| {
+            CdeError::io("Failed to read document")
+                .with_path(path_or_text)
+                .caused_by(&e)
+        })?;
+        let chunks = chunk_text(&text, max_tokens, overlap, strategy, model)?;
+        (Some(path_or_text.to_string()), chunks)
+    } else {
+        let chunks = chunk_text(path_or_text, max_tokens, overlap, strategy, model)?;
+        (None, chunks)
+    };
+    Ok(ChunkedDocument { path, chunks })
+}
+
+/// Splits a document into chunks of at most `max_tokens` tokens of `model`
+///
+/// `path_or_text` is read as a file when it is the path of one, else chunked as
+/// is. `strategy` is "markdown" (split by headings, code fences and paragraphs;
+/// chunks never span sections and carry their heading breadcrumb), "paragraph"
+/// or "fixed". Consecutive chunks of a section share up to `overlap` tokens.
+/// Returns JSON with `path` and `chunks` (`index`, `text`, `tokens`,
+/// `start_byte`, `end_byte`, `start_line`, `end_line`, `headings`).
+#[pyfunction]
+#[pyo3(signature = (path_or_text, max_tokens=512, overlap=0, strategy="markdown", model="gpt-4o"))]
+fn chunk_document_py(
+    py: Python<'_>,
+    path_or_text: &str,
+    max_tokens: usize,
+    overlap: usize,
+    strategy: &str,
+    model: &str,
+) -> PyResult<String> {
+    let strategy = Strategy::parse(strategy)?;
+    let document =
+        py.detach(|| chunk_document(path_or_text, max_tokens, overlap, strategy, model))?;
+    Ok(to_json(&document)?)
+}
+
+/// Adds the chunking functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(chunk_document_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Guide\n\nIntro paragraph.\n\n## Install\n\nRun this:\n\n```sh\ncargo build\n\ncargo test\n```\n\n## Usage\n\nFirst.\n\nSecond.\n";
+
+    #[test]
+    fn test_markdown_chunks_follow_sections_and_fences() {
+        let chunks = chunk_text(DOC, 100, 0, Strategy::Markdown, "gpt-4o").unwrap();
+        let headings: Vec<Vec<String>> = chunks.iter().map(|c| c.headings.clone()).collect();
+        assert_eq!(
+            headings,
+            vec![
+                vec!["Guide".to_string()],
+                vec!["Guide".to_string(), "Install".to_string()],
+                vec!["Guide".to_string(), "Usage".to_string()],
+            ]
+        );
+        // The blank line inside the fence does not split it
+        assert!(chunks[1].text.ends_with("cargo test\n```\n"));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (5, 13));
+        assert_eq!(
+            &DOC[chunks[2].start_byte..chunks[2].end_byte],
+            chunks[2].text
+        );
+        assert_eq!(chunks[2].text, "## Usage\n\nFirst.\n\nSecond.\n");
+    }
+
+    #[test]
+    fn test_limits_overlap_and_strategies() {
+        let text = (1..=40)
+            .map(|i| format!("Sentence number {} of the text.", i))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let chunks = chunk_text(&text, 30, 8, Strategy::Paragraph, "gpt-4o").unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.tokens <= 30));
+        // Each chunk repeats the last paragraph of the previous one
+        assert!(chunks[1].start_byte < chunks[0].end_byte);
+        assert!(chunks.last().unwrap().text.ends_with("40 of the text."));
+
+        let long_line = "word ".repeat(300);
+        let fixed = chunk_text(&long_line, 50, 0, Strategy::Fixed, "gpt-4o").unwrap();
+        assert!(fixed.len() >= 6 && fixed.iter().all(|c| c.tokens <= 50));
+        assert_eq!(
+            fixed.iter().map(|c| c.text.as_str()).collect::<String>(),
+            long_line
+        );
+
+        assert!(chunk_text("", 10, 0, Strategy::Markdown, "gpt-4o")
+            .unwrap()
+            .is_empty());
+        assert!(chunk_text("text", 10, 10, Strategy::Markdown, "gpt-4o").is_err());
+        assert!(Strategy::parse("semantic").is_err());
+    }
+}
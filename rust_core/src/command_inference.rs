@@ -0,0 +1,524 @@
+// rust_core/src/command_inference.rs
+//! Infers how to install, build, test and run a project
+//!
+//! Reads manifests and task files in the project root (package.json, Cargo.toml,
+//! pyproject.toml, go.mod, Makefile, docker-compose, ...) and returns candidate
+//! commands ranked by confidence, so agents bootstrap with the project's own tooling.
+
+use crate::error::CdeError;
+use crate::path_policy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Explicitly declared by the project (npm script, Makefile target)
+const CONFIDENCE_DECLARED: f32 = 0.9;
+/// Standard command of a detected toolchain
+const CONFIDENCE_CONVENTION: f32 = 0.7;
+/// Guessed from file layout only
+const CONFIDENCE_HEURISTIC: f32 = 0.5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InferredCommand {
+    pub kind: String, // "install", "build", "test", "run", "lint", "task"
+    pub command: String,
+    pub source: String, // File the command was inferred from
+    pub confidence: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommandInference {
+    pub commands: Vec<InferredCommand>,
+    pub entry_points: Vec<String>,
+    pub toolchains: Vec<String>,
+}
+
+/// Inspects the project root and returns ranked command candidates
+pub fn infer_project_commands(root_path: &str) -> Result<CommandInference, CdeError> {
+    let root = Path::new(root_path);
+    path_policy::check(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root_path));
+    }
+
+    let mut inference = CommandInference {
+        commands: Vec::new(),
+        entry_points: Vec::new(),
+        toolchains: Vec::new(),
+    };
+
+    infer_make(root, &mut inference);
+    infer_node(root, &mut inference);
+    infer_rust(root, &mut inference);
+    infer_python(root, &mut inference);
+    infer_go(root, &mut inference);
+    infer_jvm(root, &mut inference);
+    infer_docker(root, &mut inference);
+
+    // Highest confidence first, stable within a confidence level, no duplicates
+    let mut seen = HashSet::new();
+    inference
+        .commands
+        .retain(|c| seen.insert(c.command.clone()));
+    inference.commands.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then(kind_rank(&a.kind).cmp(&kind_rank(&b.kind)))
+    });
+
+    Ok(inference)
+}
+
+fn kind_rank(kind: &str) -> usize {
+    ["install", "build", "test", "run", "lint", "task"]
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(usize::MAX)
+}
+
+fn push(
+    inference: &mut CommandInference,
+    kind: &str,
+    command: String,
+    source: &str,
+    confidence: f32,
+) {
+    inference.commands.push(InferredCommand {
+        kind: kind.to_string(),
+        command,
+        source: source.to_string(),
+        confidence,
+    });
+}
+
+fn infer_make(root: &Path, inference: &mut CommandInference) {
+    let Ok(content) = fs::read_to_string(root.join("Makefile")) else {
+        return;
+    };
+    inference.toolchains.push("make".to_string());
+
+    let target_re = Regex::new(r"(?m)^([A-Za-z0-9][A-Za-z0-9_.-]*)\s*:([^=]|$)").unwrap();
+    for cap in target_re.captures_iter(&content) {
+        let target = &cap[1];
+        let kind = match target {
+            "install" | "deps" | "setup" => "install",
+            "build" | "all" | "compile" => "build",
+            "test" | "tests" | "check" => "test",
+            "run" | "start" | "serve" | "dev" => "run",
+            "lint" | "fmt" | "format" => "lint",
+            _ => "task",
+        };
+        let confidence = if kind == "task" {
+            CONFIDENCE_HEURISTIC
+        } else {
+            0.95
+        };
+        push(
+            inference,
+            kind,
+            format!("make {}", target),
+            "Makefile",
+            confidence,
+        );
+    }
+}
+
+fn infer_node(root: &Path, inference: &mut CommandInference) {
+    let Ok(content) = fs::read_to_string(root.join("package.json")) else {
+        return;
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return;
+    };
+
+    let manager = if root.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if root.join("yarn.lock").exists() {
+        "yarn"
+    } else if root.join("bun.lockb").exists() || root.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    };
+    inference.toolchains.push(manager.to_string());
+
+    let install = if manager == "npm" && root.join("package-lock.json").exists() {
+        "npm ci".to_string()
+    } else {
+        format!("{} install", manager)
+    };
+    push(
+        inference,
+        "install",
+        install,
+        "package.json",
+        CONFIDENCE_CONVENTION,
+    );
+
+    if let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) {
+        for name in scripts.keys() {
+            let kind = match name.as_str() {
+                "build" | "compile" => "build",
+                "test" | "test:unit" => "test",
+                "start" | "dev" | "serve" => "run",
+                "lint" | "format" | "typecheck" => "lint",
+                _ => "task",
+            };
+            let command = match (manager, name.as_str()) {
+                ("npm", "test" | "start") => format!("npm {}", name),
+                ("npm", _) => format!("npm run {}", name),
+                _ => format!("{} {}", manager, name),
+            };
+            let confidence = if kind == "task" {
+                CONFIDENCE_HEURISTIC
+            } else {
+                CONFIDENCE_DECLARED
+            };
+            push(inference, kind, command, "package.json", confidence);
+        }
+    }
+
+    if let Some(main) = package.get("main").and_then(|m| m.as_str()) {
+        inference.entry_points.push(main.to_string());
+    }
+}
+
+fn infer_rust(root: &Path, inference: &mut CommandInference) {
+    if !root.join("Cargo.toml").exists() {
+        return;
+    }
+    inference.toolchains.push("cargo".to_string());
+
+    push(
+        inference,
+        "build",
+        "cargo build".to_string(),
+        "Cargo.toml",
+        CONFIDENCE_CONVENTION,
+    );
+    push(
+        inference,
+        "test",
+        "cargo test".to_string(),
+        "Cargo.toml",
+        CONFIDENCE_CONVENTION,
+    );
+    push(
+        inference,
+        "lint",
+        "cargo clippy".to_string(),
+        "Cargo.toml",
+        CONFIDENCE_HEURISTIC,
+    );
+    if root.join("src/main.rs").exists() {
+        push(
+            inference,
+            "run",
+            "cargo run".to_string(),
+            "Cargo.toml",
+            CONFIDENCE_CONVENTION,
+        );
+        inference.entry_points.push("src/main.rs".to_string());
+    }
+}
+
+fn infer_python(root: &Path, inference: &mut CommandInference) {
+    let pyproject = fs::read_to_string(root.join("pyproject.toml")).ok();
+    let has_requirements = root.join("requirements.txt").exists();
+    let has_setup = root.join("setup.py").exists();
+    if pyproject.is_none() && !has_requirements && !has_setup {
+        return;
+    }
+    inference.toolchains.push("python".to_string());
+
+    let pyproject_text = pyproject.as_deref().unwrap_or("");
+    if root.join("uv.lock").exists() {
+        push(
+            inference,
+            "install",
+            "uv sync".to_string(),
+            "uv.lock",
+            CONFIDENCE_DECLARED,
+        );
+    } else if root.join("poetry.lock").exists() || pyproject_text.contains("[tool.poetry]") {
+        push(
+            inference,
+            "install",
+            "poetry install".to_string(),
+            "pyproject.toml",
+            CONFIDENCE_DECLARED,
+        );
+    } else if has_requirements {
+        push(
+            inference,
+            "install",
+            "pip install -r requirements.txt".to_string(),
+            "requirements.txt",
+            CONFIDENCE_CONVENTION,
+        );
+    } else {
+        let source = if pyproject.is_some() {
+            "pyproject.toml"
+        } else {
+            "setup.py"
+        };
+        push(
+            inference,
+            "install",
+            "pip install -e .".to_string(),
+            source,
+            CONFIDENCE_CONVENTION,
+        );
+    }
+
+    if root.join("pytest.ini").exists() || pyproject_text.contains("[tool.pytest") {
+        push(
+            inference,
+            "test",
+            "pytest".to_string(),
+            "pytest.ini",
+            CONFIDENCE_DECLARED,
+        );
+    } else if root.join("tox.ini").exists() {
+        push(
+            inference,
+            "test",
+            "tox".to_string(),
+            "tox.ini",
+            CONFIDENCE_CONVENTION,
+        );
+    } else if root.join("tests").is_dir() || root.join("conftest.py").exists() {
+        push(
+            inference,
+            "test",
+            "pytest".to_string(),
+            "tests/",
+            CONFIDENCE_HEURISTIC,
+        );
+    }
+
+    if pyproject_text.contains("[tool.ruff") {
+        push(
+            inference,
+            "lint",
+            "ruff check .".to_string(),
+            "pyproject.toml",
+            CONFIDENCE_DECLARED,
+        );
+    }
+
+    if root.join("manage.py").exists() {
+        push(
+            inference,
+            "run",
+            "python manage.py runserver".to_string(),
+            "manage.py",
+            CONFIDENCE_CONVENTION,
+        );
+        inference.entry_points.push("manage.py".to_string());
+    }
+    for candidate in ["main.py", "app.py", "__main__.py"] {
+        if root.join(candidate).exists() {
+            push(
+                inference,
+                "run",
+                format!("python {}", candidate),
+                candidate,
+                CONFIDENCE_HEURISTIC,
+            );
+            inference.entry_points.push(candidate.to_string());
+        }
+    }
+}
+
+fn infer_go(root: &Path, inference: &mut CommandInference) {
+    if !root.join("go.mod").exists() {
+        return;
+    }
+    inference.toolchains.push("go".to_string());
+
+    push(
+        inference,
+        "build",
+        "go build ./...".to_string(),
+        "go.mod",
+        CONFIDENCE_CONVENTION,
+    );
+    push(
+        inference,
+        "test",
+        "go test ./...".to_string(),
+        "go.mod",
+        CONFIDENCE_CONVENTION,
+    );
+    push(
+        inference,
+        "lint",
+        "go vet ./...".to_string(),
+        "go.mod",
+        CONFIDENCE_HEURISTIC,
+    );
+    if root.join("main.go").exists() {
+        push(
+            inference,
+            "run",
+            "go run .".to_string(),
+            "go.mod",
+            CONFIDENCE_CONVENTION,
+        );
+        inference.entry_points.push("main.go".to_string());
+    }
+}
+
+fn infer_jvm(root: &Path, inference: &mut CommandInference) {
+    if root.join("pom.xml").exists() {
+        inference.toolchains.push("maven".to_string());
+        let mvn = if root.join("mvnw").exists() {
+            "./mvnw"
+        } else {
+            "mvn"
+        };
+        push(
+            inference,
+            "build",
+            format!("{} package", mvn),
+            "pom.xml",
+            CONFIDENCE_CONVENTION,
+        );
+        push(
+            inference,
+            "test",
+            format!("{} test", mvn),
+            "pom.xml",
+            CONFIDENCE_CONVENTION,
+        );
+    }
+
+    let gradle_file = ["build.gradle", "build.gradle.kts"]
+        .into_iter()
+        .find(|f| root.join(f).exists());
+    if let Some(gradle_file) = gradle_file {
+        inference.toolchains.push("gradle".to_string());
+        let gradle = if root.join("gradlew").exists() {
+            "./gradlew"
+        } else {
+            "gradle"
+        };
+        push(
+            inference,
+            "build",
+            format!("{} build", gradle),
+            gradle_file,
+            CONFIDENCE_CONVENTION,
+        );
+        push(
+            inference,
+            "test",
+            format!("{} test", gradle),
+            gradle_file,
+            CONFIDENCE_CONVENTION,
+        );
+    }
+}
+
+fn infer_docker(root: &Path, inference: &mut CommandInference) {
+    let compose_file = [
+        "docker-compose.yml",
+        "docker-compose.yaml",
+        "compose.yml",
+        "compose.yaml",
+    ]
+    .into_iter()
+    .find(|f| root.join(f).exists());
+
+    if let Some(compose_file) = compose_file {
+        inference.toolchains.push("docker-compose".to_string());
+        push(
+            inference,
+            "run",
+            "docker compose up".to_string(),
+            compose_file,
+            CONFIDENCE_CONVENTION,
+        );
+
+        let services = fs::read_to_string(root.join(compose_file))
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+            .and_then(|doc| doc.get("services").and_then(|s| s.as_mapping()).cloned());
+        for name in services
+            .iter()
+            .flat_map(|s| s.keys())
+            .filter_map(|k| k.as_str())
+        {
+            push(
+                inference,
+                "run",
+                format!("docker compose up {}", name),
+                compose_file,
+                CONFIDENCE_HEURISTIC,
+            );
+        }
+    }
+
+    if root.join("Dockerfile").exists() {
+        inference.toolchains.push("docker".to_string());
+        let tag = root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "app".to_string());
+        push(
+            inference,
+            "build",
+            format!("docker build -t {} .", tag),
+            "Dockerfile",
+            CONFIDENCE_HEURISTIC,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_infer_project_commands() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        fs::write(
+            root.join("Makefile"),
+            ".PHONY: test\ntest:\n\tpytest\nVAR := 1\nrelease: build\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"main": "index.js", "scripts": {"test": "jest", "build": "tsc"}}"#,
+        )
+        .unwrap();
+        fs::write(root.join("yarn.lock"), "").unwrap();
+        fs::write(root.join("pyproject.toml"), "[tool.pytest.ini_options]\n").unwrap();
+
+        let inference = infer_project_commands(root.to_str().unwrap()).unwrap();
+        let commands: Vec<&str> = inference
+            .commands
+            .iter()
+            .map(|c| c.command.as_str())
+            .collect();
+
+        assert_eq!(commands[0], "make test");
+        assert!(commands.contains(&"yarn test"));
+        assert!(commands.contains(&"yarn build"));
+        assert!(commands.contains(&"pytest"));
+        assert!(!commands
+            .iter()
+            .any(|c| c.contains("VAR") || c.contains("PHONY")));
+        assert_eq!(inference.entry_points, vec!["index.js".to_string()]);
+        assert!(inference
+            .commands
+            .windows(2)
+            .all(|w| w[0].confidence >= w[1].confidence));
+    }
+}
@@ -0,0 +1,580 @@
+// src/config_validator.rs
+//! Validation of JSON, TOML and YAML configuration files against JSON Schemas
+//!
+//! A spec names a file or a glob under the project root (`.cde/agents.json`,
+//! `mcp.json`, `pyproject.toml`), the schema its content must satisfy (inline
+//! or in a JSON/YAML file) and optionally the dotted `section` to check, such
+//! as `tool.cde` of a `pyproject.toml`. Every format is read into a JSON value,
+//! so one schema language covers all three. Files are validated in parallel,
+//! and the issues are those of the workflow validator, which SARIF reporting
+//! shares with it.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use globset::Glob;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: String, // "error", "warning", "info"
+    pub file: String,
+    pub line: Option<usize>,
+    pub message: String,
+    /// Identifier of the check, such as "config-schema"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+}
+
+impl ValidationIssue {
+    pub fn error(file: &str, line: Option<usize>, rule: &str, message: String) -> Self {
+        Self {
+            severity: "error".to_string(),
+            file: file.to_string(),
+            line,
+            message,
+            rule: Some(rule.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Format of `path` by its extension
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yml" | "yaml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// Line of the byte `offset` in `content`, from 1
+fn line_at(content: &str, offset: usize) -> usize {
+    content.as_bytes()[..offset.min(content.len())]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+        + 1
+}
+
+/// `content` read as `format` into a JSON value; a syntax error is given with
+/// its line when known
+pub fn parse_config(content: &str, format: ConfigFormat) -> Result<Value, (String, Option<usize>)> {
+    match format {
+        ConfigFormat::Json => serde_json::from_str(content).map_err(|e| {
+            let line = (e.line() > 0).then_some(e.line());
+            (format!("Invalid JSON syntax: {}", e), line)
+        }),
+        ConfigFormat::Toml => toml::from_str(content).map_err(|e| {
+            let line = e.span().map(|span| line_at(content, span.start));
+            (format!("Invalid TOML syntax: {}", e.message()), line)
+        }),
+        ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| {
+            let line = e.location().map(|location| location.line());
+            (format!("Invalid YAML syntax: {}", e), line)
+        }),
+    }
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSpec {
+    /// File or glob, relative to the root
+    pub path: String,
+    /// JSON Schema of the content
+    #[serde(default)]
+    pub schema: Option<Value>,
+    /// JSON or YAML file holding the schema, relative to the root
+    #[serde(default)]
+    pub schema_path: Option<String>,
+    /// Dotted path of the part to validate, the whole file by default
+    #[serde(default)]
+    pub section: Option<String>,
+    /// Format of the files, by their extension by default
+    #[serde(default)]
+    pub format: Option<ConfigFormat>,
+    /// Whether a missing file or section is an error
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConfigValidationReport {
+    pub valid: bool,
+    pub total_files: usize,
+    pub valid_files: usize,
+    pub invalid_files: usize,
+    pub issues: Vec<ValidationIssue>,
+    /// Files validated, relative to the root
+    pub files: Vec<String>,
+    pub summary: String,
+    /// Whether lists were cut to `max_list_items`
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl CapLists for ConfigValidationReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.issues, max_items) | cap(&mut self.files, max_items);
+    }
+}
+
+/// Schema of `spec` compiled, None when it only checks syntax
+fn compile_schema(
+    root: &Path,
+    spec: &ConfigSpec,
+) -> Result<Option<jsonschema::Validator>, CdeError> {
+    let schema = match (&spec.schema, &spec.schema_path) {
+        (Some(schema), _) => schema.clone(),
+        (None, Some(schema_path)) => {
+            let path = root.join(schema_path);
+            let content = fs::read_to_string(&path).map_err(|e| {
+                CdeError::io("Failed to read schema")
+                    .with_path(&path)
+                    .caused_by(&e)
+            })?;
+            let format = ConfigFormat::of(&path).unwrap_or(ConfigFormat::Json);
+            parse_config(&content, format).map_err(|(message, _)| {
+                CdeError::invalid_input(format!("Invalid schema: {}", message)).with_path(&path)
+            })?
+        }
+        (None, None) => return Ok(None),
+    };
+    jsonschema::validator_for(&schema).map(Some).map_err(|e| {
+        CdeError::invalid_input(format!("Invalid JSON Schema for {}: {}", spec.path, e))
+    })
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '[', '{'])
+}
+
+/// Files under `root` matching `pattern`, relative with `/` separators
+fn expand(root: &Path, pattern: &str) -> Result<Vec<String>, CdeError> {
+    let pattern = pattern.trim_start_matches("./");
+    if !is_glob(pattern) {
+        return Ok(if root.join(pattern).is_file() {
+            vec![pattern.to_string()]
+        } else {
+            Vec::new()
+        });
+    }
+    let matcher = Glob::new(pattern)
+        .map_err(|e| {
+            CdeError::invalid_input(format!("Invalid config glob: {}", pattern)).caused_by(&e)
+        })?
+        .compile_matcher();
+    let guard = path_policy::walk_guard(root)?;
+    let mut files: Vec<String> = WalkBuilder::new(root)
+        .hidden(false)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |e| {
+            e.file_name() != ".git" && guard.allows(e.path(), e.path_is_symlink())
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| {
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            matcher.is_match(&relative).then_some(relative)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The part of `value` at the dotted `section`
+fn select<'a>(value: &'a Value, section: &str) -> Option<&'a Value> {
+    section
+        .split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn validate_file(
+    root: &Path,
+    relative: &str,
+    spec: &ConfigSpec,
+    validator: Option<&jsonschema::Validator>,
+) -> Vec<ValidationIssue> {
+    let path = root.join(relative);
+    let Some(format) = spec.format.or_else(|| ConfigFormat::of(&path)) else {
+        return vec![ValidationIssue::error(
+            relative,
+            None,
+            "config-format",
+            "Unknown config format (expected .json, .toml, .yml or .yaml, or a format)".to_string(),
+        )];
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            return vec![ValidationIssue::error(
+                relative,
+                None,
+                "config-read",
+                format!("Failed to read file: {}", e),
+            )]
+        }
+    };
+    let value = match parse_config(&content, format) {
+        Ok(value) => value,
+        Err((message, line)) => {
+            return vec![ValidationIssue::error(
+                relative,
+                line,
+                "config-syntax",
+                message,
+            )]
+        }
+    };
+    let target = match &spec.section {
+        Some(section) => match select(&value, section) {
+            Some(target) => target,
+            None if spec.required => {
+                return vec![ValidationIssue::error(
+                    relative,
+                    None,
+                    "config-missing",
+                    format!("Missing section: {}", section),
+                )]
+            }
+            None => return Vec::new(),
+        },
+        None => &value,
+    };
+    let Some(validator) = validator else {
+        return Vec::new();
+    };
+    let prefix = spec
+        .section
+        .as_deref()
+        .map(|section| format!("/{}", section.replace('.', "/")))
+        .unwrap_or_default();
+    validator
+        .iter_errors(target)
+        .map(|error| {
+            let pointer = format!("{}{}", prefix, error.instance_path);
+            let at = if pointer.is_empty() { "/" } else { &pointer };
+            ValidationIssue::error(
+                relative,
+                None,
+                "config-schema",
+                format!("{}: {}", at, error),
+            )
+        })
+        .collect()
+}
+
+/// Validates the files named by `specs` under `root`, in parallel
+pub fn validate_configs(
+    root: &str,
+    specs: &[ConfigSpec],
+) -> Result<ConfigValidationReport, CdeError> {
+    metrics::timed("validate_configs", || {
+        let root_path = Path::new(root);
+        path_policy::check(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let mut issues = Vec::new();
+        let mut jobs = Vec::new();
+        let validators = specs
+            .iter()
+            .map(|spec| compile_schema(root_path, spec))
+            .collect::<Result<Vec<_>, CdeError>>()?;
+        for (spec, validator) in specs.iter().zip(&validators) {
+            let files = expand(root_path, &spec.path)?;
+            if files.is_empty() && spec.required {
+                issues.push(ValidationIssue::error(
+                    &spec.path,
+                    None,
+                    "config-missing",
+                    "Config file not found".to_string(),
+                ));
+            }
+            jobs.extend(
+                files
+                    .into_iter()
+                    .map(|file| (file, spec, validator.as_ref())),
+            );
+        }
+
+        let results: Vec<(String, Vec<ValidationIssue>)> = jobs
+            .par_iter()
+            .map(|(file, spec, validator)| {
+                (
+                    file.clone(),
+                    validate_file(root_path, file, spec, *validator),
+                )
+            })
+            .collect();
+        let files: BTreeSet<String> = results.iter().map(|(file, _)| file.clone()).collect();
+        issues.extend(results.into_iter().flat_map(|(_, issues)| issues));
+
+        let invalid: BTreeSet<&str> = issues
+            .iter()
+            .filter(|issue| issue.severity == "error" && files.contains(&issue.file))
+            .map(|issue| issue.file.as_str())
+            .collect();
+        let invalid_files = invalid.len();
+        let total_files = files.len();
+        let valid = issues.iter().all(|issue| issue.severity != "error");
+        let summary = if valid {
+            format!("✅ All {} config files are valid.", total_files)
+        } else {
+            format!(
+                "⚠️ Found {} issues in {} of {} config files.",
+                issues.len(),
+                invalid_files,
+                total_files
+            )
+        };
+        Ok(ConfigValidationReport {
+            valid,
+            total_files,
+            valid_files: total_files - invalid_files,
+            invalid_files,
+            issues,
+            files: files.into_iter().collect(),
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+fn sarif_level(severity: &str) -> &'static str {
+    match severity {
+        "error" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+/// `issues` as a SARIF 2.1.0 log of `tool`; file paths under `root` are made
+/// relative to it
+pub fn to_sarif(tool: &str, issues: &[ValidationIssue], root: Option<&Path>) -> Value {
+    let rule_of = |issue: &ValidationIssue| {
+        issue
+            .rule
+            .clone()
+            .unwrap_or_else(|| "validation".to_string())
+    };
+    let rules: BTreeMap<String, ()> = issues.iter().map(|issue| (rule_of(issue), ())).collect();
+    let results: Vec<Value> = issues
+        .iter()
+        .map(|issue| {
+            let uri = root
+                .and_then(|root| Path::new(&issue.file).strip_prefix(root).ok())
+                .map_or_else(|| PathBuf::from(&issue.file), Path::to_path_buf)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let mut location = json!({ "artifactLocation": { "uri": uri } });
+            if let Some(line) = issue.line {
+                location["region"] = json!({ "startLine": line });
+            }
+            json!({
+                "ruleId": rule_of(issue),
+                "level": sarif_level(&issue.severity),
+                "message": { "text": issue.message },
+                "locations": [{ "physicalLocation": location }],
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": tool,
+                    "rules": rules.keys().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// Validates JSON, TOML and YAML config files against JSON Schemas, in parallel
+///
+/// `specs_json` is a list of specs: `path` (file or glob relative to `root`),
+/// `schema` (inline JSON Schema) or `schema_path` (JSON/YAML file relative to
+/// `root`), `section` (dotted path of the part to check, e.g. "tool.cde"),
+/// `format` ("json", "toml" or "yaml"; by extension by default) and `required`
+/// (true: a missing file or section is an error). Without a schema only the
+/// syntax is checked. Returns `valid`, file counts, `issues` (severity, file,
+/// line, message, rule) and `summary`; with `sarif`, the issues as a SARIF log.
+#[pyfunction]
+#[pyo3(signature = (root, specs_json, sarif=false, max_list_items=None))]
+fn validate_configs_py(
+    py: Python<'_>,
+    root: &str,
+    specs_json: &str,
+    sarif: bool,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let specs: Vec<ConfigSpec> = from_json("config specs", specs_json)?;
+    let mut report = py.detach(|| validate_configs(root, &specs))?;
+    if sarif {
+        return Ok(to_json(&to_sarif(
+            "cde-config-validator",
+            &report.issues,
+            Some(Path::new(root)),
+        ))?);
+    }
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Converts the `issues` of a validation report (config or workflow) to SARIF
+///
+/// `report_json` is any report with an `issues` list; file paths under `root`
+/// become relative to it. Returns the SARIF 2.1.0 log of `tool`, as JSON.
+#[pyfunction]
+#[pyo3(signature = (report_json, tool="cde", root=None))]
+fn validation_sarif_py(report_json: &str, tool: &str, root: Option<&str>) -> PyResult<String> {
+    #[derive(Deserialize)]
+    struct Issues {
+        issues: Vec<ValidationIssue>,
+    }
+    let report: Issues = from_json("validation report", report_json)?;
+    Ok(to_json(&to_sarif(
+        tool,
+        &report.issues,
+        root.map(Path::new),
+    ))?)
+}
+
+/// Adds the config validation functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(validate_configs_py, m)?)?;
+    m.add_function(wrap_pyfunction!(validation_sarif_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validates_configs_against_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".cde/agents")).unwrap();
+        fs::write(
+            root.join(".cde/agents/ok.json"),
+            r#"{"name": "a", "model": "m"}"#,
+        )
+        .unwrap();
+        fs::write(root.join(".cde/agents/bad.json"), r#"{"name": 3}"#).unwrap();
+        fs::write(root.join(".cde/agents/broken.json"), "{\n  \"name\": \n}").unwrap();
+        fs::write(
+            root.join("pyproject.toml"),
+            "[project]\nname = \"demo\"\n\n[tool.cde]\nworkers = \"many\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("settings.yml"), "a: [1, 2\n").unwrap();
+
+        let specs: Vec<ConfigSpec> = serde_json::from_value(json!([
+            {
+                "path": ".cde/agents/*.json",
+                "schema": {
+                    "type": "object",
+                    "required": ["name", "model"],
+                    "properties": { "name": { "type": "string" } }
+                }
+            },
+            {
+                "path": "pyproject.toml",
+                "section": "tool.cde",
+                "schema": { "properties": { "workers": { "type": "integer" } } }
+            },
+            { "path": "pyproject.toml", "section": "tool.other", "required": false },
+            { "path": "settings.yml" },
+            { "path": "mcp.json" }
+        ]))
+        .unwrap();
+        let report = validate_configs(root.to_str().unwrap(), &specs).unwrap();
+        let mut issues: Vec<(&str, Option<usize>, &str)> = report
+            .issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.file.as_str(),
+                    issue.line,
+                    issue.rule.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        issues.sort();
+        assert_eq!(
+            issues,
+            [
+                (".cde/agents/bad.json", None, "config-schema"),
+                (".cde/agents/bad.json", None, "config-schema"),
+                (".cde/agents/broken.json", Some(3), "config-syntax"),
+                ("mcp.json", None, "config-missing"),
+                ("pyproject.toml", None, "config-schema"),
+                ("settings.yml", Some(2), "config-syntax"),
+            ]
+        );
+        assert!(report
+            .issues
+            .iter()
+            .any(|issue| issue.message.starts_with("/tool/cde/workers: ")));
+        assert!(!report.valid);
+        assert_eq!((report.total_files, report.invalid_files), (5, 4));
+
+        let sarif = to_sarif("cde", &report.issues, None);
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 6);
+        let broken = results
+            .iter()
+            .find(|result| {
+                result["ruleId"] == "config-syntax"
+                    && result["locations"][0]["physicalLocation"]["region"]["startLine"] == 3
+            })
+            .unwrap();
+        assert_eq!(broken["level"], "error");
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
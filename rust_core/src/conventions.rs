@@ -0,0 +1,356 @@
+// src/conventions.rs
+//! Where documents belong in a project, and which types may live there
+//!
+//! A layout rule maps a glob (relative to the project root, `/` separators) to a
+//! category and the document types allowed in it, none meaning any. The first
+//! rule matching a document decides its category; a document matching no rule
+//! is orphaned, and one whose frontmatter `type` its category does not allow is
+//! misplaced. The default rules describe the CDE layout (`specs/<kind>/`,
+//! `agent-docs/<kind>/`, `docs/` and the standard root files); a project adds
+//! its own in `.cde/conventions.yml`, checked before the defaults unless
+//! `extend_defaults` is false. The same file may replace the vocabulary of
+//! frontmatter `type` and `status` values:
+//!
+//! ```yaml
+//! rules:
+//!   - glob: "rfcs/**"
+//!     category: rfcs
+//!     types: [design]
+//! doc_types: [feature, design, task, guide, governance, rfc]
+//! ```
+
+use crate::error::{to_json, CdeError};
+use crate::filesystem::find_markdown_files;
+use crate::metadata::extract_metadata;
+use crate::metrics;
+use crate::path_policy;
+use crate::text_file;
+use globset::{Glob, GlobMatcher};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Project conventions, relative to the project root
+pub const CONVENTIONS_FILE: &str = ".cde/conventions.yml";
+
+/// Standard files allowed at the root of a project
+const ROOT_FILES: &str =
+    "{README,CHANGELOG,CONTRIBUTING,CODE_OF_CONDUCT,LICENSE,AGENTS,GEMINI,CLAUDE}.md";
+
+/// Frontmatter `type` values of the CDE governance
+const DEFAULT_DOC_TYPES: &[&str] = &[
+    "feature",
+    "design",
+    "task",
+    "guide",
+    "governance",
+    "session",
+    "execution",
+    "feedback",
+    "research",
+];
+
+/// Frontmatter `status` values of the CDE governance
+const DEFAULT_STATUSES: &[&str] = &["draft", "active", "deprecated", "archived"];
+
+const DEFAULT_RULES: &[(&str, &str, &[&str])] = &[
+    (ROOT_FILES, "root", &[]),
+    ("specs/features/**", "specs/features", &["feature"]),
+    ("specs/design/**", "specs/design", &["design"]),
+    ("specs/tasks/**", "specs/tasks", &["task"]),
+    ("specs/governance/**", "specs/governance", &["governance"]),
+    ("specs/templates/**", "specs/templates", &[]),
+    ("specs/**", "specs", &[]),
+    (
+        "agent-docs/sessions/**",
+        "agent-docs/sessions",
+        &["session"],
+    ),
+    (
+        "agent-docs/execution/**",
+        "agent-docs/execution",
+        &["execution"],
+    ),
+    (
+        "agent-docs/feedback/**",
+        "agent-docs/feedback",
+        &["feedback"],
+    ),
+    (
+        "agent-docs/research/**",
+        "agent-docs/research",
+        &["research"],
+    ),
+    ("agent-docs/README.md", "agent-docs", &[]),
+    ("docs/**", "docs", &[]),
+    (".github/**", ".github", &[]),
+    ("memory/**", "memory", &[]),
+    ("**/README.md", "readme", &[]),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutRule {
+    pub glob: String,
+    pub category: String,
+    /// Document types allowed, any when empty
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct ConventionsFile {
+    rules: Vec<LayoutRule>,
+    extend_defaults: bool,
+    doc_types: Option<Vec<String>>,
+    statuses: Option<Vec<String>>,
+}
+
+impl Default for ConventionsFile {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            extend_defaults: true,
+            doc_types: None,
+            statuses: None,
+        }
+    }
+}
+
+/// Layout rules compiled, in the order they are tried
+pub struct Conventions {
+    rules: Vec<(GlobMatcher, LayoutRule)>,
+    doc_types: Vec<String>,
+    statuses: Vec<String>,
+}
+
+fn owned(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocPlacement {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    /// None for an orphaned document
+    pub category: Option<String>,
+    #[serde(rename = "type")]
+    pub doc_type: Option<String>,
+    pub orphaned: bool,
+    /// Whether its type is not among those of its category
+    pub misplaced: bool,
+    /// Types its category allows, any when empty
+    pub allowed_types: Vec<String>,
+}
+
+impl Conventions {
+    /// Compiles `rules` with the default vocabulary, rejecting an invalid glob
+    pub fn new(rules: Vec<LayoutRule>) -> Result<Self, CdeError> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let glob = Glob::new(&rule.glob).map_err(|e| {
+                    CdeError::invalid_input(format!("Invalid layout glob: {}", rule.glob))
+                        .caused_by(&e)
+                })?;
+                Ok((glob.compile_matcher(), rule))
+            })
+            .collect::<Result<_, CdeError>>()?;
+        Ok(Self {
+            rules,
+            doc_types: owned(DEFAULT_DOC_TYPES),
+            statuses: owned(DEFAULT_STATUSES),
+        })
+    }
+
+    /// Allowed frontmatter `type` values
+    pub fn doc_types(&self) -> &[String] {
+        &self.doc_types
+    }
+
+    /// Allowed frontmatter `status` values
+    pub fn statuses(&self) -> &[String] {
+        &self.statuses
+    }
+
+    /// The default CDE layout
+    pub fn default_rules() -> Vec<LayoutRule> {
+        DEFAULT_RULES
+            .iter()
+            .map(|(glob, category, types)| LayoutRule {
+                glob: glob.to_string(),
+                category: category.to_string(),
+                types: owned(types),
+            })
+            .collect()
+    }
+
+    /// Conventions of the project at `root`: its `.cde/conventions.yml` if any,
+    /// then the defaults
+    pub fn load(root: &Path) -> Result<Self, CdeError> {
+        let path = root.join(CONVENTIONS_FILE);
+        let file = match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str::<ConventionsFile>(&content).map_err(|e| {
+                CdeError::parse("Invalid conventions file")
+                    .with_path(&path)
+                    .caused_by(&e)
+            })?,
+            Err(_) => ConventionsFile::default(),
+        };
+        let mut rules = file.rules;
+        if file.extend_defaults {
+            rules.extend(Self::default_rules());
+        }
+        let mut conventions = Self::new(rules)?;
+        if let Some(doc_types) = file.doc_types {
+            conventions.doc_types = doc_types;
+        }
+        if let Some(statuses) = file.statuses {
+            conventions.statuses = statuses;
+        }
+        Ok(conventions)
+    }
+
+    /// First rule matching `relative`
+    pub fn rule_for(&self, relative: &str) -> Option<&LayoutRule> {
+        let relative = relative.trim_start_matches("./");
+        self.rules
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(relative))
+            .map(|(_, rule)| rule)
+    }
+
+    /// Where the document at `relative` stands, given its frontmatter `type`
+    pub fn place(&self, relative: &str, doc_type: Option<&str>) -> DocPlacement {
+        let rule = self.rule_for(relative);
+        let allowed_types = rule.map(|rule| rule.types.clone()).unwrap_or_default();
+        let misplaced = match doc_type {
+            Some(doc_type) => {
+                !allowed_types.is_empty() && !allowed_types.iter().any(|t| t == doc_type)
+            }
+            None => false,
+        };
+        DocPlacement {
+            path: relative.to_string(),
+            category: rule.map(|rule| rule.category.clone()),
+            doc_type: doc_type.map(str::to_string),
+            orphaned: rule.is_none(),
+            misplaced,
+            allowed_types,
+        }
+    }
+}
+
+/// `path` relative to `root` with `/` separators
+pub(crate) fn relative_to(root: &Path, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(root)
+        .unwrap_or(Path::new(path))
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Placement of every Markdown document under `root` by the project's
+/// conventions, sorted by path
+pub fn classify_documents(root: &str) -> Result<Vec<DocPlacement>, CdeError> {
+    metrics::timed("classify_documents", || {
+        let root_path = Path::new(root);
+        path_policy::check(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let conventions = Conventions::load(root_path)?;
+        let mut placements: Vec<DocPlacement> = find_markdown_files(root_path)
+            .par_iter()
+            .filter(|path| Path::new(path).is_file())
+            .map(|path| {
+                let doc_type = text_file::read_text(Path::new(path))
+                    .ok()
+                    .and_then(|content| extract_metadata(&content))
+                    .and_then(|metadata| metadata.doc_type);
+                conventions.place(&relative_to(root_path, path), doc_type.as_deref())
+            })
+            .collect();
+        placements.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(placements)
+    })
+}
+
+/// Classifies the Markdown documents under `root` by the layout conventions, as JSON
+///
+/// Each document gets its `category` (None when `orphaned`), its frontmatter
+/// `type`, the `allowed_types` of its category and whether it is `misplaced`
+/// there. The rules are those of `.cde/conventions.yml`, then the CDE defaults.
+#[pyfunction]
+fn classify_documents_py(py: Python<'_>, root: &str) -> PyResult<String> {
+    let placements = py.detach(|| classify_documents(root))?;
+    Ok(to_json(&placements)?)
+}
+
+/// Adds the layout convention functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(classify_documents_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_places_documents_by_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let conventions = Conventions::load(root).unwrap();
+        let place = |path: &str, doc_type: Option<&str>| conventions.place(path, doc_type);
+        assert_eq!(place("README.md", None).category.as_deref(), Some("root"));
+        assert!(place("NOTES.md", None).orphaned);
+        assert!(!place("specs/design/cache.md", Some("design")).misplaced);
+        assert!(place("specs/design/cache.md", Some("task")).misplaced);
+        assert_eq!(
+            place("rust_core/README.md", None).category.as_deref(),
+            Some("readme")
+        );
+        assert!(place("agent-docs/notes.md", None).orphaned);
+
+        fs::create_dir_all(root.join(".cde")).unwrap();
+        fs::write(
+            root.join(CONVENTIONS_FILE),
+            "rules:\n  - glob: \"rfcs/**\"\n    category: rfcs\n    types: [design]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("rfcs")).unwrap();
+        fs::write(root.join("rfcs/one.md"), "---\ntype: task\n---\n").unwrap();
+        fs::write(root.join("README.md"), "# Demo\n").unwrap();
+        let placements = classify_documents(root.to_str().unwrap()).unwrap();
+        let summary: Vec<(&str, Option<&str>, bool)> = placements
+            .iter()
+            .map(|p| (p.path.as_str(), p.category.as_deref(), p.misplaced))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("README.md", Some("root"), false),
+                ("rfcs/one.md", Some("rfcs"), true)
+            ]
+        );
+
+        fs::write(
+            root.join(CONVENTIONS_FILE),
+            "extend_defaults: false\nstatuses: [open, closed]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Conventions::load(root).unwrap().statuses(),
+            ["open", "closed"]
+        );
+        assert!(
+            Conventions::load(root)
+                .unwrap()
+                .place("README.md", None)
+                .orphaned
+        );
+    }
+}
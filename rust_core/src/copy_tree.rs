@@ -0,0 +1,357 @@
+// src/copy_tree.rs
+//! Copying (or moving) a project tree without what it does not need
+//!
+//! The source is walked like `git` sees it: `.gitignore`, `.ignore` and
+//! `.git/info/exclude` rules apply (even outside a repository), heavy directories
+//! such as `node_modules` or `target` are left out, and `exclude` globs drop
+//! more. Hidden files are kept, so `.env.example` or `.github` come along. Files
+//! are copied in parallel with their permissions; symlinks are recreated, not
+//! followed. A dry run returns the plan without touching anything.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CopyOptions {
+    pub respect_gitignore: bool,
+    /// Directory names never copied
+    pub exclude_dirs: Vec<String>,
+    /// Globs of paths not copied, relative to the source (`*.log`, `dist/**`)
+    pub exclude: Vec<String>,
+    pub include_hidden: bool,
+    /// Replace files that exist in the destination instead of skipping them
+    pub overwrite: bool,
+    /// Move the files instead of copying them; emptied source directories are
+    /// removed, ignored files stay where they are
+    pub move_files: bool,
+    pub dry_run: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            respect_gitignore: true,
+            exclude_dirs: [
+                ".git",
+                "node_modules",
+                ".venv",
+                "venv",
+                "__pycache__",
+                "target",
+            ]
+            .map(String::from)
+            .to_vec(),
+            exclude: Vec::new(),
+            include_hidden: true,
+            overwrite: false,
+            move_files: false,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    /// Relative to the source and the destination, with `/` separators
+    pub path: String,
+    pub size: u64,
+    pub is_symlink: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopyReport {
+    pub source: String,
+    pub dest: String,
+    pub dry_run: bool,
+    /// Every file of the plan, sorted
+    pub files: Vec<PlannedFile>,
+    pub directories: usize,
+    /// Files copied or moved; 0 for a dry run
+    pub copied: usize,
+    /// Files left alone because they exist in the destination
+    pub skipped: Vec<String>,
+    pub total_bytes: u64,
+}
+
+/// Directories and files of `source` to copy, relative and sorted
+fn plan(source: &Path, options: &CopyOptions) -> Result<(Vec<String>, Vec<PlannedFile>), CdeError> {
+    let guard = path_policy::walk_guard(source)?;
+    let mut overrides = OverrideBuilder::new(source);
+    for glob in &options.exclude {
+        overrides.add(&format!("!{}", glob)).map_err(|e| {
+            CdeError::invalid_input(format!("Invalid glob '{}'", glob)).caused_by(&e)
+        })?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| CdeError::invalid_input("Invalid glob set").caused_by(&e))?;
+    let exclude_dirs = options.exclude_dirs.clone();
+    let walker = WalkBuilder::new(source)
+        .hidden(!options.include_hidden)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .ignore(options.respect_gitignore)
+        .parents(options.respect_gitignore)
+        .require_git(false)
+        .overrides(overrides)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| {
+            let excluded = entry.depth() > 0
+                && entry.file_type().is_some_and(|t| t.is_dir())
+                && exclude_dirs
+                    .iter()
+                    .any(|name| entry.file_name() == name.as_str());
+            !excluded && guard.allows(entry.path(), entry.path_is_symlink())
+        })
+        .build();
+
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            dirs.push(relative);
+        } else if file_type.is_file() || file_type.is_symlink() {
+            files.push(PlannedFile {
+                path: relative,
+                size: entry.metadata().map_or(0, |m| m.len()),
+                is_symlink: file_type.is_symlink(),
+            });
+        }
+    }
+    dirs.sort();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((dirs, files))
+}
+
+/// Copies or moves one planned file; false when it was skipped
+fn transfer(
+    from: &Path,
+    to: &Path,
+    file: &PlannedFile,
+    options: &CopyOptions,
+) -> std::io::Result<bool> {
+    if to.symlink_metadata().is_ok() {
+        if !options.overwrite {
+            return Ok(false);
+        }
+        fs::remove_file(to)?;
+    }
+    if options.move_files && fs::rename(from, to).is_ok() {
+        return Ok(true);
+    }
+    if file.is_symlink {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+        #[cfg(not(unix))]
+        return Ok(false);
+    } else {
+        fs::copy(from, to)?;
+    }
+    if options.move_files {
+        fs::remove_file(from)?;
+    }
+    Ok(true)
+}
+
+/// Copies the tree at `source` to `dest`, see the module docs
+pub fn copy_tree(source: &str, dest: &str, options: &CopyOptions) -> Result<CopyReport, CdeError> {
+    metrics::timed("copy_tree", || {
+        let source_path = Path::new(source);
+        let dest_path = Path::new(dest);
+        path_policy::check(dest_path)?;
+        if !source_path.is_dir() {
+            return Err(CdeError::not_a_directory(source));
+        }
+        if path_policy::resolve(dest_path).starts_with(path_policy::resolve(source_path)) {
+            return Err(CdeError::invalid_input("Destination is inside the source").with_path(dest));
+        }
+        let (dirs, files) = plan(source_path, options)?;
+        let total_bytes = files.iter().map(|file| file.size).sum();
+        let mut report = CopyReport {
+            source: source.to_string(),
+            dest: dest.to_string(),
+            dry_run: options.dry_run,
+            directories: dirs.len(),
+            files,
+            copied: 0,
+            skipped: Vec::new(),
+            total_bytes,
+        };
+        if options.dry_run {
+            return Ok(report);
+        }
+
+        for dir in std::iter::once("").chain(dirs.iter().map(String::as_str)) {
+            let target = dest_path.join(dir);
+            fs::create_dir_all(&target).map_err(|e| {
+                CdeError::io("Failed to create directory")
+                    .with_path(&target)
+                    .caused_by(&e)
+            })?;
+        }
+        let outcomes = report
+            .files
+            .par_iter()
+            .map(|file| {
+                let (from, to) = (source_path.join(&file.path), dest_path.join(&file.path));
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        CdeError::io("Failed to create directory")
+                            .with_path(parent)
+                            .caused_by(&e)
+                    })?;
+                }
+                transfer(&from, &to, file, options).map_err(|e| {
+                    CdeError::io("Failed to copy file")
+                        .with_path(&from)
+                        .caused_by(&e)
+                })
+            })
+            .collect::<Result<Vec<bool>, CdeError>>()?;
+        for (file, copied) in report.files.iter().zip(outcomes) {
+            if copied {
+                report.copied += 1;
+            } else {
+                report.skipped.push(file.path.clone());
+            }
+        }
+        if options.move_files {
+            // Deepest first; directories still holding ignored files stay
+            for dir in dirs.iter().rev() {
+                let _ = fs::remove_dir(source_path.join(dir));
+            }
+        }
+        metrics::add_bytes("copy_tree", report.total_bytes);
+        Ok(report)
+    })
+}
+
+/// Copies (or moves) a project tree, skipping ignored and heavy files, as JSON
+///
+/// `options_json` may set `respect_gitignore` (true), `exclude_dirs` (".git",
+/// "node_modules", ".venv", "venv", "__pycache__", "target"), `exclude` globs,
+/// `include_hidden` (true), `overwrite` (false: existing files are skipped),
+/// `move_files` and `dry_run`. Returns the `files` of the plan (`path`, `size`,
+/// `is_symlink`), the number of `directories`, the number of files `copied`, the
+/// `skipped` ones and `total_bytes`.
+#[pyfunction]
+#[pyo3(signature = (src, dest, options_json=None))]
+fn copy_tree_py(
+    py: Python<'_>,
+    src: &str,
+    dest: &str,
+    options_json: Option<&str>,
+) -> PyResult<String> {
+    let options: CopyOptions = match options_json {
+        Some(json) => from_json("options", json)?,
+        None => CopyOptions::default(),
+    };
+    let report = py.detach(|| copy_tree(src, dest, &options))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the tree copy functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(copy_tree_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copies_what_git_sees_and_moves() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("project");
+        for sub in ["src", "node_modules/pkg", "build", ".github", "empty"] {
+            fs::create_dir_all(source.join(sub)).unwrap();
+        }
+        for (file, content) in [
+            ("src/main.py", "print()"),
+            ("node_modules/pkg/index.js", "x"),
+            ("build/out.bin", "x"),
+            (".gitignore", "build/\n"),
+            (".github/ci.yml", "on: push"),
+            ("debug.log", "x"),
+        ] {
+            fs::write(source.join(file), content).unwrap();
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::write(source.join("run.sh"), "#!/bin/sh").unwrap();
+            fs::set_permissions(source.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let source_str = source.to_str().unwrap();
+        let options = CopyOptions {
+            exclude: vec!["*.log".to_string()],
+            ..CopyOptions::default()
+        };
+
+        let dest = dir.path().join("clone");
+        let dry = CopyOptions {
+            dry_run: true,
+            ..options.clone()
+        };
+        let plan = copy_tree(source_str, dest.to_str().unwrap(), &dry).unwrap();
+        let mut paths: Vec<&str> = plan.files.iter().map(|f| f.path.as_str()).collect();
+        paths.retain(|p| *p != "run.sh");
+        assert_eq!(paths, vec![".github/ci.yml", ".gitignore", "src/main.py"]);
+        assert!(!dest.exists());
+
+        let report = copy_tree(source_str, dest.to_str().unwrap(), &options).unwrap();
+        assert_eq!(report.copied, report.files.len());
+        assert_eq!(
+            fs::read_to_string(dest.join("src/main.py")).unwrap(),
+            "print()"
+        );
+        assert!(dest.join("empty").is_dir());
+        assert!(!dest.join("node_modules").exists() && !dest.join("build").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dest.join("run.sh"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        // Existing files are skipped unless overwriting
+        let again = copy_tree(source_str, dest.to_str().unwrap(), &options).unwrap();
+        assert_eq!((again.copied, again.skipped.len()), (0, report.files.len()));
+        assert!(copy_tree(source_str, source.join("sub").to_str().unwrap(), &options).is_err());
+
+        let moved = dir.path().join("moved");
+        let moving = CopyOptions {
+            move_files: true,
+            ..options
+        };
+        copy_tree(source_str, moved.to_str().unwrap(), &moving).unwrap();
+        assert!(moved.join("src/main.py").exists());
+        assert!(!source.join("src").exists());
+        // Ignored files stay behind
+        assert!(source.join("build/out.bin").exists());
+    }
+}
@@ -0,0 +1,87 @@
+// src/corpus.rs
+//! Checked-in corpus of tricky real-world inputs for the parsers' tests
+//!
+//! Each parser has a directory under `testdata/corpus/` with its inputs and an
+//! `expected.json` holding what its test makes of each of them. A parser change
+//! that alters any result fails that test and prints the new values; when the
+//! change is intended, rerun the test with `UPDATE_CORPUS=1` to rewrite the
+//! expectations and review their diff.
+//!
+//! The property tests next to the parsers cover what the corpus cannot list.
+//! proptest saves the inputs that made them fail under `proptest-regressions/`;
+//! those files are checked in too, so the failure is retried on every run.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+const EXPECTED: &str = "expected.json";
+
+pub fn dir(kind: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join("corpus")
+        .join(kind)
+}
+
+/// Inputs of the `kind` corpus, by file name
+pub fn inputs(kind: &str) -> Vec<(String, PathBuf)> {
+    let mut inputs: Vec<(String, PathBuf)> = fs::read_dir(dir(kind))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .map(|path| (path.file_name().unwrap().to_string_lossy().into_owned(), path))
+        .filter(|(name, _)| name != EXPECTED)
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// Runs `summarize` on every input of the `kind` corpus and compares the
+/// results with the expected ones
+pub fn check(kind: &str, summarize: impl Fn(&Path) -> Value) {
+    let actual = inputs(kind)
+        .into_iter()
+        .map(|(name, path)| {
+            let summary = catch_unwind(AssertUnwindSafe(|| summarize(&path)))
+                .unwrap_or_else(|_| panic!("{kind}/{name} made the parser panic"));
+            (name, summary)
+        })
+        .collect();
+    check_results(kind, actual);
+}
+
+/// Compares results keyed by anything stable, for corpora whose entries are
+/// not one per file
+pub fn check_results(kind: &str, actual: BTreeMap<String, Value>) {
+    let expected_path = dir(kind).join(EXPECTED);
+    if std::env::var_os("UPDATE_CORPUS").is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        fs::write(&expected_path, json + "\n").unwrap();
+        return;
+    }
+    let expected: BTreeMap<String, Value> =
+        serde_json::from_str(&fs::read_to_string(&expected_path).unwrap()).unwrap();
+
+    let mut names: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+    names.sort();
+    names.dedup();
+    let changed: Vec<String> = names
+        .into_iter()
+        .filter(|name| expected.get(*name) != actual.get(*name))
+        .map(|name| {
+            format!(
+                "{name}:\n  expected {}\n  actual   {}",
+                expected.get(name).unwrap_or(&Value::Null),
+                actual.get(name).unwrap_or(&Value::Null)
+            )
+        })
+        .collect();
+    assert!(
+        changed.is_empty(),
+        "{kind} corpus results changed (rerun with UPDATE_CORPUS=1 if intended):\n{}",
+        changed.join("\n")
+    );
+}
@@ -0,0 +1,709 @@
+// src/cron.rs
+//! Recurring maintenance jobs run on cron schedules
+//!
+//! The extension lives as long as the MCP server, so it can keep derived data
+//! fresh in the background: a job pairs a cron expression with an action, either
+//! a Python callable (re-scanning documentation, rebuilding an index) or one of
+//! the core's own ("gc_artifacts", "refresh_git_analysis"). One thread sleeps
+//! until the next job is due and starts each run on a thread of its own, so a
+//! slow job delays no other; a job still running when it comes due again skips
+//! that run. Schedules use the local time zone, with the five standard fields
+//! (minute, hour, day of month, month, day of week), names for months and days,
+//! and the `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` shorthands.
+//! Runs are published on the event bus as "cron.started" and "cron.finished".
+
+use crate::artifacts::{self, GcPolicy};
+use crate::cancel::CancellationToken;
+use crate::error::{from_json, to_json, CdeError};
+use crate::events;
+use crate::git_analyzer;
+use crate::progress::ProgressSink;
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone,
+    Timelike,
+};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Condvar, LazyLock, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Minutes, hours or days examined looking for the next run before a schedule
+/// is deemed never to fire (such as `0 0 30 2 *`)
+const MAX_SEARCH_STEPS: usize = 100_000;
+
+/// Longest the scheduler thread sleeps, so that clock changes are caught up with
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether the day-of-month and day-of-week fields start with `*`: when
+    /// both are restricted, a day matching either one fires
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// Bit set of the values of one field, `min..=max`; names stand for `min`,
+/// `min + 1`, ...
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let lower = text.to_ascii_lowercase();
+        if let Some(index) = names.iter().position(|name| *name == lower) {
+            return Ok(min + index as u32);
+        }
+        text.parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("'{}' is not a value from {} to {}", text, min, max))
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("Invalid step '{}'", step))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("Invalid range '{}'", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, CdeError> {
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let invalid = |reason: String| {
+            CdeError::invalid_input(format!(
+                "Invalid cron expression '{}': {}",
+                expression, reason
+            ))
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let &[minute, hour, day_of_month, month, day_of_week] = fields.as_slice() else {
+            return Err(invalid(format!(
+                "expected 5 fields (minute hour day-of-month month day-of-week), found {}",
+                fields.len()
+            )));
+        };
+        let mut days_of_week = parse_field(day_of_week, 0, 7, &WEEKDAYS).map_err(invalid)?;
+        // Both 0 and 7 are Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[]).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(invalid)?,
+            days_of_month: parse_field(day_of_month, 1, 31, &[]).map_err(invalid)?,
+            months: parse_field(month, 1, 12, &MONTHS).map_err(invalid)?,
+            days_of_week,
+            any_day_of_month: day_of_month.starts_with('*'),
+            any_day_of_week: day_of_week.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.any_day_of_month || self.any_day_of_week {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+
+    /// First time strictly after `after` the schedule fires, None if it never
+    /// does; local times skipped by a DST change are skipped
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)?;
+        let mut time: NaiveDateTime = start + ChronoDuration::minutes(1);
+        for _ in 0..MAX_SEARCH_STEPS {
+            let date = time.date();
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = date.and_hms_opt(time.hour(), 0, 0)? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += ChronoDuration::minutes(1);
+            } else {
+                match after.timezone().from_local_datetime(&time).earliest() {
+                    Some(next) if next > *after => return Some(next),
+                    _ => time += ChronoDuration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// What a job does when it runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CronAction {
+    /// Garbage-collects the artifact store
+    GcArtifacts {
+        #[serde(default)]
+        max_age_secs: Option<u64>,
+        #[serde(default)]
+        max_total_bytes: Option<u64>,
+    },
+    /// Recomputes the cached analysis of a repository once HEAD has moved
+    RefreshGitAnalysis {
+        repo_path: String,
+        #[serde(default = "default_days")]
+        days: i64,
+    },
+}
+
+fn default_days() -> i64 {
+    30
+}
+
+impl CronAction {
+    fn describe(&self) -> String {
+        match self {
+            Self::GcArtifacts { .. } => "gc_artifacts".to_string(),
+            Self::RefreshGitAnalysis { repo_path, .. } => {
+                format!("refresh_git_analysis {}", repo_path)
+            }
+        }
+    }
+
+    fn run(&self) -> Result<Value, CdeError> {
+        match self {
+            Self::GcArtifacts {
+                max_age_secs,
+                max_total_bytes,
+            } => {
+                let policy = GcPolicy {
+                    max_age_secs: *max_age_secs,
+                    max_total_bytes: *max_total_bytes,
+                    keep: Vec::new(),
+                };
+                Ok(serde_json::json!(artifacts::shared().gc(&policy)?))
+            }
+            Self::RefreshGitAnalysis { repo_path, days } => {
+                let analysis = git_analyzer::analyze_git_repository_cached(
+                    repo_path,
+                    *days,
+                    &CancellationToken::default(),
+                    &ProgressSink::default(),
+                )?;
+                Ok(serde_json::json!({
+                    "repo_path": repo_path,
+                    "total_commits": analysis.total_commits,
+                }))
+            }
+        }
+    }
+}
+
+enum JobWork {
+    Core(CronAction),
+    /// Called with the job's name; a str it returns is kept as the result
+    Python(Py<PyAny>),
+}
+
+impl JobWork {
+    fn run(&self, name: &str) -> Result<Value, String> {
+        match self {
+            Self::Core(action) => action.run().map_err(|e| e.to_string()),
+            Self::Python(callback) => Python::attach(|py| {
+                let returned = callback.call1(py, (name,)).map_err(|e| e.to_string())?;
+                Ok(returned
+                    .extract::<String>(py)
+                    .map_or(Value::Null, Value::String))
+            }),
+        }
+    }
+}
+
+/// Status of a scheduled job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CronJobStatus {
+    pub name: String,
+    pub cron: String,
+    /// Core action, or "python" for a callable
+    pub action: String,
+    pub paused: bool,
+    pub running: bool,
+    /// RFC 3339; None while paused or if the schedule never fires
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    pub last_duration_ms: Option<u128>,
+    /// "succeeded" or "failed"
+    pub last_status: Option<String>,
+    pub last_error: Option<String>,
+    pub last_result: Option<Value>,
+    pub runs: u64,
+    pub failures: u64,
+    /// Due runs skipped because the previous one was still running
+    pub skipped: u64,
+}
+
+struct CronJob {
+    schedule: CronSchedule,
+    work: std::sync::Arc<JobWork>,
+    next_run: Option<DateTime<Local>>,
+    /// Changes whenever the job is replaced, so a run of the old job does not
+    /// report into the new one
+    generation: u64,
+    status: CronJobStatus,
+}
+
+#[derive(Default)]
+struct Jobs {
+    jobs: BTreeMap<String, CronJob>,
+    next_generation: u64,
+}
+
+static JOBS: LazyLock<Mutex<Jobs>> = LazyLock::new(|| Mutex::new(Jobs::default()));
+
+/// Signalled when jobs change, so the scheduler thread recomputes its sleep
+static CHANGED: Condvar = Condvar::new();
+
+fn schedule(
+    name: &str,
+    cron: &str,
+    work: JobWork,
+    action: String,
+    paused: bool,
+) -> Result<CronJobStatus, CdeError> {
+    let schedule = CronSchedule::parse(cron)?;
+    let next_run = (!paused)
+        .then(|| schedule.next_after(&Local::now()))
+        .flatten();
+    let mut jobs = JOBS.lock().unwrap();
+    jobs.next_generation += 1;
+    let job = CronJob {
+        schedule,
+        work: std::sync::Arc::new(work),
+        next_run,
+        generation: jobs.next_generation,
+        status: CronJobStatus {
+            name: name.to_string(),
+            cron: cron.to_string(),
+            action,
+            paused,
+            running: false,
+            next_run: next_run.map(|next| next.to_rfc3339()),
+            last_run: None,
+            last_duration_ms: None,
+            last_status: None,
+            last_error: None,
+            last_result: None,
+            runs: 0,
+            failures: 0,
+            skipped: 0,
+        },
+    };
+    let status = job.status.clone();
+    jobs.jobs.insert(name.to_string(), job);
+    drop(jobs);
+    start_scheduler();
+    CHANGED.notify_all();
+    Ok(status)
+}
+
+/// Starts a run of `name` on its own thread; the caller holds the jobs lock
+fn start_run(jobs: &mut Jobs, name: &str) {
+    let Some(job) = jobs.jobs.get_mut(name) else {
+        return;
+    };
+    if job.status.running {
+        job.status.skipped += 1;
+        return;
+    }
+    job.status.running = true;
+    let (work, generation, name) = (job.work.clone(), job.generation, name.to_string());
+    events::publish("cron", "cron.started", serde_json::json!({ "name": name }));
+    thread::spawn(move || {
+        let started_at = Local::now().to_rfc3339();
+        let started = Instant::now();
+        let outcome = work.run(&name);
+        let duration_ms = started.elapsed().as_millis();
+        if let Err(error) = &outcome {
+            tracing::warn!(job = %name, error = %error, "Scheduled job failed");
+        }
+        events::publish(
+            "cron",
+            "cron.finished",
+            serde_json::json!({
+                "name": name,
+                "succeeded": outcome.is_ok(),
+                "duration_ms": duration_ms,
+            }),
+        );
+
+        let mut jobs = JOBS.lock().unwrap();
+        let Some(job) = jobs
+            .jobs
+            .get_mut(&name)
+            .filter(|job| job.generation == generation)
+        else {
+            return;
+        };
+        let status = &mut job.status;
+        status.running = false;
+        status.runs += 1;
+        status.last_run = Some(started_at);
+        status.last_duration_ms = Some(duration_ms);
+        match outcome {
+            Ok(result) => {
+                status.last_status = Some("succeeded".to_string());
+                status.last_error = None;
+                status.last_result = Some(result);
+            }
+            Err(error) => {
+                status.failures += 1;
+                status.last_status = Some("failed".to_string());
+                status.last_error = Some(error);
+                status.last_result = None;
+            }
+        }
+    });
+}
+
+/// Starts the thread running due jobs (once)
+fn start_scheduler() {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    STARTED.get_or_init(|| {
+        thread::spawn(|| {
+            let mut jobs = JOBS.lock().unwrap();
+            loop {
+                let now = Local::now();
+                let due: Vec<String> = jobs
+                    .jobs
+                    .iter()
+                    .filter(|(_, job)| job.next_run.is_some_and(|next| next <= now))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                for name in due {
+                    start_run(&mut jobs, &name);
+                    if let Some(job) = jobs.jobs.get_mut(&name) {
+                        job.next_run = job.schedule.next_after(&now);
+                        job.status.next_run = job.next_run.map(|next| next.to_rfc3339());
+                    }
+                }
+
+                let sleep = jobs
+                    .jobs
+                    .values()
+                    .filter_map(|job| job.next_run)
+                    .min()
+                    .and_then(|next| (next - Local::now()).to_std().ok())
+                    .unwrap_or(MAX_SLEEP)
+                    .min(MAX_SLEEP);
+                jobs = CHANGED.wait_timeout(jobs, sleep).unwrap().0;
+            }
+        });
+    });
+}
+
+fn with_job<T>(name: &str, change: impl FnOnce(&mut CronJob) -> T) -> Option<T> {
+    let mut jobs = JOBS.lock().unwrap();
+    let result = jobs.jobs.get_mut(name).map(change);
+    drop(jobs);
+    CHANGED.notify_all();
+    result
+}
+
+/// Statuses of the scheduled jobs, by name
+pub fn list() -> Vec<CronJobStatus> {
+    JOBS.lock()
+        .unwrap()
+        .jobs
+        .values()
+        .map(|job| job.status.clone())
+        .collect()
+}
+
+/// Stops scheduling `name` until resumed; a run in progress completes
+pub fn pause(name: &str) -> bool {
+    with_job(name, |job| {
+        job.status.paused = true;
+        job.next_run = None;
+        job.status.next_run = None;
+    })
+    .is_some()
+}
+
+/// Schedules `name` again from now
+pub fn resume(name: &str) -> bool {
+    with_job(name, |job| {
+        job.status.paused = false;
+        job.next_run = job.schedule.next_after(&Local::now());
+        job.status.next_run = job.next_run.map(|next| next.to_rfc3339());
+    })
+    .is_some()
+}
+
+/// Removes `name`; a run in progress completes
+pub fn unschedule(name: &str) -> bool {
+    let removed = JOBS.lock().unwrap().jobs.remove(name).is_some();
+    CHANGED.notify_all();
+    removed
+}
+
+/// Starts a run of `name` now, outside its schedule (paused jobs included)
+pub fn run_now(name: &str) -> bool {
+    let mut jobs = JOBS.lock().unwrap();
+    if !jobs.jobs.contains_key(name) {
+        return false;
+    }
+    start_run(&mut jobs, name);
+    true
+}
+
+/// Schedule a recurring job, replacing any job of the same name
+///
+/// `cron` has the five standard fields (minute, hour, day of month, month, day
+/// of week; `*`, lists, ranges, `/` steps, `jan`-`dec` and `sun`-`sat`) or one
+/// of `@hourly`, `@daily`, `@weekly`, `@monthly`, `@yearly`, in local time.
+/// The job either calls `callback` with its name, from a background thread (a
+/// str it returns is kept as `last_result`), or runs the core action in
+/// `action_json`: `{"action": "gc_artifacts", "max_age_secs", "max_total_bytes"}`
+/// or `{"action": "refresh_git_analysis", "repo_path", "days"}`. A job created
+/// `paused` only runs once resumed. Returns the job status as JSON (see
+/// `list_scheduled_jobs_py`).
+#[pyfunction]
+#[pyo3(signature = (name, cron, callback=None, action_json=None, paused=false))]
+fn schedule_job_py(
+    name: &str,
+    cron: &str,
+    callback: Option<Py<PyAny>>,
+    action_json: Option<&str>,
+    paused: bool,
+) -> PyResult<String> {
+    let (work, action) = match (callback, action_json) {
+        (Some(callback), None) => (JobWork::Python(callback), "python".to_string()),
+        (None, Some(json)) => {
+            let action: CronAction = from_json("cron action", json)?;
+            let description = action.describe();
+            (JobWork::Core(action), description)
+        }
+        _ => {
+            return Err(CdeError::invalid_input(
+                "Expected exactly one of 'callback' and 'action_json'",
+            )
+            .into())
+        }
+    };
+    Ok(to_json(&schedule(name, cron, work, action, paused)?)?)
+}
+
+/// Status of the scheduled jobs
+///
+/// Returns a JSON list, by name, of `name`, `cron`, `action`, `paused`,
+/// `running`, `next_run`, `last_run`, `last_duration_ms`, `last_status`
+/// ("succeeded"/"failed"), `last_error`, `last_result`, `runs`, `failures` and
+/// `skipped` (runs that came due while the previous one was still running).
+#[pyfunction]
+fn list_scheduled_jobs_py() -> PyResult<String> {
+    Ok(to_json(&list())?)
+}
+
+/// Pause a scheduled job; returns False for an unknown job
+#[pyfunction]
+fn pause_job_py(name: &str) -> bool {
+    pause(name)
+}
+
+/// Resume a paused job from now; returns False for an unknown job
+#[pyfunction]
+fn resume_job_py(name: &str) -> bool {
+    resume(name)
+}
+
+/// Remove a scheduled job; returns False for an unknown job
+#[pyfunction]
+fn unschedule_job_py(name: &str) -> bool {
+    unschedule(name)
+}
+
+/// Start a run of a job now, outside its schedule; returns False for an
+/// unknown job
+#[pyfunction]
+fn run_job_now_py(name: &str) -> bool {
+    run_now(name)
+}
+
+/// Adds the cron scheduler functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(schedule_job_py, m)?)?;
+    m.add_function(wrap_pyfunction!(list_scheduled_jobs_py, m)?)?;
+    m.add_function(wrap_pyfunction!(pause_job_py, m)?)?;
+    m.add_function(wrap_pyfunction!(resume_job_py, m)?)?;
+    m.add_function(wrap_pyfunction!(unschedule_job_py, m)?)?;
+    m.add_function(wrap_pyfunction!(run_job_now_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn next(expression: &str, after: &str) -> Option<String> {
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(&at(after))
+            .map(|next| next.to_rfc3339())
+    }
+
+    #[test]
+    fn test_next_run_of_cron_expressions() {
+        let after = "2026-03-14T10:17:30+00:00";
+        assert_eq!(
+            next("*/15 * * * *", after).unwrap(),
+            "2026-03-14T10:30:00+00:00"
+        );
+        assert_eq!(next("@hourly", after).unwrap(), "2026-03-14T11:00:00+00:00");
+        assert_eq!(
+            next("0 3 * * *", after).unwrap(),
+            "2026-03-15T03:00:00+00:00"
+        );
+        // 2026-03-14 is a Saturday
+        assert_eq!(
+            next("30 9 * * mon-fri", after).unwrap(),
+            "2026-03-16T09:30:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 1 jan *", after).unwrap(),
+            "2027-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 * * 7", after).unwrap(),
+            "2026-03-15T00:00:00+00:00"
+        );
+        // Restricted day of month and day of week: either one fires
+        assert_eq!(
+            next("0 12 20 * 1", after).unwrap(),
+            "2026-03-16T12:00:00+00:00"
+        );
+        assert_eq!(next("0 0 30 2 *", after), None);
+        assert_eq!(
+            next("17 10 * * *", "2026-03-14T10:17:00+00:00").unwrap(),
+            "2026-03-15T10:17:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_cron_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@often",
+        ] {
+            assert!(CronSchedule::parse(expression).is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_jobs_pause_resume_and_run_now() {
+        let action = CronAction::GcArtifacts {
+            max_age_secs: None,
+            max_total_bytes: None,
+        };
+        let status = schedule(
+            "test-gc",
+            "0 0 1 1 *",
+            JobWork::Core(action.clone()),
+            action.describe(),
+            true,
+        )
+        .unwrap();
+        assert!(status.paused && status.next_run.is_none());
+
+        assert!(resume("test-gc"));
+        let job = |name: &str| list().into_iter().find(|job| job.name == name).unwrap();
+        assert!(job("test-gc").next_run.is_some());
+        assert!(pause("test-gc"));
+        assert!(job("test-gc").next_run.is_none());
+        assert!(!pause("test-missing"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let refresh = CronAction::RefreshGitAnalysis {
+            repo_path: dir.path().to_string_lossy().into_owned(),
+            days: 30,
+        };
+        schedule(
+            "test-refresh",
+            "@yearly",
+            JobWork::Core(refresh),
+            "refresh".to_string(),
+            false,
+        )
+        .unwrap();
+        assert!(run_now("test-refresh"));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while job("test-refresh").runs == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        // Not a repository: the run fails and the job stays scheduled
+        let finished = job("test-refresh");
+        assert_eq!(finished.last_status.as_deref(), Some("failed"));
+        assert!(finished.last_error.is_some() && !finished.running);
+        assert!(finished.next_run.is_some());
+        assert!(unschedule("test-refresh"));
+        assert!(unschedule("test-gc"));
+        assert!(!unschedule("test-gc"));
+    }
+}
@@ -0,0 +1,349 @@
+// src/dashboard.rs
+//! Local HTTP dashboard of the orchestrator's health (feature `dashboard`)
+//!
+//! `start_dashboard_py` serves, on 127.0.0.1 only, the latest report of each
+//! kind the orchestrator published with `publish_report_py` (as JSON and as the
+//! HTML page `render_report_py` lays out), the operation metrics (JSON and
+//! Prometheus text), the scheduled jobs, and the event bus as a Server-Sent
+//! Events stream. The server runs on the shared Tokio runtime until
+//! `stop_dashboard_py`; nothing it serves can change the orchestrator's state.
+
+use crate::error::{to_json, CdeError};
+use crate::events::{self, EventFilter, Subscription};
+use crate::report::{self, ReportFormat};
+use crate::{cron, metrics};
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures_util::stream::{self, Stream};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// Port the dashboard listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 8765;
+
+/// How long an SSE stream waits for an event before checking its client is
+/// still there
+const SSE_POLL: Duration = Duration::from_secs(1);
+
+/// Rows of tables and lists on a rendered report page
+const REPORT_MAX_ROWS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedReport {
+    pub name: String,
+    /// RFC 3339
+    pub published_at: String,
+    pub report: Value,
+}
+
+static REPORTS: LazyLock<Mutex<BTreeMap<String, PublishedReport>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+struct Running {
+    address: SocketAddr,
+    started: Instant,
+    shutdown: oneshot::Sender<()>,
+}
+
+static SERVER: Mutex<Option<Running>> = Mutex::new(None);
+
+/// Keeps `report` as the latest of `name`, for the dashboard to serve
+pub fn publish_report(name: &str, report: Value) {
+    let published = PublishedReport {
+        name: name.to_string(),
+        published_at: chrono::Local::now().to_rfc3339(),
+        report,
+    };
+    events::publish(
+        "dashboard",
+        "report.published",
+        serde_json::json!({ "name": name, "published_at": published.published_at }),
+    );
+    REPORTS.lock().unwrap().insert(name.to_string(), published);
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+    version: &'static str,
+    uptime_secs: u64,
+    reports: Vec<String>,
+    jobs: Vec<cron::CronJobStatus>,
+}
+
+async fn health() -> Json<Health> {
+    let uptime_secs = SERVER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |running| running.started.elapsed().as_secs());
+    Json(Health {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+        uptime_secs,
+        reports: REPORTS.lock().unwrap().keys().cloned().collect(),
+        jobs: cron::list(),
+    })
+}
+
+async fn metrics_json() -> Json<metrics::Metrics> {
+    Json(metrics::snapshot())
+}
+
+async fn metrics_prometheus() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::prometheus(&metrics::snapshot()),
+    )
+}
+
+async fn list_reports() -> Json<Vec<PublishedReport>> {
+    Json(REPORTS.lock().unwrap().values().cloned().collect())
+}
+
+fn find_report(name: &str) -> Result<PublishedReport, Response> {
+    REPORTS.lock().unwrap().get(name).cloned().ok_or_else(|| {
+        (StatusCode::NOT_FOUND, format!("No report named '{}'", name)).into_response()
+    })
+}
+
+async fn report_json(Path(name): Path<String>) -> Result<Json<PublishedReport>, Response> {
+    find_report(&name).map(Json)
+}
+
+async fn report_html(Path(name): Path<String>) -> Result<Html<String>, Response> {
+    let published = find_report(&name)?;
+    let rendered = tokio::task::spawn_blocking(move || {
+        let json = to_json(&published.report)?;
+        report::render_report(&json, ReportFormat::Html, None, REPORT_MAX_ROWS)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?;
+    // Reports of a kind the renderer does not know only have their JSON route
+    rendered.map(Html).map_err(|e| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Cannot render report '{}': {}", name, e),
+        )
+            .into_response()
+    })
+}
+
+async fn index() -> Html<String> {
+    let mut page = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>CDE orchestrator</title></head>\n<body>\n<h1>CDE orchestrator</h1>\n<h2>Reports</h2>\n<ul>\n",
+    );
+    for report in REPORTS.lock().unwrap().values() {
+        let name = report::escape_html(&report.name);
+        page.push_str(&format!(
+            "<li><a href=\"/reports/{0}\">{0}</a> ({1}, <a href=\"/api/reports/{0}\">json</a>)</li>\n",
+            name,
+            report::escape_html(&report.published_at)
+        ));
+    }
+    page.push_str(
+        "</ul>\n<h2>API</h2>\n<ul>\n<li><a href=\"/api/health\">/api/health</a></li>\n<li><a href=\"/api/metrics\">/api/metrics</a> (<a href=\"/metrics\">Prometheus</a>)</li>\n<li><a href=\"/api/reports\">/api/reports</a></li>\n<li><a href=\"/api/events\">/api/events</a> (Server-Sent Events)</li>\n</ul>\n</body>\n</html>\n",
+    );
+    Html(page)
+}
+
+/// Comma-separated `sources` and `kinds`, as in `subscribe_events_py`'s filter
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct EventsQuery {
+    sources: Option<String>,
+    kinds: Option<String>,
+}
+
+fn split_list(list: Option<String>) -> Vec<String> {
+    list.map(|list| {
+        list.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+async fn event_stream(
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let filter = EventFilter {
+        sources: split_list(query.sources),
+        kinds: split_list(query.kinds),
+    };
+    let subscription = Arc::new(Subscription::new(filter, events::DEFAULT_MAX_QUEUED));
+    // Dropping the stream when the client goes away drops the subscription
+    let stream = stream::unfold(subscription, |subscription| async move {
+        loop {
+            let waiting = Arc::clone(&subscription);
+            let event = tokio::task::spawn_blocking(move || waiting.next(SSE_POLL))
+                .await
+                .ok()
+                .flatten();
+            if let Some(event) = event {
+                let sse = SseEvent::default()
+                    .id(event.seq.to_string())
+                    .event(event.kind.clone())
+                    .data(to_json(&event).unwrap_or_default());
+                return Some((Ok(sse), subscription));
+            }
+            if subscription.is_closed() {
+                return None;
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn router() -> Router {
+    let router = Router::new()
+        .route("/", get(index))
+        .route("/api/health", get(health))
+        .route("/api/metrics", get(metrics_json))
+        .route("/metrics", get(metrics_prometheus))
+        .route("/api/reports", get(list_reports))
+        .route("/api/reports/{name}", get(report_json))
+        .route("/reports/{name}", get(report_html))
+        .route("/api/events", get(event_stream));
+    #[cfg(feature = "log_stream")]
+    let router = router.merge(crate::log_stream::routes());
+    router
+}
+
+/// Starts serving on 127.0.0.1:`port` (0 picks a free port); returns the
+/// address, or the one already served
+pub fn start(port: u16) -> Result<SocketAddr, CdeError> {
+    let mut server = SERVER.lock().unwrap();
+    if let Some(running) = server.as_ref() {
+        return Ok(running.address);
+    }
+    let runtime = pyo3_async_runtimes::tokio::get_runtime();
+    let listener = runtime
+        .block_on(tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port)))
+        .map_err(|e| CdeError::io(format!("Failed to listen on port {}", port)).caused_by(&e))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| CdeError::io("Failed to read the dashboard address").caused_by(&e))?;
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    runtime.spawn(async move {
+        let served = axum::serve(listener, router())
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .await;
+        if let Err(e) = served {
+            tracing::warn!(error = %e, "Dashboard server stopped");
+        }
+    });
+    *server = Some(Running {
+        address,
+        started: Instant::now(),
+        shutdown,
+    });
+    Ok(address)
+}
+
+/// Stops the server; false when it was not running
+pub fn stop() -> bool {
+    match SERVER.lock().unwrap().take() {
+        Some(running) => {
+            let _ = running.shutdown.send(());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Start the local dashboard and return its URL
+///
+/// Serves on 127.0.0.1:`port` (0 picks a free port): `/` (index of the
+/// reports), `/api/health` (version, uptime, report names, scheduled jobs),
+/// `/api/metrics` and `/metrics` (Prometheus text), `/api/reports` and
+/// `/api/reports/{name}` (JSON), `/reports/{name}` (HTML page) and
+/// `/api/events`, a Server-Sent Events stream of the event bus taking
+/// comma-separated `sources` and `kinds` query parameters. Built with the
+/// `log_stream` feature it also serves `/ws/agents/{pid}` and `/ws/agents`,
+/// WebSocket streams of an agent's output and of every process event. Calling
+/// it while the dashboard runs returns its URL.
+#[pyfunction]
+#[pyo3(signature = (port=DEFAULT_PORT))]
+fn start_dashboard_py(py: Python<'_>, port: u16) -> PyResult<String> {
+    let address = py.detach(|| start(port))?;
+    Ok(format!("http://{}", address))
+}
+
+/// Stop the local dashboard; returns False when it was not running
+#[pyfunction]
+fn stop_dashboard_py() -> bool {
+    stop()
+}
+
+/// Keep a report for the dashboard to serve, replacing the previous `name` one
+///
+/// `report_json` is any JSON report; those `render_report_py` knows
+/// (documentation quality, workflow validation, Git analysis, governance
+/// audit) also get an HTML page.
+#[pyfunction]
+fn publish_report_py(name: &str, report_json: &str) -> PyResult<()> {
+    let report: Value = crate::error::from_json("report", report_json)?;
+    publish_report(name, report);
+    Ok(())
+}
+
+/// Adds the dashboard functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(start_dashboard_py, m)?)?;
+    m.add_function(wrap_pyfunction!(stop_dashboard_py, m)?)?;
+    m.add_function(wrap_pyfunction!(publish_report_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn get_body(address: SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_dashboard_serves_reports_and_health() {
+        publish_report("test-report", serde_json::json!({"score": 1}));
+        let address = start(0).unwrap();
+        assert_eq!(start(0).unwrap(), address);
+
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let health = runtime.block_on(get_body(address, "/api/health"));
+        assert!(health.starts_with("HTTP/1.1 200"));
+        assert!(health.contains("\"test-report\""));
+        let report = runtime.block_on(get_body(address, "/api/reports/test-report"));
+        assert!(report.contains("\"score\":1"));
+        let missing = runtime.block_on(get_body(address, "/api/reports/missing"));
+        assert!(missing.starts_with("HTTP/1.1 404"));
+
+        assert!(stop());
+        assert!(!stop());
+    }
+}
@@ -0,0 +1,306 @@
+// src/digest.rs
+//! File checksums and Merkle digests of file sets
+//!
+//! Files are hashed in parallel with blake3 or SHA-256; large files are hashed
+//! with blake3's multithreaded, memory-mapped mode as well. The Merkle root of a
+//! set of files covers both their paths and their contents, so comparing the
+//! roots taken before and after an agent run tells whether anything in the
+//! workspace changed, and comparing the per-file hashes tells what.
+//!
+//! Leaves are `H(0x00 || path || 0x00 || file hash)` in path order, inner nodes
+//! `H(0x01 || left || right)`; an odd node is carried up unchanged. The root of an
+//! empty set is the hash of nothing.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// Files from this size on are memory-mapped and hashed by several threads
+const PARALLEL_HASH_MIN_BYTES: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Blake3,
+    Sha256,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Result<Self, CdeError> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "blake3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(CdeError::invalid_input(format!(
+                "Unknown hash algorithm '{}', expected blake3 or sha256",
+                name
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    fn hash_parts(self, parts: &[&[u8]]) -> Vec<u8> {
+        match self {
+            Self::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().as_bytes().to_vec()
+            }
+            Self::Sha256 => {
+                let mut hasher = Sha256::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                hasher.finalize().to_vec()
+            }
+        }
+    }
+
+    fn hash_file(self, path: &Path, size: u64) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                if size >= PARALLEL_HASH_MIN_BYTES {
+                    hasher.update_mmap_rayon(path)?;
+                } else {
+                    hasher.update_reader(fs::File::open(path)?)?;
+                }
+                Ok(hasher.finalize().as_bytes().to_vec())
+            }
+            Self::Sha256 => {
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+                Ok(hasher.finalize().to_vec())
+            }
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDigest {
+    /// As given, or the given directory joined with the path below it, with `/`
+    /// separators
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestError {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestReport {
+    pub algorithm: String,
+    /// Sorted by path
+    pub files: Vec<FileDigest>,
+    /// Merkle root of `files`
+    pub root_digest: String,
+    pub total_bytes: u64,
+    /// Paths that could not be read; they are left out of the digest
+    pub errors: Vec<DigestError>,
+}
+
+/// Merkle root of `(path, hex hash)` leaves sorted by path, see the module docs
+pub fn merkle_root(files: &[FileDigest], algorithm: Algorithm) -> String {
+    let mut level: Vec<Vec<u8>> = files
+        .iter()
+        .map(|file| algorithm.hash_parts(&[&[0], file.path.as_bytes(), &[0], file.hash.as_bytes()]))
+        .collect();
+    if level.is_empty() {
+        return hex(&algorithm.hash_parts(&[]));
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => algorithm.hash_parts(&[&[1], left, right]),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    hex(&level[0])
+}
+
+/// Files named by `paths`; directories are walked, honoring `.gitignore` and
+/// leaving hidden files out
+fn expand(paths: &[String], errors: &mut Vec<DigestError>) -> Vec<(String, u64)> {
+    let mut files = Vec::new();
+    for given in paths {
+        let path = Path::new(given);
+        let guard = match path_policy::walk_guard(path) {
+            Ok(guard) => guard,
+            Err(e) => {
+                errors.push(DigestError {
+                    path: given.clone(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => {
+                files.push((given.replace('\\', "/"), metadata.len()))
+            }
+            Ok(metadata) if metadata.is_dir() => {
+                let walker = WalkBuilder::new(path)
+                    .max_depth(Some(guard.max_depth()))
+                    .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+                    .build();
+                for entry in walker.flatten() {
+                    if !entry.file_type().is_some_and(|t| t.is_file()) {
+                        continue;
+                    }
+                    if let Ok(metadata) = entry.metadata() {
+                        let name = entry.path().to_string_lossy().replace('\\', "/");
+                        files.push((name, metadata.len()));
+                    }
+                }
+            }
+            Ok(_) => errors.push(DigestError {
+                path: given.clone(),
+                error: "Not a file or directory".to_string(),
+            }),
+            Err(e) => errors.push(DigestError {
+                path: given.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Hashes the files and directory trees in `paths` with `algorithm`
+pub fn hash_files(paths: &[String], algorithm: &str) -> Result<DigestReport, CdeError> {
+    let algorithm = Algorithm::parse(algorithm)?;
+    metrics::timed("hash_files", || {
+        let mut errors = Vec::new();
+        let files = expand(paths, &mut errors);
+        let hashed: Vec<Result<FileDigest, DigestError>> = files
+            .into_par_iter()
+            .map(
+                |(path, size)| match algorithm.hash_file(Path::new(&path), size) {
+                    Ok(hash) => Ok(FileDigest {
+                        hash: hex(&hash),
+                        path,
+                        size,
+                    }),
+                    Err(e) => Err(DigestError {
+                        path,
+                        error: e.to_string(),
+                    }),
+                },
+            )
+            .collect();
+        let mut files = Vec::new();
+        for result in hashed {
+            match result {
+                Ok(file) => files.push(file),
+                Err(error) => errors.push(error),
+            }
+        }
+        let total_bytes = files.iter().map(|file| file.size).sum();
+        metrics::add_bytes("hash_files", total_bytes);
+        Ok(DigestReport {
+            algorithm: algorithm.name().to_string(),
+            root_digest: merkle_root(&files, algorithm),
+            files,
+            total_bytes,
+            errors,
+        })
+    })
+}
+
+/// Checksums of files, and a Merkle digest of all of them, as JSON
+///
+/// `paths` may name files and directories; directories are walked honoring
+/// `.gitignore`, without hidden files. `algorithm` is "blake3" or "sha256".
+/// Returns `files` (`path`, `hash`, `size`, sorted by path), `root_digest` (a
+/// Merkle root over paths and hashes: equal roots mean the same files with the
+/// same contents), `total_bytes` and the `errors` of paths that could not be
+/// read.
+#[pyfunction]
+#[pyo3(signature = (paths, algorithm="blake3"))]
+fn hash_files_py(py: Python<'_>, paths: Vec<String>, algorithm: &str) -> PyResult<String> {
+    let report = py.detach(|| hash_files(&paths, algorithm))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the digest functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(hash_files_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_and_merkle_root_track_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("abc.txt"), "abc").unwrap();
+        fs::write(root.join("src").join("lib.rs"), "fn x() {}").unwrap();
+        let large = vec![7u8; PARALLEL_HASH_MIN_BYTES as usize + 1];
+        fs::write(root.join("large.bin"), &large).unwrap();
+        let tree = vec![root.to_string_lossy().into_owned()];
+
+        let sha = hash_files(
+            &[root.join("abc.txt").to_string_lossy().into_owned()],
+            "SHA-256",
+        )
+        .unwrap();
+        assert_eq!(
+            sha.files[0].hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let before = hash_files(&tree, "blake3").unwrap();
+        assert_eq!(before.files.len(), 3);
+        assert!(before.files[1].path.ends_with("large.bin"));
+        assert_eq!(before.files[1].hash, blake3::hash(&large).to_hex().as_str());
+        assert_eq!(before.total_bytes, large.len() as u64 + 3 + 9);
+        // Hashing the same tree again, or naming its files in another order, gives
+        // the same root
+        let files: Vec<String> = before.files.iter().rev().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            hash_files(&files, "blake3").unwrap().root_digest,
+            before.root_digest
+        );
+
+        fs::write(root.join("src").join("lib.rs"), "fn y() {}").unwrap();
+        let after = hash_files(&tree, "blake3").unwrap();
+        assert_ne!(after.root_digest, before.root_digest);
+        assert_ne!(after.files[2].hash, before.files[2].hash);
+        assert_eq!(after.files[0], before.files[0]);
+
+        let missing = hash_files(&["/no/such/file".to_string()], "blake3").unwrap();
+        assert_eq!(missing.errors.len(), 1);
+        assert_eq!(missing.root_digest, blake3::hash(b"").to_hex().as_str());
+        assert!(hash_files(&tree, "md5").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+// src/doc_index.rs
+//! Index files of a documentation tree, generated from frontmatter
+//!
+//! Every directory holding Markdown documents (or holding such directories) gets
+//! an index (`INDEX.md` by default) listing its documents by their frontmatter
+//! `title`, `description` and `status`, grouped by `type` and sorted by title,
+//! followed by links to the indexes of its subdirectories. A document without a
+//! title is listed by its first heading, else its file name. The index is
+//! rendered with the built-in template or a Jinja one, then formatted like
+//! `format_markdown`, so the same documents always give the same bytes. In
+//! check mode nothing is written and stale or missing indexes are reported.
+
+use crate::chunking::heading;
+use crate::error::{to_json, CdeError};
+use crate::markdown_format::{format_markdown, MarkdownStyle};
+use crate::metadata::{extract_metadata, Metadata};
+use crate::metrics;
+use crate::patch;
+use crate::path_policy;
+use crate::templating;
+use crate::text_file;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_TEMPLATE: &str = r#"<!-- Generated from the frontmatter of the documents; do not edit by hand -->
+# {{ title }}
+{% for group in groups %}
+## {{ group.type | title }}
+
+| Document | Description | Status |
+| --- | --- | --- |
+{% for doc in group.documents -%}
+| [{{ doc.title | replace("|", "\\|") }}]({{ doc.path }}) | {{ doc.description | replace("|", "\\|") }} | {{ doc.status }} |
+{% endfor -%}
+{% endfor %}
+{% if subdirectories %}
+## Subdirectories
+
+{% for sub in subdirectories -%}
+- [{{ sub.name }}]({{ sub.path }})
+{% endfor -%}
+{% endif %}
+"#;
+
+/// Group of the documents without a `type`
+const UNTYPED: &str = "other";
+
+/// Index files never listed as documents, whatever `index_name` is generated
+const INDEX_NAMES: [&str; 2] = ["INDEX.md", "SUMMARY.md"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDocument {
+    /// Relative to the directory of the index
+    pub path: String,
+    pub title: String,
+    pub description: String,
+    pub status: String,
+    #[serde(rename = "type")]
+    pub doc_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexFile {
+    /// Relative to the root
+    pub path: String,
+    /// "created", "updated" or "unchanged"; in check mode "missing", "stale" or
+    /// "unchanged"
+    pub status: String,
+    pub documents: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocIndexResult {
+    pub root: String,
+    pub check: bool,
+    /// Whether every index already matched its documents
+    pub up_to_date: bool,
+    pub indexes: Vec<IndexFile>,
+    /// Index files left in directories without documents, not removed
+    pub orphaned: Vec<String>,
+}
+
+fn relative_of(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn parent_of(relative: &str) -> &str {
+    relative.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+/// Non-blank frontmatter field, trimmed
+pub(crate) fn field(
+    metadata: Option<&Metadata>,
+    pick: fn(&Metadata) -> Option<&String>,
+) -> Option<String> {
+    metadata
+        .and_then(pick)
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Frontmatter title of a document, else its first heading, else its file name
+pub(crate) fn document_title(metadata: Option<&Metadata>, content: &str, name: &str) -> String {
+    field(metadata, |m| m.title.as_ref())
+        .or_else(|| {
+            content
+                .lines()
+                .find_map(heading)
+                .map(|(_, title)| title)
+                .filter(|title| !title.is_empty())
+        })
+        .unwrap_or_else(|| name.trim_end_matches(".md").to_string())
+}
+
+fn describe(path: &Path, name: &str) -> IndexedDocument {
+    let content = text_file::read_text(path).unwrap_or_default();
+    let metadata = extract_metadata(&content);
+    let field = |pick| field(metadata.as_ref(), pick);
+    IndexedDocument {
+        path: name.to_string(),
+        title: document_title(metadata.as_ref(), &content, name),
+        description: field(|m| m.description.as_ref())
+            .unwrap_or_default()
+            .replace('\n', " "),
+        status: field(|m| m.status.as_ref()).unwrap_or_default(),
+        doc_type: field(|m| m.doc_type.as_ref())
+            .unwrap_or_else(|| UNTYPED.to_string())
+            .to_lowercase(),
+    }
+}
+
+/// Renders the index of `dir` (relative, "" for the root)
+fn render_index(
+    root: &Path,
+    dir: &str,
+    documents: &[IndexedDocument],
+    subdirectories: &[String],
+    index_name: &str,
+    template: Option<(&Path, &str, &str)>,
+) -> Result<String, CdeError> {
+    let mut groups: BTreeMap<&str, Vec<&IndexedDocument>> = BTreeMap::new();
+    for document in documents {
+        groups.entry(&document.doc_type).or_default().push(document);
+    }
+    let groups: Vec<serde_json::Value> = groups
+        .into_iter()
+        .map(|(doc_type, mut documents)| {
+            documents.sort_by(|a, b| {
+                a.title
+                    .to_lowercase()
+                    .cmp(&b.title.to_lowercase())
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            serde_json::json!({ "type": doc_type, "documents": documents })
+        })
+        .collect();
+    let subdirectories: Vec<serde_json::Value> = subdirectories
+        .iter()
+        .map(|sub| {
+            let name = sub.rsplit('/').next().unwrap_or(sub);
+            serde_json::json!({ "name": name, "path": format!("{}/{}", name, index_name) })
+        })
+        .collect();
+    let title = match dir.rsplit('/').next().filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => root.file_name().map_or_else(
+            || "Documentation".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+    };
+    let context = serde_json::json!({
+        "title": title,
+        "directory": if dir.is_empty() { "." } else { dir },
+        "index_name": index_name,
+        "groups": groups,
+        "subdirectories": subdirectories,
+        "documents": documents.len(),
+    });
+    let rendered = match template {
+        Some((template_dir, name, source)) => {
+            templating::render_source(template_dir, name, source, &context)?
+        }
+        None => templating::render_source(root, "INDEX.md.j2", DEFAULT_TEMPLATE, &context)?,
+    };
+    format_markdown(&rendered, &MarkdownStyle::default())
+}
+
+/// Writes (or with `check`, verifies) the index of every documentation
+/// directory under `root`, see the module docs
+pub fn generate_doc_index(
+    root: &str,
+    template_path: Option<&str>,
+    index_name: &str,
+    check: bool,
+) -> Result<DocIndexResult, CdeError> {
+    metrics::timed("generate_doc_index", || {
+        let root_path = Path::new(root);
+        let guard = path_policy::walk_guard(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        if index_name.contains(['/', '\\']) || !index_name.ends_with(".md") {
+            return Err(CdeError::invalid_input(format!(
+                "Invalid index name: {} (expected a Markdown file name)",
+                index_name
+            )));
+        }
+        let template = match template_path {
+            Some(template_path) => {
+                path_policy::check(template_path)?;
+                let path = Path::new(template_path);
+                let source = fs::read_to_string(path).map_err(|e| {
+                    CdeError::not_found("Template not found")
+                        .with_path(path)
+                        .caused_by(&e)
+                })?;
+                let name = path
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+                Some((
+                    path.parent().unwrap_or(Path::new("")).to_path_buf(),
+                    name,
+                    source,
+                ))
+            }
+            None => None,
+        };
+
+        let mut documents: BTreeMap<String, Vec<IndexedDocument>> = BTreeMap::new();
+        let mut existing: BTreeSet<String> = BTreeSet::new();
+        let walker = WalkBuilder::new(root_path)
+            .require_git(false)
+            .max_depth(Some(guard.max_depth()))
+            .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+            .build();
+        for entry in walker.flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy();
+            if !name.ends_with(".md") {
+                continue;
+            }
+            let relative = relative_of(root_path, entry.path());
+            let dir = parent_of(&relative).to_string();
+            if name == index_name {
+                existing.insert(dir);
+                continue;
+            }
+            if INDEX_NAMES.contains(&name.as_ref()) {
+                continue;
+            }
+            documents
+                .entry(dir)
+                .or_default()
+                .push(describe(entry.path(), &name));
+        }
+
+        // Directories with documents, and every directory above them
+        let mut indexed: BTreeSet<String> = BTreeSet::new();
+        for dir in documents.keys() {
+            let mut current = dir.as_str();
+            loop {
+                indexed.insert(current.to_string());
+                if current.is_empty() {
+                    break;
+                }
+                current = parent_of(current);
+            }
+        }
+
+        let mut indexes = Vec::new();
+        let mut changes: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+        for dir in &indexed {
+            let subdirectories: Vec<String> = indexed
+                .iter()
+                .filter(|sub| !sub.is_empty() && parent_of(sub) == dir && *sub != dir)
+                .cloned()
+                .collect();
+            let listed = documents.get(dir).map_or(&[][..], Vec::as_slice);
+            let template = template
+                .as_ref()
+                .map(|(dir, name, source)| (dir.as_path(), name.as_str(), source.as_str()));
+            let content = render_index(
+                root_path,
+                dir,
+                listed,
+                &subdirectories,
+                index_name,
+                template,
+            )?;
+            let path = root_path.join(dir).join(index_name);
+            let current = fs::read_to_string(&path).ok();
+            let status = match (&current, check) {
+                (Some(current), _) if *current == content => "unchanged",
+                (Some(_), true) => "stale",
+                (Some(_), false) => "updated",
+                (None, true) => "missing",
+                (None, false) => "created",
+            };
+            if status != "unchanged" && !check {
+                changes.insert(path, Some(content));
+            }
+            indexes.push(IndexFile {
+                path: relative_of(root_path, &root_path.join(dir).join(index_name)),
+                status: status.to_string(),
+                documents: listed.len(),
+            });
+        }
+        if !changes.is_empty() {
+            patch::commit(&changes)?;
+        }
+        Ok(DocIndexResult {
+            root: root.to_string(),
+            check,
+            up_to_date: indexes.iter().all(|index| index.status == "unchanged"),
+            indexes,
+            orphaned: existing
+                .difference(&indexed)
+                .map(|dir| relative_of(root_path, &root_path.join(dir).join(index_name)))
+                .collect(),
+        })
+    })
+}
+
+/// Generates the index of every documentation directory from frontmatter, as JSON
+///
+/// Each directory with Markdown documents (or with such subdirectories) gets an
+/// `index_name` file listing its documents (`title`, `description`, `status`)
+/// grouped by `type`, plus links to the subdirectory indexes. `template` is the
+/// path of a Jinja template receiving `title`, `directory`, `groups` (`type`,
+/// `documents`), `subdirectories` (`name`, `path`) and `documents`; the built-in
+/// one renders tables. With `check` (CI mode) nothing is written: `up_to_date`
+/// tells whether every index matches, and each index has a `status` of
+/// "unchanged", "stale" or "missing" ("created" or "updated" when writing).
+#[pyfunction]
+#[pyo3(signature = (root, template=None, index_name="INDEX.md", check=false))]
+fn generate_doc_index_py(
+    py: Python<'_>,
+    root: &str,
+    template: Option<&str>,
+    index_name: &str,
+    check: bool,
+) -> PyResult<String> {
+    let result = py.detach(|| generate_doc_index(root, template, index_name, check))?;
+    Ok(to_json(&result)?)
+}
+
+/// Adds the documentation index functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(generate_doc_index_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generates_and_checks_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("docs");
+        fs::create_dir_all(root.join("specs")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::write(
+            root.join("specs/b.md"),
+            "---\ntitle: Beta | B\ndescription: Second\nstatus: draft\ntype: design\n---\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("specs/a.md"),
+            "---\ntitle: Alpha\ntype: design\nstatus: active\n---\n",
+        )
+        .unwrap();
+        fs::write(root.join("specs/notes.md"), "# Loose notes\n").unwrap();
+        fs::write(root.join("empty/INDEX.md"), "old").unwrap();
+        let root_str = root.to_str().unwrap();
+
+        let check = generate_doc_index(root_str, None, "INDEX.md", true).unwrap();
+        assert!(!check.up_to_date);
+        let statuses: Vec<(&str, &str)> = check
+            .indexes
+            .iter()
+            .map(|index| (index.path.as_str(), index.status.as_str()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![("INDEX.md", "missing"), ("specs/INDEX.md", "missing")]
+        );
+        assert_eq!(check.orphaned, vec!["empty/INDEX.md"]);
+        assert!(!root.join("INDEX.md").exists());
+
+        generate_doc_index(root_str, None, "INDEX.md", false).unwrap();
+        let specs = fs::read_to_string(root.join("specs/INDEX.md")).unwrap();
+        let alpha = specs.find("[Alpha](a.md)").unwrap();
+        assert!(alpha < specs.find("[Beta \\| B](b.md) | Second | draft |").unwrap());
+        assert!(specs.find("## Design").unwrap() < specs.find("## Other").unwrap());
+        assert!(specs.contains("[Loose notes](notes.md)"));
+        let top = fs::read_to_string(root.join("INDEX.md")).unwrap();
+        assert!(top.contains("- [specs](specs/INDEX.md)"));
+        assert!(
+            generate_doc_index(root_str, None, "INDEX.md", true)
+                .unwrap()
+                .up_to_date
+        );
+
+        let template = dir.path().join("summary.j2");
+        fs::write(&template, "# {{ title }}\n{% for g in groups %}{% for d in g.documents %}* {{ d.title }}\n{% endfor %}{% endfor %}").unwrap();
+        generate_doc_index(root_str, template.to_str(), "SUMMARY.md", false).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("specs/SUMMARY.md")).unwrap(),
+            "# specs\n\n- Alpha\n- Beta | B\n- Loose notes\n"
+        );
+    }
+}
@@ -0,0 +1,421 @@
+// src/doc_manifest.rs
+//! `llms.txt` and `docs-manifest.json` for the documentation of a project
+//!
+//! Both list every Markdown document the `ignore` walker keeps, with its title,
+//! description, `llm_summary` and token count: `llms.txt` as the Markdown entry
+//! point agents look for (a title, a summary quote and one link list per
+//! top-level directory), `docs-manifest.json` as the same data for tools. The
+//! per-document entries are cached in the state store under the project's
+//! namespace, and a document whose size and modification time did not change
+//! keeps its cached entry instead of being read and tokenized again. Neither
+//! output carries timestamps, so an unchanged corpus regenerates the same bytes.
+
+use crate::doc_index::{document_title, field};
+use crate::error::{from_json, to_json, CdeError};
+use crate::metadata::extract_metadata;
+use crate::metrics;
+use crate::patch;
+use crate::path_policy;
+use crate::snapshot::modified_ms;
+use crate::state::{self, StateStore};
+use crate::text_file;
+use crate::tokens::count_tokens;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const LLMS_TXT: &str = "llms.txt";
+pub const MANIFEST_FILE: &str = "docs-manifest.json";
+
+/// Key of the cached entries in the project's namespace
+const CACHE_KEY: &str = "docs_manifest";
+/// Bumped when the manifest layout changes; older caches are ignored
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManifestOptions {
+    /// Model whose tokenizer counts the tokens
+    pub model: String,
+    /// Where the two files go, the root by default
+    pub output_dir: Option<String>,
+    /// Heading of `llms.txt`; the title of README.md, else the root's name
+    pub title: Option<String>,
+    /// Quoted summary of `llms.txt`; the description of README.md by default
+    pub summary: Option<String>,
+    /// Only report what would change
+    pub check: bool,
+}
+
+impl Default for ManifestOptions {
+    fn default() -> Self {
+        Self {
+            model: "gpt-4o".to_string(),
+            output_dir: None,
+            title: None,
+            summary: None,
+            check: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub llm_summary: Option<String>,
+    #[serde(rename = "type")]
+    pub doc_type: Option<String>,
+    pub status: Option<String>,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocsManifest {
+    pub version: u32,
+    pub title: String,
+    pub summary: Option<String>,
+    pub model: String,
+    pub total_tokens: usize,
+    pub documents: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEntry {
+    size: u64,
+    modified_ms: u64,
+    entry: ManifestEntry,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    model: String,
+    entries: HashMap<String, CachedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestResult {
+    pub llms_txt: String,
+    pub manifest: String,
+    pub documents: usize,
+    pub total_tokens: usize,
+    /// Documents read and tokenized, the others came from the cache
+    pub read: usize,
+    /// Output files whose content changed (written unless `check`)
+    pub changed: Vec<String>,
+    pub written: bool,
+}
+
+fn describe(path: &Path, relative: String, model: &str) -> Option<ManifestEntry> {
+    let content = text_file::read_text(path).ok()?;
+    let metadata = extract_metadata(&content);
+    let metadata = metadata.as_ref();
+    let name = relative.rsplit('/').next().unwrap_or(&relative).to_string();
+    Some(ManifestEntry {
+        title: document_title(metadata, &content, &name),
+        description: field(metadata, |m| m.description.as_ref()),
+        llm_summary: field(metadata, |m| m.llm_summary.as_ref()),
+        doc_type: field(metadata, |m| m.doc_type.as_ref()),
+        status: field(metadata, |m| m.status.as_ref()),
+        tokens: count_tokens(&content, model),
+        path: relative,
+    })
+}
+
+/// One line of text, for a list item or a quote
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `llms.txt` of `manifest`: documents at the root first, then one section per
+/// top-level directory
+fn render_llms_txt(manifest: &DocsManifest) -> String {
+    let mut sections: BTreeMap<&str, Vec<&ManifestEntry>> = BTreeMap::new();
+    for entry in &manifest.documents {
+        let section = entry.path.split_once('/').map_or("", |(top, _)| top);
+        sections.entry(section).or_default().push(entry);
+    }
+    let mut out = format!("# {}\n", single_line(&manifest.title));
+    if let Some(summary) = &manifest.summary {
+        out.push_str(&format!("\n> {}\n", single_line(summary)));
+    }
+    for (section, entries) in sections {
+        let heading = if section.is_empty() { "Docs" } else { section };
+        out.push_str(&format!("\n## {}\n\n", heading));
+        for entry in entries {
+            out.push_str(&format!(
+                "- [{}]({})",
+                single_line(&entry.title),
+                entry.path
+            ));
+            if let Some(about) = entry.llm_summary.as_ref().or(entry.description.as_ref()) {
+                out.push_str(&format!(": {}", single_line(about)));
+            }
+            out.push_str(&format!(" ({} tokens)\n", entry.tokens));
+        }
+    }
+    out
+}
+
+/// Builds the manifest of `root` from the entries cached in `store`, reading only
+/// the documents that changed, and writes `llms.txt` and `docs-manifest.json`
+pub fn generate_docs_manifest_with(
+    store: &StateStore,
+    root: &str,
+    options: &ManifestOptions,
+) -> Result<ManifestResult, CdeError> {
+    metrics::timed("generate_docs_manifest", || {
+        let root_path = Path::new(root);
+        let guard = path_policy::walk_guard(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let output_dir = PathBuf::from(options.output_dir.as_deref().unwrap_or(root));
+        path_policy::check(&output_dir)?;
+
+        let namespace = state::namespace(root);
+        let cache: Cache = store
+            .get(&namespace, CACHE_KEY)?
+            .and_then(|cached| serde_json::from_str(&cached).ok())
+            .filter(|cache: &Cache| cache.version == FORMAT_VERSION && cache.model == options.model)
+            .unwrap_or_default();
+        metrics::cache_lookup("docs_manifest", !cache.entries.is_empty());
+
+        let walker = WalkBuilder::new(root_path)
+            .max_depth(Some(guard.max_depth()))
+            .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+            .build();
+        let found: Vec<(PathBuf, String, fs::Metadata)> = walker
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "md"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let relative = entry
+                    .path()
+                    .strip_prefix(root_path)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                Some((entry.into_path(), relative, metadata))
+            })
+            .collect();
+
+        // A document removed or unreadable since the walk is left out
+        let mut documents: Vec<(CachedEntry, bool)> = found
+            .into_par_iter()
+            .filter_map(|(path, relative, metadata)| {
+                let size = metadata.len();
+                let modified_ms = modified_ms(&metadata);
+                if let Some(cached) = cache
+                    .entries
+                    .get(&relative)
+                    .filter(|cached| cached.size == size && cached.modified_ms == modified_ms)
+                {
+                    return Some((cached.clone(), false));
+                }
+                let entry = describe(&path, relative, &options.model)?;
+                Some((
+                    CachedEntry {
+                        size,
+                        modified_ms,
+                        entry,
+                    },
+                    true,
+                ))
+            })
+            .collect();
+        documents.sort_by(|a, b| a.0.entry.path.cmp(&b.0.entry.path));
+        let read = documents.iter().filter(|(_, read)| *read).count();
+        metrics::add_bytes(
+            "generate_docs_manifest",
+            documents
+                .iter()
+                .filter(|(_, read)| *read)
+                .map(|(cached, _)| cached.size)
+                .sum(),
+        );
+
+        let readme = documents
+            .iter()
+            .map(|(cached, _)| &cached.entry)
+            .find(|entry| entry.path.eq_ignore_ascii_case("README.md"));
+        let title = options
+            .title
+            .clone()
+            .or_else(|| readme.map(|entry| entry.title.clone()))
+            .unwrap_or_else(|| {
+                state::namespace(root)
+                    .rsplit(['/', '\\'])
+                    .next()
+                    .unwrap_or(root)
+                    .to_string()
+            });
+        let summary = options.summary.clone().or_else(|| {
+            readme.and_then(|entry| entry.llm_summary.clone().or(entry.description.clone()))
+        });
+        let manifest = DocsManifest {
+            version: FORMAT_VERSION,
+            title,
+            summary,
+            model: options.model.clone(),
+            total_tokens: documents
+                .iter()
+                .map(|(cached, _)| cached.entry.tokens)
+                .sum(),
+            documents: documents
+                .iter()
+                .map(|(cached, _)| cached.entry.clone())
+                .collect(),
+        };
+
+        let llms_txt = output_dir.join(LLMS_TXT);
+        let manifest_path = output_dir.join(MANIFEST_FILE);
+        let mut manifest_json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| CdeError::serialization("Failed to serialize manifest").caused_by(&e))?;
+        manifest_json.push('\n');
+        let mut changes: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+        for (path, content) in [
+            (llms_txt.clone(), render_llms_txt(&manifest)),
+            (manifest_path.clone(), manifest_json),
+        ] {
+            if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                changes.insert(path, Some(content));
+            }
+        }
+        let written = !options.check && !changes.is_empty();
+        if written {
+            patch::commit(&changes)?;
+        }
+
+        let cache = Cache {
+            version: FORMAT_VERSION,
+            model: options.model.clone(),
+            entries: documents
+                .into_iter()
+                .map(|(cached, _)| (cached.entry.path.clone(), cached))
+                .collect(),
+        };
+        store.set(&namespace, CACHE_KEY, &to_json(&cache)?, None)?;
+
+        Ok(ManifestResult {
+            llms_txt: llms_txt.to_string_lossy().into_owned(),
+            manifest: manifest_path.to_string_lossy().into_owned(),
+            documents: manifest.documents.len(),
+            total_tokens: manifest.total_tokens,
+            read,
+            changed: changes
+                .keys()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            written,
+        })
+    })
+}
+
+/// `generate_docs_manifest_with` on the shared state store
+pub fn generate_docs_manifest(
+    root: &str,
+    options: &ManifestOptions,
+) -> Result<ManifestResult, CdeError> {
+    state::with_shared(|store| generate_docs_manifest_with(store, root, options))
+}
+
+/// Writes `llms.txt` and `docs-manifest.json` for the documentation under `root`
+///
+/// Every Markdown document is listed with its title, description, `llm_summary`
+/// and token count. Options (JSON): `model` ("gpt-4o"), `output_dir` (the root),
+/// `title` and `summary` of `llms.txt` (from README.md by default), and `check`
+/// to only report the files that would change. Documents unchanged since the
+/// previous run are taken from the cache in the state store. Returns the output
+/// paths, `documents`, `total_tokens`, how many documents were `read`, and the
+/// `changed` files.
+#[pyfunction]
+#[pyo3(signature = (root, options_json=None))]
+fn generate_llms_txt_py(
+    py: Python<'_>,
+    root: &str,
+    options_json: Option<&str>,
+) -> PyResult<String> {
+    let options: ManifestOptions = match options_json {
+        Some(json) => from_json("manifest options", json)?,
+        None => ManifestOptions::default(),
+    };
+    let result = py.detach(|| generate_docs_manifest(root, &options))?;
+    Ok(to_json(&result)?)
+}
+
+/// Adds the documentation manifest functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(generate_llms_txt_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_reuses_cached_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(root.join("docs/specs")).unwrap();
+        fs::write(
+            root.join("README.md"),
+            "---\ntitle: Demo\ndescription: A demo project\n---\n\nHello\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("docs/specs/api.md"),
+            "---\ntitle: API\nllm_summary: The HTTP API\n---\n",
+        )
+        .unwrap();
+        fs::write(root.join("docs/guide.md"), "# Guide\n\nSteps.\n").unwrap();
+        let root_str = root.to_str().unwrap();
+        let options = ManifestOptions::default();
+
+        let first = generate_docs_manifest_with(&store, root_str, &options).unwrap();
+        assert_eq!(
+            (first.documents, first.read, first.changed.len()),
+            (3, 3, 2)
+        );
+        let llms = fs::read_to_string(root.join(LLMS_TXT)).unwrap();
+        assert!(llms.starts_with(
+            "# Demo\n\n> A demo project\n\n## Docs\n\n- [Demo](README.md): A demo project ("
+        ));
+        assert!(llms.contains("## docs\n\n- [Guide](docs/guide.md) ("));
+        assert!(llms.contains("\n- [API](docs/specs/api.md): The HTTP API ("));
+        let manifest: DocsManifest =
+            serde_json::from_str(&fs::read_to_string(root.join(MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest.documents[1].path, "docs/guide.md");
+        assert!(manifest.total_tokens > 0);
+
+        let again = generate_docs_manifest_with(&store, root_str, &options).unwrap();
+        assert_eq!(
+            (again.read, again.changed.len(), again.written),
+            (0, 0, false)
+        );
+
+        fs::write(root.join("docs/guide.md"), "# User guide\n\nMore steps.\n").unwrap();
+        let check = ManifestOptions {
+            check: true,
+            ..ManifestOptions::default()
+        };
+        let stale = generate_docs_manifest_with(&store, root_str, &check).unwrap();
+        assert_eq!(
+            (stale.read, stale.changed.len(), stale.written),
+            (1, 2, false)
+        );
+        assert!(!fs::read_to_string(root.join(LLMS_TXT))
+            .unwrap()
+            .contains("User guide"));
+    }
+}
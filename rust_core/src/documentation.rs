@@ -1,62 +1,42 @@
 // src/documentation.rs
+use crate::cancel::CancellationToken;
+use crate::conventions::{relative_to, Conventions};
+use crate::error::CdeError;
 use crate::filesystem::find_markdown_files;
+use crate::metadata::{extract_metadata, Metadata};
+use crate::metrics;
+use crate::paging::{cap, page, CapLists};
+use crate::path_policy;
+use crate::progress::ProgressSink;
+use crate::text_file;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_yaml;
-use std::collections::HashMap;
-use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct YamlFrontmatter {
-    pub title: Option<String>,
-    pub description: Option<String>,
-    #[serde(rename = "type")]
-    pub doc_type: Option<String>,
-    pub status: Option<String>,
-    pub created: Option<String>,
-    pub updated: Option<String>,
-    pub author: Option<String>,
-    pub llm_summary: Option<String>,
-    #[serde(flatten)]
-    pub extra: HashMap<String, serde_yaml::Value>,
-}
+/// Number of files read between two progress reports
+const PROGRESS_INTERVAL: usize = 100;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinkInfo {
     pub text: String,
     pub url: String,
     pub is_internal: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Document {
     pub path: String,
     pub content: String,
     pub word_count: usize,
     pub has_frontmatter: bool,
-    pub metadata: Option<YamlFrontmatter>,
+    pub metadata: Option<Metadata>,
     pub links: Vec<LinkInfo>,
     pub headers: Vec<String>,
 }
 
-/// Extrae YAML frontmatter de un documento Markdown
-fn extract_frontmatter(content: &str) -> Option<YamlFrontmatter> {
-    if !content.starts_with("---") {
-        return None;
-    }
-
-    let parts: Vec<&str> = content.splitn(3, "---").collect();
-    if parts.len() < 3 {
-        return None;
-    }
-
-    let yaml_str = parts[1].trim();
-    serde_yaml::from_str(yaml_str).ok()
-}
-
 /// Extrae todos los links Markdown de un documento
 fn extract_links(content: &str) -> Vec<LinkInfo> {
     let link_regex = Regex::new(r"\[([^\]]+)\]\(([^\)]+)\)").unwrap();
@@ -89,13 +69,74 @@ fn extract_headers(content: &str) -> Vec<String> {
 
 /// Scans a documentation project, finds all Markdown files, and reads their content in parallel.
 /// Extracts YAML frontmatter, links, headers, and word count for each document.
-pub fn scan_documentation(root_path: &str) -> Result<Vec<Document>, String> {
+pub fn scan_documentation(root_path: &str) -> Result<Vec<Document>, CdeError> {
+    scan_documentation_with(root_path, &CancellationToken::default(), &ProgressSink::default())
+}
+
+/// `scan_documentation`, skipping the files not read yet once `cancel` is cancelled
+/// and reporting the "discovering" and "reading" phases to `progress`
+pub fn scan_documentation_with(
+    root_path: &str,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<Vec<Document>, CdeError> {
+    let documents = metrics::timed("scan_documentation", || {
+        read_documents(root_path, cancel, progress)
+    })?;
+    let bytes = documents.iter().map(|doc| doc.content.len() as u64).sum();
+    metrics::add_bytes("scan_documentation", bytes);
+    Ok(documents)
+}
+
+/// Reads the Markdown document at `path_str` and extracts its metadata
+pub(crate) fn read_document(path_str: &str) -> Result<Document, CdeError> {
+    let content = text_file::read_text(Path::new(path_str))?;
+    // Extraer metadata en paralelo
+    let metadata = extract_metadata(&content);
+    let has_frontmatter = metadata.is_some();
+
+    // Word count paralelo solo para archivos grandes (>100KB)
+    let word_count = if content.len() > 100_000 {
+        content.par_split_whitespace().count()
+    } else {
+        content.split_whitespace().count()
+    };
+
+    // Extraer links y headers (en paralelo para archivos grandes)
+    let (links, headers) = if content.len() > 50_000 {
+        rayon::join(
+            || extract_links(&content),
+            || extract_headers(&content),
+        )
+    } else {
+        (extract_links(&content), extract_headers(&content))
+    };
+
+    Ok(Document {
+        path: path_str.to_string(),
+        content,
+        word_count,
+        has_frontmatter,
+        metadata,
+        links,
+        headers,
+    })
+}
+
+fn read_documents(
+    root_path: &str,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<Vec<Document>, CdeError> {
     let path = Path::new(root_path);
+    path_policy::check(path)?;
     if !path.is_dir() {
-        return Err(format!("'{}' is not a valid directory.", root_path));
+        return Err(CdeError::not_a_directory(root_path));
     }
 
+    progress.report("discovering", 0, None);
     let files = find_markdown_files(path);
+    let read = AtomicUsize::new(0);
 
     // Calcular chunk size óptimo basado en CPU cores
     let num_files = files.len();
@@ -109,39 +150,15 @@ pub fn scan_documentation(root_path: &str) -> Result<Vec<Document>, String> {
         .par_iter()
         .with_min_len(chunk_size) // Evitar overhead de chunks pequeños
         .filter_map(|path_str| {
-            match fs::read_to_string(path_str) {
-                Ok(content) => {
-                    // Extraer metadata en paralelo
-                    let metadata = extract_frontmatter(&content);
-                    let has_frontmatter = metadata.is_some();
-
-                    // Word count paralelo solo para archivos grandes (>100KB)
-                    let word_count = if content.len() > 100_000 {
-                        content.par_split_whitespace().count()
-                    } else {
-                        content.split_whitespace().count()
-                    };
-
-                    // Extraer links y headers (en paralelo para archivos grandes)
-                    let (links, headers) = if content.len() > 50_000 {
-                        rayon::join(
-                            || extract_links(&content),
-                            || extract_headers(&content),
-                        )
-                    } else {
-                        (extract_links(&content), extract_headers(&content))
-                    };
-
-                    Some(Document {
-                        path: path_str.clone(),
-                        content,
-                        word_count,
-                        has_frontmatter,
-                        metadata,
-                        links,
-                        headers,
-                    })
-                }
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let done = read.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(PROGRESS_INTERVAL) || done == num_files {
+                progress.report("reading", done, Some(num_files));
+            }
+            match read_document(path_str) {
+                Ok(document) => Some(document),
                 Err(e) => {
                     // Registrar error sin detener el procesamiento
                     errors
@@ -157,9 +174,9 @@ pub fn scan_documentation(root_path: &str) -> Result<Vec<Document>, String> {
     // Log warnings pero no fallar
     let error_list = errors.lock().unwrap();
     if !error_list.is_empty() {
-        eprintln!("⚠️  Warning: Failed to read {} files", error_list.len());
-        for (path, err) in error_list.iter().take(3) {
-            eprintln!("   - {}: {}", path, err);
+        tracing::warn!(count = error_list.len(), "Failed to read some documentation files");
+        for (path, err) in error_list.iter() {
+            tracing::debug!(path = %path, error = %err, "Failed to read documentation file");
         }
     }
 
@@ -175,16 +192,73 @@ pub struct QualityReport {
     pub total_links: usize,
     pub broken_internal_links: Vec<String>,
     pub orphaned_docs: Vec<String>,
+    /// Documents whose `type` their directory does not allow
+    #[serde(default)]
+    pub misplaced_docs: Vec<String>,
     pub large_files: Vec<String>,
     pub issues: Vec<String>,
     pub recommendations: Vec<String>,
+    /// Whether lists were cut to `max_list_items`
+    #[serde(default)]
+    pub truncated: bool,
+    /// Whether the scan was cancelled; the report then covers the documents read
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+impl CapLists for QualityReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.broken_internal_links, max_items)
+            | cap(&mut self.orphaned_docs, max_items)
+            | cap(&mut self.misplaced_docs, max_items)
+            | cap(&mut self.large_files, max_items);
+    }
+}
+
+/// `scan_documentation` sorted by path, documents `offset..offset + limit`; their
+/// `content` is left empty unless `include_content`. Once `cancel` is cancelled,
+/// only the documents read so far are paged.
+pub fn scan_documentation_page(
+    root_path: &str,
+    offset: usize,
+    limit: Option<usize>,
+    include_content: bool,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<Vec<Document>, CdeError> {
+    let mut documents = scan_documentation_with(root_path, cancel, progress)?;
+    progress.finish(documents.len());
+    documents.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut documents = page(documents, offset, limit);
+    if !include_content {
+        for document in &mut documents {
+            document.content = String::new();
+        }
+    }
+    Ok(documents)
 }
 
 /// Analiza la calidad de la documentación en paralelo
-pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, String> {
-    let documents = scan_documentation(root_path)?;
+pub fn analyze_documentation_quality(
+    root_path: &str,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<QualityReport, CdeError> {
+    metrics::timed("analyze_documentation_quality", || {
+        quality_report(root_path, cancel, progress)
+    })
+}
+
+fn quality_report(
+    root_path: &str,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<QualityReport, CdeError> {
+    let documents = scan_documentation_with(root_path, cancel, progress)?;
+    let cancelled = cancel.is_cancelled();
 
     if documents.is_empty() {
+        progress.finish(0);
         return Ok(QualityReport {
             quality_score: 0.0,
             total_docs: 0,
@@ -193,20 +267,25 @@ pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, S
             total_links: 0,
             broken_internal_links: Vec::new(),
             orphaned_docs: Vec::new(),
+            misplaced_docs: Vec::new(),
             large_files: Vec::new(),
             issues: vec!["No documentation files found".to_string()],
             recommendations: vec!["Create documentation files with YAML frontmatter".to_string()],
+            truncated: false,
+            cancelled,
         });
     }
 
     // Análisis paralelo de métricas
     let total_docs = documents.len();
+    progress.report("analyzing", total_docs, Some(total_docs));
+    let conventions = Conventions::load(Path::new(root_path))?;
 
-    let (docs_with_metadata, docs_without_metadata, total_links, large_files, orphaned_docs) = documents
+    let (docs_with_metadata, docs_without_metadata, total_links, large_files, (orphaned_docs, misplaced_docs)) = documents
         .par_iter()
         .fold(
-            || (0, 0, 0, Vec::new(), Vec::new()),
-            |(with_meta, without_meta, links, mut large, mut orphaned), doc| {
+            || (0, 0, 0, Vec::new(), (Vec::new(), Vec::new())),
+            |(with_meta, without_meta, links, mut large, (mut orphaned, mut misplaced)), doc| {
                 let has_meta = if doc.has_frontmatter { 1 } else { 0 };
                 let no_meta = if doc.has_frontmatter { 0 } else { 1 };
                 let link_count = doc.links.len();
@@ -216,27 +295,25 @@ pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, S
                     large.push(doc.path.clone());
                 }
 
-                // Documentos huérfanos (no en specs/ ni agent-docs/)
-                let path_lower = doc.path.to_lowercase();
-                if !path_lower.contains("specs/")
-                    && !path_lower.contains("agent-docs/")
-                    && !path_lower.contains("readme.md")
-                    && !path_lower.contains("changelog.md")
-                    && !path_lower.contains("contributing.md")
-                    && !path_lower.contains("agents.md")
-                    && !path_lower.contains("gemini.md") {
+                // Documentos huérfanos o mal ubicados según las convenciones del proyecto
+                let doc_type = doc.metadata.as_ref().and_then(|m| m.doc_type.as_deref());
+                let placement = conventions.place(&relative_to(Path::new(root_path), &doc.path), doc_type);
+                if placement.orphaned {
                     orphaned.push(doc.path.clone());
+                } else if placement.misplaced {
+                    misplaced.push(doc.path.clone());
                 }
 
-                (with_meta + has_meta, without_meta + no_meta, links + link_count, large, orphaned)
+                (with_meta + has_meta, without_meta + no_meta, links + link_count, large, (orphaned, misplaced))
             },
         )
         .reduce(
-            || (0, 0, 0, Vec::new(), Vec::new()),
-            |(w1, wo1, l1, mut lg1, mut o1), (w2, wo2, l2, lg2, o2)| {
+            || (0, 0, 0, Vec::new(), (Vec::new(), Vec::new())),
+            |(w1, wo1, l1, mut lg1, (mut o1, mut m1)), (w2, wo2, l2, lg2, (o2, m2))| {
                 lg1.extend(lg2);
                 o1.extend(o2);
-                (w1 + w2, wo1 + wo2, l1 + l2, lg1, o1)
+                m1.extend(m2);
+                (w1 + w2, wo1 + wo2, l1 + l2, lg1, (o1, m1))
             },
         );
 
@@ -270,7 +347,7 @@ pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, S
     let orphan_penalty = (orphaned_docs.len() as f32 / total_docs as f32) * 20.0;
     let large_file_penalty = (large_files.len() as f32 / total_docs as f32) * 10.0;
 
-    let quality_score = (metadata_score + link_score + 30.0 - orphan_penalty - large_file_penalty).max(0.0).min(100.0);
+    let quality_score = (metadata_score + link_score + 30.0 - orphan_penalty - large_file_penalty).clamp(0.0, 100.0);
 
     // Generar issues y recomendaciones
     let mut issues = Vec::new();
@@ -287,8 +364,13 @@ pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, S
     }
 
     if !orphaned_docs.is_empty() {
-        issues.push(format!("⚠️ {} orphaned documents in root directory", orphaned_docs.len()));
-        recommendations.push("→ Move documents to specs/ or agent-docs/ directories".to_string());
+        issues.push(format!("⚠️ {} orphaned documents outside the documentation layout", orphaned_docs.len()));
+        recommendations.push("→ Move documents to specs/ or agent-docs/ directories, or declare their location in .cde/conventions.yml".to_string());
+    }
+
+    if !misplaced_docs.is_empty() {
+        issues.push(format!("⚠️ {} documents with a type their directory does not allow", misplaced_docs.len()));
+        recommendations.push("→ Move misplaced documents to the directory of their type, or fix their 'type'".to_string());
     }
 
     if !large_files.is_empty() {
@@ -306,16 +388,61 @@ pub fn analyze_documentation_quality(root_path: &str) -> Result<QualityReport, S
         recommendations.push("🔴 Documentation quality is poor. Major improvements needed.".to_string());
     }
 
+    progress.finish(total_docs);
     Ok(QualityReport {
         quality_score,
         total_docs,
         docs_with_metadata,
         docs_without_metadata,
         total_links,
-        broken_internal_links: broken_internal_links.into_iter().take(20).collect(),
-        orphaned_docs: orphaned_docs.into_iter().take(20).collect(),
-        large_files: large_files.into_iter().take(20).collect(),
+        broken_internal_links,
+        orphaned_docs,
+        misplaced_docs,
+        large_files,
         issues,
         recommendations,
+        truncated: false,
+        cancelled,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_documentation_page_and_capped_quality_report() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["c.md", "a.md", "b.md"] {
+            let content = format!("---\ntitle: {}\n---\n[missing](gone-{})\n", name, name);
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        let root = dir.path().to_str().unwrap();
+
+        let progress = ProgressSink::new(Some("doc-page-test".to_string()), None);
+        let page =
+            scan_documentation_page(root, 1, Some(1), false, &CancellationToken::default(), &progress)
+                .unwrap();
+        let done = crate::progress::poll("doc-page-test").unwrap();
+        assert_eq!((done.phase.as_str(), done.processed), ("done", 3));
+        assert_eq!(page.len(), 1);
+        assert!(page[0].path.ends_with("b.md"));
+        assert!(page[0].content.is_empty());
+        assert_eq!(page[0].links.len(), 1);
+
+        let never = CancellationToken::default();
+        let report = analyze_documentation_quality(root, &never, &ProgressSink::default()).unwrap();
+        let report = crate::paging::capped(report, Some(2));
+        assert_eq!(report.total_docs, 3);
+        assert_eq!(report.broken_internal_links.len(), 2);
+        assert!(report.truncated);
+        assert!(!report.cancelled);
+
+        let cancel = CancellationToken::default();
+        cancel.cancel();
+        let report = analyze_documentation_quality(root, &cancel, &ProgressSink::default()).unwrap();
+        assert_eq!(report.total_docs, 0);
+        assert!(report.cancelled);
+    }
+}
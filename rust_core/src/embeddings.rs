@@ -0,0 +1,605 @@
+// src/embeddings.rs
+//! Local vector index for semantic retrieval
+//!
+//! Embedding vectors are kept in an HNSW graph (hierarchical navigable small
+//! world) persisted as one file per index, and searched by cosine similarity.
+//! Vectors come from the caller; items given as text, and text queries, go
+//! through the `embedder` callable passed from Python (any model, e.g. an ONNX
+//! session), which maps a string to a list of floats.
+//!
+//! Upserting an existing id replaces it: the old node stays in the graph as a
+//! tombstone that searches walk through but never return, and the graph is
+//! rebuilt once tombstones outnumber live nodes. Loaded indexes are cached per
+//! path and reloaded when the file changes on disk.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::path_policy;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Neighbors per node above the bottom layer
+const M: usize = 16;
+/// Neighbors per node on the bottom layer
+const M0: usize = 2 * M;
+const EF_CONSTRUCTION: usize = 100;
+const EF_SEARCH: usize = 64;
+const MAX_LEVEL: usize = 16;
+/// Bumped when the file layout changes; older files are rejected
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    /// Unit length, so the dot product is the cosine similarity
+    vector: Vec<f32>,
+    /// JSON, as bincode cannot store arbitrary JSON values
+    metadata: String,
+    /// Neighbors on each layer the node is on, bottom first
+    layers: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HnswIndex {
+    version: u32,
+    dimension: Option<usize>,
+    nodes: Vec<Node>,
+    entry: Option<usize>,
+    /// Live node of each id, rebuilt on load
+    #[serde(skip)]
+    ids: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarItem {
+    pub id: String,
+    /// Cosine similarity, 1 for the same direction
+    pub score: f32,
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpsertReport {
+    pub upserted: usize,
+    /// Live items in the index
+    pub total: usize,
+    pub dimension: Option<usize>,
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// `vector` scaled to unit length; zero and non-finite vectors are rejected
+fn normalized(mut vector: Vec<f32>) -> Result<Vec<f32>, CdeError> {
+    let norm = dot(&vector, &vector).sqrt();
+    if !norm.is_finite() || norm == 0.0 {
+        return Err(CdeError::invalid_input(
+            "Embedding vectors must be finite and non-zero",
+        ));
+    }
+    vector.iter_mut().for_each(|x| *x /= norm);
+    Ok(vector)
+}
+
+/// Layer of a new node, geometrically distributed; derived from a hash so that
+/// the same upserts build the same graph
+fn random_level(id: &str, position: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    (id, position).hash(&mut hasher);
+    let uniform = ((hasher.finish() >> 11) as f64 / (1u64 << 53) as f64).max(f64::MIN_POSITIVE);
+    ((-uniform.ln() / (M as f64).ln()) as usize).min(MAX_LEVEL)
+}
+
+impl HnswIndex {
+    pub fn new() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// Live items
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        1.0 - dot(query, &self.nodes[node].vector)
+    }
+
+    /// The `ef` nodes of `level` closest to `query` found from `entry`, closest
+    /// first
+    fn search_layer(&self, query: &[f32], entry: &[usize], ef: usize, level: usize) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entry.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Scored> = BinaryHeap::new();
+        for &node in entry {
+            let scored = Scored {
+                distance: self.distance(query, node),
+                node,
+            };
+            candidates.push(Reverse(scored));
+            found.push(scored);
+        }
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            let worst = found.peek().map_or(f32::INFINITY, |s| s.distance);
+            if found.len() >= ef && candidate.distance > worst {
+                break;
+            }
+            let Some(neighbors) = self.nodes[candidate.node].layers.get(level) else {
+                continue;
+            };
+            for &neighbor in neighbors {
+                let neighbor = neighbor as usize;
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbor);
+                let worst = found.peek().map_or(f32::INFINITY, |s| s.distance);
+                if found.len() < ef || distance < worst {
+                    let scored = Scored {
+                        distance,
+                        node: neighbor,
+                    };
+                    candidates.push(Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    /// Closest node to `query` on the layers above `level`, from the entry point
+    fn descend(&self, query: &[f32], entry: usize, level: usize) -> usize {
+        let top = self.nodes[entry].layers.len() - 1;
+        (level + 1..=top).rev().fold(entry, |closest, layer| {
+            self.search_layer(query, &[closest], 1, layer)[0].node
+        })
+    }
+
+    fn link(&mut self, node: usize) {
+        let level = self.nodes[node].layers.len() - 1;
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+        let query = self.nodes[node].vector.clone();
+        let top = self.nodes[entry].layers.len() - 1;
+        let mut entry_points = vec![self.descend(&query, entry, level)];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, EF_CONSTRUCTION, layer);
+            let max_neighbors = if layer == 0 { M0 } else { M };
+            let neighbors: Vec<u32> = found.iter().take(M).map(|s| s.node as u32).collect();
+            for &neighbor in &neighbors {
+                let neighbor = neighbor as usize;
+                self.nodes[neighbor].layers[layer].push(node as u32);
+                if self.nodes[neighbor].layers[layer].len() > max_neighbors {
+                    self.prune(neighbor, layer, max_neighbors);
+                }
+            }
+            self.nodes[node].layers[layer] = neighbors;
+            entry_points = found.into_iter().map(|s| s.node).collect();
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    /// Keeps the `max_neighbors` closest neighbors of `node` on `layer`
+    fn prune(&mut self, node: usize, layer: usize, max_neighbors: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut neighbors: Vec<Scored> = self.nodes[node].layers[layer]
+            .iter()
+            .map(|&n| Scored {
+                distance: 1.0 - dot(vector, &self.nodes[n as usize].vector),
+                node: n as usize,
+            })
+            .collect();
+        neighbors.sort();
+        neighbors.truncate(max_neighbors);
+        self.nodes[node].layers[layer] = neighbors.into_iter().map(|s| s.node as u32).collect();
+    }
+
+    /// Adds or replaces `id`
+    pub fn upsert(
+        &mut self,
+        id: String,
+        vector: Vec<f32>,
+        metadata: &serde_json::Value,
+    ) -> Result<(), CdeError> {
+        let dimension = *self.dimension.get_or_insert(vector.len());
+        if vector.len() != dimension {
+            return Err(CdeError::invalid_input(format!(
+                "Embedding of {} has {} dimensions, the index has {}",
+                id,
+                vector.len(),
+                dimension
+            )));
+        }
+        let vector = normalized(vector)?;
+        if let Some(previous) = self.ids.remove(&id) {
+            self.nodes[previous].deleted = true;
+        }
+        let position = self.nodes.len();
+        self.nodes.push(Node {
+            layers: vec![Vec::new(); random_level(&id, position) + 1],
+            id: id.clone(),
+            vector,
+            metadata: metadata.to_string(),
+            deleted: false,
+        });
+        self.ids.insert(id, position);
+        self.link(position);
+        if self.nodes.len() > 2 * self.ids.len() {
+            self.rebuild();
+        }
+        Ok(())
+    }
+
+    /// Relinks the live nodes into a new graph, dropping tombstones
+    fn rebuild(&mut self) {
+        let live: Vec<Node> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .filter(|node| !node.deleted)
+            .collect();
+        self.entry = None;
+        self.ids.clear();
+        for node in live {
+            let position = self.nodes.len();
+            self.ids.insert(node.id.clone(), position);
+            self.nodes.push(Node {
+                layers: vec![Vec::new(); random_level(&node.id, position) + 1],
+                ..node
+            });
+            self.link(position);
+        }
+    }
+
+    /// The `k` live items most similar to `query`, most similar first
+    pub fn query(&self, query: Vec<f32>, k: usize) -> Result<Vec<SimilarItem>, CdeError> {
+        let (Some(entry), Some(dimension)) = (self.entry, self.dimension) else {
+            return Ok(Vec::new());
+        };
+        if query.len() != dimension {
+            return Err(CdeError::invalid_input(format!(
+                "Query has {} dimensions, the index has {}",
+                query.len(),
+                dimension
+            )));
+        }
+        let query = normalized(query)?;
+        // Tombstones take places in the candidate list
+        let ef = k.max(EF_SEARCH)
+            * if self.nodes.len() > self.ids.len() {
+                2
+            } else {
+                1
+            };
+        let closest = self.descend(&query, entry, 0);
+        Ok(self
+            .search_layer(&query, &[closest], ef, 0)
+            .into_iter()
+            .filter(|scored| !self.nodes[scored.node].deleted)
+            .take(k)
+            .map(|scored| {
+                let node = &self.nodes[scored.node];
+                SimilarItem {
+                    id: node.id.clone(),
+                    score: 1.0 - scored.distance,
+                    metadata: serde_json::from_str(&node.metadata)
+                        .unwrap_or(serde_json::Value::Null),
+                }
+            })
+            .collect())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CdeError> {
+        let bytes = fs::read(path).map_err(|e| {
+            CdeError::io("Failed to read embedding index")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+        let mut index: Self = bincode::deserialize(&bytes).map_err(|e| {
+            CdeError::parse("Invalid embedding index")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+        if index.version != FORMAT_VERSION {
+            return Err(CdeError::parse(format!(
+                "Unsupported embedding index version {}",
+                index.version
+            ))
+            .with_path(path));
+        }
+        index.ids = index
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.deleted)
+            .map(|(position, node)| (node.id.clone(), position))
+            .collect();
+        Ok(index)
+    }
+
+    /// Writes the index through a temporary file so readers never see a partial
+    /// one
+    pub fn save(&self, path: &Path) -> Result<(), CdeError> {
+        let bytes = bincode::serialize(self).map_err(|e| {
+            CdeError::serialization("Failed to serialize embedding index").caused_by(&e)
+        })?;
+        let io_error = |e: std::io::Error| {
+            CdeError::io("Failed to write embedding index")
+                .with_path(path)
+                .caused_by(&e)
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(format!(".{}.tmp", std::process::id()));
+        fs::write(&temporary, bytes).map_err(io_error)?;
+        fs::rename(&temporary, path).map_err(io_error)
+    }
+}
+
+/// Indexes by path, with the modification time of the file they were loaded from
+type LoadedIndexes = HashMap<PathBuf, (Option<SystemTime>, HnswIndex)>;
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Runs `work` on the index at `path`, loaded once and reloaded when the file
+/// changes; an index that does not exist yet starts empty. `path` must be
+/// allowed by the path policy.
+fn with_index<T>(
+    path: &Path,
+    work: impl FnOnce(&mut HnswIndex) -> Result<T, CdeError>,
+) -> Result<T, CdeError> {
+    path_policy::check(path)?;
+    static LOADED: OnceLock<Mutex<LoadedIndexes>> = OnceLock::new();
+    let mut loaded = LOADED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let on_disk = modified(path);
+    let stale = loaded
+        .get(path)
+        .is_none_or(|(loaded_at, _)| *loaded_at != on_disk);
+    if stale {
+        let index = match on_disk {
+            Some(_) => HnswIndex::load(path)?,
+            None => HnswIndex::new(),
+        };
+        loaded.insert(path.to_path_buf(), (on_disk, index));
+    }
+    let (loaded_at, index) = loaded.get_mut(path).expect("index was just loaded");
+    let result = work(index);
+    // A save by `work` is already in memory
+    *loaded_at = modified(path);
+    result
+}
+
+/// Upserts `items` into the index at `path` and saves it
+pub fn upsert_embeddings(
+    path: &Path,
+    items: Vec<(String, Vec<f32>, serde_json::Value)>,
+) -> Result<UpsertReport, CdeError> {
+    let upserted = items.len();
+    with_index(path, |index| {
+        for (id, vector, metadata) in items {
+            index.upsert(id, vector, &metadata)?;
+        }
+        index.save(path)?;
+        Ok(UpsertReport {
+            upserted,
+            total: index.len(),
+            dimension: index.dimension,
+        })
+    })
+}
+
+#[derive(Deserialize)]
+struct EmbeddingItem {
+    id: String,
+    #[serde(default)]
+    vector: Option<Vec<f32>>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+/// Query given as a vector, or as text for the embedder
+#[derive(FromPyObject)]
+enum Query {
+    Vector(Vec<f32>),
+    Text(String),
+}
+
+fn embed(py: Python<'_>, embedder: Option<&Py<PyAny>>, text: &str) -> PyResult<Vec<f32>> {
+    let embedder = embedder.ok_or_else(|| {
+        CdeError::invalid_input("Text given without an embedder to turn it into a vector")
+    })?;
+    embedder.call1(py, (text,))?.extract(py)
+}
+
+/// Adds or replaces items in the embedding index at `index_path`
+///
+/// `items_json` is a list of objects with `id`, optional `metadata`, and either
+/// `vector` (list of floats) or `text`, which is turned into a vector by calling
+/// `embedder(text)`. All vectors of an index have the same dimension. Returns
+/// `upserted`, `total` and `dimension` as JSON.
+#[pyfunction]
+#[pyo3(signature = (index_path, items_json, embedder=None))]
+fn upsert_embeddings_py(
+    py: Python<'_>,
+    index_path: PathBuf,
+    items_json: &str,
+    embedder: Option<Py<PyAny>>,
+) -> PyResult<String> {
+    let items: Vec<EmbeddingItem> = from_json("items", items_json)?;
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let vector = match (item.vector, item.text) {
+                (Some(vector), _) => vector,
+                (None, Some(text)) => embed(py, embedder.as_ref(), &text)?,
+                (None, None) => {
+                    return Err(CdeError::invalid_input(format!(
+                        "Item {} has neither vector nor text",
+                        item.id
+                    ))
+                    .into())
+                }
+            };
+            Ok((item.id, vector, item.metadata))
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let report = py.detach(|| upsert_embeddings(&index_path, items))?;
+    Ok(to_json(&report)?)
+}
+
+/// The `k` items of the index at `index_path` most similar to `query`, a vector
+/// or a text embedded with `embedder`; JSON list of `id`, `score` (cosine
+/// similarity) and `metadata`, most similar first
+#[pyfunction]
+#[pyo3(signature = (index_path, query, k=10, embedder=None))]
+fn query_similar_py(
+    py: Python<'_>,
+    index_path: PathBuf,
+    query: Query,
+    k: usize,
+    embedder: Option<Py<PyAny>>,
+) -> PyResult<String> {
+    let vector = match query {
+        Query::Vector(vector) => vector,
+        Query::Text(text) => embed(py, embedder.as_ref(), &text)?,
+    };
+    let similar = py.detach(|| with_index(&index_path, |index| index.query(vector, k)))?;
+    Ok(to_json(&similar)?)
+}
+
+/// Adds the embedding index functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(upsert_embeddings_py, m)?)?;
+    m.add_function(wrap_pyfunction!(query_similar_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic spread of vectors
+    fn vector(i: usize) -> Vec<f32> {
+        (0..8)
+            .map(|d| ((i * 7 + d * 13) as f32 * 0.37).sin())
+            .collect()
+    }
+
+    fn brute_force(index: &HnswIndex, query: &[f32], k: usize) -> Vec<String> {
+        let query = normalized(query.to_vec()).unwrap();
+        let mut scored: Vec<(f32, &str)> = index
+            .ids
+            .iter()
+            .map(|(id, &node)| (dot(&query, &index.nodes[node].vector), id.as_str()))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, id)| id.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_upsert_query_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("specs.hnsw");
+        let items = (0..300)
+            .map(|i| (format!("doc-{}", i), vector(i), serde_json::json!({"n": i})))
+            .collect();
+        let report = upsert_embeddings(&path, items).unwrap();
+        assert_eq!((report.total, report.dimension), (300, Some(8)));
+
+        let index = HnswIndex::load(&path).unwrap();
+        let found = index.query(vector(42), 5).unwrap();
+        assert_eq!(found[0].id, "doc-42");
+        assert!((found[0].score - 1.0).abs() < 1e-5);
+        assert_eq!(found[0].metadata, serde_json::json!({"n": 42}));
+        let ids: Vec<String> = found.into_iter().map(|item| item.id).collect();
+        assert_eq!(ids, brute_force(&index, &vector(42), 5));
+
+        // Replacing moves doc-42 elsewhere; the old vector no longer matches it
+        let replaced = vec![("doc-42".to_string(), vector(1000), serde_json::Value::Null)];
+        assert_eq!(upsert_embeddings(&path, replaced).unwrap().total, 300);
+        let index = HnswIndex::load(&path).unwrap();
+        let found = index.query(vector(42), 300).unwrap();
+        assert_eq!(found.iter().filter(|item| item.id == "doc-42").count(), 1);
+        assert_eq!(index.query(vector(1000), 1).unwrap()[0].id, "doc-42");
+
+        let mut index = index;
+        assert!(index
+            .upsert("short".to_string(), vec![1.0], &serde_json::Value::Null)
+            .is_err());
+        assert!(index
+            .upsert("zero".to_string(), vec![0.0; 8], &serde_json::Value::Null)
+            .is_err());
+        assert!(HnswIndex::new().query(vector(1), 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_policy_confines_index_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("denied-index").join("specs.hnsw");
+        let item = ("a".to_string(), vector(1), serde_json::Value::Null);
+        let denied = path_policy::with_denied("denied-index", || {
+            [
+                upsert_embeddings(&path, vec![item]).err(),
+                with_index(&path, |index| index.query(vector(1), 1)).err(),
+            ]
+        });
+        for error in denied {
+            assert!(
+                matches!(error, Some(CdeError::InvalidInput(_))),
+                "{error:?}"
+            );
+        }
+        assert!(!path.exists());
+    }
+}
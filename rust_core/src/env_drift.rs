@@ -0,0 +1,530 @@
+// src/env_drift.rs
+//! Environment variables a project needs, compared with its `.env.example`
+//!
+//! Variables are collected from three places: code reading them (`os.getenv`,
+//! `os.environ[...]`, `env("...")` settings helpers, `process.env.X`,
+//! `import.meta.env.X`, `env::var`, `os.Getenv`, `ENV[...]`), configuration
+//! files interpolating them (`${X}` in YAML, TOML, INI, Compose files and shell
+//! scripts), and the local env files (`.env`, `.env.local`, ...). Every one of
+//! them missing from the example files (`.env.example`, `.env.sample`, ...) is
+//! reported, and so is every documented variable nothing reads. Only the names
+//! of env files are read out of them, never their values. Names must be upper
+//! case, as environment variables conventionally are, which keeps unrelated
+//! `config("...")` calls out.
+
+use crate::config_validator::ValidationIssue;
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvDriftOptions {
+    /// File names of the env files documenting the variables
+    pub example_files: Vec<String>,
+    /// Variables never reported: those the system or CI provides
+    pub ignore: Vec<String>,
+    pub max_file_size_bytes: u64,
+}
+
+impl Default for EnvDriftOptions {
+    fn default() -> Self {
+        Self {
+            example_files: [".env.example", ".env.sample", ".env.template", ".env.dist"]
+                .map(String::from)
+                .to_vec(),
+            ignore: [
+                "PATH", "HOME", "USER", "PWD", "SHELL", "TERM", "LANG", "TMPDIR", "CI",
+            ]
+            .map(String::from)
+            .to_vec(),
+            max_file_size_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvReference {
+    /// Relative to the root
+    pub file: String,
+    pub line: usize,
+    /// The code supplies a value when the variable is unset
+    pub has_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvVariable {
+    pub name: String,
+    /// Where code and configuration read it
+    pub references: Vec<EnvReference>,
+    /// Local env files setting it
+    pub defined_in: Vec<String>,
+    /// Listed in an example file
+    pub documented: bool,
+    /// Read, and every read supplies a default
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvDriftReport {
+    pub files_scanned: usize,
+    pub env_files: Vec<String>,
+    pub example_files: Vec<String>,
+    pub variables: Vec<EnvVariable>,
+    /// Read or set locally, but not documented
+    pub missing: Vec<String>,
+    /// Documented, but read nowhere
+    pub extra: Vec<String>,
+    pub issues: Vec<ValidationIssue>,
+    pub summary: String,
+    pub truncated: bool,
+}
+
+impl CapLists for EnvDriftReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.variables, max_items)
+            | cap(&mut self.missing, max_items)
+            | cap(&mut self.extra, max_items)
+            | cap(&mut self.issues, max_items);
+    }
+}
+
+/// Extensions of the code each reading pattern applies to; every pattern
+/// captures `name`, and `default` when a fallback value is given
+const CODE_PATTERNS: &[(&[&str], &str)] = &[
+    (
+        &["py"],
+        r#"\b(?:os\.)?(?:environ\.get|getenv)\(\s*["'](?P<name>[A-Z][A-Z0-9_]*)["']\s*(?P<default>,)?"#,
+    ),
+    (
+        &["py"],
+        r#"\benviron\[\s*["'](?P<name>[A-Z][A-Z0-9_]*)["']\s*\]"#,
+    ),
+    // django-environ and python-decouple settings: env("X"), env.bool("X"), config("X")
+    (
+        &["py"],
+        r#"\b(?:env|config)(?:\.\w+)?\(\s*["'](?P<name>[A-Z][A-Z0-9_]*)["']\s*(?P<default>,\s*default\s*=)?"#,
+    ),
+    (
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx", "vue", "svelte"],
+        r#"\b(?:process\.env|import\.meta\.env)(?:\.(?P<name>[A-Z][A-Z0-9_]*)\b|\[\s*["'](?P<quoted>[A-Z][A-Z0-9_]*)["']\s*\])(?P<default>\s*(?:\|\||\?\?))?"#,
+    ),
+    (
+        &["rs"],
+        r#"\benv::var(?:_os)?\(\s*"(?P<name>[A-Z][A-Z0-9_]*)"\s*\)(?P<default>\s*\.(?:unwrap_or|ok\(\)))?"#,
+    ),
+    (
+        &["rs"],
+        r#"\b(?P<default>option_)?env!\(\s*"(?P<name>[A-Z][A-Z0-9_]*)""#,
+    ),
+    (
+        &["go"],
+        r#"\bos\.(?:Getenv|(?P<default>LookupEnv))\(\s*"(?P<name>[A-Z][A-Z0-9_]*)""#,
+    ),
+    (
+        &["rb"],
+        r#"\bENV(?:\[\s*["'](?P<name>[A-Z][A-Z0-9_]*)["']\s*\]|\.fetch\(\s*["'](?P<quoted>[A-Z][A-Z0-9_]*)["']\s*(?P<default>,)?)"#,
+    ),
+    // Interpolation of configuration files and scripts: ${X}, ${X:-default}
+    (
+        &[
+            "yaml", "yml", "toml", "ini", "cfg", "conf", "env", "sh", "bash",
+        ],
+        r#"\$\{(?P<name>[A-Z][A-Z0-9_]*)(?P<default>:?[-=][^}]*)?\}"#,
+    ),
+];
+
+static PATTERNS: LazyLock<Vec<(&'static [&'static str], Regex)>> = LazyLock::new(|| {
+    CODE_PATTERNS
+        .iter()
+        .map(|(extensions, pattern)| (*extensions, Regex::new(pattern).unwrap()))
+        .collect()
+});
+
+static ENV_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:export\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*=").unwrap());
+
+/// Variable names set by the env file `content`, with their lines
+fn parse_env_file(content: &str) -> Vec<(String, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| Some((ENV_LINE.captures(line)?[1].to_string(), i + 1)))
+        .collect()
+}
+
+/// Variables read by `content`, a file with extension `extension`, with their
+/// lines and whether a default is given
+fn find_references(content: &str, extension: &str) -> Vec<(String, usize, bool)> {
+    let patterns: Vec<&Regex> = PATTERNS
+        .iter()
+        .filter(|(extensions, _)| extensions.contains(&extension))
+        .map(|(_, regex)| regex)
+        .collect();
+    if patterns.is_empty() {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        for regex in &patterns {
+            for caps in regex.captures_iter(line) {
+                let Some(name) = caps.name("name").or_else(|| caps.name("quoted")) else {
+                    continue;
+                };
+                found.push((
+                    name.as_str().to_string(),
+                    i + 1,
+                    caps.name("default").is_some(),
+                ));
+            }
+        }
+    }
+    found
+}
+
+fn variable<'a>(
+    variables: &'a mut BTreeMap<String, EnvVariable>,
+    name: &str,
+) -> &'a mut EnvVariable {
+    variables
+        .entry(name.to_string())
+        .or_insert_with(|| EnvVariable {
+            name: name.to_string(),
+            references: Vec::new(),
+            defined_in: Vec::new(),
+            documented: false,
+            optional: false,
+        })
+}
+
+fn is_env_file(name: &str) -> bool {
+    name == ".env" || name.starts_with(".env.")
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Compares the variables the project under `root` reads and sets with those
+/// its example env files document
+pub fn check_env_drift(root: &str, options: &EnvDriftOptions) -> Result<EnvDriftReport, CdeError> {
+    metrics::timed("check_env_drift", || {
+        let root_path = Path::new(root);
+        path_policy::check(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let guard = path_policy::walk_guard(root_path)?;
+
+        // Env files are hidden and usually ignored: they are looked up in the
+        // directories the walk visits instead of being walked
+        let mut sources: Vec<PathBuf> = Vec::new();
+        let mut env_paths: Vec<PathBuf> = Vec::new();
+        let walk_guard = guard.clone();
+        let walker = WalkBuilder::new(root_path)
+            .require_git(false)
+            .max_filesize(Some(options.max_file_size_bytes))
+            .max_depth(Some(guard.max_depth()))
+            .filter_entry(move |entry| walk_guard.allows(entry.path(), entry.path_is_symlink()))
+            .build();
+        for entry in walker.flatten() {
+            let Some(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_file() {
+                sources.push(entry.into_path());
+            } else if file_type.is_dir() {
+                let Ok(dir) = fs::read_dir(entry.path()) else {
+                    continue;
+                };
+                env_paths.extend(
+                    dir.flatten()
+                        .filter(|file| file.file_type().is_ok_and(|t| t.is_file()))
+                        .filter(|file| file.file_name().to_str().is_some_and(is_env_file))
+                        .map(|file| file.path())
+                        .filter(|path| guard.allows(path, false)),
+                );
+            }
+        }
+        env_paths.sort();
+
+        let references: Vec<(String, Vec<(String, usize, bool)>)> = sources
+            .par_iter()
+            .filter_map(|path| {
+                let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+                let content = fs::read_to_string(path).ok()?;
+                let found = find_references(&content, &extension);
+                (!found.is_empty()).then(|| (relative(root_path, path), found))
+            })
+            .collect();
+
+        let mut variables: BTreeMap<String, EnvVariable> = BTreeMap::new();
+        let mut env_files = Vec::new();
+        let mut example_files = Vec::new();
+        // First line documenting each variable, for the issues
+        let mut documented_at: BTreeMap<String, (String, usize)> = BTreeMap::new();
+        for path in &env_paths {
+            let file = relative(root_path, path);
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let is_example = options.example_files.iter().any(|example| example == name);
+            let Ok(content) = fs::read_to_string(path) else {
+                continue;
+            };
+            for (var, line) in parse_env_file(&content) {
+                let entry = variable(&mut variables, &var);
+                if is_example {
+                    entry.documented = true;
+                    documented_at.entry(var).or_insert((file.clone(), line));
+                } else if !entry.defined_in.contains(&file) {
+                    entry.defined_in.push(file.clone());
+                }
+            }
+            if is_example {
+                example_files.push(file);
+            } else {
+                env_files.push(file);
+            }
+        }
+        let files_scanned = sources.len() + env_paths.len();
+        for (file, found) in references {
+            for (name, line, has_default) in found {
+                variable(&mut variables, &name)
+                    .references
+                    .push(EnvReference {
+                        file: file.clone(),
+                        line,
+                        has_default,
+                    });
+            }
+        }
+
+        let ignored: BTreeSet<&str> = options.ignore.iter().map(String::as_str).collect();
+        variables.retain(|name, _| !ignored.contains(name.as_str()));
+        let mut missing = Vec::new();
+        let mut extra = Vec::new();
+        let mut issues = Vec::new();
+        let example = example_files
+            .first()
+            .map_or(".env.example", String::as_str)
+            .to_string();
+        for variable in variables.values_mut() {
+            variable
+                .references
+                .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+            variable.optional = !variable.references.is_empty()
+                && variable.references.iter().all(|r| r.has_default);
+            if variable.documented {
+                if variable.references.is_empty() {
+                    let (file, line) = documented_at[&variable.name].clone();
+                    issues.push(ValidationIssue {
+                        severity: "info".to_string(),
+                        file,
+                        line: Some(line),
+                        message: format!("{} is documented but nothing reads it", variable.name),
+                        rule: Some("env-unused".to_string()),
+                    });
+                    extra.push(variable.name.clone());
+                }
+                continue;
+            }
+            missing.push(variable.name.clone());
+            let (file, line, how) = match variable.references.first() {
+                Some(reference) => (reference.file.clone(), Some(reference.line), "read"),
+                None => (variable.defined_in[0].clone(), None, "set"),
+            };
+            issues.push(ValidationIssue {
+                severity: if variable.optional { "info" } else { "warning" }.to_string(),
+                file,
+                line,
+                message: format!("{} is {} but not listed in {}", variable.name, how, example),
+                rule: Some("env-undocumented".to_string()),
+            });
+        }
+        if example_files.is_empty() && !variables.is_empty() {
+            issues.insert(
+                0,
+                ValidationIssue {
+                    severity: "warning".to_string(),
+                    file: ".env.example".to_string(),
+                    line: None,
+                    message: "No example env file documents the environment variables".to_string(),
+                    rule: Some("env-example-missing".to_string()),
+                },
+            );
+        }
+
+        let summary = if missing.is_empty() && extra.is_empty() {
+            format!(
+                "✅ All {} environment variables are documented.",
+                variables.len()
+            )
+        } else {
+            format!(
+                "⚠️ {} environment variables are undocumented and {} documented ones are unused.",
+                missing.len(),
+                extra.len()
+            )
+        };
+        Ok(EnvDriftReport {
+            files_scanned,
+            env_files,
+            example_files,
+            variables: variables.into_values().collect(),
+            missing,
+            extra,
+            issues,
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+/// Compares the environment variables a project uses with its `.env.example`
+///
+/// Variables are those read by code (Python `os.getenv`/`os.environ`/`env()`
+/// settings, JS `process.env`/`import.meta.env`, Rust `env::var`, Go
+/// `os.Getenv`, Ruby `ENV`), interpolated by configuration files (`${X}`), and
+/// set by local env files (`.env`, `.env.local`, ...; their values are never
+/// read). `options_json` accepts `example_files` (default `.env.example`,
+/// `.env.sample`, `.env.template`, `.env.dist`), `ignore` (variable names) and
+/// `max_file_size_bytes`. Returns every `variables` entry with its references,
+/// `missing` (used but undocumented), `extra` (documented but unused) and
+/// `issues` that `validation_sarif_py` converts to SARIF.
+#[pyfunction]
+#[pyo3(signature = (root, options_json=None, max_list_items=None))]
+fn check_env_drift_py(
+    py: Python<'_>,
+    root: &str,
+    options_json: Option<&str>,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let options: EnvDriftOptions = match options_json {
+        Some(json) => from_json("options", json)?,
+        None => EnvDriftOptions::default(),
+    };
+    let mut report = py.detach(|| check_env_drift(root, &options))?;
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Adds the env drift function to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(check_env_drift_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_are_found_with_their_defaults() {
+        let python = "import os\nURL = os.environ['DATABASE_URL']\nDEBUG = os.getenv(\"DEBUG\", \"0\")\nKEY = env.str('SECRET_KEY')\n";
+        assert_eq!(
+            find_references(python, "py"),
+            vec![
+                ("DATABASE_URL".to_string(), 2, false),
+                ("DEBUG".to_string(), 3, true),
+                ("SECRET_KEY".to_string(), 4, false),
+            ]
+        );
+        let js = "const port = process.env.PORT || 3000;\nconst key = process.env['API_KEY'];\n";
+        assert_eq!(
+            find_references(js, "ts"),
+            vec![
+                ("PORT".to_string(), 1, true),
+                ("API_KEY".to_string(), 2, false)
+            ]
+        );
+        let compose =
+            "image: app\nenvironment:\n  - TOKEN=${TOKEN}\n  - LEVEL=${LOG_LEVEL:-info}\n";
+        assert_eq!(
+            find_references(compose, "yml"),
+            vec![
+                ("TOKEN".to_string(), 3, false),
+                ("LOG_LEVEL".to_string(), 4, true)
+            ]
+        );
+        assert!(find_references("os.getenv('lowercase')", "py").is_empty());
+    }
+
+    #[test]
+    fn test_env_files_give_names_only() {
+        let content = "# comment\nexport API_KEY=secret\n\nDEBUG = 1\nnot a variable\n";
+        assert_eq!(
+            parse_env_file(content),
+            vec![("API_KEY".to_string(), 2), ("DEBUG".to_string(), 4)]
+        );
+    }
+
+    #[test]
+    fn test_drift_reports_missing_and_extra_variables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("config")).unwrap();
+        fs::write(
+            root.join("settings.py"),
+            "import os\nDB = os.environ['DATABASE_URL']\nHOME = os.getenv('HOME')\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("config/app.yaml"),
+            "cache: ${REDIS_URL:-redis://localhost}\n",
+        )
+        .unwrap();
+        fs::write(root.join(".env.example"), "DATABASE_URL=\nOLD_FLAG=\n").unwrap();
+        fs::write(
+            root.join(".env"),
+            "DATABASE_URL=postgres://\nLOCAL_ONLY=1\n",
+        )
+        .unwrap();
+        fs::write(root.join(".gitignore"), ".env\n").unwrap();
+
+        let report = check_env_drift(root.to_str().unwrap(), &EnvDriftOptions::default()).unwrap();
+        assert_eq!(report.example_files, vec![".env.example"]);
+        assert_eq!(report.env_files, vec![".env"]);
+        assert_eq!(report.missing, vec!["LOCAL_ONLY", "REDIS_URL"]);
+        assert_eq!(report.extra, vec!["OLD_FLAG"]);
+        let redis = report
+            .variables
+            .iter()
+            .find(|variable| variable.name == "REDIS_URL")
+            .unwrap();
+        assert!(redis.optional);
+        assert_eq!(redis.references[0].file, "config/app.yaml");
+        let database = report
+            .variables
+            .iter()
+            .find(|variable| variable.name == "DATABASE_URL")
+            .unwrap();
+        assert!(database.documented);
+        assert_eq!(database.defined_in, vec![".env"]);
+        // HOME is ignored by default
+        assert!(report
+            .variables
+            .iter()
+            .all(|variable| variable.name != "HOME"));
+        let undocumented = report
+            .issues
+            .iter()
+            .find(|issue| issue.message.starts_with("LOCAL_ONLY"))
+            .unwrap();
+        assert_eq!(undocumented.severity, "warning");
+        assert_eq!(undocumented.file, ".env");
+    }
+}
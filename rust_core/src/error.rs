@@ -0,0 +1,322 @@
+// src/error.rs
+//! Errors of the core, and the Python exceptions they become
+//!
+//! Every binding raises a subclass of `cde_rust_core.CdeError`, itself a
+//! `ValueError` so existing `except ValueError` handlers keep working. The
+//! exception instance carries the structured context as attributes: `kind`,
+//! `path`, `line` and `causes` (the underlying errors, outermost first).
+
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+pub mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyValueError;
+
+    create_exception!(
+        cde_rust_core,
+        CdeError,
+        PyValueError,
+        "Base class of the core's errors."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeIoError,
+        CdeError,
+        "A file or process could not be read, written or run."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeParseError,
+        CdeError,
+        "Malformed input data (JSON, YAML, Markdown, ...)."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeGitError,
+        CdeError,
+        "A Git operation failed."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeTimeoutError,
+        CdeError,
+        "An operation did not finish in time."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeInvalidInputError,
+        CdeError,
+        "An argument or option was rejected."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeNotFoundError,
+        CdeError,
+        "A path, revision or process does not exist."
+    );
+    create_exception!(
+        cde_rust_core,
+        CdeSerializationError,
+        CdeError,
+        "A result could not be serialized."
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CdeError {
+    Io(ErrorContext),
+    Parse(ErrorContext),
+    Git(ErrorContext),
+    Timeout(ErrorContext),
+    InvalidInput(ErrorContext),
+    NotFound(ErrorContext),
+    Serialization(ErrorContext),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ErrorContext {
+    pub message: String,
+    pub path: Option<String>,
+    pub line: Option<usize>,
+    /// Underlying errors, outermost first
+    pub causes: Vec<String>,
+}
+
+impl CdeError {
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::Io(ErrorContext::new(message))
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::Parse(ErrorContext::new(message))
+    }
+
+    pub fn git(message: impl Into<String>) -> Self {
+        Self::Git(ErrorContext::new(message))
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::Timeout(ErrorContext::new(message))
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput(ErrorContext::new(message))
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(ErrorContext::new(message))
+    }
+
+    pub fn serialization(message: impl Into<String>) -> Self {
+        Self::Serialization(ErrorContext::new(message))
+    }
+
+    /// For a root argument that is missing (`NotFound`) or not a directory
+    /// (`InvalidInput`)
+    pub fn not_a_directory(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let error = if path.exists() {
+            Self::invalid_input("Not a directory")
+        } else {
+            Self::not_found("Directory does not exist")
+        };
+        error.with_path(path)
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.context_mut().path = Some(path.as_ref().to_string_lossy().replace('\\', "/"));
+        self
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.context_mut().line = Some(line);
+        self
+    }
+
+    /// Appends `cause` (and its own `source()` chain) to the causes
+    pub fn caused_by(mut self, cause: &(dyn std::error::Error + 'static)) -> Self {
+        let causes = &mut self.context_mut().causes;
+        let mut next = Some(cause);
+        while let Some(error) = next {
+            causes.push(error.to_string());
+            next = error.source();
+        }
+        self
+    }
+
+    /// "io", "parse", "git", "timeout", "invalid_input", "not_found" or
+    /// "serialization"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "io",
+            Self::Parse(_) => "parse",
+            Self::Git(_) => "git",
+            Self::Timeout(_) => "timeout",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::NotFound(_) => "not_found",
+            Self::Serialization(_) => "serialization",
+        }
+    }
+
+    pub fn context(&self) -> &ErrorContext {
+        match self {
+            Self::Io(context)
+            | Self::Parse(context)
+            | Self::Git(context)
+            | Self::Timeout(context)
+            | Self::InvalidInput(context)
+            | Self::NotFound(context)
+            | Self::Serialization(context) => context,
+        }
+    }
+
+    fn context_mut(&mut self) -> &mut ErrorContext {
+        match self {
+            Self::Io(context)
+            | Self::Parse(context)
+            | Self::Git(context)
+            | Self::Timeout(context)
+            | Self::InvalidInput(context)
+            | Self::NotFound(context)
+            | Self::Serialization(context) => context,
+        }
+    }
+}
+
+impl ErrorContext {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// "message (path:line): cause: cause"
+impl fmt::Display for CdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let context = self.context();
+        write!(f, "{}", context.message)?;
+        match (&context.path, context.line) {
+            (Some(path), Some(line)) => write!(f, " ({}:{})", path, line)?,
+            (Some(path), None) => write!(f, " ({})", path)?,
+            _ => {}
+        }
+        for cause in &context.causes {
+            write!(f, ": {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CdeError {}
+
+impl From<CdeError> for PyErr {
+    fn from(error: CdeError) -> Self {
+        use exceptions::{
+            CdeGitError, CdeInvalidInputError, CdeIoError, CdeNotFoundError, CdeParseError,
+            CdeSerializationError, CdeTimeoutError,
+        };
+        let message = error.to_string();
+        let err = match &error {
+            CdeError::Io(_) => CdeIoError::new_err(message),
+            CdeError::Parse(_) => CdeParseError::new_err(message),
+            CdeError::Git(_) => CdeGitError::new_err(message),
+            CdeError::Timeout(_) => CdeTimeoutError::new_err(message),
+            CdeError::InvalidInput(_) => CdeInvalidInputError::new_err(message),
+            CdeError::NotFound(_) => CdeNotFoundError::new_err(message),
+            CdeError::Serialization(_) => CdeSerializationError::new_err(message),
+        };
+        Python::attach(|py| {
+            let value = err.value(py);
+            let context = error.context();
+            // Setting attributes on a fresh exception instance cannot fail
+            let _ = value.setattr("kind", error.kind());
+            let _ = value.setattr("path", context.path.clone());
+            let _ = value.setattr("line", context.line);
+            let _ = value.setattr("causes", context.causes.clone());
+        });
+        err
+    }
+}
+
+/// Serializes a binding's result, as every `*_py` function returns JSON
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, CdeError> {
+    serde_json::to_string(value)
+        .map_err(|e| CdeError::serialization("Failed to serialize result").caused_by(&e))
+}
+
+/// Converts a binding's result to native Python objects, for the `*_dict`
+/// variants that skip the JSON round trip
+pub fn to_py<'py, T: Serialize + ?Sized>(
+    py: Python<'py>,
+    value: &T,
+) -> Result<Bound<'py, PyAny>, CdeError> {
+    pythonize::pythonize(py, value)
+        .map_err(|e| CdeError::serialization("Failed to convert result").caused_by(&e))
+}
+
+/// Parses a JSON argument named `what` ("options", "paths", ...)
+pub fn from_json<T: serde::de::DeserializeOwned>(what: &str, json: &str) -> Result<T, CdeError> {
+    serde_json::from_str(json).map_err(|e| {
+        CdeError::invalid_input(format!("Invalid {}", what))
+            .with_line(e.line())
+            .caused_by(&e)
+    })
+}
+
+/// Adds the exception classes to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("CdeError", py.get_type::<exceptions::CdeError>())?;
+    m.add("CdeIoError", py.get_type::<exceptions::CdeIoError>())?;
+    m.add("CdeParseError", py.get_type::<exceptions::CdeParseError>())?;
+    m.add("CdeGitError", py.get_type::<exceptions::CdeGitError>())?;
+    m.add(
+        "CdeTimeoutError",
+        py.get_type::<exceptions::CdeTimeoutError>(),
+    )?;
+    m.add(
+        "CdeInvalidInputError",
+        py.get_type::<exceptions::CdeInvalidInputError>(),
+    )?;
+    m.add(
+        "CdeNotFoundError",
+        py.get_type::<exceptions::CdeNotFoundError>(),
+    )?;
+    m.add(
+        "CdeSerializationError",
+        py.get_type::<exceptions::CdeSerializationError>(),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_context_and_cause_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let error = CdeError::io("Failed to read workflow")
+            .with_path(Path::new("a\\b.yml"))
+            .with_line(3)
+            .caused_by(&io);
+        assert_eq!(error.kind(), "io");
+        assert_eq!(error.context().path.as_deref(), Some("a/b.yml"));
+        assert_eq!(
+            error.to_string(),
+            "Failed to read workflow (a/b.yml:3): no such file"
+        );
+
+        let parsed = from_json::<Vec<String>>("paths", "[1]").unwrap_err();
+        assert!(matches!(parsed, CdeError::InvalidInput(_)));
+        assert_eq!(parsed.context().line, Some(1));
+        assert!(parsed
+            .to_string()
+            .starts_with("Invalid paths: invalid type"));
+    }
+}
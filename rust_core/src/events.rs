@@ -0,0 +1,345 @@
+// src/events.rs
+//! Bus of the events of the core's background activity
+//!
+//! Subsystems working on their own threads publish what happens there: the
+//! index watcher ("watcher.changed", "watcher.rebuilt"), the process registry
+//! ("process.started", "process.output", "process.exited", "process.stalled",
+//! "process.active"), the agent scheduler ("job.queued", "job.started",
+//! "job.failed", "job.cancelled") and the task engine ("task.<status>"). Every
+//! subscriber has a bounded queue of its own, filled by the publishing thread
+//! without waiting for Python; once a queue is full its oldest events are
+//! dropped and counted. While nobody subscribes, publishing costs one atomic
+//! load.
+
+use crate::error::{from_json, to_json};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// Events a subscription queues unless told otherwise
+pub const DEFAULT_MAX_QUEUED: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Increasing across the process, gaps show events a queue dropped
+    pub seq: u64,
+    /// RFC 3339
+    pub at: String,
+    /// Publishing subsystem: "watcher", "process", "scheduler", "task_engine"
+    pub source: String,
+    pub kind: String,
+    pub data: Value,
+}
+
+/// Which events a subscription receives; empty lists match everything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventFilter {
+    pub sources: Vec<String>,
+    /// Exact kinds, or prefixes ending with `*` ("process.*")
+    pub kinds: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, source: &str, kind: &str) -> bool {
+        let source_matches = self.sources.is_empty() || self.sources.iter().any(|s| s == source);
+        let kind_matches = self.kinds.is_empty()
+            || self
+                .kinds
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => kind.starts_with(prefix),
+                    None => kind == pattern,
+                });
+        source_matches && kind_matches
+    }
+}
+
+#[derive(Default)]
+struct Pending {
+    events: VecDeque<Event>,
+    dropped: u64,
+    closed: bool,
+}
+
+struct Queue {
+    filter: EventFilter,
+    max_queued: usize,
+    pending: Mutex<Pending>,
+    available: Condvar,
+}
+
+impl Queue {
+    fn push(&self, event: &Event) {
+        if !self.filter.matches(&event.source, &event.kind) {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        if pending.closed {
+            return;
+        }
+        if pending.events.len() >= self.max_queued {
+            pending.events.pop_front();
+            pending.dropped += 1;
+        }
+        pending.events.push_back(event.clone());
+        self.available.notify_all();
+    }
+}
+
+static SUBSCRIBERS: LazyLock<Mutex<Vec<Weak<Queue>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// Live subscriptions, checked before building an event
+static SUBSCRIBED: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// Whether anybody listens; publishers with costly payloads check it first
+pub fn has_subscribers() -> bool {
+    SUBSCRIBED.load(Ordering::Relaxed) > 0
+}
+
+/// Hands an event to every subscription whose filter matches it
+pub fn publish(source: &str, kind: &str, data: impl Serialize) {
+    if !has_subscribers() {
+        return;
+    }
+    let event = Event {
+        seq: NEXT_SEQ.fetch_add(1, Ordering::Relaxed),
+        at: chrono::Local::now().to_rfc3339(),
+        source: source.to_string(),
+        kind: kind.to_string(),
+        data: serde_json::to_value(data).unwrap_or(Value::Null),
+    };
+    let queues: Vec<Arc<Queue>> = SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect();
+    for queue in queues {
+        queue.push(&event);
+    }
+}
+
+/// Queue of the events matching a filter, from its creation until it is
+/// closed or dropped
+pub struct Subscription {
+    queue: Arc<Queue>,
+}
+
+impl Subscription {
+    pub fn new(filter: EventFilter, max_queued: usize) -> Self {
+        let queue = Arc::new(Queue {
+            filter,
+            max_queued: max_queued.max(1),
+            pending: Mutex::new(Pending::default()),
+            available: Condvar::new(),
+        });
+        let mut subscribers = SUBSCRIBERS.lock().unwrap();
+        subscribers.retain(|weak| weak.strong_count() > 0);
+        subscribers.push(Arc::downgrade(&queue));
+        SUBSCRIBED.store(subscribers.len(), Ordering::Relaxed);
+        Self { queue }
+    }
+
+    /// Next queued event, waiting up to `timeout` for one
+    pub fn next(&self, timeout: Duration) -> Option<Event> {
+        let deadline = Instant::now() + timeout;
+        let mut pending = self.queue.pending.lock().unwrap();
+        loop {
+            if let Some(event) = pending.events.pop_front() {
+                return Some(event);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if pending.closed || left.is_zero() {
+                return None;
+            }
+            pending = self.queue.available.wait_timeout(pending, left).unwrap().0;
+        }
+    }
+
+    /// Queued events, oldest first, at most `max` of them
+    pub fn drain(&self, max: Option<usize>) -> Vec<Event> {
+        let mut pending = self.queue.pending.lock().unwrap();
+        let count = max.map_or(pending.events.len(), |max| max.min(pending.events.len()));
+        pending.events.drain(..count).collect()
+    }
+
+    /// Events dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.queue.pending.lock().unwrap().dropped
+    }
+
+    /// Stops receiving events; those queued can still be drained
+    pub fn close(&self) {
+        self.queue.pending.lock().unwrap().closed = true;
+        self.queue.available.notify_all();
+        let mut subscribers = SUBSCRIBERS.lock().unwrap();
+        subscribers.retain(|weak| {
+            weak.upgrade()
+                .is_some_and(|queue| !Arc::ptr_eq(&queue, &self.queue))
+        });
+        SUBSCRIBED.store(subscribers.len(), Ordering::Relaxed);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.pending.lock().unwrap().closed
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Events of the core received by a subscription
+///
+/// Iterating yields the queued events (as JSON strings) until the queue is
+/// empty; `get` waits for the next one.
+#[pyclass]
+pub struct EventSubscription {
+    subscription: Subscription,
+}
+
+#[pymethods]
+impl EventSubscription {
+    /// Next event as JSON, waiting up to `timeout_secs` for one; None on timeout
+    /// or once the subscription is closed and drained
+    #[pyo3(signature = (timeout_secs=0.0))]
+    fn get(&self, py: Python<'_>, timeout_secs: f64) -> PyResult<Option<String>> {
+        let timeout = Duration::try_from_secs_f64(timeout_secs).unwrap_or(Duration::ZERO);
+        let event = py.detach(|| self.subscription.next(timeout));
+        Ok(event.map(|event| to_json(&event)).transpose()?)
+    }
+
+    /// Queued events as a JSON list, oldest first, at most `max_events`
+    #[pyo3(signature = (max_events=None))]
+    fn drain(&self, max_events: Option<usize>) -> PyResult<String> {
+        Ok(to_json(&self.subscription.drain(max_events))?)
+    }
+
+    /// Stops receiving events; those queued can still be drained
+    fn close(&self) {
+        self.subscription.close();
+    }
+
+    /// Events dropped because the queue was full
+    #[getter]
+    fn dropped(&self) -> u64 {
+        self.subscription.dropped()
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.subscription.is_closed()
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self) -> PyResult<Option<String>> {
+        Ok(self
+            .subscription
+            .next(Duration::ZERO)
+            .map(|event| to_json(&event))
+            .transpose()?)
+    }
+}
+
+/// Subscribe to the events of the core's background activity
+///
+/// `filter_json` selects events by `sources` ("watcher", "process",
+/// "scheduler", "task_engine") and `kinds` (exact, or prefixes ending with `*`
+/// such as "process.*"); events are received from the call on. Each event is
+/// JSON with `seq`, `at`, `source`, `kind` and `data`. The subscription keeps
+/// at most `max_queued` events; older ones are dropped and counted in
+/// `dropped`. Drain it with `drain()`, `get(timeout_secs)` or by iterating, and
+/// `close()` it when done.
+#[pyfunction]
+#[pyo3(signature = (filter_json=None, max_queued=DEFAULT_MAX_QUEUED))]
+fn subscribe_events_py(
+    filter_json: Option<&str>,
+    max_queued: usize,
+) -> PyResult<EventSubscription> {
+    let filter = filter_json
+        .map(|json| from_json::<EventFilter>("event filter", json))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(EventSubscription {
+        subscription: Subscription::new(filter, max_queued),
+    })
+}
+
+/// Adds the event bus functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<EventSubscription>()?;
+    m.add_function(wrap_pyfunction!(subscribe_events_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_subscriptions_receive_matching_events() {
+        let processes = Subscription::new(
+            EventFilter {
+                kinds: vec!["test.process.*".to_string()],
+                ..EventFilter::default()
+            },
+            2,
+        );
+        let everything = Subscription::new(EventFilter::default(), 100);
+        assert!(has_subscribers());
+
+        publish("process", "test.process.started", json!({"pid": 1}));
+        publish("task_engine", "test.task.running", json!({"task_id": "a"}));
+        publish("process", "test.process.exited", json!({"pid": 1}));
+        publish("process", "test.process.exited", json!({"pid": 2}));
+
+        // Other tests publish too: only look at this test's kinds
+        let mine: Vec<Event> = everything
+            .drain(None)
+            .into_iter()
+            .filter(|event| event.kind.starts_with("test."))
+            .collect();
+        assert_eq!(mine.len(), 4);
+        assert!(mine.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+
+        assert_eq!(processes.dropped(), 1);
+        let exited = processes.drain(None);
+        assert_eq!(exited.len(), 2);
+        assert_eq!(exited[1].data["pid"], 2);
+        assert_eq!(processes.next(Duration::from_millis(10)), None);
+
+        processes.close();
+        publish("process", "test.process.started", json!({"pid": 3}));
+        assert!(processes.drain(None).is_empty());
+    }
+
+    #[test]
+    fn test_next_waits_for_a_publisher() {
+        let subscription = Subscription::new(
+            EventFilter {
+                sources: vec!["test_waiter".to_string()],
+                ..EventFilter::default()
+            },
+            10,
+        );
+        std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            publish("test_waiter", "ping", json!(null));
+        });
+        let event = subscription.next(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.kind, "ping");
+    }
+}
@@ -1,14 +1,25 @@
 // src/filesystem.rs
+use crate::error::CdeError;
+use crate::path_policy;
+use crate::snapshot::modified_ms;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use walkdir::WalkDir;
 use rayon::prelude::*;
 
-/// Finds all Markdown files in a directory in parallel, excluding common directories.
+/// Finds all Markdown files in a directory in parallel, excluding common directories
+/// and whatever the path policy denies.
 pub fn find_markdown_files(root_path: &Path) -> Vec<String> {
     let excluded_dirs = [".git", ".venv", "node_modules", "venv", "__pycache__", ".pytest_cache", "target"];
+    let Ok(guard) = path_policy::walk_guard(root_path) else {
+        return Vec::new();
+    };
 
     WalkDir::new(root_path)
+        .max_depth(guard.max_depth())
         .into_iter()
+        .filter_entry(|e| guard.allows(e.path(), e.path_is_symlink()))
         .filter_map(Result::ok)
         .par_bridge() // Process entries in parallel
         .filter(|e| {
@@ -25,3 +36,137 @@ pub fn find_markdown_files(root_path: &Path) -> Vec<String> {
         .map(|e| e.path().to_string_lossy().into_owned())
         .collect()
 }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMatch {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified_ms: u64,
+}
+
+/// Compiles `patterns`: a glob with a `/` matches the path relative to the root
+/// ("src/**/*.rs"), one without matches the file name ("*.md"). `{a,b}`
+/// alternatives are supported.
+pub(crate) fn compile_patterns(patterns: &[String], case_insensitive: bool) -> Result<(GlobSet, GlobSet), CdeError> {
+    let mut paths = GlobSetBuilder::new();
+    let mut names = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_start_matches("./"))
+            .literal_separator(true)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| {
+                CdeError::invalid_input(format!("Invalid file pattern: {}", pattern)).caused_by(&e)
+            })?;
+        if pattern.contains('/') {
+            paths.add(glob);
+        } else {
+            names.add(glob);
+        }
+    }
+    let build = |builder: GlobSetBuilder| {
+        builder
+            .build()
+            .map_err(|e| CdeError::invalid_input("Invalid file patterns").caused_by(&e))
+    };
+    Ok((build(paths)?, build(names)?))
+}
+
+/// Finds the files under `root_path` matching one of the glob `patterns`, in
+/// parallel, with their size and modification time. Returns them sorted by path.
+pub fn find_matching_files(
+    root_path: &str,
+    patterns: &[String],
+    case_insensitive: bool,
+) -> Result<Vec<FileMatch>, CdeError> {
+    let root = Path::new(root_path);
+    let guard = path_policy::walk_guard(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    let (paths, names) = compile_patterns(patterns, case_insensitive)?;
+
+    let mut files: Vec<FileMatch> = WalkDir::new(root)
+        .max_depth(guard.max_depth())
+        .into_iter()
+        .filter_entry(|e| guard.allows(e.path(), e.path_is_symlink()))
+        .filter_map(Result::ok)
+        .par_bridge()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(root).ok()?;
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if !paths.is_match(&relative) && !names.is_match(e.file_name()) {
+                return None;
+            }
+            let metadata = e.metadata().ok()?;
+            Some(FileMatch {
+                path: relative,
+                size: metadata.len(),
+                modified_ms: modified_ms(&metadata),
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Paths of `find_matching_files`, sorted
+pub fn find_files(root_path: &str, patterns: &[String], case_insensitive: bool) -> Result<Vec<String>, CdeError> {
+    let files = find_matching_files(root_path, patterns, case_insensitive)?;
+    Ok(files.into_iter().map(|file| file.path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_files_matches_names_by_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("docs/specs")).unwrap();
+        for file in ["README.md", "docs/specs/api.md", "docs/notes.txt", "Cargo.toml"] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+        let root = dir.path().to_str().unwrap();
+
+        let found = find_files(root, &["*.md".to_string(), "Cargo.*".to_string()], false).unwrap();
+        assert_eq!(found, vec!["Cargo.toml", "README.md", "docs/specs/api.md"]);
+        assert!(matches!(
+            find_files(root, &["[".to_string()], false),
+            Err(CdeError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_files_matches_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/core/deep")).unwrap();
+        fs::create_dir(dir.path().join("tests")).unwrap();
+        for file in ["src/lib.rs", "src/core/deep/mod.rs", "src/core/build.py", "tests/lib.rs", "src/NOTES.MD"] {
+            fs::write(dir.path().join(file), "abc").unwrap();
+        }
+        let root = dir.path().to_str().unwrap();
+        let find = |patterns: &[&str], case_insensitive: bool| {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            find_files(root, &patterns, case_insensitive).unwrap()
+        };
+
+        assert_eq!(find(&["src/**/*.rs"], false), vec!["src/core/deep/mod.rs", "src/lib.rs"]);
+        // A single star stays within one directory
+        assert_eq!(find(&["src/*.rs"], false), vec!["src/lib.rs"]);
+        assert_eq!(
+            find(&["./src/core/**/*.{rs,py}"], false),
+            vec!["src/core/build.py", "src/core/deep/mod.rs"]
+        );
+        assert!(find(&["src/*.md"], false).is_empty());
+        assert_eq!(find(&["src/*.md"], true), vec!["src/NOTES.MD"]);
+
+        let matches = find_matching_files(root, &["tests/*".to_string()], false).unwrap();
+        assert_eq!((matches[0].path.as_str(), matches[0].size), ("tests/lib.rs", 3));
+        assert!(matches[0].modified_ms > 0);
+    }
+}
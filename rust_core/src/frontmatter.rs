@@ -0,0 +1,576 @@
+// src/frontmatter.rs
+//! Reading frontmatter, and editing YAML frontmatter without rewriting it
+//!
+//! A frontmatter block opens on the first line of the document, after an
+//! optional BOM: `---` for YAML, closed by `---` or `...`, or `+++` for TOML,
+//! closed by `+++`. Delimiter lines may end in spaces or `\r\n`. Every reader
+//! of metadata in the crate finds the block with `parse` or `body`.
+//!
+//! When editing, only the top-level entries being changed are rendered again;
+//! every other line (comments, blank lines, key order, untouched values) is
+//! kept byte for byte. A changed scalar keeps its quoting style and its
+//! trailing comment, a flow collection (`[a, b]`) stays a flow collection, and
+//! setting a key to the value it already has changes nothing. New keys are
+//! appended at the end.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::patch;
+use crate::path_policy;
+use pyo3::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrontmatterUpdate {
+    pub path: String,
+    /// Whether the file was rewritten
+    pub changed: bool,
+    pub updated: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Yaml,
+    Toml,
+}
+
+/// Byte offsets of a frontmatter block: `content[..open]` is the BOM and the
+/// opening line, `content[open..close]` the frontmatter and
+/// `content[close..body]` the closing line
+struct Bounds {
+    format: Format,
+    open: usize,
+    close: usize,
+    body: usize,
+}
+
+fn bounds(content: &str) -> Option<Bounds> {
+    let bom = if content.starts_with('\u{feff}') {
+        3
+    } else {
+        0
+    };
+    let open = content[bom..].find('\n')? + bom + 1;
+    let format = match content[bom..open].trim_end() {
+        "---" => Format::Yaml,
+        "+++" => Format::Toml,
+        _ => return None,
+    };
+    let mut offset = open;
+    for line in content[open..].split_inclusive('\n') {
+        let closing = match format {
+            Format::Yaml => matches!(line.trim_end(), "---" | "..."),
+            Format::Toml => line.trim_end() == "+++",
+        };
+        if closing {
+            return Some(Bounds {
+                format,
+                open,
+                close: offset,
+                body: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The frontmatter of `content` as a `T`; `None` without frontmatter or when it
+/// does not parse as a `T`
+pub(crate) fn parse<T: DeserializeOwned>(content: &str) -> Option<T> {
+    let bounds = bounds(content)?;
+    let source = &content[bounds.open..bounds.close];
+    match bounds.format {
+        Format::Yaml => serde_yaml::from_str(source).ok(),
+        Format::Toml => {
+            let table: toml::Table = toml::from_str(source).ok()?;
+            serde_yaml::from_value(toml_to_yaml(toml::Value::Table(table))).ok()
+        }
+    }
+}
+
+/// TOML dates have no YAML counterpart and become strings, as unquoted YAML
+/// dates do
+fn toml_to_yaml(value: toml::Value) -> serde_yaml::Value {
+    match value {
+        toml::Value::String(s) => serde_yaml::Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(datetime) => serde_yaml::Value::String(datetime.to_string()),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_yaml).collect(),
+        toml::Value::Table(table) => serde_yaml::Value::Mapping(
+            table
+                .into_iter()
+                .map(|(key, value)| (serde_yaml::Value::String(key), toml_to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// `content` without its frontmatter, if any
+pub(crate) fn body(content: &str) -> &str {
+    match bounds(content) {
+        Some(bounds) => &content[bounds.body..],
+        None => content,
+    }
+}
+
+/// A file split around its YAML frontmatter: `head` holds the BOM and the
+/// opening `---` line, `lines` the frontmatter lines with their endings, `tail`
+/// the closing line and the body
+struct Split<'a> {
+    head: &'a str,
+    lines: Vec<&'a str>,
+    tail: &'a str,
+}
+
+fn split(content: &str) -> Option<Split<'_>> {
+    let bounds = bounds(content).filter(|bounds| bounds.format == Format::Yaml)?;
+    Some(Split {
+        head: &content[..bounds.open],
+        lines: content[bounds.open..bounds.close]
+            .split_inclusive('\n')
+            .collect(),
+        tail: &content[bounds.close..],
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Plain,
+    Single,
+    Double,
+    /// `[...]` or `{...}` on the key line
+    Flow,
+    /// On the lines below the key
+    Block,
+}
+
+/// A top-level `key: value` entry spanning `lines[start..end]`
+struct Entry {
+    key: String,
+    start: usize,
+    end: usize,
+    style: Style,
+    /// Trailing comment of the key line, with the spaces before it
+    comment: String,
+}
+
+/// Key of a top-level entry line
+fn entry_key(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t', '#', '-', '\r', '\n']) || line.is_empty() {
+        return None;
+    }
+    let (key, quoted) = match line.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = line[1..].find(quote)? + 1;
+            (&line[1..close], &line[close + 1..])
+        }
+        _ => {
+            let colon = line.find(": ").or_else(|| {
+                let trimmed = line.trim_end();
+                trimmed.ends_with(':').then(|| trimmed.len() - 1)
+            })?;
+            (line[..colon].trim_end(), &line[colon..])
+        }
+    };
+    quoted.starts_with(':').then(|| key.to_string())
+}
+
+/// Position of the ` #` starting a comment in `value`, outside quotes
+fn comment_start(value: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in value.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && previous == ' ' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return Some(index),
+            None => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with([' ', '\t']) || line.trim_end() == "-" || line.starts_with("- ")
+}
+
+fn entries(lines: &[&str]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let Some(key) = entry_key(lines[index]) else {
+            index += 1;
+            continue;
+        };
+        let line = lines[index].trim_end_matches(['\r', '\n']);
+        let colon = line.find(':').map_or(line.len(), |c| {
+            // The colon after a quoted key
+            if line.starts_with(['"', '\'']) {
+                line[1..].find(&line[..1]).map_or(c, |close| close + 2)
+            } else {
+                c
+            }
+        });
+        let rest = &line[(colon + 1).min(line.len())..];
+        let (value, comment) = match comment_start(rest) {
+            Some(at) => {
+                let value = rest[..at].trim_end();
+                (value, rest[value.len()..].to_string())
+            }
+            None => (rest, String::new()),
+        };
+        let value = value.trim();
+        let style = match value.chars().next() {
+            None | Some('|') | Some('>') => Style::Block,
+            Some('\'') => Style::Single,
+            Some('"') => Style::Double,
+            Some('[') | Some('{') => Style::Flow,
+            _ => Style::Plain,
+        };
+        let mut end = index + 1;
+        loop {
+            // Blank lines inside a block value, not after it
+            let next = (end..lines.len()).find(|&at| !lines[at].trim().is_empty());
+            match next {
+                Some(at) if is_continuation(lines[at]) => end = at + 1,
+                _ => break,
+            }
+        }
+        entries.push(Entry {
+            key,
+            start: index,
+            end,
+            style,
+            comment,
+        });
+        index = end;
+    }
+    entries
+}
+
+/// `key` as it must be written
+fn render_key(key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c) || c.is_whitespace())
+        && !key.contains(": ")
+        && !key.contains(" #")
+        && !key.ends_with([':', ' ']);
+    if plain {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_default()
+    }
+}
+
+/// Whether `text` reads back as the same string when written unquoted
+fn plain_safe(text: &str) -> bool {
+    !text.is_empty()
+        && !text.contains('\n')
+        && text.trim() == text
+        && serde_yaml::from_str::<serde_yaml::Value>(&format!("k: {}", text))
+            .ok()
+            .and_then(|value| value.get("k").cloned())
+            == Some(serde_yaml::Value::String(text.to_string()))
+}
+
+/// `value` as a flow collection or scalar, items unquoted where they can be
+fn render_flow(value: &Value) -> String {
+    match value {
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(render_flow).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("{}: {}", render_key(key), render_flow(value)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Value::String(text) if plain_safe(text) && !text.contains([',', '[', ']', '{', '}']) => {
+            text.clone()
+        }
+        _ => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+/// Lines of the entry `key: value`, ended with `newline`
+fn render_entry(key: &str, value: &Value, style: Style, comment: &str, newline: &str) -> String {
+    let key = render_key(key);
+    let inline = |text: String| format!("{}: {}{}{}", key, text, comment, newline);
+    match value {
+        Value::String(text) => match style {
+            Style::Single if !text.contains('\n') => {
+                inline(format!("'{}'", text.replace('\'', "''")))
+            }
+            Style::Plain | Style::Block | Style::Flow if plain_safe(text) => inline(text.clone()),
+            _ => inline(serde_json::to_string(text).unwrap_or_default()),
+        },
+        Value::Array(items) if items.is_empty() => inline("[]".to_string()),
+        Value::Object(fields) if fields.is_empty() => inline("{}".to_string()),
+        Value::Array(_) | Value::Object(_) if style == Style::Flow => inline(render_flow(value)),
+        Value::Array(_) | Value::Object(_) => {
+            let yaml = serde_yaml::to_string(value).unwrap_or_default();
+            let mut lines = format!("{}:{}{}", key, comment, newline);
+            for line in yaml.trim_start_matches("---\n").lines() {
+                lines.push_str(&format!("  {}{}", line, newline));
+            }
+            lines
+        }
+        _ => inline(value.to_string()),
+    }
+}
+
+/// Frontmatter of `content` with `changes` made (a null value removes the key),
+/// and the keys updated, added and removed
+fn edit(
+    content: &str,
+    changes: &serde_json::Map<String, Value>,
+) -> Result<(String, [Vec<String>; 3]), CdeError> {
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    if bounds(content).is_some_and(|bounds| bounds.format == Format::Toml) {
+        return Err(CdeError::invalid_input("TOML frontmatter cannot be edited, only YAML"));
+    }
+    let owned;
+    let split = match split(content) {
+        Some(split) => split,
+        None => {
+            // No frontmatter yet: start an empty one
+            let (bom, body) = match content.strip_prefix('\u{feff}') {
+                Some(body) => ("\u{feff}", body),
+                None => ("", content),
+            };
+            owned = format!("{}---{nl}---{nl}{}", bom, body, nl = newline);
+            split(&owned).expect("frontmatter was just written")
+        }
+    };
+    let original: String = split.lines.concat();
+    let current: serde_yaml::Mapping = match original.trim() {
+        "" => serde_yaml::Mapping::new(),
+        yaml => serde_yaml::from_str(yaml)
+            .map_err(|e| CdeError::parse("Invalid YAML frontmatter").caused_by(&e))?,
+    };
+
+    let entries = entries(&split.lines);
+    let mut replaced: BTreeMap<usize, (usize, String)> = BTreeMap::new();
+    let mut appended = String::new();
+    let (mut updated, mut added, mut removed) = (Vec::new(), Vec::new(), Vec::new());
+    for (key, value) in changes {
+        let existing = current.get(key.as_str());
+        let wanted = serde_yaml::to_value(value)
+            .map_err(|e| CdeError::serialization("Unsupported value").caused_by(&e))?;
+        let entry = entries.iter().find(|entry| &entry.key == key);
+        match (entry, value) {
+            (Some(entry), Value::Null) => {
+                replaced.insert(entry.start, (entry.end, String::new()));
+                removed.push(key.clone());
+            }
+            (None, Value::Null) => {}
+            (Some(_), _) if existing == Some(&wanted) => {}
+            (Some(entry), _) => {
+                let lines = render_entry(key, value, entry.style, &entry.comment, newline);
+                replaced.insert(entry.start, (entry.end, lines));
+                updated.push(key.clone());
+            }
+            (None, _) => {
+                appended.push_str(&render_entry(key, value, Style::Plain, "", newline));
+                added.push(key.clone());
+            }
+        }
+    }
+
+    let mut frontmatter = String::with_capacity(original.len() + appended.len());
+    let mut index = 0;
+    while index < split.lines.len() {
+        match replaced.get(&index) {
+            Some((end, lines)) => {
+                frontmatter.push_str(lines);
+                index = *end;
+            }
+            None => {
+                frontmatter.push_str(split.lines[index]);
+                index += 1;
+            }
+        }
+    }
+    if !appended.is_empty() && !frontmatter.is_empty() && !frontmatter.ends_with('\n') {
+        frontmatter.push_str(newline);
+    }
+    frontmatter.push_str(&appended);
+    // Never write what would not read back
+    if !frontmatter.trim().is_empty() {
+        serde_yaml::from_str::<serde_yaml::Mapping>(&frontmatter).map_err(|e| {
+            CdeError::parse("The edited frontmatter is not valid YAML").caused_by(&e)
+        })?;
+    }
+    let edited = format!("{}{}{}", split.head, frontmatter, split.tail);
+    Ok((edited, [updated, added, removed]))
+}
+
+/// Sets (or, with null, removes) the top-level frontmatter keys of `changes` in
+/// the Markdown file at `path`, see the module docs; the file is replaced atomically
+pub fn update_frontmatter(
+    path: &str,
+    changes: &serde_json::Map<String, Value>,
+) -> Result<FrontmatterUpdate, CdeError> {
+    metrics::timed("update_frontmatter", || {
+        path_policy::check(path)?;
+        let content = fs::read_to_string(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                CdeError::not_found("File not found").with_path(path)
+            } else {
+                CdeError::io("Failed to read file")
+                    .with_path(path)
+                    .caused_by(&e)
+            }
+        })?;
+        let (edited, [updated, added, removed]) =
+            edit(&content, changes).map_err(|e| e.with_path(path))?;
+        let changed = !(updated.is_empty() && added.is_empty() && removed.is_empty());
+        if changed {
+            let changes = BTreeMap::from([(PathBuf::from(path), Some(edited))]);
+            patch::commit(&changes)?;
+        }
+        metrics::add_bytes("update_frontmatter", content.len() as u64);
+        Ok(FrontmatterUpdate {
+            path: Path::new(path).to_string_lossy().into_owned(),
+            changed,
+            updated,
+            added,
+            removed,
+        })
+    })
+}
+
+/// Edits the YAML frontmatter of a Markdown file in place, as JSON
+///
+/// `changes_json` is an object of top-level keys to set; a null value removes
+/// the key. Comments, key order, quoting style and untouched lines are kept, so
+/// the diff shows only the changed entries; new keys are appended. A file without
+/// frontmatter gets one. Returns whether the file `changed` and the keys
+/// `updated`, `added` and `removed`.
+#[pyfunction]
+fn update_frontmatter_py(py: Python<'_>, path: &str, changes_json: &str) -> PyResult<String> {
+    let changes: serde_json::Map<String, Value> = from_json("changes", changes_json)?;
+    let update = py.detach(|| update_frontmatter(path, &changes))?;
+    Ok(to_json(&update)?)
+}
+
+/// Adds the frontmatter functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(update_frontmatter_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yaml_and_toml_blocks() {
+        let title = |content: &str| {
+            parse::<serde_yaml::Mapping>(content)
+                .and_then(|mapping| mapping.get("title")?.as_str().map(str::to_string))
+        };
+        assert_eq!(
+            title("---\ntitle: A\n---\n# A\n---\n").as_deref(),
+            Some("A")
+        );
+        assert_eq!(
+            title("\u{feff}---\r\ntitle: B\r\n...\r\n").as_deref(),
+            Some("B")
+        );
+        assert_eq!(title("+++\ntitle = \"C\"\n+++\n").as_deref(), Some("C"));
+        assert_eq!(title("+++\ntitle: D\n---\n"), None);
+        assert_eq!(title("\n---\ntitle: E\n---\n"), None);
+
+        let toml = "+++\ncreated = 2024-01-15\ntags = [\"a\"]\n+++\nBody\n";
+        let mapping: serde_yaml::Mapping = parse(toml).unwrap();
+        assert_eq!(
+            mapping.get("created").and_then(|v| v.as_str()),
+            Some("2024-01-15")
+        );
+        assert_eq!(body(toml), "Body\n");
+        assert_eq!(body("---\ntitle: A\n---"), "");
+        assert!(matches!(
+            edit(toml, &serde_json::Map::new()),
+            Err(CdeError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_edits_keep_comments_order_and_quoting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.md");
+        let original = "---\n\
+            # Document metadata\n\
+            title: 'Old title'  # shown in the index\n\
+            status: draft\n\
+            tags: [a, b]\n\
+            owners:\n\
+            \x20 - ana\n\
+            \n\
+            \x20 - luis\n\
+            legacy: yes\n\
+            ---\n\
+            # Body\n";
+        fs::write(&path, original).unwrap();
+        let path_str = path.to_str().unwrap();
+        let changes: serde_json::Map<String, Value> = serde_json::from_str(
+            r#"{"title": "It's new", "status": "draft", "tags": ["a", "c"],
+                "owners": ["ana"], "legacy": null, "updated": "2025-01-02: review"}"#,
+        )
+        .unwrap();
+
+        let update = update_frontmatter(path_str, &changes).unwrap();
+        assert!(update.changed);
+        assert_eq!(update.updated, vec!["owners", "tags", "title"]);
+        assert_eq!(
+            (update.added, update.removed),
+            (vec!["updated".to_string()], vec!["legacy".to_string()])
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\n\
+             # Document metadata\n\
+             title: 'It''s new'  # shown in the index\n\
+             status: draft\n\
+             tags: [a, c]\n\
+             owners:\n\
+             \x20 - ana\n\
+             updated: \"2025-01-02: review\"\n\
+             ---\n\
+             # Body\n"
+        );
+
+        // The same values again change nothing
+        let again = update_frontmatter(path_str, &changes).unwrap();
+        assert!(!again.changed);
+
+        let bare = dir.path().join("bare.md");
+        fs::write(&bare, "# Bare\r\n").unwrap();
+        let title: serde_json::Map<String, Value> =
+            serde_json::from_str(r#"{"title": "Bare"}"#).unwrap();
+        update_frontmatter(bare.to_str().unwrap(), &title).unwrap();
+        assert_eq!(
+            fs::read_to_string(&bare).unwrap(),
+            "---\r\ntitle: Bare\r\n---\r\n# Bare\r\n"
+        );
+    }
+}
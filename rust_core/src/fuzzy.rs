@@ -0,0 +1,343 @@
+// src/fuzzy.rs
+//! fzf-style fuzzy matching of project paths
+//!
+//! A query matches a path when its characters appear in the path in order. Each
+//! match is scored like fzf's v2 algorithm: points per matched character, a bonus
+//! for characters at word boundaries (after `/`, `_`, `-`, `.`, a space, or a
+//! camelCase hump), a bonus for consecutive characters, and a penalty for gaps;
+//! the best-scoring alignment wins and its positions are reported. Queries are
+//! case-insensitive unless they contain an uppercase letter, and space-separated
+//! terms must all match.
+//!
+//! The file list of a root (walked with `.gitignore` rules, hidden files left
+//! out) is kept in memory for `FILE_LIST_TTL`, so the keystroke-by-keystroke
+//! queries of a picker do not walk the tree again. Lists walked under another
+//! path policy are not reused.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::path_policy::{self, WalkGuard};
+use ignore::{WalkBuilder, WalkState};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a walked file list is reused
+const FILE_LIST_TTL: Duration = Duration::from_secs(10);
+
+const SCORE_MATCH: i32 = 16;
+const GAP_START: i32 = -3;
+const GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_DELIMITER: i32 = 9;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FuzzyMatch {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    pub score: i32,
+    /// Character (not byte) indices of the matched characters in `path`, sorted
+    pub positions: Vec<usize>,
+}
+
+/// By canonical root and path policy generation
+type FileLists = HashMap<(PathBuf, u64), (Instant, Arc<Vec<String>>)>;
+
+static FILE_LISTS: LazyLock<Mutex<FileLists>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn walk_files(root: &Path, guard: WalkGuard) -> Vec<String> {
+    let found: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    WalkBuilder::new(root)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .threads(rayon::current_num_threads())
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if entry.file_type().is_some_and(|t| t.is_file()) {
+                    let relative = entry
+                        .path()
+                        .strip_prefix(root)
+                        .unwrap_or(entry.path())
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    found.lock().unwrap().push(relative);
+                }
+                WalkState::Continue
+            })
+        });
+    let mut files = found.into_inner().unwrap();
+    files.sort();
+    files
+}
+
+/// Files under `root`, from the cache unless it is stale or `refresh` is set;
+/// `generation` is the path policy generation `guard` was taken under
+fn project_files(
+    root: &Path,
+    generation: u64,
+    guard: WalkGuard,
+    refresh: bool,
+) -> Arc<Vec<String>> {
+    let key = (
+        root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+        generation,
+    );
+    let cached = FILE_LISTS
+        .lock()
+        .unwrap()
+        .get(&key)
+        .filter(|(listed_at, _)| !refresh && listed_at.elapsed() < FILE_LIST_TTL)
+        .map(|(_, files)| files.clone());
+    metrics::cache_lookup("file_list", cached.is_some());
+    if let Some(files) = cached {
+        return files;
+    }
+    let files = Arc::new(walk_files(root, guard));
+    FILE_LISTS
+        .lock()
+        .unwrap()
+        .insert(key, (Instant::now(), files.clone()));
+    files
+}
+
+/// Bonus for a match on `current` following `previous` (None at the start)
+fn bonus(previous: Option<char>, current: char) -> i32 {
+    match previous {
+        None => BONUS_BOUNDARY,
+        Some('/' | '\\') => BONUS_DELIMITER,
+        Some('_' | '-' | '.' | ' ') => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(p) if !p.is_numeric() && current.is_numeric() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Best score of `pattern` in `text`, and the character positions it matched at
+fn score_term(pattern: &[char], text: &[char], case_sensitive: bool) -> Option<(i32, Vec<usize>)> {
+    let folded: Vec<char> = text.iter().map(|&c| fold(c, case_sensitive)).collect();
+    // Cheap rejection: the pattern must be a subsequence
+    let mut rest = folded.iter();
+    if !pattern.iter().all(|p| rest.any(|c| c == p)) {
+        return None;
+    }
+
+    const NONE: i32 = i32::MIN / 2;
+    let (m, n) = (pattern.len(), text.len());
+    // scores[i][j]: best score with pattern[i] matched at text[j]; from[i][j]: the
+    // position pattern[i - 1] was matched at
+    let mut scores = vec![vec![NONE; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+    for i in 0..m {
+        // Best score of matching pattern[i - 1] at least two characters back,
+        // with the gap up to j already paid for
+        let mut gap: (i32, usize) = (NONE, 0);
+        for j in 0..n {
+            if i > 0 && j >= 2 && scores[i - 1][j - 2] > NONE {
+                let started = scores[i - 1][j - 2] + GAP_START;
+                gap = if started >= gap.0 + GAP_EXTENSION {
+                    (started, j - 2)
+                } else {
+                    (gap.0 + GAP_EXTENSION, gap.1)
+                };
+            } else if gap.0 > NONE {
+                gap.0 += GAP_EXTENSION;
+            }
+            if folded[j] != pattern[i] {
+                continue;
+            }
+            let bonus = bonus(j.checked_sub(1).map(|p| text[p]), text[j]);
+            if i == 0 {
+                scores[0][j] = SCORE_MATCH + bonus * FIRST_CHAR_MULTIPLIER;
+                continue;
+            }
+            let consecutive = (j >= 1 && scores[i - 1][j - 1] > NONE)
+                .then(|| scores[i - 1][j - 1] + SCORE_MATCH + bonus.max(BONUS_CONSECUTIVE));
+            let gapped = (gap.0 > NONE).then(|| gap.0 + SCORE_MATCH + bonus);
+            match (consecutive, gapped) {
+                (Some(c), Some(g)) if g > c => (scores[i][j], from[i][j]) = (g, gap.1),
+                (Some(c), _) => (scores[i][j], from[i][j]) = (c, j - 1),
+                (None, Some(g)) => (scores[i][j], from[i][j]) = (g, gap.1),
+                (None, None) => {}
+            }
+        }
+    }
+
+    let (end, &score) = scores[m - 1]
+        .iter()
+        .enumerate()
+        .filter(|(_, &score)| score > NONE)
+        .max_by_key(|(j, &score)| (score, std::cmp::Reverse(*j)))?;
+    let mut positions = vec![end; m];
+    for i in (1..m).rev() {
+        positions[i - 1] = from[i][positions[i]];
+    }
+    Some((score, positions))
+}
+
+/// Score of `path` for the whitespace-separated `terms`, None unless all match
+fn score_path(terms: &[Vec<char>], path: &str, case_sensitive: bool) -> Option<FuzzyMatch> {
+    let text: Vec<char> = path.chars().collect();
+    let mut score = 0;
+    let mut positions = Vec::new();
+    for term in terms {
+        let (term_score, term_positions) = score_term(term, &text, case_sensitive)?;
+        score += term_score;
+        positions.extend(term_positions);
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    Some(FuzzyMatch {
+        path: path.to_string(),
+        score,
+        positions,
+    })
+}
+
+/// Best `limit` matches of `query` among `paths`: highest score first, then
+/// shorter paths, then alphabetically
+pub fn fuzzy_match(paths: &[String], query: &str, limit: usize) -> Vec<FuzzyMatch> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let terms: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|term| term.chars().collect())
+        .collect();
+    let mut matches: Vec<FuzzyMatch> = paths
+        .par_iter()
+        .filter_map(|path| score_path(&terms, path, case_sensitive))
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.path.len().cmp(&b.path.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+/// Best `limit` project files under `root` for `query`, see the module docs
+pub fn fuzzy_find(
+    root: &str,
+    query: &str,
+    limit: usize,
+    refresh: bool,
+) -> Result<Vec<FuzzyMatch>, CdeError> {
+    metrics::timed("fuzzy_find", || {
+        let root = Path::new(root);
+        let generation = path_policy::generation();
+        let guard = path_policy::walk_guard(root)?;
+        if !root.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        let files = project_files(root, generation, guard, refresh);
+        Ok(fuzzy_match(&files, query, limit))
+    })
+}
+
+/// Project files under `root` best matching `query`, fzf-style, as JSON
+///
+/// Returns up to `limit` objects with `path`, `score` and `positions` (character
+/// indices of the matched characters, for highlighting), best first. Smart case:
+/// the match ignores case unless the query has an uppercase letter. The file list
+/// is cached for a few seconds; `refresh` walks the tree again.
+#[pyfunction]
+#[pyo3(signature = (root, query, limit=20, refresh=false))]
+fn fuzzy_find_py(
+    py: Python<'_>,
+    root: &str,
+    query: &str,
+    limit: usize,
+    refresh: bool,
+) -> PyResult<String> {
+    let matches = py.detach(|| fuzzy_find(root, query, limit, refresh))?;
+    Ok(to_json(&matches)?)
+}
+
+/// Adds the fuzzy matching functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(fuzzy_find_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_fuzzy_ranks_boundaries_and_caches_file_list() {
+        let paths: Vec<String> = [
+            "src/main.rs",
+            "docs/maintenance.md",
+            "src/domain/models.py",
+            "tests/test_main.py",
+            "README.md",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+
+        let matches = fuzzy_match(&paths, "main", 10);
+        let ranked: Vec<&str> = matches.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(ranked[0], "src/main.rs");
+        assert_eq!(matches[0].positions, vec![4, 5, 6, 7]);
+        assert!(!ranked.contains(&"README.md"));
+
+        // Word starts beat scattered characters
+        let matches = fuzzy_match(&paths, "sdm", 10);
+        assert_eq!(matches[0].path, "src/domain/models.py");
+        assert_eq!(matches[0].positions, vec![0, 4, 11]);
+
+        // Terms must all match; uppercase makes the query case-sensitive
+        assert_eq!(
+            fuzzy_match(&paths, "test py", 10)[0].path,
+            "tests/test_main.py"
+        );
+        assert_eq!(fuzzy_match(&paths, "README", 10).len(), 1);
+        assert!(fuzzy_match(&paths, "Main", 10).is_empty());
+        assert_eq!(fuzzy_match(&paths, "m", 2).len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("config.yaml"), "").unwrap();
+        assert_eq!(fuzzy_find(root, "cfg", 5, false).unwrap().len(), 1);
+        fs::write(dir.path().join("cfg_loader.rs"), "").unwrap();
+        // The cached list does not know the new file until refreshed
+        assert_eq!(fuzzy_find(root, "cfg", 5, false).unwrap().len(), 1);
+        let refreshed = fuzzy_find(root, "cfg", 5, true).unwrap();
+        assert_eq!(refreshed[0].path, "cfg_loader.rs");
+    }
+
+    #[test]
+    fn test_cached_file_list_follows_policy_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("policy-hidden.md"), "").unwrap();
+        assert_eq!(fuzzy_find(root, "hidden", 5, false).unwrap().len(), 1);
+
+        let denied = path_policy::with_denied("policy-hidden.md", || {
+            fuzzy_find(root, "hidden", 5, false).unwrap()
+        });
+        assert!(denied.is_empty());
+        assert_eq!(fuzzy_find(root, "hidden", 5, false).unwrap().len(), 1);
+    }
+}
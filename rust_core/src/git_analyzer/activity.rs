@@ -0,0 +1,211 @@
+// rust_core/src/git_analyzer/activity.rs
+//! When people commit, in their own time zone
+//!
+//! Hours and weekdays are taken from each author date in the offset it was
+//! recorded with, so a team spread over several time zones shows its real
+//! working habits instead of the analyzing machine's clock.
+
+use super::backend::RawCommit;
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Weekday names, index 0 is Monday as in `Weekday::num_days_from_monday`
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Share of a contributor's commits their activity window must contain
+const WINDOW_COVERAGE: f64 = 0.8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ActivityWindow {
+    pub name: String,
+    pub email: String,
+    pub total_commits: usize,
+    /// Most frequent offset of the author dates, as "+02:00"
+    pub utc_offset: String,
+    /// Shortest span of hours (inclusive, may wrap past midnight) holding 80% of
+    /// the commits, in the contributor's time and in UTC
+    pub local_start_hour: u8,
+    pub local_end_hour: u8,
+    pub utc_start_hour: u8,
+    pub utc_end_hour: u8,
+    /// Weekdays with commits, most active first
+    pub active_days: Vec<String>,
+}
+
+/// Commits per hour of the author's day (24 entries)
+pub fn hour_histogram<'a>(
+    times: impl IntoIterator<Item = &'a DateTime<FixedOffset>>,
+) -> Vec<usize> {
+    let mut histogram = vec![0; 24];
+    for time in times {
+        histogram[time.hour() as usize] += 1;
+    }
+    histogram
+}
+
+/// Commits per hour of the UTC day (24 entries)
+fn utc_hour_histogram<'a>(
+    times: impl IntoIterator<Item = &'a DateTime<FixedOffset>>,
+) -> Vec<usize> {
+    let mut histogram = vec![0; 24];
+    for time in times {
+        histogram[time.with_timezone(&Utc).hour() as usize] += 1;
+    }
+    histogram
+}
+
+/// Commits per weekday of the author's calendar (7 entries, Monday first)
+pub fn weekday_histogram<'a>(
+    times: impl IntoIterator<Item = &'a DateTime<FixedOffset>>,
+) -> Vec<usize> {
+    let mut histogram = vec![0; 7];
+    for time in times {
+        histogram[time.weekday().num_days_from_monday() as usize] += 1;
+    }
+    histogram
+}
+
+/// Indexes of the non-zero entries of `histogram`, highest count first
+pub fn ranked(histogram: &[usize]) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..histogram.len()).filter(|&i| histogram[i] > 0).collect();
+    indexes.sort_by_key(|&i| std::cmp::Reverse(histogram[i]));
+    indexes
+}
+
+/// Activity window of every contributor (grouped by email), most commits first
+pub fn activity_windows(commits: &[RawCommit]) -> Vec<ActivityWindow> {
+    let mut by_email: HashMap<&str, Vec<&RawCommit>> = HashMap::new();
+    for commit in commits {
+        by_email.entry(&commit.email).or_default().push(commit);
+    }
+
+    let mut windows: Vec<ActivityWindow> = by_email
+        .into_values()
+        .map(|commits| {
+            let times = || commits.iter().map(|commit| &commit.author_time);
+            let (start, end) = shortest_window(&hour_histogram(times()));
+            // From the UTC hours themselves: half-hour offsets do not shift by whole hours
+            let (utc_start, utc_end) = shortest_window(&utc_hour_histogram(times()));
+            let offset = most_common_offset(&commits);
+            ActivityWindow {
+                // Commits are newest first: the latest name wins
+                name: commits[0].author.clone(),
+                email: commits[0].email.clone(),
+                total_commits: commits.len(),
+                utc_offset: offset.to_string(),
+                local_start_hour: start as u8,
+                local_end_hour: end as u8,
+                utc_start_hour: utc_start as u8,
+                utc_end_hour: utc_end as u8,
+                active_days: ranked(&weekday_histogram(times()))
+                    .into_iter()
+                    .map(|day| WEEKDAYS[day].to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+    windows.sort_by(|a, b| {
+        b.total_commits
+            .cmp(&a.total_commits)
+            .then_with(|| a.email.cmp(&b.email))
+    });
+    windows
+}
+
+fn most_common_offset(commits: &[&RawCommit]) -> FixedOffset {
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for commit in commits {
+        *counts
+            .entry(commit.author_time.offset().local_minus_utc())
+            .or_insert(0) += 1;
+    }
+    let seconds = counts
+        .into_iter()
+        .max_by_key(|&(seconds, count)| (count, std::cmp::Reverse(seconds)))
+        .map_or(0, |(seconds, _)| seconds);
+    FixedOffset::east_opt(seconds).unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+/// First and last hour of the shortest circular span holding `WINDOW_COVERAGE`
+/// of the commits; the busiest, then earliest, span among equally short ones
+fn shortest_window(hours: &[usize]) -> (usize, usize) {
+    let total: usize = hours.iter().sum();
+    let needed = (total as f64 * WINDOW_COVERAGE).ceil() as usize;
+    for length in 1..=24 {
+        let best = (0..24)
+            .map(|start| {
+                let count: usize = (start..start + length).map(|hour| hours[hour % 24]).sum();
+                (start, count)
+            })
+            .filter(|&(_, count)| count >= needed)
+            .max_by_key(|&(start, count)| (count, std::cmp::Reverse(start)));
+        if let Some((start, _)) = best {
+            return (start, (start + length - 1) % 24);
+        }
+    }
+    (0, 23)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histograms_and_windows_use_author_time_zones() {
+        let kenji = |date| RawCommit::fixture("").email("kenji@example.com").at(date);
+        let ana = |date| RawCommit::fixture("").email("ana@example.com").at(date);
+        let commits = [
+            // Sunday 23:30 in Tokyo, 14:30 UTC
+            kenji("2024-05-05T23:30:00+09:00"),
+            kenji("2024-05-06T22:10:00+09:00"),
+            kenji("2024-05-07T00:40:00+09:00"),
+            kenji("2024-05-08T01:05:00+09:00"),
+            kenji("2024-05-08T23:59:00+09:00"),
+            ana("2024-05-06T09:00:00-03:00"),
+            ana("2024-05-06T10:30:00-03:00"),
+        ];
+
+        let times = || commits.iter().map(|commit| &commit.author_time);
+        let hours = hour_histogram(times());
+        assert_eq!((hours[23], hours[0], hours[9], hours[14]), (2, 1, 1, 0));
+        let days = weekday_histogram(times());
+        assert_eq!(days, vec![3, 1, 2, 0, 0, 0, 1]);
+        assert_eq!(ranked(&days)[0], 0);
+
+        let windows = activity_windows(&commits);
+        let kenji = &windows[0];
+        assert_eq!(kenji.total_commits, 5);
+        assert_eq!(kenji.utc_offset, "+09:00");
+        assert_eq!((kenji.local_start_hour, kenji.local_end_hour), (22, 0));
+        assert_eq!((kenji.utc_start_hour, kenji.utc_end_hour), (13, 15));
+        assert_eq!(kenji.active_days[0], "Wednesday");
+        let ana = &windows[1];
+        assert_eq!((ana.local_start_hour, ana.local_end_hour), (9, 10));
+        assert_eq!((ana.utc_start_hour, ana.utc_end_hour), (12, 13));
+        assert_eq!(ana.active_days, vec!["Monday"]);
+    }
+
+    #[test]
+    fn test_windows_handle_half_hour_offsets() {
+        let priya = |date| RawCommit::fixture("").email("priya@example.com").at(date);
+        let commits = [
+            // 03:30, 04:15 and 04:50 UTC
+            priya("2024-05-06T09:00:00+05:30"),
+            priya("2024-05-06T09:45:00+05:30"),
+            priya("2024-05-07T10:20:00+05:30"),
+        ];
+        let priya = &activity_windows(&commits)[0];
+        assert_eq!(priya.utc_offset, "+05:30");
+        assert_eq!((priya.local_start_hour, priya.local_end_hour), (9, 10));
+        assert_eq!((priya.utc_start_hour, priya.utc_end_hour), (3, 4));
+    }
+}
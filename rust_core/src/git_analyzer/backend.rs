@@ -0,0 +1,242 @@
+// rust_core/src/git_analyzer/backend.rs
+//! Repository access behind a common trait
+//!
+//! The analysis only needs a handful of raw queries (commits with per-file stats,
+//! branches, tags, HEAD). They are answered by libgit2 in-process, so no `git`
+//! binary is required and nothing depends on its locale or output format. The
+//! `git` CLI is only used for repositories libgit2 cannot open (for example ones
+//! using repository extensions it does not support yet).
+
+use super::cli::CliBackend;
+use super::diff::{DiffOptions, FileDiff};
+use super::history::FileRevision;
+use super::libgit2::Git2Backend;
+use crate::cancel::CancellationToken;
+use crate::error::CdeError;
+use chrono::{DateTime, FixedOffset, Local};
+use std::path::{Path, PathBuf};
+
+/// Format of every date the analyzer reports, as `git log --format=%ai`
+pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStat {
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawCommit {
+    pub hash: String,
+    /// First parent first, as recorded in the commit
+    pub parents: Vec<String>,
+    pub author: String,
+    pub email: String,
+    pub author_time: DateTime<FixedOffset>,
+    /// First line of the message
+    pub summary: String,
+    pub message: String,
+    /// Per-file stats against the first parent; empty for merge commits
+    pub files: Vec<FileStat>,
+}
+
+/// Commits for the analysis tests, e.g. `RawCommit::fixture("abc").email(..).files(..)`
+#[cfg(test)]
+impl RawCommit {
+    /// Commit `hash` by ana@example.com on 2024-05-01 10:00 +02:00, without
+    /// parents, message or files
+    pub(crate) fn fixture(hash: &str) -> Self {
+        Self {
+            hash: hash.to_string(),
+            parents: Vec::new(),
+            author: String::new(),
+            email: String::new(),
+            author_time: DateTime::default(),
+            summary: String::new(),
+            message: String::new(),
+            files: Vec::new(),
+        }
+        .email("ana@example.com")
+        .at("2024-05-01T10:00:00+02:00")
+    }
+
+    /// Sets the email, and the author name to its local part
+    pub(crate) fn email(mut self, email: &str) -> Self {
+        self.author = email.split('@').next().unwrap_or(email).to_string();
+        self.email = email.to_string();
+        self
+    }
+
+    pub(crate) fn author(mut self, author: &str) -> Self {
+        self.author = author.to_string();
+        self
+    }
+
+    /// Sets the author date, given in RFC 3339
+    pub(crate) fn at(mut self, date: &str) -> Self {
+        self.author_time = DateTime::parse_from_rfc3339(date).unwrap();
+        self
+    }
+
+    /// Sets the message, and the summary to its first line
+    pub(crate) fn message(mut self, message: &str) -> Self {
+        self.summary = message.lines().next().unwrap_or("").to_string();
+        self.message = message.to_string();
+        self
+    }
+
+    pub(crate) fn parents(mut self, parents: &[&str]) -> Self {
+        self.parents = parents.iter().map(|parent| parent.to_string()).collect();
+        self
+    }
+
+    /// Sets the changed files, as (path, insertions, deletions)
+    pub(crate) fn files(mut self, files: &[(&str, usize, usize)]) -> Self {
+        self.files = files
+            .iter()
+            .map(|&(path, insertions, deletions)| FileStat {
+                path: path.to_string(),
+                insertions,
+                deletions,
+            })
+            .collect();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawBranch {
+    /// Short name; remote-tracking branches are prefixed with their remote
+    pub name: String,
+    pub last_commit_time: DateTime<FixedOffset>,
+    /// Commits on the branch that the base branch does not have, and the other way around
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawTag {
+    pub name: String,
+    pub commit_hash: String,
+    pub commit_time: DateTime<FixedOffset>,
+    pub commit_summary: String,
+    /// Tagger date of annotated tags, commit date of lightweight ones
+    pub created: DateTime<FixedOffset>,
+}
+
+/// A file that differs between HEAD, the index and the working copy
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: String,
+    pub change: String, // "added", "modified", "deleted", "typechange"
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawWorkingTree {
+    /// Index against HEAD
+    pub staged: Vec<FileChange>,
+    /// Working copy against the index
+    pub unstaged: Vec<FileChange>,
+    /// Files that are neither tracked nor ignored
+    pub untracked: Vec<String>,
+    /// Files with unresolved merge conflicts in the index
+    pub conflicted: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitSpan {
+    pub total_commits: usize,
+    pub first_commit_time: DateTime<FixedOffset>,
+    pub last_commit_time: DateTime<FixedOffset>,
+}
+
+pub trait GitBackend: Send + Sync {
+    /// Short name of the checked-out branch, "HEAD" when detached
+    fn current_branch(&self) -> Result<String, CdeError>;
+
+    fn remote_url(&self) -> Option<String>;
+
+    /// Full hash of the commit HEAD points to
+    fn head_commit(&self) -> Result<String, CdeError>;
+
+    /// The repository's `.git` directory (or the repository itself when bare)
+    fn git_dir(&self) -> Result<PathBuf, CdeError>;
+
+    /// Number of commits reachable from HEAD and their oldest/newest author dates
+    fn commit_span(&self) -> Result<CommitSpan, CdeError>;
+
+    /// Commits reachable from HEAD committed at or after `since`, newest first
+    fn commits_since(&self, since: DateTime<Local>) -> Result<Vec<RawCommit>, CdeError>;
+
+    /// `commits_since`, stopping with the newest commits read so far once `cancel`
+    /// is cancelled; backends that cannot stop midway return every commit
+    fn commits_since_cancellable(
+        &self,
+        since: DateTime<Local>,
+        _cancel: &CancellationToken,
+    ) -> Result<Vec<RawCommit>, CdeError> {
+        self.commits_since(since)
+    }
+
+    /// Commits reachable from `to_ref` but not from `from_ref`, newest first
+    fn commits_between(&self, from_ref: &str, to_ref: &str) -> Result<Vec<RawCommit>, CdeError>;
+
+    /// The branch others are merged into: the target of `origin/HEAD`, else a local
+    /// "main" or "master"
+    fn default_branch(&self) -> Option<String>;
+
+    /// Local and remote-tracking branches compared to the revision `base`
+    fn branches(&self, base: &str) -> Result<Vec<RawBranch>, CdeError>;
+
+    /// Commits of `branch` missing from `base`, and of `base` missing from `branch`
+    fn ahead_behind(&self, branch: &str, base: &str) -> Result<(usize, usize), CdeError>;
+
+    /// Full hash of the best common ancestor of `branch` and `base`
+    fn merge_base(&self, branch: &str, base: &str) -> Result<String, CdeError>;
+
+    /// Paths that would conflict when merging `branch` into `base`, found with an
+    /// in-memory merge that leaves the index and working copy untouched
+    fn merge_conflicts(&self, branch: &str, base: &str) -> Result<Vec<String>, CdeError>;
+
+    /// Tags pointing at commits, most recently created first
+    fn tags(&self) -> Result<Vec<RawTag>, CdeError>;
+
+    /// Uncommitted changes; renames are reported as a deletion and an addition
+    fn working_tree(&self) -> Result<RawWorkingTree, CdeError>;
+
+    /// Non-merge commits reachable from HEAD that changed `path`, newest first;
+    /// with `follow_renames` older commits are matched against the previous name
+    fn file_history(&self, path: &str, follow_renames: bool) -> Result<Vec<FileRevision>, CdeError>;
+
+    /// Files changed from `from_ref` to `to_ref` with their hunks
+    fn diff(
+        &self,
+        from_ref: &str,
+        to_ref: &str,
+        options: &DiffOptions,
+    ) -> Result<Vec<FileDiff>, CdeError>;
+}
+
+/// The backend for the repository at `path`: libgit2, or the `git` CLI when
+/// libgit2 cannot open it
+pub fn open(path: &Path) -> Result<Box<dyn GitBackend>, CdeError> {
+    match Git2Backend::open(path) {
+        Ok(backend) => Ok(Box::new(backend)),
+        Err(libgit2_error) => CliBackend::open(path)
+            .map(|backend| Box::new(backend) as Box<dyn GitBackend>)
+            .map_err(|cli_error| {
+                CdeError::git(format!(
+                    "Failed to open Git repository: {} (git CLI fallback: {})",
+                    libgit2_error, cli_error
+                ))
+                .with_path(path)
+            }),
+    }
+}
+
+pub fn format_time(time: &DateTime<FixedOffset>) -> String {
+    time.format(DATE_FORMAT).to_string()
+}
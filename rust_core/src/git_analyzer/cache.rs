@@ -0,0 +1,108 @@
+// rust_core/src/git_analyzer/cache.rs
+//! On-disk cache of full repository analyses
+//!
+//! An analysis only depends on the history reachable from HEAD and the requested
+//! window, so it is stored as JSON keyed by the HEAD commit and `days`, next to the
+//! repository data (`<git dir>/cde/analysis-cache`) or under
+//! `CDE_GIT_CACHE_DIR/<repository hash>` when that variable is set. Branch and tag
+//! changes that leave HEAD alone are not detected: callers invalidate explicitly,
+//! and entries expire after a day since the `days` window slides with the clock.
+
+use super::GitAnalysis;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable moving every repository's cache under one directory
+pub const CACHE_DIR_ENV: &str = "CDE_GIT_CACHE_DIR";
+
+/// Age after which an entry is recomputed even if HEAD did not move
+const MAX_ENTRY_AGE_SECS: i64 = 24 * 60 * 60;
+
+/// Stored with `&GitAnalysis`, read back with `GitAnalysis`
+#[derive(Serialize, Deserialize)]
+struct CacheEntry<A> {
+    repo_path: String,
+    head: String,
+    days: i64,
+    created_at: String,
+    analysis: A,
+}
+
+/// Cache directory of the repository at `repo_path` whose data lives in `git_dir`
+pub fn cache_dir(repo_path: &str, git_dir: &Path) -> PathBuf {
+    match std::env::var_os(CACHE_DIR_ENV) {
+        Some(root) => {
+            let canonical =
+                fs::canonicalize(repo_path).unwrap_or_else(|_| PathBuf::from(repo_path));
+            let mut hasher = DefaultHasher::new();
+            canonical.hash(&mut hasher);
+            PathBuf::from(root).join(format!("{:016x}", hasher.finish()))
+        }
+        None => git_dir.join("cde").join("analysis-cache"),
+    }
+}
+
+fn entry_path(dir: &Path, head: &str, days: i64) -> PathBuf {
+    dir.join(format!("{}-{}.json", head, days))
+}
+
+/// The analysis stored for `head` and `days`, unless missing, unreadable or expired
+pub fn load(dir: &Path, repo_path: &str, head: &str, days: i64) -> Option<GitAnalysis> {
+    let content = fs::read_to_string(entry_path(dir, head, days)).ok()?;
+    let entry: CacheEntry<GitAnalysis> = serde_json::from_str(&content).ok()?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&entry.created_at).ok()?;
+    let age = chrono::Local::now().fixed_offset() - created_at;
+    let valid = entry.repo_path == repo_path
+        && entry.head == head
+        && entry.days == days
+        && (0..MAX_ENTRY_AGE_SECS).contains(&age.num_seconds());
+    valid.then_some(entry.analysis)
+}
+
+/// Stores `analysis`; written to a temporary file first so readers never see a
+/// partial entry
+pub fn store(
+    dir: &Path,
+    repo_path: &str,
+    head: &str,
+    days: i64,
+    analysis: &GitAnalysis,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let content = serde_json::to_string(&CacheEntry {
+        repo_path: repo_path.to_string(),
+        head: head.to_string(),
+        days,
+        created_at: chrono::Local::now().to_rfc3339(),
+        analysis,
+    })?;
+    let path = entry_path(dir, head, days);
+    let temporary = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, &path)
+}
+
+/// Removes every entry of the cache directory; returns how many there were
+pub fn invalidate(dir: &Path) -> io::Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            fs::remove_file(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,373 @@
+// rust_core/src/git_analyzer/changelog.rs
+//! Changelogs between two revisions
+//!
+//! Commits are grouped by their Conventional Commit type (`feat(scope)!: ...`),
+//! breaking changes are collected separately and `#123` / `owner/repo#123`
+//! references are linked when the origin remote is on GitHub. Commits that do not
+//! follow the convention end up under "Other Changes" instead of being dropped.
+
+use super::backend::RawCommit;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Section titles of the known commit types, in changelog order
+const SECTIONS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("revert", "Reverts"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "CI"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+];
+
+/// Section of commits without a known type
+const OTHER_SECTION: (&str, &str) = ("other", "Other Changes");
+
+/// Length of the abbreviated hashes shown in entries
+const SHORT_HASH_LENGTH: usize = 7;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changelog {
+    pub from_ref: String,
+    pub to_ref: String,
+    /// Author date of the newest commit (YYYY-MM-DD)
+    pub date: Option<String>,
+    pub total_commits: usize,
+    pub sections: Vec<ChangelogSection>,
+    /// Also listed in their type's section
+    pub breaking_changes: Vec<ChangelogEntry>,
+    pub contributors: Vec<String>,
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangelogSection {
+    pub kind: String, // "feat", "fix", ... or "other"
+    pub title: String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangelogEntry {
+    pub hash: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+    pub author: String,
+    pub references: Vec<Reference>,
+}
+
+/// A pull request or issue mentioned in a commit message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    /// As written: "#12" or "owner/repo#12"
+    pub id: String,
+    /// Link on the hosting service, when the remote is on GitHub
+    pub url: Option<String>,
+}
+
+/// Builds the changelog of `commits` (newest first); `remote_url` is used to link
+/// commits and references
+pub fn build_changelog(
+    from_ref: &str,
+    to_ref: &str,
+    commits: &[RawCommit],
+    remote_url: Option<&str>,
+) -> Changelog {
+    let github = remote_url.and_then(github_repository);
+    let mut sections: Vec<ChangelogSection> = SECTIONS
+        .iter()
+        .chain([&OTHER_SECTION])
+        .map(|(kind, title)| ChangelogSection {
+            kind: kind.to_string(),
+            title: title.to_string(),
+            entries: Vec::new(),
+        })
+        .collect();
+    let mut contributors: Vec<String> = Vec::new();
+
+    for commit in commits
+        .iter()
+        .filter(|commit| !is_merge_message(&commit.summary))
+    {
+        let (kind, entry) = parse_entry(commit, github.as_deref());
+        let section = sections
+            .iter_mut()
+            .find(|section| section.kind == kind)
+            .expect("every kind has a section");
+        section.entries.push(entry);
+        if !contributors.contains(&commit.author) {
+            contributors.push(commit.author.clone());
+        }
+    }
+    sections.retain(|section| !section.entries.is_empty());
+    contributors.sort();
+
+    let breaking_changes: Vec<ChangelogEntry> = sections
+        .iter()
+        .flat_map(|section| &section.entries)
+        .filter(|entry| entry.breaking)
+        .cloned()
+        .collect();
+    let date = commits
+        .first()
+        .map(|commit| commit.author_time.format("%Y-%m-%d").to_string());
+
+    let mut changelog = Changelog {
+        from_ref: from_ref.to_string(),
+        to_ref: to_ref.to_string(),
+        date,
+        total_commits: commits.len(),
+        sections,
+        breaking_changes,
+        contributors,
+        markdown: String::new(),
+    };
+    changelog.markdown = render_markdown(&changelog, github.as_deref());
+    changelog
+}
+
+/// Type of the commit (a key of `SECTIONS`, or "other") and its entry
+fn parse_entry(commit: &RawCommit, github: Option<&str>) -> (&'static str, ChangelogEntry) {
+    static HEADER_RE: OnceLock<Regex> = OnceLock::new();
+    let header_re =
+        HEADER_RE.get_or_init(|| Regex::new(r"^(\w+)(?:\(([^)]*)\))?(!)?:\s*(.+)$").unwrap());
+
+    let known_kind = |kind: &str| {
+        SECTIONS
+            .iter()
+            .map(|(known, _)| *known)
+            .find(|known| known.eq_ignore_ascii_case(kind))
+    };
+    let body_breaking =
+        commit.message.lines().skip(1).any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+
+    let header = header_re
+        .captures(&commit.summary)
+        .and_then(|caps| Some((known_kind(&caps[1])?, caps)));
+    let (kind, scope, bang, description) = match header {
+        Some((kind, caps)) => (
+            kind,
+            caps.get(2)
+                .map(|scope| scope.as_str().trim().to_string())
+                .filter(|scope| !scope.is_empty()),
+            caps.get(3).is_some(),
+            caps[4].trim().to_string(),
+        ),
+        None => (
+            OTHER_SECTION.0,
+            None,
+            false,
+            commit.summary.trim().to_string(),
+        ),
+    };
+
+    let entry = ChangelogEntry {
+        hash: commit.hash.chars().take(SHORT_HASH_LENGTH).collect(),
+        scope,
+        description,
+        breaking: bang || body_breaking,
+        author: commit.author.clone(),
+        references: references(&commit.message, github),
+    };
+    (kind, entry)
+}
+
+/// Merge commits created by git or a hosting service repeat what the merged
+/// commits already say
+fn is_merge_message(summary: &str) -> bool {
+    [
+        "Merge pull request ",
+        "Merge branch ",
+        "Merge remote-tracking branch ",
+    ]
+    .iter()
+    .any(|prefix| summary.starts_with(prefix))
+}
+
+/// `#12` and `owner/repo#12` mentions of `message`, first mention order
+fn references(message: &str, github: Option<&str>) -> Vec<Reference> {
+    static REFERENCE_RE: OnceLock<Regex> = OnceLock::new();
+    let reference_re = REFERENCE_RE
+        .get_or_init(|| Regex::new(r"(?m)(?:^|[\s(\[,])((?:[\w.-]+/[\w.-]+)?#(\d+))\b").unwrap());
+
+    let mut found: Vec<Reference> = Vec::new();
+    for caps in reference_re.captures_iter(message) {
+        let id = caps[1].to_string();
+        if found.iter().any(|reference| reference.id == id) {
+            continue;
+        }
+        // GitHub redirects issue links to the pull request with that number
+        let url = github.and_then(|current| {
+            let (repo, number) = id.split_once('#')?;
+            let repo = if repo.is_empty() { current } else { repo };
+            Some(format!("https://github.com/{}/issues/{}", repo, number))
+        });
+        found.push(Reference { id, url });
+    }
+    found
+}
+
+/// "owner/repo" of a GitHub remote, in HTTPS or SSH form
+fn github_repository(remote_url: &str) -> Option<String> {
+    let path = [
+        "https://github.com/",
+        "http://github.com/",
+        "git@github.com:",
+        "ssh://git@github.com/",
+    ]
+    .iter()
+    .find_map(|prefix| remote_url.trim().strip_prefix(prefix))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    (path.split('/').count() == 2).then(|| path.to_string())
+}
+
+fn render_markdown(changelog: &Changelog, github: Option<&str>) -> String {
+    let mut markdown = format!("## {}", changelog.to_ref);
+    if let Some(date) = &changelog.date {
+        markdown.push_str(&format!(" ({})", date));
+    }
+    markdown.push_str("\n\n");
+
+    let line = |entry: &ChangelogEntry| {
+        let mut line = String::from("- ");
+        if let Some(scope) = &entry.scope {
+            line.push_str(&format!("**{}:** ", scope));
+        }
+        line.push_str(&entry.description);
+        let mut links = vec![match github {
+            Some(repo) => format!(
+                "[{}](https://github.com/{}/commit/{})",
+                entry.hash, repo, entry.hash
+            ),
+            None => entry.hash.clone(),
+        }];
+        links.extend(
+            entry
+                .references
+                .iter()
+                .map(|reference| match &reference.url {
+                    Some(url) => format!("[{}]({})", reference.id, url),
+                    None => reference.id.clone(),
+                }),
+        );
+        line.push_str(&format!(" ({})\n", links.join(", ")));
+        line
+    };
+
+    if !changelog.breaking_changes.is_empty() {
+        markdown.push_str("### Breaking Changes\n\n");
+        changelog
+            .breaking_changes
+            .iter()
+            .for_each(|entry| markdown.push_str(&line(entry)));
+        markdown.push('\n');
+    }
+    for section in &changelog.sections {
+        markdown.push_str(&format!("### {}\n\n", section.title));
+        section
+            .entries
+            .iter()
+            .for_each(|entry| markdown.push_str(&line(entry)));
+        markdown.push('\n');
+    }
+    if changelog.sections.is_empty() {
+        markdown.push_str("No changes.\n");
+    }
+    markdown.trim_end().to_string() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changelog_groups_by_type_and_links_references() {
+        let commits = [
+            RawCommit::fixture("aaaaaaaaaa")
+                .author("Grace")
+                .message("feat(api)!: drop v1 endpoints (#12)"),
+            RawCommit::fixture("bbbbbbbbbb")
+                .author("Ada")
+                .message("Merge pull request #12 from ada/v2"),
+            RawCommit::fixture("cccccccccc")
+                .author("Ada")
+                .message("fix: handle empty tags\n\nCloses #7, other/tool#3"),
+            RawCommit::fixture("dddddddddd")
+                .author("Ada")
+                .message("Update readme"),
+            RawCommit::fixture("eeeeeeeeee")
+                .author("Ada")
+                .message("refactor: split parser\n\nBREAKING CHANGE: new API"),
+        ];
+
+        let changelog = build_changelog(
+            "v1.0.0",
+            "v2.0.0",
+            &commits,
+            Some("git@github.com:acme/widgets.git"),
+        );
+
+        let kinds: Vec<&str> = changelog.sections.iter().map(|s| s.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["feat", "fix", "refactor", "other"]);
+        assert_eq!(changelog.total_commits, 5);
+        assert_eq!(changelog.date.as_deref(), Some("2024-05-01"));
+        assert_eq!(changelog.contributors, vec!["Ada", "Grace"]);
+
+        let feat = &changelog.sections[0].entries[0];
+        assert_eq!(feat.hash, "aaaaaaa");
+        assert_eq!(feat.scope.as_deref(), Some("api"));
+        assert_eq!(feat.description, "drop v1 endpoints (#12)");
+        assert!(feat.breaking);
+        let fix = &changelog.sections[1].entries[0];
+        assert_eq!(
+            fix.references,
+            vec![
+                Reference {
+                    id: "#7".to_string(),
+                    url: Some("https://github.com/acme/widgets/issues/7".to_string()),
+                },
+                Reference {
+                    id: "other/tool#3".to_string(),
+                    url: Some("https://github.com/other/tool/issues/3".to_string()),
+                },
+            ]
+        );
+        assert_eq!(changelog.breaking_changes.len(), 2);
+        assert_eq!(
+            changelog.sections[3].entries[0].description,
+            "Update readme"
+        );
+
+        assert!(changelog
+            .markdown
+            .starts_with("## v2.0.0 (2024-05-01)\n\n### Breaking Changes\n"));
+        assert!(changelog.markdown.contains(
+            "- **api:** drop v1 endpoints (#12) ([aaaaaaa](https://github.com/acme/widgets/commit/aaaaaaa), \
+             [#12](https://github.com/acme/widgets/issues/12))\n"
+        ));
+        assert!(changelog
+            .markdown
+            .contains("### Other Changes\n\n- Update readme"));
+
+        let unlinked = build_changelog(
+            "a",
+            "b",
+            &commits[2..3],
+            Some("https://gitlab.com/acme/widgets"),
+        );
+        assert_eq!(unlinked.sections[0].entries[0].references[0].url, None);
+        assert!(unlinked
+            .markdown
+            .contains("- handle empty tags (ccccccc, #7, other/tool#3)\n"));
+    }
+}
@@ -0,0 +1,332 @@
+// rust_core/src/git_analyzer/changesets.rs
+//! Commits grouped into the pull requests (or pushes) that brought them in
+//!
+//! The first-parent line of HEAD is the history of the default branch. A merge
+//! commit on it stands for the commits of its merged side, a squash merge
+//! ("title (#12)" or a "Squashed commit of the following" body) for itself, and
+//! the remaining commits made directly on the line are grouped when the same
+//! author made them within a few hours of each other. Only commits of the
+//! analyzed window are looked at, so a branch started before it is cut short.
+
+use super::backend::{format_time, RawCommit};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Longest gap between two direct commits of one author in the same change set
+const DIRECT_GAP_HOURS: i64 = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeSet {
+    /// Hash of the merge or squash commit, else of the newest commit
+    pub id: String,
+    pub kind: String, // "merge", "squash" or "direct"
+    pub title: String,
+    pub pr_number: Option<u64>,
+    /// Merged branch, as named in the merge message
+    pub branch: Option<String>,
+    /// Commits of the change, newest first; the merge commit itself is the id
+    pub commits: Vec<String>,
+    pub commit_count: usize,
+    /// Most commits first
+    pub authors: Vec<String>,
+    /// Distinct paths changed by the commits
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub first_commit_date: String,
+    pub last_commit_date: String,
+    pub duration_hours: f64,
+}
+
+/// Change sets of `commits` (newest first, as `commits_since` lists them), newest
+/// first
+pub fn group_change_sets(commits: &[RawCommit]) -> Vec<ChangeSet> {
+    let by_hash: HashMap<&str, &RawCommit> = commits
+        .iter()
+        .map(|commit| (commit.hash.as_str(), commit))
+        .collect();
+
+    let mut mainline: Vec<&RawCommit> = Vec::new();
+    let mut next = commits.first();
+    while let Some(commit) = next {
+        mainline.push(commit);
+        next = commit
+            .parents
+            .first()
+            .and_then(|parent| by_hash.get(parent.as_str()).copied());
+    }
+
+    // Oldest merge first, so a commit merged twice belongs to the first merge
+    let mut claimed: HashSet<&str> = mainline.iter().map(|c| c.hash.as_str()).collect();
+    let mut merged: HashMap<&str, Vec<&RawCommit>> = HashMap::new();
+    for merge in mainline.iter().rev().filter(|c| c.parents.len() > 1) {
+        let mut side = Vec::new();
+        let mut pending: Vec<&str> = merge.parents[1..].iter().map(String::as_str).collect();
+        while let Some(hash) = pending.pop() {
+            let Some(&commit) = by_hash.get(hash) else {
+                continue;
+            };
+            if !claimed.insert(hash) {
+                continue;
+            }
+            side.push(commit);
+            pending.extend(commit.parents.iter().map(String::as_str));
+        }
+        side.sort_by_key(|commit| std::cmp::Reverse(commit.author_time));
+        merged.insert(merge.hash.as_str(), side);
+    }
+
+    let mut change_sets = Vec::new();
+    let mut direct: Vec<&RawCommit> = Vec::new();
+    for &commit in &mainline {
+        let is_merge = commit.parents.len() > 1;
+        let squash_pr = squash_pr_number(commit);
+        let is_squash = squash_pr.is_some() || is_squash_body(&commit.message);
+        if !direct.is_empty() && (is_merge || is_squash || !continues_direct(&direct, commit)) {
+            change_sets.push(direct_change_set(&direct));
+            direct.clear();
+        }
+
+        if is_merge {
+            let (title, pr_number, branch) = merge_title(commit);
+            let side = &merged[commit.hash.as_str()];
+            change_sets.push(change_set(commit, "merge", title, pr_number, branch, side));
+        } else if is_squash {
+            let title = squash_title_re()
+                .replace(&commit.summary, "")
+                .trim()
+                .to_string();
+            change_sets.push(change_set(
+                commit,
+                "squash",
+                title,
+                squash_pr,
+                None,
+                &[commit],
+            ));
+        } else {
+            direct.push(commit);
+        }
+    }
+    if !direct.is_empty() {
+        change_sets.push(direct_change_set(&direct));
+    }
+    change_sets
+}
+
+/// Whether `commit` (older than the others) extends the non-empty run of direct
+/// commits
+fn continues_direct(direct: &[&RawCommit], commit: &RawCommit) -> bool {
+    let last = direct[direct.len() - 1];
+    last.email == commit.email
+        && (last.author_time - commit.author_time).num_hours() < DIRECT_GAP_HOURS
+}
+
+fn direct_change_set(direct: &[&RawCommit]) -> ChangeSet {
+    let newest = direct[0];
+    let oldest = direct[direct.len() - 1];
+    let title = if direct.len() == 1 {
+        newest.summary.clone()
+    } else {
+        format!("{} (+{} more)", oldest.summary, direct.len() - 1)
+    };
+    change_set(newest, "direct", title, None, None, direct)
+}
+
+fn change_set(
+    head: &RawCommit,
+    kind: &str,
+    title: String,
+    pr_number: Option<u64>,
+    branch: Option<String>,
+    commits: &[&RawCommit],
+) -> ChangeSet {
+    let mut author_counts: Vec<(&str, usize)> = Vec::new();
+    let mut paths: HashSet<&str> = HashSet::new();
+    let (mut insertions, mut deletions) = (0, 0);
+    for commit in commits {
+        match author_counts
+            .iter_mut()
+            .find(|(name, _)| *name == commit.author)
+        {
+            Some((_, count)) => *count += 1,
+            None => author_counts.push((&commit.author, 1)),
+        }
+        for file in &commit.files {
+            paths.insert(&file.path);
+            insertions += file.insertions;
+            deletions += file.deletions;
+        }
+    }
+    author_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    // The merge commit closes the change: it is part of its duration
+    let times = commits
+        .iter()
+        .map(|commit| commit.author_time)
+        .chain([head.author_time]);
+    let first = times.clone().min().unwrap_or(head.author_time);
+    let last = times.max().unwrap_or(head.author_time);
+
+    ChangeSet {
+        id: head.hash.clone(),
+        kind: kind.to_string(),
+        title,
+        pr_number,
+        branch,
+        commits: commits.iter().map(|commit| commit.hash.clone()).collect(),
+        commit_count: commits.len(),
+        authors: author_counts
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        files_changed: paths.len(),
+        insertions,
+        deletions,
+        first_commit_date: format_time(&first),
+        last_commit_date: format_time(&last),
+        duration_hours: (last - first).num_seconds() as f64 / 3600.0,
+    }
+}
+
+/// Title, pull request number and branch of a merge commit: the hosting service's
+/// "Merge pull request #12 from owner/branch" is titled by the message body
+fn merge_title(commit: &RawCommit) -> (String, Option<u64>, Option<String>) {
+    static PULL_REQUEST_RE: OnceLock<Regex> = OnceLock::new();
+    static BRANCH_RE: OnceLock<Regex> = OnceLock::new();
+    let pull_request_re = PULL_REQUEST_RE
+        .get_or_init(|| Regex::new(r"^Merge pull request #(\d+) from (\S+)").unwrap());
+    let branch_re = BRANCH_RE
+        .get_or_init(|| Regex::new(r"^Merge (?:remote-tracking )?branch '([^']+)'").unwrap());
+
+    if let Some(captures) = pull_request_re.captures(&commit.summary) {
+        let source = &captures[2];
+        // "owner/feature/x" -> "feature/x"
+        let branch = source.split_once('/').map_or(source, |(_, branch)| branch);
+        let title = commit
+            .message
+            .lines()
+            .skip(1)
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or(&commit.summary);
+        return (
+            title.to_string(),
+            captures[1].parse().ok(),
+            Some(branch.to_string()),
+        );
+    }
+    let branch = branch_re
+        .captures(&commit.summary)
+        .map(|captures| captures[1].to_string());
+    (commit.summary.clone(), None, branch)
+}
+
+fn squash_title_re() -> &'static Regex {
+    static SQUASH_TITLE_RE: OnceLock<Regex> = OnceLock::new();
+    SQUASH_TITLE_RE.get_or_init(|| Regex::new(r"\s*\(#(\d+)\)$").unwrap())
+}
+
+/// The "(#12)" suffix hosting services add to squash-merged pull requests
+fn squash_pr_number(commit: &RawCommit) -> Option<u64> {
+    if commit.parents.len() > 1 {
+        return None;
+    }
+    squash_title_re()
+        .captures(commit.summary.trim_end())
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Body written by `git merge --squash`
+fn is_squash_body(message: &str) -> bool {
+    message.contains("Squashed commit of the following")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_merges_squashes_and_direct_commits() {
+        let commits = [
+            RawCommit::fixture("m1")
+                .parents(&["d2", "f2"])
+                .email("ana@example.com")
+                .at("2024-05-06T18:00:00+00:00")
+                .message("Merge pull request #7 from ana/feature/login\n\nAdd login form"),
+            RawCommit::fixture("f2")
+                .parents(&["f1"])
+                .email("bo@example.com")
+                .at("2024-05-06T12:00:00+00:00")
+                .message("Validate"),
+            RawCommit::fixture("d2")
+                .parents(&["s1"])
+                .email("cy@example.com")
+                .at("2024-05-06T10:00:00+00:00")
+                .message("Fix typo"),
+            RawCommit::fixture("s1")
+                .parents(&["d1"])
+                .email("cy@example.com")
+                .at("2024-05-06T09:00:00+00:00")
+                .message("feat: search (#5)"),
+            RawCommit::fixture("f1")
+                .parents(&["d0"])
+                .email("ana@example.com")
+                .at("2024-05-05T12:00:00+00:00")
+                .message("Login form"),
+            RawCommit::fixture("d1")
+                .parents(&["d0"])
+                .email("cy@example.com")
+                .at("2024-05-05T09:00:00+00:00")
+                .message("Docs"),
+            RawCommit::fixture("d0")
+                .parents(&[])
+                .email("cy@example.com")
+                .at("2024-05-05T08:00:00+00:00")
+                .message("Init"),
+        ]
+        // Every commit changes a file of its own
+        .map(|commit| {
+            let path = format!("{}.rs", commit.hash);
+            commit.files(&[(&path, 10, 2)])
+        });
+
+        let sets = group_change_sets(&commits);
+        let kinds: Vec<(&str, &str)> = sets
+            .iter()
+            .map(|s| (s.kind.as_str(), s.id.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("merge", "m1"),
+                ("direct", "d2"),
+                ("squash", "s1"),
+                ("direct", "d1")
+            ]
+        );
+
+        let merge = &sets[0];
+        assert_eq!(merge.title, "Add login form");
+        assert_eq!(merge.pr_number, Some(7));
+        assert_eq!(merge.branch.as_deref(), Some("feature/login"));
+        assert_eq!(merge.commits, vec!["f2", "f1"]);
+        assert_eq!(merge.authors, vec!["ana", "bo"]);
+        assert_eq!(
+            (merge.files_changed, merge.insertions, merge.deletions),
+            (2, 20, 4)
+        );
+        assert_eq!(merge.duration_hours, 30.0);
+
+        assert_eq!(
+            (sets[2].title.as_str(), sets[2].pr_number),
+            ("feat: search", Some(5))
+        );
+        // One author, one hour apart: a single push
+        assert_eq!(sets[3].commits, vec!["d1", "d0"]);
+        assert_eq!(sets[3].title, "Init (+1 more)");
+        assert_eq!(sets[3].duration_hours, 1.0);
+    }
+}
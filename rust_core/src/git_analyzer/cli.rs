@@ -0,0 +1,643 @@
+// rust_core/src/git_analyzer/cli.rs
+//! Repository access through the `git` binary
+//!
+//! Fallback for repositories libgit2 cannot open. Output is requested in
+//! machine-oriented formats (strict ISO dates, unit/record separators, untranslated
+//! messages) so parsing does not depend on the user's locale or configuration.
+
+use super::backend::{
+    format_time, CommitSpan, FileChange, FileStat, GitBackend, RawBranch, RawCommit, RawTag,
+    RawWorkingTree,
+};
+use super::diff::{DiffHunk, DiffLine, DiffOptions, FileDiff};
+use super::history::FileRevision;
+use crate::error::CdeError;
+use chrono::{DateTime, FixedOffset, Local};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Separates the fields of one record in formatted output
+const FIELD: char = '\x1f';
+/// Starts each record in formatted output
+const RECORD: char = '\x1e';
+
+/// `git log` format of the records read by `parse_log`
+const LOG_FORMAT: &str = "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%B%x1f";
+
+pub struct CliBackend {
+    path: PathBuf,
+}
+
+impl CliBackend {
+    pub fn open(path: &Path) -> Result<Self, CdeError> {
+        let backend = Self {
+            path: path.to_path_buf(),
+        };
+        backend.git(&["rev-parse", "--git-dir"])?;
+        Ok(backend)
+    }
+
+    fn command(&self, args: &[&str]) -> Result<Output, CdeError> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.path)
+            .args(["-c", "core.quotepath=off", "-c", "log.showSignature=false"])
+            .args(args)
+            .env("LC_ALL", "C")
+            .output()
+            .map_err(|e| CdeError::io("Failed to execute git command").caused_by(&e))
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, CdeError> {
+        let output = self.command(args)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(CdeError::git(String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+
+    /// `git diff` of the working copy, or of the index with `--cached`
+    fn diff_changes(&self, cached: bool) -> Result<Vec<FileChange>, CdeError> {
+        let diff = |format: &str| {
+            let mut args = vec!["diff", "--no-renames", "--no-ext-diff", "-z", format];
+            if cached {
+                args.push("--cached");
+            }
+            self.git(&args)
+        };
+        let stats: Vec<FileStat> = diff("--numstat")?
+            .split('\0')
+            .filter_map(parse_numstat)
+            .collect();
+        let name_status = diff("--name-status")?;
+        let fields: Vec<&str> = name_status.split('\0').collect();
+
+        Ok(fields
+            .chunks_exact(2)
+            .filter_map(|pair| {
+                let change = match pair[0] {
+                    "A" => "added",
+                    "D" => "deleted",
+                    "M" => "modified",
+                    "T" => "typechange",
+                    _ => return None,
+                };
+                let stat = stats.iter().find(|stat| stat.path == pair[1]);
+                Some(FileChange {
+                    path: pair[1].to_string(),
+                    change: change.to_string(),
+                    insertions: stat.map_or(0, |stat| stat.insertions),
+                    deletions: stat.map_or(0, |stat| stat.deletions),
+                })
+            })
+            .collect())
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn current_branch(&self) -> Result<String, CdeError> {
+        Ok(self
+            .git(&["rev-parse", "--abbrev-ref", "HEAD"])?
+            .trim()
+            .to_string())
+    }
+
+    fn remote_url(&self) -> Option<String> {
+        let url = self.git(&["config", "--get", "remote.origin.url"]).ok()?;
+        Some(url.trim().to_string()).filter(|url| !url.is_empty())
+    }
+
+    fn head_commit(&self) -> Result<String, CdeError> {
+        Ok(self
+            .git(&["rev-parse", "--verify", "HEAD^{commit}"])?
+            .trim()
+            .to_string())
+    }
+
+    fn git_dir(&self) -> Result<PathBuf, CdeError> {
+        let dir = self.git(&["rev-parse", "--absolute-git-dir"])?;
+        Ok(PathBuf::from(dir.trim()))
+    }
+
+    fn commit_span(&self) -> Result<CommitSpan, CdeError> {
+        let dates: Vec<DateTime<FixedOffset>> = self
+            .git(&["log", "--format=%aI"])?
+            .lines()
+            .filter_map(|line| DateTime::parse_from_rfc3339(line.trim()).ok())
+            .collect();
+        match (dates.iter().min(), dates.iter().max()) {
+            (Some(first), Some(last)) => Ok(CommitSpan {
+                total_commits: dates.len(),
+                first_commit_time: *first,
+                last_commit_time: *last,
+            }),
+            _ => Err(CdeError::git("Repository has no commits")),
+        }
+    }
+
+    fn commits_since(&self, since: DateTime<Local>) -> Result<Vec<RawCommit>, CdeError> {
+        let output = self.git(&[
+            "log",
+            "--no-renames",
+            "--numstat",
+            &format!("--since={}", since.to_rfc3339()),
+            LOG_FORMAT,
+        ])?;
+        Ok(parse_log(&output))
+    }
+
+    fn commits_between(&self, from_ref: &str, to_ref: &str) -> Result<Vec<RawCommit>, CdeError> {
+        let output = self.git(&[
+            "log",
+            "--no-renames",
+            "--numstat",
+            LOG_FORMAT,
+            &format!("{}..{}", from_ref, to_ref),
+            "--",
+        ])?;
+        Ok(parse_log(&output))
+    }
+
+    fn default_branch(&self) -> Option<String> {
+        if let Ok(target) = self.git(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"]) {
+            return Some(target.trim().to_string());
+        }
+        ["main", "master"]
+            .into_iter()
+            .find(|name| {
+                self.git(&[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("refs/heads/{}", name),
+                ])
+                .is_ok()
+            })
+            .map(str::to_string)
+    }
+
+    fn branches(&self, base: &str) -> Result<Vec<RawBranch>, CdeError> {
+        let output = self.git(&[
+            "for-each-ref",
+            "--format=%(refname:short)%1f%(refname)%1f%(committerdate:iso-strict)%1f%(symref)",
+            "refs/heads",
+            "refs/remotes",
+        ])?;
+        let mut branches = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split(FIELD).collect();
+            let [name, refname, date, symref] = fields[..] else {
+                continue;
+            };
+            let Ok(last_commit_time) = DateTime::parse_from_rfc3339(date) else {
+                continue;
+            };
+            if !symref.is_empty() {
+                continue;
+            }
+            let (ahead, behind) = self.ahead_behind(refname, base).unwrap_or((0, 0));
+            branches.push(RawBranch {
+                name: name.to_string(),
+                last_commit_time,
+                ahead,
+                behind,
+            });
+        }
+        Ok(branches)
+    }
+
+    fn ahead_behind(&self, branch: &str, base: &str) -> Result<(usize, usize), CdeError> {
+        let counts = self.git(&[
+            "rev-list",
+            "--left-right",
+            "--count",
+            &format!("{}...{}", branch, base),
+        ])?;
+        let mut counts = counts.split_whitespace().map(|n| n.parse().unwrap_or(0));
+        Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+    }
+
+    fn merge_base(&self, branch: &str, base: &str) -> Result<String, CdeError> {
+        Ok(self.git(&["merge-base", branch, base])?.trim().to_string())
+    }
+
+    fn merge_conflicts(&self, branch: &str, base: &str) -> Result<Vec<String>, CdeError> {
+        // Exits with 1 and lists the conflicted paths after the tree id on conflicts
+        let output = self.command(&[
+            "merge-tree",
+            "--write-tree",
+            "--name-only",
+            "--no-messages",
+            base,
+            branch,
+        ])?;
+        match output.status.code() {
+            Some(0) => Ok(Vec::new()),
+            Some(1) => {
+                let mut paths: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .skip(1)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                paths.sort();
+                paths.dedup();
+                Ok(paths)
+            }
+            _ => Err(CdeError::git(String::from_utf8_lossy(&output.stderr).trim())),
+        }
+    }
+
+    fn tags(&self) -> Result<Vec<RawTag>, CdeError> {
+        let output = self.git(&[
+            "for-each-ref",
+            "--sort=-creatordate",
+            "--format=%(refname:short)%1f%(creatordate:iso-strict)\
+                      %1f%(objecttype)%1f%(objectname)%1f%(authordate:iso-strict)%1f%(contents:subject)\
+                      %1f%(*objecttype)%1f%(*objectname)%1f%(*authordate:iso-strict)%1f%(*contents:subject)",
+            "refs/tags",
+        ])?;
+        Ok(output.lines().filter_map(parse_tag).collect())
+    }
+
+    fn working_tree(&self) -> Result<RawWorkingTree, CdeError> {
+        let status = self.git(&[
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--no-renames",
+            "--untracked-files=all",
+        ])?;
+        let mut tree = RawWorkingTree {
+            staged: self.diff_changes(true)?,
+            unstaged: self.diff_changes(false)?,
+            ..RawWorkingTree::default()
+        };
+        // `XY <path>` entries; unmerged states are DD, AU, UD, UA, DU, AA and UU
+        for entry in status.split('\0').filter(|entry| entry.len() > 3) {
+            let (code, path) = (&entry[..2], entry[3..].to_string());
+            if code == "??" {
+                tree.untracked.push(path);
+            } else if code.contains('U') || code == "DD" || code == "AA" {
+                tree.conflicted.push(path);
+            }
+        }
+        Ok(tree)
+    }
+
+    fn file_history(&self, path: &str, follow_renames: bool) -> Result<Vec<FileRevision>, CdeError> {
+        let output = self.git(&[
+            "log",
+            "--no-merges",
+            if follow_renames {
+                "--follow"
+            } else {
+                "--no-renames"
+            },
+            "--raw",
+            "--numstat",
+            "-z",
+            LOG_FORMAT,
+            "--",
+            path,
+        ])?;
+        Ok(output
+            .split(RECORD)
+            .filter_map(parse_record)
+            .filter_map(|(commit, changes)| parse_file_change(commit, changes))
+            .collect())
+    }
+
+    fn diff(
+        &self,
+        from_ref: &str,
+        to_ref: &str,
+        options: &DiffOptions,
+    ) -> Result<Vec<FileDiff>, CdeError> {
+        let context = format!("-U{}", options.context_lines);
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff", &context];
+        args.push(if options.detect_renames {
+            "-M"
+        } else {
+            "--no-renames"
+        });
+        if options.ignore_whitespace {
+            args.push("-w");
+        }
+        args.extend([from_ref, to_ref, "--"]);
+        args.extend(options.paths.iter().map(String::as_str));
+        Ok(parse_diff(&self.git(&args)?))
+    }
+}
+
+/// Commits of `git log --numstat` in the record format of `LOG_FORMAT`
+fn parse_log(output: &str) -> Vec<RawCommit> {
+    output
+        .split(RECORD)
+        .filter_map(parse_record)
+        .map(|(mut commit, numstat)| {
+            commit.files = numstat.lines().filter_map(parse_numstat).collect();
+            commit
+        })
+        .collect()
+}
+
+/// One `LOG_FORMAT` record: the commit without files, and the diff output after it
+fn parse_record(record: &str) -> Option<(RawCommit, &str)> {
+    let fields: Vec<&str> = record.splitn(7, FIELD).collect();
+    let [hash, parents, author, email, date, message, rest] = fields[..] else {
+        return None;
+    };
+    let message = message.trim_end().to_string();
+    let commit = RawCommit {
+        hash: hash.trim().to_string(),
+        parents: parents.split_whitespace().map(str::to_string).collect(),
+        author: author.to_string(),
+        email: email.to_string(),
+        author_time: DateTime::parse_from_rfc3339(date.trim()).ok()?,
+        summary: message.lines().next().unwrap_or_default().to_string(),
+        message,
+        files: Vec::new(),
+    };
+    Some((commit, rest))
+}
+
+/// The `--raw --numstat -z` entries of a single-file `git log` record: a raw
+/// `:<modes> <ids> <status>` field followed by one path (two for renames), then
+/// `<insertions>\t<deletions>\t<path>` (an empty path followed by both names
+/// for renames)
+fn parse_file_change(commit: RawCommit, changes: &str) -> Option<FileRevision> {
+    let mut fields = changes.trim_start_matches(['\0', '\n']).split('\0');
+    let raw = fields.next()?;
+    let status = raw.rsplit(' ').next()?;
+    let change = match status.chars().next()? {
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'T' => "typechange",
+        _ => "modified",
+    };
+    let (old_path, path) = if change == "renamed" {
+        (Some(fields.next()?.to_string()), fields.next()?.to_string())
+    } else {
+        (None, fields.next()?.to_string())
+    };
+    let mut numstat = fields.next()?.splitn(3, '\t');
+    let (insertions, deletions) = (numstat.next()?, numstat.next()?);
+
+    Some(FileRevision {
+        hash: commit.hash,
+        author: commit.author,
+        email: commit.email,
+        date: format_time(&commit.author_time),
+        summary: commit.summary,
+        path,
+        old_path,
+        change: change.to_string(),
+        insertions: insertions.parse().unwrap_or(0),
+        deletions: deletions.parse().unwrap_or(0),
+    })
+}
+
+/// Files of unified `git diff` output
+fn parse_diff(output: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let (mut old_lineno, mut new_lineno) = (0, 0);
+    for line in output.lines() {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            // Overwritten by the ---/+++ or rename lines when the paths contain " b/"
+            let path = paths.rsplit_once(" b/").map_or(paths, |(_, path)| path);
+            files.push(FileDiff {
+                path: path.to_string(),
+                old_path: None,
+                change: "modified".to_string(),
+                binary: false,
+                insertions: 0,
+                deletions: 0,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if let Some(hunk) = file.hunks.last_mut() {
+            let (kind, content) = match line.split_at_checked(1) {
+                Some((" ", content)) => ("context", content),
+                Some(("+", content)) => ("added", content),
+                Some(("-", content)) => ("removed", content),
+                // "\ No newline at end of file"
+                Some(("\\", _)) => continue,
+                _ if line.is_empty() => ("context", ""),
+                _ => ("", ""),
+            };
+            if !kind.is_empty() {
+                hunk.lines.push(DiffLine {
+                    kind: kind.to_string(),
+                    content: content.to_string(),
+                    old_lineno: (kind != "added").then_some(old_lineno),
+                    new_lineno: (kind != "removed").then_some(new_lineno),
+                });
+                if kind != "added" {
+                    old_lineno += 1;
+                }
+                if kind != "removed" {
+                    new_lineno += 1;
+                }
+                continue;
+            }
+        }
+        if let Some(header) = line.strip_prefix("@@ -") {
+            let Some((ranges, _)) = header.split_once(" @@") else {
+                continue;
+            };
+            let Some((old, new)) = ranges.split_once(" +") else {
+                continue;
+            };
+            let range = |range: &str| {
+                let (start, lines) = range.split_once(',').unwrap_or((range, "1"));
+                (start.parse().unwrap_or(0), lines.parse().unwrap_or(0))
+            };
+            let ((old_start, old_lines), (new_start, new_lines)) = (range(old), range(new));
+            (old_lineno, new_lineno) = (old_start, new_start);
+            file.hunks.push(DiffHunk {
+                header: line.trim_end().to_string(),
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            });
+        } else if line.starts_with("new file mode") {
+            file.change = "added".to_string();
+        } else if line.starts_with("deleted file mode") {
+            file.change = "deleted".to_string();
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            file.change = "renamed".to_string();
+            file.old_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            file.path = path.to_string();
+        } else if line.starts_with("Binary files ") {
+            file.binary = true;
+        } else if let Some(path) = line.strip_prefix("+++ b/") {
+            file.path = path.to_string();
+        } else if let Some(path) = line.strip_prefix("--- a/") {
+            if file.change == "deleted" {
+                file.path = path.to_string();
+            }
+        }
+    }
+    files.into_iter().map(FileDiff::with_line_counts).collect()
+}
+
+/// `<insertions>\t<deletions>\t<path>`; binary files report `-` for both counts
+fn parse_numstat(line: &str) -> Option<FileStat> {
+    let mut parts = line.splitn(3, '\t');
+    let (insertions, deletions, path) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(FileStat {
+        path: path.to_string(),
+        insertions: insertions.parse().unwrap_or(0),
+        deletions: deletions.parse().unwrap_or(0),
+    })
+}
+
+/// One `for-each-ref` line of `tags`; annotated tags take the peeled (`*`) fields
+fn parse_tag(line: &str) -> Option<RawTag> {
+    let fields: Vec<&str> = line.split(FIELD).collect();
+    let [name, created, kind, hash, date, subject, peeled_kind, peeled_hash, peeled_date, peeled_subject] =
+        fields[..]
+    else {
+        return None;
+    };
+    let (hash, date, subject) = match (kind, peeled_kind) {
+        ("commit", _) => (hash, date, subject),
+        (_, "commit") => (peeled_hash, peeled_date, peeled_subject),
+        _ => return None,
+    };
+    let commit_time = DateTime::parse_from_rfc3339(date).ok()?;
+    Some(RawTag {
+        name: name.to_string(),
+        commit_hash: hash.to_string(),
+        commit_time,
+        commit_summary: subject.to_string(),
+        created: DateTime::parse_from_rfc3339(created).unwrap_or(commit_time),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+
+    fn commit_summary(commit: &RawCommit) -> Value {
+        let files: Vec<String> = commit
+            .files
+            .iter()
+            .map(|file| format!("{} +{} -{}", file.path, file.insertions, file.deletions))
+            .collect();
+        json!({
+            "hash": commit.hash,
+            "parents": commit.parents,
+            "author": commit.author,
+            "email": commit.email,
+            "date": commit.author_time.to_rfc3339(),
+            "summary": commit.summary,
+            "message": commit.message,
+            "files": files,
+        })
+    }
+
+    /// `*.log` files are `commits_since` output, `*.z` files `file_history` output
+    #[test]
+    fn test_git_log_corpus() {
+        crate::corpus::check("git_log", |path| {
+            let output = std::fs::read_to_string(path).unwrap();
+            if path.extension().is_some_and(|extension| extension == "z") {
+                let revisions: Vec<FileRevision> = output
+                    .split(RECORD)
+                    .filter_map(parse_record)
+                    .filter_map(|(commit, changes)| parse_file_change(commit, changes))
+                    .collect();
+                serde_json::to_value(revisions).unwrap()
+            } else {
+                parse_log(&output).iter().map(commit_summary).collect()
+            }
+        });
+    }
+
+    /// What `git log --numstat` prints for `commit` with `LOG_FORMAT`
+    fn log_record(commit: &RawCommit) -> String {
+        let mut record = format!(
+            "{RECORD}{}{FIELD}{}{FIELD}{}{FIELD}{}{FIELD}{}{FIELD}{}\n{FIELD}\n",
+            commit.hash,
+            commit.parents.join(" "),
+            commit.author,
+            commit.email,
+            commit.author_time.to_rfc3339(),
+            commit.message,
+        );
+        if !commit.files.is_empty() {
+            record.push('\n');
+        }
+        for file in &commit.files {
+            record.push_str(&format!(
+                "{}\t{}\t{}\n",
+                file.insertions, file.deletions, file.path
+            ));
+        }
+        record
+    }
+
+    prop_compose! {
+        fn raw_commit()(
+            hash in "[0-9a-f]{40}",
+            parents in prop::collection::vec("[0-9a-f]{40}", 0..3),
+            author in r"[^\x1e\x1f\n]{0,20}",
+            email in r"[^\x1e\x1f\n]{0,20}",
+            seconds in 0i64..4_000_000_000,
+            offset_minutes in -720i32..=840,
+            message in r"([^\x1e\x1f\r\n]{0,30}\n){0,4}",
+            files in prop::collection::vec((0usize..100_000, 0usize..100_000, r"[^\t\r\n\x1e\x1f]{1,30}"), 0..4),
+        ) -> RawCommit {
+            let message = message.trim_end().to_string();
+            RawCommit {
+                hash,
+                parents,
+                author,
+                email,
+                author_time: FixedOffset::east_opt(offset_minutes * 60)
+                    .unwrap()
+                    .timestamp_opt(seconds, 0)
+                    .unwrap(),
+                summary: message.lines().next().unwrap_or_default().to_string(),
+                message,
+                files: files
+                    .into_iter()
+                    .map(|(insertions, deletions, path)| FileStat { path, insertions, deletions })
+                    .collect(),
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_log_round_trips(commits in prop::collection::vec(raw_commit(), 0..5)) {
+            let output: String = commits.iter().map(log_record).collect();
+            prop_assert_eq!(parse_log(&output), commits);
+        }
+
+        #[test]
+        fn prop_parsers_never_panic(output in r"[\x00\x1e\x1f\t\n :A-Z0-9a-f+-]{0,200}|\PC{0,200}") {
+            parse_log(&output);
+            for (commit, changes) in output.split(RECORD).filter_map(parse_record) {
+                parse_file_change(commit, changes);
+            }
+            for line in output.lines() {
+                parse_tag(line);
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// rust_core/src/git_analyzer/decisions.rs
+//! Architectural decisions found in history, checked against ADR files
+//!
+//! Commits are classified by keyword sets (decision type -> keywords), and
+//! Architecture Decision Records found by the documentation scan are matched to
+//! them: a decision is synced when an ADR cites its commit hash or the commit
+//! itself touched an ADR. The rest are reported so they can be written down.
+
+use super::backend::{format_time, RawCommit};
+use super::ArchitecturalDecision;
+use crate::documentation::Document;
+use glob::Pattern;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Shortest hexadecimal string taken as a commit reference in an ADR
+const MIN_HASH_LENGTH: usize = 7;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecisionOptions {
+    /// Decision type -> keywords matched case-insensitively in commit messages
+    pub keywords: BTreeMap<String, Vec<String>>,
+    /// Repository-relative globs of ADR files
+    pub adr_patterns: Vec<String>,
+}
+
+impl Default for DecisionOptions {
+    fn default() -> Self {
+        let keywords = [
+            ("refactor", &["refactor"][..]),
+            ("migration", &["migrate", "migration"]),
+            ("architecture", &["architecture", "redesign"]),
+            ("deprecation", &["deprecate"]),
+            ("breaking", &["breaking"]),
+        ];
+        Self {
+            keywords: keywords
+                .iter()
+                .map(|(kind, words)| {
+                    (
+                        kind.to_string(),
+                        words.iter().map(|w| w.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            adr_patterns: vec!["docs/adr/*.md".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdrRecord {
+    pub path: String,
+    /// Frontmatter title, else the first heading, else the file name
+    pub title: String,
+    pub status: Option<String>,
+    /// Hexadecimal strings of commit-hash length cited in the record
+    pub cited_hashes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncedDecision {
+    pub decision: ArchitecturalDecision,
+    pub adr_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DecisionSync {
+    pub total_decisions: usize,
+    pub synced: Vec<SyncedDecision>,
+    /// Decisions no ADR accounts for
+    pub unsynced: Vec<ArchitecturalDecision>,
+    pub adrs: Vec<AdrRecord>,
+}
+
+/// One decision per matching (type, commit) pair, grouped by type
+pub fn find_decisions(
+    commits: &[RawCommit],
+    keywords: &BTreeMap<String, Vec<String>>,
+) -> Vec<ArchitecturalDecision> {
+    let mut decisions = Vec::new();
+    for (decision_type, words) in keywords {
+        let words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+        for commit in commits {
+            // Matches the whole message, like `git log --grep -i`
+            let message = commit.message.to_lowercase();
+            if words
+                .iter()
+                .any(|word| !word.is_empty() && message.contains(word.as_str()))
+            {
+                decisions.push(decision(commit, decision_type));
+            }
+        }
+    }
+    decisions
+}
+
+fn decision(commit: &RawCommit, decision_type: &str) -> ArchitecturalDecision {
+    let message = commit.summary.clone();
+    let lowercase = message.to_lowercase();
+
+    let impact = if lowercase.contains("breaking") || lowercase.contains("major") {
+        "high"
+    } else if lowercase.contains("minor") || lowercase.contains("fix") {
+        "low"
+    } else {
+        "medium"
+    };
+
+    ArchitecturalDecision {
+        commit_hash: commit.hash.clone(),
+        date: format_time(&commit.author_time),
+        author: commit.author.clone(),
+        message,
+        decision_type: decision_type.to_string(),
+        impact: impact.to_string(),
+    }
+}
+
+/// The scanned documents under `root` matching one of `patterns`
+pub fn find_adrs(root: &Path, documents: &[Document], patterns: &[String]) -> Vec<AdrRecord> {
+    static HASH_RE: OnceLock<Regex> = OnceLock::new();
+    let hash_re = HASH_RE.get_or_init(|| Regex::new(r"\b[0-9a-f]{7,40}\b").unwrap());
+    let patterns: Vec<Pattern> = patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect();
+
+    let mut adrs: Vec<AdrRecord> = documents
+        .iter()
+        .filter_map(|document| {
+            let path = relative_path(root, &document.path);
+            if !patterns.iter().any(|pattern| pattern.matches(&path)) {
+                return None;
+            }
+            let metadata = document.metadata.as_ref();
+            let title = metadata
+                .and_then(|m| m.title.clone())
+                .or_else(|| {
+                    document
+                        .headers
+                        .first()
+                        .map(|header| header.trim().to_string())
+                })
+                .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(&path).to_string());
+            let mut cited_hashes: Vec<String> = hash_re
+                .find_iter(&document.content)
+                .map(|m| m.as_str().to_string())
+                // Plain numbers are dates or counts, not hashes
+                .filter(|hash| {
+                    hash.len() >= MIN_HASH_LENGTH && hash.chars().any(|c| c.is_ascii_alphabetic())
+                })
+                .collect();
+            cited_hashes.sort();
+            cited_hashes.dedup();
+            Some(AdrRecord {
+                title,
+                status: metadata
+                    .and_then(|m| m.status.clone())
+                    .or_else(|| status_line(&document.content)),
+                cited_hashes,
+                path,
+            })
+        })
+        .collect();
+    adrs.sort_by(|a, b| a.path.cmp(&b.path));
+    adrs
+}
+
+/// Splits `decisions` into those an ADR accounts for and the others
+pub fn cross_reference(
+    decisions: Vec<ArchitecturalDecision>,
+    commits: &[RawCommit],
+    adrs: Vec<AdrRecord>,
+    patterns: &[String],
+) -> DecisionSync {
+    let patterns: Vec<Pattern> = patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect();
+    let total_decisions = decisions.len();
+    let (mut synced, mut unsynced) = (Vec::new(), Vec::new());
+
+    for decision in decisions {
+        let touched_adrs: Vec<&str> = commits
+            .iter()
+            .filter(|commit| commit.hash == decision.commit_hash)
+            .flat_map(|commit| &commit.files)
+            .map(|file| file.path.as_str())
+            .filter(|path| patterns.iter().any(|pattern| pattern.matches(path)))
+            .collect();
+        let mut adr_paths: Vec<String> = adrs
+            .iter()
+            .filter(|adr| {
+                adr.cited_hashes
+                    .iter()
+                    .any(|hash| decision.commit_hash.starts_with(hash.as_str()))
+            })
+            .map(|adr| adr.path.clone())
+            .collect();
+        // Also when the ADR the commit wrote has since been moved or deleted
+        for path in touched_adrs {
+            if !adr_paths.iter().any(|known| known == path) {
+                adr_paths.push(path.to_string());
+            }
+        }
+
+        if adr_paths.is_empty() {
+            unsynced.push(decision);
+        } else {
+            synced.push(SyncedDecision {
+                decision,
+                adr_paths,
+            });
+        }
+    }
+
+    DecisionSync {
+        total_decisions,
+        synced,
+        unsynced,
+        adrs,
+    }
+}
+
+/// `Status: Accepted` lines of ADR templates (bold or not), or a `## Status`
+/// heading followed by the value
+fn status_line(content: &str) -> Option<String> {
+    let mut lines = content.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let plain = line.trim_start_matches(['#', '*', ' ']);
+        if !plain.to_lowercase().starts_with("status") {
+            continue;
+        }
+        let value = plain["status".len()..]
+            .trim_start_matches([':', '*', ' '])
+            .trim();
+        if !value.is_empty() {
+            return Some(value.to_string());
+        }
+        if line.starts_with('#') {
+            return lines.find(|line| !line.is_empty()).map(str::to_string);
+        }
+    }
+    None
+}
+
+fn relative_path(root: &Path, path: &str) -> String {
+    let path = Path::new(path);
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
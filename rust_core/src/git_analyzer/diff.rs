@@ -0,0 +1,90 @@
+// rust_core/src/git_analyzer/diff.rs
+//! Structured diffs between two revisions
+//!
+//! Every changed file is reported with its kind of change, the rename source,
+//! whether it is binary and its hunks line by line, so callers can summarize a
+//! change without parsing `git diff` output themselves.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Unchanged lines shown around each change
+    pub context_lines: u32,
+    /// Pair deleted and added files with similar content as renames
+    pub detect_renames: bool,
+    pub ignore_whitespace: bool,
+    /// Limit the diff to these paths (pathspecs); empty means every file
+    pub paths: Vec<String>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            context_lines: 3,
+            detect_renames: true,
+            ignore_whitespace: false,
+            paths: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitDiff {
+    pub from_ref: String,
+    pub to_ref: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// Previous path of renamed files
+    pub old_path: Option<String>,
+    pub change: String, // "added", "deleted", "modified", "renamed"
+    /// Binary files have no hunks
+    pub binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// The `@@ -a,b +c,d @@` line
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: String, // "context", "added", "removed"
+    /// Line without its trailing newline
+    pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+impl FileDiff {
+    /// Counts the added and removed lines of the hunks
+    pub fn with_line_counts(mut self) -> Self {
+        let lines = self.hunks.iter().flat_map(|hunk| &hunk.lines);
+        let (insertions, deletions) =
+            lines.fold((0, 0), |(added, removed), line| match line.kind.as_str() {
+                "added" => (added + 1, removed),
+                "removed" => (added, removed + 1),
+                _ => (added, removed),
+            });
+        self.insertions = insertions;
+        self.deletions = deletions;
+        self
+    }
+}
@@ -0,0 +1,85 @@
+// rust_core/src/git_analyzer/history.rs
+//! History of a single file
+//!
+//! Lists the commits that touched a file, newest first, optionally following it
+//! across renames (as `git log --follow`), with per-commit line stats and a
+//! summary of who changed it. Merge commits are skipped: their changes are
+//! already reported by the commits they merge.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHistory {
+    pub path: String,
+    pub follow_renames: bool,
+    pub total_commits: usize,
+    /// Most commits first
+    pub authors: Vec<FileAuthor>,
+    pub revisions: Vec<FileRevision>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRevision {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    pub date: String,
+    pub summary: String,
+    /// Path of the file in this commit
+    pub path: String,
+    /// Path before the commit, for renames
+    pub old_path: Option<String>,
+    pub change: String, // "added", "deleted", "modified", "renamed", "typechange"
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileAuthor {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub last_commit_date: String,
+}
+
+impl FileHistory {
+    pub fn new(path: &str, follow_renames: bool, revisions: Vec<FileRevision>) -> Self {
+        let mut authors: Vec<FileAuthor> = Vec::new();
+        // Revisions are newest first: the first one seen per email is the latest
+        for revision in &revisions {
+            let index = match authors.iter().position(|a| a.email == revision.email) {
+                Some(index) => index,
+                None => {
+                    authors.push(FileAuthor {
+                        name: revision.author.clone(),
+                        email: revision.email.clone(),
+                        commits: 0,
+                        insertions: 0,
+                        deletions: 0,
+                        last_commit_date: revision.date.clone(),
+                    });
+                    authors.len() - 1
+                }
+            };
+            let author = &mut authors[index];
+            author.commits += 1;
+            author.insertions += revision.insertions;
+            author.deletions += revision.deletions;
+        }
+        authors.sort_by(|a, b| {
+            b.commits
+                .cmp(&a.commits)
+                .then_with(|| a.email.cmp(&b.email))
+        });
+
+        Self {
+            path: path.to_string(),
+            follow_renames,
+            total_commits: revisions.len(),
+            authors,
+            revisions,
+        }
+    }
+}
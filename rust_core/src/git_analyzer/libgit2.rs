@@ -0,0 +1,630 @@
+// rust_core/src/git_analyzer/libgit2.rs
+//! In-process repository access through libgit2
+//!
+//! A `Repository` handle is not `Sync`, so every query opens its own; opening is
+//! cheap next to walking history. Per-commit diff stats, the expensive part, are
+//! computed in parallel chunks, each with its own handle.
+
+use super::backend::format_time;
+use super::backend::{
+    CommitSpan, FileChange, FileStat, GitBackend, RawBranch, RawCommit, RawTag, RawWorkingTree,
+};
+use super::diff::{DiffHunk, DiffLine, DiffOptions, FileDiff};
+use super::history::FileRevision;
+use crate::cancel::CancellationToken;
+use crate::error::CdeError;
+use chrono::{DateTime, FixedOffset, Local};
+use git2::{
+    BranchType, Commit, Delta, Diff, DiffFindOptions, Oid, Patch, Repository, Sort, StatusOptions,
+};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Commits whose stats are computed by one parallel task
+const STATS_CHUNK_SIZE: usize = 64;
+
+pub struct Git2Backend {
+    path: PathBuf,
+}
+
+impl Git2Backend {
+    pub fn open(path: &Path) -> Result<Self, CdeError> {
+        let backend = Self {
+            path: path.to_path_buf(),
+        };
+        backend.repo()?;
+        Ok(backend)
+    }
+
+    fn repo(&self) -> Result<Repository, CdeError> {
+        Repository::open(&self.path).map_err(git_error)
+    }
+
+    /// Commits with their per-file stats, in the order of `oids`. Chunks not
+    /// started when `cancel` is cancelled are skipped, and the result stops at the
+    /// first skipped chunk so it stays a prefix of `oids`.
+    fn raw_commits(
+        &self,
+        oids: &[Oid],
+        cancel: &CancellationToken,
+    ) -> Result<Vec<RawCommit>, CdeError> {
+        let chunks: Vec<Option<Vec<RawCommit>>> = oids
+            .par_chunks(STATS_CHUNK_SIZE)
+            .map(|chunk| {
+                if cancel.is_cancelled() {
+                    return Ok(None);
+                }
+                let repo = self.repo()?;
+                chunk
+                    .iter()
+                    .map(|oid| raw_commit(&repo, *oid))
+                    .collect::<Result<Vec<_>, CdeError>>()
+                    .map(Some)
+            })
+            .collect::<Result<_, CdeError>>()?;
+        Ok(chunks.into_iter().map_while(|chunk| chunk).flatten().collect())
+    }
+}
+
+fn peel_commit<'r>(repo: &'r Repository, rev: &str) -> Result<Commit<'r>, CdeError> {
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| CdeError::not_found(format!("Unknown revision '{}'", rev)).caused_by(&e))
+}
+
+impl GitBackend for Git2Backend {
+    fn current_branch(&self) -> Result<String, CdeError> {
+        let repo = self.repo()?;
+        let head = repo.head().map_err(git_error)?;
+        Ok(String::from_utf8_lossy(head.shorthand_bytes()).into_owned())
+    }
+
+    fn remote_url(&self) -> Option<String> {
+        let repo = self.repo().ok()?;
+        let remote = repo.find_remote("origin").ok()?;
+        remote.url().map(str::to_string)
+    }
+
+    fn head_commit(&self) -> Result<String, CdeError> {
+        let repo = self.repo()?;
+        let commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(git_error)?;
+        Ok(commit.id().to_string())
+    }
+
+    fn git_dir(&self) -> Result<PathBuf, CdeError> {
+        Ok(self.repo()?.path().to_path_buf())
+    }
+
+    fn commit_span(&self) -> Result<CommitSpan, CdeError> {
+        let repo = self.repo()?;
+        let mut walk = repo.revwalk().map_err(git_error)?;
+        walk.push_head().map_err(git_error)?;
+
+        let mut total_commits = 0;
+        let mut span: Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> = None;
+        for oid in walk {
+            let commit = repo
+                .find_commit(oid.map_err(git_error)?)
+                .map_err(git_error)?;
+            let time = to_datetime(commit.author().when());
+            total_commits += 1;
+            span = Some(match span {
+                Some((first, last)) => (first.min(time), last.max(time)),
+                None => (time, time),
+            });
+        }
+        let (first_commit_time, last_commit_time) =
+            span.ok_or_else(|| CdeError::git("Repository has no commits"))?;
+        Ok(CommitSpan {
+            total_commits,
+            first_commit_time,
+            last_commit_time,
+        })
+    }
+
+    fn commits_since(&self, since: DateTime<Local>) -> Result<Vec<RawCommit>, CdeError> {
+        self.commits_since_cancellable(since, &CancellationToken::default())
+    }
+
+    fn commits_since_cancellable(
+        &self,
+        since: DateTime<Local>,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<RawCommit>, CdeError> {
+        let repo = self.repo()?;
+        let mut walk = repo.revwalk().map_err(git_error)?;
+        walk.set_sorting(Sort::TIME).map_err(git_error)?;
+        walk.push_head().map_err(git_error)?;
+
+        // Time-sorted, so the first older commit ends the range (as `git log --since`)
+        let since = since.timestamp();
+        let mut oids = Vec::new();
+        for oid in walk {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let oid = oid.map_err(git_error)?;
+            let commit = repo.find_commit(oid).map_err(git_error)?;
+            if commit.time().seconds() < since {
+                break;
+            }
+            oids.push(oid);
+        }
+        self.raw_commits(&oids, cancel)
+    }
+
+    fn commits_between(&self, from_ref: &str, to_ref: &str) -> Result<Vec<RawCommit>, CdeError> {
+        let repo = self.repo()?;
+        let (from, to) = (peel_commit(&repo, from_ref)?, peel_commit(&repo, to_ref)?);
+        let mut walk = repo.revwalk().map_err(git_error)?;
+        walk.set_sorting(Sort::TIME).map_err(git_error)?;
+        walk.push(to.id()).map_err(git_error)?;
+        walk.hide(from.id()).map_err(git_error)?;
+        let oids = walk.collect::<Result<Vec<Oid>, _>>().map_err(git_error)?;
+        self.raw_commits(&oids, &CancellationToken::default())
+    }
+
+    fn default_branch(&self) -> Option<String> {
+        let repo = self.repo().ok()?;
+        if let Ok(origin_head) = repo.find_reference("refs/remotes/origin/HEAD") {
+            if let Some(target) = origin_head.symbolic_target() {
+                return Some(target.trim_start_matches("refs/remotes/").to_string());
+            }
+        }
+        ["main", "master"]
+            .into_iter()
+            .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+            .map(str::to_string)
+    }
+
+    fn branches(&self, base: &str) -> Result<Vec<RawBranch>, CdeError> {
+        let repo = self.repo()?;
+        let head = peel_commit(&repo, base).ok().map(|commit| commit.id());
+        let mut branches = Vec::new();
+        for entry in repo.branches(None).map_err(git_error)? {
+            let (branch, _) = entry.map_err(git_error)?;
+            let reference = branch.get();
+            if reference.symbolic_target_bytes().is_some() {
+                continue;
+            }
+            let commit = reference.peel_to_commit().map_err(git_error)?;
+            let (ahead, behind) = match head {
+                Some(head) => repo
+                    .graph_ahead_behind(commit.id(), head)
+                    .map_err(git_error)?,
+                None => (0, 0),
+            };
+            branches.push(RawBranch {
+                name: String::from_utf8_lossy(branch.name_bytes().map_err(git_error)?).into_owned(),
+                last_commit_time: to_datetime(commit.time()),
+                ahead,
+                behind,
+            });
+        }
+        Ok(branches)
+    }
+
+    fn ahead_behind(&self, branch: &str, base: &str) -> Result<(usize, usize), CdeError> {
+        let repo = self.repo()?;
+        let (branch, base) = (peel_commit(&repo, branch)?, peel_commit(&repo, base)?);
+        repo.graph_ahead_behind(branch.id(), base.id())
+            .map_err(git_error)
+    }
+
+    fn merge_base(&self, branch: &str, base: &str) -> Result<String, CdeError> {
+        let repo = self.repo()?;
+        let (branch, base) = (peel_commit(&repo, branch)?, peel_commit(&repo, base)?);
+        let oid = repo.merge_base(branch.id(), base.id()).map_err(git_error)?;
+        Ok(oid.to_string())
+    }
+
+    fn merge_conflicts(&self, branch: &str, base: &str) -> Result<Vec<String>, CdeError> {
+        let repo = self.repo()?;
+        let (theirs, ours) = (peel_commit(&repo, branch)?, peel_commit(&repo, base)?);
+        let index = repo
+            .merge_commits(&ours, &theirs, None)
+            .map_err(git_error)?;
+        let mut paths = Vec::new();
+        for conflict in index.conflicts().map_err(git_error)? {
+            let conflict = conflict.map_err(git_error)?;
+            if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn tags(&self) -> Result<Vec<RawTag>, CdeError> {
+        let repo = self.repo()?;
+        let names = repo.tag_names(None).map_err(git_error)?;
+        let mut tags = Vec::new();
+        for name in names.iter().flatten() {
+            let object = repo
+                .revparse_single(&format!("refs/tags/{}", name))
+                .map_err(git_error)?;
+            // Tags of trees or blobs have no release date to report
+            let Ok(commit) = object.peel_to_commit() else {
+                continue;
+            };
+            let commit_time = to_datetime(commit.author().when());
+            let created = object
+                .as_tag()
+                .and_then(|tag| tag.tagger())
+                .map_or(commit_time, |tagger| to_datetime(tagger.when()));
+            tags.push(RawTag {
+                name: name.to_string(),
+                commit_hash: commit.id().to_string(),
+                commit_time,
+                commit_summary: summary(&commit),
+                created,
+            });
+        }
+        tags.sort_by_key(|tag| std::cmp::Reverse(tag.created));
+        Ok(tags)
+    }
+
+    fn working_tree(&self) -> Result<RawWorkingTree, CdeError> {
+        let repo = self.repo()?;
+        // No HEAD tree yet on an unborn branch: everything staged is added
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree().map_err(git_error)?),
+            Err(_) => None,
+        };
+        let index = repo.index().map_err(git_error)?;
+        let staged = repo
+            .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+            .map_err(git_error)?;
+        let unstaged = repo
+            .diff_index_to_workdir(Some(&index), None)
+            .map_err(git_error)?;
+
+        let mut tree = RawWorkingTree {
+            staged: changed_files(&staged)?,
+            unstaged: changed_files(&unstaged)?,
+            ..RawWorkingTree::default()
+        };
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        for entry in repo.statuses(Some(&mut options)).map_err(git_error)?.iter() {
+            let path = String::from_utf8_lossy(entry.path_bytes()).into_owned();
+            let status = entry.status();
+            if status.is_conflicted() {
+                tree.conflicted.push(path);
+            } else if status.is_wt_new() {
+                tree.untracked.push(path);
+            }
+        }
+        Ok(tree)
+    }
+
+    fn file_history(&self, path: &str, follow_renames: bool) -> Result<Vec<FileRevision>, CdeError> {
+        let repo = self.repo()?;
+        let mut walk = repo.revwalk().map_err(git_error)?;
+        walk.set_sorting(Sort::TIME).map_err(git_error)?;
+        walk.push_head().map_err(git_error)?;
+
+        let mut path = path.to_string();
+        let mut revisions = Vec::new();
+        for oid in walk {
+            let commit = repo
+                .find_commit(oid.map_err(git_error)?)
+                .map_err(git_error)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let tree = commit.tree().map_err(git_error)?;
+            let parent_tree = match commit.parent_count() {
+                0 => None,
+                _ => Some(
+                    commit
+                        .parent(0)
+                        .and_then(|parent| parent.tree())
+                        .map_err(git_error)?,
+                ),
+            };
+            // Comparing blob ids is enough to skip the commits that leave the file alone
+            let blob =
+                |tree: &git2::Tree| tree.get_path(Path::new(&path)).ok().map(|entry| entry.id());
+            let (old_blob, new_blob) = (parent_tree.as_ref().and_then(blob), blob(&tree));
+            if old_blob == new_blob {
+                continue;
+            }
+
+            let author = commit.author();
+            let revision = |path: &str,
+                            old_path: Option<String>,
+                            change: &str,
+                            stats: (usize, usize)| FileRevision {
+                hash: commit.id().to_string(),
+                author: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+                email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+                date: format_time(&to_datetime(author.when())),
+                summary: summary(&commit),
+                path: path.to_string(),
+                old_path,
+                change: change.to_string(),
+                insertions: stats.0,
+                deletions: stats.1,
+            };
+
+            if follow_renames && old_blob.is_none() {
+                let mut diff = repo
+                    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+                    .map_err(git_error)?;
+                diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+                    .map_err(git_error)?;
+                let renamed = diff.deltas().position(|delta| {
+                    delta.status() == Delta::Renamed
+                        && delta.new_file().path() == Some(Path::new(&path))
+                });
+                if let Some(index) = renamed {
+                    let old_path = diff.get_delta(index).and_then(|delta| {
+                        delta
+                            .old_file()
+                            .path()
+                            .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    });
+                    let stats = match Patch::from_diff(&diff, index).map_err(git_error)? {
+                        Some(patch) => {
+                            let (_, insertions, deletions) =
+                                patch.line_stats().map_err(git_error)?;
+                            (insertions, deletions)
+                        }
+                        None => (0, 0),
+                    };
+                    revisions.push(revision(&path, old_path.clone(), "renamed", stats));
+                    if let Some(old_path) = old_path {
+                        path = old_path;
+                    }
+                    continue;
+                }
+            }
+
+            let mut options = git2::DiffOptions::new();
+            options.pathspec(&path).disable_pathspec_match(true);
+            let diff = repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+                .map_err(git_error)?;
+            for (delta, stat) in diff_stats(&diff)? {
+                let change = match delta {
+                    Delta::Added => "added",
+                    Delta::Deleted => "deleted",
+                    Delta::Typechange => "typechange",
+                    _ => "modified",
+                };
+                revisions.push(revision(
+                    &stat.path,
+                    None,
+                    change,
+                    (stat.insertions, stat.deletions),
+                ));
+            }
+        }
+        Ok(revisions)
+    }
+
+    fn diff(
+        &self,
+        from_ref: &str,
+        to_ref: &str,
+        options: &DiffOptions,
+    ) -> Result<Vec<FileDiff>, CdeError> {
+        let repo = self.repo()?;
+        let tree = |rev: &str| {
+            repo.revparse_single(rev)
+                .and_then(|object| object.peel_to_tree())
+                .map_err(|e| CdeError::not_found(format!("Unknown revision '{}'", rev)).caused_by(&e))
+        };
+        let (old_tree, new_tree) = (tree(from_ref)?, tree(to_ref)?);
+
+        let mut diff_options = git2::DiffOptions::new();
+        diff_options
+            .context_lines(options.context_lines)
+            .ignore_whitespace(options.ignore_whitespace);
+        for path in &options.paths {
+            diff_options.pathspec(path);
+        }
+        let mut diff = repo
+            .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_options))
+            .map_err(git_error)?;
+        if options.detect_renames {
+            diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+                .map_err(git_error)?;
+        }
+
+        let mut files = Vec::new();
+        for index in 0..diff.deltas().len() {
+            let Some(patch) = Patch::from_diff(&diff, index).map_err(git_error)? else {
+                continue;
+            };
+            files.push(file_diff(&patch)?);
+        }
+        Ok(files)
+    }
+}
+
+fn raw_commit(repo: &Repository, oid: Oid) -> Result<RawCommit, CdeError> {
+    let commit = repo.find_commit(oid).map_err(git_error)?;
+    let author = commit.author();
+    Ok(RawCommit {
+        hash: oid.to_string(),
+        parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        author: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+        email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+        author_time: to_datetime(author.when()),
+        summary: summary(&commit),
+        message: String::from_utf8_lossy(commit.message_bytes())
+            .trim_end()
+            .to_string(),
+        files: if commit.parent_count() > 1 {
+            Vec::new()
+        } else {
+            file_stats(repo, &commit)?
+        },
+    })
+}
+
+/// Lines added/deleted per file against the parent (or the empty tree for a root commit)
+fn file_stats(repo: &Repository, commit: &Commit) -> Result<Vec<FileStat>, CdeError> {
+    let tree = commit.tree().map_err(git_error)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(
+            commit
+                .parent(0)
+                .and_then(|parent| parent.tree())
+                .map_err(git_error)?,
+        ),
+    };
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+        .map_err(git_error)?;
+    Ok(diff_stats(&diff)?
+        .into_iter()
+        .map(|(_, stat)| stat)
+        .collect())
+}
+
+/// Files of a diff with their kind of change; conflicted entries are left out
+fn changed_files(diff: &Diff) -> Result<Vec<FileChange>, CdeError> {
+    Ok(diff_stats(diff)?
+        .into_iter()
+        .filter_map(|(delta, stat)| {
+            let change = match delta {
+                Delta::Added => "added",
+                Delta::Deleted => "deleted",
+                Delta::Modified => "modified",
+                Delta::Typechange => "typechange",
+                _ => return None,
+            };
+            Some(FileChange {
+                path: stat.path,
+                change: change.to_string(),
+                insertions: stat.insertions,
+                deletions: stat.deletions,
+            })
+        })
+        .collect())
+}
+
+/// Lines added/deleted per file of a diff
+fn diff_stats(diff: &Diff) -> Result<Vec<(Delta, FileStat)>, CdeError> {
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let (insertions, deletions) = match Patch::from_diff(diff, index).map_err(git_error)? {
+            Some(patch) => {
+                let (_, insertions, deletions) = patch.line_stats().map_err(git_error)?;
+                (insertions, deletions)
+            }
+            None => (0, 0),
+        };
+        files.push((
+            delta.status(),
+            FileStat {
+                path: path.to_string_lossy().replace('\\', "/"),
+                insertions,
+                deletions,
+            },
+        ));
+    }
+    Ok(files)
+}
+
+fn file_diff(patch: &Patch) -> Result<FileDiff, CdeError> {
+    let delta = patch.delta();
+    let path = |file: git2::DiffFile| {
+        file.path()
+            .map(|path| path.to_string_lossy().replace('\\', "/"))
+    };
+    let change = match delta.status() {
+        Delta::Added => "added",
+        Delta::Deleted => "deleted",
+        Delta::Renamed => "renamed",
+        _ => "modified",
+    };
+    let binary = delta.flags().is_binary();
+
+    let mut hunks = Vec::new();
+    for hunk_index in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_index).map_err(git_error)?;
+        let mut lines = Vec::new();
+        for line_index in 0..line_count {
+            let line = patch
+                .line_in_hunk(hunk_index, line_index)
+                .map_err(git_error)?;
+            let kind = match line.origin() {
+                ' ' => "context",
+                '+' => "added",
+                '-' => "removed",
+                // "\ No newline at end of file" markers
+                _ => continue,
+            };
+            let content = String::from_utf8_lossy(line.content());
+            lines.push(DiffLine {
+                kind: kind.to_string(),
+                content: content.strip_suffix('\n').unwrap_or(&content).to_string(),
+                old_lineno: line.old_lineno(),
+                new_lineno: line.new_lineno(),
+            });
+        }
+        hunks.push(DiffHunk {
+            header: String::from_utf8_lossy(hunk.header())
+                .trim_end()
+                .to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    Ok(FileDiff {
+        path: path(delta.new_file())
+            .or_else(|| path(delta.old_file()))
+            .unwrap_or_default(),
+        old_path: (change == "renamed")
+            .then(|| path(delta.old_file()))
+            .flatten(),
+        change: change.to_string(),
+        binary,
+        insertions: 0,
+        deletions: 0,
+        hunks: if binary { Vec::new() } else { hunks },
+    }
+    .with_line_counts())
+}
+
+fn summary(commit: &Commit) -> String {
+    commit
+        .summary_bytes()
+        .map(|summary| String::from_utf8_lossy(summary).into_owned())
+        .unwrap_or_default()
+}
+
+fn to_datetime(time: git2::Time) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east_opt(time.offset_minutes() * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    DateTime::from_timestamp(time.seconds(), 0)
+        .unwrap_or_default()
+        .with_timezone(&offset)
+}
+
+fn git_error(e: git2::Error) -> CdeError {
+    match e.code() {
+        git2::ErrorCode::NotFound => CdeError::not_found(e.message()),
+        _ => CdeError::git(e.message()),
+    }
+}
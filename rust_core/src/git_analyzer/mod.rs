@@ -0,0 +1,1754 @@
+// rust_core/src/git_analyzer.rs
+//! Professional Git History Analyzer with parallel processing
+//!
+//! Provides comprehensive Git repository analysis:
+//! - Commit history with parallel processing
+//! - Branch analysis (active, stale, merged)
+//! - Contributor insights (commits, impact, activity)
+//! - Code churn analysis (files changed most, risk hotspots)
+//! - Development patterns (commit frequency, peak times)
+//! - Architectural decisions (refactoring, migrations)
+//! - Release patterns (tags, versions) and changelogs between tags
+//! - Working-tree status (staged, unstaged, untracked, conflicted files)
+//!
+//! Repository data comes from a `GitBackend` (libgit2, with the `git` CLI as a
+//! fallback). History is walked once and every commit-based section is derived
+//! from that single walk.
+
+mod activity;
+mod backend;
+mod cache;
+mod changelog;
+mod changesets;
+mod decisions;
+mod cli;
+mod diff;
+mod history;
+mod libgit2;
+mod ownership;
+mod portfolio;
+mod pr_description;
+mod risk;
+mod security;
+
+use backend::{format_time, FileChange, GitBackend, RawCommit};
+use crate::cancel::CancellationToken;
+use crate::error::CdeError;
+use crate::metrics;
+use crate::progress::ProgressSink;
+pub use activity::ActivityWindow;
+pub use changelog::Changelog;
+pub use changesets::ChangeSet;
+pub use decisions::{DecisionOptions, DecisionSync};
+pub use diff::{CommitDiff, DiffOptions};
+pub use history::FileHistory;
+pub use ownership::KnowledgeDistribution;
+pub use portfolio::PortfolioAnalysis;
+pub use pr_description::PrDescription;
+pub use risk::RiskHotspot;
+pub use security::SecurityScan;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Steps of an analysis, the items its progress counts: reading the repository
+/// (history, branches, tags) and deriving the sections from it
+const ANALYSIS_STEPS: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitAnalysis {
+    pub repository_info: RepositoryInfo,
+    pub commit_history: CommitHistory,
+    pub branch_analysis: BranchAnalysis,
+    pub contributor_insights: Vec<ContributorInsight>,
+    pub knowledge_distribution: KnowledgeDistribution,
+    pub code_churn: CodeChurn,
+    pub development_patterns: DevelopmentPatterns,
+    pub architectural_decisions: Vec<ArchitecturalDecision>,
+    pub release_patterns: ReleasePatterns,
+    /// Whether the history walk was cancelled; commit-based sections then cover the
+    /// newest commits walked
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepositoryInfo {
+    pub path: String,
+    pub remote_url: Option<String>,
+    pub default_branch: String,
+    pub total_commits: usize,
+    pub first_commit_date: String,
+    pub last_commit_date: String,
+    pub repository_age_days: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitHistory {
+    pub recent_commits: Vec<CommitInfo>,
+    pub commits_by_month: HashMap<String, usize>,
+    /// Keyed by weekday name, in each author's time zone
+    pub commits_by_day_of_week: HashMap<String, usize>,
+    pub average_commits_per_week: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    pub date: String,
+    pub message: String,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BranchAnalysis {
+    /// Branch the others are compared to; "HEAD" when no default branch is found
+    pub default_branch: String,
+    pub total_branches: usize,
+    pub active_branches: Vec<BranchInfo>,
+    pub stale_branches: Vec<BranchInfo>,
+    pub merged_branches_count: usize,
+    pub conflicting_branches_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BranchInfo {
+    pub name: String,
+    pub last_commit_date: String,
+    /// Relative to the default branch
+    pub commits_ahead: usize,
+    pub commits_behind: usize,
+    pub is_merged: bool,
+    /// Merging into the default branch would stop on conflicts
+    pub would_conflict: bool,
+    pub conflicting_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergePrediction {
+    pub branch: String,
+    pub target: String,
+    pub commits_ahead: usize,
+    pub commits_behind: usize,
+    pub would_conflict: bool,
+    pub conflicting_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContributorInsight {
+    pub name: String,
+    pub email: String,
+    pub total_commits: usize,
+    pub first_commit_date: String,
+    pub last_commit_date: String,
+    pub lines_added: usize,
+    pub lines_deleted: usize,
+    pub files_modified: usize,
+    pub impact_score: f64, // Weighted score based on commits + churn
+    /// Directories where they are the only owner (bus factor of one)
+    pub sole_owner_of: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CodeChurn {
+    pub most_changed_files: Vec<FileChurn>,
+    pub total_files_ever_changed: usize,
+    pub hotspots: Vec<String>, // Files changed frequently
+    /// Changed files ranked by churn combined with complexity
+    pub risk_hotspots: Vec<RiskHotspot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileChurn {
+    pub path: String,
+    pub times_changed: usize,
+    pub total_insertions: usize,
+    pub total_deletions: usize,
+    pub last_modified: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevelopmentPatterns {
+    pub commit_frequency: String, // "Very active", "Active", "Moderate", "Low"
+    pub peak_development_hours: Vec<u8>,
+    pub peak_development_days: Vec<String>,
+    /// Commits per hour (24 entries) and weekday (7 entries, Monday first), in
+    /// each author's time zone
+    pub hour_histogram: Vec<usize>,
+    pub day_of_week_histogram: Vec<usize>,
+    pub contributor_activity: Vec<ActivityWindow>,
+    pub average_commit_size: f64, // Lines changed per commit
+    pub median_commit_size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchitecturalDecision {
+    pub commit_hash: String,
+    pub date: String,
+    pub author: String,
+    pub message: String,
+    pub decision_type: String, // "refactor", "migration", "architecture", "deprecation"
+    pub impact: String,        // "high", "medium", "low"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReleasePatterns {
+    pub total_tags: usize,
+    pub recent_tags: Vec<TagInfo>,
+    pub average_days_between_releases: f64,
+    pub release_frequency: String, // "Weekly", "Monthly", "Quarterly", "Irregular"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub name: String,
+    pub date: String,
+    pub commit_hash: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkingTreeStatus {
+    /// Checked-out branch, "HEAD" when detached, `None` before the first commit
+    pub branch: Option<String>,
+    pub is_clean: bool,
+    /// Changes in the index against HEAD
+    pub staged: Vec<WorkingTreeFile>,
+    /// Changes in the working copy against the index
+    pub unstaged: Vec<WorkingTreeFile>,
+    /// Files neither tracked nor ignored, counted as all lines added
+    pub untracked: Vec<WorkingTreeFile>,
+    /// Files with unresolved merge conflicts in the index
+    pub conflicted: Vec<WorkingTreeFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkingTreeFile {
+    pub path: String,
+    pub change: String, // "added", "modified", "deleted", "typechange", "conflicted"
+    pub insertions: usize,
+    pub deletions: usize,
+    /// `<<<<<<<` / `=======` / `>>>>>>>` blocks left in the working copy
+    pub conflict_markers: usize,
+}
+
+/// Analyze Git repository with parallel processing; once `cancel` is cancelled the
+/// history walk stops and the analysis covers the commits walked so far. Progress
+/// counts the analysis steps ("reading repository", "analyzing history").
+pub fn analyze_git_repository(
+    repo_path: &str,
+    days: i64,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<GitAnalysis, CdeError> {
+    metrics::timed("analyze_git_repository", || {
+        analyze(open_repository(repo_path)?.as_ref(), repo_path, days, cancel, progress)
+    })
+}
+
+/// `analyze_git_repository`, reusing the stored result while HEAD has not moved.
+/// A cache that cannot be written only costs the next call a recomputation;
+/// cancelled analyses are not stored.
+pub fn analyze_git_repository_cached(
+    repo_path: &str,
+    days: i64,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<GitAnalysis, CdeError> {
+    metrics::timed("analyze_git_repository", || {
+        cached_analysis(repo_path, days, cancel, progress)
+    })
+}
+
+fn cached_analysis(
+    repo_path: &str,
+    days: i64,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<GitAnalysis, CdeError> {
+    let backend = open_repository(repo_path)?;
+    // Without a HEAD commit (empty repository) there is nothing to key on
+    let (Ok(head), Ok(git_dir)) = (backend.head_commit(), backend.git_dir()) else {
+        return analyze(backend.as_ref(), repo_path, days, cancel, progress);
+    };
+    let dir = cache::cache_dir(repo_path, &git_dir);
+    let cached = cache::load(&dir, repo_path, &head, days);
+    metrics::cache_lookup("git_analysis", cached.is_some());
+    if let Some(analysis) = cached {
+        progress.finish(ANALYSIS_STEPS);
+        return Ok(analysis);
+    }
+    let analysis = analyze(backend.as_ref(), repo_path, days, cancel, progress)?;
+    if !analysis.cancelled {
+        let _ = cache::store(&dir, repo_path, &head, days, &analysis);
+    }
+    Ok(analysis)
+}
+
+/// Analyses of several repositories, run in parallel, with an aggregate of their
+/// contributors and most changed files; a repository that fails is reported with
+/// its error. With `use_cache`, each analysis goes through the analysis cache.
+/// Once `cancel` is cancelled, repositories not started yet are reported as
+/// cancelled and running analyses stop their history walk. Progress counts the
+/// repositories analyzed.
+pub fn analyze_repositories(
+    paths: &[String],
+    days: i64,
+    use_cache: bool,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> PortfolioAnalysis {
+    let analyzed = AtomicUsize::new(0);
+    progress.report("analyzing repositories", 0, Some(paths.len()));
+    let portfolio = portfolio::analyze_portfolio(paths, cancel, |path| {
+        let quiet = ProgressSink::default();
+        let analysis = if use_cache {
+            analyze_git_repository_cached(path, days, cancel, &quiet)
+        } else {
+            analyze_git_repository(path, days, cancel, &quiet)
+        };
+        let done = analyzed.fetch_add(1, Ordering::Relaxed) + 1;
+        progress.report("analyzing repositories", done, Some(paths.len()));
+        analysis
+    });
+    progress.finish(paths.len());
+    portfolio
+}
+
+/// Drops the cached analyses of the repository; returns how many were removed
+pub fn invalidate_analysis_cache(repo_path: &str) -> Result<usize, CdeError> {
+    let git_dir = open_repository(repo_path)?.git_dir()?;
+    let dir = cache::cache_dir(repo_path, &git_dir);
+    cache::invalidate(&dir).map_err(|e| {
+        CdeError::io("Failed to clear analysis cache")
+            .with_path(&dir)
+            .caused_by(&e)
+    })
+}
+
+fn analyze(
+    backend: &dyn GitBackend,
+    repo_path: &str,
+    days: i64,
+    cancel: &CancellationToken,
+    progress: &ProgressSink<'_>,
+) -> Result<GitAnalysis, CdeError> {
+    let since = since(days);
+    progress.report("reading repository", 0, Some(ANALYSIS_STEPS));
+
+    // Gather all data in parallel (nested rayon::join for 4 operations)
+    let (
+        (repo_info, commits),
+        (branch_analysis, release_patterns)
+    ) = rayon::join(
+        || {
+            rayon::join(
+                || get_repository_info(backend, repo_path),
+                || backend.commits_since_cancellable(since, cancel),
+            )
+        },
+        || {
+            rayon::join(
+                || get_branch_analysis(backend),
+                || analyze_release_patterns(backend),
+            )
+        },
+    );
+
+    let commits = commits?;
+    progress.report("analyzing history", 1, Some(ANALYSIS_STEPS));
+    let commit_hist = get_commit_history(&commits, days);
+    let dev_patterns = analyze_development_patterns(&commits, commit_hist.average_commits_per_week)?;
+    let knowledge = ownership::knowledge_distribution(&commits);
+
+    progress.finish(ANALYSIS_STEPS);
+    Ok(GitAnalysis {
+        repository_info: repo_info?,
+        commit_history: commit_hist,
+        branch_analysis: branch_analysis?,
+        contributor_insights: get_contributor_insights(&commits, &knowledge),
+        knowledge_distribution: knowledge,
+        code_churn: get_code_churn(&commits, Path::new(repo_path)),
+        development_patterns: dev_patterns,
+        architectural_decisions: decisions::find_decisions(&commits, &DecisionOptions::default().keywords),
+        release_patterns: release_patterns?,
+        cancelled: cancel.is_cancelled(),
+    })
+}
+
+/// Contributors of the last `days`, highest impact first
+pub fn analyze_contributors(repo_path: &str, days: i64) -> Result<Vec<ContributorInsight>, CdeError> {
+    let commits = open_repository(repo_path)?.commits_since(since(days))?;
+    Ok(get_contributor_insights(&commits, &ownership::knowledge_distribution(&commits)))
+}
+
+/// Bus factor of the repository and of its directories over the last `days`, with
+/// the often-changed directories only one contributor knows
+pub fn analyze_knowledge_distribution(repo_path: &str, days: i64) -> Result<KnowledgeDistribution, CdeError> {
+    let commits = open_repository(repo_path)?.commits_since(since(days))?;
+    Ok(ownership::knowledge_distribution(&commits))
+}
+
+/// Files changed most often in the last `days`
+pub fn analyze_code_churn(repo_path: &str, days: i64) -> Result<CodeChurn, CdeError> {
+    let commits = open_repository(repo_path)?.commits_since(since(days))?;
+    Ok(get_code_churn(&commits, Path::new(repo_path)))
+}
+
+/// Local and remote-tracking branches compared to the default branch
+pub fn analyze_branches(repo_path: &str) -> Result<BranchAnalysis, CdeError> {
+    get_branch_analysis(open_repository(repo_path)?.as_ref())
+}
+
+/// Whether merging `branch` into `target` (the default branch when `None`) would
+/// conflict, without touching the index or working copy
+pub fn predict_merge_conflicts(
+    repo_path: &str,
+    branch: &str,
+    target: Option<&str>,
+) -> Result<MergePrediction, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let target = target.map_or_else(|| base_branch(backend.as_ref()), str::to_string);
+    let (commits_ahead, commits_behind) = backend.ahead_behind(branch, &target)?;
+    let conflicting_files = predicted_conflicts(backend.as_ref(), branch, &target, commits_ahead, commits_behind)?;
+    Ok(MergePrediction {
+        branch: branch.to_string(),
+        target,
+        commits_ahead,
+        commits_behind,
+        would_conflict: !conflicting_files.is_empty(),
+        conflicting_files,
+    })
+}
+
+/// Uncommitted state of the repository with per-file diff stats
+pub fn analyze_working_tree(repo_path: &str) -> Result<WorkingTreeStatus, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let raw = backend.working_tree()?;
+    let root = Path::new(repo_path);
+    let is_conflicted = |change: &FileChange| raw.conflicted.contains(&change.path);
+
+    let tracked = |changes: &[FileChange]| -> Vec<WorkingTreeFile> {
+        let mut files: Vec<WorkingTreeFile> = changes
+            .iter()
+            .filter(|change| !is_conflicted(change))
+            .map(|change| WorkingTreeFile {
+                path: change.path.clone(),
+                change: change.change.clone(),
+                insertions: change.insertions,
+                deletions: change.deletions,
+                conflict_markers: count_conflict_markers(&root.join(&change.path)),
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    };
+    let untracked_or_conflicted = |paths: &[String], change: &str| -> Vec<WorkingTreeFile> {
+        let mut files: Vec<WorkingTreeFile> = paths
+            .iter()
+            .map(|path| {
+                let content = std::fs::read_to_string(root.join(path)).unwrap_or_default();
+                WorkingTreeFile {
+                    path: path.clone(),
+                    change: change.to_string(),
+                    insertions: if change == "added" { content.lines().count() } else { 0 },
+                    deletions: 0,
+                    conflict_markers: conflict_markers_in(&content),
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    };
+
+    let staged = tracked(&raw.staged);
+    let unstaged = tracked(&raw.unstaged);
+    let untracked = untracked_or_conflicted(&raw.untracked, "added");
+    let conflicted = untracked_or_conflicted(&raw.conflicted, "conflicted");
+
+    Ok(WorkingTreeStatus {
+        branch: backend.current_branch().ok(),
+        is_clean: staged.is_empty() && unstaged.is_empty() && untracked.is_empty() && conflicted.is_empty(),
+        staged,
+        unstaged,
+        untracked,
+        conflicted,
+    })
+}
+
+/// Changelog of the commits in `to_ref` that are not in `from_ref`, grouped by
+/// Conventional Commit type
+pub fn generate_changelog(repo_path: &str, from_ref: &str, to_ref: &str) -> Result<Changelog, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let commits = backend.commits_between(from_ref, to_ref)?;
+    Ok(changelog::build_changelog(
+        from_ref,
+        to_ref,
+        &commits,
+        backend.remote_url().as_deref(),
+    ))
+}
+
+/// Commits of the last `days` grouped into change sets: merged pull requests and
+/// branches, squash merges and runs of direct commits by one author, newest first
+pub fn analyze_change_sets(repo_path: &str, days: i64) -> Result<Vec<ChangeSet>, CdeError> {
+    let commits = open_repository(repo_path)?.commits_since(since(days))?;
+    Ok(changesets::group_change_sets(&commits))
+}
+
+/// Commits that changed `path` (relative to the repository root), newest first,
+/// with per-commit line stats and the authors who changed it most
+pub fn file_history(repo_path: &str, path: &str, follow_renames: bool) -> Result<FileHistory, CdeError> {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    let revisions = open_repository(repo_path)?.file_history(path, follow_renames)?;
+    Ok(FileHistory::new(path, follow_renames, revisions))
+}
+
+/// Architectural decisions of the last `days` matched against the ADR files of the
+/// documentation scan; decisions without an ADR are reported as unsynced
+pub fn cross_reference_decisions(
+    repo_path: &str,
+    days: i64,
+    options: &DecisionOptions,
+) -> Result<DecisionSync, CdeError> {
+    let commits = open_repository(repo_path)?.commits_since(since(days))?;
+    let documents = crate::documentation::scan_documentation(repo_path)?;
+    let adrs = decisions::find_adrs(Path::new(repo_path), &documents, &options.adr_patterns);
+    let found = decisions::find_decisions(&commits, &options.keywords);
+    Ok(decisions::cross_reference(found, &commits, adrs, &options.adr_patterns))
+}
+
+/// Commits of the last `days` touching security-sensitive paths or making suspicious
+/// changes (test deletions, disabled checks), each with the heuristic that fired
+pub fn detect_security_events(repo_path: &str, days: i64) -> Result<SecurityScan, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let commits = backend.commits_since(since(days))?;
+    security::scan_commits(backend.as_ref(), &commits)
+}
+
+/// Structured diff between two revisions (anything `git rev-parse` accepts)
+pub fn diff_refs(
+    repo_path: &str,
+    from_ref: &str,
+    to_ref: &str,
+    options: &DiffOptions,
+) -> Result<CommitDiff, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let files = backend.diff(from_ref, to_ref, options)?;
+    Ok(CommitDiff {
+        from_ref: from_ref.to_string(),
+        to_ref: to_ref.to_string(),
+        files_changed: files.len(),
+        insertions: files.iter().map(|file| file.insertions).sum(),
+        deletions: files.iter().map(|file| file.deletions).sum(),
+        files,
+    })
+}
+
+/// Draft description of a pull request merging `head` into `base`: the commits
+/// of `head` missing from `base` grouped by type, and the files changed since
+/// their merge base, rendered with the built-in template or `template_path`
+pub fn draft_pr_description(
+    repo_path: &str,
+    base: &str,
+    head: &str,
+    template_path: Option<&str>,
+) -> Result<PrDescription, CdeError> {
+    let backend = open_repository(repo_path)?;
+    let merge_base = backend.merge_base(head, base)?;
+    let commits = backend.commits_between(base, head)?;
+    let files = backend.diff(&merge_base, head, &DiffOptions::default())?;
+    let head_branch = match head {
+        "HEAD" => backend.current_branch()?,
+        _ => head.to_string(),
+    };
+    let mut description = pr_description::build_pr_description(
+        base,
+        head,
+        &head_branch,
+        &merge_base,
+        &commits,
+        &files,
+        backend.remote_url().as_deref(),
+    );
+
+    let template = match template_path {
+        Some(template_path) => {
+            crate::path_policy::check(template_path)?;
+            let path = Path::new(template_path);
+            let source = std::fs::read_to_string(path).map_err(|e| {
+                CdeError::not_found("Template not found")
+                    .with_path(path)
+                    .caused_by(&e)
+            })?;
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            Some((path.parent().unwrap_or(Path::new("")).to_path_buf(), name, source))
+        }
+        None => None,
+    };
+    description.markdown = pr_description::render_markdown(
+        &description,
+        Path::new(repo_path),
+        template
+            .as_ref()
+            .map(|(dir, name, source)| (dir.as_path(), name.as_str(), source.as_str())),
+    )?;
+    Ok(description)
+}
+
+fn open_repository(repo_path: &str) -> Result<Box<dyn GitBackend>, CdeError> {
+    let path = Path::new(repo_path);
+
+    if !path.exists() {
+        return Err(CdeError::not_found("Path does not exist").with_path(path));
+    }
+
+    if !path.join(".git").exists() {
+        return Err(CdeError::invalid_input("Not a Git repository").with_path(path));
+    }
+
+    backend::open(path)
+}
+
+fn since(days: i64) -> chrono::DateTime<chrono::Local> {
+    chrono::Local::now() - chrono::Duration::days(days)
+}
+
+fn get_repository_info(backend: &dyn GitBackend, repo_path: &str) -> Result<RepositoryInfo, CdeError> {
+    let default_branch = backend.current_branch()?;
+    let span = backend.commit_span()?;
+
+    Ok(RepositoryInfo {
+        path: repo_path.to_string(),
+        remote_url: backend.remote_url(),
+        default_branch,
+        total_commits: span.total_commits,
+        first_commit_date: format_time(&span.first_commit_time),
+        last_commit_date: format_time(&span.last_commit_time),
+        repository_age_days: (span.last_commit_time - span.first_commit_time).num_days(),
+    })
+}
+
+fn get_commit_history(raw: &[RawCommit], days: i64) -> CommitHistory {
+    let commits: Vec<CommitInfo> = raw.iter().map(commit_info).collect();
+
+    let mut commits_by_month: HashMap<String, usize> = HashMap::new();
+    for commit in &commits {
+        if let Some(month) = commit.date.split('-').take(2).collect::<Vec<_>>().get(0..2) {
+            let month_key = month.join("-");
+            *commits_by_month.entry(month_key).or_insert(0) += 1;
+        }
+    }
+    let commits_by_day: HashMap<String, usize> = activity::WEEKDAYS
+        .iter()
+        .zip(activity::weekday_histogram(raw.iter().map(|commit| &commit.author_time)))
+        .filter(|(_, count)| *count > 0)
+        .map(|(day, count)| (day.to_string(), count))
+        .collect();
+
+    let weeks = (days as f64 / 7.0).max(1.0);
+    let avg_commits_per_week = commits.len() as f64 / weeks;
+
+    CommitHistory {
+        recent_commits: commits.into_iter().take(50).collect(),
+        commits_by_month,
+        commits_by_day_of_week: commits_by_day,
+        average_commits_per_week: avg_commits_per_week,
+    }
+}
+
+fn base_branch(backend: &dyn GitBackend) -> String {
+    backend.default_branch().unwrap_or_else(|| "HEAD".to_string())
+}
+
+/// Conflicting paths of merging `branch` into `base`; merged and fast-forward
+/// branches cannot conflict, so only diverged ones are merged in memory
+fn predicted_conflicts(
+    backend: &dyn GitBackend,
+    branch: &str,
+    base: &str,
+    ahead: usize,
+    behind: usize,
+) -> Result<Vec<String>, CdeError> {
+    if ahead == 0 || behind == 0 {
+        return Ok(Vec::new());
+    }
+    backend.merge_conflicts(branch, base)
+}
+
+fn get_branch_analysis(backend: &dyn GitBackend) -> Result<BranchAnalysis, CdeError> {
+    let now = chrono::Local::now();
+    let default_branch = base_branch(backend);
+    let (active_branches, stale_branches): (Vec<_>, Vec<_>) = backend
+        .branches(&default_branch)?
+        .into_iter()
+        .map(|branch| {
+            let active = (now.fixed_offset() - branch.last_commit_time).num_days() <= 30;
+            // A branch whose merge cannot be simulated is reported without conflicts
+            let conflicting_files =
+                predicted_conflicts(backend, &branch.name, &default_branch, branch.ahead, branch.behind)
+                    .unwrap_or_default();
+            let info = BranchInfo {
+                name: branch.name,
+                last_commit_date: format_time(&branch.last_commit_time),
+                commits_ahead: branch.ahead,
+                commits_behind: branch.behind,
+                // Nothing on the branch that the default branch does not already contain
+                is_merged: branch.ahead == 0,
+                would_conflict: !conflicting_files.is_empty(),
+                conflicting_files,
+            };
+            (info, active)
+        })
+        .partition(|(_, active)| *active);
+
+    let active_branches: Vec<BranchInfo> = active_branches.into_iter().map(|(b, _)| b).collect();
+    let stale_branches: Vec<BranchInfo> = stale_branches.into_iter().map(|(b, _)| b).collect();
+    let merged_count = active_branches
+        .iter()
+        .chain(&stale_branches)
+        .filter(|b| b.is_merged)
+        .count();
+    let conflicting_count = active_branches
+        .iter()
+        .chain(&stale_branches)
+        .filter(|b| b.would_conflict)
+        .count();
+
+    Ok(BranchAnalysis {
+        default_branch,
+        total_branches: active_branches.len() + stale_branches.len(),
+        active_branches,
+        stale_branches,
+        merged_branches_count: merged_count,
+        conflicting_branches_count: conflicting_count,
+    })
+}
+
+fn get_contributor_insights(commits: &[RawCommit], knowledge: &KnowledgeDistribution) -> Vec<ContributorInsight> {
+    // Commits are newest first: the first one seen per email sets the name
+    let mut by_email: HashMap<&str, ContributorInsight> = HashMap::new();
+    for commit in commits {
+        let date = format_time(&commit.author_time);
+        let insight = by_email.entry(&commit.email).or_insert_with(|| ContributorInsight {
+            name: commit.author.clone(),
+            email: commit.email.clone(),
+            total_commits: 0,
+            first_commit_date: String::new(),
+            last_commit_date: date.clone(),
+            lines_added: 0,
+            lines_deleted: 0,
+            files_modified: 0,
+            impact_score: 0.0,
+            sole_owner_of: Vec::new(),
+        });
+        insight.total_commits += 1;
+        insight.first_commit_date = date;
+        insight.files_modified += commit.files.len();
+        for file in &commit.files {
+            insight.lines_added += file.insertions;
+            insight.lines_deleted += file.deletions;
+        }
+    }
+
+    let mut contributors: Vec<ContributorInsight> = by_email
+        .into_values()
+        .map(|mut insight| {
+            insight.impact_score = (insight.total_commits as f64 * 10.0)
+                + (insight.lines_added as f64 * 0.1)
+                + (insight.files_modified as f64 * 0.5);
+            insight.sole_owner_of = ownership::sole_owner_paths(knowledge, &insight.email)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            insight
+        })
+        .collect();
+    contributors.sort_by(|a, b| b.impact_score.total_cmp(&a.impact_score));
+    contributors
+}
+
+/// `root` is the working copy the complexity of the changed files is read from
+fn get_code_churn(commits: &[RawCommit], root: &Path) -> CodeChurn {
+    let mut file_changes: HashMap<&str, FileChurn> = HashMap::new();
+
+    for commit in commits {
+        for file in &commit.files {
+            // Commits are newest first: the first one seen modified the file last
+            let churn = file_changes.entry(&file.path).or_insert_with(|| FileChurn {
+                path: file.path.clone(),
+                times_changed: 0,
+                total_insertions: 0,
+                total_deletions: 0,
+                last_modified: format_time(&commit.author_time),
+            });
+            churn.times_changed += 1;
+            churn.total_insertions += file.insertions;
+            churn.total_deletions += file.deletions;
+        }
+    }
+
+    let total_files_ever_changed = file_changes.len();
+    let mut most_changed: Vec<FileChurn> = file_changes.into_values().collect();
+    let risk_hotspots = risk::risk_hotspots(root, &most_changed);
+    most_changed.sort_by(|a, b| b.times_changed.cmp(&a.times_changed).then(a.path.cmp(&b.path)));
+    most_changed.truncate(20);
+    let most_changed_files = most_changed;
+
+    let hotspots: Vec<String> = most_changed_files
+        .iter()
+        .filter(|f| f.times_changed > 5)
+        .map(|f| f.path.clone())
+        .collect();
+
+    CodeChurn {
+        most_changed_files,
+        total_files_ever_changed,
+        hotspots,
+        risk_hotspots,
+    }
+}
+
+fn analyze_development_patterns(
+    commits: &[RawCommit],
+    average_commits_per_week: f64,
+) -> Result<DevelopmentPatterns, CdeError> {
+    let commit_frequency = if average_commits_per_week > 20.0 {
+        "Very active"
+    } else if average_commits_per_week > 10.0 {
+        "Active"
+    } else if average_commits_per_week > 5.0 {
+        "Moderate"
+    } else {
+        "Low"
+    };
+
+    // Author dates keep the offset they were recorded with
+    let times = || commits.iter().map(|commit| &commit.author_time);
+    let hour_histogram = activity::hour_histogram(times());
+    let day_of_week_histogram = activity::weekday_histogram(times());
+
+    let mut commit_sizes: Vec<usize> = commits
+        .iter()
+        .map(|commit| commit.files.iter().map(|f| f.insertions + f.deletions).sum())
+        .collect();
+    let total_size: usize = commit_sizes.iter().sum();
+
+    commit_sizes.sort();
+    let median_size = if !commit_sizes.is_empty() {
+        commit_sizes[commit_sizes.len() / 2]
+    } else {
+        0
+    };
+
+    let avg_size = if !commits.is_empty() {
+        total_size as f64 / commits.len() as f64
+    } else {
+        0.0
+    };
+
+    Ok(DevelopmentPatterns {
+        commit_frequency: commit_frequency.to_string(),
+        peak_development_hours: activity::ranked(&hour_histogram)
+            .into_iter()
+            .take(5)
+            .map(|hour| hour as u8)
+            .collect(),
+        peak_development_days: activity::ranked(&day_of_week_histogram)
+            .into_iter()
+            .take(3)
+            .map(|day| activity::WEEKDAYS[day].to_string())
+            .collect(),
+        hour_histogram,
+        day_of_week_histogram,
+        contributor_activity: activity::activity_windows(commits),
+        average_commit_size: avg_size,
+        median_commit_size: median_size,
+    })
+}
+
+fn analyze_release_patterns(backend: &dyn GitBackend) -> Result<ReleasePatterns, CdeError> {
+    let tags = backend.tags()?;
+    let total_tags = tags.len();
+
+    let frequency = if total_tags > 50 {
+        "Weekly"
+    } else if total_tags > 20 {
+        "Monthly"
+    } else if total_tags > 5 {
+        "Quarterly"
+    } else {
+        "Irregular"
+    };
+
+    // Calculate average days between releases
+    let recent = &tags[..total_tags.min(10)];
+    let gaps: Vec<i64> = recent
+        .windows(2)
+        .map(|pair| (pair[0].commit_time - pair[1].commit_time).num_days().abs())
+        .collect();
+
+    let avg_days = if !gaps.is_empty() {
+        gaps.iter().sum::<i64>() as f64 / gaps.len() as f64
+    } else {
+        0.0
+    };
+
+    let recent_tags: Vec<TagInfo> = recent
+        .iter()
+        .map(|tag| TagInfo {
+            name: tag.name.clone(),
+            date: format_time(&tag.commit_time),
+            commit_hash: tag.commit_hash.clone(),
+            message: tag.commit_summary.clone(),
+        })
+        .collect();
+
+    Ok(ReleasePatterns {
+        total_tags,
+        recent_tags,
+        average_days_between_releases: avg_days,
+        release_frequency: frequency.to_string(),
+    })
+}
+
+// Helper functions
+
+fn commit_info(commit: &RawCommit) -> CommitInfo {
+    CommitInfo {
+        hash: commit.hash.clone(),
+        author: commit.author.clone(),
+        email: commit.email.clone(),
+        date: format_time(&commit.author_time),
+        message: commit.summary.clone(),
+        files_changed: commit.files.len(),
+        insertions: commit.files.iter().map(|f| f.insertions).sum(),
+        deletions: commit.files.iter().map(|f| f.deletions).sum(),
+    }
+}
+
+/// Conflict blocks in a working-copy file; unreadable or binary files have none
+fn count_conflict_markers(path: &Path) -> usize {
+    std::fs::read_to_string(path).map_or(0, |content| conflict_markers_in(&content))
+}
+
+/// Complete `<<<<<<<` ... `=======` ... `>>>>>>>` blocks in `content`
+fn conflict_markers_in(content: &str) -> usize {
+    let mut blocks = 0;
+    let mut expected = "<<<<<<<";
+    for line in content.lines() {
+        if !line.starts_with(expected) {
+            continue;
+        }
+        expected = match expected {
+            "<<<<<<<" => "=======",
+            "=======" => ">>>>>>>",
+            _ => {
+                blocks += 1;
+                "<<<<<<<"
+            }
+        };
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Repository, Signature, Time};
+
+    fn commit(repo: &Repository, files: &[(&str, &str)], message: &str, days_ago: i64) {
+        let root = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (path, content) in files {
+            std::fs::write(root.join(path), content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let seconds = chrono::Local::now().timestamp() - days_ago * 86_400;
+        let signature = Signature::new("Ada", "ada@example.com", &Time::new(seconds, 120)).unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .map(|head| head.peel_to_commit().unwrap())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_working_tree_reports_staged_unstaged_untracked_and_conflicts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "one\n"), ("d.txt", "d\n")], "Base", 3);
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        commit(&repo, &[("a.txt", "theirs\n")], "Theirs", 2);
+        let theirs = repo.head().unwrap().target().unwrap();
+        repo.reset(base.as_object(), git2::ResetType::Hard, None)
+            .unwrap();
+        commit(&repo, &[("a.txt", "ours\n")], "Ours", 1);
+        let theirs = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&theirs], None, None).unwrap();
+
+        let root = dir.path();
+        std::fs::write(root.join("b.txt"), "staged\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("b.txt")).unwrap();
+        index.write().unwrap();
+        std::fs::write(root.join("d.txt"), "d\nmore\n").unwrap();
+        std::fs::create_dir(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/c.txt"), "one\ntwo\n").unwrap();
+
+        let status = analyze_working_tree(root.to_str().unwrap()).unwrap();
+        assert!(!status.is_clean);
+        let paths = |files: &[WorkingTreeFile]| -> Vec<(String, String, usize)> {
+            files
+                .iter()
+                .map(|f| (f.path.clone(), f.change.clone(), f.insertions))
+                .collect()
+        };
+        assert_eq!(
+            paths(&status.staged),
+            vec![("b.txt".into(), "added".into(), 1)]
+        );
+        assert_eq!(
+            paths(&status.unstaged),
+            vec![("d.txt".into(), "modified".into(), 1)]
+        );
+        assert_eq!(
+            paths(&status.untracked),
+            vec![("notes/c.txt".into(), "added".into(), 2)]
+        );
+        assert_eq!(status.conflicted[0].path, "a.txt");
+        assert_eq!(status.conflicted[0].conflict_markers, 1);
+
+        if let Ok(cli_backend) = cli::CliBackend::open(root) {
+            let git2_backend = libgit2::Git2Backend::open(root).unwrap();
+            let mut from_cli = cli_backend.working_tree().unwrap();
+            let mut from_git2 = git2_backend.working_tree().unwrap();
+            for tree in [&mut from_cli, &mut from_git2] {
+                tree.staged.retain(|change| change.path != "a.txt");
+                tree.unstaged.retain(|change| change.path != "a.txt");
+            }
+            assert_eq!(from_cli, from_git2);
+        }
+        assert_eq!(conflict_markers_in("<<<<<<< HEAD\n=======\n"), 0);
+    }
+
+    #[test]
+    fn test_diff_reports_hunks_renames_and_binary_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let old = "shared line one\nshared line two\nshared line three\n";
+        commit(
+            &repo,
+            &[
+                ("a.txt", "1\n2\n3\n4\n5\n"),
+                ("old.txt", old),
+                ("logo.bin", "\0\x01\x02"),
+            ],
+            "Base",
+            2,
+        );
+        std::fs::remove_file(dir.path().join("old.txt")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("old.txt")).unwrap();
+        index.write().unwrap();
+        commit(
+            &repo,
+            &[
+                ("a.txt", "1\n2\nthree\n4\n5\n"),
+                ("new.txt", old),
+                ("logo.bin", "\0\x03"),
+            ],
+            "Rename and edit",
+            1,
+        );
+
+        let options = DiffOptions {
+            context_lines: 1,
+            ..DiffOptions::default()
+        };
+        let repo_path = dir.path().to_str().unwrap();
+        let diff = diff_refs(repo_path, "HEAD~1", "HEAD", &options).unwrap();
+        assert_eq!(diff.files_changed, 3);
+        assert_eq!((diff.insertions, diff.deletions), (1, 1));
+
+        let file = |path: &str| diff.files.iter().find(|file| file.path == path).unwrap();
+        let a = file("a.txt");
+        assert_eq!(a.change, "modified");
+        assert_eq!(a.hunks.len(), 1);
+        assert_eq!(a.hunks[0].header, "@@ -2,3 +2,3 @@");
+        let lines: Vec<(&str, &str, Option<u32>, Option<u32>)> = a.hunks[0]
+            .lines
+            .iter()
+            .map(|l| {
+                (
+                    l.kind.as_str(),
+                    l.content.as_str(),
+                    l.old_lineno,
+                    l.new_lineno,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("context", "2", Some(2), Some(2)),
+                ("removed", "3", Some(3), None),
+                ("added", "three", None, Some(3)),
+                ("context", "4", Some(4), Some(4)),
+            ]
+        );
+        let renamed = file("new.txt");
+        assert_eq!(renamed.change, "renamed");
+        assert_eq!(renamed.old_path.as_deref(), Some("old.txt"));
+        assert!(renamed.hunks.is_empty());
+        let binary = file("logo.bin");
+        assert!(binary.binary);
+        assert!(binary.hunks.is_empty());
+
+        let no_renames = DiffOptions {
+            detect_renames: false,
+            paths: vec!["*.txt".to_string()],
+            ..options.clone()
+        };
+        let diff = diff_refs(repo_path, "HEAD~1", "HEAD", &no_renames).unwrap();
+        let mut changes: Vec<(&str, &str)> = diff
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.change.as_str()))
+            .collect();
+        changes.sort();
+        assert_eq!(
+            changes,
+            vec![
+                ("a.txt", "modified"),
+                ("new.txt", "added"),
+                ("old.txt", "deleted")
+            ]
+        );
+        assert!(diff_refs(repo_path, "no-such-ref", "HEAD", &options).is_err());
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            let git2_backend = libgit2::Git2Backend::open(dir.path()).unwrap();
+            for options in [&options, &no_renames] {
+                assert_eq!(
+                    cli_backend.diff("HEAD~1", "HEAD", options).unwrap(),
+                    git2_backend.diff("HEAD~1", "HEAD", options).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_branch_analysis_predicts_merge_conflicts_with_default_branch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "one\n"), ("b.txt", "b\n")], "Base", 3);
+        let default_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("clash", &base, false).unwrap();
+        repo.branch("clean", &base, false).unwrap();
+        commit(&repo, &[("a.txt", "main\n")], "Main edit", 2);
+        let switch = |branch: &str| {
+            repo.set_head(&format!("refs/heads/{}", branch)).unwrap();
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+                .unwrap();
+        };
+        switch("clash");
+        commit(&repo, &[("a.txt", "clash\n")], "Clashing edit", 1);
+        switch("clean");
+        commit(&repo, &[("c.txt", "c\n")], "Unrelated edit", 1);
+        switch(&default_branch);
+
+        let repo_path = dir.path().to_str().unwrap();
+        let analysis = analyze_branches(repo_path).unwrap();
+        assert_eq!(analysis.default_branch, default_branch);
+        assert_eq!(analysis.conflicting_branches_count, 1);
+        let branch = |name: &str| {
+            analysis
+                .active_branches
+                .iter()
+                .find(|b| b.name == name)
+                .unwrap()
+        };
+        let clash = branch("clash");
+        assert_eq!((clash.commits_ahead, clash.commits_behind), (1, 1));
+        assert!(clash.would_conflict);
+        assert_eq!(clash.conflicting_files, vec!["a.txt"]);
+        assert!(!branch("clean").would_conflict);
+        assert!(branch(&default_branch).is_merged);
+
+        let prediction = predict_merge_conflicts(repo_path, "clean", Some("clash")).unwrap();
+        assert_eq!(
+            (prediction.commits_ahead, prediction.commits_behind),
+            (1, 1)
+        );
+        assert!(!prediction.would_conflict);
+        assert!(predict_merge_conflicts(repo_path, "no-such-branch", None).is_err());
+        // The simulation must leave the checkout alone
+        assert!(analyze_working_tree(repo_path).unwrap().is_clean);
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            let git2_backend = libgit2::Git2Backend::open(dir.path()).unwrap();
+            assert_eq!(cli_backend.default_branch(), git2_backend.default_branch());
+            assert_eq!(
+                cli_backend.branches(&default_branch).unwrap(),
+                git2_backend.branches(&default_branch).unwrap()
+            );
+            assert_eq!(
+                cli_backend
+                    .merge_conflicts("clash", &default_branch)
+                    .unwrap(),
+                vec!["a.txt"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_pr_description_covers_changes_since_the_merge_base() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("app.py", "print(1)\n")], "Base", 3);
+        let default_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature/add-guide", &base, false).unwrap();
+        commit(&repo, &[("setup.cfg", "[metadata]\n")], "chore: release", 2);
+        repo.set_head("refs/heads/feature/add-guide").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        commit(&repo, &[("guide.md", "# Guide\n")], "docs: add a guide (#4)", 1);
+        commit(&repo, &[("test_app.py", "def test(): pass\n")], "test: cover app", 1);
+
+        let repo_path = dir.path().to_str().unwrap();
+        let description = draft_pr_description(repo_path, &default_branch, "HEAD", None).unwrap();
+        assert_eq!(description.merge_base, base.id().to_string());
+        assert_eq!(description.title, "Add guide");
+        assert_eq!(description.commits, 2);
+        // The release commit of the base branch is not part of the change
+        assert_eq!(description.files_changed, 2);
+        assert_eq!(description.changed_docs, vec!["guide.md"]);
+        assert_eq!(description.changed_tests, vec!["test_app.py"]);
+        assert!(description.markdown.starts_with("# Add guide\n"));
+        assert!(description.markdown.contains("- add a guide (#4)"));
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            assert_eq!(
+                cli_backend.merge_base("HEAD", &default_branch).unwrap(),
+                description.merge_base
+            );
+        }
+    }
+
+    #[test]
+    fn test_change_sets_group_merged_branches_and_squash_merges() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "a\n")], "Base", 5);
+        let default_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature", &base, false).unwrap();
+        commit(&repo, &[("s.txt", "s\n")], "feat: search (#5)", 4);
+        let switch = |branch: &str| {
+            repo.set_head(&format!("refs/heads/{}", branch)).unwrap();
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+                .unwrap();
+        };
+        switch("feature");
+        commit(&repo, &[("f.txt", "f\n")], "Login form", 3);
+        commit(
+            &repo,
+            &[("f.txt", "f\ng\n"), ("a.txt", "b\n")],
+            "Validate",
+            2,
+        );
+        switch(&default_branch);
+
+        let ours = repo.head().unwrap().peel_to_commit().unwrap();
+        let theirs = repo
+            .find_branch("feature", git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        let mut index = repo.merge_commits(&ours, &theirs, None).unwrap();
+        let tree = repo.find_tree(index.write_tree_to(&repo).unwrap()).unwrap();
+        let seconds = chrono::Local::now().timestamp() - 86_400;
+        let signature = Signature::new("Ada", "ada@example.com", &Time::new(seconds, 120)).unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Merge pull request #7 from ada/feature\n\nAdd login form",
+            &tree,
+            &[&ours, &theirs],
+        )
+        .unwrap();
+
+        let sets = analyze_change_sets(dir.path().to_str().unwrap(), 30).unwrap();
+        let kinds: Vec<&str> = sets.iter().map(|set| set.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["merge", "squash", "direct"]);
+        let merge = &sets[0];
+        assert_eq!(merge.title, "Add login form");
+        assert_eq!(
+            (merge.pr_number, merge.branch.as_deref()),
+            (Some(7), Some("feature"))
+        );
+        assert_eq!(merge.commit_count, 2);
+        assert_eq!(
+            (merge.files_changed, merge.insertions, merge.deletions),
+            (2, 3, 1)
+        );
+        assert_eq!(merge.duration_hours, 48.0);
+        assert_eq!(
+            (sets[1].title.as_str(), sets[1].pr_number),
+            ("feat: search", Some(5))
+        );
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            let git2_backend = libgit2::Git2Backend::open(dir.path()).unwrap();
+            let since = since(30);
+            let cli_commits = cli_backend.commits_since(since).unwrap();
+            assert_eq!(cli_commits, git2_backend.commits_since(since).unwrap());
+            assert_eq!(cli_commits[0].parents.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_repositories_are_analyzed_together() {
+        let first = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(first.path()).unwrap();
+        commit(&repo, &[("a.txt", "one\n")], "Initial", 3);
+        commit(&repo, &[("a.txt", "two\n")], "Second", 2);
+        commit(&repo, &[("a.txt", "three\n")], "Third", 1);
+        let second = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(second.path()).unwrap();
+        commit(&repo, &[("b.txt", "one\n")], "Initial", 5);
+
+        let paths: Vec<String> = [first.path(), second.path(), Path::new("/no/such/repo")]
+            .iter()
+            .map(|path| path.to_str().unwrap().to_string())
+            .collect();
+        let quiet = ProgressSink::default();
+        let portfolio =
+            analyze_repositories(&paths, 30, false, &CancellationToken::default(), &quiet);
+
+        let reports: Vec<&str> = portfolio
+            .repositories
+            .iter()
+            .map(|r| r.path.as_str())
+            .collect();
+        assert_eq!(
+            reports,
+            paths.iter().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert!(portfolio.repositories[0].analysis.is_some());
+        assert!(portfolio.repositories[2].error.is_some());
+
+        let aggregate = &portfolio.aggregate;
+        assert_eq!(
+            (
+                aggregate.total_repositories,
+                aggregate.analyzed_repositories
+            ),
+            (3, 2)
+        );
+        assert_eq!(aggregate.total_commits, 4);
+        assert_eq!(aggregate.contributors.len(), 1);
+        let ada = &aggregate.contributors[0];
+        assert_eq!(ada.total_commits, 4);
+        assert_eq!(ada.repositories, vec![paths[0].clone(), paths[1].clone()]);
+        assert!(ada.first_commit_date < ada.last_commit_date);
+        let leader = &aggregate.churn_leaders[0];
+        assert_eq!(
+            (leader.repository.as_str(), leader.path.as_str()),
+            (paths[0].as_str(), "a.txt")
+        );
+        assert_eq!(leader.times_changed, 3);
+        assert_eq!(aggregate.churn_leaders.len(), 2);
+        assert!(!portfolio.cancelled);
+
+        let cancel = CancellationToken::default();
+        cancel.cancel();
+        let portfolio = analyze_repositories(&paths, 30, false, &cancel, &quiet);
+        assert!(portfolio.cancelled);
+        assert_eq!(portfolio.aggregate.analyzed_repositories, 0);
+        assert!(portfolio.repositories.iter().all(|r| r.error.is_some()));
+    }
+
+    #[test]
+    fn test_security_events_read_added_lines_of_each_commit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("client.py", "get(url)\n")], "Initial", 3);
+        commit(
+            &repo,
+            &[("client.py", "get(url, verify=False)\n")],
+            "Work around proxy",
+            2,
+        );
+        commit(&repo, &[("auth.py", "ok = True\n")], "Add auth", 1);
+
+        let scan = detect_security_events(dir.path().to_str().unwrap(), 30).unwrap();
+        assert_eq!(scan.commits_scanned, 3);
+        let flagged: Vec<(&str, &str)> = scan
+            .security_events
+            .iter()
+            .map(|event| (event.summary.as_str(), event.heuristic.as_str()))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![
+                ("Add auth", "auth_path"),
+                ("Work around proxy", "disabled_security_check"),
+            ]
+        );
+        assert_eq!(
+            scan.security_events[1].evidence,
+            vec!["client.py: get(url, verify=False)"]
+        );
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            let commits = cli_backend.commits_since(since(30)).unwrap();
+            assert_eq!(
+                security::scan_commits(&cli_backend, &commits).unwrap(),
+                scan
+            );
+        }
+    }
+
+    #[test]
+    fn test_file_history_follows_renames() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let content = "first line\nsecond line\nthird line\nfourth line\n";
+        commit(
+            &repo,
+            &[("old.txt", content), ("other.txt", "x\n")],
+            "Add old",
+            4,
+        );
+        commit(
+            &repo,
+            &[("old.txt", &format!("{}fifth line\n", content))],
+            "Grow old",
+            3,
+        );
+        commit(&repo, &[("other.txt", "y\n")], "Unrelated", 2);
+        std::fs::remove_file(dir.path().join("old.txt")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("old.txt")).unwrap();
+        index.write().unwrap();
+        commit(
+            &repo,
+            &[(
+                "new name.txt",
+                &format!("{}fifth line\nsixth line\n", content),
+            )],
+            "Rename",
+            1,
+        );
+
+        let repo_path = dir.path().to_str().unwrap();
+        let history = file_history(repo_path, "./new name.txt", true).unwrap();
+        let revisions: Vec<(&str, &str, Option<&str>, usize)> = history
+            .revisions
+            .iter()
+            .map(|r| {
+                (
+                    r.summary.as_str(),
+                    r.change.as_str(),
+                    r.old_path.as_deref(),
+                    r.insertions,
+                )
+            })
+            .collect();
+        assert_eq!(
+            revisions,
+            vec![
+                ("Rename", "renamed", Some("old.txt"), 1),
+                ("Grow old", "modified", None, 1),
+                ("Add old", "added", None, 4),
+            ]
+        );
+        assert_eq!(history.path, "new name.txt");
+        assert_eq!(history.revisions[2].path, "old.txt");
+        assert_eq!(history.authors[0].commits, 3);
+        assert_eq!(history.authors[0].insertions, 6);
+
+        let unfollowed = file_history(repo_path, "new name.txt", false).unwrap();
+        assert_eq!(unfollowed.total_commits, 1);
+        assert_eq!(unfollowed.revisions[0].change, "added");
+        let deleted = file_history(repo_path, "old.txt", false).unwrap();
+        assert_eq!(deleted.revisions[0].change, "deleted");
+        assert_eq!(deleted.total_commits, 3);
+
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            let git2_backend = libgit2::Git2Backend::open(dir.path()).unwrap();
+            for (path, follow) in [
+                ("new name.txt", true),
+                ("new name.txt", false),
+                ("old.txt", false),
+            ] {
+                assert_eq!(
+                    cli_backend.file_history(path, follow).unwrap(),
+                    git2_backend.file_history(path, follow).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decisions_without_adr_are_reported_unsynced() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join("docs/adr")).unwrap();
+        commit(
+            &repo,
+            &[
+                ("db.rs", "sqlite\n"),
+                (
+                    "docs/adr/0001-sqlite.md",
+                    "# Use SQLite\n\n**Status:** Accepted\n",
+                ),
+            ],
+            "Migrate storage to SQLite",
+            3,
+        );
+        commit(
+            &repo,
+            &[("plugins.rs", "v2\n")],
+            "Redesign plugin loading",
+            2,
+        );
+        let redesign = repo.head().unwrap().target().unwrap().to_string();
+        commit(&repo, &[("api.rs", "v2\n")], "Deprecate v1 API", 1);
+        commit(&repo, &[("api.rs", "v2!\n")], "Fix typo", 1);
+        std::fs::write(
+            dir.path().join("docs/adr/0002-plugins.md"),
+            format!(
+                "---\ntitle: Plugin loading\n---\n## Status\n\nProposed\n\nSee {}.\n",
+                &redesign[..10]
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("docs/notes.md"), format!("{}\n", redesign)).unwrap();
+
+        let repo_path = dir.path().to_str().unwrap();
+        let sync = cross_reference_decisions(repo_path, 30, &DecisionOptions::default()).unwrap();
+        assert_eq!(sync.total_decisions, 3);
+        let adrs: Vec<(&str, &str, Option<&str>)> = sync
+            .adrs
+            .iter()
+            .map(|adr| (adr.path.as_str(), adr.title.as_str(), adr.status.as_deref()))
+            .collect();
+        assert_eq!(
+            adrs,
+            vec![
+                ("docs/adr/0001-sqlite.md", "Use SQLite", Some("Accepted")),
+                (
+                    "docs/adr/0002-plugins.md",
+                    "Plugin loading",
+                    Some("Proposed")
+                ),
+            ]
+        );
+        let synced: Vec<(&str, &str, &str)> = sync
+            .synced
+            .iter()
+            .map(|s| {
+                (
+                    s.decision.decision_type.as_str(),
+                    s.decision.message.as_str(),
+                    s.adr_paths[0].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            synced,
+            vec![
+                (
+                    "architecture",
+                    "Redesign plugin loading",
+                    "docs/adr/0002-plugins.md"
+                ),
+                (
+                    "migration",
+                    "Migrate storage to SQLite",
+                    "docs/adr/0001-sqlite.md"
+                ),
+            ]
+        );
+        assert_eq!(sync.unsynced.len(), 1);
+        assert_eq!(sync.unsynced[0].decision_type, "deprecation");
+
+        let options: DecisionOptions = serde_json::from_str(
+            r#"{"keywords": {"typo": ["TYPO"]}, "adr_patterns": ["docs/*.md"]}"#,
+        )
+        .unwrap();
+        let sync = cross_reference_decisions(repo_path, 30, &options).unwrap();
+        assert_eq!(sync.total_decisions, 1);
+        assert_eq!(sync.unsynced[0].message, "Fix typo");
+        assert_eq!(sync.adrs.len(), 3);
+    }
+
+    #[test]
+    fn test_cached_analysis_is_keyed_by_head_and_days() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "one\n")], "Initial import", 2);
+        let repo_path = dir.path().to_str().unwrap();
+        let cache_dir = cache::cache_dir(repo_path, repo.path());
+        let head = repo.head().unwrap().target().unwrap().to_string();
+
+        let (never, quiet) = (CancellationToken::default(), ProgressSink::default());
+        let first = analyze_git_repository_cached(repo_path, 30, &never, &quiet).unwrap();
+        assert_eq!(first.repository_info.total_commits, 1);
+        assert!(cache::load(&cache_dir, repo_path, &head, 30).is_some());
+        assert!(cache::load(&cache_dir, repo_path, &head, 7).is_none());
+
+        // A stored entry is served as is, so a doctored one proves the cache was hit
+        let mut doctored = first;
+        doctored.repository_info.total_commits = 99;
+        cache::store(&cache_dir, repo_path, &head, 30, &doctored).unwrap();
+        let cached = analyze_git_repository_cached(repo_path, 30, &never, &quiet).unwrap();
+        assert_eq!(cached.repository_info.total_commits, 99);
+
+        commit(&repo, &[("a.txt", "two\n")], "Second", 1);
+        let moved = analyze_git_repository_cached(repo_path, 30, &never, &quiet).unwrap();
+        assert_eq!(moved.repository_info.total_commits, 2);
+
+        assert_eq!(invalidate_analysis_cache(repo_path).unwrap(), 2);
+        assert_eq!(invalidate_analysis_cache(repo_path).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_backends_agree_on_history_branches_and_tags() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "one\n")], "Initial import", 40);
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("old-feature", &head, false).unwrap();
+        repo.tag_lightweight("v0.1", head.as_object(), false)
+            .unwrap();
+        commit(
+            &repo,
+            &[("a.txt", "one\ntwo\n"), ("b.txt", "b\n")],
+            "Refactor storage\n\nBreaking layout change",
+            2,
+        );
+        commit(&repo, &[("a.txt", "two\n")], "Trim a", 1);
+
+        let since = chrono::Local::now() - chrono::Duration::days(30);
+        let git2_backend = libgit2::Git2Backend::open(dir.path()).unwrap();
+        let commits = git2_backend.commits_since(since).unwrap();
+        let cancel = CancellationToken::default();
+        cancel.cancel();
+        assert!(git2_backend
+            .commits_since_cancellable(since, &cancel)
+            .unwrap()
+            .is_empty());
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].summary, "Trim a");
+        assert_eq!(commits[0].files[0].insertions, 0);
+        assert_eq!(commits[0].files[0].deletions, 1);
+        assert_eq!(commits[1].files.len(), 2);
+        assert_eq!(git2_backend.commit_span().unwrap().total_commits, 3);
+        let branches = git2_backend.branches("HEAD").unwrap();
+        let old_feature = branches.iter().find(|b| b.name == "old-feature").unwrap();
+        assert_eq!((old_feature.ahead, old_feature.behind), (0, 2));
+        assert_eq!(git2_backend.tags().unwrap()[0].name, "v0.1");
+
+        let churn = get_code_churn(&commits, dir.path());
+        assert_eq!(churn.most_changed_files[0].path, "a.txt");
+        assert_eq!(churn.most_changed_files[0].times_changed, 2);
+        assert_eq!(churn.risk_hotspots[0].path, "a.txt");
+        assert_eq!(churn.risk_hotspots.len(), 2);
+        let decisions = decisions::find_decisions(&commits, &DecisionOptions::default().keywords);
+        assert_eq!(decisions.len(), 2); // "refactor" in the subject, "breaking" in the body
+        let knowledge = ownership::knowledge_distribution(&commits);
+        assert_eq!(knowledge.bus_factor, 1);
+        let contributors = get_contributor_insights(&commits, &knowledge);
+        assert_eq!(contributors[0].total_commits, 2);
+        assert!(contributors[0].sole_owner_of.is_empty()); // No directories
+
+        // The CLI fallback must report the same data where a git binary is available
+        assert_eq!(
+            git2_backend.commits_between("v0.1", "HEAD").unwrap(),
+            commits
+        );
+        if let Ok(cli_backend) = cli::CliBackend::open(dir.path()) {
+            assert_eq!(cli_backend.commits_since(since).unwrap(), commits);
+            assert_eq!(
+                cli_backend.commits_between("v0.1", "HEAD").unwrap(),
+                commits
+            );
+            assert_eq!(
+                cli_backend.commit_span().unwrap(),
+                git2_backend.commit_span().unwrap()
+            );
+            assert_eq!(cli_backend.branches("HEAD").unwrap(), branches);
+            assert_eq!(cli_backend.tags().unwrap(), git2_backend.tags().unwrap());
+            assert_eq!(
+                cli_backend.current_branch().unwrap(),
+                git2_backend.current_branch().unwrap()
+            );
+        }
+
+        let analysis = analyze_git_repository(
+            dir.path().to_str().unwrap(),
+            30,
+            &CancellationToken::default(),
+            &ProgressSink::default(),
+        ).unwrap();
+        assert_eq!(analysis.repository_info.total_commits, 3);
+        assert_eq!(analysis.repository_info.repository_age_days, 39);
+        assert_eq!(analysis.branch_analysis.merged_branches_count, 2);
+        assert_eq!(analysis.release_patterns.total_tags, 1);
+        let by_day = &analysis.commit_history.commits_by_day_of_week;
+        assert_eq!(by_day.values().sum::<usize>(), 2);
+        assert!(by_day
+            .keys()
+            .all(|day| activity::WEEKDAYS.contains(&day.as_str())));
+        let patterns = &analysis.development_patterns;
+        assert_eq!(patterns.hour_histogram.iter().sum::<usize>(), 2);
+        assert_eq!(patterns.contributor_activity[0].utc_offset, "+02:00");
+        let changelog = generate_changelog(dir.path().to_str().unwrap(), "v0.1", "HEAD").unwrap();
+        assert_eq!(changelog.total_commits, 2);
+        assert_eq!(changelog.sections[0].kind, "other");
+        assert!(changelog.breaking_changes.is_empty());
+
+        let repo_path = dir.path().to_str().unwrap();
+        assert_eq!(
+            analyze_contributors(repo_path, 30).unwrap()[0].email,
+            "ada@example.com"
+        );
+        assert_eq!(
+            analyze_code_churn(repo_path, 1)
+                .unwrap()
+                .total_files_ever_changed,
+            1
+        );
+        assert_eq!(analyze_branches(repo_path).unwrap().total_branches, 2);
+        assert!(analyze_branches(dir.path().join("a.txt").to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// rust_core/src/git_analyzer/ownership.rs
+//! How concentrated the knowledge of each directory is
+//!
+//! Every file change counts its changed lines (at least one, for binary files)
+//! toward the file's directories, up to `MAX_DEPTH` levels, per contributor
+//! email. The bus factor of a directory is the fewest contributors whose changes
+//! add up to `OWNERSHIP_SHARE` of the total; directories with a bus factor of one
+//! that are changed often are the knowledge silos worth spreading.
+
+use super::backend::RawCommit;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Share of a directory's changes its owners account for
+const OWNERSHIP_SHARE: f64 = 0.8;
+
+/// Deepest directory level reported ("src/a/b")
+const MAX_DEPTH: usize = 3;
+
+/// Commits a single-owner directory needs to be reported as critical
+const MIN_CRITICAL_COMMITS: usize = 3;
+
+/// Directories listed, most commits first
+const MAX_DIRECTORIES: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KnowledgeDistribution {
+    /// Bus factor of the whole repository
+    pub bus_factor: usize,
+    pub directories: Vec<DirectoryOwnership>,
+    /// Directories with one owner and at least `MIN_CRITICAL_COMMITS` commits,
+    /// most commits first
+    pub single_owner_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DirectoryOwnership {
+    /// "." for the repository root
+    pub path: String,
+    pub commits: usize,
+    pub changes: usize,
+    pub contributors: usize,
+    pub bus_factor: usize,
+    /// The `bus_factor` contributors, largest share first
+    pub owners: Vec<OwnerShare>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnerShare {
+    pub name: String,
+    pub email: String,
+    /// Fraction of the directory's changes, 0.0 to 1.0
+    pub share: f64,
+}
+
+#[derive(Default)]
+struct DirectoryStats<'a> {
+    commits: usize,
+    changes_by_email: HashMap<&'a str, usize>,
+}
+
+/// Knowledge distribution of `commits` (newest first)
+pub fn knowledge_distribution(commits: &[RawCommit]) -> KnowledgeDistribution {
+    // Commits are newest first: the first name seen per email is the current one
+    let mut names: HashMap<&str, &str> = HashMap::new();
+    let mut stats: HashMap<String, DirectoryStats> = HashMap::new();
+    for commit in commits {
+        names.entry(&commit.email).or_insert(&commit.author);
+        let mut touched: HashSet<String> = HashSet::new();
+        for file in &commit.files {
+            let changes = (file.insertions + file.deletions).max(1);
+            for directory in directories(&file.path) {
+                *stats
+                    .entry(directory.clone())
+                    .or_default()
+                    .changes_by_email
+                    .entry(&commit.email)
+                    .or_insert(0) += changes;
+                touched.insert(directory);
+            }
+        }
+        for directory in touched {
+            stats.entry(directory).or_default().commits += 1;
+        }
+    }
+
+    let mut directories: Vec<DirectoryOwnership> = stats
+        .into_iter()
+        .map(|(path, stats)| ownership(path, stats, &names))
+        .collect();
+    directories.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.path.cmp(&b.path)));
+
+    let bus_factor = directories
+        .iter()
+        .find(|directory| directory.path == ".")
+        .map_or(0, |root| root.bus_factor);
+    let single_owner_paths = directories
+        .iter()
+        .filter(|d| d.path != "." && d.bus_factor == 1 && d.commits >= MIN_CRITICAL_COMMITS)
+        .map(|d| d.path.clone())
+        .collect();
+    directories.truncate(MAX_DIRECTORIES);
+
+    KnowledgeDistribution {
+        bus_factor,
+        directories,
+        single_owner_paths,
+    }
+}
+
+/// Directories of the paths where `email` is the only owner
+pub fn sole_owner_paths<'a>(distribution: &'a KnowledgeDistribution, email: &str) -> Vec<&'a str> {
+    distribution
+        .directories
+        .iter()
+        .filter(|d| d.path != "." && d.bus_factor == 1 && d.owners[0].email == email)
+        .map(|d| d.path.as_str())
+        .collect()
+}
+
+fn ownership(
+    path: String,
+    stats: DirectoryStats,
+    names: &HashMap<&str, &str>,
+) -> DirectoryOwnership {
+    let mut changes: Vec<(&str, usize)> = stats.changes_by_email.into_iter().collect();
+    changes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let total: usize = changes.iter().map(|(_, count)| count).sum();
+
+    let mut owners = Vec::new();
+    let mut covered = 0;
+    for (email, count) in &changes {
+        owners.push(OwnerShare {
+            name: names.get(email).unwrap_or(email).to_string(),
+            email: email.to_string(),
+            share: *count as f64 / total as f64,
+        });
+        covered += count;
+        if covered as f64 >= total as f64 * OWNERSHIP_SHARE {
+            break;
+        }
+    }
+
+    DirectoryOwnership {
+        path,
+        commits: stats.commits,
+        changes: total,
+        contributors: changes.len(),
+        bus_factor: owners.len(),
+        owners,
+    }
+}
+
+/// "." and the directories of `path` down to `MAX_DEPTH` levels
+fn directories(path: &str) -> Vec<String> {
+    let mut directories = vec![".".to_string()];
+    let parts: Vec<&str> = path.split('/').collect();
+    for depth in 1..parts.len().min(MAX_DEPTH + 1) {
+        directories.push(parts[..depth].join("/"));
+    }
+    directories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_factor_and_single_owner_directories() {
+        let commits = [
+            RawCommit::fixture("")
+                .email("ana@example.com")
+                .files(&[("src/core/engine.rs", 50, 0)]),
+            RawCommit::fixture("")
+                .email("ana@example.com")
+                .files(&[("src/core/engine.rs", 30, 0)]),
+            RawCommit::fixture("")
+                .email("ana@example.com")
+                .files(&[("src/core/state.rs", 20, 0), ("README.md", 5, 0)]),
+            RawCommit::fixture("")
+                .email("bo@example.com")
+                .files(&[("src/web/app.rs", 40, 0)]),
+            RawCommit::fixture("")
+                .email("cy@example.com")
+                .files(&[("src/web/app.rs", 40, 0), ("logo.png", 0, 0)]),
+        ];
+
+        let distribution = knowledge_distribution(&commits);
+        assert_eq!(distribution.bus_factor, 3);
+        let directory = |path: &str| {
+            distribution
+                .directories
+                .iter()
+                .find(|d| d.path == path)
+                .unwrap()
+        };
+        let core = directory("src/core");
+        assert_eq!((core.commits, core.changes, core.bus_factor), (3, 100, 1));
+        assert_eq!(core.owners[0].name, "ana");
+        assert_eq!(core.owners[0].share, 1.0);
+        let web = directory("src/web");
+        assert_eq!((web.contributors, web.bus_factor), (2, 2));
+        assert_eq!(directory(".").changes, 186);
+        assert_eq!(distribution.single_owner_paths, vec!["src/core"]);
+        assert_eq!(
+            sole_owner_paths(&distribution, "ana@example.com"),
+            vec!["src/core"]
+        );
+        assert_eq!(directories("a/b/c/d/e.rs"), vec![".", "a", "a/b", "a/b/c"]);
+    }
+}
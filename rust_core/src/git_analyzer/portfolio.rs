@@ -0,0 +1,235 @@
+// rust_core/src/git_analyzer/portfolio.rs
+//! Analyses of several repositories at once
+//!
+//! Each repository is analyzed on its own rayon task; a repository that cannot be
+//! analyzed is reported with its error instead of failing the whole run. The
+//! aggregate merges contributors across repositories by email (case-insensitive)
+//! and ranks the most changed files of every repository together.
+
+use super::backend::DATE_FORMAT;
+use super::{FileChurn, GitAnalysis};
+use crate::cancel::CancellationToken;
+use crate::error::CdeError;
+use chrono::DateTime;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Files listed in the cross-repository churn ranking
+const CHURN_LEADERS: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortfolioAnalysis {
+    /// In the order the paths were given
+    pub repositories: Vec<RepositoryReport>,
+    pub aggregate: PortfolioAggregate,
+    /// Whether the run was cancelled; repositories not started are reported with
+    /// an error and left out of the aggregate
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepositoryReport {
+    pub path: String,
+    pub analysis: Option<GitAnalysis>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortfolioAggregate {
+    pub total_repositories: usize,
+    pub analyzed_repositories: usize,
+    /// Commits of the analyzed window, over all repositories
+    pub total_commits: usize,
+    /// Highest combined impact first
+    pub contributors: Vec<PortfolioContributor>,
+    /// Most changed files of all repositories, most changes first
+    pub churn_leaders: Vec<RepositoryFileChurn>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortfolioContributor {
+    pub name: String,
+    pub email: String,
+    pub total_commits: usize,
+    pub lines_added: usize,
+    pub lines_deleted: usize,
+    pub files_modified: usize,
+    pub impact_score: f64,
+    pub first_commit_date: String,
+    pub last_commit_date: String,
+    /// Paths of the repositories they committed to, most commits first
+    pub repositories: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepositoryFileChurn {
+    pub repository: String,
+    pub path: String,
+    pub times_changed: usize,
+    pub total_insertions: usize,
+    pub total_deletions: usize,
+    pub last_modified: String,
+}
+
+/// Runs `analyze` on every path in parallel and aggregates the results; paths not
+/// started once `cancel` is cancelled are not analyzed
+pub fn analyze_portfolio<F>(
+    paths: &[String],
+    cancel: &CancellationToken,
+    analyze: F,
+) -> PortfolioAnalysis
+where
+    F: Fn(&str) -> Result<GitAnalysis, CdeError> + Sync,
+{
+    let repositories: Vec<RepositoryReport> = paths
+        .par_iter()
+        .map(|path| {
+            let result = if cancel.is_cancelled() {
+                Err("Cancelled before analysis".to_string())
+            } else {
+                analyze(path).map_err(|e| e.to_string())
+            };
+            match result {
+                Ok(analysis) => RepositoryReport {
+                    path: path.clone(),
+                    analysis: Some(analysis),
+                    error: None,
+                },
+                Err(error) => RepositoryReport {
+                    path: path.clone(),
+                    analysis: None,
+                    error: Some(error),
+                },
+            }
+        })
+        .collect();
+    let aggregate = aggregate(&repositories);
+    PortfolioAnalysis {
+        repositories,
+        aggregate,
+        cancelled: cancel.is_cancelled(),
+    }
+}
+
+fn aggregate(repositories: &[RepositoryReport]) -> PortfolioAggregate {
+    let analyzed: Vec<(&str, &GitAnalysis)> = repositories
+        .iter()
+        .filter_map(|report| Some((report.path.as_str(), report.analysis.as_ref()?)))
+        .collect();
+
+    let mut contributors: HashMap<String, PortfolioContributor> = HashMap::new();
+    // Commits per repository of each contributor, to rank their repositories
+    let mut commits_by_repository: HashMap<String, Vec<(&str, usize)>> = HashMap::new();
+    for (path, analysis) in &analyzed {
+        for insight in &analysis.contributor_insights {
+            let key = insight.email.to_lowercase();
+            let contributor =
+                contributors
+                    .entry(key.clone())
+                    .or_insert_with(|| PortfolioContributor {
+                        name: insight.name.clone(),
+                        email: insight.email.clone(),
+                        total_commits: 0,
+                        lines_added: 0,
+                        lines_deleted: 0,
+                        files_modified: 0,
+                        impact_score: 0.0,
+                        first_commit_date: insight.first_commit_date.clone(),
+                        last_commit_date: insight.last_commit_date.clone(),
+                        repositories: Vec::new(),
+                    });
+            contributor.total_commits += insight.total_commits;
+            contributor.lines_added += insight.lines_added;
+            contributor.lines_deleted += insight.lines_deleted;
+            contributor.files_modified += insight.files_modified;
+            contributor.impact_score += insight.impact_score;
+            if is_before(&insight.first_commit_date, &contributor.first_commit_date) {
+                contributor.first_commit_date = insight.first_commit_date.clone();
+            }
+            if is_before(&contributor.last_commit_date, &insight.last_commit_date) {
+                // The latest commit also has the name they use now
+                contributor.last_commit_date = insight.last_commit_date.clone();
+                contributor.name = insight.name.clone();
+            }
+            commits_by_repository
+                .entry(key)
+                .or_default()
+                .push((path, insight.total_commits));
+        }
+    }
+    let mut contributors: Vec<PortfolioContributor> = contributors
+        .into_iter()
+        .map(|(key, mut contributor)| {
+            let mut repositories = commits_by_repository.remove(&key).unwrap_or_default();
+            repositories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            contributor.repositories = repositories
+                .into_iter()
+                .map(|(path, _)| path.to_string())
+                .collect();
+            contributor
+        })
+        .collect();
+    contributors.sort_by(|a, b| {
+        b.impact_score
+            .total_cmp(&a.impact_score)
+            .then_with(|| a.email.cmp(&b.email))
+    });
+
+    let mut churn_leaders: Vec<RepositoryFileChurn> = analyzed
+        .iter()
+        .flat_map(|(path, analysis)| {
+            analysis
+                .code_churn
+                .most_changed_files
+                .iter()
+                .map(|churn| repository_churn(path, churn))
+        })
+        .collect();
+    churn_leaders.sort_by(|a, b| {
+        b.times_changed
+            .cmp(&a.times_changed)
+            .then_with(|| {
+                (b.total_insertions + b.total_deletions)
+                    .cmp(&(a.total_insertions + a.total_deletions))
+            })
+            .then_with(|| a.repository.cmp(&b.repository))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    churn_leaders.truncate(CHURN_LEADERS);
+
+    PortfolioAggregate {
+        total_repositories: repositories.len(),
+        analyzed_repositories: analyzed.len(),
+        total_commits: analyzed
+            .iter()
+            .flat_map(|(_, analysis)| &analysis.contributor_insights)
+            .map(|insight| insight.total_commits)
+            .sum(),
+        contributors,
+        churn_leaders,
+    }
+}
+
+fn repository_churn(repository: &str, churn: &FileChurn) -> RepositoryFileChurn {
+    RepositoryFileChurn {
+        repository: repository.to_string(),
+        path: churn.path.clone(),
+        times_changed: churn.times_changed,
+        total_insertions: churn.total_insertions,
+        total_deletions: churn.total_deletions,
+        last_modified: churn.last_modified.clone(),
+    }
+}
+
+/// Compares two `format_time` dates as instants, whatever their offsets
+fn is_before(a: &str, b: &str) -> bool {
+    match (
+        DateTime::parse_from_str(a, DATE_FORMAT),
+        DateTime::parse_from_str(b, DATE_FORMAT),
+    ) {
+        (Ok(a), Ok(b)) => a < b,
+        _ => false,
+    }
+}
@@ -0,0 +1,317 @@
+// rust_core/src/git_analyzer/pr_description.rs
+//! Pull request descriptions drafted from a branch's commits and diff
+//!
+//! The commits of the head branch missing from the base are grouped like a
+//! changelog (Conventional Commit types, breaking changes, `#123` references)
+//! and the files changed since the merge base are classified as documentation,
+//! tests, configuration or code. The description is rendered with a built-in
+//! Jinja template, or the caller's, as a first draft the orchestrator may then
+//! refine; no model is involved here.
+
+use super::backend::RawCommit;
+use super::changelog::{self, ChangelogEntry, ChangelogSection, Reference};
+use super::diff::FileDiff;
+use crate::error::CdeError;
+use crate::markdown_format::{format_markdown, MarkdownStyle};
+use crate::templating;
+use crate::test_detection::is_test_file;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const DEFAULT_TEMPLATE: &str = r#"# {{ title }}
+
+## Summary
+
+{{ commits }} commit{{ "" if commits == 1 else "s" }} from `{{ head }}` onto `{{ base }}`: {{ files_changed }} file{{ "" if files_changed == 1 else "s" }} changed, +{{ insertions }} / -{{ deletions }}.
+{% if breaking_changes %}
+## Breaking Changes
+
+{% for entry in breaking_changes -%}
+- {% if entry.scope %}**{{ entry.scope }}:** {% endif %}{{ entry.description }} ({{ entry.hash }})
+{% endfor -%}
+{% endif %}
+## Changes
+{% for section in sections %}
+### {{ section.title }}
+
+{% for entry in section.entries -%}
+- {% if entry.scope %}**{{ entry.scope }}:** {% endif %}{{ entry.description }} ({{ entry.hash }})
+{% endfor -%}
+{% else %}
+No commits.
+{% endfor %}
+## Documentation
+
+{% for path in changed_docs -%}
+- `{{ path }}`
+{% else -%}
+No documentation changed.
+{% endfor %}
+## Tests
+
+{% for path in changed_tests -%}
+- `{{ path }}`
+{% else -%}
+No test files changed.
+{% endfor %}
+## Files
+
+| File | Change | Added | Removed |
+| --- | --- | --- | --- |
+{% for file in files -%}
+| `{{ file.path }}` | {{ file.change }}{% if file.old_path %} from `{{ file.old_path }}`{% endif %} | {{ file.insertions }} | {{ file.deletions }} |
+{% endfor -%}
+{% if references %}
+## References
+
+{% for reference in references -%}
+- {% if reference.url %}[{{ reference.id }}]({{ reference.url }}){% else %}{{ reference.id }}{% endif %}
+{% endfor -%}
+{% endif %}
+"#;
+
+/// File names that are documentation wherever they are
+const DOC_FILE_NAMES: &[&str] = &["README", "CHANGELOG", "CONTRIBUTING"];
+
+/// Extensions of documentation files
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "rst", "adoc", "txt"];
+
+/// Extensions of configuration files
+const CONFIG_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json", "ini", "cfg", "lock"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrDescription {
+    pub base: String,
+    pub head: String,
+    /// Commit the diff starts from: the best common ancestor of base and head
+    pub merge_base: String,
+    pub title: String,
+    pub commits: usize,
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub sections: Vec<ChangelogSection>,
+    /// Also listed in their type's section
+    pub breaking_changes: Vec<ChangelogEntry>,
+    /// Pull requests and issues the commits mention
+    pub references: Vec<Reference>,
+    pub contributors: Vec<String>,
+    pub files: Vec<ChangedFile>,
+    pub changed_docs: Vec<String>,
+    pub changed_tests: Vec<String>,
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub change: String,   // "added", "deleted", "modified", "renamed"
+    pub category: String, // "docs", "tests", "config", "code"
+    pub binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// "docs", "tests", "config" or "code"
+fn category(path: &str) -> &'static str {
+    let path = Path::new(path);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let in_docs = path
+        .components()
+        .any(|c| c.as_os_str().eq_ignore_ascii_case("docs"));
+    if is_test_file(path) {
+        "tests"
+    } else if DOC_FILE_NAMES.contains(&stem.to_ascii_uppercase().as_str())
+        || (DOC_EXTENSIONS.contains(&extension.as_str()) && (in_docs || extension != "txt"))
+    {
+        "docs"
+    } else if CONFIG_EXTENSIONS.contains(&extension.as_str()) || stem.starts_with(".env") {
+        "config"
+    } else {
+        "code"
+    }
+}
+
+/// Title of the pull request: the summary of its only commit, else the head
+/// branch name as words ("feature/retry-uploads" -> "Retry uploads")
+fn title(commits: &[RawCommit], head_branch: &str) -> String {
+    let name = head_branch.rsplit('/').next().unwrap_or(head_branch);
+    let words = name.replace(['-', '_'], " ");
+    let words = words.trim();
+    match commits {
+        [only] => only.summary.trim().to_string(),
+        _ if words.is_empty() || head_branch == "HEAD" => commits
+            .first()
+            .map_or_else(String::new, |newest| newest.summary.trim().to_string()),
+        _ => {
+            let mut chars = words.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Builds the description of `commits` (newest first) and `files`, without its
+/// Markdown; `head_branch` names the branch when `head` is a bare revision
+pub fn build_pr_description(
+    base: &str,
+    head: &str,
+    head_branch: &str,
+    merge_base: &str,
+    commits: &[RawCommit],
+    files: &[FileDiff],
+    remote_url: Option<&str>,
+) -> PrDescription {
+    let log = changelog::build_changelog(base, head, commits, remote_url);
+    let mut references: Vec<Reference> = Vec::new();
+    for reference in log
+        .sections
+        .iter()
+        .flat_map(|section| &section.entries)
+        .flat_map(|entry| &entry.references)
+    {
+        if !references.iter().any(|known| known.id == reference.id) {
+            references.push(reference.clone());
+        }
+    }
+    let files: Vec<ChangedFile> = files
+        .iter()
+        .map(|file| ChangedFile {
+            path: file.path.clone(),
+            old_path: file.old_path.clone(),
+            change: file.change.clone(),
+            category: category(&file.path).to_string(),
+            binary: file.binary,
+            insertions: file.insertions,
+            deletions: file.deletions,
+        })
+        .collect();
+    let paths_of = |wanted: &str| -> Vec<String> {
+        files
+            .iter()
+            .filter(|file| file.category == wanted)
+            .map(|file| file.path.clone())
+            .collect()
+    };
+
+    PrDescription {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base: merge_base.to_string(),
+        title: title(commits, head_branch),
+        commits: commits.len(),
+        files_changed: files.len(),
+        insertions: files.iter().map(|file| file.insertions).sum(),
+        deletions: files.iter().map(|file| file.deletions).sum(),
+        sections: log.sections,
+        breaking_changes: log.breaking_changes,
+        references,
+        contributors: log.contributors,
+        changed_docs: paths_of("docs"),
+        changed_tests: paths_of("tests"),
+        files,
+        markdown: String::new(),
+    }
+}
+
+/// Renders `description` with the built-in template, or `template` (its
+/// directory, name and source)
+pub fn render_markdown(
+    description: &PrDescription,
+    root: &Path,
+    template: Option<(&Path, &str, &str)>,
+) -> Result<String, CdeError> {
+    let context = serde_json::to_value(description)
+        .map_err(|e| CdeError::serialization("Failed to serialize PR description").caused_by(&e))?;
+    let rendered = match template {
+        Some((dir, name, source)) => templating::render_source(dir, name, source, &context)?,
+        None => templating::render_source(root, "PR.md.j2", DEFAULT_TEMPLATE, &context)?,
+    };
+    format_markdown(&rendered, &MarkdownStyle::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_diff(path: &str, change: &str, insertions: usize) -> FileDiff {
+        FileDiff {
+            path: path.to_string(),
+            old_path: None,
+            change: change.to_string(),
+            binary: false,
+            insertions,
+            deletions: 0,
+            hunks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_files_are_classified() {
+        assert_eq!(category("docs/guide.md"), "docs");
+        assert_eq!(category("README"), "docs");
+        assert_eq!(category("docs/notes.txt"), "docs");
+        assert_eq!(category("tests/test_api.py"), "tests");
+        assert_eq!(category("src/parser_test.go"), "tests");
+        assert_eq!(category("Cargo.toml"), "config");
+        assert_eq!(category("src/main.rs"), "code");
+    }
+
+    #[test]
+    fn test_title_prefers_the_only_commit_then_the_branch_name() {
+        let one = [RawCommit::fixture("aaaaaaaaaa").message("fix: handle empty tags")];
+        assert_eq!(title(&one, "feature/empty-tags"), "fix: handle empty tags");
+        let two = [
+            RawCommit::fixture("bbbbbbbbbb").message("feat: retry uploads"),
+            RawCommit::fixture("aaaaaaaaaa").message("fix: handle empty tags"),
+        ];
+        assert_eq!(title(&two, "feature/retry_uploads"), "Retry uploads");
+        assert_eq!(title(&two, "HEAD"), "feat: retry uploads");
+    }
+
+    #[test]
+    fn test_description_groups_commits_and_renders_markdown() {
+        let commits = [
+            RawCommit::fixture("bbbbbbbbbb").message("feat(api)!: drop v1 endpoints (#12)"),
+            RawCommit::fixture("aaaaaaaaaa").message("docs: explain v2"),
+        ];
+        let files = [
+            file_diff("src/api.rs", "modified", 10),
+            file_diff("docs/v2.md", "added", 4),
+        ];
+        let mut description = build_pr_description(
+            "main",
+            "HEAD",
+            "feature/api-v2",
+            "cccccccccc",
+            &commits,
+            &files,
+            Some("https://github.com/acme/widgets"),
+        );
+        assert_eq!(description.title, "Api v2");
+        assert_eq!(description.insertions, 14);
+        assert_eq!(description.changed_docs, vec!["docs/v2.md"]);
+        assert!(description.changed_tests.is_empty());
+        assert_eq!(description.breaking_changes.len(), 1);
+        assert_eq!(description.references[0].id, "#12");
+
+        description.markdown = render_markdown(&description, Path::new("."), None).unwrap();
+        let markdown = &description.markdown;
+        assert!(markdown.starts_with("# Api v2\n"));
+        assert!(markdown.contains("2 commits from `HEAD` onto `main`"));
+        assert!(markdown
+            .contains("## Breaking Changes\n\n- **api:** drop v1 endpoints (#12) (bbbbbbb)"));
+        assert!(markdown.contains("### Documentation\n\n- explain v2 (aaaaaaa)"));
+        assert!(markdown.contains("No test files changed."));
+        assert!(markdown.contains("| `docs/v2.md` | added | 4 | 0 |"));
+        assert!(markdown.contains("[#12](https://github.com/acme/widgets/issues/12)"));
+    }
+}
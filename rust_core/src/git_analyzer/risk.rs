@@ -0,0 +1,182 @@
+// rust_core/src/git_analyzer/risk.rs
+//! Refactoring risk of frequently changed files
+//!
+//! Churn alone flags config files and changelogs; complexity alone flags stable
+//! legacy code nobody touches. A file is risky when it is both changed often and
+//! hard to change, so the score multiplies relative churn by relative complexity
+//! (a cyclomatic estimate and the file size) of the file as it is checked out.
+
+use super::FileChurn;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Files larger than this are not read for the complexity estimate (bytes)
+const MAX_ANALYZED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Share of the complexity part of the score taken by the cyclomatic estimate;
+/// the rest is file size
+const CYCLOMATIC_WEIGHT: f64 = 0.7;
+
+/// Relative value (0-1) from which a metric is listed as a contributing factor
+const FACTOR_THRESHOLD: f64 = 0.5;
+
+/// Number of ranked hotspots returned
+const MAX_HOTSPOTS: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RiskHotspot {
+    pub path: String,
+    /// 0-100, relative to the other changed files of the repository
+    pub risk_score: f64,
+    pub times_changed: usize,
+    pub lines_changed: usize,
+    pub line_count: usize,
+    /// 1 + decision points (branches, loops, catches, boolean operators)
+    pub cyclomatic_estimate: usize,
+    /// "frequent_changes", "heavy_churn", "high_complexity", "large_file"
+    pub factors: Vec<String>,
+}
+
+/// Changed files that still exist as text, highest risk first
+pub fn risk_hotspots(root: &Path, churn: &[FileChurn]) -> Vec<RiskHotspot> {
+    let measured: Vec<(&FileChurn, usize, usize)> = churn
+        .par_iter()
+        .filter_map(|file| {
+            let path = root.join(&file.path);
+            if std::fs::metadata(&path).ok()?.len() > MAX_ANALYZED_FILE_SIZE {
+                return None;
+            }
+            // Binary and non-UTF8 files have no meaningful complexity
+            let content = std::fs::read_to_string(path).ok()?;
+            Some((file, content.lines().count(), cyclomatic_estimate(&content)))
+        })
+        .collect();
+
+    let max = |metric: &dyn Fn(&(&FileChurn, usize, usize)) -> usize| {
+        measured.iter().map(metric).max().unwrap_or(0).max(1) as f64
+    };
+    let max_changes = max(&|(file, _, _)| file.times_changed);
+    let max_churn = max(&|(file, _, _)| file.total_insertions + file.total_deletions);
+    let max_lines = max(&|(_, lines, _)| *lines);
+    let max_complexity = max(&|(_, _, complexity)| *complexity);
+
+    let mut hotspots: Vec<RiskHotspot> = measured
+        .into_iter()
+        .map(|(file, line_count, cyclomatic_estimate)| {
+            let lines_changed = file.total_insertions + file.total_deletions;
+            let changes = file.times_changed as f64 / max_changes;
+            let churn = lines_changed as f64 / max_churn;
+            let size = line_count as f64 / max_lines;
+            let complexity = cyclomatic_estimate as f64 / max_complexity;
+
+            let change_part = (changes + churn) / 2.0;
+            let complexity_part = CYCLOMATIC_WEIGHT * complexity + (1.0 - CYCLOMATIC_WEIGHT) * size;
+            let factors = [
+                ("frequent_changes", changes),
+                ("heavy_churn", churn),
+                ("high_complexity", complexity),
+                ("large_file", size),
+            ]
+            .into_iter()
+            .filter(|(_, value)| *value >= FACTOR_THRESHOLD)
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+            RiskHotspot {
+                path: file.path.clone(),
+                risk_score: (change_part * complexity_part * 1000.0).round() / 10.0,
+                times_changed: file.times_changed,
+                lines_changed,
+                line_count,
+                cyclomatic_estimate,
+                factors,
+            }
+        })
+        .filter(|hotspot| hotspot.risk_score > 0.0)
+        .collect();
+
+    hotspots.sort_by(|a, b| {
+        b.risk_score
+            .total_cmp(&a.risk_score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    hotspots.truncate(MAX_HOTSPOTS);
+    hotspots
+}
+
+/// Language-agnostic McCabe estimate: 1 + the decision points found outside
+/// comment lines
+pub fn cyclomatic_estimate(content: &str) -> usize {
+    static DECISION_RE: OnceLock<Regex> = OnceLock::new();
+    let decision_re = DECISION_RE.get_or_init(|| {
+        Regex::new(r"\b(?:if|elif|elsif|for|foreach|while|case|catch|except|when)\b|&&|\|\|")
+            .unwrap()
+    });
+
+    let decisions: usize = content
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| {
+            !["//", "#", "/*", "*", "--"]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+        })
+        .map(|line| decision_re.find_iter(line).count())
+        .sum();
+    1 + decisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn churn(path: &str, times_changed: usize, lines: usize) -> FileChurn {
+        FileChurn {
+            path: path.to_string(),
+            times_changed,
+            total_insertions: lines,
+            total_deletions: 0,
+            last_modified: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_risk_ranks_complex_churned_files_above_simple_or_stable_ones() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let complex = "fn run(x: i32) {\n    // if this is a comment it does not count\n    \
+                       if x > 0 && x < 10 {\n        for i in 0..x {\n            \
+                       while i > 2 {}\n        }\n    }\n}\n";
+        assert_eq!(cyclomatic_estimate(complex), 5);
+        std::fs::write(dir.path().join("engine.rs"), complex).unwrap();
+        std::fs::write(dir.path().join("CHANGELOG.md"), "- fix\n").unwrap();
+        std::fs::write(dir.path().join("legacy.rs"), complex).unwrap();
+        std::fs::write(dir.path().join("logo.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let files = [
+            churn("CHANGELOG.md", 10, 10),
+            churn("engine.rs", 8, 40),
+            churn("legacy.rs", 1, 1),
+            churn("logo.bin", 10, 0),
+            churn("deleted.rs", 10, 50),
+        ];
+        let hotspots = risk_hotspots(dir.path(), &files);
+
+        let ranked: Vec<&str> = hotspots.iter().map(|h| h.path.as_str()).collect();
+        assert_eq!(ranked, vec!["engine.rs", "CHANGELOG.md", "legacy.rs"]);
+        assert_eq!(hotspots[0].cyclomatic_estimate, 5);
+        assert_eq!(
+            hotspots[0].factors,
+            vec![
+                "frequent_changes",
+                "heavy_churn",
+                "high_complexity",
+                "large_file"
+            ]
+        );
+        assert_eq!(hotspots[1].factors, vec!["frequent_changes"]);
+        assert!(hotspots[0].risk_score > 2.0 * hotspots[1].risk_score);
+    }
+}
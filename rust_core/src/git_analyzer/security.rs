@@ -0,0 +1,349 @@
+// rust_core/src/git_analyzer/security.rs
+//! Commits a security reviewer should look at first
+//!
+//! Two kinds of heuristics flag a commit. Path rules match the files it touched
+//! (authentication, cryptography, CI configuration and secrets, dependency
+//! manifests). Delta rules look at what it changed: many test lines removed for
+//! few added, or added lines that switch off a safety check (TLS verification,
+//! CSRF protection, commit hooks, linters' security rules). Every event says which
+//! rule fired and on what, so the ranking can be explained to the reviewer.
+
+use super::backend::{format_time, GitBackend, RawCommit};
+use super::diff::DiffOptions;
+use crate::error::CdeError;
+use rayon::prelude::*;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Test lines a commit must remove to be flagged
+const LARGE_TEST_DELETION: usize = 100;
+
+/// Evidence items kept per event
+const MAX_EVIDENCE: usize = 5;
+
+/// Characters of an added line kept as evidence
+const MAX_EVIDENCE_LENGTH: usize = 160;
+
+/// Rules matched against lowercase paths: (heuristic, category, severity,
+/// explanation, pattern)
+const PATH_RULES: &[(&str, &str, &str, &str, &str)] = &[
+    (
+        "auth_path",
+        "auth",
+        "high",
+        "Touches authentication or authorization code",
+        r"(^|/|_|-)(auth|authn|authz|login|logout|session|oauth2?|sso|saml|permissions?|acl|rbac|passwords?|jwt)(/|\.|_|-|s/|$)",
+    ),
+    (
+        "crypto_path",
+        "crypto",
+        "high",
+        "Touches cryptography, TLS or key material",
+        r"(crypt|cipher|tls|ssl|certificate|keystore|signing)|\.(pem|key|crt|p12|pfx|jks)$",
+    ),
+    (
+        "ci_secrets_path",
+        "ci_secrets",
+        "high",
+        "Changes CI pipelines, secrets or environment files",
+        r"(^|/)(\.github/workflows|\.circleci|\.buildkite)/|(^|/)(\.gitlab-ci\.yml|jenkinsfile|azure-pipelines\.ya?ml|\.travis\.yml)$|(^|/)\.env($|\.)|(^|/)secrets?(/|\.)",
+    ),
+    (
+        "dependency_manifest",
+        "dependencies",
+        "medium",
+        "Changes dependency manifests or lock files",
+        r"(^|/)(package(-lock)?\.json|yarn\.lock|pnpm-lock\.yaml|cargo\.(toml|lock)|requirements[\w.-]*\.txt|pyproject\.toml|poetry\.lock|uv\.lock|pipfile(\.lock)?|setup\.(py|cfg)|go\.(mod|sum)|gemfile(\.lock)?|pom\.xml|build\.gradle(\.kts)?|composer\.(json|lock))$",
+    ),
+];
+
+/// Patterns of added lines that disable a check: (explanation, pattern)
+const DISABLED_CHECKS: &[(&str, &str)] = &[
+    (
+        "TLS certificate verification turned off",
+        r"(?i)verify\s*=\s*false|rejectunauthorized\s*:\s*false|node_tls_reject_unauthorized\s*=?\s*['\x22]?0|insecureskipverify\s*:\s*true|danger_accept_invalid_certs|check_hostname\s*=\s*false|_create_unverified_context|cert_none",
+    ),
+    (
+        "SSH host key checking turned off",
+        r"(?i)stricthostkeychecking\s*=?\s*no",
+    ),
+    (
+        "Insecure transfer flag",
+        r"(^|\s)(curl|wget)\s.*(\s-k\b|--insecure|--no-check-certificate)",
+    ),
+    (
+        "Commit hooks or signature checks skipped",
+        r"--no-verify|--no-gpg-sign|--allow-unsigned",
+    ),
+    (
+        "CSRF protection disabled",
+        r"(?i)@csrf_exempt|csrf\w*\s*[:=]\s*false|csrf\(\)\.disable\(\)",
+    ),
+    (
+        "Security linter finding suppressed",
+        r"(?i)#\s*nosec|nosemgrep|eslint-disable.*security|noqa:\s*s\d+|@suppresswarnings\(.*security",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityScan {
+    pub commits_scanned: usize,
+    /// High severity first, then newest first
+    pub security_events: Vec<SecurityEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    pub commit_hash: String,
+    pub date: String,
+    pub author: String,
+    pub summary: String,
+    /// Rule that fired, e.g. "auth_path" or "disabled_security_check"
+    pub heuristic: String,
+    pub category: String, // "auth", "crypto", "ci_secrets", "dependencies", "tests", "disabled_check"
+    pub severity: String, // "high", "medium", "low"
+    /// Why the rule fired, in words
+    pub explanation: String,
+    /// Matched paths, or "path: line" for added lines
+    pub evidence: Vec<String>,
+}
+
+/// Flags the non-merge `commits` (newest first); added lines are read from each
+/// commit's diff against its first parent
+pub fn scan_commits(
+    backend: &dyn GitBackend,
+    commits: &[RawCommit],
+) -> Result<SecurityScan, CdeError> {
+    let options = DiffOptions {
+        context_lines: 0,
+        detect_renames: false,
+        ..DiffOptions::default()
+    };
+    let scanned: Vec<&RawCommit> = commits
+        .iter()
+        .filter(|commit| commit.parents.len() <= 1)
+        .collect();
+    let events = scanned
+        .par_iter()
+        .map(|commit| {
+            let added = match commit.parents.first() {
+                Some(parent) => added_lines(&backend.diff(parent, &commit.hash, &options)?),
+                // Root commits only get the path rules
+                None => Vec::new(),
+            };
+            Ok(classify(commit, &added))
+        })
+        .collect::<Result<Vec<Vec<SecurityEvent>>, CdeError>>()?;
+
+    let mut security_events: Vec<SecurityEvent> = events.into_iter().flatten().collect();
+    // Stable: commits keep their newest-first order within a severity
+    security_events.sort_by_key(|event| severity_rank(&event.severity));
+    Ok(SecurityScan {
+        commits_scanned: scanned.len(),
+        security_events,
+    })
+}
+
+fn added_lines(files: &[super::diff::FileDiff]) -> Vec<(String, String)> {
+    files
+        .iter()
+        .flat_map(|file| {
+            file.hunks
+                .iter()
+                .flat_map(|hunk| &hunk.lines)
+                .filter(|line| line.kind == "added")
+                .map(|line| (file.path.clone(), line.content.clone()))
+        })
+        .collect()
+}
+
+/// Events of one commit, given the lines it added as (path, line)
+pub fn classify(commit: &RawCommit, added: &[(String, String)]) -> Vec<SecurityEvent> {
+    let event = |heuristic: &str,
+                 category: &str,
+                 severity: &str,
+                 explanation: String,
+                 evidence: Vec<String>| {
+        SecurityEvent {
+            commit_hash: commit.hash.clone(),
+            date: format_time(&commit.author_time),
+            author: commit.author.clone(),
+            summary: commit.summary.clone(),
+            heuristic: heuristic.to_string(),
+            category: category.to_string(),
+            severity: severity.to_string(),
+            explanation,
+            evidence: evidence.into_iter().take(MAX_EVIDENCE).collect(),
+        }
+    };
+    let mut events = Vec::new();
+
+    let path_rules = path_rules();
+    for (index, (heuristic, category, severity, explanation, _)) in PATH_RULES.iter().enumerate() {
+        let paths: Vec<String> = commit
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .filter(|path| path_rules.matches(&path.to_lowercase()).matched(index))
+            .collect();
+        if !paths.is_empty() {
+            events.push(event(
+                heuristic,
+                category,
+                severity,
+                explanation.to_string(),
+                paths,
+            ));
+        }
+    }
+
+    let test_files: Vec<_> = commit
+        .files
+        .iter()
+        .filter(|file| is_test_path(&file.path))
+        .collect();
+    let removed: usize = test_files.iter().map(|file| file.deletions).sum();
+    let kept: usize = test_files.iter().map(|file| file.insertions).sum();
+    if removed >= LARGE_TEST_DELETION && removed > kept * 2 {
+        events.push(event(
+            "test_deletion",
+            "tests",
+            "medium",
+            format!("Removes {} test lines and adds {}", removed, kept),
+            test_files
+                .iter()
+                .filter(|file| file.deletions > file.insertions)
+                .map(|file| file.path.clone())
+                .collect(),
+        ));
+    }
+
+    let checks = disabled_checks();
+    let mut explanations: Vec<&str> = Vec::new();
+    let mut evidence = Vec::new();
+    for (path, line) in added {
+        let matched = checks.matches(line);
+        if !matched.matched_any() {
+            continue;
+        }
+        for index in matched.iter() {
+            if !explanations.contains(&DISABLED_CHECKS[index].0) {
+                explanations.push(DISABLED_CHECKS[index].0);
+            }
+        }
+        let line: String = line.trim().chars().take(MAX_EVIDENCE_LENGTH).collect();
+        evidence.push(format!("{}: {}", path, line));
+    }
+    if !explanations.is_empty() {
+        events.push(event(
+            "disabled_security_check",
+            "disabled_check",
+            "high",
+            explanations.join("; "),
+            evidence,
+        ));
+    }
+    events
+}
+
+fn path_rules() -> &'static RegexSet {
+    static PATH_RULES_SET: OnceLock<RegexSet> = OnceLock::new();
+    PATH_RULES_SET.get_or_init(|| RegexSet::new(PATH_RULES.iter().map(|rule| rule.4)).unwrap())
+}
+
+fn disabled_checks() -> &'static RegexSet {
+    static DISABLED_CHECKS_SET: OnceLock<RegexSet> = OnceLock::new();
+    DISABLED_CHECKS_SET
+        .get_or_init(|| RegexSet::new(DISABLED_CHECKS.iter().map(|check| check.1)).unwrap())
+}
+
+fn is_test_path(path: &str) -> bool {
+    static TEST_PATH_RE: OnceLock<Regex> = OnceLock::new();
+    TEST_PATH_RE
+        .get_or_init(|| {
+            Regex::new(
+                r"(^|/)(tests?|__tests__|spec)/|(^|/)test_[^/]*$|_test\.\w+$|\.(test|spec)\.\w+$",
+            )
+            .unwrap()
+        })
+        .is_match(&path.to_lowercase())
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "high" => 0,
+        "medium" => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heuristics(events: &[SecurityEvent]) -> Vec<&str> {
+        events
+            .iter()
+            .map(|event| event.heuristic.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_sensitive_paths_are_flagged() {
+        let commit = RawCommit::fixture("abc123").files(&[
+            ("src/auth/middleware.py", 3, 1),
+            ("certs/server.pem", 1, 1),
+            (".github/workflows/release.yml", 2, 0),
+            ("rust_core/Cargo.lock", 10, 4),
+            ("src/author_list.py", 1, 0),
+            ("docs/readme.md", 1, 0),
+        ]);
+        let events = classify(&commit, &[]);
+        assert_eq!(
+            heuristics(&events),
+            vec![
+                "auth_path",
+                "crypto_path",
+                "ci_secrets_path",
+                "dependency_manifest"
+            ]
+        );
+        assert_eq!(events[0].evidence, vec!["src/auth/middleware.py"]);
+        assert_eq!(events[3].severity, "medium");
+    }
+
+    #[test]
+    fn test_suspicious_deltas_are_flagged() {
+        let commit = RawCommit::fixture("abc123")
+            .files(&[("tests/test_api.py", 5, 240), ("src/client.py", 2, 1)]);
+        let added = [
+            (
+                "src/client.py".to_string(),
+                "    requests.get(url, verify=False)".to_string(),
+            ),
+            (
+                "src/client.py".to_string(),
+                "    x = 1  # nosec".to_string(),
+            ),
+        ];
+        let events = classify(&commit, &added);
+        assert_eq!(
+            heuristics(&events),
+            vec!["test_deletion", "disabled_security_check"]
+        );
+        assert_eq!(events[0].explanation, "Removes 240 test lines and adds 5");
+        assert_eq!(
+            events[1].explanation,
+            "TLS certificate verification turned off; Security linter finding suppressed"
+        );
+        assert_eq!(
+            events[1].evidence[0],
+            "src/client.py: requests.get(url, verify=False)"
+        );
+
+        // Rewriting tests is not deleting them
+        let rewrite = RawCommit::fixture("abc123").files(&[("src/app.test.ts", 150, 200)]);
+        assert!(classify(&rewrite, &[]).is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+// rust_core/src/grep.rs
+//! Parallel regex search across a project, ripgrep style
+//!
+//! Walks the tree with the `ignore` crate's parallel walker so `.gitignore`,
+//! `.ignore` and hidden-file rules are honored, and returns structured matches
+//! instead of text so Python does not need an `rg` binary.
+
+use crate::error::CdeError;
+use crate::metrics;
+use crate::path_policy;
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Bytes inspected to decide whether a file is binary
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GrepOptions {
+    pub case_insensitive: bool,
+    /// Treat the pattern as a literal string instead of a regex
+    pub fixed_strings: bool,
+    /// Glob filters; prefix with `!` to exclude (e.g. `["*.py", "!tests/**"]`)
+    pub globs: Vec<String>,
+    pub include_hidden: bool,
+    pub max_matches: usize,
+    pub max_file_size_bytes: u64,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            fixed_strings: false,
+            globs: Vec::new(),
+            include_hidden: false,
+            max_matches: 10_000,
+            max_file_size_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrepMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+    /// Byte offsets of the match within the file
+    pub byte_start: usize,
+    pub byte_end: usize,
+    /// Byte offset of the match within its line
+    pub column: usize,
+    /// Positional capture groups (group 1 onwards); `None` when a group did not participate
+    pub captures: Vec<Option<String>>,
+    pub named_captures: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GrepResult {
+    pub pattern: String,
+    pub matches: Vec<GrepMatch>,
+    pub files_searched: usize,
+    pub files_with_matches: usize,
+    pub files_skipped: usize,
+    pub truncated: bool,
+    pub search_time_ms: u128,
+}
+
+/// Searches every non-ignored text file under `root_path` for `pattern` in parallel
+pub fn grep_project(
+    root_path: &str,
+    pattern: &str,
+    options: &GrepOptions,
+) -> Result<GrepResult, CdeError> {
+    metrics::timed("grep_project", || search_project(root_path, pattern, options))
+}
+
+fn search_project(
+    root_path: &str,
+    pattern: &str,
+    options: &GrepOptions,
+) -> Result<GrepResult, CdeError> {
+    let start = Instant::now();
+    let root = Path::new(root_path);
+    let guard = path_policy::walk_guard(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root_path));
+    }
+
+    let source = if options.fixed_strings {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    let re = RegexBuilder::new(&source)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .map_err(|e| CdeError::invalid_input("Invalid regex").caused_by(&e))?;
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.globs {
+        overrides
+            .add(glob)
+            .map_err(|e| CdeError::invalid_input(format!("Invalid glob '{}'", glob)).caused_by(&e))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| CdeError::invalid_input("Invalid glob set").caused_by(&e))?;
+
+    let walker = WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .overrides(overrides)
+        .max_filesize(Some(options.max_file_size_bytes))
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .threads(rayon::current_num_threads())
+        .build_parallel();
+
+    let matches = Mutex::new(Vec::new());
+    let files_searched = AtomicUsize::new(0);
+    let files_with_matches = AtomicUsize::new(0);
+    let files_skipped = AtomicUsize::new(0);
+    let bytes_searched = AtomicU64::new(0);
+    let match_count = AtomicUsize::new(0);
+    let truncated = AtomicBool::new(false);
+
+    walker.run(|| {
+        Box::new(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => return WalkState::Continue,
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return WalkState::Continue;
+            }
+
+            let content = match fs::read(entry.path()) {
+                Ok(bytes) if !bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) => {
+                    match String::from_utf8(bytes) {
+                        Ok(content) => content,
+                        Err(_) => {
+                            files_skipped.fetch_add(1, Ordering::Relaxed);
+                            return WalkState::Continue;
+                        }
+                    }
+                }
+                _ => {
+                    files_skipped.fetch_add(1, Ordering::Relaxed);
+                    return WalkState::Continue;
+                }
+            };
+            files_searched.fetch_add(1, Ordering::Relaxed);
+            bytes_searched.fetch_add(content.len() as u64, Ordering::Relaxed);
+
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            let file_matches = search_content(&re, &relative, &content);
+            if file_matches.is_empty() {
+                return WalkState::Continue;
+            }
+
+            files_with_matches.fetch_add(1, Ordering::Relaxed);
+            let previous = match_count.fetch_add(file_matches.len(), Ordering::Relaxed);
+            let remaining = options.max_matches.saturating_sub(previous);
+            let mut guard = matches.lock().unwrap();
+            if file_matches.len() > remaining {
+                truncated.store(true, Ordering::Relaxed);
+                guard.extend(file_matches.into_iter().take(remaining));
+                return WalkState::Quit;
+            }
+            guard.extend(file_matches);
+            WalkState::Continue
+        })
+    });
+
+    let mut matches = matches.into_inner().unwrap();
+    matches.sort_by(|a, b| a.path.cmp(&b.path).then(a.byte_start.cmp(&b.byte_start)));
+
+    metrics::add_bytes("grep_project", bytes_searched.into_inner());
+    Ok(GrepResult {
+        pattern: pattern.to_string(),
+        matches,
+        files_searched: files_searched.into_inner(),
+        files_with_matches: files_with_matches.into_inner(),
+        files_skipped: files_skipped.into_inner(),
+        truncated: truncated.into_inner(),
+        search_time_ms: start.elapsed().as_millis(),
+    })
+}
+
+/// Finds all matches of `re` in `content`, line by line
+fn search_content(re: &regex::Regex, path: &str, content: &str) -> Vec<GrepMatch> {
+    let mut results = Vec::new();
+    let mut line_start = 0;
+
+    for (index, raw_line) in content.split_inclusive('\n').enumerate() {
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+        for caps in re.captures_iter(line) {
+            let whole = caps.get(0).expect("group 0 always participates");
+            let named_captures = re
+                .capture_names()
+                .flatten()
+                .filter_map(|name| Some((name.to_string(), caps.name(name)?.as_str().to_string())))
+                .collect();
+
+            results.push(GrepMatch {
+                path: path.to_string(),
+                line_number: index + 1,
+                line: line.to_string(),
+                byte_start: line_start + whole.start(),
+                byte_end: line_start + whole.end(),
+                column: whole.start(),
+                captures: caps
+                    .iter()
+                    .skip(1)
+                    .map(|m| m.map(|m| m.as_str().to_string()))
+                    .collect(),
+                named_captures,
+            });
+        }
+        line_start += raw_line.len();
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_grep_project_honors_ignore_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        // The ignore crate only reads .gitignore inside git repositories
+        fs::create_dir(root.join(".git")).unwrap();
+        writeln!(File::create(root.join(".gitignore")).unwrap(), "build/").unwrap();
+        fs::create_dir(root.join("build")).unwrap();
+        writeln!(File::create(root.join("build/out.py")).unwrap(), "def skipped(): pass").unwrap();
+        write!(
+            File::create(root.join("app.py")).unwrap(),
+            "import os\r\ndef main(): pass\ndef helper(x): pass\n"
+        )
+        .unwrap();
+
+        let result = grep_project(
+            root.to_str().unwrap(),
+            r"def (?P<name>\w+)\((\w*)\)",
+            &GrepOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(result.matches.len(), 2);
+        let second = &result.matches[1];
+        assert_eq!(second.path, "app.py");
+        assert_eq!(second.line_number, 3);
+        assert_eq!(second.byte_start, 28);
+        assert_eq!(second.column, 0);
+        assert_eq!(second.captures, vec![Some("helper".to_string()), Some("x".to_string())]);
+        assert_eq!(second.named_captures["name"], "helper");
+        assert!(!result.truncated);
+    }
+}
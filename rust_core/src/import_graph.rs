@@ -0,0 +1,535 @@
+// rust_core/src/import_graph.rs
+//! Module-level dependency graph for Python and TypeScript/JavaScript
+//!
+//! Parses `import`/`from ... import`/`require` statements, resolves them to files
+//! inside the project and returns the internal dependency graph with import cycles
+//! highlighted. A dependency closure can be requested for a set of focus files so
+//! agent tasks can be scoped to a self-contained subgraph.
+
+use crate::error::CdeError;
+use crate::metrics;
+use crate::path_policy;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "mts", "cts", "js", "jsx", "mjs", "cjs"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModuleNode {
+    pub path: String,
+    pub language: String, // "python", "typescript", "javascript"
+    pub internal_imports: usize,
+    pub external_imports: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ImportEdge {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportGraph {
+    pub nodes: Vec<ModuleNode>,
+    pub edges: Vec<ImportEdge>,
+    /// Strongly connected components with more than one module (or a self-import)
+    pub cycles: Vec<Vec<String>>,
+    /// Third-party / stdlib modules by number of importing files
+    pub external_dependencies: HashMap<String, usize>,
+    /// Focus files plus everything they transitively import, when focus files were given
+    pub dependency_closure: Option<Vec<String>>,
+    pub analysis_time_ms: u128,
+}
+
+/// Raw import found in a source file, before resolution
+#[derive(Debug, Clone, PartialEq)]
+enum ImportSpec {
+    /// `import a.b` / `from a.b import c` (level = number of leading dots)
+    Python {
+        level: usize,
+        module: String,
+        names: Vec<String>,
+    },
+    /// `import x from './y'`, `require('z')`
+    Js(String),
+}
+
+/// Builds the internal import graph of `root_path`
+///
+/// # Arguments
+/// * `root_path` - Project root
+/// * `focus_files` - Root-relative paths whose dependency closure should be returned
+pub fn build_import_graph(root_path: &str, focus_files: &[String]) -> Result<ImportGraph, CdeError> {
+    metrics::timed("build_import_graph", || build_graph(root_path, focus_files))
+}
+
+fn build_graph(root_path: &str, focus_files: &[String]) -> Result<ImportGraph, CdeError> {
+    let start = Instant::now();
+    let root = Path::new(root_path);
+    let guard = path_policy::walk_guard(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root_path));
+    }
+
+    let files: Vec<String> = WalkBuilder::new(root)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter(|e| language_of(e.path()).is_some())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(root)
+                .ok()
+                .and_then(|p| p.to_str())
+                .map(|p| p.replace('\\', "/"))
+        })
+        .collect();
+
+    let file_set: HashSet<&str> = files.iter().map(String::as_str).collect();
+    let python_modules = python_module_index(root, &files);
+
+    let parsed: Vec<(String, &'static str, Vec<ImportSpec>)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let language = language_of(Path::new(path))?;
+            let content = fs::read_to_string(root.join(path)).ok()?;
+            let specs = if language == "python" {
+                parse_python_imports(&content)
+            } else {
+                parse_js_imports(&content)
+            };
+            Some((path.clone(), language, specs))
+        })
+        .collect();
+
+    let mut nodes = Vec::with_capacity(parsed.len());
+    let mut edges = BTreeSet::new();
+    let mut external_dependencies: HashMap<String, usize> = HashMap::new();
+
+    for (path, language, specs) in &parsed {
+        let mut internal = BTreeSet::new();
+        let mut external = BTreeSet::new();
+        for spec in specs {
+            let resolved = match spec {
+                ImportSpec::Python { .. } => resolve_python(path, spec, &python_modules),
+                ImportSpec::Js(specifier) => resolve_js(path, specifier, &file_set),
+            };
+            match resolved {
+                Ok(targets) => internal.extend(targets),
+                Err(Some(name)) => {
+                    external.insert(name);
+                }
+                Err(None) => {}
+            }
+        }
+
+        for name in &external {
+            *external_dependencies.entry(name.clone()).or_insert(0) += 1;
+        }
+        nodes.push(ModuleNode {
+            path: path.clone(),
+            language: language.to_string(),
+            internal_imports: internal.len(),
+            external_imports: external.len(),
+        });
+        edges.extend(internal.into_iter().map(|to| ImportEdge {
+            from: path.clone(),
+            to,
+        }));
+    }
+    nodes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let adjacency = adjacency_of(&edges);
+    let cycles = find_cycles(&nodes, &adjacency);
+    let dependency_closure = if focus_files.is_empty() {
+        None
+    } else {
+        Some(dependency_closure(focus_files, &adjacency))
+    };
+
+    Ok(ImportGraph {
+        nodes,
+        edges: edges.into_iter().collect(),
+        cycles,
+        external_dependencies,
+        dependency_closure,
+        analysis_time_ms: start.elapsed().as_millis(),
+    })
+}
+
+fn language_of(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "py" => Some("python"),
+        "ts" | "tsx" | "mts" | "cts" => Some("typescript"),
+        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
+        _ => None,
+    }
+}
+
+/// Maps dotted Python module names to files, for the project root and a `src/` layout
+fn python_module_index(root: &Path, files: &[String]) -> HashMap<String, String> {
+    let source_roots: Vec<&str> = if root.join("src").is_dir() {
+        vec!["", "src/"]
+    } else {
+        vec![""]
+    };
+
+    let mut index = HashMap::new();
+    for file in files.iter().filter(|f| f.ends_with(".py")) {
+        for source_root in &source_roots {
+            let Some(relative) = file.strip_prefix(source_root) else {
+                continue;
+            };
+            let module = relative
+                .trim_end_matches(".py")
+                .trim_end_matches("/__init__")
+                .replace('/', ".");
+            index.entry(module).or_insert_with(|| file.clone());
+        }
+    }
+    index
+}
+
+fn parse_python_imports(content: &str) -> Vec<ImportSpec> {
+    static IMPORT_RE: OnceLock<Regex> = OnceLock::new();
+    static FROM_RE: OnceLock<Regex> = OnceLock::new();
+    let import_re = IMPORT_RE.get_or_init(|| Regex::new(r"^\s*import\s+(.+)$").unwrap());
+    let from_re = FROM_RE
+        .get_or_init(|| Regex::new(r"^\s*from\s+(\.*)([\w.]*)\s+import\s+\(?([^)#]*)").unwrap());
+
+    let mut specs = Vec::new();
+    for line in content.lines() {
+        if let Some(cap) = from_re.captures(line) {
+            specs.push(ImportSpec::Python {
+                level: cap[1].len(),
+                module: cap[2].to_string(),
+                names: cap[3]
+                    .split(',')
+                    .filter_map(|n| n.split_whitespace().next())
+                    .map(str::to_string)
+                    .collect(),
+            });
+        } else if let Some(cap) = import_re.captures(line) {
+            let list = cap[1].split('#').next().unwrap_or("");
+            for module in list.split(',').filter_map(|m| m.split_whitespace().next()) {
+                specs.push(ImportSpec::Python {
+                    level: 0,
+                    module: module.to_string(),
+                    names: Vec::new(),
+                });
+            }
+        }
+    }
+    specs
+}
+
+fn parse_js_imports(content: &str) -> Vec<ImportSpec> {
+    static JS_RE: OnceLock<Regex> = OnceLock::new();
+    let re = JS_RE.get_or_init(|| {
+        Regex::new(
+            r#"(?:\bimport\s+(?:[\w*{}\s,$]+\s+from\s+)?|\bexport\s+[\w*{}\s,$]+\s+from\s+|\brequire\s*\(\s*|\bimport\s*\(\s*)["']([^"']+)["']"#,
+        )
+        .unwrap()
+    });
+
+    re.captures_iter(content)
+        .map(|cap| ImportSpec::Js(cap[1].to_string()))
+        .collect()
+}
+
+/// Resolves a Python import to internal files, or `Err(Some(top_level_package))` when external
+fn resolve_python(
+    importer: &str,
+    spec: &ImportSpec,
+    modules: &HashMap<String, String>,
+) -> Result<Vec<String>, Option<String>> {
+    let ImportSpec::Python {
+        level,
+        module,
+        names,
+    } = spec
+    else {
+        return Err(None);
+    };
+
+    let base = if *level > 0 {
+        // Root-relative dotted name ("src.app.b"); always present in the module index
+        let importer_module = importer.trim_end_matches(".py").replace('/', ".");
+        let mut package: Vec<&str> = importer_module.split('.').collect();
+        package.pop(); // module itself, or `__init__` for packages
+        for _ in 1..*level {
+            package.pop();
+        }
+        let mut parts: Vec<&str> = package.into_iter().filter(|p| !p.is_empty()).collect();
+        parts.extend(module.split('.').filter(|p| !p.is_empty()));
+        parts.join(".")
+    } else {
+        module.clone()
+    };
+
+    // `from pkg import submodule` imports files; `from pkg.mod import name` imports pkg/mod.py
+    let mut targets: Vec<String> = names
+        .iter()
+        .filter_map(|name| {
+            let candidate = if base.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", base, name)
+            };
+            modules.get(&candidate).cloned()
+        })
+        .collect();
+
+    if targets.is_empty() {
+        let mut candidate = base.as_str();
+        loop {
+            if let Some(file) = modules.get(candidate) {
+                targets.push(file.clone());
+                break;
+            }
+            match candidate.rsplit_once('.') {
+                Some((parent, _)) => candidate = parent,
+                None => break,
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        if *level > 0 {
+            Err(None)
+        } else {
+            Err(base.split('.').next().map(str::to_string))
+        }
+    } else {
+        targets.retain(|t| t != importer);
+        Ok(targets)
+    }
+}
+
+/// Resolves a relative JS/TS specifier to an internal file, or `Err(Some(package))` when external
+fn resolve_js(
+    importer: &str,
+    specifier: &str,
+    files: &HashSet<&str>,
+) -> Result<Vec<String>, Option<String>> {
+    if !specifier.starts_with('.') {
+        let package = if specifier.starts_with('@') {
+            specifier
+                .splitn(3, '/')
+                .take(2)
+                .collect::<Vec<_>>()
+                .join("/")
+        } else {
+            specifier.split('/').next().unwrap_or(specifier).to_string()
+        };
+        return Err(Some(package));
+    }
+
+    let base = normalize(
+        &Path::new(importer)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(specifier),
+    );
+    let stripped = base
+        .strip_suffix(".js")
+        .or_else(|| base.strip_suffix(".jsx"))
+        .unwrap_or(&base);
+
+    let mut candidates = vec![base.clone()];
+    for ext in JS_EXTENSIONS {
+        candidates.push(format!("{}.{}", stripped, ext));
+    }
+    for ext in JS_EXTENSIONS {
+        candidates.push(format!("{}/index.{}", base, ext));
+    }
+
+    candidates
+        .into_iter()
+        .find(|c| files.contains(c.as_str()))
+        .map(|c| vec![c])
+        .ok_or(None)
+}
+
+/// Lexically resolves `.` and `..` components into a forward-slash relative path
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+fn adjacency_of(edges: &BTreeSet<ImportEdge>) -> HashMap<&str, Vec<&str>> {
+    let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        adjacency.entry(&edge.from).or_default().push(&edge.to);
+    }
+    adjacency
+}
+
+/// Tarjan's strongly connected components; returns components that form import cycles
+fn find_cycles(nodes: &[ModuleNode], adjacency: &HashMap<&str, Vec<&str>>) -> Vec<Vec<String>> {
+    struct State<'a> {
+        index: usize,
+        indices: HashMap<&'a str, usize>,
+        lowlink: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        components: Vec<Vec<String>>,
+    }
+
+    fn visit<'a>(node: &'a str, adjacency: &HashMap<&'a str, Vec<&'a str>>, state: &mut State<'a>) {
+        state.indices.insert(node, state.index);
+        state.lowlink.insert(node, state.index);
+        state.index += 1;
+        state.stack.push(node);
+        state.on_stack.insert(node);
+
+        for &next in adjacency.get(node).into_iter().flatten() {
+            if !state.indices.contains_key(next) {
+                visit(next, adjacency, state);
+                let low = state.lowlink[node].min(state.lowlink[next]);
+                state.lowlink.insert(node, low);
+            } else if state.on_stack.contains(next) {
+                let low = state.lowlink[node].min(state.indices[next]);
+                state.lowlink.insert(node, low);
+            }
+        }
+
+        if state.lowlink[node] == state.indices[node] {
+            let mut component = Vec::new();
+            while let Some(member) = state.stack.pop() {
+                state.on_stack.remove(member);
+                component.push(member.to_string());
+                if member == node {
+                    break;
+                }
+            }
+            let self_loop = adjacency.get(node).is_some_and(|n| n.contains(&node));
+            if component.len() > 1 || self_loop {
+                component.sort();
+                state.components.push(component);
+            }
+        }
+    }
+
+    let mut state = State {
+        index: 0,
+        indices: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for node in nodes {
+        if !state.indices.contains_key(node.path.as_str()) {
+            visit(&node.path, adjacency, &mut state);
+        }
+    }
+
+    state.components.sort();
+    state.components
+}
+
+/// Focus files plus all files reachable from them through imports
+fn dependency_closure(focus_files: &[String], adjacency: &HashMap<&str, Vec<&str>>) -> Vec<String> {
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut queue: Vec<String> = focus_files
+        .iter()
+        .map(|f| normalize(&PathBuf::from(f)))
+        .collect();
+
+    while let Some(file) = queue.pop() {
+        if !seen.insert(file.clone()) {
+            continue;
+        }
+        for next in adjacency.get(file.as_str()).into_iter().flatten() {
+            if !seen.contains(*next) {
+                queue.push(next.to_string());
+            }
+        }
+    }
+
+    seen.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_python_imports() {
+        let specs = parse_python_imports(
+            "import os, sys  # stdlib\nfrom ..core import (models, views)\nfrom pkg.mod import thing\n",
+        );
+        assert_eq!(specs.len(), 4);
+        assert_eq!(
+            specs[2],
+            ImportSpec::Python {
+                level: 2,
+                module: "core".to_string(),
+                names: vec!["models".to_string(), "views".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_import_graph() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/app")).unwrap();
+        fs::create_dir_all(root.join("web/lib")).unwrap();
+
+        fs::write(root.join("src/app/__init__.py"), "").unwrap();
+        fs::write(
+            root.join("src/app/a.py"),
+            "import json\nfrom app import b\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/app/b.py"), "from .a import helper\n").unwrap();
+        fs::write(root.join("src/app/c.py"), "from app.b import thing\n").unwrap();
+        fs::write(
+            root.join("web/index.ts"),
+            "import React from 'react';\nimport { x } from './lib';\nconst y = require('./lib/util.js');\n",
+        )
+        .unwrap();
+        fs::write(root.join("web/lib/index.ts"), "export * from './util';\n").unwrap();
+        fs::write(root.join("web/lib/util.ts"), "export const x = 1;\n").unwrap();
+
+        let graph =
+            build_import_graph(root.to_str().unwrap(), &["src/app/c.py".to_string()]).unwrap();
+
+        assert_eq!(graph.nodes.len(), 7);
+        assert!(graph.edges.contains(&ImportEdge {
+            from: "web/index.ts".to_string(),
+            to: "web/lib/util.ts".to_string(),
+        }));
+        assert_eq!(
+            graph.cycles,
+            vec![vec!["src/app/a.py".to_string(), "src/app/b.py".to_string()]]
+        );
+        assert_eq!(graph.external_dependencies["react"], 1);
+        assert_eq!(graph.external_dependencies["json"], 1);
+        assert_eq!(
+            graph.dependency_closure.unwrap(),
+            vec!["src/app/a.py", "src/app/b.py", "src/app/c.py"]
+        );
+    }
+}
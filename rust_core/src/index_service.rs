@@ -0,0 +1,684 @@
+// src/index_service.rs
+//! Resident project index, kept warm by a background thread
+//!
+//! The first query on a root builds its index: the files a `git`-aware walk sees
+//! (with size and modification time), the Markdown documents parsed as the
+//! documentation scanner does, and the uncommitted state of the repository. A
+//! filesystem watcher then feeds changes to the thread, which applies them in
+//! batches (after `DEBOUNCE` of quiet, or `MAX_DELAY` at most), so later queries
+//! are answered from memory instead of walking the tree again.
+//!
+//! Single-file changes are checked against the `.gitignore` files seen by the
+//! walk; editing a `.gitignore`, or a watcher that lost events, rebuilds the index.
+
+use crate::documentation::{self, Document};
+use crate::error::{from_json, to_json, CdeError};
+use crate::events;
+use crate::filesystem::{self, FileMatch};
+use crate::fuzzy::{self, FuzzyMatch};
+use crate::git_analyzer::{self, WorkingTreeStatus};
+use crate::metrics;
+use crate::path_policy::{self, WalkGuard};
+use crate::snapshot::modified_ms;
+use ignore::gitignore::Gitignore;
+use ignore::WalkBuilder;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Quiet period after a change before the batch is applied
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Longest a change waits while others keep arriving
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest a query waits for the first build of the index
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Changed paths listed in a "watcher.changed" event
+const MAX_EVENT_PATHS: usize = 100;
+
+static SERVICES: LazyLock<Mutex<HashMap<PathBuf, Arc<IndexService>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+type WatchEvents = Receiver<notify::Result<notify::Event>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexQuery {
+    Status,
+    /// Files matching one of `patterns` (all without), as `find_files` matches them
+    Files {
+        #[serde(default)]
+        patterns: Vec<String>,
+        #[serde(default)]
+        case_insensitive: bool,
+        #[serde(default = "default_limit")]
+        limit: usize,
+    },
+    /// Files ranked by `fuzzy_find` scoring
+    Fuzzy {
+        query: String,
+        #[serde(default = "default_fuzzy_limit")]
+        limit: usize,
+    },
+    /// Markdown documents, those whose content contains `text` (ignoring case) when given
+    Docs {
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        include_content: bool,
+        #[serde(default = "default_limit")]
+        limit: usize,
+    },
+    /// Uncommitted state of the repository
+    Git,
+}
+
+fn default_limit() -> usize {
+    1000
+}
+
+fn default_fuzzy_limit() -> usize {
+    20
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexStatus {
+    pub root: String,
+    /// Whether the first build is done
+    pub ready: bool,
+    /// Whether changes are being watched; without, the index stays as built
+    pub watching: bool,
+    pub files: usize,
+    pub documents: usize,
+    pub is_git_repository: bool,
+    /// Batches of changes applied, rebuilds included
+    pub generation: u64,
+    /// RFC 3339 time of the last update
+    pub updated_at: Option<String>,
+    /// Why the watcher could not start, if it did not
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexAnswer {
+    Status(IndexStatus),
+    Files {
+        files: Vec<FileMatch>,
+        /// Matches before `limit`
+        total: usize,
+    },
+    Fuzzy {
+        matches: Vec<FuzzyMatch>,
+    },
+    Docs {
+        documents: Vec<Document>,
+        total: usize,
+    },
+    Git {
+        /// None outside a repository
+        status: Option<WorkingTreeStatus>,
+    },
+}
+
+#[derive(Default)]
+struct IndexState {
+    ready: bool,
+    watching: bool,
+    error: Option<String>,
+    files: BTreeMap<String, FileMatch>,
+    /// Keyed like `files`; document paths are absolute
+    docs: BTreeMap<String, Document>,
+    git: Option<WorkingTreeStatus>,
+    ignores: Vec<Gitignore>,
+    generation: u64,
+    updated_at: Option<String>,
+}
+
+pub struct IndexService {
+    root: PathBuf,
+    guard: WalkGuard,
+    state: Mutex<IndexState>,
+    built: Condvar,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+fn is_document(relative: &str) -> bool {
+    relative.ends_with(".md")
+}
+
+fn relative_of(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/")).filter(|r| !r.is_empty())
+}
+
+/// Files and `.gitignore` rules found walking `dir`, a directory of the root
+fn walk(root: &Path, dir: &Path, guard: &WalkGuard) -> (Vec<FileMatch>, Vec<Gitignore>) {
+    let guard = guard.clone();
+    let mut files = Vec::new();
+    let mut ignores = Vec::new();
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .build();
+    for entry in walker.flatten() {
+        if entry.file_type().is_some_and(|t| t.is_dir()) {
+            // Hidden, so not walked: the rules are read with their directory
+            let rules = entry.path().join(".gitignore");
+            if rules.is_file() {
+                ignores.push(Gitignore::new(rules).0);
+            }
+        }
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let (Some(relative), Ok(metadata)) = (relative_of(root, entry.path()), entry.metadata())
+        else {
+            continue;
+        };
+        files.push(FileMatch {
+            path: relative,
+            size: metadata.len(),
+            modified_ms: modified_ms(&metadata),
+        });
+    }
+    (files, ignores)
+}
+
+fn read_documents(root: &Path, files: &[FileMatch]) -> Vec<(String, Document)> {
+    files
+        .par_iter()
+        .filter(|file| is_document(&file.path))
+        .filter_map(|file| {
+            let path = root.join(&file.path);
+            let document = documentation::read_document(&path.to_string_lossy()).ok()?;
+            Some((file.path.clone(), document))
+        })
+        .collect()
+}
+
+fn working_tree(root: &Path) -> Option<WorkingTreeStatus> {
+    git_analyzer::analyze_working_tree(&root.to_string_lossy()).ok()
+}
+
+impl IndexService {
+    fn lock(&self) -> MutexGuard<'_, IndexState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn touch(state: &mut IndexState) {
+        state.generation += 1;
+        state.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    /// Builds the whole index again
+    fn rebuild(&self) {
+        let (files, ignores) = walk(&self.root, &self.root, &self.guard);
+        let docs = read_documents(&self.root, &files);
+        let git = working_tree(&self.root);
+        let mut state = self.lock();
+        state.files = files
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect();
+        state.docs = docs.into_iter().collect();
+        state.git = git;
+        state.ignores = ignores;
+        state.ready = true;
+        Self::touch(&mut state);
+        drop(state);
+        self.built.notify_all();
+    }
+
+    fn is_ignored(state: &IndexState, path: &Path, is_dir: bool) -> bool {
+        state.ignores.iter().any(|ignore| {
+            path.starts_with(ignore.path())
+                && ignore.matched_path_or_any_parents(path, is_dir).is_ignore()
+        })
+    }
+
+    /// Applies changes to `paths`; false when the index must be rebuilt instead
+    fn apply(&self, paths: &BTreeSet<PathBuf>) -> bool {
+        let mut git_changed = false;
+        let mut removed = Vec::new();
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        {
+            let state = self.lock();
+            for path in paths {
+                let Some(relative) = relative_of(&self.root, path) else {
+                    continue;
+                };
+                git_changed = true;
+                if relative == ".git" || relative.starts_with(".git/") {
+                    continue;
+                }
+                let name = path.file_name().unwrap_or_default();
+                if name == ".gitignore" || name == ".ignore" {
+                    return false;
+                }
+                let metadata = path.symlink_metadata();
+                let hidden = relative.split('/').any(|part| part.starts_with('.'));
+                let is_dir = metadata.as_ref().is_ok_and(|m| m.is_dir());
+                if hidden
+                    || !self
+                        .guard
+                        .allows(path, metadata.as_ref().is_ok_and(|m| m.is_symlink()))
+                    || Self::is_ignored(&state, path, is_dir)
+                {
+                    continue;
+                }
+                match metadata {
+                    Err(_) => removed.push(relative),
+                    Ok(_) if is_dir => dirs.push(path.clone()),
+                    Ok(metadata) if metadata.is_file() => files.push(FileMatch {
+                        path: relative,
+                        size: metadata.len(),
+                        modified_ms: modified_ms(&metadata),
+                    }),
+                    Ok(_) => {}
+                }
+            }
+        }
+
+        for dir in &dirs {
+            let (found, ignores) = walk(&self.root, dir, &self.guard);
+            if !ignores.is_empty() {
+                return false;
+            }
+            files.extend(found);
+        }
+        let docs = read_documents(&self.root, &files);
+        let git = if git_changed {
+            working_tree(&self.root)
+        } else {
+            None
+        };
+
+        let mut state = self.lock();
+        for relative in removed {
+            // A removed directory takes its files along
+            let prefix = format!("{}/", relative);
+            state.files.remove(&relative);
+            state.files.retain(|path, _| !path.starts_with(&prefix));
+            state.docs.remove(&relative);
+            state.docs.retain(|path, _| !path.starts_with(&prefix));
+        }
+        for file in &files {
+            if is_document(&file.path) {
+                state.docs.remove(&file.path);
+            }
+        }
+        state.docs.extend(docs);
+        state
+            .files
+            .extend(files.into_iter().map(|file| (file.path.clone(), file)));
+        if git_changed {
+            state.git = git;
+        }
+        Self::touch(&mut state);
+        true
+    }
+
+    /// Builds the index, then applies the watched changes until the watcher stops
+    fn run(&self, events: WatchEvents) {
+        self.rebuild();
+        self.publish("watcher.rebuilt", &BTreeSet::new());
+        let mut pending = BTreeSet::new();
+        let mut rescan = false;
+        let mut since: Option<Instant> = None;
+        loop {
+            let flush = match events.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => {
+                    rescan |= event.need_rescan();
+                    pending.extend(event.paths);
+                    since.get_or_insert_with(Instant::now).elapsed() >= MAX_DELAY
+                }
+                // Events were lost
+                Ok(Err(_)) => {
+                    rescan = true;
+                    since.get_or_insert_with(Instant::now).elapsed() >= MAX_DELAY
+                }
+                Err(RecvTimeoutError::Timeout) => true,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if !flush || (pending.is_empty() && !rescan) {
+                continue;
+            }
+            if rescan || !self.apply(&pending) {
+                self.rebuild();
+                self.publish("watcher.rebuilt", &pending);
+            } else {
+                self.publish("watcher.changed", &pending);
+            }
+            pending.clear();
+            rescan = false;
+            since = None;
+        }
+    }
+
+    /// Tells the event bus which paths of the root a batch of changes touched
+    fn publish(&self, kind: &str, paths: &BTreeSet<PathBuf>) {
+        if !events::has_subscribers() {
+            return;
+        }
+        let relative: Vec<String> = paths
+            .iter()
+            .filter_map(|path| relative_of(&self.root, path))
+            .take(MAX_EVENT_PATHS)
+            .collect();
+        events::publish(
+            "watcher",
+            kind,
+            serde_json::json!({
+                "root": self.root.to_string_lossy(),
+                "changed": paths.len(),
+                "paths": relative,
+            }),
+        );
+    }
+
+    /// Waits for the first build, at most `READY_TIMEOUT`
+    fn ready(&self) -> Result<MutexGuard<'_, IndexState>, CdeError> {
+        let state = self.lock();
+        let (state, waited) = self
+            .built
+            .wait_timeout_while(state, READY_TIMEOUT, |state| !state.ready)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if waited.timed_out() && !state.ready {
+            return Err(
+                CdeError::timeout("The project index is still being built").with_path(&self.root)
+            );
+        }
+        Ok(state)
+    }
+
+    fn status_of(&self, state: &IndexState) -> IndexStatus {
+        IndexStatus {
+            root: self.root.to_string_lossy().into_owned(),
+            ready: state.ready,
+            watching: state.watching,
+            files: state.files.len(),
+            documents: state.docs.len(),
+            is_git_repository: state.git.is_some(),
+            generation: state.generation,
+            updated_at: state.updated_at.clone(),
+            error: state.error.clone(),
+        }
+    }
+
+    /// Status of the index, without waiting for it
+    pub fn status(&self) -> IndexStatus {
+        self.status_of(&self.lock())
+    }
+
+    /// Answers `query` from the index, once it is built
+    pub fn query(&self, query: &IndexQuery) -> Result<IndexAnswer, CdeError> {
+        let state = self.ready()?;
+        Ok(match query {
+            IndexQuery::Status => IndexAnswer::Status(self.status_of(&state)),
+            IndexQuery::Files {
+                patterns,
+                case_insensitive,
+                limit,
+            } => {
+                let (paths, names) = filesystem::compile_patterns(patterns, *case_insensitive)?;
+                let matched: Vec<&FileMatch> = state
+                    .files
+                    .values()
+                    .filter(|file| {
+                        let name = file.path.rsplit('/').next().unwrap_or(&file.path);
+                        patterns.is_empty() || paths.is_match(&file.path) || names.is_match(name)
+                    })
+                    .collect();
+                IndexAnswer::Files {
+                    total: matched.len(),
+                    files: matched.into_iter().take(*limit).cloned().collect(),
+                }
+            }
+            IndexQuery::Fuzzy { query, limit } => {
+                let paths: Vec<String> = state.files.keys().cloned().collect();
+                drop(state);
+                IndexAnswer::Fuzzy {
+                    matches: fuzzy::fuzzy_match(&paths, query, *limit),
+                }
+            }
+            IndexQuery::Docs {
+                text,
+                include_content,
+                limit,
+            } => {
+                let text = text.as_ref().map(|text| text.to_lowercase());
+                let matched: Vec<&Document> = state
+                    .docs
+                    .values()
+                    .filter(|doc| {
+                        text.as_ref()
+                            .is_none_or(|text| doc.content.to_lowercase().contains(text))
+                    })
+                    .collect();
+                IndexAnswer::Docs {
+                    total: matched.len(),
+                    documents: matched
+                        .into_iter()
+                        .take(*limit)
+                        .map(|doc| Document {
+                            content: if *include_content {
+                                doc.content.clone()
+                            } else {
+                                String::new()
+                            },
+                            ..doc.clone()
+                        })
+                        .collect(),
+                }
+            }
+            IndexQuery::Git => IndexAnswer::Git {
+                status: state.git.clone(),
+            },
+        })
+    }
+
+    fn stop(&self) {
+        // Dropping the watcher disconnects the worker, which then returns
+        self.watcher.lock().unwrap().take();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The index service of `root`, started (in the background) when there is none
+pub fn start(root: &str) -> Result<Arc<IndexService>, CdeError> {
+    let path = Path::new(root);
+    let guard = path_policy::walk_guard(path)?;
+    if !path.is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    let root = path_policy::resolve(path);
+    let mut services = SERVICES.lock().unwrap();
+    let running = services.get(&root).cloned();
+    metrics::cache_lookup("project_index", running.is_some());
+    if let Some(service) = running {
+        return Ok(service);
+    }
+
+    let service = Arc::new(IndexService {
+        root: root.clone(),
+        guard,
+        state: Mutex::new(IndexState::default()),
+        built: Condvar::new(),
+        watcher: Mutex::new(None),
+        worker: Mutex::new(None),
+    });
+    let (sender, events) = mpsc::channel();
+    match notify::recommended_watcher(sender).and_then(|mut watcher| {
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map(|_| watcher)
+    }) {
+        Ok(watcher) => {
+            *service.watcher.lock().unwrap() = Some(watcher);
+            service.lock().watching = true;
+        }
+        Err(e) => {
+            tracing::warn!(root = %root.display(), error = %e, "Project index is not watching changes");
+            service.lock().error = Some(e.to_string());
+        }
+    }
+    let worker = service.clone();
+    let handle = std::thread::Builder::new()
+        .name("cde-index".to_string())
+        .spawn(move || worker.run(events))
+        .map_err(|e| CdeError::io("Failed to start the index thread").caused_by(&e))?;
+    *service.worker.lock().unwrap() = Some(handle);
+    services.insert(root, service.clone());
+    Ok(service)
+}
+
+/// Stops the index service of `root`; returns whether one was running
+pub fn stop(root: &str) -> bool {
+    let root = path_policy::resolve(Path::new(root));
+    let service = SERVICES.lock().unwrap().remove(&root);
+    match service {
+        Some(service) => {
+            service.stop();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Answers `query` from the index of `root`, starting its service if needed
+pub fn index_query(root: &str, query: &IndexQuery) -> Result<IndexAnswer, CdeError> {
+    metrics::timed("index_query", || start(root)?.query(query))
+}
+
+/// Starts the resident index of a project in the background, as JSON status
+///
+/// The index (files, Markdown documents, git working tree) is built by a
+/// background thread and kept up to date from filesystem events until
+/// `stop_index_service_py`. Starting an index already running returns its status.
+#[pyfunction]
+fn start_index_service_py(py: Python<'_>, root: &str) -> PyResult<String> {
+    let status = py.detach(|| start(root).map(|service| service.status()))?;
+    Ok(to_json(&status)?)
+}
+
+/// Stops the resident index of a project; returns whether one was running
+#[pyfunction]
+fn stop_index_service_py(py: Python<'_>, root: &str) -> bool {
+    py.detach(|| stop(root))
+}
+
+/// Answers a query from the resident index of a project, as JSON
+///
+/// `query_json` is one of `{"kind": "status"}`, `{"kind": "files", "patterns",
+/// "case_insensitive", "limit"=1000}`, `{"kind": "fuzzy", "query", "limit"=20}`,
+/// `{"kind": "docs", "text", "include_content"=false, "limit"=1000}` and
+/// `{"kind": "git"}`. The index is started on first use; that query waits for
+/// the first build, later ones are answered from memory. The answer carries the
+/// same `kind`.
+#[pyfunction]
+fn index_query_py(py: Python<'_>, root: &str, query_json: &str) -> PyResult<String> {
+    let query: IndexQuery = from_json("query", query_json)?;
+    let answer = py.detach(|| index_query(root, &query))?;
+    Ok(to_json(&answer)?)
+}
+
+/// Adds the resident index functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(start_index_service_py, m)?)?;
+    m.add_function(wrap_pyfunction!(stop_index_service_py, m)?)?;
+    m.add_function(wrap_pyfunction!(index_query_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Runs `query` until `done` accepts the answer, for up to 10 seconds
+    fn eventually(root: &str, query: &IndexQuery, done: impl Fn(&IndexAnswer) -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if done(&index_query(root, query).unwrap()) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_index_follows_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("build/out.md"), "# Ignored").unwrap();
+        fs::write(root.join("README.md"), "---\ntitle: Readme\n---\n# Hello").unwrap();
+        let root_str = root.to_str().unwrap();
+
+        let Ok(IndexAnswer::Status(status)) = index_query(root_str, &IndexQuery::Status) else {
+            panic!("expected a status");
+        };
+        assert!(status.ready && !status.is_git_repository);
+        assert_eq!((status.files, status.documents), (2, 1));
+        let files = IndexQuery::Files {
+            patterns: vec!["*.rs".to_string()],
+            case_insensitive: false,
+            limit: 10,
+        };
+        let Ok(IndexAnswer::Files { files: found, .. }) = index_query(root_str, &files) else {
+            panic!("expected files");
+        };
+        assert_eq!(found[0].path, "src/main.rs");
+
+        let docs = IndexQuery::Docs {
+            text: Some("guide".to_string()),
+            include_content: false,
+            limit: 10,
+        };
+        let has_guide = |answer: &IndexAnswer| {
+            matches!(answer, IndexAnswer::Docs { documents, .. }
+                if documents.len() == 1 && documents[0].headers == vec!["Guide"])
+        };
+        if status.watching {
+            fs::create_dir_all(root.join("docs")).unwrap();
+            fs::write(root.join("docs/guide.md"), "# Guide").unwrap();
+            fs::write(root.join("build/new.md"), "# Guide too").unwrap();
+            assert!(eventually(root_str, &docs, has_guide));
+            fs::remove_dir_all(root.join("docs")).unwrap();
+            assert!(eventually(root_str, &docs, |answer| {
+                matches!(answer, IndexAnswer::Docs { total: 0, .. })
+            }));
+        }
+        let fuzzy = IndexQuery::Fuzzy {
+            query: "mainrs".to_string(),
+            limit: 5,
+        };
+        let Ok(IndexAnswer::Fuzzy { matches }) = index_query(root_str, &fuzzy) else {
+            panic!("expected matches");
+        };
+        assert_eq!(matches[0].path, "src/main.rs");
+        assert!(stop(root_str));
+        assert!(!stop(root_str));
+    }
+}
@@ -1,14 +1,80 @@
 // src/lib.rs
 use pyo3::prelude::*;
+use cancel::CancellationToken;
+use error::{from_json, to_json, to_py};
+use paging::capped;
+use progress::ProgressSink;
 use rayon::ThreadPoolBuilder;
 use std::sync::Once;
 
+#[cfg(feature = "async")]
+mod async_bindings;
+mod agent_registry;
+pub mod api;
+mod api_surface;
+mod audit;
+mod archive;
+mod artifacts;
+mod cancel;
+mod checkpoint;
+mod chunking;
+mod command_inference;
+mod config_validator;
+mod conventions;
+mod copy_tree;
+#[cfg(test)]
+mod corpus;
+mod cron;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod digest;
+mod doc_index;
+mod doc_manifest;
 mod filesystem;
+mod frontmatter;
+mod fuzzy;
 mod documentation;
+mod embeddings;
+mod env_drift;
+mod error;
+mod events;
 mod git_analyzer;
+mod grep;
+mod import_graph;
+mod index_service;
+#[cfg(feature = "log_stream")]
+mod log_stream;
+mod logging;
+mod markdown_format;
+mod metadata;
+mod metrics;
+mod openapi_validator;
+mod paging;
+mod patch;
+mod path_policy;
+mod progress;
+mod rate_limit;
 mod workflow_validator;
+mod project_lock;
 mod project_scanner;
 mod process_manager;
+mod rename;
+mod repo_health;
+mod report;
+mod snapshot;
+mod state;
+mod summarize;
+mod system_info;
+mod task_engine;
+mod templating;
+mod test_detection;
+mod test_reports;
+mod text;
+mod text_file;
+mod tokens;
+mod tool_probe;
+mod trash;
+mod trend;
 
 static INIT: Once = Once::new();
 
@@ -23,112 +89,674 @@ fn init_rayon() {
             .thread_name(|i| format!("cde-rayon-{}", i))
             .panic_handler(|_| {
                 // Prevenir panic unwinding en threads paralelos
-                eprintln!("Rayon thread panicked, but continuing execution");
+                tracing::error!("Rayon thread panicked, but continuing execution");
             })
             .build_global()
             .expect("Failed to initialize Rayon thread pool");
 
-        eprintln!("✅ Rayon initialized with {} threads", num_threads);
+        tracing::info!(threads = num_threads, "Rayon initialized");
     });
 }
 
 /// Scans a documentation project, finds all Markdown files, and returns their content.
 /// Extracts YAML frontmatter, links, headers, and word count in parallel.
+/// Documents are sorted by path; `offset`/`limit` page through them and
+/// `include_content=False` leaves their `content` empty. Cancelling `cancel_token`
+/// stops reading files; the documents read so far are returned. `progress_callback`
+/// receives JSON progress snapshots, kept for `poll_progress_py(op_id)` with an `op_id`.
 #[pyfunction]
-fn scan_documentation_py(root_path: String) -> PyResult<String> {
-    match documentation::scan_documentation(&root_path) {
-        Ok(documents) => {
-            let json_result = serde_json::to_string(&documents).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
-        }
-        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e)),
-    }
+#[pyo3(signature = (root_path, offset=0, limit=None, include_content=true, cancel_token=None, progress_callback=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+fn scan_documentation_py(
+    py: Python<'_>,
+    root_path: String,
+    offset: usize,
+    limit: Option<usize>,
+    include_content: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<String> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let documents = py.detach(|| {
+        documentation::scan_documentation_page(&root_path, offset, limit, include_content, &cancel, &progress)
+    })?;
+    Ok(to_json(&documents)?)
+}
+
+/// Same as `scan_documentation_py`, returning a list of dicts instead of JSON.
+#[pyfunction]
+#[pyo3(signature = (root_path, offset=0, limit=None, include_content=true, cancel_token=None, progress_callback=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+fn scan_documentation_dict(
+    py: Python<'_>,
+    root_path: String,
+    offset: usize,
+    limit: Option<usize>,
+    include_content: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let documents = py.detach(|| {
+        documentation::scan_documentation_page(&root_path, offset, limit, include_content, &cancel, &progress)
+    })?;
+    Ok(to_py(py, &documents)?)
 }
 
 /// Analyzes documentation quality in parallel.
 /// Returns quality score, broken links, missing metadata, and recommendations.
+/// Each list holds at most `max_list_items` entries (all with `None`); `truncated`
+/// tells whether any was cut. Cancelling `cancel_token` stops the scan; `cancelled`
+/// is then set and the report covers the documents read. Progress is reported as
+/// for `scan_documentation_py`. With `record_trend` the score of a complete run is
+/// recorded for `get_quality_trend_py`; otherwise nothing is written.
 #[pyfunction]
-fn analyze_documentation_quality_py(root_path: String) -> PyResult<String> {
-    match documentation::analyze_documentation_quality(&root_path) {
-        Ok(report) => {
-            let json_result = serde_json::to_string(&report).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
-        }
-        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e)),
+#[pyo3(signature = (root_path, max_list_items=Some(20), cancel_token=None, progress_callback=None, op_id=None, record_trend=false))]
+fn analyze_documentation_quality_py(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+    record_trend: bool,
+) -> PyResult<String> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let report = py.detach(|| {
+        documentation::analyze_documentation_quality(&root_path, &cancel, &progress)
+    })?;
+    if record_trend {
+        trend::record_quality_run(&root_path, &report);
     }
+    Ok(to_json(&capped(report, max_list_items))?)
+}
+
+/// Same as `analyze_documentation_quality_py`, returning a dict instead of JSON.
+#[pyfunction]
+#[pyo3(signature = (root_path, max_list_items=Some(20), cancel_token=None, progress_callback=None, op_id=None, record_trend=false))]
+fn analyze_documentation_quality_dict(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+    record_trend: bool,
+) -> PyResult<Bound<'_, PyAny>> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let report = py.detach(|| {
+        documentation::analyze_documentation_quality(&root_path, &cancel, &progress)
+    })?;
+    if record_trend {
+        trend::record_quality_run(&root_path, &report);
+    }
+    Ok(to_py(py, &capped(report, max_list_items))?)
 }
 
 /// Validates workflow YAML files in parallel.
 /// Returns validation report with issues, missing templates, and summary.
+/// `max_list_items` caps each list and sets `truncated` when one was cut.
 #[pyfunction]
-fn validate_workflows_py(root_path: String) -> PyResult<String> {
-    match workflow_validator::validate_workflows(&root_path) {
-        Ok(report) => {
-            let json_result = serde_json::to_string(&report).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
-        }
-        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e)),
-    }
+#[pyo3(signature = (root_path, max_list_items=None))]
+fn validate_workflows_py(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let report = py.detach(|| workflow_validator::validate_workflows(&root_path))?;
+    Ok(to_json(&capped(report, max_list_items))?)
+}
+
+/// Same as `validate_workflows_py`, returning a dict instead of JSON.
+#[pyfunction]
+#[pyo3(signature = (root_path, max_list_items=None))]
+fn validate_workflows_dict(
+    py: Python<'_>,
+    root_path: String,
+    max_list_items: Option<usize>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let report = py.detach(|| workflow_validator::validate_workflows(&root_path))?;
+    Ok(to_py(py, &capped(report, max_list_items))?)
 }
 
 /// Scans a project directory in parallel, analyzing file types and structure.
 /// Excludes common dependency directories and build artifacts.
-/// Returns file count, language statistics, and dependency files found.
+/// Returns file count, language statistics, dependency files found, and test-to-code ratios.
+/// Symlinks are skipped unless `follow_symlinks` is set; hard-linked files can be counted once.
+/// Health findings flag files above `large_file_threshold_bytes`, paths longer than
+/// `max_path_length`, non-UTF8 names and case-colliding names.
+/// `api_endpoints` lists the HTTP endpoints found, as `extract_api_endpoints_py` does.
+/// Pass a `ScanHandle` to cancel from another thread, and `progress_callback` to receive
+/// JSON progress snapshots (phase, files processed, percent, elapsed, ETA), also kept for
+/// `poll_progress_py(op_id)` when `op_id` is given. The GIL is released while scanning.
+/// A `cancel_token` cancels the scan in place of the handle's own token.
+/// `max_list_items` caps each list and sets `truncated` when one was cut.
 #[pyfunction]
+#[pyo3(signature = (root_path, excluded_dirs, excluded_patterns, follow_symlinks=false, detect_cycles=true, dedupe_hardlinks=false, large_file_threshold_bytes=5242880, max_path_length=260, handle=None, progress_callback=None, max_list_items=None, cancel_token=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
 fn scan_project_py(
+    py: Python<'_>,
     root_path: String,
     excluded_dirs: Vec<String>,
     excluded_patterns: Vec<String>,
+    follow_symlinks: bool,
+    detect_cycles: bool,
+    dedupe_hardlinks: bool,
+    large_file_threshold_bytes: u64,
+    max_path_length: usize,
+    handle: Option<project_scanner::ScanHandle>,
+    progress_callback: Option<Py<PyAny>>,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    op_id: Option<String>,
 ) -> PyResult<String> {
-    match project_scanner::scan_project(&root_path, excluded_dirs, excluded_patterns) {
-        Ok(result) => {
-            let json_result = serde_json::to_string(&result).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
-        }
-        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e)),
-    }
+    let options = project_scanner::ScanOptions {
+        follow_symlinks,
+        detect_cycles,
+        dedupe_hardlinks,
+        large_file_threshold_bytes,
+        max_path_length,
+    };
+    let result = py.detach(|| {
+        scan_project(
+            &root_path,
+            excluded_dirs,
+            excluded_patterns,
+            &options,
+            handle,
+            progress_callback,
+            cancel_token,
+            op_id,
+        )
+    })?;
+    Ok(to_json(&capped(result, max_list_items))?)
+}
+
+/// Same as `scan_project_py`, returning a dict instead of JSON.
+#[pyfunction]
+#[pyo3(signature = (root_path, excluded_dirs, excluded_patterns, follow_symlinks=false, detect_cycles=true, dedupe_hardlinks=false, large_file_threshold_bytes=5242880, max_path_length=260, handle=None, progress_callback=None, max_list_items=None, cancel_token=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+fn scan_project_dict(
+    py: Python<'_>,
+    root_path: String,
+    excluded_dirs: Vec<String>,
+    excluded_patterns: Vec<String>,
+    follow_symlinks: bool,
+    detect_cycles: bool,
+    dedupe_hardlinks: bool,
+    large_file_threshold_bytes: u64,
+    max_path_length: usize,
+    handle: Option<project_scanner::ScanHandle>,
+    progress_callback: Option<Py<PyAny>>,
+    max_list_items: Option<usize>,
+    cancel_token: Option<CancellationToken>,
+    op_id: Option<String>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let options = project_scanner::ScanOptions {
+        follow_symlinks,
+        detect_cycles,
+        dedupe_hardlinks,
+        large_file_threshold_bytes,
+        max_path_length,
+    };
+    let result = py.detach(|| {
+        scan_project(
+            &root_path,
+            excluded_dirs,
+            excluded_patterns,
+            &options,
+            handle,
+            progress_callback,
+            cancel_token,
+            op_id,
+        )
+    })?;
+    Ok(to_py(py, &capped(result, max_list_items))?)
+}
+
+/// Runs a project scan without the GIL, reporting progress to `progress_callback`
+/// and `op_id`; `cancel_token` replaces the handle's token when given
+#[allow(clippy::too_many_arguments)]
+fn scan_project(
+    root_path: &str,
+    excluded_dirs: Vec<String>,
+    excluded_patterns: Vec<String>,
+    options: &project_scanner::ScanOptions,
+    handle: Option<project_scanner::ScanHandle>,
+    progress_callback: Option<Py<PyAny>>,
+    cancel_token: Option<CancellationToken>,
+    op_id: Option<String>,
+) -> Result<project_scanner::ProjectAnalysisResult, error::CdeError> {
+    let handle = handle.unwrap_or_default();
+    let handle = match cancel_token {
+        Some(token) => handle.with_token(token),
+        None => handle,
+    };
+    let progress = ProgressSink::python(op_id, progress_callback);
+
+    project_scanner::scan_project(
+        root_path,
+        excluded_dirs,
+        excluded_patterns,
+        options,
+        &handle,
+        &progress,
+    )
 }
 
 /// Analyzes Git repository with parallel processing.
 /// Returns comprehensive Git insights including commits, branches, contributors, and code churn.
+/// With `use_cache`, a result computed for the same HEAD commit and `days` is reused.
+/// Cancelling `cancel_token` stops the history walk; `cancelled` is then set and the
+/// commit-based sections cover the commits walked. `progress_callback` and `op_id`
+/// receive progress over the analysis steps, as for `scan_documentation_py`.
 #[pyfunction]
-fn analyze_git_repository_py(repo_path: String, days: i64) -> PyResult<String> {
-    match git_analyzer::analyze_git_repository(&repo_path, days) {
-        Ok(analysis) => {
-            let json_result = serde_json::to_string(&analysis).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Failed to serialize result: {}", e))
-            })?;
-            Ok(json_result)
+#[pyo3(signature = (repo_path, days, use_cache=true, cancel_token=None, progress_callback=None, op_id=None))]
+fn analyze_git_repository_py(
+    py: Python<'_>,
+    repo_path: String,
+    days: i64,
+    use_cache: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<String> {
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let analysis = py.detach(|| {
+        if use_cache {
+            git_analyzer::analyze_git_repository_cached(&repo_path, days, &cancel, &progress)
+        } else {
+            git_analyzer::analyze_git_repository(&repo_path, days, &cancel, &progress)
         }
-        Err(e) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e)),
-    }
+    })?;
+    Ok(to_json(&analysis)?)
+}
+
+/// Analyzes several repositories in parallel. `paths_json` is a JSON array of
+/// repository paths. Returns one report per path (its analysis, or the error that
+/// stopped it) and an aggregate: contributors merged across repositories by email
+/// and the most changed files of all of them. Cancelling `cancel_token` reports the
+/// repositories not started as cancelled and sets `cancelled`. Progress counts the
+/// repositories analyzed.
+#[pyfunction]
+#[pyo3(signature = (paths_json, days, use_cache=true, cancel_token=None, progress_callback=None, op_id=None))]
+fn analyze_repositories_py(
+    py: Python<'_>,
+    paths_json: String,
+    days: i64,
+    use_cache: bool,
+    cancel_token: Option<CancellationToken>,
+    progress_callback: Option<Py<PyAny>>,
+    op_id: Option<String>,
+) -> PyResult<String> {
+    let paths: Vec<String> = from_json("paths", &paths_json)?;
+    let cancel = cancel_token.unwrap_or_default();
+    let progress = ProgressSink::python(op_id, progress_callback);
+    let portfolio = py.detach(|| {
+        git_analyzer::analyze_repositories(&paths, days, use_cache, &cancel, &progress)
+    });
+    Ok(to_json(&portfolio)?)
+}
+
+/// Drops the cached `analyze_git_repository_py` results of a repository, e.g. after
+/// fetching branches or tags without moving HEAD. Returns the number of entries removed.
+#[pyfunction]
+fn invalidate_git_analysis_cache_py(py: Python<'_>, repo_path: String) -> PyResult<usize> {
+    Ok(py.detach(|| git_analyzer::invalidate_analysis_cache(&repo_path))?)
+}
+
+/// Contributors of the last `days` with commit counts, line stats and impact score,
+/// highest impact first.
+#[pyfunction]
+fn get_git_contributors_py(py: Python<'_>, repo_path: String, days: i64) -> PyResult<String> {
+    let contributors = py.detach(|| git_analyzer::analyze_contributors(&repo_path, days))?;
+    Ok(to_json(&contributors)?)
+}
+
+/// Bus factor of the repository and of each directory over the last `days`: the
+/// fewest contributors accounting for 80% of the changed lines. Often-changed
+/// directories with a single owner are listed in `single_owner_paths`.
+#[pyfunction]
+fn get_git_bus_factor_py(py: Python<'_>, repo_path: String, days: i64) -> PyResult<String> {
+    let distribution = py.detach(|| git_analyzer::analyze_knowledge_distribution(&repo_path, days))?;
+    Ok(to_json(&distribution)?)
+}
+
+/// Code churn of the last `days`: the 20 most changed files, hotspots, risk
+/// hotspots ranked by churn and complexity, and the number of files changed.
+#[pyfunction]
+fn get_git_code_churn_py(py: Python<'_>, repo_path: String, days: i64) -> PyResult<String> {
+    let churn = py.detach(|| git_analyzer::analyze_code_churn(&repo_path, days))?;
+    Ok(to_json(&churn)?)
+}
+
+/// Active and stale branches with commits ahead/behind the default branch, merge
+/// state and the files that would conflict when merging them into it.
+#[pyfunction]
+fn get_git_branches_py(py: Python<'_>, repo_path: String) -> PyResult<String> {
+    let branches = py.detach(|| git_analyzer::analyze_branches(&repo_path))?;
+    Ok(to_json(&branches)?)
+}
+
+/// Simulates merging `branch` into `target` (the default branch when omitted) in
+/// memory and reports divergence and the files that would conflict.
+#[pyfunction]
+#[pyo3(signature = (repo_path, branch, target=None))]
+fn predict_merge_conflicts_py(
+    py: Python<'_>,
+    repo_path: String,
+    branch: String,
+    target: Option<String>,
+) -> PyResult<String> {
+    let prediction = py.detach(|| git_analyzer::predict_merge_conflicts(&repo_path, &branch, target.as_deref()))?;
+    Ok(to_json(&prediction)?)
+}
+
+/// Uncommitted state of a repository: staged, unstaged, untracked and conflicted
+/// files with per-file line stats and the conflict markers left in each file.
+#[pyfunction]
+fn analyze_working_tree_py(py: Python<'_>, repo_path: String) -> PyResult<String> {
+    let status = py.detach(|| git_analyzer::analyze_working_tree(&repo_path))?;
+    Ok(to_json(&status)?)
+}
+
+/// Changelog from `from_tag` to `to_tag` (any revision works): commits grouped by
+/// Conventional Commit type, breaking changes and linked PR/issue references, as
+/// JSON with the rendered Markdown in its `markdown` field.
+#[pyfunction]
+#[pyo3(signature = (repo_path, from_tag, to_tag="HEAD".to_string()))]
+fn generate_changelog_py(py: Python<'_>, repo_path: String, from_tag: String, to_tag: String) -> PyResult<String> {
+    let changelog = py.detach(|| git_analyzer::generate_changelog(&repo_path, &from_tag, &to_tag))?;
+    Ok(to_json(&changelog)?)
+}
+
+/// Commits that touched `path` (relative to the repository root), newest first and
+/// following renames by default, with per-commit diffstats and per-author totals.
+#[pyfunction]
+#[pyo3(signature = (repo_path, path, follow_renames=true))]
+fn get_file_history_py(py: Python<'_>, repo_path: String, path: String, follow_renames: bool) -> PyResult<String> {
+    let history = py.detach(|| git_analyzer::file_history(&repo_path, &path, follow_renames))?;
+    Ok(to_json(&history)?)
+}
+
+/// Commits of the last `days` grouped into change sets (merged pull requests,
+/// squash merges, runs of direct commits) with their authors, diffstats and the
+/// time from first to last commit.
+#[pyfunction]
+fn get_change_sets_py(py: Python<'_>, repo_path: String, days: i64) -> PyResult<String> {
+    let change_sets = py.detach(|| git_analyzer::analyze_change_sets(&repo_path, days))?;
+    Ok(to_json(&change_sets)?)
+}
+
+/// Commits of the last `days` a security review should look at first: changes to
+/// authentication, cryptography, CI/secrets or dependency manifests, large test
+/// deletions and added lines disabling security checks. Returns `commits_scanned`
+/// and `security_events`, each naming the heuristic that fired and its evidence.
+#[pyfunction]
+fn get_security_events_py(py: Python<'_>, repo_path: String, days: i64) -> PyResult<String> {
+    let scan = py.detach(|| git_analyzer::detect_security_events(&repo_path, days))?;
+    Ok(to_json(&scan)?)
+}
+
+/// Architectural decisions of the last `days` cross-referenced with the ADR files
+/// found by the documentation scan. `options_json` accepts `keywords` (decision
+/// type -> keywords) and `adr_patterns` (globs, default `docs/adr/*.md`).
+/// Decisions no ADR cites or was written with are listed as `unsynced`.
+#[pyfunction]
+#[pyo3(signature = (repo_path, days, options_json=None))]
+fn check_decision_sync_py(
+    py: Python<'_>,
+    repo_path: String,
+    days: i64,
+    options_json: Option<String>,
+) -> PyResult<String> {
+    let options: git_analyzer::DecisionOptions = match options_json {
+        Some(json) => from_json("options", &json)?,
+        None => git_analyzer::DecisionOptions::default(),
+    };
+
+    let sync = py.detach(|| git_analyzer::cross_reference_decisions(&repo_path, days, &options))?;
+    Ok(to_json(&sync)?)
+}
+
+/// Structured diff between two revisions: per-file hunks with line numbers,
+/// renames and binary flags. `options_json` accepts context_lines,
+/// detect_renames, ignore_whitespace and paths.
+#[pyfunction]
+#[pyo3(signature = (repo_path, from_ref, to_ref, options_json=None))]
+fn get_diff_py(
+    py: Python<'_>,
+    repo_path: String,
+    from_ref: String,
+    to_ref: String,
+    options_json: Option<String>,
+) -> PyResult<String> {
+    let options: git_analyzer::DiffOptions = match options_json {
+        Some(json) => from_json("options", &json)?,
+        None => git_analyzer::DiffOptions::default(),
+    };
+
+    let diff = py.detach(|| git_analyzer::diff_refs(&repo_path, &from_ref, &to_ref, &options))?;
+    Ok(to_json(&diff)?)
+}
+
+/// Draft pull request description for merging `head` into `base`, without any
+/// model: the commits of `head` missing from `base` grouped by Conventional
+/// Commit type with breaking changes and `#123` references, and the files
+/// changed since their merge base classified as docs, tests, config or code.
+/// The JSON has the rendered Markdown in `markdown`; `template_path` replaces
+/// the built-in Jinja template, whose context is the rest of the JSON.
+#[pyfunction]
+#[pyo3(signature = (repo_path, base, head="HEAD".to_string(), template_path=None))]
+fn draft_pr_description_py(
+    py: Python<'_>,
+    repo_path: String,
+    base: String,
+    head: String,
+    template_path: Option<String>,
+) -> PyResult<String> {
+    let description = py.detach(|| {
+        git_analyzer::draft_pr_description(&repo_path, &base, &head, template_path.as_deref())
+    })?;
+    Ok(to_json(&description)?)
+}
+
+/// Searches project files for a regex in parallel, honoring .gitignore rules.
+/// `options_json` accepts case_insensitive, fixed_strings, globs, include_hidden,
+/// max_matches and max_file_size_bytes. Returns matches with line numbers, byte
+/// offsets and capture groups.
+#[pyfunction]
+#[pyo3(signature = (root_path, pattern, options_json=None))]
+fn grep_project_py(
+    py: Python<'_>,
+    root_path: String,
+    pattern: String,
+    options_json: Option<String>,
+) -> PyResult<String> {
+    let options: grep::GrepOptions = match options_json {
+        Some(json) => from_json("options", &json)?,
+        None => grep::GrepOptions::default(),
+    };
+
+    let result = py.detach(|| grep::grep_project(&root_path, &pattern, &options))?;
+    Ok(to_json(&result)?)
+}
+
+/// Infers install/build/test/run commands from the project's manifests and task files.
+/// Returns candidate commands ordered by confidence, plus detected entry points.
+#[pyfunction]
+fn infer_project_commands_py(py: Python<'_>, root_path: String) -> PyResult<String> {
+    let inference = py.detach(|| command_inference::infer_project_commands(&root_path))?;
+    Ok(to_json(&inference)?)
+}
+
+/// Builds the internal import graph of Python and TypeScript/JavaScript modules.
+/// Import cycles are listed separately; when `focus_files` is given, the result also
+/// contains their transitive dependency closure.
+#[pyfunction]
+#[pyo3(signature = (root_path, focus_files=Vec::new()))]
+fn build_import_graph_py(py: Python<'_>, root_path: String, focus_files: Vec<String>) -> PyResult<String> {
+    let graph = py.detach(|| import_graph::build_import_graph(&root_path, &focus_files))?;
+    Ok(to_json(&graph)?)
+}
+
+/// Finds files matching one of the glob `patterns` under `root_path`: a glob with
+/// a `/` matches the path relative to the root ("src/**/*.rs", "docs/*.{md,txt}"),
+/// one without matches the file name ("*.md"). Returns sorted relative paths.
+#[pyfunction]
+#[pyo3(signature = (root_path, patterns, case_insensitive=false))]
+fn find_files_fast(py: Python<'_>, root_path: String, patterns: Vec<String>, case_insensitive: bool) -> PyResult<Vec<String>> {
+    Ok(py.detach(|| filesystem::find_files(&root_path, &patterns, case_insensitive))?)
+}
+
+/// Like `find_files_fast`, as JSON objects with the `path`, `size` and
+/// `modified_ms` (milliseconds since the Unix epoch) of each match.
+#[pyfunction]
+#[pyo3(signature = (root_path, patterns, case_insensitive=false))]
+fn find_files_py(py: Python<'_>, root_path: String, patterns: Vec<String>, case_insensitive: bool) -> PyResult<String> {
+    let files = py.detach(|| filesystem::find_matching_files(&root_path, &patterns, case_insensitive))?;
+    Ok(to_json(&files)?)
+}
+
+/// Extracts the YAML or TOML frontmatter of Markdown `content` as JSON (`null` without one).
+#[pyfunction]
+fn extract_metadata_fast(content: &str) -> PyResult<String> {
+    Ok(to_json(&metadata::extract_metadata(content))?)
+}
+
+/// Analyzes Markdown `content` that is not on disk. `analysis_type` is "quality"
+/// (structure counts and a 0-100 score), "metadata" (the frontmatter) or "structure"
+/// (the headings with their level and line). Returns JSON.
+#[pyfunction]
+fn analyze_text_fast(content: &str, analysis_type: &str) -> PyResult<String> {
+    Ok(text::analyze_text(content, analysis_type)?)
 }
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn cde_rust_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Logs fuera de stdout, antes de que nada registre eventos
+    logging::init();
     // Inicializar Rayon thread pool al cargar el módulo
     init_rayon();
 
+    error::register(m)?;
+    logging::register(m)?;
+    progress::register(m)?;
+    metrics::register(m)?;
+    path_policy::register(m)?;
+    state::register(m)?;
+    artifacts::register(m)?;
+    templating::register(m)?;
+    tokens::register(m)?;
+    chunking::register(m)?;
+    embeddings::register(m)?;
+    snapshot::register(m)?;
+    patch::register(m)?;
+    fuzzy::register(m)?;
+    digest::register(m)?;
+    text_file::register(m)?;
+    archive::register(m)?;
+    copy_tree::register(m)?;
+    trash::register(m)?;
+    rename::register(m)?;
+    index_service::register(m)?;
+    frontmatter::register(m)?;
+    markdown_format::register(m)?;
+    doc_index::register(m)?;
+    doc_manifest::register(m)?;
+    summarize::register(m)?;
+    conventions::register(m)?;
+    audit::register(m)?;
+    report::register(m)?;
+    trend::register(m)?;
+    test_reports::register(m)?;
+    config_validator::register(m)?;
+    env_drift::register(m)?;
+    api_surface::register(m)?;
+    openapi_validator::register(m)?;
+    agent_registry::register(m)?;
+    tool_probe::register(m)?;
+    system_info::register(m)?;
+    task_engine::register(m)?;
+    checkpoint::register(m)?;
+    project_lock::register(m)?;
+    rate_limit::register(m)?;
+    events::register(m)?;
+    cron::register(m)?;
+    #[cfg(feature = "dashboard")]
+    dashboard::register(m)?;
+    #[cfg(feature = "async")]
+    async_bindings::register(m)?;
     m.add_function(wrap_pyfunction!(scan_documentation_py, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_documentation_dict, m)?)?;
     m.add_function(wrap_pyfunction!(analyze_documentation_quality_py, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_documentation_quality_dict, m)?)?;
     m.add_function(wrap_pyfunction!(validate_workflows_py, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_workflows_dict, m)?)?;
     m.add_function(wrap_pyfunction!(scan_project_py, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_project_dict, m)?)?;
+    m.add_class::<project_scanner::ScanHandle>()?;
+    m.add_class::<CancellationToken>()?;
     m.add_function(wrap_pyfunction!(analyze_git_repository_py, m)?)?;
+    m.add_function(wrap_pyfunction!(invalidate_git_analysis_cache_py, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_repositories_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_git_contributors_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_git_bus_factor_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_git_code_churn_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_git_branches_py, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_working_tree_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_diff_py, m)?)?;
+    m.add_function(wrap_pyfunction!(draft_pr_description_py, m)?)?;
+    m.add_function(wrap_pyfunction!(check_decision_sync_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_file_history_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_change_sets_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_security_events_py, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_changelog_py, m)?)?;
+    m.add_function(wrap_pyfunction!(predict_merge_conflicts_py, m)?)?;
+    m.add_function(wrap_pyfunction!(grep_project_py, m)?)?;
+    m.add_function(wrap_pyfunction!(infer_project_commands_py, m)?)?;
+    m.add_function(wrap_pyfunction!(build_import_graph_py, m)?)?;
+    m.add_function(wrap_pyfunction!(find_files_fast, m)?)?;
+    m.add_function(wrap_pyfunction!(find_files_py, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_metadata_fast, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_text_fast, m)?)?;
 
     // Process Manager functions
     m.add_function(wrap_pyfunction!(process_manager::spawn_agents_parallel, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::run_agent_batch_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::parse_agent_result_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::collect_workspace_changes_py, m)?)?;
     m.add_function(wrap_pyfunction!(process_manager::spawn_agent_async, m)?)?;
     m.add_function(wrap_pyfunction!(process_manager::monitor_process_health, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::monitor_all_agents_py, m)?)?;
     m.add_function(wrap_pyfunction!(process_manager::kill_process, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::get_process_status_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::get_process_status_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::wait_process_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::wait_process_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::list_managed_processes_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::list_managed_processes_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::read_agent_output_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::read_agent_output_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::kill_process_tree_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::write_stdin_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::close_stdin_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::get_queue_state_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::get_supervised_agents_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::set_max_concurrent_agents_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::set_scheduler_limits_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::set_agent_history_path_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::query_agent_history_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::set_audit_mode_py, m)?)?;
+    m.add_function(wrap_pyfunction!(process_manager::get_audit_log_py, m)?)?;
 
     Ok(())
 }
@@ -0,0 +1,201 @@
+// src/log_stream.rs
+//! WebSocket streams of the managed agents' output (feature `log_stream`)
+//!
+//! Served by the local dashboard so IDE extensions can attach to running
+//! agents. `/ws/agents/{pid}` replays the agent's buffered lines from
+//! `since_seq` on, then follows it live, reading the registry with the same
+//! cursor as `read_agent_output_py` (nothing is lost or repeated between the
+//! replay and the live part); the output events of the bus only wake it up.
+//! `/ws/agents` relays every process event of the bus. Both are read-only.
+
+use crate::error::to_json;
+use crate::events::{self, Event, EventFilter, Subscription};
+use crate::process_manager::registry::{self, OutputLine};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a stream waits for an event before checking its client is still
+/// there
+const WAKE_POLL: Duration = Duration::from_secs(1);
+
+/// Lines sent per registry read
+const MAX_LINES_PER_READ: usize = 500;
+
+/// Routes merged into the dashboard's router
+pub fn routes() -> Router {
+    Router::new()
+        .route("/ws/agents", get(all_agents))
+        .route("/ws/agents/{pid}", get(one_agent))
+}
+
+/// Same payload as the `on_output` callbacks: `{"pid", "seq", "stream", "line"}`
+fn line_message(pid: u32, line: &OutputLine) -> Value {
+    serde_json::json!({
+        "pid": pid,
+        "seq": line.seq,
+        "stream": line.stream,
+        "line": line.line,
+    })
+}
+
+async fn send(socket: &mut WebSocket, message: &Value) -> bool {
+    socket
+        .send(Message::Text(message.to_string().into()))
+        .await
+        .is_ok()
+}
+
+/// Waits up to `WAKE_POLL` for events; None once the client went away
+async fn next_events(
+    socket: &mut WebSocket,
+    subscription: &Arc<Subscription>,
+) -> Option<Vec<Event>> {
+    let waiting = Arc::clone(subscription);
+    let mut wait = tokio::task::spawn_blocking(move || {
+        let mut events: Vec<Event> = waiting.next(WAKE_POLL).into_iter().collect();
+        events.extend(waiting.drain(None));
+        events
+    });
+    tokio::select! {
+        events = &mut wait => Some(events.unwrap_or_default()),
+        received = socket.recv() => match received {
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => None,
+            // Clients have nothing to say: the events being waited for still count
+            Some(Ok(_)) => Some(wait.await.unwrap_or_default()),
+        },
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AgentQuery {
+    since_seq: u64,
+}
+
+async fn one_agent(
+    ws: WebSocketUpgrade,
+    Path(pid): Path<u32>,
+    Query(query): Query<AgentQuery>,
+) -> Response {
+    if registry::status(pid).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Process {} is not managed", pid),
+        )
+            .into_response();
+    }
+    ws.on_upgrade(move |socket| follow_agent(socket, pid, query.since_seq))
+}
+
+/// Sends the agent's lines until it exits, then its final status
+///
+/// Stall changes arrive as `stream: "status"` lines ("stalled", "active"), as
+/// for `on_output`; the last message has `stream: "status"`, `line: "exited"`
+/// and the process info under `process`.
+async fn follow_agent(mut socket: WebSocket, pid: u32, since_seq: u64) {
+    // Subscribed before the first read, so no wake-up is missed
+    let subscription = Arc::new(Subscription::new(
+        EventFilter {
+            sources: vec!["process".to_string()],
+            ..EventFilter::default()
+        },
+        events::DEFAULT_MAX_QUEUED,
+    ));
+    let mut cursor = since_seq;
+    loop {
+        let Some(chunk) = registry::read_output(pid, cursor, MAX_LINES_PER_READ) else {
+            break;
+        };
+        for line in &chunk.lines {
+            if !send(&mut socket, &line_message(pid, line)).await {
+                return;
+            }
+        }
+        cursor = chunk.next_seq;
+        if !chunk.lines.is_empty() {
+            continue;
+        }
+        if chunk.finished {
+            let exited = serde_json::json!({
+                "pid": pid,
+                "seq": cursor,
+                "stream": "status",
+                "line": "exited",
+                "process": registry::status(pid),
+            });
+            send(&mut socket, &exited).await;
+            break;
+        }
+        let Some(events) = next_events(&mut socket, &subscription).await else {
+            return;
+        };
+        for event in events.iter().filter(|event| event.data["pid"] == pid) {
+            let status = match event.kind.as_str() {
+                "process.stalled" => "stalled",
+                "process.active" => "active",
+                _ => continue,
+            };
+            let message = serde_json::json!({
+                "pid": pid,
+                "seq": cursor,
+                "stream": "status",
+                "line": status,
+            });
+            if !send(&mut socket, &message).await {
+                return;
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+async fn all_agents(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(relay_process_events)
+}
+
+/// Sends every process event of the bus, as `subscribe_events_py` yields them
+async fn relay_process_events(mut socket: WebSocket) {
+    let subscription = Arc::new(Subscription::new(
+        EventFilter {
+            sources: vec!["process".to_string()],
+            ..EventFilter::default()
+        },
+        events::DEFAULT_MAX_QUEUED,
+    ));
+    while let Some(events) = next_events(&mut socket, &subscription).await {
+        for event in &events {
+            let Ok(json) = to_json(event) else {
+                continue;
+            };
+            if socket.send(Message::Text(json.into())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_message_matches_on_output_payload() {
+        let line = OutputLine {
+            seq: 3,
+            stream: "stderr".to_string(),
+            line: "warning".to_string(),
+        };
+        assert_eq!(
+            line_message(42, &line),
+            serde_json::json!({"pid": 42, "seq": 3, "stream": "stderr", "line": "warning"})
+        );
+    }
+}
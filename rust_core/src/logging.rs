@@ -0,0 +1,380 @@
+// src/logging.rs
+//! Log events of the core, kept off stdout
+//!
+//! The core logs through `tracing`. The MCP server speaks JSON-RPC over stdio, so
+//! nothing may reach stdout: events go to stderr (the default), to Python's
+//! `logging` module (loggers named after the Rust module, `cde_rust_core.grep`), to
+//! an in-memory ring buffer drained with `drain_logs_py`, or nowhere. The level
+//! defaults to `warn`, or to `CDE_RUST_LOG` when it is set at startup.
+//!
+//! Events are forwarded to Python from a dedicated thread: a worker thread that
+//! logs never waits for the GIL, which the caller of a binding may be holding.
+
+use crate::error::{to_json, CdeError};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// Environment variable setting the level when the module is loaded
+pub const LOG_LEVEL_ENV: &str = "CDE_RUST_LOG";
+
+/// Records kept by the buffer sink, and queued for Python, unless configured
+const DEFAULT_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    /// "error", "warn", "info", "debug" or "trace"
+    pub level: String,
+    /// Rust module that logged it ("cde_rust_core::documentation")
+    pub target: String,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sink {
+    Off,
+    Stderr,
+    Python,
+    Buffer,
+}
+
+struct LogState {
+    level: LevelFilter,
+    sink: Sink,
+    capacity: usize,
+    buffer: VecDeque<LogRecord>,
+    /// Queue of the forwarding thread, started by the first `set_log_sink_py("python")`
+    python: Option<SyncSender<LogRecord>>,
+}
+
+fn state() -> &'static Mutex<LogState> {
+    static STATE: OnceLock<Mutex<LogState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(LogState {
+            level: LevelFilter::WARN,
+            sink: Sink::Stderr,
+            capacity: DEFAULT_CAPACITY,
+            buffer: VecDeque::new(),
+            python: None,
+        })
+    })
+}
+
+/// Installs the core's subscriber, once; the level comes from `CDE_RUST_LOG`
+pub fn init() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        if let Some(level) = std::env::var(LOG_LEVEL_ENV)
+            .ok()
+            .and_then(|value| parse_level(&value).ok())
+        {
+            state().lock().unwrap().level = level;
+        }
+        // The embedding process may have installed its own subscriber already
+        let _ =
+            tracing::subscriber::set_global_default(tracing_subscriber::registry().with(CoreLayer));
+    });
+}
+
+/// Sets the most verbose level recorded: "off", "error", "warn", "info", "debug"
+/// or "trace"
+pub fn set_level(level: &str) -> Result<(), CdeError> {
+    state().lock().unwrap().level = parse_level(level)?;
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+/// Routes events to "stderr", "python", "buffer" or "off"; `capacity` bounds the
+/// buffer and the queue to Python
+pub fn set_sink(sink: &str, capacity: Option<usize>) -> Result<(), CdeError> {
+    let sink = match sink.to_ascii_lowercase().as_str() {
+        "off" => Sink::Off,
+        "stderr" => Sink::Stderr,
+        "python" => Sink::Python,
+        "buffer" => Sink::Buffer,
+        _ => {
+            return Err(CdeError::invalid_input(format!(
+                "Unknown log sink: {} (expected stderr, python, buffer or off)",
+                sink
+            )))
+        }
+    };
+    if capacity == Some(0) {
+        return Err(CdeError::invalid_input("capacity must be at least 1"));
+    }
+
+    let mut state = state().lock().unwrap();
+    state.sink = sink;
+    state.capacity = capacity.unwrap_or(DEFAULT_CAPACITY);
+    let excess = state.buffer.len().saturating_sub(state.capacity);
+    state.buffer.drain(..excess);
+    Ok(())
+}
+
+/// Removes and returns the buffered records, oldest first (at most `max_records`)
+pub fn drain(max_records: Option<usize>) -> Vec<LogRecord> {
+    let mut state = state().lock().unwrap();
+    let count = max_records.map_or(state.buffer.len(), |max| max.min(state.buffer.len()));
+    state.buffer.drain(..count).collect()
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, CdeError> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "off" => Ok(LevelFilter::OFF),
+        "error" => Ok(LevelFilter::ERROR),
+        "warn" | "warning" => Ok(LevelFilter::WARN),
+        "info" => Ok(LevelFilter::INFO),
+        "debug" => Ok(LevelFilter::DEBUG),
+        "trace" => Ok(LevelFilter::TRACE),
+        _ => Err(CdeError::invalid_input(format!(
+            "Unknown log level: {} (expected off, error, warn, info, debug or trace)",
+            level
+        ))),
+    }
+}
+
+fn current_level() -> LevelFilter {
+    state().lock().unwrap().level
+}
+
+struct CoreLayer;
+
+impl<S: Subscriber> Layer<S> for CoreLayer {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if *metadata.level() <= current_level() {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        *metadata.level() <= current_level()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(current_level())
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let record = LogRecord::from_event(event);
+        let mut state = state().lock().unwrap();
+        match state.sink {
+            Sink::Off => {}
+            Sink::Stderr => {
+                let _ = writeln!(std::io::stderr(), "{}", record);
+            }
+            Sink::Buffer => {
+                if state.buffer.len() >= state.capacity {
+                    state.buffer.pop_front();
+                }
+                state.buffer.push_back(record);
+            }
+            Sink::Python => {
+                if let Some(sender) = &state.python {
+                    // A full queue drops the event rather than blocking the worker
+                    let _ = sender.try_send(record);
+                }
+            }
+        }
+    }
+}
+
+impl LogRecord {
+    fn from_event(event: &Event<'_>) -> Self {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: level_name(metadata.level()).to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        }
+    }
+
+    /// Message followed by the fields, as "message key=value"
+    fn text(&self) -> String {
+        let mut text = self.message.clone();
+        for (key, value) in &self.fields {
+            let _ = write!(text, " {}={}", key, value);
+        }
+        text
+    }
+}
+
+/// "2024-05-06T10:00:00+02:00 WARN cde_rust_core::grep: message key=value"
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}: {}",
+            self.timestamp,
+            self.level.to_uppercase(),
+            self.target,
+            self.text()
+        )
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = value;
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+fn level_name(level: &Level) -> &'static str {
+    match *level {
+        Level::ERROR => "error",
+        Level::WARN => "warn",
+        Level::INFO => "info",
+        Level::DEBUG => "debug",
+        Level::TRACE => "trace",
+    }
+}
+
+/// Starts the thread forwarding events to Python, unless it runs already
+fn ensure_forwarder() {
+    let mut state = state().lock().unwrap();
+    if state.python.is_none() {
+        state.python = Some(start_forwarder(state.capacity));
+    }
+}
+
+fn start_forwarder(capacity: usize) -> SyncSender<LogRecord> {
+    let (sender, receiver) = mpsc::sync_channel::<LogRecord>(capacity);
+    thread::Builder::new()
+        .name("cde-log-forwarder".to_string())
+        .spawn(move || {
+            for record in receiver {
+                Python::attach(|py| {
+                    if let Err(e) = forward(py, &record) {
+                        e.print(py);
+                    }
+                });
+            }
+        })
+        .expect("Failed to spawn the log forwarding thread");
+    sender
+}
+
+/// Logs `record` with Python's `logging`, on the logger named after its target
+fn forward(py: Python<'_>, record: &LogRecord) -> PyResult<()> {
+    let level = match record.level.as_str() {
+        "error" => 40,
+        "warn" => 30,
+        "info" => 20,
+        "debug" => 10,
+        _ => 5,
+    };
+    py.import("logging")?
+        .call_method1("getLogger", (record.target.replace("::", "."),))?
+        .call_method1("log", (level, record.text()))?;
+    Ok(())
+}
+
+/// Set the most verbose level the core logs: "off", "error", "warn" (the default),
+/// "info", "debug" or "trace"
+#[pyfunction]
+fn set_log_level_py(level: &str) -> PyResult<()> {
+    Ok(set_level(level)?)
+}
+
+/// Choose where log events go: "stderr" (the default), "python" (the `logging`
+/// module, loggers named `cde_rust_core.<module>`), "buffer" (kept for
+/// `drain_logs_py`, the latest `capacity` events) or "off". Nothing is ever
+/// written to stdout.
+#[pyfunction]
+#[pyo3(signature = (sink, capacity=None))]
+fn set_log_sink_py(sink: &str, capacity: Option<usize>) -> PyResult<()> {
+    set_sink(sink, capacity)?;
+    if sink.eq_ignore_ascii_case("python") {
+        ensure_forwarder();
+    }
+    Ok(())
+}
+
+/// Remove and return the buffered log events, oldest first
+///
+/// Returns a JSON list of records with `timestamp`, `level`, `target` (the Rust
+/// module), `message` and structured `fields`.
+#[pyfunction]
+#[pyo3(signature = (max_records=None))]
+fn drain_logs_py(max_records: Option<usize>) -> PyResult<String> {
+    Ok(to_json(&drain(max_records))?)
+}
+
+/// Adds the logging functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(set_log_level_py, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_sink_py, m)?)?;
+    m.add_function(wrap_pyfunction!(drain_logs_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_sink_records_structured_events() {
+        let subscriber = tracing_subscriber::registry().with(CoreLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            set_level("info").unwrap();
+            set_sink("buffer", Some(2)).unwrap();
+            drain(None);
+
+            tracing::debug!("filtered out");
+            tracing::info!(files = 3, "First");
+            tracing::warn!(path = %"a/b.md", "Second");
+            tracing::error!("Third");
+            let records = drain(None);
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].level, "warn");
+            assert_eq!(records[0].message, "Second");
+            assert_eq!(records[0].fields["path"], "a/b.md");
+            assert_eq!(records[0].target, "cde_rust_core::logging::tests");
+            assert!(records[1]
+                .to_string()
+                .contains(" ERROR cde_rust_core::logging::tests: Third"));
+
+            assert!(set_level("loud").is_err());
+            assert!(set_sink("stdout", None).is_err());
+            set_sink("stderr", None).unwrap();
+            set_level("warn").unwrap();
+        });
+    }
+}
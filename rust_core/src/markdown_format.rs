@@ -0,0 +1,440 @@
+// src/markdown_format.rs
+//! Formatting Markdown deterministically, as rustfmt formats code
+//!
+//! Headings become ATX headings (`## Title`, setext ones included) surrounded
+//! by a blank line; bullets use one marker; fenced code blocks get a lowercase,
+//! de-aliased language (or the default one); trailing whitespace goes, except
+//! the two spaces of a hard line break; runs of blank lines are collapsed; and
+//! lines longer than `wrap` are broken between words, never before a word that
+//! would start a block of its own. Frontmatter and code blocks are left exactly
+//! as they are. Formatting formatted text changes nothing.
+
+use crate::chunking::{fence, heading};
+use crate::error::{from_json, to_json, CdeError};
+use crate::frontmatter;
+use crate::metrics;
+use crate::patch;
+use crate::path_policy;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkdownStyle {
+    /// Bullet marker: "-", "*" or "+"
+    pub list_marker: String,
+    /// Language given to fences without one; empty leaves them bare
+    pub default_fence_language: String,
+    /// Fence languages replaced by their canonical name (`py` by `python`)
+    pub fence_language_aliases: HashMap<String, String>,
+    /// Line width to wrap paragraphs and list items at; None does not wrap
+    pub wrap: Option<usize>,
+    pub max_blank_lines: usize,
+}
+
+impl Default for MarkdownStyle {
+    fn default() -> Self {
+        let aliases = [
+            ("py", "python"),
+            ("sh", "bash"),
+            ("shell", "bash"),
+            ("yml", "yaml"),
+            ("js", "javascript"),
+            ("ts", "typescript"),
+            ("rs", "rust"),
+        ];
+        Self {
+            list_marker: "-".to_string(),
+            default_fence_language: "text".to_string(),
+            fence_language_aliases: aliases
+                .iter()
+                .map(|(alias, name)| (alias.to_string(), name.to_string()))
+                .collect(),
+            wrap: None,
+            max_blank_lines: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatResult {
+    /// The file formatted, None for text
+    pub path: Option<String>,
+    pub changed: bool,
+    /// Whether the file was rewritten
+    pub written: bool,
+    pub content: String,
+    /// Unified diff from the original, empty when unchanged
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Blank,
+    Heading,
+    /// Frontmatter and code blocks, never changed
+    Verbatim,
+    Text,
+}
+
+/// Whether `line` is a thematic break (`---`, `* * *`)
+fn is_thematic_break(line: &str) -> bool {
+    let trimmed = line.trim();
+    let Some(marker) = trimmed.chars().next().filter(|c| "-*_".contains(*c)) else {
+        return false;
+    };
+    trimmed.chars().all(|c| c == marker || c == ' ') && trimmed.matches(marker).count() >= 3
+}
+
+/// Whether a line starting with `word` would open a block instead of
+/// continuing a paragraph
+fn starts_block(word: &str) -> bool {
+    let ordered = word.trim_end_matches(['.', ')']);
+    word.starts_with(['#', '>', '-', '+', '*', '=', '|', '`', '~', '<'])
+        || (ordered.len() < word.len()
+            && !ordered.is_empty()
+            && ordered.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Indentation, bullet and spacing opening `line`, if it is a list item
+fn list_prefix(line: &str) -> Option<(usize, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+    let marker = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 || digits > 9 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let spaces = rest[marker..].len() - rest[marker..].trim_start_matches(' ').len();
+    (spaces > 0 || rest.len() == marker).then_some((indent, marker + spaces))
+}
+
+/// `line` broken into lines of at most `width` where it can be
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if line.chars().count() <= width {
+        return vec![line.to_string()];
+    }
+    let (prefix, hanging) = match list_prefix(line) {
+        Some((indent, marker)) => (indent + marker, indent + marker),
+        None => {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            (indent, indent)
+        }
+    };
+    let hard_break = line.ends_with("  ");
+    let mut lines = Vec::new();
+    let mut current = line[..prefix].to_string();
+    let mut filled = false;
+    for word in line[prefix..].split_whitespace() {
+        let fits = current.chars().count() + 1 + word.chars().count() <= width;
+        if filled && !fits && !starts_block(word) {
+            lines.push(current);
+            current = " ".repeat(hanging);
+        } else if filled {
+            current.push(' ');
+        }
+        current.push_str(word);
+        filled = true;
+    }
+    if hard_break {
+        current.push_str("  ");
+    }
+    lines.push(current);
+    lines
+}
+
+/// The opening fence line `line` with its language normalized
+fn format_fence_open(line: &str, marker: char, length: usize, style: &MarkdownStyle) -> String {
+    let trimmed = line.trim();
+    let info = trimmed[length..].trim();
+    let (language, rest) = info.split_once(char::is_whitespace).unwrap_or((info, ""));
+    let language = language.to_lowercase();
+    let language = match style.fence_language_aliases.get(&language) {
+        Some(name) => name.clone(),
+        None if language.is_empty() => style.default_fence_language.clone(),
+        None => language,
+    };
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let fence: String = std::iter::repeat_n(marker, length).collect();
+    let info = [language.as_str(), rest.trim()]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{}{}{}", indent, fence, info)
+}
+
+fn classify(text: &str, style: &MarkdownStyle) -> Vec<(Kind, String)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out: Vec<(Kind, String)> = Vec::with_capacity(lines.len());
+    // Frontmatter stays as it is
+    let frontmatter_lines = text[..text.len() - frontmatter::body(text).len()]
+        .lines()
+        .count();
+    out.extend(
+        lines[..frontmatter_lines]
+            .iter()
+            .map(|line| (Kind::Verbatim, line.to_string())),
+    );
+    let mut index = frontmatter_lines;
+    let mut open_fence: Option<(char, usize)> = None;
+    while index < lines.len() {
+        let line = lines[index];
+        let next = lines.get(index + 1).copied();
+        index += 1;
+        if let Some((marker, length)) = open_fence {
+            let closing = fence(line).is_some_and(|(m, l)| {
+                m == marker && l >= length && line.trim().chars().all(|c| c == marker)
+            });
+            if closing {
+                open_fence = None;
+            }
+            out.push((Kind::Verbatim, line.to_string()));
+            continue;
+        }
+        if let Some((marker, length)) = fence(line) {
+            open_fence = Some((marker, length));
+            // Backtick fences cannot have backticks in their info string
+            let normalized = match marker == '`' && line.trim()[length..].contains('`') {
+                true => line.to_string(),
+                false => format_fence_open(line, marker, length, style),
+            };
+            out.push((Kind::Verbatim, normalized));
+            continue;
+        }
+        if line.trim().is_empty() {
+            out.push((Kind::Blank, String::new()));
+            continue;
+        }
+        if let Some((level, title)) = heading(line) {
+            let hashes = "#".repeat(level);
+            let formatted = if title.is_empty() {
+                hashes
+            } else {
+                format!("{} {}", hashes, title)
+            };
+            out.push((Kind::Heading, formatted));
+            continue;
+        }
+        // A one-line paragraph underlined with `===` or `---` is a setext heading
+        let starts_paragraph = out.last().is_none_or(|(kind, _)| *kind != Kind::Text);
+        let underline = next.map(str::trim_end).filter(|underline| {
+            !underline.is_empty()
+                && !underline.starts_with(' ')
+                && (underline.chars().all(|c| c == '=') || underline.chars().all(|c| c == '-'))
+        });
+        let plain = list_prefix(line).is_none()
+            && !line.starts_with(['>', '|', '<', ' ', '\t'])
+            && !is_thematic_break(line);
+        if let (Some(underline), true, true) = (underline, starts_paragraph, plain) {
+            let level = if underline.starts_with('=') { 1 } else { 2 };
+            out.push((
+                Kind::Heading,
+                format!("{} {}", "#".repeat(level), line.trim()),
+            ));
+            index += 1;
+            continue;
+        }
+
+        let mut formatted = line.trim_end().to_string();
+        let hard_break = line.ends_with("  ") && next.is_some_and(|next| !next.trim().is_empty());
+        if let Some((indent, _)) = list_prefix(line).filter(|_| !is_thematic_break(line)) {
+            if line[indent..].starts_with(['-', '*', '+']) {
+                formatted.replace_range(indent..indent + 1, &style.list_marker);
+            }
+        }
+        if hard_break {
+            formatted.push_str("  ");
+        }
+        // Tables, quotes, HTML and indented code keep their lines
+        let indented_code = line.starts_with("    ") && list_prefix(line).is_none();
+        let wrappable = !indented_code
+            && !line.starts_with(['|', '>', '<', '\t'])
+            && !line.trim_start().starts_with('[');
+        match style.wrap {
+            Some(width) if wrappable => out.extend(
+                wrap(&formatted, width)
+                    .into_iter()
+                    .map(|wrapped| (Kind::Text, wrapped)),
+            ),
+            _ => out.push((Kind::Text, formatted)),
+        }
+    }
+    out
+}
+
+/// `text` formatted with `style`, see the module docs
+pub fn format_markdown(text: &str, style: &MarkdownStyle) -> Result<String, CdeError> {
+    if !["-", "*", "+"].contains(&style.list_marker.as_str()) {
+        return Err(CdeError::invalid_input(format!(
+            "Unknown list marker: {} (expected -, * or +)",
+            style.list_marker
+        )));
+    }
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let (bom, text) = match text.strip_prefix('\u{feff}') {
+        Some(text) => ("\u{feff}", text),
+        None => ("", text),
+    };
+    let lines = classify(text, style);
+
+    // Headings get a blank line around them
+    let mut spaced: Vec<(Kind, String)> = Vec::with_capacity(lines.len());
+    for (position, (kind, line)) in lines.iter().enumerate() {
+        let previous = spaced.last().map(|(kind, _)| *kind);
+        if *kind == Kind::Heading && previous.is_some_and(|previous| previous != Kind::Blank) {
+            spaced.push((Kind::Blank, String::new()));
+        }
+        spaced.push((*kind, line.clone()));
+        let next = lines.get(position + 1).map(|(kind, _)| *kind);
+        if *kind == Kind::Heading && next.is_some_and(|next| next != Kind::Blank) {
+            spaced.push((Kind::Blank, String::new()));
+        }
+    }
+
+    let mut formatted = String::with_capacity(text.len());
+    formatted.push_str(bom);
+    let mut blanks = 0;
+    let mut started = false;
+    for (kind, line) in &spaced {
+        if *kind == Kind::Blank {
+            blanks += 1;
+            continue;
+        }
+        if started {
+            for _ in 0..blanks.min(style.max_blank_lines) {
+                formatted.push_str(newline);
+            }
+        }
+        blanks = 0;
+        started = true;
+        formatted.push_str(line);
+        formatted.push_str(newline);
+    }
+    Ok(formatted)
+}
+
+/// Formats the Markdown file at `path_or_text` (a path when it names a file)
+/// or the text itself; with `write`, the file is rewritten when it changes
+pub fn format_markdown_source(
+    path_or_text: &str,
+    style: &MarkdownStyle,
+    write: bool,
+) -> Result<FormatResult, CdeError> {
+    metrics::timed("format_markdown", || {
+        let is_path = !path_or_text.contains('\n') && Path::new(path_or_text).is_file();
+        let original = if is_path {
+            path_policy::check(path_or_text)?;
+            fs::read_to_string(path_or_text).map_err(|e| {
+                CdeError::io("Failed to read file")
+                    .with_path(path_or_text)
+                    .caused_by(&e)
+            })?
+        } else if write {
+            return Err(CdeError::invalid_input(
+                "Only a file can be written; the argument is not an existing file",
+            ));
+        } else {
+            path_or_text.to_string()
+        };
+        let content = format_markdown(&original, style)?;
+        let changed = content != original;
+        let diff = if changed {
+            let name = if is_path { path_or_text } else { "text" };
+            TextDiff::from_lines(&original, &content)
+                .unified_diff()
+                .header(&format!("a/{}", name), &format!("b/{}", name))
+                .to_string()
+        } else {
+            String::new()
+        };
+        let written = write && changed;
+        if written {
+            patch::commit(&BTreeMap::from([(
+                PathBuf::from(path_or_text),
+                Some(content.clone()),
+            )]))?;
+        }
+        metrics::add_bytes("format_markdown", original.len() as u64);
+        Ok(FormatResult {
+            path: is_path.then(|| path_or_text.to_string()),
+            changed,
+            written,
+            content,
+            diff,
+        })
+    })
+}
+
+/// Formats Markdown deterministically, as JSON
+///
+/// `path_or_text` is a file path when it names an existing file, else the
+/// Markdown itself. `style_json` may set `list_marker` ("-"),
+/// `default_fence_language` ("text"), `fence_language_aliases` (py→python,
+/// sh→bash, yml→yaml, ...), `wrap` (a line width; none by default) and
+/// `max_blank_lines` (1). Returns the formatted `content`, whether it `changed`
+/// and the unified `diff`; with `write`, a changed file is rewritten atomically.
+#[pyfunction]
+#[pyo3(signature = (path_or_text, style_json=None, write=false))]
+fn format_markdown_py(
+    py: Python<'_>,
+    path_or_text: &str,
+    style_json: Option<&str>,
+    write: bool,
+) -> PyResult<String> {
+    let style: MarkdownStyle = match style_json {
+        Some(json) => from_json("style", json)?,
+        None => MarkdownStyle::default(),
+    };
+    let result = py.detach(|| format_markdown_source(path_or_text, &style, write))?;
+    Ok(to_json(&result)?)
+}
+
+/// Adds the Markdown formatting functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(format_markdown_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_deterministically() {
+        let messy = "---\ntitle:   Keep   \n---\nTitle\n=====\nIntro text. \nHard break  \nnext line\n\n\n\n##   Setup ##\n* one\n+ two\n  * nested\n```PY\nx = 1   \n\n\n```\n~~~\nplain\n~~~\nA long line that certainly goes past the width - and would make a list if broken badly.\n";
+        let style = MarkdownStyle {
+            wrap: Some(40),
+            ..MarkdownStyle::default()
+        };
+        let formatted = format_markdown(messy, &style).unwrap();
+        assert_eq!(
+            formatted,
+            "---\ntitle:   Keep   \n---\n\n# Title\n\nIntro text.\nHard break  \nnext line\n\n## Setup\n\n- one\n- two\n  - nested\n```python\nx = 1   \n\n\n```\n~~~text\nplain\n~~~\nA long line that certainly goes past the\nwidth - and would make a list if broken\nbadly.\n"
+        );
+        assert_eq!(format_markdown(&formatted, &style).unwrap(), formatted);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.md");
+        fs::write(&path, "#Not a heading\n#  Heading\ntext\n").unwrap();
+        let path_str = path.to_str().unwrap();
+        let preview = format_markdown_source(path_str, &MarkdownStyle::default(), false).unwrap();
+        assert!(preview.changed && !preview.written);
+        assert!(preview.diff.contains("+# Heading"));
+        format_markdown_source(path_str, &MarkdownStyle::default(), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "#Not a heading\n\n# Heading\n\ntext\n"
+        );
+        assert!(format_markdown_source("# text\n", &MarkdownStyle::default(), true).is_err());
+    }
+}
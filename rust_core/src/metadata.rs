@@ -0,0 +1,212 @@
+// src/metadata.rs
+//! Document metadata: one typed struct, one parser, one validation
+//!
+//! Documentation scans, text analysis, the doc index, manifests, summaries,
+//! conventions and the audit all read frontmatter with `extract_metadata`, so
+//! a file has the same metadata everywhere. `validate_metadata` checks it
+//! against the project's conventions: every governance field present, `type`
+//! and `status` from the conventions' vocabulary, and `YYYY-MM-DD` dates.
+
+use crate::conventions::Conventions;
+use crate::frontmatter;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Frontmatter of a document; fields outside the CDE governance are kept in
+/// `extra`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub doc_type: Option<String>,
+    pub status: Option<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub author: Option<String>,
+    pub llm_summary: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_yaml::Value>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MetadataIssue {
+    /// "error" or "warning"
+    pub severity: &'static str,
+    pub message: String,
+}
+
+impl MetadataIssue {
+    fn error(message: String) -> Self {
+        Self {
+            severity: "error",
+            message,
+        }
+    }
+}
+
+/// YAML or TOML frontmatter of `content` (see `frontmatter`); `None` without
+/// one or when it is not a mapping of the expected types
+pub fn extract_metadata(content: &str) -> Option<Metadata> {
+    frontmatter::parse(content)
+}
+
+/// Governance problems of the document at `relative` (to the project root)
+/// whose frontmatter is `metadata`. A document without frontmatter is an error
+/// where the conventions restrict the types of its directory, a warning
+/// elsewhere.
+pub fn validate_metadata(
+    metadata: Option<&Metadata>,
+    relative: &str,
+    conventions: &Conventions,
+) -> Vec<MetadataIssue> {
+    let Some(metadata) = metadata else {
+        let typed = conventions
+            .rule_for(relative)
+            .is_some_and(|rule| !rule.types.is_empty());
+        return vec![MetadataIssue {
+            severity: if typed { "error" } else { "warning" },
+            message: "No YAML frontmatter".to_string(),
+        }];
+    };
+
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    let date = DATE.get_or_init(|| Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap());
+    let mut issues = Vec::new();
+    let fields = [
+        ("title", &metadata.title),
+        ("description", &metadata.description),
+        ("type", &metadata.doc_type),
+        ("status", &metadata.status),
+        ("created", &metadata.created),
+        ("updated", &metadata.updated),
+        ("author", &metadata.author),
+    ];
+    let missing: Vec<&str> = fields
+        .iter()
+        .filter(|(_, value)| value.as_deref().is_none_or(|v| v.trim().is_empty()))
+        .map(|(name, _)| *name)
+        .collect();
+    if !missing.is_empty() {
+        issues.push(MetadataIssue::error(format!(
+            "Missing required fields: {}",
+            missing.join(", ")
+        )));
+    }
+    if let Some(doc_type) = &metadata.doc_type {
+        if !conventions.doc_types().contains(doc_type) {
+            issues.push(MetadataIssue::error(format!(
+                "Invalid type '{}'. Must be one of: {}",
+                doc_type,
+                conventions.doc_types().join(", ")
+            )));
+        }
+    }
+    if let Some(status) = &metadata.status {
+        if !conventions.statuses().contains(status) {
+            issues.push(MetadataIssue::error(format!(
+                "Invalid status '{}'. Must be one of: {}",
+                status,
+                conventions.statuses().join(", ")
+            )));
+        }
+    }
+    for (name, value) in [
+        ("created", &metadata.created),
+        ("updated", &metadata.updated),
+    ] {
+        if let Some(value) = value.as_deref().filter(|v| !v.trim().is_empty()) {
+            if !date.is_match(value) {
+                issues.push(MetadataIssue::error(format!(
+                    "Invalid date format for '{}': '{}'. Expected YYYY-MM-DD",
+                    name, value
+                )));
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn test_validate_metadata() {
+        let conventions = Conventions::new(Conventions::default_rules()).unwrap();
+        let metadata = extract_metadata(
+            "---\ntitle: Plan\ndescription: Roadmap\ntype: design\nstatus: active\n\
+             created: 2024-01-15\nupdated: 15/01/2024\nauthor: Ana\n---\n",
+        )
+        .unwrap();
+        let issues = validate_metadata(Some(&metadata), "specs/design/plan.md", &conventions);
+        assert_eq!(
+            issues,
+            vec![MetadataIssue::error(
+                "Invalid date format for 'updated': '15/01/2024'. Expected YYYY-MM-DD".to_string()
+            )]
+        );
+
+        let bare = extract_metadata("---\ntype: roadmap\n---\n").unwrap();
+        let messages: Vec<&str> = validate_metadata(Some(&bare), "docs/a.md", &conventions)
+            .iter()
+            .map(|issue| issue.message.split(['\'', ':']).next().unwrap())
+            .collect();
+        assert_eq!(messages, vec!["Missing required fields", "Invalid type "]);
+
+        let severity = |relative| validate_metadata(None, relative, &conventions)[0].severity;
+        assert_eq!(severity("specs/design/plan.md"), "error");
+        assert_eq!(severity("notes/scratch.md"), "warning");
+    }
+
+    #[test]
+    fn test_frontmatter_corpus() {
+        crate::corpus::check("frontmatter", |path| {
+            let content = fs::read_to_string(path).unwrap();
+            let Some(metadata) = extract_metadata(&content) else {
+                return serde_json::Value::Null;
+            };
+            let mut extra: Vec<&String> = metadata.extra.keys().collect();
+            extra.sort();
+            serde_json::json!({
+                "title": metadata.title,
+                "type": metadata.doc_type,
+                "status": metadata.status,
+                "extra": extra,
+            })
+        });
+    }
+
+    proptest! {
+        #[test]
+        fn prop_frontmatter_never_panics(content in r"(---\n)?\PC{0,200}") {
+            extract_metadata(&content);
+        }
+
+        #[test]
+        fn prop_frontmatter_round_trips(
+            title in r"\PC{0,40}",
+            description in r"[a-z -]{0,20}(\n[a-z -]{0,20}){0,3}",
+            status in prop::option::of("[a-z]{1,10}"),
+            body in r"(\PC{0,40}\n){0,5}",
+        ) {
+            let written = Metadata {
+                title: Some(title),
+                description: Some(description),
+                doc_type: None,
+                status,
+                created: None,
+                updated: None,
+                author: None,
+                llm_summary: None,
+                extra: HashMap::new(),
+            };
+            let content = format!("---\n{}---\n{}", serde_yaml::to_string(&written).unwrap(), body);
+            prop_assert_eq!(extract_metadata(&content), Some(written));
+        }
+    }
+}
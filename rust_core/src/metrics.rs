@@ -0,0 +1,237 @@
+// src/metrics.rs
+//! Call counts, durations, bytes processed and cache hit rates of the core
+//!
+//! The main entry points (documentation and project scans, workflow validation,
+//! git analyses, grep, import graphs, text analyses) run through `timed`, which
+//! counts calls and errors and sums durations per operation. Counters live for
+//! the whole process and are read with `get_metrics_py`, as JSON or in the
+//! Prometheus text format.
+
+use crate::error::{to_json, CdeError};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationMetrics {
+    pub calls: u64,
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub bytes_processed: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    /// Hits over lookups, 0 before the first lookup
+    pub hit_rate: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub operations: BTreeMap<String, OperationMetrics>,
+    pub caches: BTreeMap<String, CacheMetrics>,
+}
+
+fn registry() -> &'static Mutex<Metrics> {
+    static REGISTRY: OnceLock<Mutex<Metrics>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Metrics::default()))
+}
+
+/// Runs `work`, recording one call of `operation` with its duration and whether
+/// it failed
+pub fn timed<T, E>(operation: &str, work: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let start = Instant::now();
+    let result = work();
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let mut metrics = registry().lock().unwrap();
+    let stats = metrics.operations.entry(operation.to_string()).or_default();
+    stats.calls += 1;
+    if result.is_err() {
+        stats.errors += 1;
+    }
+    stats.total_ms += elapsed_ms;
+    stats.max_ms = stats.max_ms.max(elapsed_ms);
+    result
+}
+
+/// Adds `bytes` read or searched to the counters of `operation`
+pub fn add_bytes(operation: &str, bytes: u64) {
+    let mut metrics = registry().lock().unwrap();
+    metrics
+        .operations
+        .entry(operation.to_string())
+        .or_default()
+        .bytes_processed += bytes;
+}
+
+/// Records a lookup in `cache`
+pub fn cache_lookup(cache: &str, hit: bool) {
+    let mut metrics = registry().lock().unwrap();
+    let stats = metrics.caches.entry(cache.to_string()).or_default();
+    if hit {
+        stats.hits += 1;
+    } else {
+        stats.misses += 1;
+    }
+}
+
+/// Counters recorded since the start of the process or the last `reset`
+pub fn snapshot() -> Metrics {
+    let mut metrics = registry().lock().unwrap().clone();
+    for stats in metrics.operations.values_mut() {
+        stats.mean_ms = stats.total_ms / stats.calls.max(1) as f64;
+    }
+    for stats in metrics.caches.values_mut() {
+        let lookups = stats.hits + stats.misses;
+        stats.hit_rate = stats.hits as f64 / lookups.max(1) as f64;
+    }
+    metrics
+}
+
+pub fn reset() {
+    *registry().lock().unwrap() = Metrics::default();
+}
+
+/// `metrics` in the Prometheus text exposition format
+pub fn prometheus(metrics: &Metrics) -> String {
+    let mut text = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(text, "{}{{{}}} {}", name, labels, value);
+        }
+    };
+    let per_operation = |value: &dyn Fn(&OperationMetrics) -> String| {
+        metrics
+            .operations
+            .iter()
+            .map(|(name, stats)| (format!("operation=\"{}\"", name), value(stats)))
+            .collect::<Vec<_>>()
+    };
+    let per_cache = |value: &dyn Fn(&CacheMetrics) -> String| {
+        metrics
+            .caches
+            .iter()
+            .map(|(name, stats)| (format!("cache=\"{}\"", name), value(stats)))
+            .collect::<Vec<_>>()
+    };
+
+    family(
+        "cde_rust_calls_total",
+        "counter",
+        "Calls of each core operation",
+        per_operation(&|s| s.calls.to_string()),
+    );
+    family(
+        "cde_rust_errors_total",
+        "counter",
+        "Calls of each core operation that returned an error",
+        per_operation(&|s| s.errors.to_string()),
+    );
+    family(
+        "cde_rust_duration_seconds_total",
+        "counter",
+        "Time spent in each core operation",
+        per_operation(&|s| (s.total_ms / 1000.0).to_string()),
+    );
+    family(
+        "cde_rust_duration_seconds_max",
+        "gauge",
+        "Longest call of each core operation",
+        per_operation(&|s| (s.max_ms / 1000.0).to_string()),
+    );
+    family(
+        "cde_rust_bytes_processed_total",
+        "counter",
+        "Bytes read or searched by each core operation",
+        per_operation(&|s| s.bytes_processed.to_string()),
+    );
+    family(
+        "cde_rust_cache_hits_total",
+        "counter",
+        "Lookups answered by each cache",
+        per_cache(&|s| s.hits.to_string()),
+    );
+    family(
+        "cde_rust_cache_misses_total",
+        "counter",
+        "Lookups each cache could not answer",
+        per_cache(&|s| s.misses.to_string()),
+    );
+    text
+}
+
+/// Performance counters of the core since it was loaded (or last reset)
+///
+/// With `format="json"` (the default), returns `operations` (per operation:
+/// `calls`, `errors`, `total_ms`, `mean_ms`, `max_ms`, `bytes_processed`) and
+/// `caches` (`hits`, `misses`, `hit_rate`). With `format="prometheus"`, returns the
+/// same counters in the Prometheus text format, prefixed with `cde_rust_`.
+#[pyfunction]
+#[pyo3(signature = (format="json"))]
+fn get_metrics_py(format: &str) -> PyResult<String> {
+    let metrics = snapshot();
+    match format {
+        "json" => Ok(to_json(&metrics)?),
+        "prometheus" => Ok(prometheus(&metrics)),
+        _ => Err(CdeError::invalid_input(format!(
+            "Unknown metrics format: {} (expected json or prometheus)",
+            format
+        ))
+        .into()),
+    }
+}
+
+/// Clear every counter
+#[pyfunction]
+fn reset_metrics_py() {
+    reset();
+}
+
+/// Adds the metrics functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_metrics_py, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_metrics_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_operations_and_prometheus_text() {
+        let _ = timed("metrics_test_ok", || Ok::<_, CdeError>(()));
+        let _ = timed("metrics_test_ok", || Err::<(), _>(CdeError::io("failed")));
+        add_bytes("metrics_test_ok", 42);
+        cache_lookup("metrics_test_cache", true);
+        cache_lookup("metrics_test_cache", false);
+        cache_lookup("metrics_test_cache", true);
+
+        let metrics = snapshot();
+        let stats = &metrics.operations["metrics_test_ok"];
+        assert_eq!(
+            (stats.calls, stats.errors, stats.bytes_processed),
+            (2, 1, 42)
+        );
+        assert!(stats.max_ms <= stats.total_ms);
+        let cache = &metrics.caches["metrics_test_cache"];
+        assert_eq!((cache.hits, cache.misses), (2, 1));
+        assert!((cache.hit_rate - 2.0 / 3.0).abs() < 1e-9);
+
+        let text = prometheus(&metrics);
+        assert!(text.contains("# TYPE cde_rust_calls_total counter\n"));
+        assert!(text.contains("cde_rust_calls_total{operation=\"metrics_test_ok\"} 2\n"));
+        assert!(text.contains("cde_rust_bytes_processed_total{operation=\"metrics_test_ok\"} 42\n"));
+        assert!(text.contains("cde_rust_cache_misses_total{cache=\"metrics_test_cache\"} 1\n"));
+    }
+}
@@ -0,0 +1,620 @@
+// src/openapi_validator.rs
+//! Validation of OpenAPI 3 and Swagger 2 documents, and of their drift from
+//! the code
+//!
+//! The structural rules a spec most often breaks are checked without a
+//! schema: the version and `info` fields, paths starting with `/`, operations
+//! with responses and unique `operationId`s, path templates matching the
+//! declared path parameters, paths that differ only by parameter names, and
+//! local `$ref`s pointing at something. Given the root of the service's code,
+//! the endpoints `api_surface` finds there are compared with the spec's:
+//! endpoints the spec does not document and operations no code serves any more
+//! are reported. Issues are those of the other validators, so SARIF reporting
+//! works the same.
+
+use crate::api_surface::{self, ApiEndpoint};
+use crate::config_validator::{parse_config, to_sarif, ConfigFormat, ValidationIssue};
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use pyo3::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+const HTTP_METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Keys of a path item besides its operations
+const PATH_ITEM_KEYS: &[&str] = &["$ref", "summary", "description", "servers", "parameters"];
+
+static TEMPLATE_PARAM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([^}/]+)\}").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiDrift {
+    /// Endpoints found in the code, besides those of API documents
+    pub code_endpoints: usize,
+    /// Served by the code, missing from the spec
+    pub undocumented: Vec<ApiEndpoint>,
+    /// In the spec, served by no code
+    pub removed: Vec<ApiEndpoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenApiReport {
+    pub spec_path: String,
+    /// "3.1.0", "2.0", ...
+    pub version: Option<String>,
+    pub valid: bool,
+    pub operations: usize,
+    pub issues: Vec<ValidationIssue>,
+    /// Present when the code was compared with the spec
+    pub drift: Option<ApiDrift>,
+    pub summary: String,
+    pub truncated: bool,
+}
+
+impl CapLists for OpenApiReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.issues, max_items);
+        if let Some(drift) = &mut self.drift {
+            self.truncated |=
+                cap(&mut drift.undocumented, max_items) | cap(&mut drift.removed, max_items);
+        }
+    }
+}
+
+/// Collects the issues of one spec file
+struct Checker<'a> {
+    file: &'a str,
+    content: &'a str,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, line: Option<usize>, rule: &str, message: String) {
+        self.issues
+            .push(ValidationIssue::error(self.file, line, rule, message));
+    }
+
+    fn warning(&mut self, line: Option<usize>, rule: &str, message: String) {
+        self.issues.push(ValidationIssue {
+            severity: "warning".to_string(),
+            file: self.file.to_string(),
+            line,
+            message,
+            rule: Some(rule.to_string()),
+        });
+    }
+
+    fn line_of(&self, path: &str) -> Option<usize> {
+        Some(api_surface::spec_line(self.content, path))
+    }
+}
+
+/// Target of the local reference `reference` ("#/components/schemas/Pet")
+fn resolve<'a>(spec: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    spec.pointer(pointer)
+}
+
+/// Every `$ref` string of `value`
+fn collect_refs<'a>(value: &'a Value, refs: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                match (key.as_str(), child) {
+                    ("$ref", Value::String(reference)) => refs.push(reference),
+                    _ => collect_refs(child, refs),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_refs(item, refs)),
+        _ => {}
+    }
+}
+
+/// Names of the path parameters of `parameters`, following local references
+fn path_parameters(spec: &Value, parameters: Option<&Value>) -> BTreeSet<String> {
+    parameters
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(
+            |parameter| match parameter.get("$ref").and_then(Value::as_str) {
+                Some(reference) => resolve(spec, reference),
+                None => Some(parameter),
+            },
+        )
+        .filter(|parameter| parameter.get("in").and_then(Value::as_str) == Some("path"))
+        .filter_map(|parameter| parameter.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Checks the document `spec`; returns its version and operation count
+fn check_spec(checker: &mut Checker<'_>, spec: &Value) -> (Option<String>, usize) {
+    let version = match (spec.get("openapi"), spec.get("swagger")) {
+        (Some(Value::String(version)), _) if version.starts_with("3.") => Some(version.clone()),
+        (_, Some(Value::String(version))) if version == "2.0" => Some(version.clone()),
+        (None, None) => {
+            checker.error(
+                Some(1),
+                "openapi-version",
+                "Missing 'openapi' (3.x) or 'swagger' (2.0) version field".to_string(),
+            );
+            None
+        }
+        (openapi, swagger) => {
+            let found = openapi
+                .or(swagger)
+                .map_or_else(String::new, Value::to_string);
+            checker.error(
+                Some(1),
+                "openapi-version",
+                format!("Unsupported OpenAPI version {}", found),
+            );
+            None
+        }
+    };
+
+    match spec.get("info") {
+        Some(info) => {
+            for field in ["title", "version"] {
+                if info.get(field).is_none() {
+                    checker.error(
+                        None,
+                        "openapi-info",
+                        format!("'info' is missing its '{}'", field),
+                    );
+                }
+            }
+        }
+        None => checker.error(None, "openapi-info", "Missing 'info' object".to_string()),
+    }
+
+    let mut refs = Vec::new();
+    collect_refs(spec, &mut refs);
+    let missing: BTreeSet<&str> = refs
+        .into_iter()
+        .filter(|reference| reference.starts_with('#') && resolve(spec, reference).is_none())
+        .collect();
+    for reference in missing {
+        checker.error(
+            None,
+            "openapi-ref",
+            format!("Reference '{}' points at nothing", reference),
+        );
+    }
+
+    let Some(paths) = spec.get("paths").and_then(Value::as_object) else {
+        // OpenAPI 3.1 documents may only hold webhooks or components
+        if !version.as_deref().is_some_and(|v| v.starts_with("3.1")) {
+            checker.error(None, "openapi-paths", "Missing 'paths' object".to_string());
+        }
+        return (version, 0);
+    };
+
+    let mut operations = 0;
+    let mut operation_ids: HashMap<String, String> = HashMap::new();
+    let mut templates: HashMap<String, String> = HashMap::new();
+    for (path, item) in paths {
+        let line = checker.line_of(path);
+        if !path.starts_with('/') {
+            checker.error(
+                line,
+                "openapi-path",
+                format!("Path '{}' must start with '/'", path),
+            );
+        }
+        let template = TEMPLATE_PARAM.replace_all(path, "{}").into_owned();
+        if let Some(other) = templates.insert(template, path.clone()) {
+            checker.error(
+                line,
+                "openapi-path-duplicate",
+                format!(
+                    "Paths '{}' and '{}' only differ by parameter names",
+                    other, path
+                ),
+            );
+        }
+        let Some(item) = item.as_object() else {
+            checker.error(
+                line,
+                "openapi-path",
+                format!("Path '{}' is not an object", path),
+            );
+            continue;
+        };
+        let in_template: BTreeSet<String> = TEMPLATE_PARAM
+            .captures_iter(path)
+            .map(|caps| caps[1].to_string())
+            .collect();
+        let shared = path_parameters(spec, item.get("parameters"));
+
+        for (key, operation) in item {
+            if !HTTP_METHODS.contains(&key.as_str()) {
+                if !PATH_ITEM_KEYS.contains(&key.as_str()) && !key.starts_with("x-") {
+                    checker.warning(
+                        line,
+                        "openapi-operation",
+                        format!("Unknown key '{}' in path '{}'", key, path),
+                    );
+                }
+                continue;
+            }
+            operations += 1;
+            let name = format!("{} {}", key.to_uppercase(), path);
+            if operation.get("responses").is_none() {
+                checker.error(
+                    line,
+                    "openapi-responses",
+                    format!("{} has no responses", name),
+                );
+            }
+            if let Some(id) = operation.get("operationId").and_then(Value::as_str) {
+                if let Some(first) = operation_ids.insert(id.to_string(), name.clone()) {
+                    checker.error(
+                        line,
+                        "openapi-operation-id",
+                        format!(
+                            "operationId '{}' of {} is already used by {}",
+                            id, name, first
+                        ),
+                    );
+                }
+            }
+            let declared: BTreeSet<String> = shared
+                .iter()
+                .cloned()
+                .chain(path_parameters(spec, operation.get("parameters")))
+                .collect();
+            for missing in in_template.difference(&declared) {
+                checker.error(
+                    line,
+                    "openapi-path-params",
+                    format!("{} does not declare path parameter '{}'", name, missing),
+                );
+            }
+            for unused in declared.difference(&in_template) {
+                checker.error(
+                    line,
+                    "openapi-path-params",
+                    format!(
+                        "{} declares path parameter '{}' missing from its path",
+                        name, unused
+                    ),
+                );
+            }
+        }
+    }
+    (version, operations)
+}
+
+/// Path prefix of the first server of an OpenAPI 3 document ("/api/v1")
+fn server_prefix(spec: &Value) -> String {
+    let url = spec
+        .pointer("/servers/0/url")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |i| &rest[i..]),
+        None => url,
+    };
+    path.trim_end_matches('/').to_string()
+}
+
+/// Method and path of `endpoint` with parameter names erased
+fn route_key(method: &str, path: &str) -> (String, String) {
+    let path = TEMPLATE_PARAM.replace_all(path, "{}");
+    let path = path.trim_end_matches('/');
+    let path = if path.is_empty() { "/" } else { path };
+    (method.to_string(), path.to_string())
+}
+
+/// Compares the endpoints of the code with those of the spec; `prefix` is the
+/// server path the spec's paths are relative to
+pub fn compare_endpoints(code: &[ApiEndpoint], spec: &[ApiEndpoint], prefix: &str) -> ApiDrift {
+    let code: Vec<&ApiEndpoint> = code
+        .iter()
+        .filter(|endpoint| endpoint.framework != "openapi" && endpoint.method != "WEBSOCKET")
+        .collect();
+    // A spec path matches code with or without the server prefix
+    let spec_keys: BTreeSet<(String, String)> = spec
+        .iter()
+        .flat_map(|endpoint| {
+            let prefixed = format!("{}{}", prefix, endpoint.path);
+            [
+                route_key(&endpoint.method, &endpoint.path),
+                route_key(&endpoint.method, &prefixed),
+            ]
+        })
+        .collect();
+    let spec_paths: BTreeSet<String> = spec_keys.iter().map(|(_, path)| path.clone()).collect();
+    let code_keys: BTreeSet<(String, String)> = code
+        .iter()
+        .map(|endpoint| route_key(&endpoint.method, &endpoint.path))
+        .collect();
+    let code_any: BTreeSet<String> = code
+        .iter()
+        .filter(|endpoint| endpoint.method == "ANY")
+        .map(|endpoint| route_key("ANY", &endpoint.path).1)
+        .collect();
+
+    let undocumented: Vec<ApiEndpoint> = code
+        .iter()
+        .filter(|endpoint| {
+            let (method, path) = route_key(&endpoint.method, &endpoint.path);
+            match method.as_str() {
+                "ANY" => !spec_paths.contains(&path),
+                _ => !spec_keys.contains(&(method, path)),
+            }
+        })
+        .map(|endpoint| (*endpoint).clone())
+        .collect();
+    let removed: Vec<ApiEndpoint> = spec
+        .iter()
+        .filter(|endpoint| {
+            let prefixed = format!("{}{}", prefix, endpoint.path);
+            [&endpoint.path, &prefixed].iter().all(|path| {
+                let key = route_key(&endpoint.method, path);
+                !code_any.contains(&key.1) && !code_keys.contains(&key)
+            })
+        })
+        .cloned()
+        .collect();
+    ApiDrift {
+        code_endpoints: code.len(),
+        undocumented,
+        removed,
+    }
+}
+
+/// Validates the OpenAPI document at `spec_path`; with `code_root`, also
+/// compares it with the endpoints of the code under that directory
+pub fn validate_openapi(
+    spec_path: &str,
+    code_root: Option<&str>,
+) -> Result<OpenApiReport, CdeError> {
+    metrics::timed("validate_openapi", || {
+        let path = Path::new(spec_path);
+        path_policy::check(path)?;
+        let content = fs::read_to_string(path).map_err(|e| {
+            CdeError::io("Failed to read OpenAPI document")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+        let format = match ConfigFormat::of(path) {
+            Some(ConfigFormat::Json) => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        };
+        let mut checker = Checker {
+            file: spec_path,
+            content: &content,
+            issues: Vec::new(),
+        };
+        let (version, operations, spec) = match parse_config(&content, format) {
+            Ok(spec) => {
+                let (version, operations) = check_spec(&mut checker, &spec);
+                (version, operations, Some(spec))
+            }
+            Err((message, line)) => {
+                checker.error(line, "openapi-syntax", message);
+                (None, 0, None)
+            }
+        };
+        let mut issues = checker.issues;
+
+        let drift = match (code_root, &spec) {
+            (Some(root), Some(spec)) => {
+                let surface = api_surface::extract_api_endpoints(root)?;
+                let documented =
+                    api_surface::openapi_endpoints(&content, spec_path).unwrap_or_default();
+                let drift =
+                    compare_endpoints(&surface.endpoints, &documented, &server_prefix(spec));
+                let in_root =
+                    |file: &str| Path::new(root).join(file).to_string_lossy().into_owned();
+                for endpoint in &drift.undocumented {
+                    issues.push(ValidationIssue {
+                        severity: "warning".to_string(),
+                        file: in_root(&endpoint.file),
+                        line: Some(endpoint.line),
+                        message: format!(
+                            "{} {} is served but not documented in the spec",
+                            endpoint.method, endpoint.path
+                        ),
+                        rule: Some("openapi-undocumented".to_string()),
+                    });
+                }
+                for endpoint in &drift.removed {
+                    issues.push(ValidationIssue {
+                        severity: "warning".to_string(),
+                        file: spec_path.to_string(),
+                        line: Some(endpoint.line),
+                        message: format!(
+                            "{} {} is documented but no code serves it",
+                            endpoint.method, endpoint.path
+                        ),
+                        rule: Some("openapi-removed".to_string()),
+                    });
+                }
+                Some(drift)
+            }
+            _ => None,
+        };
+
+        let errors = issues
+            .iter()
+            .filter(|issue| issue.severity == "error")
+            .count();
+        let valid = errors == 0;
+        let mut summary = if valid {
+            format!("✅ The spec is valid ({} operations).", operations)
+        } else {
+            format!("⚠️ Found {} errors in the spec.", errors)
+        };
+        if let Some(drift) = &drift {
+            summary.push_str(&format!(
+                " {} endpoints undocumented, {} documented endpoints not served.",
+                drift.undocumented.len(),
+                drift.removed.len()
+            ));
+        }
+        Ok(OpenApiReport {
+            spec_path: spec_path.to_string(),
+            version,
+            valid,
+            operations,
+            issues,
+            drift,
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+/// Validates an OpenAPI 3 or Swagger 2 document (YAML or JSON)
+///
+/// Checks the version and `info` fields, paths starting with `/`, operations
+/// with `responses` and unique `operationId`s, path templates against the
+/// declared path parameters, paths differing only by parameter names and local
+/// `$ref`s. With `code_root`, the endpoints found in the code there (FastAPI,
+/// Flask, Express, see `extract_api_endpoints_py`) are compared with the spec:
+/// `drift.undocumented` are served but not documented, `drift.removed`
+/// documented but not served. Returns `valid`, `issues` (severity, file, line,
+/// message, rule) and `summary`; with `sarif`, the issues as a SARIF log.
+#[pyfunction]
+#[pyo3(signature = (spec_path, code_root=None, sarif=false, max_list_items=None))]
+fn validate_openapi_py(
+    py: Python<'_>,
+    spec_path: &str,
+    code_root: Option<&str>,
+    sarif: bool,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let mut report = py.detach(|| validate_openapi(spec_path, code_root))?;
+    if sarif {
+        return Ok(to_json(&to_sarif(
+            "cde-openapi-validator",
+            &report.issues,
+            code_root.map(Path::new),
+        ))?);
+    }
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Adds the OpenAPI validation functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(validate_openapi_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const SPEC: &str = r#"openapi: 3.0.3
+info:
+  title: Pets
+paths:
+  /pets/{petId}:
+    get:
+      operationId: showPet
+      responses:
+        '200':
+          $ref: '#/components/responses/Pet'
+  /pets/{id}:
+    delete:
+      operationId: showPet
+      parameters:
+        - name: id
+          in: path
+      responses: {}
+  /health:
+    get: {}
+components:
+  responses: {}
+"#;
+
+    fn rules(report: &OpenApiReport) -> BTreeMap<String, usize> {
+        let mut rules = BTreeMap::new();
+        for issue in &report.issues {
+            *rules.entry(issue.rule.clone().unwrap()).or_insert(0) += 1;
+        }
+        rules
+    }
+
+    #[test]
+    fn test_structural_errors_are_reported() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spec_path = dir.path().join("openapi.yaml");
+        fs::write(&spec_path, SPEC).unwrap();
+        let report = validate_openapi(spec_path.to_str().unwrap(), None).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.version.as_deref(), Some("3.0.3"));
+        assert_eq!(report.operations, 3);
+        let expected: BTreeMap<String, usize> = [
+            ("openapi-info", 1),
+            ("openapi-operation-id", 1),
+            ("openapi-path-duplicate", 1),
+            ("openapi-path-params", 1),
+            ("openapi-ref", 1),
+            ("openapi-responses", 1),
+        ]
+        .into_iter()
+        .map(|(rule, count)| (rule.to_string(), count))
+        .collect();
+        assert_eq!(rules(&report), expected);
+        let params = report
+            .issues
+            .iter()
+            .find(|issue| issue.rule.as_deref() == Some("openapi-path-params"))
+            .unwrap();
+        assert_eq!(params.line, Some(5));
+        assert!(params.message.contains("'petId'"));
+
+        fs::write(&spec_path, "openapi: [\n").unwrap();
+        let broken = validate_openapi(spec_path.to_str().unwrap(), None).unwrap();
+        assert_eq!(broken.issues[0].rule.as_deref(), Some("openapi-syntax"));
+    }
+
+    #[test]
+    fn test_drift_between_code_and_spec() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("app.py"),
+            "from fastapi import FastAPI\napp = FastAPI()\n\n@app.get('/api/pets/{pet_id}')\ndef show(pet_id): ...\n\n@app.post('/api/pets')\ndef create(): ...\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("openapi.yaml"),
+            "openapi: 3.1.0\ninfo: {title: Pets, version: '1'}\nservers:\n  - url: https://example.com/api\npaths:\n  /pets/{id}:\n    get:\n      parameters: [{name: id, in: path, required: true}]\n      responses: {'200': {description: ok}}\n  /pets/{id}/photo:\n    get:\n      parameters: [{name: id, in: path, required: true}]\n      responses: {'200': {description: ok}}\n",
+        )
+        .unwrap();
+
+        let spec_path = root.join("openapi.yaml");
+        let report =
+            validate_openapi(spec_path.to_str().unwrap(), Some(root.to_str().unwrap())).unwrap();
+        assert!(report.valid, "{:?}", report.issues);
+        let drift = report.drift.unwrap();
+        assert_eq!(drift.code_endpoints, 2);
+        let undocumented: Vec<(&str, &str)> = drift
+            .undocumented
+            .iter()
+            .map(|e| (e.method.as_str(), e.path.as_str()))
+            .collect();
+        assert_eq!(undocumented, vec![("POST", "/api/pets")]);
+        let removed: Vec<&str> = drift.removed.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(removed, vec!["/pets/{id}/photo"]);
+    }
+}
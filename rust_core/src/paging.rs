@@ -0,0 +1,54 @@
+// src/paging.rs
+//! Pagination and size caps of the scan and report APIs
+//!
+//! APIs returning a list of items take `offset` / `limit` over a stable order
+//! (documents are sorted by path). Report APIs take `max_list_items`: each list
+//! of the report is cut to that many items and the report's `truncated` flag is
+//! set when anything was cut; counts such as `total_docs` keep the full totals.
+
+/// Reports whose lists can be cut to a maximum length
+pub trait CapLists {
+    /// Cuts every list to `max_items` and sets `truncated` when items were dropped
+    fn cap_lists(&mut self, max_items: usize);
+}
+
+/// `report` with its lists cut to `max_items`, when given
+pub fn capped<T: CapLists>(mut report: T, max_items: Option<usize>) -> T {
+    if let Some(max_items) = max_items {
+        report.cap_lists(max_items);
+    }
+    report
+}
+
+/// Cuts `list` to `max_items`; true when items were dropped
+pub fn cap<T>(list: &mut Vec<T>, max_items: usize) -> bool {
+    let cut = list.len() > max_items;
+    list.truncate(max_items);
+    cut
+}
+
+/// Items `offset..offset + limit` of `items`, to the end without a limit
+pub fn page<T>(items: Vec<T>, offset: usize, limit: Option<usize>) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_and_cap() {
+        assert_eq!(page(vec![1, 2, 3, 4], 1, Some(2)), vec![2, 3]);
+        assert_eq!(page(vec![1, 2, 3], 2, None), vec![3]);
+        assert!(page(vec![1, 2], 5, Some(1)).is_empty());
+
+        let mut list = vec!["a", "b", "c"];
+        assert!(!cap(&mut list, 3));
+        assert!(cap(&mut list, 1));
+        assert_eq!(list, vec!["a"]);
+    }
+}
@@ -0,0 +1,880 @@
+// src/patch.rs
+//! All-or-nothing application of unified diffs and file edits
+//!
+//! Changes are validated in memory first: every hunk must find its context (at
+//! the line the diff says, or within `max_offset` lines of it), every edit its
+//! target. If anything is rejected, no file is touched and the result says which
+//! hunks or edits failed and why. Otherwise the new contents are written to
+//! temporary files next to their targets, the originals are moved aside and the
+//! temporaries renamed into place; a failure at any step restores the originals.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatchOptions {
+    /// Leading path components removed from the paths of the diff, as `patch -p`
+    pub strip: usize,
+    /// How far from its stated line a hunk's context may be found
+    pub max_offset: usize,
+    /// Validate only, writing nothing
+    pub dry_run: bool,
+}
+
+impl Default for PatchOptions {
+    fn default() -> Self {
+        Self {
+            strip: 1,
+            max_offset: 200,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HunkResult {
+    pub index: usize,
+    /// The `@@ ... @@` line
+    pub header: String,
+    /// "applied" or "rejected"
+    pub status: String,
+    /// 1-based line of the original file the hunk applied at
+    pub applied_at: Option<usize>,
+    /// Lines between the stated and the actual position
+    pub offset: isize,
+    pub reason: Option<String>,
+    /// For rejected hunks: the lines the hunk expected, and the lines found where
+    /// it should have applied
+    pub expected: Vec<String>,
+    pub found: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilePatchResult {
+    pub path: String,
+    /// "modified", "created", "deleted" or "rejected"
+    pub status: String,
+    pub reason: Option<String>,
+    pub hunks: Vec<HunkResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchResult {
+    /// Whether the files were written; false when anything was rejected or for a
+    /// dry run
+    pub applied: bool,
+    pub dry_run: bool,
+    pub files: Vec<FilePatchResult>,
+}
+
+/// One edit of `apply_file_edits`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FileEdit {
+    /// Creates or overwrites the file
+    Write {
+        path: String,
+        content: String,
+    },
+    /// Replaces `old` by `new`, which must occur exactly `count` times
+    Replace {
+        path: String,
+        old: String,
+        new: String,
+        #[serde(default = "one")]
+        count: usize,
+    },
+    Delete {
+        path: String,
+    },
+}
+
+fn one() -> usize {
+    1
+}
+
+impl FileEdit {
+    fn path(&self) -> &str {
+        match self {
+            Self::Write { path, .. } | Self::Replace { path, .. } | Self::Delete { path } => path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditResult {
+    pub index: usize,
+    pub path: String,
+    /// "applied" or "rejected"
+    pub status: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditsResult {
+    pub applied: bool,
+    pub dry_run: bool,
+    pub edits: Vec<EditResult>,
+}
+
+/// `path` under `root`; with a root, paths must be relative and stay inside it.
+/// Either way the path policy must allow it.
+fn resolve(root: Option<&Path>, path: &str) -> Result<PathBuf, CdeError> {
+    let Some(root) = root else {
+        path_policy::check(path)?;
+        return Ok(PathBuf::from(path));
+    };
+    let relative = Path::new(path);
+    let escapes = relative.components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if escapes || path.is_empty() {
+        return Err(CdeError::invalid_input("Path must stay inside the root").with_path(path));
+    }
+    let target = root.join(relative);
+    path_policy::check(&target)?;
+    Ok(target)
+}
+
+/// Current content of `path`, None if it does not exist
+fn read_text(path: &Path) -> Result<Option<String>, String> {
+    if path.exists() && !path.is_file() {
+        return Err("Not a regular file".to_string());
+    }
+    match fs::read(path) {
+        Ok(bytes) => String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| "Not a UTF-8 text file".to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// A file of a commit, and how far it got
+struct Staged {
+    target: PathBuf,
+    temporary: Option<PathBuf>,
+    backup: Option<PathBuf>,
+    /// The temporary file was renamed to the target
+    replaced: bool,
+}
+
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{}.cde-{}-{}", name, suffix, std::process::id()))
+}
+
+/// Missing ancestors of `dir`, deepest first
+fn missing_dirs(dir: &Path) -> Vec<PathBuf> {
+    dir.ancestors()
+        .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Undoes what `commit` did so far, newest first
+fn roll_back(staged: &[Staged], created_dirs: &[PathBuf]) {
+    for file in staged.iter().rev() {
+        if file.replaced && file.backup.is_none() {
+            let _ = fs::remove_file(&file.target);
+        }
+        if let Some(backup) = &file.backup {
+            let _ = fs::rename(backup, &file.target);
+        }
+        if let Some(temporary) = &file.temporary {
+            if !file.replaced {
+                let _ = fs::remove_file(temporary);
+            }
+        }
+    }
+    for dir in created_dirs {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+/// Writes every change (None deletes the file) or none of them
+pub(crate) fn commit(changes: &BTreeMap<PathBuf, Option<String>>) -> Result<(), CdeError> {
+    let mut staged: Vec<Staged> = Vec::new();
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+    let fail = |staged: &[Staged],
+                created_dirs: &[PathBuf],
+                message: &str,
+                path: &Path,
+                e: std::io::Error| {
+        roll_back(staged, created_dirs);
+        CdeError::io(message).with_path(path).caused_by(&e)
+    };
+
+    // Stage the new contents next to their targets
+    for (target, content) in changes {
+        let mut file = Staged {
+            target: target.clone(),
+            temporary: None,
+            backup: None,
+            replaced: false,
+        };
+        if let Some(content) = content {
+            if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+                let missing = missing_dirs(parent);
+                if let Err(e) = fs::create_dir_all(parent) {
+                    return Err(fail(
+                        &staged,
+                        &created_dirs,
+                        "Failed to create directory",
+                        parent,
+                        e,
+                    ));
+                }
+                created_dirs.extend(missing);
+            }
+            let temporary = sibling(target, "tmp");
+            let written = fs::write(&temporary, content).and_then(|_| match fs::metadata(target) {
+                Ok(metadata) => fs::set_permissions(&temporary, metadata.permissions()),
+                Err(_) => Ok(()),
+            });
+            file.temporary = Some(temporary.clone());
+            staged.push(file);
+            if let Err(e) = written {
+                return Err(fail(
+                    &staged,
+                    &created_dirs,
+                    "Failed to stage file",
+                    &temporary,
+                    e,
+                ));
+            }
+        } else {
+            staged.push(file);
+        }
+    }
+
+    // Move the originals aside and the new contents in
+    for index in 0..staged.len() {
+        let file = &mut staged[index];
+        if file.target.exists() {
+            let backup = sibling(&file.target, "bak");
+            if let Err(e) = fs::rename(&file.target, &backup) {
+                let target = file.target.clone();
+                return Err(fail(
+                    &staged,
+                    &created_dirs,
+                    "Failed to replace file",
+                    &target,
+                    e,
+                ));
+            }
+            file.backup = Some(backup);
+        }
+        if let Some(temporary) = &file.temporary {
+            if let Err(e) = fs::rename(temporary, &file.target) {
+                let target = file.target.clone();
+                return Err(fail(
+                    &staged,
+                    &created_dirs,
+                    "Failed to replace file",
+                    &target,
+                    e,
+                ));
+            }
+            file.replaced = true;
+        }
+    }
+
+    for file in &staged {
+        if let Some(backup) = &file.backup {
+            let _ = fs::remove_file(backup);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Hunk {
+    header: String,
+    old_start: usize,
+    old_count: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+    /// "\ No newline at end of file" after the last old or new line
+    old_no_newline: bool,
+    new_no_newline: bool,
+}
+
+#[derive(Debug)]
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+/// Path of a `---`/`+++` line, None for /dev/null
+fn diff_path(line: &str, strip: usize) -> Option<String> {
+    let path = line[4..].split('\t').next().unwrap_or("").trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let components: Vec<&str> = path.split('/').collect();
+    Some(components[strip.min(components.len() - 1)..].join("/"))
+}
+
+/// `(start, count)` of one side of a hunk header, such as `-12,3`
+fn hunk_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    Some((start.parse().ok()?, count.parse().ok()?))
+}
+
+fn parse_diff(diff: &str, strip: usize) -> Result<Vec<FilePatch>, CdeError> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("--- ")
+            && lines
+                .get(i + 1)
+                .is_some_and(|next| next.starts_with("+++ "))
+        {
+            files.push(FilePatch {
+                old_path: diff_path(line, strip),
+                new_path: diff_path(lines[i + 1], strip),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if !line.starts_with("@@ ") {
+            // Git headers, index lines and commentary
+            i += 1;
+            continue;
+        }
+        let malformed = || CdeError::parse("Malformed hunk header").with_line(i + 1);
+        let file = files.last_mut().ok_or_else(|| {
+            CdeError::parse("Hunk before any ---/+++ file header").with_line(i + 1)
+        })?;
+        let mut ranges = line[3..].split_whitespace();
+        let (old_start, old_count) = ranges
+            .next()
+            .and_then(|range| hunk_range(range.strip_prefix('-')?))
+            .ok_or_else(malformed)?;
+        let (_, new_count) = ranges
+            .next()
+            .and_then(|range| hunk_range(range.strip_prefix('+')?))
+            .ok_or_else(malformed)?;
+        let mut hunk = Hunk {
+            header: line.to_string(),
+            old_start,
+            old_count,
+            ..Hunk::default()
+        };
+        i += 1;
+        // The side the last line belonged to, for "\ No newline at end of file"
+        let mut last_side = ' ';
+        while i < lines.len() && (hunk.old.len() < old_count || hunk.new.len() < new_count) {
+            let line = lines[i];
+            match line.chars().next() {
+                Some(' ') | None => {
+                    let text = line.get(1..).unwrap_or("").to_string();
+                    hunk.old.push(text.clone());
+                    hunk.new.push(text);
+                    last_side = ' ';
+                }
+                Some('-') => {
+                    hunk.old.push(line[1..].to_string());
+                    last_side = '-';
+                }
+                Some('+') => {
+                    hunk.new.push(line[1..].to_string());
+                    last_side = '+';
+                }
+                Some('\\') => {}
+                _ => {
+                    return Err(CdeError::parse("Unexpected line in hunk").with_line(i + 1));
+                }
+            }
+            i += 1;
+        }
+        if hunk.old.len() != old_count || hunk.new.len() != new_count {
+            return Err(CdeError::parse("Hunk shorter than its header says").with_line(i));
+        }
+        if lines.get(i).is_some_and(|line| line.starts_with('\\')) {
+            match last_side {
+                '-' => hunk.old_no_newline = true,
+                '+' => hunk.new_no_newline = true,
+                _ => {
+                    hunk.old_no_newline = true;
+                    hunk.new_no_newline = true;
+                }
+            }
+            i += 1;
+        }
+        // A marker for the old side can be followed by the new side's lines
+        file.hunks.push(hunk);
+    }
+    Ok(files)
+}
+
+/// Lines of a text without their terminators, the terminator used, and whether
+/// the last line has one
+fn split_lines(text: &str) -> (Vec<String>, &'static str, bool) {
+    let eol = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let trailing_newline = text.ends_with('\n');
+    let lines = text
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect();
+    (lines, eol, trailing_newline || text.is_empty())
+}
+
+/// Applies `hunks` to `original`; the new text, or None if a hunk was rejected
+fn apply_hunks(
+    original: &str,
+    hunks: &[Hunk],
+    max_offset: usize,
+) -> (Option<String>, Vec<HunkResult>) {
+    let (mut lines, eol, mut trailing_newline) = split_lines(original);
+    let mut results = Vec::new();
+    let mut rejected = false;
+    // Lines added minus lines removed by the hunks applied so far
+    let mut delta: isize = 0;
+    // First line the next hunk may touch, in the patched text
+    let mut floor = 0;
+    for (index, hunk) in hunks.iter().enumerate() {
+        let stated = if hunk.old_count == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (stated as isize + delta).max(0) as usize;
+        let matches_at = |at: usize| {
+            at >= floor
+                && at + hunk.old.len() <= lines.len()
+                && lines[at..at + hunk.old.len()] == hunk.old[..]
+        };
+        let position = (0..=max_offset)
+            .flat_map(|distance| {
+                [
+                    expected.checked_sub(distance),
+                    expected.checked_add(distance),
+                ]
+            })
+            .flatten()
+            .find(|&at| matches_at(at));
+        let mut result = HunkResult {
+            index,
+            header: hunk.header.clone(),
+            status: "applied".to_string(),
+            applied_at: None,
+            offset: 0,
+            reason: None,
+            expected: Vec::new(),
+            found: Vec::new(),
+        };
+        match position {
+            Some(at) => {
+                result.applied_at = Some((at as isize - delta) as usize + 1);
+                result.offset = at as isize - expected as isize;
+                let touches_end = at + hunk.old.len() == lines.len();
+                lines.splice(at..at + hunk.old.len(), hunk.new.iter().cloned());
+                if touches_end {
+                    if hunk.new_no_newline {
+                        trailing_newline = false;
+                    } else if hunk.old_no_newline || !hunk.new.is_empty() {
+                        trailing_newline = true;
+                    }
+                }
+                delta += hunk.new.len() as isize - hunk.old.len() as isize;
+                floor = at + hunk.new.len();
+            }
+            None => {
+                rejected = true;
+                result.status = "rejected".to_string();
+                result.reason = Some(format!(
+                    "Context not found within {} lines of line {}",
+                    max_offset,
+                    stated + 1
+                ));
+                result.expected = hunk.old.clone();
+                let end = (expected + hunk.old.len()).min(lines.len());
+                result.found = lines[expected.min(end)..end].to_vec();
+            }
+        }
+        results.push(result);
+    }
+    if rejected {
+        return (None, results);
+    }
+    let mut text = lines.join(eol);
+    if trailing_newline && !lines.is_empty() {
+        text.push_str(eol);
+    }
+    (Some(text), results)
+}
+
+/// Applies the unified diff `diff` to the files under `root`
+pub fn apply_patch(
+    root: &str,
+    diff: &str,
+    options: &PatchOptions,
+) -> Result<PatchResult, CdeError> {
+    metrics::timed("apply_patch", || patch_files(root, diff, options))
+}
+
+fn patch_files(root: &str, diff: &str, options: &PatchOptions) -> Result<PatchResult, CdeError> {
+    let root = Path::new(root);
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    let patches = parse_diff(diff, options.strip)?;
+    if patches.is_empty() {
+        return Err(CdeError::invalid_input("No file changes found in the diff"));
+    }
+
+    let mut changes: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+    let mut files = Vec::new();
+    let mut rejected = false;
+    for patch in &patches {
+        let (path, status) = match (&patch.old_path, &patch.new_path) {
+            (_, Some(new)) if patch.old_path.is_none() => (new, "created"),
+            (Some(old), None) => (old, "deleted"),
+            (_, Some(new)) => (new, "modified"),
+            (None, None) => return Err(CdeError::parse("File header without a path")),
+        };
+        let target = resolve(Some(root), path)?;
+        let mut result = FilePatchResult {
+            path: path.clone(),
+            status: status.to_string(),
+            reason: None,
+            hunks: Vec::new(),
+        };
+        // A file patched twice in one diff continues from its first patch
+        let current = match changes.get(&target) {
+            Some(content) => Ok(content.clone()),
+            None => read_text(&target),
+        };
+        let original = match (current, status) {
+            (Err(reason), _) => Err(reason),
+            (Ok(Some(_)), "created") => Err("File already exists".to_string()),
+            (Ok(None), "modified" | "deleted") => Err("File does not exist".to_string()),
+            (Ok(content), _) => Ok(content.unwrap_or_default()),
+        };
+        match original {
+            Err(reason) => {
+                result.status = "rejected".to_string();
+                result.reason = Some(reason);
+                rejected = true;
+            }
+            Ok(original) => {
+                let (patched, hunks) = apply_hunks(&original, &patch.hunks, options.max_offset);
+                result.hunks = hunks;
+                match patched {
+                    None => {
+                        result.status = "rejected".to_string();
+                        result.reason = Some("Some hunks did not apply".to_string());
+                        rejected = true;
+                    }
+                    Some(patched) if status == "deleted" => {
+                        if patched.is_empty() {
+                            changes.insert(target, None);
+                        } else {
+                            result.status = "rejected".to_string();
+                            result.reason =
+                                Some("Deleted file still has content after the patch".to_string());
+                            rejected = true;
+                        }
+                    }
+                    Some(patched) => {
+                        changes.insert(target, Some(patched));
+                    }
+                }
+            }
+        }
+        files.push(result);
+    }
+
+    let applied = !rejected && !options.dry_run;
+    if applied {
+        commit(&changes)?;
+    }
+    Ok(PatchResult {
+        applied,
+        dry_run: options.dry_run,
+        files,
+    })
+}
+
+/// Applies `edits` in order, under `root` when given
+pub fn apply_file_edits(
+    edits: &[FileEdit],
+    root: Option<&str>,
+    dry_run: bool,
+) -> Result<EditsResult, CdeError> {
+    let root = root.map(Path::new);
+    if let Some(root) = root.filter(|root| !root.is_dir()) {
+        return Err(CdeError::not_a_directory(root));
+    }
+    let mut changes: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+    let mut results = Vec::new();
+    let mut rejected = false;
+    for (index, edit) in edits.iter().enumerate() {
+        let target = resolve(root, edit.path())?;
+        let current = match changes.get(&target) {
+            Some(content) => Ok(content.clone()),
+            None => read_text(&target),
+        };
+        let outcome = current.and_then(|current| match (edit, current) {
+            (FileEdit::Write { content, .. }, _) => Ok(Some(content.clone())),
+            (FileEdit::Delete { .. }, Some(_)) => Ok(None),
+            (
+                FileEdit::Replace {
+                    old, new, count, ..
+                },
+                Some(text),
+            ) => {
+                let found = if old.is_empty() {
+                    0
+                } else {
+                    text.matches(old.as_str()).count()
+                };
+                if found == *count {
+                    Ok(Some(text.replace(old.as_str(), new)))
+                } else {
+                    Err(format!(
+                        "Expected {} occurrence(s) of the old text, found {}",
+                        count, found
+                    ))
+                }
+            }
+            (_, None) => Err("File does not exist".to_string()),
+        });
+        let reason = match outcome {
+            Ok(content) => {
+                changes.insert(target, content);
+                None
+            }
+            Err(reason) => {
+                rejected = true;
+                Some(reason)
+            }
+        };
+        results.push(EditResult {
+            index,
+            path: edit.path().to_string(),
+            status: if reason.is_some() {
+                "rejected"
+            } else {
+                "applied"
+            }
+            .to_string(),
+            reason,
+        });
+    }
+
+    let applied = !rejected && !dry_run;
+    if applied {
+        commit(&changes)?;
+    }
+    Ok(EditsResult {
+        applied,
+        dry_run,
+        edits: results,
+    })
+}
+
+/// Applies a unified diff to the files under `root`, all or nothing
+///
+/// `options_json` may set `strip` (path components removed, default 1),
+/// `max_offset` (lines a hunk may have moved, default 200) and `dry_run`. Returns
+/// JSON with `applied` and, per file, its `status` ("modified", "created",
+/// "deleted" or "rejected") and its hunks; rejected hunks carry the `reason`, the
+/// `expected` lines and the lines `found` instead. Nothing is written unless
+/// every hunk applies.
+#[pyfunction]
+#[pyo3(signature = (root, unified_diff, options_json=None))]
+fn apply_patch_py(
+    py: Python<'_>,
+    root: &str,
+    unified_diff: &str,
+    options_json: Option<&str>,
+) -> PyResult<String> {
+    let options: PatchOptions = match options_json {
+        Some(json) => from_json("options", json)?,
+        None => PatchOptions::default(),
+    };
+    let result = py.detach(|| apply_patch(root, unified_diff, &options))?;
+    Ok(to_json(&result)?)
+}
+
+/// Applies file edits in order, all or nothing
+///
+/// `edits_json` is a list of `{"op": "write", "path", "content"}`,
+/// `{"op": "replace", "path", "old", "new", "count"=1}` (the old text must occur
+/// exactly `count` times) and `{"op": "delete", "path"}`. With `root`, paths are
+/// relative to it and may not leave it. Returns JSON with `applied` and the
+/// `status` and `reason` of each edit.
+#[pyfunction]
+#[pyo3(signature = (edits_json, root=None, dry_run=false))]
+fn apply_file_edits_py(
+    py: Python<'_>,
+    edits_json: &str,
+    root: Option<&str>,
+    dry_run: bool,
+) -> PyResult<String> {
+    let edits: Vec<FileEdit> = from_json("edits", edits_json)?;
+    let result = py.detach(|| apply_file_edits(&edits, root, dry_run))?;
+    Ok(to_json(&result)?)
+}
+
+/// Adds the patch functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(apply_patch_py, m)?)?;
+    m.add_function(wrap_pyfunction!(apply_file_edits_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,3 +2,3 @@
+ two
+-three
++THREE
+ four
+@@ -7,2 +7,3 @@
+ seven
+ eight
++nine
+--- /dev/null
++++ b/docs/new.md
+@@ -0,0 +1,2 @@
++# New
++text
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+\\ No newline at end of file
+";
+
+    #[test]
+    fn test_patch_applies_everywhere_or_nowhere() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        // Two lines were inserted at the top since the diff was made
+        let original = "zero\nhalf\none\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n";
+        fs::write(root.join("src/lib.rs"), original).unwrap();
+        fs::write(root.join("old.txt"), "gone").unwrap();
+        let root_path = root.to_str().unwrap();
+
+        let dry = PatchOptions {
+            dry_run: true,
+            ..PatchOptions::default()
+        };
+        let result = apply_patch(root_path, DIFF, &dry).unwrap();
+        assert!(!result.applied);
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            original
+        );
+
+        let result = apply_patch(root_path, DIFF, &PatchOptions::default()).unwrap();
+        assert!(result.applied);
+        let statuses: Vec<&str> = result.files.iter().map(|f| f.status.as_str()).collect();
+        assert_eq!(statuses, vec!["modified", "created", "deleted"]);
+        assert_eq!(result.files[0].hunks[0].offset, 2);
+        assert_eq!(result.files[0].hunks[0].applied_at, Some(4));
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            "zero\nhalf\none\ntwo\nTHREE\nfour\nfive\nsix\nseven\neight\nnine\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("docs/new.md")).unwrap(),
+            "# New\ntext\n"
+        );
+        assert!(!root.join("old.txt").exists());
+
+        // Applying again: the first hunk no longer finds "three", so nothing changes
+        fs::write(root.join("old.txt"), "gone").unwrap();
+        fs::remove_file(root.join("docs/new.md")).unwrap();
+        let before = fs::read_to_string(root.join("src/lib.rs")).unwrap();
+        let result = apply_patch(root_path, DIFF, &PatchOptions::default()).unwrap();
+        assert!(!result.applied);
+        let hunk = &result.files[0].hunks[0];
+        assert_eq!(hunk.status, "rejected");
+        assert_eq!(hunk.expected, vec!["two", "three", "four"]);
+        assert_eq!(fs::read_to_string(root.join("src/lib.rs")).unwrap(), before);
+        assert!(!root.join("docs/new.md").exists());
+        assert!(root.join("old.txt").exists());
+
+        assert!(apply_patch(root_path, "--- a/x\n+++ b/x\n@@ -1 +1 @@\n", &dry).is_err());
+        let escape = "--- a/../x\n+++ b/../x\n@@ -0,0 +1 @@\n+x\n";
+        assert!(apply_patch(root_path, escape, &dry).is_err());
+    }
+
+    #[test]
+    fn test_file_edits_are_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.txt"), "alpha beta").unwrap();
+        let root_path = Some(root.to_str().unwrap());
+        let edit = |json: &str| -> Vec<FileEdit> { serde_json::from_str(json).unwrap() };
+
+        let rejected = edit(
+            r#"[{"op": "replace", "path": "a.txt", "old": "beta", "new": "gamma"},
+                {"op": "write", "path": "b/c.txt", "content": "new"},
+                {"op": "replace", "path": "a.txt", "old": "missing", "new": "x"}]"#,
+        );
+        let result = apply_file_edits(&rejected, root_path, false).unwrap();
+        assert!(!result.applied);
+        assert_eq!(result.edits[2].status, "rejected");
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "alpha beta"
+        );
+        assert!(!root.join("b").exists());
+
+        let accepted = edit(
+            r#"[{"op": "replace", "path": "a.txt", "old": "beta", "new": "gamma"},
+                {"op": "replace", "path": "a.txt", "old": "a", "new": "A", "count": 4},
+                {"op": "write", "path": "b/c.txt", "content": "new"}]"#,
+        );
+        let result = apply_file_edits(&accepted, root_path, false).unwrap();
+        assert!(result.applied);
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "AlphA gAmmA"
+        );
+        assert_eq!(fs::read_to_string(root.join("b/c.txt")).unwrap(), "new");
+
+        let delete = edit(r#"[{"op": "delete", "path": "b/c.txt"}]"#);
+        assert!(apply_file_edits(&delete, root_path, false).unwrap().applied);
+        assert!(!root.join("b/c.txt").exists());
+        // No temporary or backup files are left behind
+        let leftovers: Vec<_> = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().contains(".cde-"))
+            .collect();
+        assert!(leftovers.is_empty());
+        let outside = edit(r#"[{"op": "write", "path": "/etc/x", "content": ""}]"#);
+        assert!(apply_file_edits(&outside, root_path, false).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+// src/path_policy.rs
+//! Which paths the core may touch
+//!
+//! Once the server sets a policy, every root and file argument is resolved
+//! (symlinks and `..` included) and refused unless it lies under one of the
+//! allowed roots and matches none of the denied globs. The walks of the project
+//! scanner, the documentation scanner and the file finders also skip denied
+//! entries, stop at `max_depth` below the allowed root, and do not follow a
+//! symlink out of the allowed roots. Without a policy nothing is restricted.
+//!
+//! A denied glob without `/` matches any path component (`.env`, `*.pem`); one
+//! with `/` matches the path relative to the allowed root (`secrets/**`).
+
+use crate::error::{to_json, CdeError};
+use glob::{MatchOptions, Pattern};
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+static POLICY: RwLock<Option<Arc<PathPolicy>>> = RwLock::new(None);
+
+/// Bumped by every `set_policy`
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathPolicy {
+    /// Canonical; empty allows every location
+    pub allowed_roots: Vec<PathBuf>,
+    pub denied_globs: Vec<String>,
+    /// Directory levels below the allowed root (or the walked root, without
+    /// allowed roots) a walk may descend
+    pub max_depth: Option<usize>,
+    #[serde(skip)]
+    denied: Vec<Pattern>,
+}
+
+/// `path` made absolute, with `..` and symlinks resolved as far as it exists
+pub(crate) fn resolve(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let absolute = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    let mut lexical = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                lexical.pop();
+            }
+            other => lexical.push(other),
+        }
+    }
+    // Resolve the part that exists, so a symlinked parent is seen through
+    let existing = lexical.ancestors().find(|ancestor| ancestor.exists());
+    match existing.and_then(|ancestor| Some((ancestor.canonicalize().ok()?, ancestor))) {
+        Some((canonical, ancestor)) => {
+            canonical.join(lexical.strip_prefix(ancestor).unwrap_or(Path::new("")))
+        }
+        None => lexical,
+    }
+}
+
+impl PathPolicy {
+    pub fn new(
+        allowed_roots: &[String],
+        denied_globs: &[String],
+        max_depth: Option<usize>,
+    ) -> Result<Self, CdeError> {
+        let allowed_roots = allowed_roots
+            .iter()
+            .map(|root| {
+                Path::new(root).canonicalize().map_err(|e| {
+                    CdeError::not_found("Allowed root does not exist")
+                        .with_path(root)
+                        .caused_by(&e)
+                })
+            })
+            .collect::<Result<Vec<PathBuf>, CdeError>>()?;
+        let denied = denied_globs
+            .iter()
+            .map(|glob| {
+                Pattern::new(glob).map_err(|e| {
+                    CdeError::invalid_input(format!("Invalid denied glob: {}", glob)).caused_by(&e)
+                })
+            })
+            .collect::<Result<Vec<Pattern>, CdeError>>()?;
+        Ok(Self {
+            allowed_roots,
+            denied_globs: denied_globs.to_vec(),
+            max_depth,
+            denied,
+        })
+    }
+
+    /// `resolved` relative to the allowed root containing it; None when outside
+    /// them all. Without allowed roots, the whole path.
+    fn relative<'a>(&self, resolved: &'a Path) -> Option<&'a Path> {
+        if self.allowed_roots.is_empty() {
+            return Some(resolved);
+        }
+        self.allowed_roots
+            .iter()
+            .find_map(|root| resolved.strip_prefix(root).ok())
+    }
+
+    /// Whether `relative` (to its allowed root) matches a denied glob
+    fn is_denied(&self, relative: &Path) -> bool {
+        let joined = relative.to_string_lossy().replace('\\', "/");
+        self.denied.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(&joined, MATCH_OPTIONS)
+            } else {
+                relative.components().any(|component| {
+                    pattern.matches_with(&component.as_os_str().to_string_lossy(), MATCH_OPTIONS)
+                })
+            }
+        })
+    }
+
+    /// Refuses `path` unless it is under an allowed root and not denied
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<(), CdeError> {
+        let path = path.as_ref();
+        let resolved = resolve(path);
+        let Some(relative) = self.relative(&resolved) else {
+            return Err(
+                CdeError::invalid_input("Path is outside the allowed roots").with_path(path)
+            );
+        };
+        if self.is_denied(relative) {
+            return Err(
+                CdeError::invalid_input("Path is denied by the path policy").with_path(path)
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks `root` and returns the guard of a walk below it
+    pub fn walk_guard(self: &Arc<Self>, root: &Path) -> Result<WalkGuard, CdeError> {
+        self.check(root)?;
+        let resolved = resolve(root);
+        let prefix = match self.allowed_roots.is_empty() {
+            true => PathBuf::new(),
+            false => self
+                .relative(&resolved)
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+        };
+        Ok(WalkGuard {
+            policy: Some(self.clone()),
+            root: root.to_path_buf(),
+            prefix,
+        })
+    }
+}
+
+/// Filters the entries of a walk under the policy in force when it started
+#[derive(Debug, Clone, Default)]
+pub struct WalkGuard {
+    policy: Option<Arc<PathPolicy>>,
+    root: PathBuf,
+    /// The walked root relative to its allowed root
+    prefix: PathBuf,
+}
+
+impl WalkGuard {
+    /// Whether the walk may yield (or descend into) `path`, an entry below the
+    /// walked root; `is_symlink` entries must also point inside the allowed roots
+    pub fn allows(&self, path: &Path, is_symlink: bool) -> bool {
+        let Some(policy) = &self.policy else {
+            return true;
+        };
+        let below = path.strip_prefix(&self.root).unwrap_or(path);
+        let relative = self.prefix.join(below);
+        if let Some(max_depth) = policy.max_depth {
+            // The depth of a file is that of its directory
+            if relative.components().count() > max_depth + 1 {
+                return false;
+            }
+        }
+        if policy.is_denied(&relative) {
+            return false;
+        }
+        !is_symlink || policy.check(path).is_ok()
+    }
+
+    /// Depth limit to give the walker, relative to the walked root
+    pub fn max_depth(&self) -> usize {
+        self.policy
+            .as_ref()
+            .and_then(|policy| policy.max_depth)
+            .map_or(usize::MAX, |max_depth| {
+                (max_depth + 1).saturating_sub(self.prefix.components().count())
+            })
+    }
+}
+
+/// The policy in force, if any
+pub fn current() -> Option<Arc<PathPolicy>> {
+    POLICY.read().unwrap().clone()
+}
+
+/// Installs `policy`, or lifts all restrictions with None
+pub fn set_policy(policy: Option<PathPolicy>) {
+    *POLICY.write().unwrap() = policy.map(Arc::new);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Changes whenever a policy is installed or lifted; caches of walk results
+/// read it before taking their walk guard and key their entries with it
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Refuses `path` under the policy in force
+pub fn check(path: impl AsRef<Path>) -> Result<(), CdeError> {
+    match current() {
+        Some(policy) => policy.check(path),
+        None => Ok(()),
+    }
+}
+
+/// Checks `root` under the policy in force and returns the guard of a walk below it
+pub fn walk_guard(root: &Path) -> Result<WalkGuard, CdeError> {
+    match current() {
+        Some(policy) => policy.walk_guard(root),
+        None => Ok(WalkGuard::default()),
+    }
+}
+
+/// Runs `work` with a policy denying every path through a `denied` component,
+/// one caller at a time so tests installing policies do not lift each other's
+#[cfg(test)]
+pub(crate) fn with_denied<T>(denied: &str, work: impl FnOnce() -> T) -> T {
+    static INSTALLED: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = INSTALLED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_policy(Some(
+        PathPolicy::new(&[], &[denied.to_string()], None).unwrap(),
+    ));
+    let result = work();
+    set_policy(None);
+    result
+}
+
+/// Restricts every filesystem access of the core to `allowed_roots`
+///
+/// Paths matching `denied_globs` are refused or skipped even under an allowed
+/// root: a glob without `/` matches any path component (".env", "*.pem"), one
+/// with `/` the path relative to the allowed root ("secrets/**"). Walks stop
+/// `max_depth` directory levels below the allowed root. Arguments outside the
+/// policy raise `InvalidInputError`. Calling it without arguments lifts the
+/// policy.
+#[pyfunction]
+#[pyo3(signature = (allowed_roots=vec![], denied_globs=vec![], max_depth=None))]
+fn set_path_policy_py(
+    allowed_roots: Vec<String>,
+    denied_globs: Vec<String>,
+    max_depth: Option<usize>,
+) -> PyResult<()> {
+    if allowed_roots.is_empty() && denied_globs.is_empty() && max_depth.is_none() {
+        set_policy(None);
+    } else {
+        set_policy(Some(PathPolicy::new(
+            &allowed_roots,
+            &denied_globs,
+            max_depth,
+        )?));
+    }
+    Ok(())
+}
+
+/// The path policy in force as JSON (`allowed_roots` canonicalized,
+/// `denied_globs`, `max_depth`), or None
+#[pyfunction]
+fn get_path_policy_py() -> PyResult<Option<String>> {
+    Ok(current().map(|policy| to_json(&*policy)).transpose()?)
+}
+
+/// Adds the path policy functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(set_path_policy_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_path_policy_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // The policy is only ever installed by the server: installing one here would
+    // restrict the tests running alongside, so the policy is exercised directly
+    #[test]
+    fn test_policy_confines_paths_and_walks() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src/deep/deeper")).unwrap();
+        fs::create_dir_all(project.join("secrets")).unwrap();
+        fs::write(dir.path().join("outside.txt"), "").unwrap();
+        let roots = vec![project.to_string_lossy().into_owned()];
+        let denied = vec![".env".to_string(), "secrets/**".to_string()];
+        let policy = Arc::new(PathPolicy::new(&roots, &denied, Some(2)).unwrap());
+
+        assert!(policy.check(project.join("src")).is_ok());
+        assert!(policy.check(project.join("src/new_file.rs")).is_ok());
+        // Hostile paths are resolved before they are compared
+        assert!(policy.check(project.join("src/../../outside.txt")).is_err());
+        assert!(policy.check(project.join("../../etc/passwd")).is_err());
+        assert!(policy.check("/etc").is_err());
+        assert!(policy.check(project.join("src/.env")).is_err());
+        assert!(policy.check(project.join("secrets/key.pem")).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = project.join("src/escape");
+            std::os::unix::fs::symlink(dir.path(), &link).unwrap();
+            assert!(policy.check(link.join("outside.txt")).is_err());
+            let guard = policy.walk_guard(&project).unwrap();
+            assert!(!guard.allows(&link, true));
+        }
+
+        let guard = policy.walk_guard(&project.join("src")).unwrap();
+        assert!(guard.allows(&project.join("src/deep/a.rs"), false));
+        assert!(!guard.allows(&project.join("src/deep/deeper/a.rs"), false));
+        assert!(!guard.allows(&project.join("src/deep/.env"), false));
+        assert_eq!(guard.max_depth(), 2);
+        assert!(policy.walk_guard(dir.path()).is_err());
+        assert!(WalkGuard::default().allows(Path::new("/etc/passwd"), true));
+        assert!(PathPolicy::new(&["/no/such/root".to_string()], &[], None).is_err());
+    }
+
+    // Denying only a component no other test uses leaves the tests running
+    // alongside unrestricted
+    #[test]
+    fn test_installed_policy_confines_entry_points() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("policy-denied-project");
+        fs::create_dir_all(&project).unwrap();
+        let template = project.join("prompt.j2");
+        fs::write(&template, "{{ name }}").unwrap();
+        let root = project.to_str().unwrap();
+
+        let denied = with_denied("policy-denied-project", || {
+            [
+                crate::workflow_validator::validate_workflows(root).err(),
+                crate::import_graph::build_import_graph(root, &[]).err(),
+                crate::command_inference::infer_project_commands(root).err(),
+                crate::templating::render_template(template.to_str().unwrap(), "{}", false).err(),
+            ]
+        });
+        for error in denied {
+            assert!(
+                matches!(error, Some(CdeError::InvalidInput(_))),
+                "{error:?}"
+            );
+        }
+    }
+}
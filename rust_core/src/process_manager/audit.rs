@@ -0,0 +1,192 @@
+// rust_core/src/process_manager/audit.rs
+//! Dry-run audit mode
+//!
+//! While audit mode is on (`set_audit_mode_py` or the `CDE_AGENT_AUDIT_LOG`
+//! environment variable), spawn functions execute nothing. Each agent's fully
+//! resolved program, arguments, working directory and environment are recorded
+//! instead, in memory and optionally as JSON Lines, and callers get synthetic
+//! "dry_run" results. This lets a reviewer see exactly what the orchestrator would
+//! run in an environment before it runs anything there.
+
+use super::options::SpawnOptions;
+use super::secrets;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// Environment variable enabling audit mode, logging to its path, when the server starts
+pub const AUDIT_LOG_ENV: &str = "CDE_AGENT_AUDIT_LOG";
+
+/// Entries kept in memory for `get_audit_log_py`
+const MAX_KEPT_ENTRIES: usize = 1000;
+
+/// Value recorded for the `<NAME>_FILE` variables of secrets
+const SECRET_FILE_PLACEHOLDER: &str = "<secret file>";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    /// Command as requested by the caller
+    pub command: Vec<String>,
+    /// Program and arguments after resolution (PATHEXT shims on Windows)
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    /// Whether the agent inherits the server environment (no `env_whitelist`)
+    pub inherits_env: bool,
+    /// Variables set explicitly for the agent, secret values redacted
+    pub env: BTreeMap<String, String>,
+    pub cpu_time_secs: Option<u64>,
+    pub max_memory_mb: Option<u64>,
+    pub disable_network: bool,
+    pub hard_timeout_secs: Option<f64>,
+    /// Source tree copied into a private workspace, when the agent gets one
+    pub workspace_source: Option<String>,
+}
+
+impl AuditEntry {
+    /// Describes what spawning `command` (built from `cmd` and `options`) would run
+    pub fn new(cmd: &[String], command: &Command, options: &SpawnOptions) -> Self {
+        let redactions = options.redactions();
+        let mut env: BTreeMap<String, String> = command
+            .get_envs()
+            .filter_map(|(name, value)| {
+                Some((
+                    name.to_string_lossy().into_owned(),
+                    secrets::redact(&value?.to_string_lossy(), &redactions),
+                ))
+            })
+            .collect();
+        for name in options.secrets.keys() {
+            env.insert(
+                format!("{}_FILE", name),
+                SECRET_FILE_PLACEHOLDER.to_string(),
+            );
+        }
+        let cwd = command
+            .get_current_dir()
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_dir().ok())
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            command: cmd.to_vec(),
+            program: command.get_program().to_string_lossy().into_owned(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            workspace_source: options
+                .workspace
+                .as_ref()
+                .map(|workspace| workspace.source.clone().unwrap_or_else(|| cwd.clone())),
+            cwd,
+            inherits_env: options.env_whitelist.is_none(),
+            env,
+            cpu_time_secs: options.cpu_time_secs,
+            max_memory_mb: options.max_memory_mb,
+            disable_network: options.disable_network,
+            hard_timeout_secs: options.hard_timeout_secs,
+        }
+    }
+}
+
+struct AuditState {
+    enabled: bool,
+    log_path: Option<PathBuf>,
+    entries: VecDeque<AuditEntry>,
+}
+
+fn audit_state() -> &'static Mutex<AuditState> {
+    static STATE: OnceLock<Mutex<AuditState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        let log_path = std::env::var_os(AUDIT_LOG_ENV).map(PathBuf::from);
+        Mutex::new(AuditState {
+            enabled: log_path.is_some(),
+            log_path,
+            entries: VecDeque::new(),
+        })
+    })
+}
+
+pub fn is_enabled() -> bool {
+    audit_state().lock().unwrap().enabled
+}
+
+/// Turns audit mode on or off; `log_path` also appends entries to a JSON Lines file
+pub fn set_mode(enabled: bool, log_path: Option<PathBuf>) {
+    let mut state = audit_state().lock().unwrap();
+    state.enabled = enabled;
+    state.log_path = log_path;
+}
+
+/// Keeps `entry` and appends it to the log file, if one is set
+pub fn record(entry: AuditEntry) -> io::Result<()> {
+    let mut state = audit_state().lock().unwrap();
+    if let Some(path) = &state.log_path {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())?;
+    }
+    if state.entries.len() >= MAX_KEPT_ENTRIES {
+        state.entries.pop_front();
+    }
+    state.entries.push_back(entry);
+    Ok(())
+}
+
+/// Entries recorded since the server started, oldest first; `clear` forgets them
+pub fn entries(clear: bool) -> Vec<AuditEntry> {
+    let mut state = audit_state().lock().unwrap();
+    if clear {
+        state.entries.drain(..).collect()
+    } else {
+        state.entries.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_resolves_command_and_redacts_secrets() {
+        let mut options = SpawnOptions::from_json(Some(
+            r#"{"cwd": ".", "env_whitelist": ["PATH"], "env": {"MODE": "review"},
+                "secrets": {"API_KEY": "sk-123"}, "hard_timeout_secs": 60}"#,
+        ))
+        .unwrap();
+        options
+            .env
+            .insert("LEAKY".to_string(), "token=sk-123".to_string());
+        let cmd = vec!["agent".to_string(), "--task".to_string(), "fix".to_string()];
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]);
+        options.apply(&mut command);
+
+        let entry = AuditEntry::new(&cmd, &command, &options);
+
+        assert_eq!(entry.program, "agent");
+        assert_eq!(entry.args, vec!["--task", "fix"]);
+        assert_eq!(entry.cwd, ".");
+        assert!(!entry.inherits_env);
+        assert_eq!(entry.env["MODE"], "review");
+        assert_eq!(entry.env["LEAKY"], format!("token={}", secrets::REDACTED));
+        assert_eq!(entry.env["API_KEY_FILE"], SECRET_FILE_PLACEHOLDER);
+        assert_eq!(entry.hard_timeout_secs, Some(60.0));
+        assert_eq!(entry.workspace_source, None);
+    }
+}
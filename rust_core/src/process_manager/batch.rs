@@ -0,0 +1,565 @@
+// rust_core/src/process_manager/batch.rs
+//! Batch execution with an aggregated outcome
+//!
+//! Every command of a batch is queued at once; results are collected as agents
+//! finish. As soon as the batch policy decides the outcome (a failure under
+//! fail-fast, quorum reached or out of reach), agents that have not finished are
+//! cancelled: queued jobs are dropped and running ones have their tree killed.
+//! Cancelling the batch's `CancellationToken` does the same to every agent left.
+//! A batch started with an `op_id` checkpoints each result, and resuming it runs
+//! only the commands that did not complete.
+
+use super::options::SpawnOptions;
+use super::registry::{self, OutputOptions};
+use super::scheduler::{scheduler, Job, JobState};
+use super::{audit, supervisor, tree, AgentResult};
+use crate::cancel::CancellationToken;
+use crate::checkpoint::{Checkpointer, OpStatus};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a batch waiting for results checks its cancellation token
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How the outcome of a batch is decided
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchPolicy {
+    pub mode: String, // "fail-fast", "continue", "quorum"
+    /// Successful agents needed for the batch to succeed
+    pub required: usize,
+}
+
+impl BatchPolicy {
+    pub fn new(mode: &str, quorum: Option<usize>, total: usize) -> Result<Self, String> {
+        let required = match (mode, quorum) {
+            ("fail-fast" | "continue", None) => total,
+            ("quorum", Some(quorum)) if (1..=total).contains(&quorum) => quorum,
+            ("quorum", Some(quorum)) => {
+                return Err(format!(
+                    "Invalid quorum {}: expected 1 to {} successes",
+                    quorum, total
+                ))
+            }
+            ("quorum", None) => return Err("Quorum policy requires 'quorum'".to_string()),
+            ("fail-fast" | "continue", Some(_)) => {
+                return Err(format!(
+                    "'quorum' is only valid with the quorum policy, not '{}'",
+                    mode
+                ))
+            }
+            (other, _) => {
+                return Err(format!(
+                    "Invalid batch policy '{}': expected fail-fast, continue or quorum",
+                    other
+                ))
+            }
+        };
+        Ok(Self {
+            mode: mode.to_string(),
+            required,
+        })
+    }
+
+    /// The batch outcome once the results so far settle it, `None` while undecided
+    fn decide(&self, total: usize, succeeded: usize, failed: usize) -> Option<bool> {
+        if succeeded >= self.required {
+            Some(true)
+        } else if total - failed < self.required {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Whether agents still running are cancelled once the outcome is decided
+    fn cancels_remaining(&self) -> bool {
+        self.mode != "continue"
+    }
+}
+
+/// Aggregated outcome of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub policy: String,
+    pub status: String, // "succeeded", "failed", "cancelled" by the token, "dry_run" in audit mode
+    pub required: usize,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub duration_ms: u128,
+    /// One result per command, in command order; cancelled agents have status "cancelled"
+    pub results: Vec<AgentResult>,
+}
+
+/// Runs all commands under `policy` and aggregates their results; once `cancel`
+/// is cancelled, the agents that have not finished are cancelled
+pub fn run_batch(
+    commands: &[Vec<String>],
+    options: &SpawnOptions,
+    output: &OutputOptions,
+    policy: &BatchPolicy,
+    cancel: &CancellationToken,
+) -> BatchReport {
+    run_batch_with(commands, options, output, policy, cancel, &|_, _| {})
+}
+
+/// `run_batch`, calling `on_result` with the index and result of each command
+/// as it finishes
+pub fn run_batch_with(
+    commands: &[Vec<String>],
+    options: &SpawnOptions,
+    output: &OutputOptions,
+    policy: &BatchPolicy,
+    cancel: &CancellationToken,
+    on_result: &dyn Fn(usize, &AgentResult),
+) -> BatchReport {
+    let started = Instant::now();
+    let total = commands.len();
+    if audit::is_enabled() {
+        return dry_run_report(commands, options, policy, started);
+    }
+    let job_ids: Vec<Option<u64>> = commands
+        .iter()
+        .map(|cmd| {
+            (!cmd.is_empty()).then(|| {
+                scheduler().submit(Job {
+                    command: cmd.clone(),
+                    options: options.clone(),
+                    output: output.clone(),
+                })
+            })
+        })
+        .collect();
+
+    let mut results: Vec<Option<AgentResult>> = vec![None; total];
+    let (mut succeeded, mut failed, mut cancelled) = (0, 0, 0);
+    let mut interrupted = false;
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for (index, (cmd, job_id)) in commands.iter().zip(&job_ids).enumerate() {
+            let sender = sender.clone();
+            scope.spawn(move || {
+                let result = match job_id {
+                    Some(job_id) => super::await_agent(cmd, *job_id, options),
+                    None => super::failed_result(cmd, "empty"),
+                };
+                let _ = sender.send((index, result));
+            });
+        }
+        drop(sender);
+
+        let mut cancelling = false;
+        loop {
+            let (index, mut result) = match receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(received) => received,
+                Err(RecvTimeoutError::Timeout) => {
+                    if !cancelling && cancel.is_cancelled() {
+                        cancelling = true;
+                        interrupted = true;
+                        cancel_unfinished(&results, &job_ids);
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if result.status == "completed" {
+                succeeded += 1;
+            } else if cancelling {
+                result.status = "cancelled".to_string();
+                cancelled += 1;
+            } else {
+                failed += 1;
+            }
+            on_result(index, &result);
+            results[index] = Some(result);
+
+            if cancelling {
+                continue;
+            }
+            if cancel.is_cancelled() {
+                cancelling = true;
+                interrupted = true;
+                cancel_unfinished(&results, &job_ids);
+            } else if policy.cancels_remaining()
+                && policy.decide(total, succeeded, failed).is_some()
+            {
+                cancelling = true;
+                cancel_unfinished(&results, &job_ids);
+            }
+        }
+    });
+
+    BatchReport {
+        policy: policy.mode.clone(),
+        status: if succeeded >= policy.required {
+            "succeeded"
+        } else if interrupted {
+            "cancelled"
+        } else {
+            "failed"
+        }
+        .to_string(),
+        required: policy.required,
+        total,
+        succeeded,
+        failed,
+        cancelled,
+        duration_ms: started.elapsed().as_millis(),
+        results: results
+            .into_iter()
+            .zip(commands)
+            .map(|(result, cmd)| result.unwrap_or_else(|| super::failed_result(cmd, "panicked")))
+            .collect(),
+    }
+}
+
+/// What a batch started with an `op_id` needs to run again, kept in its checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchParams {
+    pub commands: Vec<Vec<String>>,
+    pub policy: String,
+    pub quorum: Option<usize>,
+    /// Spawn options without secret values, which are never checkpointed
+    pub options: SpawnOptions,
+    /// Names of the secrets the batch was given
+    pub secret_names: Vec<String>,
+}
+
+impl BatchParams {
+    pub fn new(
+        commands: &[Vec<String>],
+        policy: &str,
+        quorum: Option<usize>,
+        options: &SpawnOptions,
+    ) -> Self {
+        let mut options = options.clone();
+        let secret_names = options.secrets.keys().cloned().collect();
+        options.secrets.clear();
+        Self {
+            commands: commands.to_vec(),
+            policy: policy.to_string(),
+            quorum,
+            options,
+            secret_names,
+        }
+    }
+}
+
+/// Checkpoint state of a batch started with an `op_id`: one result per
+/// command, None until it finishes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub results: Vec<Option<AgentResult>>,
+}
+
+/// Runs the commands of `checkpointer`'s batch that have not completed, writing
+/// each result to the checkpoint as it finishes, and reports on the whole batch
+///
+/// Results that completed in an earlier run are kept; the policy only asks the
+/// commands run again for the successes still missing.
+pub fn run_checkpointed(
+    params: &BatchParams,
+    options: &SpawnOptions,
+    output: &OutputOptions,
+    cancel: &CancellationToken,
+    checkpointer: Checkpointer,
+) -> Result<BatchReport, String> {
+    let started = Instant::now();
+    let total = params.commands.len();
+    let policy = BatchPolicy::new(&params.policy, params.quorum, total)?;
+    let mut progress: BatchProgress = serde_json::from_value(checkpointer.checkpoint().state)
+        .map_err(|e| format!("Invalid batch checkpoint: {}", e))?;
+    if progress.results.is_empty() {
+        progress.results = vec![None; total];
+    } else if progress.results.len() != total {
+        return Err(format!(
+            "Invalid batch checkpoint: {} results for {} commands",
+            progress.results.len(),
+            total
+        ));
+    }
+
+    let completed = |result: &Option<AgentResult>| {
+        result
+            .as_ref()
+            .is_some_and(|result| result.status == "completed")
+    };
+    let pending: Vec<usize> = (0..total)
+        .filter(|&index| !completed(&progress.results[index]))
+        .collect();
+    let already_succeeded = total - pending.len();
+
+    let mut status = "succeeded".to_string();
+    if already_succeeded < policy.required {
+        let quorum = params.quorum.map(|_| policy.required - already_succeeded);
+        let remaining = BatchPolicy::new(&params.policy, quorum, pending.len())?;
+        let commands: Vec<Vec<String>> = pending
+            .iter()
+            .map(|&index| params.commands[index].clone())
+            .collect();
+
+        let results = Mutex::new(progress.results);
+        let report = run_batch_with(
+            &commands,
+            options,
+            output,
+            &remaining,
+            cancel,
+            &|local, result| {
+                let mut results = results.lock().unwrap();
+                results[pending[local]] = Some(result.clone());
+                let processed = results.iter().filter(|result| result.is_some()).count();
+                let state = serde_json::to_value(BatchProgress {
+                    results: results.clone(),
+                });
+                checkpointer.update(|checkpoint| {
+                    checkpoint.processed = processed;
+                    if let Ok(state) = state {
+                        checkpoint.state = state;
+                    }
+                });
+            },
+        );
+        // Agents cancelled without a result never reached `on_result`
+        progress.results = results.into_inner().unwrap();
+        for (local, result) in report.results.into_iter().enumerate() {
+            progress.results[pending[local]] = Some(result);
+        }
+        status = report.status;
+    }
+
+    let results: Vec<AgentResult> = progress
+        .results
+        .iter()
+        .zip(&params.commands)
+        .map(|(result, cmd)| {
+            result
+                .clone()
+                .unwrap_or_else(|| super::failed_result(cmd, "panicked"))
+        })
+        .collect();
+    let succeeded = results.iter().filter(|r| r.status == "completed").count();
+    let cancelled = results.iter().filter(|r| r.status == "cancelled").count();
+    let op_status = match status.as_str() {
+        "failed" => OpStatus::Failed,
+        "cancelled" => OpStatus::Cancelled,
+        _ => OpStatus::Completed,
+    };
+    checkpointer.update(|checkpoint| {
+        checkpoint.processed = total;
+        if let Ok(state) = serde_json::to_value(&progress) {
+            checkpoint.state = state;
+        }
+    });
+    checkpointer.finish(op_status);
+
+    Ok(BatchReport {
+        policy: policy.mode,
+        status,
+        required: policy.required,
+        total,
+        succeeded,
+        failed: total - succeeded - cancelled,
+        cancelled,
+        duration_ms: started.elapsed().as_millis(),
+        results,
+    })
+}
+
+/// Records every command of the batch without running any (audit mode)
+fn dry_run_report(
+    commands: &[Vec<String>],
+    options: &SpawnOptions,
+    policy: &BatchPolicy,
+    started: Instant,
+) -> BatchReport {
+    let results: Vec<AgentResult> = commands
+        .iter()
+        .map(|cmd| {
+            if cmd.is_empty() {
+                super::failed_result(cmd, "empty")
+            } else {
+                super::dry_run_result(cmd, options)
+            }
+        })
+        .collect();
+    BatchReport {
+        policy: policy.mode.clone(),
+        status: "dry_run".to_string(),
+        required: policy.required,
+        total: commands.len(),
+        succeeded: 0,
+        failed: results.iter().filter(|r| r.status != "dry_run").count(),
+        cancelled: 0,
+        duration_ms: started.elapsed().as_millis(),
+        results,
+    }
+}
+
+/// Cancels the jobs of the commands without a result yet
+fn cancel_unfinished(results: &[Option<AgentResult>], job_ids: &[Option<u64>]) {
+    for (result, job_id) in results.iter().zip(job_ids) {
+        if let (None, Some(job_id)) = (result, job_id) {
+            cancel_job(*job_id);
+        }
+    }
+}
+
+/// Drops a queued job, or stops and kills the agent a started job became
+fn cancel_job(job_id: u64) {
+    if scheduler().cancel(job_id) {
+        return;
+    }
+    if let Some((JobState::Running(pid), _)) = scheduler().job_state(job_id) {
+        let pid = supervisor::agent_for_pid(pid)
+            .and_then(supervisor::stop)
+            .unwrap_or(pid);
+        tree::kill_tree(pid);
+        registry::kill(pid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_policy_decisions() {
+        let fail_fast = BatchPolicy::new("fail-fast", None, 3).unwrap();
+        assert_eq!(fail_fast.decide(3, 2, 0), None);
+        assert_eq!(fail_fast.decide(3, 0, 1), Some(false));
+        assert_eq!(fail_fast.decide(3, 3, 0), Some(true));
+
+        let quorum = BatchPolicy::new("quorum", Some(2), 4).unwrap();
+        assert_eq!(quorum.decide(4, 1, 2), None);
+        assert_eq!(quorum.decide(4, 2, 0), Some(true));
+        assert_eq!(quorum.decide(4, 1, 3), Some(false));
+
+        assert!(!BatchPolicy::new("continue", None, 2)
+            .unwrap()
+            .cancels_remaining());
+        assert!(BatchPolicy::new("quorum", None, 2).is_err());
+        assert!(BatchPolicy::new("quorum", Some(3), 2).is_err());
+        assert!(BatchPolicy::new("fail-fast", Some(1), 2).is_err());
+        assert!(BatchPolicy::new("best-effort", None, 2).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fail_fast_cancels_remaining_agents() {
+        let sh = |script: &str| vec!["sh".to_string(), "-c".to_string(), script.to_string()];
+        let commands = vec![sh("exit 1"), sh("sleep 10"), sh("echo ok")];
+        let policy = BatchPolicy::new("fail-fast", None, commands.len()).unwrap();
+
+        let report = run_batch(
+            &commands,
+            &SpawnOptions::default(),
+            &OutputOptions::default(),
+            &policy,
+            &CancellationToken::default(),
+        );
+
+        assert_eq!(report.status, "failed");
+        assert_eq!(report.results[0].status, "failed");
+        assert_eq!(report.results[1].status, "cancelled");
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.succeeded + report.cancelled, 2);
+        assert!(report.duration_ms < 5_000);
+
+        let quorum = BatchPolicy::new("quorum", Some(1), 2).unwrap();
+        let report = run_batch(
+            &[sh("echo ok"), sh("sleep 10")],
+            &SpawnOptions::default(),
+            &OutputOptions::default(),
+            &quorum,
+            &CancellationToken::default(),
+        );
+        assert_eq!(report.status, "succeeded");
+        assert_eq!(report.results[0].stdout, "ok\n");
+        assert_eq!(report.results[1].status, "cancelled");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancelled_token_stops_the_batch() {
+        let sh = |script: &str| vec!["sh".to_string(), "-c".to_string(), script.to_string()];
+        let commands = vec![sh("sleep 10"), sh("sleep 10")];
+        let policy = BatchPolicy::new("continue", None, commands.len()).unwrap();
+        let cancel = CancellationToken::default();
+
+        let report = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(300));
+                cancel.cancel();
+            });
+            run_batch(
+                &commands,
+                &SpawnOptions::default(),
+                &OutputOptions::default(),
+                &policy,
+                &cancel,
+            )
+        });
+
+        assert_eq!(report.status, "cancelled");
+        assert_eq!(report.cancelled, 2);
+        assert!(report.duration_ms < 5_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_checkpointed_batch_runs_only_incomplete_commands() {
+        use crate::checkpoint::Checkpoint;
+        use std::sync::Arc;
+
+        let sh = |script: &str| vec!["sh".to_string(), "-c".to_string(), script.to_string()];
+        let params = BatchParams::new(
+            &[sh("exit 1"), sh("echo again")],
+            "continue",
+            None,
+            &SpawnOptions::default(),
+        );
+        let previous = AgentResult {
+            status: "completed".to_string(),
+            stdout: "first run\n".to_string(),
+            ..super::failed_result(&params.commands[0], "")
+        };
+        let progress = BatchProgress {
+            results: vec![Some(previous), None],
+        };
+        let saved = Arc::new(std::sync::Mutex::new(None));
+        let sink = saved.clone();
+        let checkpointer = Checkpointer::new(
+            Checkpoint::new(
+                "batch-resume-test",
+                crate::checkpoint::AGENT_BATCH_KIND,
+                None,
+                serde_json::to_value(&params).unwrap(),
+                serde_json::to_value(&progress).unwrap(),
+            ),
+            Box::new(move |cp| *sink.lock().unwrap() = Some(cp.clone())),
+        )
+        .unwrap();
+
+        let report = run_checkpointed(
+            &params,
+            &params.options,
+            &OutputOptions::default(),
+            &CancellationToken::default(),
+            checkpointer,
+        )
+        .unwrap();
+
+        assert_eq!(report.status, "succeeded");
+        assert_eq!(report.succeeded, 2);
+        assert_eq!(report.results[0].stdout, "first run\n");
+        assert_eq!(report.results[1].stdout, "again\n");
+        let saved = saved.lock().unwrap().clone().unwrap();
+        assert_eq!(saved.status, OpStatus::Completed);
+        assert_eq!(saved.processed, 2);
+    }
+}
@@ -0,0 +1,230 @@
+// rust_core/src/process_manager/executable.rs
+//! Executable resolution for agent commands
+//!
+//! On Unix `execvp` already searches `PATH`. Windows `CreateProcess` only appends
+//! `.exe`, so npm-style shims (`npx.cmd`, `gh.cmd`) and PowerShell scripts are not
+//! found by name the way a shell finds them. There, commands are resolved against
+//! `PATH` and `PATHEXT` first: `.ps1` scripts run through PowerShell, batch files are
+//! left to std (which runs them through `cmd.exe` with safe argument quoting) and
+//! executables beyond `MAX_PATH` get a verbatim `\\?\` prefix.
+
+// The Windows helpers are compiled everywhere so their tests run on every platform
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use std::path::{Path, PathBuf};
+
+/// Used when `PATHEXT` is not set
+pub const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// Path length from which Windows APIs need the `\\?\` prefix
+const MAX_PATH: usize = 260;
+
+/// Variables asking common agent runtimes to write UTF-8 to pipes instead of the
+/// ANSI code page; captured output is decoded as UTF-8
+pub const UTF8_ENV: [(&str, &str); 2] = [("PYTHONUTF8", "1"), ("PYTHONIOENCODING", "utf-8")];
+
+/// Program and arguments to hand to `Command` for `cmd`
+#[cfg(windows)]
+pub fn resolve_command(cmd: &[String]) -> (PathBuf, Vec<String>) {
+    let search_dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| DEFAULT_PATHEXT.to_string());
+    resolve(cmd, &search_dirs, &pathext, |path| path.is_file())
+}
+
+/// Program and arguments to hand to `Command` for `cmd`
+#[cfg(not(windows))]
+pub fn resolve_command(cmd: &[String]) -> (PathBuf, Vec<String>) {
+    (PathBuf::from(&cmd[0]), cmd[1..].to_vec())
+}
+
+/// Full path of the file the platform would run for `program`, if any
+pub fn which(program: &str) -> Option<PathBuf> {
+    let search_dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if cfg!(windows) {
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| DEFAULT_PATHEXT.to_string());
+        return find_executable(program, &search_dirs, &pathext, |path| path.is_file());
+    }
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            path.metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        path.is_file()
+    };
+    if program.contains('/') {
+        return is_executable(Path::new(program)).then(|| PathBuf::from(program));
+    }
+    search_dirs
+        .iter()
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Windows resolution of `cmd` against `search_dirs` and `pathext`
+///
+/// Unresolvable programs are passed through unchanged so the spawn error names them.
+pub fn resolve(
+    cmd: &[String],
+    search_dirs: &[PathBuf],
+    pathext: &str,
+    is_file: impl Fn(&Path) -> bool,
+) -> (PathBuf, Vec<String>) {
+    let args = cmd[1..].to_vec();
+    let Some(program) = find_executable(&cmd[0], search_dirs, pathext, is_file) else {
+        return (PathBuf::from(&cmd[0]), args);
+    };
+
+    match extension(&program.to_string_lossy()).as_deref() {
+        Some("ps1") => {
+            let mut script_args: Vec<String> = [
+                "-NoProfile",
+                "-NonInteractive",
+                "-ExecutionPolicy",
+                "Bypass",
+                "-File",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+            script_args.push(program.to_string_lossy().into_owned());
+            script_args.extend(args);
+            (PathBuf::from("powershell.exe"), script_args)
+        }
+        // cmd.exe does not accept verbatim paths, so batch files keep theirs
+        Some("bat" | "cmd") => (program, args),
+        _ => (long_path(&program), args),
+    }
+}
+
+/// The file Windows would run for `program`, trying each `PATHEXT` extension
+/// (and `.ps1`) when the name has none
+pub fn find_executable(
+    program: &str,
+    search_dirs: &[PathBuf],
+    pathext: &str,
+    is_file: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    let mut extensions: Vec<String> = pathext
+        .split(';')
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if !extensions.iter().any(|ext| ext == ".ps1") {
+        extensions.push(".ps1".to_string());
+    }
+
+    let try_base = |base: &str| -> Option<PathBuf> {
+        if extension(base).is_some() && is_file(Path::new(base)) {
+            return Some(PathBuf::from(base));
+        }
+        extensions
+            .iter()
+            .map(|ext| PathBuf::from(format!("{}{}", base, ext)))
+            .find(|candidate| is_file(candidate))
+    };
+
+    if program.contains(['/', '\\']) {
+        return try_base(program);
+    }
+    search_dirs
+        .iter()
+        .find_map(|dir| try_base(&dir.join(program).to_string_lossy()))
+}
+
+/// Lowercased extension of the last path component, split on both separators
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match name.rfind('.') {
+        Some(index) if index > 0 && index + 1 < name.len() => {
+            Some(name[index + 1..].to_ascii_lowercase())
+        }
+        _ => None,
+    }
+}
+
+/// Prefixes absolute paths of `MAX_PATH` or more with `\\?\` so CreateProcess accepts them
+pub fn long_path(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if text.len() < MAX_PATH || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+    if let Some(share) = text.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else if text.as_bytes().get(1) == Some(&b':') {
+        PathBuf::from(format!(r"\\?\{}", text.replace('/', "\\")))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_windows_shims_and_scripts() {
+        let dirs = vec![PathBuf::from(r"C:\Windows"), PathBuf::from(r"C:\nodejs")];
+        let files = [
+            dirs[1].join("npx.cmd"),
+            dirs[1].join("deploy.ps1"),
+            dirs[0].join("git.EXE"),
+        ];
+        let is_file = |path: &Path| {
+            files.iter().any(|f| {
+                f.to_string_lossy()
+                    .eq_ignore_ascii_case(&path.to_string_lossy())
+            })
+        };
+        let cmd = |parts: &[&str]| parts.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        let (program, args) = resolve(&cmd(&["npx", "-y", "pkg"]), &dirs, DEFAULT_PATHEXT, is_file);
+        assert_eq!(program, files[0]);
+        assert_eq!(args, cmd(&["-y", "pkg"]));
+
+        let (program, args) = resolve(&cmd(&["deploy", "prod"]), &dirs, DEFAULT_PATHEXT, is_file);
+        assert_eq!(program, PathBuf::from("powershell.exe"));
+        assert_eq!(
+            args[4..],
+            cmd(&["-File", &files[1].to_string_lossy(), "prod"])
+        );
+
+        assert_eq!(
+            find_executable("git", &dirs, ".com;.exe", is_file)
+                .map(|p| extension(&p.to_string_lossy())),
+            Some(Some("exe".to_string()))
+        );
+        let (program, _) = resolve(&cmd(&["missing-tool"]), &dirs, DEFAULT_PATHEXT, is_file);
+        assert_eq!(program, PathBuf::from("missing-tool"));
+    }
+
+    #[test]
+    fn test_long_path_prefix() {
+        let short = Path::new(r"C:\tools\agent.exe");
+        assert_eq!(long_path(short), short);
+
+        let deep = format!(r"C:\{}\agent.exe", "nested\\".repeat(40));
+        assert_eq!(
+            long_path(Path::new(&deep)),
+            PathBuf::from(format!(r"\\?\{}", deep))
+        );
+
+        let unc = format!(r"\\server\share\{}\agent.exe", "d".repeat(260));
+        assert_eq!(
+            long_path(Path::new(&unc)),
+            PathBuf::from(format!(
+                r"\\?\UNC\server\share\{}\agent.exe",
+                "d".repeat(260)
+            ))
+        );
+        assert_eq!(extension(r"C:\my.dir\npx"), None);
+        assert_eq!(extension("npx.CMD"), Some("cmd".to_string()));
+    }
+}
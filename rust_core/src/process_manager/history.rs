@@ -0,0 +1,406 @@
+// rust_core/src/process_manager/history.rs
+//! Durable execution history of managed agents
+//!
+//! Once a history file is configured (`set_agent_history_path_py` or the
+//! `CDE_AGENT_HISTORY_PATH` environment variable), a recorder thread appends one
+//! JSON record per finished agent: command, timestamps, exit status, resource peaks
+//! seen by the health monitor and the tail of its output. The file is append-only
+//! JSON Lines, so it survives server restarts and stays readable with plain tools.
+
+use super::{monitor, registry};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Environment variable enabling the history when the server starts
+pub const HISTORY_PATH_ENV: &str = "CDE_AGENT_HISTORY_PATH";
+
+/// How often the recorder looks for agents that finished since its last pass
+const RECORD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Bytes of stdout/stderr kept per record (the tail is kept)
+const MAX_RECORDED_OUTPUT_BYTES: usize = 4096;
+
+/// Records returned by a query when the filter does not set `limit`
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub pid: u32,
+    pub command: String,
+    pub status: String,
+    pub exit_code: Option<i32>,
+    pub termination_path: Option<String>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: u128,
+    /// Highest CPU usage / memory of the agent's tree among the monitor's samples
+    pub peak_cpu_usage: Option<f32>,
+    pub peak_memory_mb: Option<u64>,
+    pub stdout: String,
+    pub stderr: String,
+    /// True when stdout or stderr was cut to its last `MAX_RECORDED_OUTPUT_BYTES`
+    pub output_truncated: bool,
+}
+
+/// Criteria of `query_agent_history_py`; every set field must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Substring of the command line
+    pub command: Option<String>,
+    pub status: Option<String>,
+    pub exit_code: Option<i32>,
+    /// RFC 3339 bounds on `started_at`
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    pub fn from_json(filter_json: Option<&str>) -> Result<Self, String> {
+        let filter: HistoryFilter = match filter_json {
+            Some(json) => {
+                serde_json::from_str(json).map_err(|e| format!("Invalid history filter: {}", e))?
+            }
+            None => HistoryFilter::default(),
+        };
+        for bound in [&filter.since, &filter.until].into_iter().flatten() {
+            parse_timestamp(bound)?;
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, record: &ExecutionRecord) -> bool {
+        let in_range = match parse_timestamp(&record.started_at) {
+            Ok(started) => {
+                self.since
+                    .as_deref()
+                    .is_none_or(|s| parse_timestamp(s).is_ok_and(|since| started >= since))
+                    && self
+                        .until
+                        .as_deref()
+                        .is_none_or(|u| parse_timestamp(u).is_ok_and(|until| started <= until))
+            }
+            Err(_) => self.since.is_none() && self.until.is_none(),
+        };
+        in_range
+            && self
+                .command
+                .as_ref()
+                .is_none_or(|c| record.command.contains(c.as_str()))
+            && self.status.as_ref().is_none_or(|s| &record.status == s)
+            && self
+                .exit_code
+                .is_none_or(|code| record.exit_code == Some(code))
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(value).map_err(|e| format!("Invalid timestamp '{}': {}", value, e))
+}
+
+/// Append-only JSON Lines file of execution records
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &ExecutionRecord) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// Matching records, newest first; lines that fail to parse are skipped
+    pub fn query(&self, filter: &HistoryFilter) -> io::Result<Vec<ExecutionRecord>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut records: Vec<ExecutionRecord> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .filter(|record| filter.matches(record))
+            .collect();
+        records.reverse();
+        records.truncate(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+        Ok(records)
+    }
+}
+
+struct HistoryState {
+    store: Option<HistoryStore>,
+    /// (pid, started_at) of agents already written, so PID reuse is told apart
+    recorded: HashSet<(u32, String)>,
+    recorder_started: bool,
+}
+
+fn history_state() -> &'static Mutex<HistoryState> {
+    static STATE: OnceLock<Mutex<HistoryState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(HistoryState {
+            store: std::env::var_os(HISTORY_PATH_ENV).map(HistoryStore::new),
+            recorded: HashSet::new(),
+            recorder_started: false,
+        })
+    })
+}
+
+/// The configured store, if history is enabled
+pub fn store() -> Option<HistoryStore> {
+    history_state().lock().unwrap().store.clone()
+}
+
+/// Enables history at `path`, or disables it with `None`
+pub fn set_path(path: Option<PathBuf>) {
+    history_state().lock().unwrap().store = path.map(HistoryStore::new);
+    ensure_recorder();
+}
+
+/// Starts the recorder thread (once) when history is enabled
+pub fn ensure_recorder() {
+    let mut state = history_state().lock().unwrap();
+    if state.store.is_none() || state.recorder_started {
+        return;
+    }
+    state.recorder_started = true;
+    monitor::ensure_started();
+    thread::spawn(|| loop {
+        thread::sleep(RECORD_INTERVAL);
+        record_finished();
+    });
+}
+
+/// Writes a record for every managed agent that finished since the last pass
+fn record_finished() {
+    let processes = registry::list();
+    let pending: Vec<registry::ProcessInfo> = {
+        let mut state = history_state().lock().unwrap();
+        if state.store.is_none() {
+            return;
+        }
+        state.recorded.retain(|(pid, started_at)| {
+            processes
+                .iter()
+                .any(|p| p.pid == *pid && &p.started_at == started_at)
+        });
+        processes
+            .into_iter()
+            .filter(|p| !p.is_running())
+            .filter(|p| !state.recorded.contains(&(p.pid, p.started_at.clone())))
+            .collect()
+    };
+
+    for process in pending {
+        let Some(waited) = registry::wait(process.pid, Some(Duration::ZERO)) else {
+            continue;
+        };
+        let record = execution_record(waited, monitor::peaks(process.pid));
+        let mut state = history_state().lock().unwrap();
+        // Another pass may have written it while the output was collected
+        if !state.recorded.insert((process.pid, process.started_at)) {
+            continue;
+        }
+        let Some(store) = &state.store else {
+            return;
+        };
+        if let Err(e) = store.append(&record) {
+            tracing::warn!(
+                pid = record.pid,
+                path = %store.path().display(),
+                error = %e,
+                "Failed to record agent in the history"
+            );
+        }
+    }
+}
+
+fn execution_record(
+    waited: registry::ProcessWaitResult,
+    peaks: Option<(f32, u64)>,
+) -> ExecutionRecord {
+    let process = waited.process;
+    let finished_at = parse_timestamp(&process.started_at)
+        .map(|started| {
+            (started + chrono::Duration::milliseconds(process.runtime_ms as i64)).to_rfc3339()
+        })
+        .unwrap_or_else(|_| chrono::Local::now().to_rfc3339());
+    let (stdout, stdout_truncated) = tail(&waited.stdout, MAX_RECORDED_OUTPUT_BYTES);
+    let (stderr, stderr_truncated) = tail(&waited.stderr, MAX_RECORDED_OUTPUT_BYTES);
+
+    ExecutionRecord {
+        pid: process.pid,
+        command: process.command,
+        status: process.status,
+        exit_code: process.exit_code,
+        termination_path: process.termination_path,
+        started_at: process.started_at,
+        finished_at,
+        duration_ms: process.runtime_ms,
+        peak_cpu_usage: peaks.map(|(cpu, _)| cpu),
+        peak_memory_mb: peaks.map(|(_, memory)| memory),
+        stdout,
+        stderr,
+        output_truncated: stdout_truncated || stderr_truncated,
+    }
+}
+
+/// Last `max_bytes` of `text`, cut at a character boundary
+fn tail(text: &str, max_bytes: usize) -> (String, bool) {
+    if text.len() <= max_bytes {
+        return (text.to_string(), false);
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    (text[start..].to_string(), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pid: u32, command: &str, status: &str, started_at: &str) -> ExecutionRecord {
+        ExecutionRecord {
+            pid,
+            command: command.to_string(),
+            status: status.to_string(),
+            exit_code: Some(if status == "completed" { 0 } else { 1 }),
+            termination_path: None,
+            started_at: started_at.to_string(),
+            finished_at: started_at.to_string(),
+            duration_ms: 10,
+            peak_cpu_usage: None,
+            peak_memory_mb: Some(12),
+            stdout: String::new(),
+            stderr: String::new(),
+            output_truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_history_store_append_and_query() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = HistoryStore::new(dir.path().join("nested").join("history.jsonl"));
+        assert!(store.query(&HistoryFilter::default()).unwrap().is_empty());
+
+        store
+            .append(&record(
+                1,
+                "claude --print",
+                "completed",
+                "2025-01-01T10:00:00+00:00",
+            ))
+            .unwrap();
+        store
+            .append(&record(
+                2,
+                "gemini run",
+                "failed",
+                "2025-01-02T10:00:00+00:00",
+            ))
+            .unwrap();
+        store
+            .append(&record(
+                3,
+                "claude --print",
+                "failed",
+                "2025-01-03T10:00:00+00:00",
+            ))
+            .unwrap();
+
+        let all = store.query(&HistoryFilter::default()).unwrap();
+        assert_eq!(all.iter().map(|r| r.pid).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(
+            all[2],
+            record(
+                1,
+                "claude --print",
+                "completed",
+                "2025-01-01T10:00:00+00:00"
+            )
+        );
+
+        let filter = HistoryFilter::from_json(Some(
+            r#"{"command": "claude", "since": "2025-01-02T00:00:00+00:00"}"#,
+        ))
+        .unwrap();
+        let matched = store.query(&filter).unwrap();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].pid, 3);
+
+        let failed = HistoryFilter {
+            status: Some("failed".to_string()),
+            limit: Some(1),
+            ..HistoryFilter::default()
+        };
+        assert_eq!(store.query(&failed).unwrap()[0].pid, 3);
+        assert!(HistoryFilter::from_json(Some(r#"{"until": "yesterday"}"#)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recorder_persists_finished_agents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("history.jsonl");
+        set_path(Some(path.clone()));
+
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo recorded-run".to_string(),
+        ];
+        let pid = super::super::spawn_process(
+            &command,
+            &super::super::SpawnOptions::default(),
+            registry::OutputOptions::default(),
+        )
+        .unwrap();
+        registry::wait(pid, Some(Duration::from_secs(5))).unwrap();
+        record_finished();
+
+        let filter = HistoryFilter {
+            command: Some("recorded-run".to_string()),
+            ..HistoryFilter::default()
+        };
+        let records = HistoryStore::new(&path).query(&filter).unwrap();
+        set_path(None);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].pid, pid);
+        assert_eq!(records[0].status, "completed");
+        assert_eq!(records[0].stdout, "recorded-run\n");
+    }
+
+    #[test]
+    fn test_tail_keeps_char_boundaries() {
+        assert_eq!(tail("short", 10), ("short".to_string(), false));
+        assert_eq!(tail("abcdef", 3), ("def".to_string(), true));
+        assert_eq!(tail("aé", 1), (String::new(), true));
+    }
+}
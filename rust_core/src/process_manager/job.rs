@@ -0,0 +1,151 @@
+// rust_core/src/process_manager/job.rs
+//! Windows Job Objects holding each agent's process tree
+//!
+//! Every agent is assigned to a job of its own as soon as it is spawned, and the
+//! processes it starts join that job whether or not their parent is still alive,
+//! so `terminate` reaches grandchildren that `taskkill /T` would miss. Jobs are
+//! created with `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`: releasing one when the
+//! agent's registry entry is pruned kills whatever the agent left behind.
+//!
+//! The job also enforces the agent's `cpu_time_secs` and `max_memory_mb`, over
+//! the whole tree rather than per process as the Unix rlimits do: past either
+//! limit Windows terminates every process in the job.
+
+use super::options::SpawnOptions;
+use std::collections::HashMap;
+use std::io;
+use std::os::windows::io::RawHandle;
+use std::sync::{Mutex, OnceLock};
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+use windows_sys::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+    SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_JOB_TIME, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+
+/// Exit code of the processes killed with their job
+const KILLED_EXIT_CODE: u32 = 1;
+
+/// Job time limits are counted in 100-nanosecond ticks
+const TICKS_PER_SEC: i64 = 10_000_000;
+
+/// Owned job handle, closed (killing its processes) on drop
+struct Job(HANDLE);
+
+// SAFETY: a job handle may be used and closed from any thread
+unsafe impl Send for Job {}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by CreateJobObjectW and is closed once
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Jobs of the managed agents, by PID of the agent
+fn jobs() -> &'static Mutex<HashMap<u32, Job>> {
+    static JOBS: OnceLock<Mutex<HashMap<u32, Job>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Puts the freshly spawned agent `pid`, whose handle is `process`, in a job of
+/// its own, limited as `options` ask
+pub fn contain(pid: u32, process: RawHandle, options: &SpawnOptions) -> io::Result<()> {
+    // SAFETY: null attributes and name are allowed; the handle is owned by `job`
+    let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+    if handle.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let job = Job(handle);
+
+    // SAFETY: the all-zero structure is a valid "no limits" value
+    let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+    limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+    if let Some(secs) = options.cpu_time_secs {
+        limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_TIME;
+        limits.BasicLimitInformation.PerJobUserTimeLimit =
+            i64::try_from(secs).map_or(i64::MAX, |secs| secs.saturating_mul(TICKS_PER_SEC));
+    }
+    if let Some(mb) = options.max_memory_mb {
+        limits.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+        limits.JobMemoryLimit =
+            usize::try_from(mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+    }
+    // SAFETY: `limits` outlives the call and its size is passed along
+    let set = unsafe {
+        SetInformationJobObject(
+            job.0,
+            JobObjectExtendedLimitInformation,
+            &limits as *const _ as *const std::ffi::c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        )
+    };
+    if set == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: both handles are open for the duration of the call
+    if unsafe { AssignProcessToJobObject(job.0, process as HANDLE) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    jobs().lock().unwrap().insert(pid, job);
+    Ok(())
+}
+
+/// Kills every process in the job of `pid`; false when it has no job
+pub fn terminate(pid: u32) -> bool {
+    match jobs().lock().unwrap().remove(&pid) {
+        // SAFETY: the handle is open until `job` is dropped
+        Some(job) => unsafe { TerminateJobObject(job.0, KILLED_EXIT_CODE) != 0 },
+        None => false,
+    }
+}
+
+/// Closes the job of `pid`, killing whatever is still in it
+pub fn release(pid: u32) {
+    jobs().lock().unwrap().remove(&pid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Command;
+    use windows_sys::Win32::System::JobObjects::QueryInformationJobObject;
+
+    #[test]
+    fn test_job_enforces_spawn_limits() {
+        let options =
+            SpawnOptions::from_json(Some(r#"{"cpu_time_secs": 7, "max_memory_mb": 64}"#)).unwrap();
+        let mut child = Command::new("cmd")
+            .args(["/C", "ping -n 30 127.0.0.1 >NUL"])
+            .spawn()
+            .unwrap();
+        contain(child.id(), child.as_raw_handle(), &options).unwrap();
+
+        // SAFETY: the all-zero structure is valid, and the buffer outlives the call
+        let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        let queried = unsafe {
+            let jobs = jobs().lock().unwrap();
+            QueryInformationJobObject(
+                jobs[&child.id()].0,
+                JobObjectExtendedLimitInformation,
+                &mut limits as *mut _ as *mut std::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                std::ptr::null_mut(),
+            )
+        };
+        assert_ne!(queried, 0);
+        let basic = limits.BasicLimitInformation;
+        let flags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE
+            | JOB_OBJECT_LIMIT_JOB_TIME
+            | JOB_OBJECT_LIMIT_JOB_MEMORY;
+        assert_eq!(basic.LimitFlags & flags, flags);
+        assert_eq!(basic.PerJobUserTimeLimit, 7 * TICKS_PER_SEC);
+        assert_eq!(limits.JobMemoryLimit, 64 * 1024 * 1024);
+
+        assert!(terminate(child.id()));
+        assert_eq!(child.wait().unwrap().code(), Some(KILLED_EXIT_CODE as i32));
+        assert!(!terminate(child.id()));
+    }
+}
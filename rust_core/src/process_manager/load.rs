@@ -0,0 +1,97 @@
+// rust_core/src/process_manager/load.rs
+//! System load sampling for load-aware scheduling
+//!
+//! A sampler thread refreshes global CPU usage and available memory at a fixed
+//! interval once load limits are in use. The scheduler defers queued agents while
+//! the machine is busy instead of starting all of them at once.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+
+/// How often system load is sampled (sysinfo needs at least 200ms between CPU refreshes)
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoadSample {
+    /// Increases with every sample, so callers can wait for a fresh one
+    pub seq: u64,
+    /// Global CPU usage in percent (0-100)
+    pub cpu_pct: f32,
+    pub available_memory_mb: u64,
+    pub total_memory_mb: u64,
+}
+
+struct LoadState {
+    latest: Option<LoadSample>,
+    started: bool,
+}
+
+fn load_state() -> &'static Mutex<LoadState> {
+    static STATE: OnceLock<Mutex<LoadState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(LoadState {
+            latest: None,
+            started: false,
+        })
+    })
+}
+
+/// Latest system load, starting the sampler on first use; `None` until the first
+/// sample is taken
+pub fn latest() -> Option<LoadSample> {
+    let mut state = load_state().lock().unwrap();
+    if !state.started {
+        state.started = true;
+        thread::spawn(run_sampler);
+    }
+    state.latest
+}
+
+fn run_sampler() {
+    let mut system = System::new_with_specifics(
+        RefreshKind::nothing()
+            .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+            .with_memory(MemoryRefreshKind::nothing().with_ram()),
+    );
+    // The first CPU refresh only sets the baseline usage is measured against
+    system.refresh_cpu_usage();
+    let mut seq = 0;
+    loop {
+        thread::sleep(SAMPLE_INTERVAL);
+        system.refresh_cpu_usage();
+        system.refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+        seq += 1;
+        load_state().lock().unwrap().latest = Some(LoadSample {
+            seq,
+            cpu_pct: system.global_cpu_usage(),
+            available_memory_mb: system.available_memory() / 1024 / 1024,
+            total_memory_mb: system.total_memory() / 1024 / 1024,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_sampler_reports_fresh_load() {
+        let started = Instant::now();
+        let mut first = latest();
+        while first.is_none() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(50));
+            first = latest();
+        }
+        let first = first.unwrap();
+        assert!((0.0..=100.0).contains(&first.cpu_pct));
+        assert!(first.total_memory_mb > 0);
+        assert!(first.available_memory_mb <= first.total_memory_mb);
+
+        thread::sleep(SAMPLE_INTERVAL * 3);
+        assert!(latest().unwrap().seq > first.seq);
+    }
+}
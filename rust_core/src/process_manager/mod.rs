@@ -0,0 +1,1285 @@
+// rust_core/src/process_manager/mod.rs
+//! Process management for parallel agent execution
+
+use crate::cancel::CancellationToken;
+use crate::checkpoint::{self, Checkpoint, Checkpointer};
+use crate::error::{to_json, to_py, CdeError};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+mod audit;
+mod batch;
+pub(crate) mod executable;
+mod history;
+#[cfg(windows)]
+mod job;
+mod load;
+mod monitor;
+mod options;
+mod pty;
+pub(crate) mod registry;
+mod scheduler;
+mod secrets;
+mod structured;
+mod supervisor;
+pub(crate) mod tree;
+mod workspace;
+
+use options::SpawnOptions;
+use registry::OutputOptions;
+use scheduler::{scheduler, Job, JobState, LoadLimits};
+use secrets::SecretFiles;
+
+/// Represents a spawned agent process
+#[pyclass]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentProcess {
+    #[pyo3(get)]
+    pub pid: u32,
+    #[pyo3(get)]
+    pub command: String,
+    #[pyo3(get)]
+    pub status: String, // "running", "queued", "failed_<error>"
+    #[pyo3(get)]
+    pub job_id: u64,
+    /// Position in the spawn queue while `status` is "queued"
+    #[pyo3(get)]
+    pub queue_position: Option<usize>,
+    /// Stable id across restarts when a restart policy is set
+    #[pyo3(get)]
+    pub agent_id: Option<u64>,
+}
+
+/// Outcome of an agent that was awaited to completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentResult {
+    pub pid: u32,
+    pub command: String,
+    pub status: String, // "completed", "failed", "timeout", "failed_<error>"
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u128,
+    pub timed_out: bool,
+    pub termination_path: Option<String>, // "graceful", "forced" when timed out
+    pub restarts: u32,
+    /// Parsed from stdout when the `result_format` option is set
+    pub result: Option<serde_json::Value>,
+    /// Why no result could be parsed (missing block, invalid JSON, missing keys)
+    pub result_error: Option<String>,
+    /// Files the agent changed in its workspace when the `workspace` option is set
+    pub workspace: Option<workspace::WorkspaceReport>,
+}
+
+/// Spawn multiple CLI agents through the bounded-concurrency scheduler
+///
+/// Agents beyond the concurrency limit are queued (status "queued" with a
+/// `queue_position`) and started as running agents finish; see `get_queue_state_py`.
+///
+/// With `wait=True` every command is run to completion and the result contains
+/// stdout, stderr, exit code and duration per agent. `timeout_secs` is shorthand
+/// for the `hard_timeout_secs` option.
+/// `on_output` streams output lines as they are produced (see `spawn_agent_async`).
+///
+/// `options_json` contains containment settings applied to every agent:
+/// `cwd`, `env_whitelist`, `cpu_time_secs`, `max_memory_mb` and `disable_network`,
+/// the scheduling `priority` (higher first), and `soft_timeout_secs` /
+/// `hard_timeout_secs`: past the soft timeout the agent's process group is asked
+/// to exit, past the hard timeout it is killed. `termination_path` in the result
+/// records which one ended it ("graceful" or "forced"). `stall_timeout_secs` marks
+/// an agent "stalled" after that long without output (`on_output` then receives a
+/// `stream: "status"` line "stalled", and "active" once output resumes);
+/// `terminate_on_stall` kills it instead (status "timeout", termination_path
+/// "stalled"). `result_format` (`{"format": "auto", "required_keys": [...]}`) parses
+/// a structured `result` out of stdout, see `parse_agent_result_py`. `workspace`
+/// (`{"source": ..., "include": ["src/**"], "keep": false}`) runs each agent in a
+/// private copy of the source tree (default: `cwd`); the result's `workspace` lists
+/// the files it added, modified or deleted, see `collect_workspace_changes_py`.
+/// `restart` sets a restart
+/// policy (see `get_supervised_agents_py`): `{"policy": "on-failure", "max_retries": 3,
+/// "backoff_initial_ms": 500, "backoff_max_ms": 30000, "crash_loop_threshold": 5,
+/// "crash_loop_window_secs": 60}`; with `wait=True` the result is the final run.
+/// `interactive` keeps stdin open for `write_stdin_py`; `pty` runs the agent on a
+/// pseudo-terminal (a ConPTY pseudo console on Windows) with ANSI-clean output.
+/// `env` adds variables whose values may reference the server environment as
+/// `${VAR}`; `secrets` maps names to values that are written to owner-only temp
+/// files, passed as `<NAME>_FILE` and redacted from captured output, so API keys
+/// never appear in argv.
+/// Options the platform cannot enforce raise `ValueError` instead of being ignored.
+/// In audit mode nothing is run, see `set_audit_mode_py`.
+#[pyfunction]
+#[pyo3(signature = (commands, wait=false, timeout_secs=None, on_output=None, max_buffered_lines=None, options_json=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_agents_parallel(
+    py: Python<'_>,
+    commands: Vec<Vec<String>>,
+    wait: bool,
+    timeout_secs: Option<f64>,
+    on_output: Option<Py<PyAny>>,
+    max_buffered_lines: Option<usize>,
+    options_json: Option<String>,
+) -> PyResult<String> {
+    let mut options = SpawnOptions::from_json(options_json.as_deref())
+        .map_err(CdeError::invalid_input)?;
+    if options.hard_timeout_secs.is_none() {
+        options.hard_timeout_secs = timeout_secs;
+        options.validate().map_err(CdeError::invalid_input)?;
+    }
+    let output = output_options(on_output, max_buffered_lines);
+    if wait {
+        let results = py.detach(|| run_agents_to_completion(&commands, &options, &output));
+        return Ok(to_json(&results)?);
+    }
+
+    let results: Vec<AgentProcess> = py.detach(|| {
+        commands
+            .iter()
+            .map(|cmd| submit_agent(cmd, &options, output.clone()))
+            .collect()
+    });
+
+    Ok(to_json(&results)?)
+}
+
+/// Run a batch of agents to completion and return an aggregated report
+///
+/// `policy` decides the batch outcome: "continue" runs every command and succeeds
+/// when all do; "fail-fast" cancels the remaining agents as soon as one fails;
+/// "quorum" succeeds once `quorum` agents succeed and cancels the rest as soon as
+/// the outcome is known either way. Cancelled agents are dropped from the queue or
+/// have their process tree killed, and are reported with status "cancelled".
+/// Cancelling `cancel_token` cancels every agent left; the batch status is then
+/// "cancelled" unless it had already succeeded.
+///
+/// Returns JSON with `policy`, `status` ("succeeded"/"failed"/"cancelled"), `required`, `total`,
+/// `succeeded`, `failed`, `cancelled`, `duration_ms` and per-command `results`
+/// (as returned by `spawn_agents_parallel(wait=True)`). `options_json` takes the
+/// same options as `spawn_agents_parallel`.
+///
+/// With an `op_id`, each result is checkpointed as it comes in, and a batch
+/// that fails, is cancelled or is cut short by a server restart can be resumed
+/// with `resume_op_py(op_id)`, which runs only the commands that did not complete.
+#[pyfunction]
+#[pyo3(signature = (commands, policy="continue", quorum=None, timeout_secs=None, on_output=None, options_json=None, cancel_token=None, op_id=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn run_agent_batch_py(
+    py: Python<'_>,
+    commands: Vec<Vec<String>>,
+    policy: &str,
+    quorum: Option<usize>,
+    timeout_secs: Option<f64>,
+    on_output: Option<Py<PyAny>>,
+    options_json: Option<String>,
+    cancel_token: Option<CancellationToken>,
+    op_id: Option<String>,
+) -> PyResult<String> {
+    let batch_policy = batch::BatchPolicy::new(policy, quorum, commands.len())
+        .map_err(CdeError::invalid_input)?;
+    let mut options = SpawnOptions::from_json(options_json.as_deref())
+        .map_err(CdeError::invalid_input)?;
+    if options.hard_timeout_secs.is_none() {
+        options.hard_timeout_secs = timeout_secs;
+        options.validate().map_err(CdeError::invalid_input)?;
+    }
+    let output = output_options(on_output, None);
+    let cancel = cancel_token.unwrap_or_default();
+
+    let Some(op_id) = op_id else {
+        let report =
+            py.detach(|| batch::run_batch(&commands, &options, &output, &batch_policy, &cancel));
+        return Ok(to_json(&report)?);
+    };
+    let params = batch::BatchParams::new(&commands, policy, quorum, &options);
+    let mut checkpoint = Checkpoint::new(
+        &op_id,
+        checkpoint::AGENT_BATCH_KIND,
+        options.cwd.clone(),
+        batch_state(&params)?,
+        batch_state(&batch::BatchProgress::default())?,
+    );
+    checkpoint.total = Some(commands.len());
+    let checkpointer = Checkpointer::shared(checkpoint)?;
+    let report = py
+        .detach(|| batch::run_checkpointed(&params, &options, &output, &cancel, checkpointer))
+        .map_err(CdeError::invalid_input)?;
+    Ok(to_json(&report)?)
+}
+
+/// Runs again the commands of a claimed "agent_batch" checkpoint that did not
+/// complete; returns the report of the whole batch as JSON
+///
+/// `options_json` replaces the checkpointed spawn options, and is required
+/// when the batch had secrets, whose values are not checkpointed.
+pub(crate) fn resume_agent_batch(
+    checkpoint: Checkpoint,
+    options_json: Option<&str>,
+    on_output: Option<Py<PyAny>>,
+    cancel: &CancellationToken,
+) -> Result<String, CdeError> {
+    let params: batch::BatchParams = serde_json::from_value(checkpoint.params.clone())
+        .map_err(|e| CdeError::invalid_input(format!("Invalid batch checkpoint: {}", e)))?;
+    let options = match options_json {
+        Some(json) => SpawnOptions::from_json(Some(json)).map_err(CdeError::invalid_input)?,
+        None if !params.secret_names.is_empty() => {
+            return Err(CdeError::invalid_input(format!(
+                "Batch {} had secrets ({}); pass options_json with them to resume it",
+                checkpoint.op_id,
+                params.secret_names.join(", ")
+            )))
+        }
+        None => params.options.clone(),
+    };
+    let output = output_options(on_output, None);
+    let checkpointer = Checkpointer::shared(checkpoint)?;
+    let report = batch::run_checkpointed(&params, &options, &output, cancel, checkpointer)
+        .map_err(CdeError::invalid_input)?;
+    to_json(&report)
+}
+
+/// Checkpoint state of a batch, failing with a serialization error rather than panicking
+fn batch_state<T: Serialize>(value: &T) -> Result<serde_json::Value, CdeError> {
+    serde_json::to_value(value)
+        .map_err(|e| CdeError::serialization("Failed to serialize batch").caused_by(&e))
+}
+
+fn not_managed(pid: u32) -> CdeError {
+    CdeError::not_found(format!("Process {} is not managed by the core", pid))
+}
+
+fn build_command(cmd: &[String], options: &SpawnOptions) -> Command {
+    let (program, args) = executable::resolve_command(cmd);
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(if options.interactive { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // Own process group so the whole agent tree can be signalled at once
+    // (PTY sessions get one from setsid instead)
+    #[cfg(unix)]
+    if !options.pty {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    // No console window flashing up for any agent, not only `cmd` ones
+    #[cfg(windows)]
+    {
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW);
+    }
+
+    options.apply(&mut command);
+
+    #[cfg(windows)]
+    for (name, value) in executable::UTF8_ENV {
+        if !options.env.contains_key(name) {
+            command.env(name, value);
+        }
+    }
+    command
+}
+
+/// Builds the registry output options, forwarding lines to a Python callable
+///
+/// The callable receives one JSON string per line: `{"pid", "seq", "stream", "line"}`.
+fn output_options(on_output: Option<Py<PyAny>>, max_buffered_lines: Option<usize>) -> OutputOptions {
+    let sink: Option<registry::OutputSink> = on_output.map(|callback| {
+        Arc::new(move |pid: u32, line: &registry::OutputLine| {
+            let payload = serde_json::json!({
+                "pid": pid,
+                "seq": line.seq,
+                "stream": line.stream,
+                "line": line.line,
+            })
+            .to_string();
+            Python::attach(|py| {
+                if let Err(e) = callback.call1(py, (payload,)) {
+                    e.print(py);
+                }
+            });
+        }) as registry::OutputSink
+    });
+
+    OutputOptions {
+        sink,
+        max_buffered_lines: max_buffered_lines.unwrap_or(registry::DEFAULT_MAX_BUFFERED_LINES),
+        ..OutputOptions::default()
+    }
+}
+
+/// Queues the agent with the scheduler and reports where it stands
+fn submit_agent(cmd: &[String], options: &SpawnOptions, output: OutputOptions) -> AgentProcess {
+    if cmd.is_empty() {
+        return AgentProcess {
+            pid: 0,
+            command: String::new(),
+            status: "failed_empty".to_string(),
+            job_id: 0,
+            queue_position: None,
+            agent_id: None,
+        };
+    }
+    if audit::is_enabled() {
+        let result = dry_run_result(cmd, options);
+        return AgentProcess {
+            pid: 0,
+            command: result.command,
+            status: result.status,
+            job_id: 0,
+            queue_position: None,
+            agent_id: None,
+        };
+    }
+
+    let job_id = scheduler().submit(Job {
+        command: cmd.to_vec(),
+        options: options.clone(),
+        output,
+    });
+    let (pid, status, queue_position) = match scheduler().job_state(job_id) {
+        Some((JobState::Running(pid), _)) => (pid, "running".to_string(), None),
+        Some((JobState::Queued, position)) => (0, "queued".to_string(), position),
+        Some((JobState::Failed(reason), _)) => (0, format!("failed_{}", reason), None),
+        None => (0, "failed_unknown_job".to_string(), None),
+    };
+
+    AgentProcess {
+        pid,
+        command: cmd.join(" "),
+        status,
+        job_id,
+        queue_position,
+        agent_id: supervisor::agent_for_pid(pid),
+    }
+}
+
+/// Spawns the agent and hands the child over to the registry
+fn spawn_process(
+    cmd: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> Result<u32, std::io::Error> {
+    history::ensure_recorder();
+    let secret_files = if options.secrets.is_empty() {
+        None
+    } else {
+        Some(SecretFiles::write(&options.secrets)?)
+    };
+    let mut command = build_command(cmd, options);
+    if let Some(files) = &secret_files {
+        command.envs(files.env_vars());
+    }
+    let workspace = match &options.workspace {
+        Some(workspace_options) => {
+            let source = match workspace_options.source.as_ref().or(options.cwd.as_ref()) {
+                Some(source) => std::path::PathBuf::from(source),
+                None => std::env::current_dir()?,
+            };
+            let workspace = workspace::Workspace::create(&source, workspace_options)?;
+            command.current_dir(workspace.root());
+            Some(workspace)
+        }
+        None => None,
+    };
+    let output = OutputOptions {
+        redactions: options.redactions(),
+        ..output
+    };
+
+    #[cfg(unix)]
+    let pid = if options.pty {
+        spawn_pty_process(command, cmd, options, output)?
+    } else {
+        registry::register(command.spawn()?, cmd.join(" "), output, options.timeouts())
+    };
+    #[cfg(windows)]
+    let pid = if options.pty {
+        spawn_pty_process(command, cmd, options, output)?
+    } else {
+        registry::register(spawn_in_job(&mut command, options)?, cmd.join(" "), output, options.timeouts())
+    };
+    #[cfg(not(any(unix, windows)))]
+    let pid = registry::register(command.spawn()?, cmd.join(" "), output, options.timeouts());
+
+    if let Some(files) = secret_files {
+        registry::attach_secrets(pid, files);
+    }
+    if let Some(workspace) = workspace {
+        registry::attach_workspace(pid, workspace);
+    }
+    Ok(pid)
+}
+
+/// Spawns the agent in a job object of its own, so its whole tree can be killed
+/// and its CPU and memory limits apply to the tree
+#[cfg(windows)]
+fn spawn_in_job(
+    command: &mut Command,
+    options: &SpawnOptions,
+) -> Result<std::process::Child, std::io::Error> {
+    use std::os::windows::io::AsRawHandle;
+
+    let mut child = command.spawn()?;
+    if let Err(e) = job::contain(child.id(), child.as_raw_handle(), options) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+    Ok(child)
+}
+
+/// Spawns the agent as a session leader attached to a new pseudo-terminal
+#[cfg(unix)]
+fn spawn_pty_process(
+    mut command: Command,
+    cmd: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> Result<u32, std::io::Error> {
+    let pty = pty::open_pty()?;
+    pty.attach(&mut command)?;
+    let child = command.spawn()?;
+
+    let master = pty.into_master();
+    let io = registry::ChildIo {
+        stdout: Some(Box::new(master.try_clone()?)),
+        stderr: None,
+        stdin: Some(Box::new(master)),
+    };
+    let output = OutputOptions {
+        strip_ansi: true,
+        ..output
+    };
+    Ok(registry::register_with_io(
+        child,
+        io,
+        cmd.join(" "),
+        output,
+        options.timeouts(),
+    ))
+}
+
+/// Spawns the agent on a new pseudo console, in a job object of its own
+#[cfg(windows)]
+fn spawn_pty_process(
+    command: Command,
+    cmd: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> Result<u32, std::io::Error> {
+    let session = pty::spawn_console(&command, options.env_whitelist.is_some())?;
+    let mut child = session.child;
+    let contained = match child.as_raw_handle() {
+        Some(process) => job::contain(child.id(), process, options),
+        None => Err(std::io::Error::other("Agent has no process handle")),
+    };
+    if let Err(e) = contained {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+
+    let io = registry::ChildIo {
+        stdout: Some(session.output),
+        stderr: None,
+        stdin: Some(session.input),
+    };
+    let output = OutputOptions {
+        strip_ansi: true,
+        ..output
+    };
+    Ok(registry::register_with_io(
+        child,
+        io,
+        cmd.join(" "),
+        output,
+        options.timeouts(),
+    ))
+}
+
+/// Spawns the agent and puts it under supervision when it has a restart policy
+fn spawn_agent_sync(
+    cmd: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> Result<AgentProcess, std::io::Error> {
+    let pid = spawn_process(cmd, options, output.clone())?;
+    let agent_id = options
+        .restart
+        .is_enabled()
+        .then(|| supervisor::supervise(pid, cmd, options, output));
+
+    Ok(AgentProcess {
+        pid,
+        command: cmd.join(" "),
+        status: "running".to_string(),
+        job_id: 0,
+        queue_position: None,
+        agent_id,
+    })
+}
+
+/// Runs every command to completion on its own thread
+///
+/// Plain threads instead of Rayon: each agent blocks for its whole lifetime and
+/// must not starve the global pool used by the scanners.
+fn run_agents_to_completion(
+    commands: &[Vec<String>],
+    options: &SpawnOptions,
+    output: &OutputOptions,
+) -> Vec<AgentResult> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = commands
+            .iter()
+            .map(|cmd| scope.spawn(move || run_agent_to_completion(cmd, options, output.clone())))
+            .collect();
+        handles
+            .into_iter()
+            .zip(commands)
+            .map(|(handle, cmd)| {
+                handle.join().unwrap_or_else(|_| failed_result(cmd, "panicked"))
+            })
+            .collect()
+    })
+}
+
+fn failed_result(cmd: &[String], reason: &str) -> AgentResult {
+    AgentResult {
+        pid: 0,
+        command: cmd.join(" "),
+        status: format!("failed_{}", reason),
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        duration_ms: 0,
+        timed_out: false,
+        termination_path: None,
+        restarts: 0,
+        result: None,
+        result_error: None,
+        workspace: None,
+    }
+}
+
+fn run_agent_to_completion(
+    cmd: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> AgentResult {
+    if cmd.is_empty() {
+        return failed_result(cmd, "empty");
+    }
+    if audit::is_enabled() {
+        return dry_run_result(cmd, options);
+    }
+
+    let job_id = scheduler().submit(Job {
+        command: cmd.to_vec(),
+        options: options.clone(),
+        output,
+    });
+    await_agent(cmd, job_id, options)
+}
+
+/// Records what running `cmd` would execute instead of running it (audit mode)
+fn dry_run_result(cmd: &[String], options: &SpawnOptions) -> AgentResult {
+    let entry = audit::AuditEntry::new(cmd, &build_command(cmd, options), options);
+    if let Err(e) = audit::record(entry) {
+        return failed_result(cmd, &format!("audit_{}", e));
+    }
+    AgentResult {
+        status: "dry_run".to_string(),
+        ..failed_result(cmd, "")
+    }
+}
+
+/// Waits for a submitted job to start and for its agent, restarts included, to finish
+fn await_agent(cmd: &[String], job_id: u64, options: &SpawnOptions) -> AgentResult {
+    let pid = match scheduler().wait_started(job_id) {
+        Ok(pid) => pid,
+        Err(reason) => return failed_result(cmd, &reason),
+    };
+    // Follow restarts: the result describes the last run
+    let settled = supervisor::agent_for_pid(pid).and_then(supervisor::wait_settled);
+    let pid = settled.as_ref().map_or(pid, |agent| agent.current_pid);
+    let Some(waited) = registry::wait(pid, None) else {
+        return failed_result(cmd, "unregistered");
+    };
+    let status = match &settled {
+        Some(agent) if agent.status == "crash_looping" => agent.status.clone(),
+        _ => waited.process.status.clone(),
+    };
+    let (result, result_error) = match options
+        .result_format
+        .as_ref()
+        .map(|format| format.parse(&waited.stdout))
+    {
+        Some(Ok(value)) => (Some(value), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    AgentResult {
+        pid,
+        timed_out: waited.process.status == "timeout",
+        command: waited.process.command,
+        status,
+        exit_code: waited.process.exit_code,
+        stdout: waited.stdout,
+        stderr: waited.stderr,
+        duration_ms: waited.process.runtime_ms,
+        termination_path: waited.process.termination_path,
+        restarts: settled.map_or(0, |agent| agent.restarts),
+        result,
+        result_error,
+        workspace: registry::workspace(pid).and_then(|workspace| workspace.collect().ok()),
+    }
+}
+
+/// Parse the structured result an agent printed
+///
+/// `format` is "markers" (JSON between two `===CDE_RESULT===` lines, the last block
+/// wins), "json_trailer" (a JSON document ending the output, possibly multi-line)
+/// or "auto" (markers when present, otherwise the trailer). With `required_keys`
+/// the result must be an object containing them. Returns the result as JSON and
+/// raises `ValueError` when none can be extracted.
+#[pyfunction]
+#[pyo3(signature = (output, format="auto", required_keys=None))]
+pub fn parse_agent_result_py(
+    py: Python<'_>,
+    output: &str,
+    format: &str,
+    required_keys: Option<Vec<String>>,
+) -> PyResult<String> {
+    let result_format = structured::ResultFormat {
+        format: format.to_string(),
+        required_keys: required_keys.unwrap_or_default(),
+    };
+    py.detach(|| {
+        result_format
+            .validate()
+            .and_then(|_| result_format.parse(output))
+            .map(|value| value.to_string())
+    })
+    .map_err(|e| CdeError::parse(e).into())
+}
+
+/// Files a workspace agent added, modified or deleted so far
+///
+/// Returns JSON with the workspace `path`, its `source`, `files_copied` and
+/// `changes` (`path`, `change`: "added"/"modified"/"deleted", and the new `content`
+/// of UTF-8 files up to 1 MiB). The workspace is removed once the core forgets the
+/// agent, unless it was spawned with `"keep": true`.
+#[pyfunction]
+pub fn collect_workspace_changes_py(py: Python<'_>, pid: u32) -> PyResult<String> {
+    let Some(workspace) = registry::workspace(pid) else {
+        return Err(CdeError::invalid_input(format!("Process {} has no workspace", pid)).into());
+    };
+    let report = py
+        .detach(|| workspace.collect())
+        .map_err(|e| CdeError::io("Failed to collect workspace changes").caused_by(&e))?;
+    Ok(to_json(&report)?)
+}
+
+/// Status of a process spawned by the core
+///
+/// Returns JSON with pid, command, status ("running", "completed", "failed",
+/// "killed", "timeout"), exit_code, started_at, runtime_ms and termination_path.
+#[pyfunction]
+pub fn get_process_status_py(pid: u32) -> PyResult<String> {
+    match registry::status(pid) {
+        Some(info) => Ok(to_json(&info)?),
+        None => Err(not_managed(pid).into()),
+    }
+}
+
+/// Same as `get_process_status_py`, returning a dict instead of JSON
+#[pyfunction]
+pub fn get_process_status_dict(py: Python<'_>, pid: u32) -> PyResult<Bound<'_, PyAny>> {
+    let info = registry::status(pid).ok_or_else(|| not_managed(pid))?;
+    Ok(to_py(py, &info)?)
+}
+
+/// Block until a managed process exits or `timeout_secs` elapses
+///
+/// The process is left running on timeout; the result contains its status,
+/// captured stdout/stderr and whether the wait timed out.
+#[pyfunction]
+#[pyo3(signature = (pid, timeout_secs=None))]
+pub fn wait_process_py(py: Python<'_>, pid: u32, timeout_secs: Option<f64>) -> PyResult<String> {
+    let timeout = timeout_secs.map(Duration::from_secs_f64);
+    let result = py.detach(|| wait_for(pid, timeout))?;
+    Ok(to_json(&result)?)
+}
+
+/// Same as `wait_process_py`, returning a dict instead of JSON
+#[pyfunction]
+#[pyo3(signature = (pid, timeout_secs=None))]
+pub fn wait_process_dict(py: Python<'_>, pid: u32, timeout_secs: Option<f64>) -> PyResult<Bound<'_, PyAny>> {
+    let timeout = timeout_secs.map(Duration::from_secs_f64);
+    let result = py.detach(|| wait_for(pid, timeout))?;
+    Ok(to_py(py, &result)?)
+}
+
+/// Blocks until a managed process exits or `timeout` elapses
+pub fn wait_for(pid: u32, timeout: Option<Duration>) -> Result<registry::ProcessWaitResult, CdeError> {
+    registry::wait(pid, timeout).ok_or_else(|| not_managed(pid))
+}
+
+/// List every process spawned by the core, running or recently finished
+#[pyfunction]
+pub fn list_managed_processes_py() -> PyResult<String> {
+    Ok(to_json(&registry::list())?)
+}
+
+/// Same as `list_managed_processes_py`, returning a list of dicts instead of JSON
+#[pyfunction]
+pub fn list_managed_processes_dict(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    Ok(to_py(py, &registry::list())?)
+}
+
+/// Spawn agent with async log streaming
+///
+/// Output lines are kept in a per-PID ring buffer of `max_buffered_lines` lines
+/// (poll it with `read_agent_output_py`) and, when `on_output` is given, passed
+/// to that callable as they arrive. A slow callback throttles the agent instead
+/// of buffering without bound.
+#[pyfunction]
+#[pyo3(signature = (command, on_output=None, max_buffered_lines=None))]
+pub fn spawn_agent_async(
+    py: Python<'_>,
+    command: Vec<String>,
+    on_output: Option<Py<PyAny>>,
+    max_buffered_lines: Option<usize>,
+) -> PyResult<String> {
+    if command.is_empty() {
+        return Ok(serde_json::json!({
+            "pid": 0,
+            "command": "",
+            "status": "failed_empty",
+        }).to_string());
+    }
+
+    let output = output_options(on_output, max_buffered_lines);
+    let process = py.detach(|| submit_agent(&command, &SpawnOptions::default(), output));
+    Ok(to_json(&process)?)
+}
+
+/// Read buffered output lines of a managed agent
+///
+/// Returns JSON with `lines` (seq, stream, line), `next_seq` to pass as `since_seq`
+/// on the next call, `dropped_lines` evicted before being read, and `finished`.
+#[pyfunction]
+#[pyo3(signature = (pid, since_seq=0, max_lines=1000))]
+pub fn read_agent_output_py(pid: u32, since_seq: u64, max_lines: usize) -> PyResult<String> {
+    match registry::read_output(pid, since_seq, max_lines) {
+        Some(chunk) => Ok(to_json(&chunk)?),
+        None => Err(not_managed(pid).into()),
+    }
+}
+
+/// Same as `read_agent_output_py`, returning a dict instead of JSON
+#[pyfunction]
+#[pyo3(signature = (pid, since_seq=0, max_lines=1000))]
+pub fn read_agent_output_dict(py: Python<'_>, pid: u32, since_seq: u64, max_lines: usize) -> PyResult<Bound<'_, PyAny>> {
+    let chunk = registry::read_output(pid, since_seq, max_lines).ok_or_else(|| not_managed(pid))?;
+    Ok(to_py(py, &chunk)?)
+}
+
+/// Monitor process health
+///
+/// Point-in-time sample of a single PID; `monitor_all_agents_py` keeps history.
+#[pyfunction]
+pub fn monitor_process_health(py: Python<'_>, pid: u32) -> PyResult<String> {
+    Ok(py.detach(|| process_health(pid)))
+}
+
+fn process_health(pid: u32) -> String {
+    use sysinfo::{Pid, System};
+
+    let managed = registry::status(pid);
+    if let Some(info) = managed.as_ref().filter(|info| !info.is_running()) {
+        return serde_json::json!({
+            "pid": pid,
+            "status": info.status,
+            "exit_code": info.exit_code,
+        })
+        .to_string();
+    }
+
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let pid = Pid::from_u32(pid);
+
+    if let Some(process) = system.process(pid) {
+        let health = serde_json::json!({
+            "pid": pid.as_u32(),
+            "status": "running",
+            "cpu_usage": process.cpu_usage(),
+            "memory_mb": process.memory() / 1024 / 1024,
+            "disk_usage_bytes": process.disk_usage().total_written_bytes,
+        });
+
+        health.to_string()
+    } else {
+        serde_json::json!({
+            "pid": pid.as_u32(),
+            "status": "not_found",
+        })
+        .to_string()
+    }
+}
+
+/// Sample health of every managed agent in the background and return its history
+///
+/// The first call starts a sampler thread recording CPU, memory and disk I/O of each
+/// running agent (summed over its process tree) every `interval_secs`; later calls
+/// may change the interval. Returns JSON with `interval_ms` and `agents`, each with
+/// pid, command, status and the latest `max_samples` samples (all when omitted).
+#[pyfunction]
+#[pyo3(signature = (interval_secs=None, max_samples=None))]
+pub fn monitor_all_agents_py(interval_secs: Option<f64>, max_samples: Option<usize>) -> PyResult<String> {
+    let interval = match interval_secs {
+        Some(secs) if !secs.is_finite() || secs <= 0.0 => {
+            return Err(CdeError::invalid_input(format!("Invalid interval: {}", secs)).into())
+        }
+        Some(secs) => Duration::from_secs_f64(secs),
+        None => monitor::DEFAULT_SAMPLE_INTERVAL,
+    };
+    monitor::start(interval);
+    Ok(to_json(&monitor::snapshot(max_samples))?)
+}
+
+/// Kill process by PID
+///
+/// Processes spawned by the core are killed and reaped through the registry;
+/// any other PID falls back to a process-table lookup.
+#[pyfunction]
+pub fn kill_process(py: Python<'_>, pid: u32) -> PyResult<bool> {
+    Ok(py.detach(|| kill_pid(pid)))
+}
+
+fn kill_pid(pid: u32) -> bool {
+    use sysinfo::{Pid, System};
+
+    if let Some(killed) = registry::kill(pid) {
+        return killed;
+    }
+
+    let mut system = System::new_all();
+    system.refresh_all();
+
+    let pid = Pid::from_u32(pid);
+
+    if let Some(process) = system.process(pid) {
+        process.kill()
+    } else {
+        false
+    }
+}
+
+/// List agents spawned with a restart policy
+///
+/// Returns JSON with agent_id, command, policy, status ("running", "backoff",
+/// "completed", "failed", "stopped", "crash_looping"), current_pid, pids, restarts,
+/// last_exit_code and last_error. Killing `current_pid` through the core stops the agent.
+#[pyfunction]
+pub fn get_supervised_agents_py() -> PyResult<String> {
+    Ok(to_json(&supervisor::list())?)
+}
+
+/// Report scheduled agents that are running and those still waiting in the queue
+///
+/// Returns JSON with `max_concurrent`, `running` (job_id, pid, command, priority,
+/// started_at) and `queued` (job_id, command, priority, position, enqueued_at), plus
+/// the load `limits`, the latest system `load` and why jobs are `deferred`, if they are.
+#[pyfunction]
+pub fn get_queue_state_py() -> PyResult<String> {
+    Ok(to_json(&scheduler().queue_state())?)
+}
+
+/// Set how many scheduled agents may run at once (defaults to the CPU count)
+#[pyfunction]
+pub fn set_max_concurrent_agents_py(max_concurrent: usize) -> PyResult<()> {
+    if max_concurrent == 0 {
+        return Err(CdeError::invalid_input("max_concurrent must be at least 1").into());
+    }
+    scheduler().set_max_concurrent(max_concurrent);
+    Ok(())
+}
+
+/// Defer queued agents while the machine is busy
+///
+/// No new scheduled agent starts while global CPU usage is above `cpu_pct` percent
+/// or less than `mem_mb` MiB of memory is available; `None` disables a limit.
+/// One agent still starts whenever none is running.
+#[pyfunction]
+#[pyo3(signature = (cpu_pct=None, mem_mb=None))]
+pub fn set_scheduler_limits_py(cpu_pct: Option<f32>, mem_mb: Option<u64>) -> PyResult<()> {
+    if let Some(cpu_pct) = cpu_pct.filter(|pct| !(*pct > 0.0 && *pct <= 100.0)) {
+        return Err(CdeError::invalid_input(format!(
+            "cpu_pct must be above 0 and at most 100, got {}",
+            cpu_pct
+        ))
+        .into());
+    }
+    scheduler().set_limits(LoadLimits {
+        max_cpu_pct: cpu_pct,
+        min_free_memory_mb: mem_mb,
+    });
+    Ok(())
+}
+
+/// Write text to the stdin of an agent spawned with `interactive` or `pty`
+///
+/// Returns the number of bytes written. Include the trailing newline when
+/// answering a prompt.
+#[pyfunction]
+pub fn write_stdin_py(py: Python<'_>, pid: u32, data: String) -> PyResult<usize> {
+    Ok(py.detach(|| registry::write_stdin(pid, data.as_bytes()))?)
+}
+
+/// Close the stdin of an interactive agent so it sees end-of-file
+#[pyfunction]
+pub fn close_stdin_py(pid: u32) -> PyResult<()> {
+    Ok(registry::close_stdin(pid)?)
+}
+
+/// Enable the durable agent execution history at `path`, or disable it with `None`
+///
+/// Every managed agent that finishes afterwards is appended to the JSON Lines file
+/// at `path` (created with its parent directories). The history can also be enabled
+/// at startup with the `CDE_AGENT_HISTORY_PATH` environment variable.
+#[pyfunction]
+#[pyo3(signature = (path=None))]
+pub fn set_agent_history_path_py(path: Option<String>) {
+    history::set_path(path.map(std::path::PathBuf::from));
+}
+
+/// Query the agent execution history, newest first
+///
+/// `filter_json` may set `command` (substring), `status`, `exit_code`, `since` /
+/// `until` (RFC 3339 bounds on `started_at`) and `limit` (default 100). Returns a
+/// JSON list of records with command, timestamps, exit status, `peak_cpu_usage` /
+/// `peak_memory_mb` and the last 4 KiB of stdout/stderr.
+#[pyfunction]
+#[pyo3(signature = (filter_json=None))]
+pub fn query_agent_history_py(py: Python<'_>, filter_json: Option<String>) -> PyResult<String> {
+    let filter = history::HistoryFilter::from_json(filter_json.as_deref())
+        .map_err(CdeError::invalid_input)?;
+    let Some(store) = history::store() else {
+        return Err(CdeError::invalid_input(
+            "Agent history is not enabled: call set_agent_history_path_py or set CDE_AGENT_HISTORY_PATH",
+        )
+        .into());
+    };
+    let records = py
+        .detach(|| store.query(&filter))
+        .map_err(|e| CdeError::io("Failed to read agent history").caused_by(&e))?;
+    Ok(to_json(&records)?)
+}
+
+/// Turn the dry-run audit mode on or off
+///
+/// While it is on, spawn functions run nothing. The resolved program, arguments,
+/// working directory and environment (secret values redacted) of every agent are
+/// recorded instead and callers get results with status "dry_run" and pid 0.
+/// `log_path` also appends the entries to a JSON Lines file. Audit mode can be
+/// enabled at startup with `CDE_AGENT_AUDIT_LOG` set to the log file path.
+#[pyfunction]
+#[pyo3(signature = (enabled, log_path=None))]
+pub fn set_audit_mode_py(enabled: bool, log_path: Option<String>) {
+    audit::set_mode(enabled, log_path.map(std::path::PathBuf::from));
+}
+
+/// Agents recorded in audit mode, oldest first (the latest 1000)
+///
+/// Returns a JSON list of entries with `timestamp`, the requested `command`, the
+/// resolved `program` and `args`, `cwd`, `inherits_env`, explicitly set `env`,
+/// resource limits, `hard_timeout_secs` and `workspace_source`. `clear` empties
+/// the in-memory log after reading it.
+#[pyfunction]
+#[pyo3(signature = (clear=false))]
+pub fn get_audit_log_py(clear: bool) -> PyResult<String> {
+    Ok(to_json(&audit::entries(clear))?)
+}
+
+/// Kill a process together with every sub-process it spawned
+///
+/// Agents are spawned in their own process group, which is signalled as a whole;
+/// descendants that escaped the group are killed individually. Returns JSON with
+/// `killed_pids`, `group_signalled` and `survivors` (empty when the tree is gone).
+#[pyfunction]
+pub fn kill_process_tree_py(py: Python<'_>, pid: u32) -> PyResult<String> {
+    let report = py.detach(|| {
+        let report = tree::kill_tree(pid);
+        // Reap the child and record it as killed if the core owns it
+        registry::kill(pid);
+        report
+    });
+    Ok(to_json(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_agents_to_completion() {
+        let commands = vec![
+            vec!["sh".to_string(), "-c".to_string(), "echo out; echo err >&2; exit 3".to_string()],
+            vec!["sleep".to_string(), "5".to_string()],
+            vec![],
+        ];
+
+        let options = SpawnOptions {
+            hard_timeout_secs: Some(0.2),
+            ..SpawnOptions::default()
+        };
+        let results = run_agents_to_completion(&commands, &options, &OutputOptions::default());
+
+        assert_eq!(results[0].status, "failed");
+        assert_eq!(results[0].exit_code, Some(3));
+        assert_eq!(results[0].stdout, "out\n");
+        assert_eq!(results[0].stderr, "err\n");
+        assert!(results[1].timed_out);
+        assert_eq!(results[1].status, "timeout");
+        assert_eq!(results[1].termination_path.as_deref(), Some("forced"));
+        assert!(results[1].duration_ms < 5000);
+        assert_eq!(results[2].status, "failed_empty");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_soft_timeout_terminates_gracefully() {
+        let cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "trap 'echo bye; exit 0' TERM; sleep 5 & wait".to_string(),
+        ];
+        let options = SpawnOptions {
+            soft_timeout_secs: Some(0.2),
+            hard_timeout_secs: Some(3.0),
+            ..SpawnOptions::default()
+        };
+
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+
+        assert!(result.timed_out);
+        assert_eq!(result.termination_path.as_deref(), Some("graceful"));
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(result.stdout, "bye\n");
+        assert!(result.duration_ms < 3000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_tree_terminates_grandchildren() {
+        let cmd = vec!["sh".to_string(), "-c".to_string(), "sleep 30 & sleep 30 & wait".to_string()];
+        let process = spawn_agent_sync(&cmd, &SpawnOptions::default(), OutputOptions::default()).unwrap();
+
+        // Let the shell fork its children
+        let start = std::time::Instant::now();
+        loop {
+            let mut system = sysinfo::System::new();
+            system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+            if tree::descendants(&system, process.pid).len() == 2 || start.elapsed() > Duration::from_secs(5) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let report = tree::kill_tree(process.pid);
+        registry::kill(process.pid);
+
+        assert!(report.group_signalled);
+        assert_eq!(report.killed_pids.len(), 3);
+        assert!(report.survivors.is_empty());
+        assert_eq!(registry::status(process.pid).unwrap().status, "killed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spawn_options_containment() {
+        let workdir = tempfile::TempDir::new().unwrap();
+        let options = SpawnOptions::from_json(Some(&format!(
+            r#"{{"cwd": {:?}, "env_whitelist": ["PATH"], "cpu_time_secs": 7}}"#,
+            workdir.path().to_str().unwrap()
+        )))
+        .unwrap();
+        let cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "pwd; echo ${HOME:-unset}; ulimit -t".to_string(),
+        ];
+
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+
+        let canonical = workdir.path().canonicalize().unwrap();
+        let lines: Vec<&str> = result.stdout.lines().collect();
+        assert_eq!(result.status, "completed");
+        assert_eq!(std::path::Path::new(lines[0]).canonicalize().unwrap(), canonical);
+        assert_eq!(lines[1], "unset");
+        assert_eq!(lines[2], "7");
+        assert!(SpawnOptions::from_json(Some(r#"{"cwd": "/definitely/missing"}"#)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_templating_and_secret_files() {
+        let options = SpawnOptions::from_json(Some(
+            r#"{"env": {"AGENT_HOME": "${PATH}:extra"}, "secrets": {"API_KEY": "sk-secret-42"}}"#,
+        ))
+        .unwrap();
+        let cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            r#"echo "$AGENT_HOME"; echo "$API_KEY_FILE"; echo "key $(cat "$API_KEY_FILE")""#
+                .to_string(),
+        ];
+
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+
+        let lines: Vec<&str> = result.stdout.lines().collect();
+        assert_eq!(result.status, "completed");
+        assert_eq!(lines[0], format!("{}:extra", std::env::var("PATH").unwrap()));
+        assert!(!std::path::Path::new(lines[1]).exists());
+        assert_eq!(lines[2], "key [REDACTED]");
+        assert!(!result.command.contains("sk-secret-42"));
+        assert!(SpawnOptions::from_json(Some(r#"{"env": {"X": "${CDE_UNDEFINED_VAR}"}}"#)).is_err());
+        assert!(SpawnOptions::from_json(Some(r#"{"secrets": {"BAD-NAME": "x"}}"#)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_structured_result_is_parsed_from_stdout() {
+        let options = SpawnOptions::from_json(Some(
+            r#"{"result_format": {"format": "markers", "required_keys": ["changed"]}}"#,
+        ))
+        .unwrap();
+        let script = r#"echo working; echo ===CDE_RESULT===; echo '{"changed": 2}'; echo ===CDE_RESULT==="#;
+        let cmd = vec!["sh".to_string(), "-c".to_string(), script.to_string()];
+
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+        assert_eq!(result.result, Some(serde_json::json!({"changed": 2})));
+        assert_eq!(result.result_error, None);
+        assert!(result.stdout.starts_with("working\n"));
+
+        let cmd = vec!["echo".to_string(), "no result".to_string()];
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+        assert_eq!(result.result, None);
+        assert!(result.result_error.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_workspace_isolates_agent_edits() {
+        let source = tempfile::TempDir::new().unwrap();
+        std::fs::write(source.path().join("shared.txt"), "original\n").unwrap();
+        let options = SpawnOptions::from_json(Some(&format!(
+            r#"{{"workspace": {{"source": {:?}}}}}"#,
+            source.path().to_str().unwrap()
+        )))
+        .unwrap();
+        let cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo edited > shared.txt; echo hi > new.txt".to_string(),
+        ];
+
+        let result = run_agent_to_completion(&cmd, &options, OutputOptions::default());
+
+        let report = result.workspace.unwrap();
+        assert_eq!(result.status, "completed");
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(report.changes[0].path, "new.txt");
+        assert_eq!(report.changes[1].change, "modified");
+        assert_eq!(report.changes[1].content.as_deref(), Some("edited\n"));
+        assert_eq!(
+            std::fs::read_to_string(source.path().join("shared.txt")).unwrap(),
+            "original\n"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_records_instead_of_running() {
+        let marker = std::env::temp_dir().join(format!("cde-dry-run-{}", std::process::id()));
+        let cmd = vec!["touch".to_string(), marker.to_string_lossy().into_owned()];
+
+        let result = dry_run_result(&cmd, &SpawnOptions::default());
+
+        assert_eq!(result.status, "dry_run");
+        assert_eq!(result.pid, 0);
+        assert!(!marker.exists());
+        let entries = audit::entries(false);
+        let entry = entries.iter().rev().find(|e| e.command == cmd).unwrap();
+        assert_eq!(entry.args, cmd[1..]);
+        assert!(entry.inherits_env);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interactive_stdin_and_pty_session() {
+        let interactive = SpawnOptions {
+            interactive: true,
+            ..SpawnOptions::default()
+        };
+        let cmd = vec!["sh".to_string(), "-c".to_string(), "read x; echo got $x".to_string()];
+        let pid = spawn_process(&cmd, &interactive, OutputOptions::default()).unwrap();
+        assert_eq!(registry::write_stdin(pid, b"hi\n"), Ok(3));
+        let waited = registry::wait(pid, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(waited.stdout, "got hi\n");
+        registry::close_stdin(pid).unwrap();
+        assert!(registry::write_stdin(pid, b"late").is_err());
+
+        let pty = SpawnOptions {
+            pty: true,
+            ..SpawnOptions::default()
+        };
+        let cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            r#"read x; printf '\033[31mgot %s\033[0m\n' "$x"; [ -t 1 ] && echo tty"#.to_string(),
+        ];
+        let pid = spawn_process(&cmd, &pty, OutputOptions::default()).unwrap();
+        registry::write_stdin(pid, b"hello\n").unwrap();
+        let waited = registry::wait(pid, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(waited.process.status, "completed");
+        assert!(waited.stdout.contains("got hello\n"));
+        assert!(waited.stdout.contains("tty\n"));
+        assert!(!waited.stdout.contains('\x1b'));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_pty_session_on_pseudo_console() {
+        let pty = SpawnOptions {
+            pty: true,
+            ..SpawnOptions::default()
+        };
+        let cmd = vec![
+            "cmd".to_string(),
+            "/V:ON".to_string(),
+            "/C".to_string(),
+            "set /p x=& echo got !x!".to_string(),
+        ];
+        let pid = spawn_process(&cmd, &pty, OutputOptions::default()).unwrap();
+        registry::write_stdin(pid, b"hello\r\n").unwrap();
+        let waited = registry::wait(pid, Some(Duration::from_secs(10))).unwrap();
+        assert_eq!(waited.process.status, "completed");
+        assert!(waited.stdout.contains("got hello"));
+        assert!(!waited.stdout.contains('\x1b'));
+    }
+}
@@ -0,0 +1,220 @@
+// rust_core/src/process_manager/monitor.rs
+//! Background health sampling of every managed agent
+//!
+//! A sampler thread records CPU, memory and disk I/O of each running agent (summed
+//! over its whole process tree) at a fixed interval, keeping a bounded time series
+//! per PID so the orchestrator can spot runaway agents from trends.
+
+use super::registry;
+use super::tree;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessesToUpdate, System};
+
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Samples kept per agent (10 minutes at the default interval)
+const MAX_SAMPLES_PER_AGENT: usize = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub timestamp: String,
+    pub cpu_usage: f32,
+    pub memory_mb: u64,
+    pub disk_read_bytes: u64,
+    pub disk_written_bytes: u64,
+    /// Processes in the agent's tree, including the agent itself
+    pub process_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentHealthHistory {
+    pub pid: u32,
+    pub command: String,
+    pub status: String,
+    pub samples: Vec<HealthSample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorSnapshot {
+    pub interval_ms: u128,
+    pub agents: Vec<AgentHealthHistory>,
+}
+
+struct MonitorState {
+    interval: Duration,
+    history: HashMap<u32, VecDeque<HealthSample>>,
+    started: bool,
+}
+
+fn monitor_state() -> &'static Mutex<MonitorState> {
+    static STATE: OnceLock<Mutex<MonitorState>> = OnceLock::new();
+    STATE.get_or_init(|| {
+        Mutex::new(MonitorState {
+            interval: DEFAULT_SAMPLE_INTERVAL,
+            history: HashMap::new(),
+            started: false,
+        })
+    })
+}
+
+/// Starts the sampler thread (once) and updates its interval
+pub fn start(interval: Duration) {
+    let mut state = monitor_state().lock().unwrap();
+    state.interval = interval.max(Duration::from_millis(10));
+    if state.started {
+        return;
+    }
+    state.started = true;
+    thread::spawn(|| {
+        let mut system = System::new();
+        loop {
+            let interval = {
+                let mut state = monitor_state().lock().unwrap();
+                sample_all(&mut system, &mut state.history);
+                state.interval
+            };
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// Starts the sampler thread at its current interval unless it already runs
+pub fn ensure_started() {
+    let interval = monitor_state().lock().unwrap().interval;
+    start(interval);
+}
+
+/// Records one sample for every running managed agent and forgets agents the
+/// registry no longer knows about
+fn sample_all(system: &mut System, history: &mut HashMap<u32, VecDeque<HealthSample>>) {
+    let processes = registry::list();
+    history.retain(|pid, _| processes.iter().any(|p| p.pid == *pid));
+
+    let running: Vec<u32> = processes
+        .iter()
+        .filter(|p| p.is_running())
+        .map(|p| p.pid)
+        .collect();
+    if running.is_empty() {
+        return;
+    }
+
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let timestamp = chrono::Local::now().to_rfc3339();
+    for pid in running {
+        let Some(sample) = sample_tree(system, pid, &timestamp) else {
+            continue;
+        };
+        let samples = history.entry(pid).or_default();
+        if samples.len() >= MAX_SAMPLES_PER_AGENT {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+}
+
+/// Sums usage over `pid` and all its descendants
+fn sample_tree(system: &System, pid: u32, timestamp: &str) -> Option<HealthSample> {
+    system.process(Pid::from_u32(pid))?;
+    let mut sample = HealthSample {
+        timestamp: timestamp.to_string(),
+        cpu_usage: 0.0,
+        memory_mb: 0,
+        disk_read_bytes: 0,
+        disk_written_bytes: 0,
+        process_count: 0,
+    };
+    let mut memory_bytes = 0;
+    for member in std::iter::once(pid).chain(tree::descendants(system, pid)) {
+        if let Some(process) = system.process(Pid::from_u32(member)) {
+            let disk = process.disk_usage();
+            sample.cpu_usage += process.cpu_usage();
+            memory_bytes += process.memory();
+            sample.disk_read_bytes += disk.total_read_bytes;
+            sample.disk_written_bytes += disk.total_written_bytes;
+            sample.process_count += 1;
+        }
+    }
+    sample.memory_mb = memory_bytes / 1024 / 1024;
+    Some(sample)
+}
+
+/// Highest CPU usage and memory (MiB) among the kept samples of `pid`
+pub fn peaks(pid: u32) -> Option<(f32, u64)> {
+    let state = monitor_state().lock().unwrap();
+    let samples = state.history.get(&pid).filter(|s| !s.is_empty())?;
+    Some(samples.iter().fold((0.0, 0), |(cpu, memory), sample| {
+        (cpu.max(sample.cpu_usage), memory.max(sample.memory_mb))
+    }))
+}
+
+/// Time series of every managed agent, limited to the latest `max_samples` each
+pub fn snapshot(max_samples: Option<usize>) -> MonitorSnapshot {
+    let state = monitor_state().lock().unwrap();
+    let agents = registry::list()
+        .into_iter()
+        .map(|process| {
+            let samples = state
+                .history
+                .get(&process.pid)
+                .map(|samples| {
+                    let skip = max_samples.map_or(0, |max| samples.len().saturating_sub(max));
+                    samples.iter().skip(skip).cloned().collect()
+                })
+                .unwrap_or_default();
+            AgentHealthHistory {
+                pid: process.pid,
+                command: process.command,
+                status: process.status,
+                samples,
+            }
+        })
+        .collect();
+
+    MonitorSnapshot {
+        interval_ms: state.interval.as_millis(),
+        agents,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_manager::registry::{OutputOptions, Timeouts};
+    use std::process::{Command, Stdio};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sample_all_records_agent_tree() {
+        let child = Command::new("sh")
+            .args(["-c", "sleep 5 & wait"])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = registry::register(
+            child,
+            "sh".to_string(),
+            OutputOptions::default(),
+            Timeouts::default(),
+        );
+        thread::sleep(Duration::from_millis(100));
+
+        let mut system = System::new();
+        let mut history = HashMap::new();
+        history.insert(u32::MAX, VecDeque::new());
+        sample_all(&mut system, &mut history);
+        sample_all(&mut system, &mut history);
+
+        assert!(!history.contains_key(&u32::MAX));
+        let samples = &history[&pid];
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].process_count, 2);
+
+        crate::process_manager::tree::kill_tree(pid);
+        registry::kill(pid);
+    }
+}
@@ -0,0 +1,221 @@
+// rust_core/src/process_manager/options.rs
+//! Per-agent spawn options: working directory, environment and resource limits
+//!
+//! Agent CLIs are untrusted, so the core can contain them with CPU time and memory
+//! limits (rlimits on Unix, the agent's job object on Windows), a scrubbed
+//! environment and, on Linux, an empty network namespace.
+//! Extra variables may reference the server environment as `${VAR}`; secrets never
+//! enter the environment or argv directly (see `secrets`).
+
+use super::registry::Timeouts;
+use super::secrets;
+use super::structured::ResultFormat;
+use super::supervisor::RestartPolicy;
+use super::workspace::WorkspaceOptions;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SpawnOptions {
+    /// Working directory of the agent (defaults to the server's cwd)
+    pub cwd: Option<String>,
+    /// When set, only these variables are inherited from the server environment
+    pub env_whitelist: Option<Vec<String>>,
+    /// Extra variables for the agent; values may reference the server environment as `${VAR}`
+    pub env: BTreeMap<String, String>,
+    /// Secret values written to owner-only temp files and exposed as `<NAME>_FILE`;
+    /// the values are redacted from captured output
+    pub secrets: BTreeMap<String, String>,
+    /// CPU time limit in seconds (RLIMIT_CPU); the agent gets SIGXCPU/SIGKILL past it.
+    /// On Windows it is the user time of the agent's whole job
+    pub cpu_time_secs: Option<u64>,
+    /// Address-space limit in MiB (RLIMIT_AS); on Windows the committed memory of the
+    /// agent's whole job
+    pub max_memory_mb: Option<u64>,
+    /// Run the agent in an empty network namespace (Linux only)
+    pub disable_network: bool,
+    /// Scheduling priority; higher starts first, equal priorities run FIFO
+    pub priority: i32,
+    /// Seconds after which the agent is asked to exit (SIGTERM / CTRL_BREAK)
+    pub soft_timeout_secs: Option<f64>,
+    /// Seconds after which the agent is killed
+    pub hard_timeout_secs: Option<f64>,
+    /// Seconds without any output after which the agent reports status "stalled"
+    pub stall_timeout_secs: Option<f64>,
+    /// Kill the agent once it stalls (status "timeout", termination path "stalled")
+    pub terminate_on_stall: bool,
+    /// Whether and how the agent is restarted after it exits
+    pub restart: RestartPolicy,
+    /// Keep stdin open so the orchestrator can answer prompts (`write_stdin_py`)
+    pub interactive: bool,
+    /// Run the agent on a pseudo-terminal (a ConPTY pseudo console on Windows);
+    /// implies `interactive`, and stdout/stderr are merged into one ANSI-stripped
+    /// "stdout" stream
+    pub pty: bool,
+    /// Parse a structured result out of stdout when the agent is awaited
+    pub result_format: Option<ResultFormat>,
+    /// Run the agent in a private copy of the source tree and report its changes
+    pub workspace: Option<WorkspaceOptions>,
+}
+
+impl SpawnOptions {
+    /// Parses options from the JSON accepted by the Python API
+    pub fn from_json(options_json: Option<&str>) -> Result<Self, String> {
+        let options: SpawnOptions = match options_json {
+            Some(json) => {
+                serde_json::from_str(json).map_err(|e| format!("Invalid spawn options: {}", e))?
+            }
+            None => SpawnOptions::default(),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Rejects options that cannot be honored, instead of silently running unconfined
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(cwd) = &self.cwd {
+            if !Path::new(cwd).is_dir() {
+                return Err(format!("'{}' is not a valid directory.", cwd));
+            }
+        }
+        if !cfg!(any(unix, windows))
+            && (self.cpu_time_secs.is_some() || self.max_memory_mb.is_some())
+        {
+            return Err("CPU and memory limits are only supported on Unix and Windows".to_string());
+        }
+        for timeout in [
+            self.soft_timeout_secs,
+            self.hard_timeout_secs,
+            self.stall_timeout_secs,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !timeout.is_finite() || timeout < 0.0 {
+                return Err(format!("Invalid timeout: {}", timeout));
+            }
+        }
+        if self.terminate_on_stall && self.stall_timeout_secs.is_none() {
+            return Err("terminate_on_stall requires stall_timeout_secs".to_string());
+        }
+        self.restart.validate()?;
+        if let Some(result_format) = &self.result_format {
+            result_format.validate()?;
+        }
+        if let Some(workspace) = &self.workspace {
+            workspace.validate()?;
+        }
+        for name in self.env.keys().chain(self.secrets.keys()) {
+            if !secrets::is_valid_name(name) {
+                return Err(format!("Invalid environment variable name '{}'", name));
+            }
+        }
+        self.resolved_env()?;
+        if !cfg!(any(unix, windows)) && self.pty {
+            return Err("PTY mode is only supported on Unix and Windows".to_string());
+        }
+        if !cfg!(target_os = "linux") && self.disable_network {
+            return Err("Network isolation is only supported on Linux".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            soft: self.soft_timeout_secs.map(Duration::from_secs_f64),
+            hard: self.hard_timeout_secs.map(Duration::from_secs_f64),
+            stall: self.stall_timeout_secs.map(Duration::from_secs_f64),
+            kill_on_stall: self.terminate_on_stall,
+        }
+    }
+
+    /// The `env` map with `${VAR}` references to the server environment expanded
+    pub fn resolved_env(&self) -> Result<Vec<(String, String)>, String> {
+        self.env
+            .iter()
+            .map(|(name, template)| {
+                let value = secrets::expand(template, |var| std::env::var(var).ok())?;
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Secret values to redact from the agent's output
+    pub fn redactions(&self) -> Vec<String> {
+        self.secrets.values().cloned().collect()
+    }
+
+    /// Applies the options to a command before it is spawned
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        if let Some(whitelist) = &self.env_whitelist {
+            command.env_clear();
+            for name in whitelist {
+                if let Some(value) = std::env::var_os(name) {
+                    command.env(name, value);
+                }
+            }
+        }
+
+        // Validated when the options were parsed
+        command.envs(self.resolved_env().unwrap_or_default());
+
+        #[cfg(unix)]
+        self.apply_limits(command);
+    }
+
+    #[cfg(unix)]
+    fn apply_limits(&self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        let cpu_time_secs = self.cpu_time_secs;
+        let max_memory_bytes = self.max_memory_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+        let disable_network = self.disable_network;
+        if cpu_time_secs.is_none() && max_memory_bytes.is_none() && !disable_network {
+            return;
+        }
+
+        // SAFETY: the hook runs between fork and exec and only calls
+        // async-signal-safe functions (setrlimit, unshare) on captured integers
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(secs) = cpu_time_secs {
+                    set_rlimit(libc::RLIMIT_CPU, secs)?;
+                }
+                if let Some(bytes) = max_memory_bytes {
+                    set_rlimit(libc::RLIMIT_AS, bytes)?;
+                }
+                #[cfg(target_os = "linux")]
+                if disable_network && libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
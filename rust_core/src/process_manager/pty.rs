@@ -0,0 +1,249 @@
+// rust_core/src/process_manager/pty.rs
+//! Pseudo-terminal support for interactive agent sessions
+//!
+//! Some agent CLIs only prompt (or only print progress) when attached to a terminal.
+//! PTY mode gives them one owned by the core; output is read from the master side
+//! with ANSI escape sequences stripped, and input is written to it.
+//!
+//! On Unix the agent is a `std` child made the leader of a new session whose
+//! controlling terminal is a pseudo-terminal opened with `openpty`. On Windows it
+//! is spawned by `portable-pty` on a ConPTY pseudo console, so the registry keeps
+//! it as a `ConsoleChild` rather than a `std` child; it is still put in a job
+//! object, which is how Windows agents are limited and killed.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Terminal size reported to agents
+#[cfg(any(unix, windows))]
+const ROWS: u16 = 50;
+#[cfg(any(unix, windows))]
+const COLUMNS: u16 = 200;
+
+/// Removes ANSI escape sequences (CSI, OSC and two-byte escapes) and carriage returns
+pub fn strip_ansi(text: &str) -> String {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let re = ANSI.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[=>@-Z\\-_]")
+            .expect("valid ANSI regex")
+    });
+    re.replace_all(text, "").replace('\r', "")
+}
+
+#[cfg(unix)]
+pub use unix::open_pty;
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    use super::{COLUMNS, ROWS};
+
+    /// Both ends of a freshly opened pseudo-terminal
+    pub struct Pty {
+        pub master: File,
+        slave: OwnedFd,
+    }
+
+    pub fn open_pty() -> io::Result<Pty> {
+        let mut master = -1;
+        let mut slave = -1;
+        let size = libc::winsize {
+            ws_row: ROWS,
+            ws_col: COLUMNS,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: out-pointers are valid and the name/termios arguments may be null
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &size,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: openpty succeeded, so both descriptors are open and owned by us
+        unsafe {
+            Ok(Pty {
+                master: File::from_raw_fd(master),
+                slave: OwnedFd::from_raw_fd(slave),
+            })
+        }
+    }
+
+    impl Pty {
+        /// Attaches the command to the slave side as its controlling terminal
+        ///
+        /// The child becomes a session leader, which also makes it a process group
+        /// leader, so the command must not request a process group of its own.
+        pub fn attach(&self, command: &mut Command) -> io::Result<()> {
+            command
+                .stdin(Stdio::from(self.slave.try_clone()?))
+                .stdout(Stdio::from(self.slave.try_clone()?))
+                .stderr(Stdio::from(self.slave.try_clone()?));
+
+            // SAFETY: setsid and ioctl are async-signal-safe and only use stdin's fd
+            unsafe {
+                command.pre_exec(|| {
+                    if libc::setsid() < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    if libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+            Ok(())
+        }
+
+        /// Closes the parent's copy of the slave side; call once the child is spawned
+        /// so reads on the master report EOF when the session ends
+        pub fn into_master(self) -> File {
+            self.master
+        }
+    }
+}
+
+#[cfg(windows)]
+pub use windows::{spawn_console, ConsoleChild, ConsoleSession};
+
+#[cfg(windows)]
+mod windows {
+    use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, MasterPty, PtySize};
+    use std::io::{self, Read, Write};
+    use std::os::windows::io::RawHandle;
+    use std::os::windows::process::ExitStatusExt;
+    use std::process::{Command, ExitStatus};
+
+    use super::{COLUMNS, ROWS};
+
+    /// An agent attached to a pseudo console
+    pub struct ConsoleChild {
+        pid: u32,
+        child: Box<dyn Child + Send + Sync>,
+        /// Closed once the agent exits: until then reads of its output never end
+        console: Option<Box<dyn MasterPty + Send>>,
+    }
+
+    /// A freshly spawned agent with both ends of its pseudo console
+    pub struct ConsoleSession {
+        pub child: ConsoleChild,
+        pub output: Box<dyn Read + Send>,
+        pub input: Box<dyn Write + Send>,
+    }
+
+    fn pty_error(e: impl std::fmt::Display) -> io::Error {
+        io::Error::other(format!("Failed to set up pseudo console: {}", e))
+    }
+
+    /// Program, arguments, directory and environment of `command`; `clear_env`
+    /// when it does not inherit the environment of the core
+    fn builder(command: &Command, clear_env: bool) -> CommandBuilder {
+        let mut builder = CommandBuilder::new(command.get_program());
+        builder.args(command.get_args());
+        if let Some(dir) = command.get_current_dir() {
+            builder.cwd(dir);
+        }
+        if clear_env {
+            builder.env_clear();
+        }
+        for (name, value) in command.get_envs() {
+            match value {
+                Some(value) => builder.env(name, value),
+                None => builder.env_remove(name),
+            }
+        }
+        builder
+    }
+
+    /// Spawns `command` on a new pseudo console
+    pub fn spawn_console(command: &Command, clear_env: bool) -> io::Result<ConsoleSession> {
+        let size = PtySize {
+            rows: ROWS,
+            cols: COLUMNS,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = native_pty_system().openpty(size).map_err(pty_error)?;
+        let mut child = pair
+            .slave
+            .spawn_command(builder(command, clear_env))
+            .map_err(pty_error)?;
+        let streams = pair
+            .master
+            .try_clone_reader()
+            .and_then(|output| Ok((output, pair.master.take_writer()?)));
+        let (output, input, pid) = match (streams, child.process_id()) {
+            (Ok((output, input)), Some(pid)) => (output, input, pid),
+            (streams, _) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(pty_error(streams.err().map_or_else(
+                    || "agent exited before it could be managed".to_string(),
+                    |e| e.to_string(),
+                )));
+            }
+        };
+        Ok(ConsoleSession {
+            child: ConsoleChild {
+                pid,
+                child,
+                console: Some(pair.master),
+            },
+            output,
+            input,
+        })
+    }
+
+    impl ConsoleChild {
+        pub fn id(&self) -> u32 {
+            self.pid
+        }
+
+        pub fn as_raw_handle(&self) -> Option<RawHandle> {
+            self.child.as_raw_handle()
+        }
+
+        pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+            let status = self.child.try_wait()?;
+            Ok(status.map(|status| self.exited(status)))
+        }
+
+        pub fn wait(&mut self) -> io::Result<ExitStatus> {
+            let status = self.child.wait()?;
+            Ok(self.exited(status))
+        }
+
+        pub fn kill(&mut self) -> io::Result<()> {
+            self.child.kill()
+        }
+
+        fn exited(&mut self, status: portable_pty::ExitStatus) -> ExitStatus {
+            self.console = None;
+            ExitStatus::from_raw(status.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1;31mred\x1b[0m text\r\n\x1b]0;title\x07done\x1b="),
+            "red text\ndone"
+        );
+    }
+}
@@ -0,0 +1,958 @@
+// rust_core/src/process_manager/registry.rs
+//! Global registry owning every child process spawned by the Rust core
+//!
+//! Children used to be dropped right after spawning, losing exit codes and output.
+//! The registry keeps the child handle (`std` child, or the pseudo console child of
+//! a Windows PTY agent), drains its pipes on background threads and reaps it on
+//! demand, so status and exit codes are exact instead of relying on sysinfo
+//! process-table scans.
+
+use super::secrets::SecretFiles;
+use super::workspace::Workspace;
+use crate::error::CdeError;
+use crate::events;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bytes of stdout/stderr kept per process (the tail is kept when exceeded)
+const MAX_CAPTURED_BYTES: usize = 1024 * 1024;
+
+/// Output lines kept per process for polling when the caller does not choose a limit
+pub const DEFAULT_MAX_BUFFERED_LINES: usize = 10_000;
+
+/// Finished processes kept for status queries before the oldest are dropped
+const MAX_FINISHED_ENTRIES: usize = 256;
+
+/// How often the watchdog checks timeout deadlines
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to keep reading pipes after the child exited (grandchildren may hold them open)
+const PIPE_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub type ProcessRegistry = Arc<Mutex<HashMap<u32, ManagedChild>>>;
+
+/// Receives every output line as soon as it is read
+///
+/// Called on the pipe reader thread: a slow sink stalls the reader, which fills
+/// the OS pipe and in turn blocks the agent, giving natural backpressure.
+pub type OutputSink = Arc<dyn Fn(u32, &OutputLine) + Send + Sync>;
+
+/// How a registered process's output is delivered
+#[derive(Clone)]
+pub struct OutputOptions {
+    pub sink: Option<OutputSink>,
+    pub max_buffered_lines: usize,
+    /// Remove ANSI escape sequences from captured output (used for PTY sessions)
+    pub strip_ansi: bool,
+    /// Secret values replaced with `[REDACTED]` in captured output
+    pub redactions: Vec<String>,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            sink: None,
+            max_buffered_lines: DEFAULT_MAX_BUFFERED_LINES,
+            strip_ansi: false,
+            redactions: Vec::new(),
+        }
+    }
+}
+
+/// Per-process deadlines, measured from spawn (`stall`: from the last output line)
+///
+/// Past `soft` the process group gets SIGTERM (Windows: `taskkill /T` without `/F`);
+/// past `hard` it is killed. A running process silent for `stall` reports status
+/// "stalled", and is killed when `kill_on_stall` is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timeouts {
+    pub soft: Option<Duration>,
+    pub hard: Option<Duration>,
+    pub stall: Option<Duration>,
+    pub kill_on_stall: bool,
+}
+
+/// One line of agent output, numbered across both streams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputLine {
+    pub seq: u64,
+    pub stream: String, // "stdout", "stderr"
+    pub line: String,
+}
+
+/// Ring buffer of the most recent output lines of a process
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    pub lines: VecDeque<OutputLine>,
+    pub next_seq: u64,
+    pub capacity: usize,
+    pub dropped: u64,
+}
+
+impl LineBuffer {
+    fn push(&mut self, stream: &str, line: String) -> OutputLine {
+        let entry = OutputLine {
+            seq: self.next_seq,
+            stream: stream.to_string(),
+            line,
+        };
+        self.next_seq += 1;
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        if self.capacity > 0 {
+            self.lines.push_back(entry.clone());
+        } else {
+            self.dropped += 1;
+        }
+        entry
+    }
+}
+
+/// Lines returned by a poll, with the cursor to pass to the next poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputChunk {
+    pub pid: u32,
+    pub lines: Vec<OutputLine>,
+    pub next_seq: u64,
+    /// Lines evicted from the ring buffer before they could be read
+    pub dropped_lines: u64,
+    /// True once the process exited and both pipes reached EOF
+    pub finished: bool,
+}
+
+/// Captured output of one stream, bounded to `MAX_CAPTURED_BYTES`
+#[derive(Debug, Default)]
+pub struct OutputBuffer {
+    pub bytes: Vec<u8>,
+    pub total_bytes: usize,
+    pub closed: bool,
+}
+
+impl OutputBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        self.total_bytes += chunk.len();
+        self.bytes.extend_from_slice(chunk);
+        if self.bytes.len() > MAX_CAPTURED_BYTES {
+            let excess = self.bytes.len() - MAX_CAPTURED_BYTES;
+            self.bytes.drain(..excess);
+        }
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+}
+
+/// Streams connected to a child: pipes, or both ends of a PTY master
+#[derive(Default)]
+pub struct ChildIo {
+    pub stdout: Option<Box<dyn Read + Send>>,
+    pub stderr: Option<Box<dyn Read + Send>>,
+    pub stdin: Option<Box<dyn Write + Send>>,
+}
+
+impl ChildIo {
+    /// Takes whatever pipes the child was spawned with
+    pub fn from_child(child: &mut Child) -> Self {
+        Self {
+            stdout: child
+                .stdout
+                .take()
+                .map(|p| Box::new(p) as Box<dyn Read + Send>),
+            stderr: child
+                .stderr
+                .take()
+                .map(|p| Box::new(p) as Box<dyn Read + Send>),
+            stdin: child
+                .stdin
+                .take()
+                .map(|p| Box::new(p) as Box<dyn Write + Send>),
+        }
+    }
+}
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Handle of a managed process
+pub enum AgentChild {
+    Process(Child),
+    /// Agent running on a ConPTY pseudo console
+    #[cfg(windows)]
+    Console(super::pty::ConsoleChild),
+}
+
+impl From<Child> for AgentChild {
+    fn from(child: Child) -> Self {
+        Self::Process(child)
+    }
+}
+
+#[cfg(windows)]
+impl From<super::pty::ConsoleChild> for AgentChild {
+    fn from(child: super::pty::ConsoleChild) -> Self {
+        Self::Console(child)
+    }
+}
+
+impl AgentChild {
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Process(child) => child.id(),
+            #[cfg(windows)]
+            Self::Console(child) => child.id(),
+        }
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        match self {
+            Self::Process(child) => child.try_wait(),
+            #[cfg(windows)]
+            Self::Console(child) => child.try_wait(),
+        }
+    }
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        match self {
+            Self::Process(child) => child.wait(),
+            #[cfg(windows)]
+            Self::Console(child) => child.wait(),
+        }
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Process(child) => child.kill(),
+            #[cfg(windows)]
+            Self::Console(child) => child.kill(),
+        }
+    }
+}
+
+/// A child process owned by the registry
+pub struct ManagedChild {
+    pub child: AgentChild,
+    pub command: String,
+    pub started_at: String,
+    pub started: Instant,
+    pub finished: Option<Instant>,
+    pub exit_status: Option<ExitStatus>,
+    /// Set when the core terminated the process ("killed", "timeout")
+    pub termination: Option<String>,
+    /// How a timed-out process ended: "graceful" or "forced"
+    pub termination_path: Option<String>,
+    pub timeouts: Timeouts,
+    pub terminate_sent: bool,
+    pub stdout: Arc<Mutex<OutputBuffer>>,
+    pub stderr: Arc<Mutex<OutputBuffer>>,
+    pub lines: Arc<Mutex<LineBuffer>>,
+    /// Present for interactive agents until stdin is closed
+    pub stdin: Option<SharedWriter>,
+    /// Secret files of the agent, deleted as soon as it is seen to have exited
+    pub secret_files: Option<SecretFiles>,
+    /// Private copy of the source tree the agent runs in
+    pub workspace: Option<Arc<Workspace>>,
+    /// When the last output line was read (spawn time until the first one)
+    pub last_output: Arc<Mutex<Instant>>,
+    /// Whether the sink was told about the current stall
+    pub stall_reported: bool,
+    pub sink: Option<OutputSink>,
+}
+
+impl ManagedChild {
+    /// Reaps the child if it exited; returns true while it is still running
+    fn refresh(&mut self) -> bool {
+        let running = self.poll();
+        if !running {
+            self.secret_files = None;
+        }
+        running
+    }
+
+    fn poll(&mut self) -> bool {
+        if self.exit_status.is_some() {
+            return false;
+        }
+        match self.child.try_wait() {
+            Ok(Some(status)) => {
+                self.exit_status = Some(status);
+                self.finished = Some(Instant::now());
+                if self.terminate_sent && self.termination.is_none() {
+                    self.termination = Some("timeout".to_string());
+                    self.termination_path = Some("graceful".to_string());
+                }
+                self.publish_exit();
+                false
+            }
+            Ok(None) => true,
+            Err(_) => {
+                self.finished = Some(Instant::now());
+                self.publish_exit();
+                false
+            }
+        }
+    }
+
+    /// Tells the event bus the process was seen to exit (once per process)
+    fn publish_exit(&self) {
+        if events::has_subscribers() {
+            events::publish("process", "process.exited", self.info());
+        }
+    }
+
+    fn status(&self) -> String {
+        if let Some(termination) = &self.termination {
+            return termination.clone();
+        }
+        match (self.finished, self.exit_status) {
+            (None, _) if self.is_stalled() => "stalled",
+            (None, _) => "running",
+            (Some(_), Some(status)) if status.success() => "completed",
+            (Some(_), Some(status)) if status.code().is_none() => "killed",
+            _ => "failed",
+        }
+        .to_string()
+    }
+
+    /// Whether a running process has been silent for longer than its stall timeout
+    fn is_stalled(&self) -> bool {
+        self.timeouts
+            .stall
+            .is_some_and(|stall| self.last_output.lock().unwrap().elapsed() >= stall)
+    }
+
+    /// Kills the whole process group and records why
+    fn kill_group(&mut self, termination_path: &str) {
+        super::tree::signal_group(self.child.id());
+        let _ = self.child.kill();
+        self.exit_status = self.child.wait().ok();
+        self.finished = Some(Instant::now());
+        self.termination = Some("timeout".to_string());
+        self.termination_path = Some(termination_path.to_string());
+        self.publish_exit();
+    }
+
+    /// Applies the soft/hard/stall deadlines to a running process
+    fn enforce_timeouts(&mut self) {
+        let elapsed = self.started.elapsed();
+        if self.timeouts.hard.is_some_and(|hard| elapsed >= hard) {
+            self.kill_group("forced");
+        } else if self.timeouts.kill_on_stall && self.is_stalled() {
+            self.kill_group("stalled");
+        } else if !self.terminate_sent && self.timeouts.soft.is_some_and(|soft| elapsed >= soft) {
+            self.terminate_sent = true;
+            super::tree::terminate_group(self.child.id());
+        }
+    }
+
+    pub fn info(&self) -> ProcessInfo {
+        let end = self.finished.unwrap_or_else(Instant::now);
+        ProcessInfo {
+            pid: self.child.id(),
+            command: self.command.clone(),
+            status: self.status(),
+            exit_code: self.exit_status.and_then(|s| s.code()),
+            started_at: self.started_at.clone(),
+            runtime_ms: end.duration_since(self.started).as_millis(),
+            termination_path: self.termination_path.clone(),
+        }
+    }
+}
+
+/// Serializable snapshot of a managed process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub command: String,
+    pub status: String, // "running", "stalled", "completed", "failed", "killed", "timeout"
+    pub exit_code: Option<i32>,
+    pub started_at: String,
+    pub runtime_ms: u128,
+    pub termination_path: Option<String>, // "graceful", "forced", "stalled" when a timeout fired
+}
+
+impl ProcessInfo {
+    /// True until the process exits, whether or not it is producing output
+    pub fn is_running(&self) -> bool {
+        matches!(self.status.as_str(), "running" | "stalled")
+    }
+}
+
+/// Result of waiting on a managed process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessWaitResult {
+    pub process: ProcessInfo,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The process-wide registry
+pub fn registry() -> &'static ProcessRegistry {
+    static REGISTRY: OnceLock<ProcessRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Arc::new(Mutex::new(HashMap::new())))
+}
+
+/// Takes ownership of a freshly spawned child and starts draining its pipes
+pub fn register(
+    mut child: Child,
+    command: String,
+    output: OutputOptions,
+    timeouts: Timeouts,
+) -> u32 {
+    let io = ChildIo::from_child(&mut child);
+    register_with_io(child, io, command, output, timeouts)
+}
+
+/// Like `register`, for children whose streams are not plain pipes (PTY sessions)
+pub fn register_with_io(
+    child: impl Into<AgentChild>,
+    io: ChildIo,
+    command: String,
+    output: OutputOptions,
+    timeouts: Timeouts,
+) -> u32 {
+    let child = child.into();
+    let pid = child.id();
+    let stdout = Arc::new(Mutex::new(OutputBuffer::default()));
+    let stderr = Arc::new(Mutex::new(OutputBuffer::default()));
+    let lines = Arc::new(Mutex::new(LineBuffer {
+        capacity: output.max_buffered_lines,
+        ..LineBuffer::default()
+    }));
+    let last_output = Arc::new(Mutex::new(Instant::now()));
+    let reader = PipeReader {
+        pid,
+        lines: Arc::clone(&lines),
+        last_output: Arc::clone(&last_output),
+        sink: output.sink.clone(),
+        strip_ansi: output.strip_ansi,
+        redactions: output.redactions,
+    };
+    reader.spawn("stdout", io.stdout, Arc::clone(&stdout));
+    reader.spawn("stderr", io.stderr, Arc::clone(&stderr));
+
+    let managed = ManagedChild {
+        child,
+        command,
+        started_at: chrono::Local::now().to_rfc3339(),
+        started: Instant::now(),
+        finished: None,
+        exit_status: None,
+        termination: None,
+        termination_path: None,
+        timeouts,
+        terminate_sent: false,
+        stdout,
+        stderr,
+        lines,
+        stdin: io.stdin.map(|w| Arc::new(Mutex::new(w))),
+        secret_files: None,
+        workspace: None,
+        last_output,
+        stall_reported: false,
+        sink: output.sink,
+    };
+
+    let info = managed.info();
+    let mut processes = registry().lock().unwrap();
+    prune_finished(&mut processes);
+    processes.insert(pid, managed);
+    drop(processes);
+    events::publish("process", "process.started", info);
+
+    if timeouts.soft.is_some() || timeouts.hard.is_some() || timeouts.stall.is_some() {
+        start_watchdog();
+    }
+    pid
+}
+
+/// Hands the agent's secret files to the registry, which deletes them once it exits
+pub fn attach_secrets(pid: u32, files: SecretFiles) {
+    let mut processes = registry().lock().unwrap();
+    if let Some(managed) = processes.get_mut(&pid) {
+        if managed.refresh() {
+            managed.secret_files = Some(files);
+        }
+    }
+}
+
+/// Hands the agent's workspace to the registry, which keeps it until the entry is pruned
+pub fn attach_workspace(pid: u32, workspace: Workspace) {
+    if let Some(managed) = registry().lock().unwrap().get_mut(&pid) {
+        managed.workspace = Some(Arc::new(workspace));
+    }
+}
+
+/// The workspace a managed process runs in, if it has one
+pub fn workspace(pid: u32) -> Option<Arc<Workspace>> {
+    registry().lock().unwrap().get(&pid)?.workspace.clone()
+}
+
+/// Starts the thread enforcing timeouts of every managed process (once)
+///
+/// Stall changes are reported to output sinks as `stream: "status"` lines
+/// ("stalled", then "active" once output resumes), outside the registry lock.
+fn start_watchdog() {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    STARTED.get_or_init(|| {
+        thread::spawn(|| loop {
+            thread::sleep(WATCHDOG_INTERVAL);
+            let mut events = Vec::new();
+            {
+                let mut processes = registry().lock().unwrap();
+                for (pid, managed) in processes.iter_mut() {
+                    if managed.refresh() {
+                        managed.enforce_timeouts();
+                    }
+                    let stalled = managed.finished.is_none() && managed.is_stalled();
+                    if stalled != managed.stall_reported {
+                        managed.stall_reported = stalled;
+                        let kind = if stalled {
+                            "process.stalled"
+                        } else {
+                            "process.active"
+                        };
+                        events::publish("process", kind, serde_json::json!({ "pid": pid }));
+                        if let Some(sink) = &managed.sink {
+                            let line = OutputLine {
+                                seq: managed.lines.lock().unwrap().next_seq,
+                                stream: "status".to_string(),
+                                line: if stalled { "stalled" } else { "active" }.to_string(),
+                            };
+                            events.push((*pid, Arc::clone(sink), line));
+                        }
+                    }
+                }
+            }
+            for (pid, sink, line) in events {
+                sink(pid, &line);
+            }
+        });
+    });
+}
+
+/// Shared state of the two reader threads of one process
+#[derive(Clone)]
+struct PipeReader {
+    pid: u32,
+    lines: Arc<Mutex<LineBuffer>>,
+    last_output: Arc<Mutex<Instant>>,
+    sink: Option<OutputSink>,
+    strip_ansi: bool,
+    redactions: Vec<String>,
+}
+
+impl PipeReader {
+    fn spawn<R: Read + Send + 'static>(
+        &self,
+        stream: &'static str,
+        pipe: Option<R>,
+        buffer: Arc<Mutex<OutputBuffer>>,
+    ) {
+        let Some(pipe) = pipe else {
+            buffer.lock().unwrap().closed = true;
+            return;
+        };
+        let reader = self.clone();
+        thread::spawn(move || {
+            let mut pipe = BufReader::new(pipe);
+            let mut raw = Vec::new();
+            loop {
+                raw.clear();
+                match pipe.read_until(b'\n', &mut raw) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                *reader.last_output.lock().unwrap() = Instant::now();
+                let mut text = String::from_utf8_lossy(&raw).into_owned();
+                if reader.strip_ansi {
+                    text = super::pty::strip_ansi(&text);
+                }
+                if !reader.redactions.is_empty() {
+                    text = super::secrets::redact(&text, &reader.redactions);
+                }
+                buffer.lock().unwrap().push(text.as_bytes());
+                let line = text.trim_end_matches(['\n', '\r']).to_string();
+                let entry = reader.lines.lock().unwrap().push(stream, line);
+                if let Some(sink) = &reader.sink {
+                    sink(reader.pid, &entry);
+                }
+                if events::has_subscribers() {
+                    let payload = serde_json::json!({
+                        "pid": reader.pid,
+                        "seq": entry.seq,
+                        "stream": entry.stream,
+                        "line": entry.line,
+                    });
+                    events::publish("process", "process.output", payload);
+                }
+            }
+            buffer.lock().unwrap().closed = true;
+        });
+    }
+}
+
+/// Drops the oldest finished entries once too many have accumulated
+fn prune_finished(processes: &mut HashMap<u32, ManagedChild>) {
+    let mut finished: Vec<(u32, Instant)> = processes
+        .iter_mut()
+        .filter_map(|(pid, p)| {
+            p.refresh();
+            p.finished.map(|f| (*pid, f))
+        })
+        .collect();
+    if finished.len() < MAX_FINISHED_ENTRIES {
+        return;
+    }
+    finished.sort_by_key(|(_, f)| *f);
+    for (pid, _) in finished
+        .iter()
+        .take(finished.len() + 1 - MAX_FINISHED_ENTRIES)
+    {
+        processes.remove(pid);
+        #[cfg(windows)]
+        super::job::release(*pid);
+    }
+}
+
+/// Current status of a managed process, `None` when the PID is not managed
+pub fn status(pid: u32) -> Option<ProcessInfo> {
+    let mut processes = registry().lock().unwrap();
+    let managed = processes.get_mut(&pid)?;
+    managed.refresh();
+    Some(managed.info())
+}
+
+/// Snapshots of all managed processes, ordered by PID
+pub fn list() -> Vec<ProcessInfo> {
+    let mut processes = registry().lock().unwrap();
+    let mut infos: Vec<ProcessInfo> = processes
+        .values_mut()
+        .map(|p| {
+            p.refresh();
+            p.info()
+        })
+        .collect();
+    infos.sort_by_key(|p| p.pid);
+    infos
+}
+
+/// Waits for a managed process to exit
+///
+/// When `timeout` elapses the process is left running and `timed_out` is set;
+/// killing hung processes is the job of the per-process `Timeouts`.
+pub fn wait(pid: u32, timeout: Option<Duration>) -> Option<ProcessWaitResult> {
+    let start = Instant::now();
+    let mut poll_interval = Duration::from_millis(5);
+
+    let timed_out = loop {
+        {
+            let mut processes = registry().lock().unwrap();
+            let managed = processes.get_mut(&pid)?;
+            if !managed.refresh() {
+                break false;
+            }
+            if timeout.is_some_and(|t| start.elapsed() >= t) {
+                break true;
+            }
+        }
+        thread::sleep(poll_interval);
+        poll_interval = (poll_interval * 2).min(Duration::from_millis(100));
+    };
+
+    let (info, stdout, stderr) = {
+        let processes = registry().lock().unwrap();
+        let managed = processes.get(&pid)?;
+        (
+            managed.info(),
+            Arc::clone(&managed.stdout),
+            Arc::clone(&managed.stderr),
+        )
+    };
+
+    // Give the reader threads a moment to see EOF after the child exited
+    if !info.is_running() {
+        let drain_start = Instant::now();
+        while drain_start.elapsed() < PIPE_DRAIN_TIMEOUT
+            && !(stdout.lock().unwrap().closed && stderr.lock().unwrap().closed)
+        {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    let stdout = stdout.lock().unwrap().text();
+    let stderr = stderr.lock().unwrap().text();
+    Some(ProcessWaitResult {
+        process: info,
+        timed_out,
+        stdout,
+        stderr,
+    })
+}
+
+/// Returns buffered lines with `seq >= since_seq`, at most `max_lines` of them
+pub fn read_output(pid: u32, since_seq: u64, max_lines: usize) -> Option<OutputChunk> {
+    let (lines, stdout, stderr, running) = {
+        let mut processes = registry().lock().unwrap();
+        let managed = processes.get_mut(&pid)?;
+        let running = managed.refresh();
+        (
+            Arc::clone(&managed.lines),
+            Arc::clone(&managed.stdout),
+            Arc::clone(&managed.stderr),
+            running,
+        )
+    };
+    let finished = !running && stdout.lock().unwrap().closed && stderr.lock().unwrap().closed;
+
+    let buffer = lines.lock().unwrap();
+    let batch: Vec<OutputLine> = buffer
+        .lines
+        .iter()
+        .filter(|l| l.seq >= since_seq)
+        .take(max_lines)
+        .cloned()
+        .collect();
+    let next_seq = batch
+        .last()
+        .map_or(since_seq.min(buffer.next_seq), |l| l.seq + 1);
+    Some(OutputChunk {
+        pid,
+        lines: batch,
+        next_seq,
+        dropped_lines: buffer.dropped,
+        finished,
+    })
+}
+
+/// Writes to the stdin of an interactive agent, returning the number of bytes written
+///
+/// The registry lock is released before writing, so an agent that does not read
+/// its input only blocks the caller.
+pub fn write_stdin(pid: u32, data: &[u8]) -> Result<usize, CdeError> {
+    let writer = {
+        let processes = registry().lock().unwrap();
+        let managed = processes.get(&pid).ok_or_else(|| {
+            CdeError::not_found(format!("Process {} is not managed by the core", pid))
+        })?;
+        managed.stdin.clone().ok_or_else(|| {
+            CdeError::invalid_input(format!(
+                "Process {} has no open stdin (spawn it with interactive or pty)",
+                pid
+            ))
+        })?
+    };
+    let mut writer = writer.lock().unwrap();
+    writer
+        .write_all(data)
+        .and_then(|_| writer.flush())
+        .map_err(|e| CdeError::io(format!("Failed to write to process {}", pid)).caused_by(&e))?;
+    Ok(data.len())
+}
+
+/// Closes the stdin of an interactive agent so it sees end-of-file
+pub fn close_stdin(pid: u32) -> Result<(), CdeError> {
+    let mut processes = registry().lock().unwrap();
+    let managed = processes.get_mut(&pid).ok_or_else(|| {
+        CdeError::not_found(format!("Process {} is not managed by the core", pid))
+    })?;
+    managed.stdin = None;
+    Ok(())
+}
+
+/// Kills a managed process and reaps it; `None` when the PID is not managed
+pub fn kill(pid: u32) -> Option<bool> {
+    let mut processes = registry().lock().unwrap();
+    let managed = processes.get_mut(&pid)?;
+    if !managed.refresh() {
+        return Some(false);
+    }
+    let killed = managed.child.kill().is_ok();
+    if killed {
+        managed.termination = Some("killed".to_string());
+        managed.exit_status = managed.child.wait().ok();
+        managed.finished = Some(Instant::now());
+        managed.publish_exit();
+    }
+    Some(killed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_registry_tracks_status_and_output() {
+        let child = Command::new("sh")
+            .args(["-c", "echo hello; exit 2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = register(
+            child,
+            "sh -c echo".to_string(),
+            OutputOptions::default(),
+            Timeouts::default(),
+        );
+
+        let waited = wait(pid, Some(Duration::from_secs(5))).unwrap();
+        assert!(!waited.timed_out);
+        assert_eq!(waited.process.status, "failed");
+        assert_eq!(waited.process.exit_code, Some(2));
+        assert_eq!(waited.stdout, "hello\n");
+        assert!(list().iter().any(|p| p.pid == pid));
+
+        let sleeper = Command::new("sleep").arg("5").spawn().unwrap();
+        let sleeper_pid = register(
+            sleeper,
+            "sleep 5".to_string(),
+            OutputOptions::default(),
+            Timeouts::default(),
+        );
+        let waited = wait(sleeper_pid, Some(Duration::from_millis(50))).unwrap();
+        assert!(waited.timed_out);
+        assert_eq!(status(sleeper_pid).unwrap().status, "running");
+        assert_eq!(kill(sleeper_pid), Some(true));
+        assert_eq!(status(sleeper_pid).unwrap().status, "killed");
+        assert_eq!(kill(sleeper_pid), Some(false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_output_ring_buffer_and_sink() {
+        let child = Command::new("sh")
+            .args(["-c", "for i in 1 2 3 4 5 6; do echo line$i; done"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink_seen = Arc::clone(&seen);
+        let output = OutputOptions {
+            sink: Some(Arc::new(move |_pid, line: &OutputLine| {
+                sink_seen.lock().unwrap().push(line.line.clone());
+            })),
+            max_buffered_lines: 4,
+            ..OutputOptions::default()
+        };
+        let pid = register(child, "sh".to_string(), output, Timeouts::default());
+        wait(pid, Some(Duration::from_secs(5))).unwrap();
+
+        let chunk = read_output(pid, 0, 3).unwrap();
+        assert_eq!(chunk.dropped_lines, 2);
+        assert_eq!(chunk.lines.len(), 3);
+        assert_eq!(chunk.lines[0].seq, 2);
+        assert!(chunk.finished);
+
+        let rest = read_output(pid, chunk.next_seq, 10).unwrap();
+        assert_eq!(rest.lines.len(), 1);
+        assert_eq!(rest.lines[0].stream, "stdout");
+        assert_eq!(rest.lines[0].line, "line6");
+        assert_eq!(rest.next_seq, 6);
+        assert_eq!(seen.lock().unwrap().len(), 6);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_lines_are_published_as_events() {
+        let subscription = events::Subscription::new(
+            events::EventFilter {
+                kinds: vec!["process.output".to_string()],
+                ..events::EventFilter::default()
+            },
+            events::DEFAULT_MAX_QUEUED,
+        );
+        let child = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = register(
+            child,
+            "sh".to_string(),
+            OutputOptions::default(),
+            Timeouts::default(),
+        );
+        wait(pid, Some(Duration::from_secs(5))).unwrap();
+
+        // Other tests' agents publish too: only look at this one's lines
+        let mut lines: Vec<(String, String)> = subscription
+            .drain(None)
+            .into_iter()
+            .filter(|event| event.data["pid"] == pid)
+            .map(|event| {
+                let stream = event.data["stream"].as_str().unwrap().to_string();
+                (stream, event.data["line"].as_str().unwrap().to_string())
+            })
+            .collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                ("stderr".to_string(), "err".to_string()),
+                ("stdout".to_string(), "out".to_string())
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stall_detection_reports_and_kills_silent_agents() {
+        use std::os::unix::process::CommandExt;
+        let spawn = || {
+            Command::new("sh")
+                .args(["-c", "echo started; sleep 5"])
+                .stdout(Stdio::piped())
+                .process_group(0)
+                .spawn()
+                .unwrap()
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = Arc::clone(&events);
+        let output = OutputOptions {
+            sink: Some(Arc::new(move |_pid, line: &OutputLine| {
+                if line.stream == "status" {
+                    sink_events.lock().unwrap().push(line.line.clone());
+                }
+            })),
+            ..OutputOptions::default()
+        };
+        let stall = Timeouts {
+            stall: Some(Duration::from_millis(200)),
+            ..Timeouts::default()
+        };
+
+        let watched = register(spawn(), "sh".to_string(), output, stall);
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(status(watched).unwrap().status, "stalled");
+        assert!(status(watched).unwrap().is_running());
+        assert_eq!(*events.lock().unwrap(), vec!["stalled".to_string()]);
+        super::super::tree::kill_tree(watched);
+        kill(watched);
+
+        let killed = register(
+            spawn(),
+            "sh".to_string(),
+            OutputOptions::default(),
+            Timeouts {
+                kill_on_stall: true,
+                ..stall
+            },
+        );
+        let waited = wait(killed, Some(Duration::from_secs(5))).unwrap();
+        assert!(!waited.timed_out);
+        assert_eq!(waited.process.status, "timeout");
+        assert_eq!(waited.process.termination_path.as_deref(), Some("stalled"));
+    }
+}
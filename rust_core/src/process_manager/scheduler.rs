@@ -0,0 +1,433 @@
+// rust_core/src/process_manager/scheduler.rs
+//! Bounded-concurrency scheduler for agent spawns
+//!
+//! Jobs are queued by priority (higher first, FIFO among equals) and started only
+//! while fewer than `max_concurrent` scheduled agents are running. A dispatcher
+//! thread notices finished agents through the registry and starts the next jobs.
+//!
+//! With load limits set, queued jobs are also deferred while system CPU usage is
+//! above the limit or available memory below the headroom. Only one job starts per
+//! load sample, so its own load shows up before the next one is considered, and a
+//! job always starts when no scheduled agent runs so the queue cannot stall.
+
+use super::load::{self, LoadSample};
+use super::options::SpawnOptions;
+use super::registry::{self, OutputOptions};
+use crate::events;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// How often the dispatcher checks running agents for completion
+const DISPATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Started/failed job states kept so late waiters can still look them up
+const MAX_SETTLED_JOBS: usize = 1024;
+
+/// A command waiting to be spawned
+pub struct Job {
+    pub command: Vec<String>,
+    pub options: SpawnOptions,
+    pub output: OutputOptions,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running(u32),
+    Failed(String),
+}
+
+struct QueuedJob {
+    job_id: u64,
+    priority: i32,
+    enqueued_at: String,
+    job: Job,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningJobInfo {
+    pub job_id: u64,
+    pub pid: u32,
+    pub command: String,
+    pub priority: i32,
+    pub started_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedJobInfo {
+    pub job_id: u64,
+    pub command: String,
+    pub priority: i32,
+    /// 0 means next to start
+    pub position: usize,
+    pub enqueued_at: String,
+}
+
+/// System load above which queued jobs are deferred; `None` disables a limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadLimits {
+    pub max_cpu_pct: Option<f32>,
+    /// Available memory (MiB) that must remain before another job starts
+    pub min_free_memory_mb: Option<u64>,
+}
+
+impl LoadLimits {
+    fn is_active(&self) -> bool {
+        self.max_cpu_pct.is_some() || self.min_free_memory_mb.is_some()
+    }
+
+    /// Why the next job must wait given the latest `sample`, `None` if it may start
+    fn deferral(
+        &self,
+        sample: Option<LoadSample>,
+        running: usize,
+        last_start_seq: Option<u64>,
+    ) -> Option<String> {
+        if !self.is_active() || running == 0 {
+            return None;
+        }
+        let Some(sample) = sample else {
+            return Some("waiting for the first load sample".to_string());
+        };
+        if last_start_seq.is_some_and(|seq| sample.seq <= seq) {
+            return Some("waiting for load to reflect the last started agent".to_string());
+        }
+        if let Some(max_cpu_pct) = self.max_cpu_pct.filter(|max| sample.cpu_pct > *max) {
+            return Some(format!(
+                "CPU usage {:.0}% above the {:.0}% limit",
+                sample.cpu_pct, max_cpu_pct
+            ));
+        }
+        if let Some(min_free_memory_mb) = self
+            .min_free_memory_mb
+            .filter(|min| sample.available_memory_mb < *min)
+        {
+            return Some(format!(
+                "{} MiB available memory below the {} MiB headroom",
+                sample.available_memory_mb, min_free_memory_mb
+            ));
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueState {
+    pub max_concurrent: usize,
+    pub limits: LoadLimits,
+    /// Why queued jobs are currently held back by the load limits
+    pub deferred: Option<String>,
+    pub load: Option<LoadSample>,
+    pub running: Vec<RunningJobInfo>,
+    pub queued: Vec<QueuedJobInfo>,
+}
+
+struct SchedulerState {
+    max_concurrent: usize,
+    limits: LoadLimits,
+    deferred: Option<String>,
+    /// Load sample current when the last job started under limits
+    last_start_seq: Option<u64>,
+    /// Sorted by priority (descending), then job id
+    queue: Vec<QueuedJob>,
+    running: Vec<RunningJobInfo>,
+    jobs: HashMap<u64, JobState>,
+    settled: VecDeque<u64>,
+    next_job_id: u64,
+    dispatcher_started: bool,
+}
+
+pub struct Scheduler {
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+}
+
+/// The process-wide scheduler, limited to one agent per CPU by default
+pub fn scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    SCHEDULER.get_or_init(|| Scheduler::new(num_cpus::get()))
+}
+
+impl Scheduler {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                max_concurrent: max_concurrent.max(1),
+                limits: LoadLimits::default(),
+                deferred: None,
+                last_start_seq: None,
+                queue: Vec::new(),
+                running: Vec::new(),
+                jobs: HashMap::new(),
+                settled: VecDeque::new(),
+                next_job_id: 1,
+                dispatcher_started: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Queues a job and starts it right away if a slot is free
+    pub fn submit(&'static self, job: Job) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let job_id = state.next_job_id;
+        state.next_job_id += 1;
+
+        let priority = job.options.priority;
+        events::publish(
+            "scheduler",
+            "job.queued",
+            serde_json::json!({ "job_id": job_id, "command": job.command.join(" "), "priority": priority }),
+        );
+        let index = state.queue.partition_point(|q| q.priority >= priority);
+        state.queue.insert(
+            index,
+            QueuedJob {
+                job_id,
+                priority,
+                enqueued_at: chrono::Local::now().to_rfc3339(),
+                job,
+            },
+        );
+        state.jobs.insert(job_id, JobState::Queued);
+
+        self.dispatch(&mut state);
+        if !state.dispatcher_started {
+            state.dispatcher_started = true;
+            thread::spawn(move || self.run_dispatcher());
+        }
+        job_id
+    }
+
+    /// Current state of a job, with its queue position while queued
+    pub fn job_state(&self, job_id: u64) -> Option<(JobState, Option<usize>)> {
+        let state = self.state.lock().unwrap();
+        let job_state = state.jobs.get(&job_id)?.clone();
+        let position = state.queue.iter().position(|q| q.job_id == job_id);
+        Some((job_state, position))
+    }
+
+    /// Blocks until the job has been spawned (returns its PID) or failed to spawn
+    pub fn wait_started(&self, job_id: u64) -> Result<u32, String> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match state.jobs.get(&job_id) {
+                Some(JobState::Queued) => state = self.changed.wait(state).unwrap(),
+                Some(JobState::Running(pid)) => return Ok(*pid),
+                Some(JobState::Failed(reason)) => return Err(reason.clone()),
+                None => return Err("unknown_job".to_string()),
+            }
+        }
+    }
+
+    /// Removes a job that has not started yet; false once it was spawned
+    pub fn cancel(&self, job_id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(index) = state.queue.iter().position(|q| q.job_id == job_id) else {
+            return false;
+        };
+        state.queue.remove(index);
+        state
+            .jobs
+            .insert(job_id, JobState::Failed("cancelled".to_string()));
+        state.settled.push_back(job_id);
+        self.changed.notify_all();
+        events::publish(
+            "scheduler",
+            "job.cancelled",
+            serde_json::json!({ "job_id": job_id }),
+        );
+        true
+    }
+
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.max_concurrent = max_concurrent.max(1);
+        self.dispatch(&mut state);
+    }
+
+    pub fn set_limits(&self, limits: LoadLimits) {
+        let mut state = self.state.lock().unwrap();
+        state.limits = limits;
+        state.last_start_seq = None;
+        self.dispatch(&mut state);
+    }
+
+    pub fn queue_state(&self) -> QueueState {
+        let mut state = self.state.lock().unwrap();
+        self.dispatch(&mut state);
+        QueueState {
+            max_concurrent: state.max_concurrent,
+            limits: state.limits,
+            deferred: state.deferred.clone(),
+            load: state.limits.is_active().then(load::latest).flatten(),
+            running: state.running.clone(),
+            queued: state
+                .queue
+                .iter()
+                .enumerate()
+                .map(|(position, q)| QueuedJobInfo {
+                    job_id: q.job_id,
+                    command: q.job.command.join(" "),
+                    priority: q.priority,
+                    position,
+                    enqueued_at: q.enqueued_at.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn run_dispatcher(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            state = self
+                .changed
+                .wait_timeout(state, DISPATCH_INTERVAL)
+                .unwrap()
+                .0;
+            if !state.running.is_empty() || !state.queue.is_empty() {
+                self.dispatch(&mut state);
+            }
+        }
+    }
+
+    /// Drops finished agents from the running set and starts queued jobs into free slots
+    fn dispatch(&self, state: &mut SchedulerState) {
+        let before = (state.running.len(), state.queue.len());
+        state
+            .running
+            .retain(|r| registry::status(r.pid).is_some_and(|info| info.is_running()));
+
+        state.deferred = None;
+        while state.running.len() < state.max_concurrent && !state.queue.is_empty() {
+            let sample = state.limits.is_active().then(load::latest).flatten();
+            state.deferred =
+                state
+                    .limits
+                    .deferral(sample, state.running.len(), state.last_start_seq);
+            if state.deferred.is_some() {
+                break;
+            }
+            state.last_start_seq = sample.map(|sample| sample.seq);
+
+            let queued = state.queue.remove(0);
+            let command = queued.job.command.join(" ");
+            let job_state = match super::spawn_agent_sync(
+                &queued.job.command,
+                &queued.job.options,
+                queued.job.output,
+            ) {
+                Ok(process) => {
+                    state.running.push(RunningJobInfo {
+                        job_id: queued.job_id,
+                        pid: process.pid,
+                        command,
+                        priority: queued.priority,
+                        started_at: chrono::Local::now().to_rfc3339(),
+                    });
+                    events::publish(
+                        "scheduler",
+                        "job.started",
+                        serde_json::json!({ "job_id": queued.job_id, "pid": process.pid }),
+                    );
+                    JobState::Running(process.pid)
+                }
+                Err(e) => {
+                    events::publish(
+                        "scheduler",
+                        "job.failed",
+                        serde_json::json!({ "job_id": queued.job_id, "error": e.to_string() }),
+                    );
+                    JobState::Failed(e.to_string())
+                }
+            };
+            state.jobs.insert(queued.job_id, job_state);
+            state.settled.push_back(queued.job_id);
+        }
+
+        while state.settled.len() > MAX_SETTLED_JOBS {
+            if let Some(job_id) = state.settled.pop_front() {
+                state.jobs.remove(&job_id);
+            }
+        }
+
+        if before != (state.running.len(), state.queue.len()) {
+            self.changed.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scheduler_limits_concurrency_and_orders_by_priority() {
+        let scheduler: &'static Scheduler = Box::leak(Box::new(Scheduler::new(1)));
+        let job = |command: &str, priority: i32| Job {
+            command: vec!["sh".to_string(), "-c".to_string(), command.to_string()],
+            options: SpawnOptions {
+                priority,
+                ..SpawnOptions::default()
+            },
+            output: OutputOptions::default(),
+        };
+
+        let first = scheduler.submit(job("sleep 0.3", 0));
+        let low = scheduler.submit(job("true", 0));
+        let high = scheduler.submit(job("true", 5));
+
+        let state = scheduler.queue_state();
+        assert_eq!(state.running.len(), 1);
+        assert_eq!(state.running[0].job_id, first);
+        let queued: Vec<u64> = state.queued.iter().map(|q| q.job_id).collect();
+        assert_eq!(queued, vec![high, low]);
+        assert_eq!(
+            scheduler.job_state(low).unwrap(),
+            (JobState::Queued, Some(1))
+        );
+
+        let high_pid = scheduler.wait_started(high).unwrap();
+        scheduler.wait_started(low).unwrap();
+        assert!(high_pid > 0);
+        assert!(scheduler.queue_state().queued.is_empty());
+    }
+
+    #[test]
+    fn test_load_limits_defer_jobs() {
+        let limits = LoadLimits {
+            max_cpu_pct: Some(80.0),
+            min_free_memory_mb: Some(2048),
+        };
+        let sample = |seq, cpu_pct, available_memory_mb| LoadSample {
+            seq,
+            cpu_pct,
+            available_memory_mb,
+            total_memory_mb: 16384,
+        };
+
+        assert_eq!(LoadLimits::default().deferral(None, 4, None), None);
+        assert_eq!(limits.deferral(None, 0, None), None);
+        assert!(limits.deferral(None, 1, None).is_some());
+        assert_eq!(
+            limits.deferral(Some(sample(3, 40.0, 8192)), 1, Some(2)),
+            None
+        );
+        assert!(limits
+            .deferral(Some(sample(3, 40.0, 8192)), 1, Some(3))
+            .unwrap()
+            .contains("last started agent"));
+        assert_eq!(
+            limits.deferral(Some(sample(4, 95.0, 8192)), 2, None),
+            Some("CPU usage 95% above the 80% limit".to_string())
+        );
+        assert_eq!(
+            limits.deferral(Some(sample(5, 10.0, 1024)), 2, None),
+            Some("1024 MiB available memory below the 2048 MiB headroom".to_string())
+        );
+    }
+}
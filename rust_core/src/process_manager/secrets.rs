@@ -0,0 +1,181 @@
+// rust_core/src/process_manager/secrets.rs
+//! Environment templating and secret injection for agent commands
+//!
+//! API keys passed on the command line show up in every process listing. Secrets
+//! are instead written to owner-only temp files whose paths reach the agent as
+//! `<NAME>_FILE` variables, the files are deleted once the agent exits, and secret
+//! values are redacted from captured output.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Replacement for secret values found in agent output
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Expands `${VAR}` references in `template`; `$$` is a literal `$`
+///
+/// Unknown variables are an error rather than an empty string, so a typo does not
+/// silently run an agent with a blank API endpoint or model name.
+pub fn expand(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("Unterminated variable reference in '{}'", template))?;
+            let name = &after[..end];
+            if !is_valid_name(name) {
+                return Err(format!("Invalid variable name '{}'", name));
+            }
+            let value = lookup(name).ok_or_else(|| format!("Undefined variable '{}'", name))?;
+            result.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Portable environment variable name: letters, digits and `_`, not starting with a digit
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces every occurrence of a secret value with `REDACTED`
+pub fn redact(text: &str, secrets: &[String]) -> String {
+    let mut text = text.to_string();
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        if text.contains(secret.as_str()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+    }
+    text
+}
+
+/// Private directory holding one file per secret, removed on drop
+pub struct SecretFiles {
+    dir: PathBuf,
+    files: Vec<(String, PathBuf)>,
+}
+
+impl SecretFiles {
+    /// Writes each secret to its own owner-only file
+    pub fn write(secrets: &BTreeMap<String, String>) -> io::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "cde-secrets-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        create_private_dir(&dir)?;
+        let mut files = SecretFiles {
+            dir,
+            files: Vec::new(),
+        };
+        for (name, value) in secrets {
+            let path = files.dir.join(name);
+            write_private_file(&path, value.as_bytes())?;
+            files.files.push((format!("{}_FILE", name), path));
+        }
+        Ok(files)
+    }
+
+    /// `<NAME>_FILE` variables pointing at the secret files
+    pub fn env_vars(&self) -> impl Iterator<Item = (&str, &PathBuf)> {
+        self.files.iter().map(|(name, path)| (name.as_str(), path))
+    }
+}
+
+impl Drop for SecretFiles {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    // The per-user temp directory is already private to the account on Windows
+    fs::create_dir(dir)
+}
+
+#[cfg(unix)]
+fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_and_redact() {
+        let lookup = |name: &str| (name == "MODEL").then(|| "sonnet".to_string());
+        assert_eq!(
+            expand("--model=${MODEL} costs $$5 in $HOME", lookup).unwrap(),
+            "--model=sonnet costs $5 in $HOME"
+        );
+        assert!(expand("${MISSING}", lookup).is_err());
+        assert!(expand("${MODEL", lookup).is_err());
+        assert!(expand("${1BAD}", lookup).is_err());
+
+        let secrets = vec!["sk-123".to_string(), String::new()];
+        assert_eq!(
+            redact("key=sk-123 again sk-123", &secrets),
+            "key=[REDACTED] again [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_secret_files_are_private_and_removed() {
+        let secrets = BTreeMap::from([("API_KEY".to_string(), "sk-123".to_string())]);
+        let files = SecretFiles::write(&secrets).unwrap();
+        let (name, path) = files.env_vars().next().unwrap();
+        assert_eq!(name, "API_KEY_FILE");
+        assert_eq!(fs::read_to_string(path).unwrap(), "sk-123");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                fs::metadata(path).unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+
+        let path = path.clone();
+        drop(files);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,169 @@
+// rust_core/src/process_manager/structured.rs
+//! Structured results extracted from agent output
+//!
+//! Agents report machine-readable results either as a block between two
+//! `===CDE_RESULT===` marker lines or as a JSON document at the very end of
+//! stdout. The parsed value is returned next to the untouched log, so adapters no
+//! longer scrape free-form output themselves.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Line delimiting a result block (before and after it)
+pub const RESULT_MARKER: &str = "===CDE_RESULT===";
+
+/// Lines scanned backwards from the end of stdout for the start of a JSON trailer
+const MAX_TRAILER_LINES: usize = 500;
+
+/// How a result is extracted and what it must contain
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ResultFormat {
+    pub format: String, // "markers", "json_trailer", "auto" (markers, then trailer)
+    /// Top-level keys the result object must have
+    pub required_keys: Vec<String>,
+}
+
+impl Default for ResultFormat {
+    fn default() -> Self {
+        Self {
+            format: "auto".to_string(),
+            required_keys: Vec::new(),
+        }
+    }
+}
+
+impl ResultFormat {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.format.as_str() {
+            "markers" | "json_trailer" | "auto" => Ok(()),
+            other => Err(format!(
+                "Invalid result format '{}': expected markers, json_trailer or auto",
+                other
+            )),
+        }
+    }
+
+    /// Extracts and validates the result from `stdout`
+    pub fn parse(&self, stdout: &str) -> Result<Value, String> {
+        let value = match self.format.as_str() {
+            "markers" => parse_marked_block(stdout)?,
+            "json_trailer" => parse_json_trailer(stdout)?,
+            _ if marked_block(stdout).is_some() => parse_marked_block(stdout)?,
+            _ => parse_json_trailer(stdout)?,
+        };
+        self.check_required_keys(&value)?;
+        Ok(value)
+    }
+
+    fn check_required_keys(&self, value: &Value) -> Result<(), String> {
+        if self.required_keys.is_empty() {
+            return Ok(());
+        }
+        let Some(object) = value.as_object() else {
+            return Err("Result is not a JSON object".to_string());
+        };
+        let missing: Vec<&str> = self
+            .required_keys
+            .iter()
+            .filter(|key| !object.contains_key(key.as_str()))
+            .map(String::as_str)
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Result is missing keys: {}", missing.join(", ")))
+        }
+    }
+}
+
+/// Text between the last complete pair of marker lines
+fn marked_block(stdout: &str) -> Option<String> {
+    let markers: Vec<usize> = stdout
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == RESULT_MARKER)
+        .map(|(index, _)| index)
+        .collect();
+    // Pairs are (0, 1), (2, 3), ...
+    let pair = (markers.len() / 2).checked_sub(1)?;
+    let (start, end) = (markers[pair * 2], markers[pair * 2 + 1]);
+    let block: Vec<&str> = stdout
+        .lines()
+        .skip(start + 1)
+        .take(end - start - 1)
+        .collect();
+    Some(block.join("\n"))
+}
+
+/// JSON between the last pair of marker lines
+pub fn parse_marked_block(stdout: &str) -> Result<Value, String> {
+    let block = marked_block(stdout)
+        .ok_or_else(|| format!("No complete {} block in output", RESULT_MARKER))?;
+    serde_json::from_str(&block)
+        .map_err(|e| format!("Invalid JSON in {} block: {}", RESULT_MARKER, e))
+}
+
+/// The JSON document that ends stdout, which may span several lines
+pub fn parse_json_trailer(stdout: &str) -> Result<Value, String> {
+    let lines: Vec<&str> = stdout.trim_end().lines().collect();
+    let mut last_error = None;
+    for start in (lines.len().saturating_sub(MAX_TRAILER_LINES)..lines.len()).rev() {
+        let first = lines[start].trim_start();
+        if !(first.starts_with('{') || first.starts_with('[')) {
+            continue;
+        }
+        match serde_json::from_str(&lines[start..].join("\n")) {
+            Ok(value) => return Ok(value),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => format!("Invalid JSON trailer: {}", e),
+        None => "No JSON trailer in output".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parses_marked_blocks_and_trailers() {
+        let marked = "thinking...\n===CDE_RESULT===\n{\"files\": 2}\n===CDE_RESULT===\n\
+                      ===CDE_RESULT===\n{\"files\": 3,\n \"ok\": true}\n===CDE_RESULT===\nbye\n";
+        assert_eq!(
+            parse_marked_block(marked).unwrap(),
+            json!({"files": 3, "ok": true})
+        );
+        assert!(
+            parse_marked_block("===CDE_RESULT===\nnot json\n===CDE_RESULT===")
+                .unwrap_err()
+                .starts_with("Invalid JSON")
+        );
+
+        let trailer = "log line {not json}\n{\n  \"status\": \"done\",\n  \"items\": [1, 2]\n}\n\n";
+        assert_eq!(
+            parse_json_trailer(trailer).unwrap(),
+            json!({"status": "done", "items": [1, 2]})
+        );
+        assert!(parse_json_trailer("plain log\n").is_err());
+
+        let auto = ResultFormat {
+            format: "auto".to_string(),
+            required_keys: vec!["status".to_string()],
+        };
+        assert_eq!(auto.parse(trailer).unwrap()["status"], "done");
+        assert_eq!(
+            auto.parse(marked).unwrap_err(),
+            "Result is missing keys: status"
+        );
+        assert!(ResultFormat {
+            format: "xml".to_string(),
+            ..ResultFormat::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,379 @@
+// rust_core/src/process_manager/supervisor.rs
+//! Restart policies for managed agents
+//!
+//! A supervised agent keeps a stable `agent_id` across restarts while each run gets
+//! a new PID. Failed runs are restarted with exponential backoff until the retry
+//! budget is spent; too many failures inside the crash-loop window stop the agent
+//! with status "crash_looping" instead of restarting it forever.
+
+use super::options::SpawnOptions;
+use super::registry::{self, OutputOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the supervisor checks supervised agents for exits and due restarts
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RestartPolicy {
+    pub policy: String, // "never", "on-failure", "always"
+    pub max_retries: u32,
+    pub backoff_initial_ms: u64,
+    pub backoff_max_ms: u64,
+    /// Failures within `crash_loop_window_secs` that mark the agent as crash looping
+    pub crash_loop_threshold: usize,
+    pub crash_loop_window_secs: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            policy: "never".to_string(),
+            max_retries: 3,
+            backoff_initial_ms: 500,
+            backoff_max_ms: 30_000,
+            crash_loop_threshold: 5,
+            crash_loop_window_secs: 60,
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.policy != "never"
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.policy.as_str() {
+            "never" | "on-failure" | "always" => Ok(()),
+            other => Err(format!(
+                "Invalid restart policy '{}': expected never, on-failure or always",
+                other
+            )),
+        }
+    }
+
+    fn backoff(&self, restarts: u32) -> Duration {
+        let factor = 2u64.saturating_pow(restarts);
+        Duration::from_millis(
+            self.backoff_initial_ms
+                .saturating_mul(factor)
+                .min(self.backoff_max_ms),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisedInfo {
+    pub agent_id: u64,
+    pub command: String,
+    pub policy: String,
+    pub status: String, // "running", "backoff", "completed", "failed", "stopped", "crash_looping"
+    pub current_pid: u32,
+    /// Every PID the agent ran as, oldest first
+    pub pids: Vec<u32>,
+    pub restarts: u32,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+}
+
+struct Supervised {
+    info: SupervisedInfo,
+    command: Vec<String>,
+    options: SpawnOptions,
+    output: OutputOptions,
+    recent_failures: VecDeque<Instant>,
+    restart_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct SupervisorState {
+    agents: HashMap<u64, Supervised>,
+    next_agent_id: u64,
+    started: bool,
+}
+
+fn supervisor_state() -> &'static Mutex<SupervisorState> {
+    static STATE: OnceLock<Mutex<SupervisorState>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(SupervisorState::default()))
+}
+
+/// Puts an already spawned agent under its restart policy; returns the agent id
+pub fn supervise(
+    pid: u32,
+    command: &[String],
+    options: &SpawnOptions,
+    output: OutputOptions,
+) -> u64 {
+    let mut state = supervisor_state().lock().unwrap();
+    state.next_agent_id += 1;
+    let agent_id = state.next_agent_id;
+    state.agents.insert(
+        agent_id,
+        Supervised {
+            info: SupervisedInfo {
+                agent_id,
+                command: command.join(" "),
+                policy: options.restart.policy.clone(),
+                status: "running".to_string(),
+                current_pid: pid,
+                pids: vec![pid],
+                restarts: 0,
+                last_exit_code: None,
+                last_error: None,
+            },
+            command: command.to_vec(),
+            options: options.clone(),
+            output,
+            recent_failures: VecDeque::new(),
+            restart_at: None,
+        },
+    );
+
+    if !state.started {
+        state.started = true;
+        thread::spawn(|| loop {
+            thread::sleep(SUPERVISE_INTERVAL);
+            let mut state = supervisor_state().lock().unwrap();
+            for agent in state.agents.values_mut() {
+                tick(agent, Instant::now());
+            }
+        });
+    }
+    agent_id
+}
+
+/// Advances one agent: notices exits, schedules restarts and performs due ones
+fn tick(agent: &mut Supervised, now: Instant) {
+    match agent.info.status.as_str() {
+        "running" => {
+            let Some(process) = registry::status(agent.info.current_pid) else {
+                agent.info.status = "failed".to_string();
+                agent.info.last_error = Some("process no longer tracked".to_string());
+                return;
+            };
+            if process.is_running() {
+                return;
+            }
+            agent.info.last_exit_code = process.exit_code;
+            on_exit(agent, &process.status, now);
+        }
+        "backoff" if agent.restart_at.is_some_and(|at| now >= at) => {
+            agent.restart_at = None;
+            match super::spawn_process(&agent.command, &agent.options, agent.output.clone()) {
+                Ok(pid) => {
+                    agent.info.current_pid = pid;
+                    agent.info.pids.push(pid);
+                    agent.info.restarts += 1;
+                    agent.info.status = "running".to_string();
+                }
+                Err(e) => {
+                    agent.info.status = "failed".to_string();
+                    agent.info.last_error = Some(e.to_string());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Decides what happens after a run ended with registry status `exit_status`
+fn on_exit(agent: &mut Supervised, exit_status: &str, now: Instant) {
+    let policy = &agent.options.restart;
+    let failed = matches!(exit_status, "failed" | "timeout");
+
+    // A kill requested through the core is a stop request, never a crash
+    let wants_restart = exit_status != "killed"
+        && match policy.policy.as_str() {
+            "always" => true,
+            "on-failure" => failed,
+            _ => false,
+        };
+    if !wants_restart {
+        agent.info.status = match exit_status {
+            "completed" => "completed",
+            "killed" => "stopped",
+            _ => "failed",
+        }
+        .to_string();
+        return;
+    }
+
+    if failed {
+        let window = Duration::from_secs(policy.crash_loop_window_secs);
+        agent.recent_failures.push_back(now);
+        while agent
+            .recent_failures
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > window)
+        {
+            agent.recent_failures.pop_front();
+        }
+        if agent.recent_failures.len() >= policy.crash_loop_threshold {
+            agent.info.status = "crash_looping".to_string();
+            agent.info.last_error = Some(format!(
+                "{} failures within {}s",
+                agent.recent_failures.len(),
+                policy.crash_loop_window_secs
+            ));
+            return;
+        }
+    }
+
+    if agent.info.restarts >= policy.max_retries {
+        agent.info.status = "failed".to_string();
+        agent.info.last_error = Some(format!("gave up after {} restarts", agent.info.restarts));
+        return;
+    }
+
+    agent.info.status = "backoff".to_string();
+    agent.restart_at = Some(now + policy.backoff(agent.info.restarts));
+}
+
+/// The supervised agent a PID belongs to, if any
+pub fn agent_for_pid(pid: u32) -> Option<u64> {
+    let state = supervisor_state().lock().unwrap();
+    state
+        .agents
+        .values()
+        .find(|a| a.info.pids.contains(&pid))
+        .map(|a| a.info.agent_id)
+}
+
+/// Stops restarting the agent and returns the PID of its latest run, which the
+/// caller is expected to kill
+pub fn stop(agent_id: u64) -> Option<u32> {
+    let mut state = supervisor_state().lock().unwrap();
+    let agent = state.agents.get_mut(&agent_id)?;
+    agent.restart_at = None;
+    if matches!(agent.info.status.as_str(), "running" | "backoff") {
+        agent.info.status = "stopped".to_string();
+    }
+    Some(agent.info.current_pid)
+}
+
+/// Blocks until the agent stops running and will not be restarted
+pub fn wait_settled(agent_id: u64) -> Option<SupervisedInfo> {
+    loop {
+        {
+            let state = supervisor_state().lock().unwrap();
+            let agent = state.agents.get(&agent_id)?;
+            if !matches!(agent.info.status.as_str(), "running" | "backoff") {
+                return Some(agent.info.clone());
+            }
+        }
+        thread::sleep(SUPERVISE_INTERVAL);
+    }
+}
+
+/// Snapshots of all supervised agents, ordered by agent id
+pub fn list() -> Vec<SupervisedInfo> {
+    let state = supervisor_state().lock().unwrap();
+    let mut agents: Vec<SupervisedInfo> = state.agents.values().map(|a| a.info.clone()).collect();
+    agents.sort_by_key(|a| a.agent_id);
+    agents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(policy: RestartPolicy) -> Supervised {
+        Supervised {
+            info: SupervisedInfo {
+                agent_id: 1,
+                command: "false".to_string(),
+                policy: policy.policy.clone(),
+                status: "running".to_string(),
+                current_pid: 1,
+                pids: vec![1],
+                restarts: 0,
+                last_exit_code: None,
+                last_error: None,
+            },
+            command: vec!["false".to_string()],
+            options: SpawnOptions {
+                restart: policy,
+                ..SpawnOptions::default()
+            },
+            output: OutputOptions::default(),
+            recent_failures: VecDeque::new(),
+            restart_at: None,
+        }
+    }
+
+    #[test]
+    fn test_restart_policy_backoff_and_crash_loop() {
+        let policy = RestartPolicy {
+            policy: "on-failure".to_string(),
+            max_retries: 10,
+            backoff_initial_ms: 100,
+            backoff_max_ms: 250,
+            crash_loop_threshold: 3,
+            crash_loop_window_secs: 60,
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(5), Duration::from_millis(250));
+
+        let now = Instant::now();
+        let mut looping = agent(policy.clone());
+        for restarts in 0..2 {
+            looping.info.restarts = restarts;
+            on_exit(&mut looping, "failed", now);
+            assert_eq!(looping.info.status, "backoff");
+        }
+        on_exit(&mut looping, "failed", now);
+        assert_eq!(looping.info.status, "crash_looping");
+
+        let mut succeeded = agent(policy.clone());
+        on_exit(&mut succeeded, "completed", now);
+        assert_eq!(succeeded.info.status, "completed");
+
+        let mut stopped = agent(RestartPolicy {
+            policy: "always".to_string(),
+            ..policy.clone()
+        });
+        on_exit(&mut stopped, "killed", now);
+        assert_eq!(stopped.info.status, "stopped");
+
+        let mut exhausted = agent(RestartPolicy {
+            max_retries: 1,
+            ..policy
+        });
+        exhausted.info.restarts = 1;
+        on_exit(&mut exhausted, "failed", now);
+        assert_eq!(exhausted.info.status, "failed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_supervised_agent_restarts_until_crash_loop() {
+        let options = SpawnOptions {
+            restart: RestartPolicy {
+                policy: "on-failure".to_string(),
+                max_retries: 10,
+                backoff_initial_ms: 10,
+                backoff_max_ms: 10,
+                crash_loop_threshold: 3,
+                crash_loop_window_secs: 60,
+            },
+            ..SpawnOptions::default()
+        };
+        let command = vec!["false".to_string()];
+        let pid =
+            super::super::spawn_process(&command, &options, OutputOptions::default()).unwrap();
+        let agent_id = supervise(pid, &command, &options, OutputOptions::default());
+
+        let info = wait_settled(agent_id).unwrap();
+        assert_eq!(info.status, "crash_looping");
+        assert_eq!(info.restarts, 2);
+        assert_eq!(info.pids.len(), 3);
+        assert_eq!(info.last_exit_code, Some(1));
+        assert_eq!(agent_for_pid(pid), Some(agent_id));
+    }
+}
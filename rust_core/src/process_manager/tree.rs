@@ -0,0 +1,161 @@
+// rust_core/src/process_manager/tree.rs
+//! Whole-tree termination of agent processes
+//!
+//! Agents are spawned as leaders of their own process group (Unix) or in a job
+//! object of their own (Windows, see `job`), so the tree can be killed at once.
+//! Descendants that left the group (e.g. via `setsid`) are found through the
+//! process table and killed individually.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::thread;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, System};
+
+/// Process-table checks (50ms apart) before a PID is reported as a survivor
+const SURVIVOR_CHECKS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeKillReport {
+    pub pid: u32,
+    /// Root plus every descendant that was alive when the kill started
+    pub killed_pids: Vec<u32>,
+    /// True when the whole process group was signalled in one call
+    pub group_signalled: bool,
+    /// PIDs that were still alive after the kill
+    pub survivors: Vec<u32>,
+}
+
+/// All live descendants of `root`, parents before children
+pub fn descendants(system: &System, root: u32) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, process) in system.processes() {
+        if let Some(parent) = process.parent() {
+            children
+                .entry(parent.as_u32())
+                .or_default()
+                .push(pid.as_u32());
+        }
+    }
+
+    let mut seen = HashSet::from([root]);
+    let mut order = Vec::new();
+    let mut queue = vec![root];
+    while let Some(pid) = queue.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if seen.insert(child) {
+                order.push(child);
+                queue.push(child);
+            }
+        }
+    }
+    order
+}
+
+/// Kills `pid` and every process it spawned
+///
+/// The tree is snapshotted before any signal is sent, since killed parents get
+/// their children re-parented to init and the links are lost.
+pub fn kill_tree(pid: u32) -> TreeKillReport {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let tree = descendants(&system, pid);
+
+    let group_signalled = signal_group(pid);
+    for &member in std::iter::once(&pid).chain(&tree) {
+        if let Some(process) = system.process(Pid::from_u32(member)) {
+            process.kill();
+        }
+    }
+
+    let mut killed_pids = vec![pid];
+    killed_pids.extend(&tree);
+
+    // Signals are delivered asynchronously; give the kernel a moment before reporting survivors
+    let mut survivors = Vec::new();
+    for _ in 0..SURVIVOR_CHECKS {
+        system.refresh_processes(ProcessesToUpdate::All, true);
+        survivors = killed_pids
+            .iter()
+            .copied()
+            .filter(|p| {
+                system
+                    .process(Pid::from_u32(*p))
+                    .is_some_and(|process| process.status() != ProcessStatus::Zombie)
+            })
+            .collect();
+        if survivors.is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    TreeKillReport {
+        pid,
+        killed_pids,
+        group_signalled,
+        survivors,
+    }
+}
+
+/// Whether `pid` is a live process (zombies count as exited)
+pub fn is_alive(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system
+        .process(pid)
+        .is_some_and(|process| process.status() != ProcessStatus::Zombie)
+}
+
+/// Kills the process group led by `pid`
+pub fn signal_group(pid: u32) -> bool {
+    send_group(pid, true)
+}
+
+/// Asks the process group led by `pid` to exit (SIGTERM / `taskkill /T` without `/F`)
+pub fn terminate_group(pid: u32) -> bool {
+    send_group(pid, false)
+}
+
+/// Signals the process group led by `pid`
+///
+/// Only groups created by our own spawns are signalled: never the group this
+/// process belongs to.
+#[cfg(unix)]
+fn send_group(pid: u32, force: bool) -> bool {
+    let pid = pid as libc::pid_t;
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: getpgid/getpgrp/killpg only take integer arguments
+    unsafe {
+        let pgid = libc::getpgid(pid);
+        if pgid != pid || pgid == libc::getpgrp() {
+            return false;
+        }
+        libc::killpg(pgid, signal) == 0
+    }
+}
+
+/// Terminates the job of `pid` when forced; otherwise asks the process and its
+/// children to close with `taskkill /T`, which only reaches live parent links
+#[cfg(windows)]
+fn send_group(pid: u32, force: bool) -> bool {
+    use std::os::windows::process::CommandExt;
+
+    if force {
+        return super::job::terminate(pid);
+    }
+    let pid = pid.to_string();
+    std::process::Command::new("taskkill")
+        .args(["/T", "/PID", pid.as_str()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .creation_flags(0x08000000)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn send_group(_pid: u32, _force: bool) -> bool {
+    false
+}
@@ -0,0 +1,289 @@
+// rust_core/src/process_manager/workspace.rs
+//! Ephemeral per-agent workspaces
+//!
+//! Parallel agents editing the same checkout clobber each other's changes. With a
+//! workspace, the agent runs in a private copy of the source tree (files ignored by
+//! `.gitignore` and `.git` itself are skipped; `include` narrows it to an allowlist)
+//! and the files it added, modified or deleted are reported when it exits.
+//! The copy walks the source under the path policy, like every other walk of the
+//! core. `fs::copy` clones file extents on copy-on-write filesystems (Btrfs, XFS,
+//! APFS).
+
+use crate::path_policy::{self, WalkGuard};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest changed file whose new contents are included in the report
+const MAX_REPORTED_FILE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct WorkspaceOptions {
+    /// Directory copied into the workspace (defaults to the agent's `cwd`)
+    pub source: Option<String>,
+    /// Globs relative to `source` of the files to copy; empty copies every file
+    pub include: Vec<String>,
+    /// Leave the workspace on disk once the core forgets the agent
+    pub keep: bool,
+}
+
+impl WorkspaceOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(source) = &self.source {
+            if !Path::new(source).is_dir() {
+                return Err(format!("'{}' is not a valid directory.", source));
+            }
+        }
+        self.overrides(Path::new(".")).map(|_| ())
+    }
+
+    fn overrides(&self, root: &Path) -> Result<ignore::overrides::Override, String> {
+        let mut overrides = OverrideBuilder::new(root);
+        for glob in &self.include {
+            overrides
+                .add(glob)
+                .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        }
+        overrides
+            .build()
+            .map_err(|e| format!("Invalid glob set: {}", e))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path relative to the workspace root, with `/` separators
+    pub path: String,
+    pub change: String, // "added", "modified", "deleted"
+    /// New contents of added/modified UTF-8 files up to 1 MiB
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceReport {
+    pub path: String,
+    pub source: String,
+    pub files_copied: usize,
+    pub changes: Vec<FileChange>,
+}
+
+/// A private copy of a source tree, removed on drop unless kept
+pub struct Workspace {
+    root: PathBuf,
+    source: PathBuf,
+    /// Fingerprint of every copied file, keyed by relative path
+    snapshot: HashMap<String, u64>,
+    keep: bool,
+}
+
+impl Workspace {
+    /// Copies `source` into a fresh temporary directory
+    pub fn create(source: &Path, options: &WorkspaceOptions) -> io::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let guard = path_policy::walk_guard(source)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        let root = std::env::temp_dir().join(format!(
+            "cde-workspace-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&root)?;
+        let mut workspace = Workspace {
+            root,
+            source: source.to_path_buf(),
+            snapshot: HashMap::new(),
+            keep: options.keep,
+        };
+
+        let overrides = options
+            .overrides(source)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        for (relative, path) in tree_files(source, Some(overrides), guard) {
+            let target = workspace.root.join(&relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&path, &target)?;
+            workspace.snapshot.insert(relative, fingerprint(&target)?);
+        }
+        Ok(workspace)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Files added, modified or deleted in the workspace since it was created
+    pub fn collect(&self) -> io::Result<WorkspaceReport> {
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+        for (relative, path) in tree_files(&self.root, None, WalkGuard::default()) {
+            let change = match self.snapshot.get(&relative) {
+                None => Some("added"),
+                Some(before) if *before != fingerprint(&path)? => Some("modified"),
+                Some(_) => None,
+            };
+            if let Some(change) = change {
+                changes.push(FileChange {
+                    path: relative.clone(),
+                    change: change.to_string(),
+                    content: reported_content(&path),
+                });
+            }
+            seen.insert(relative);
+        }
+        for relative in self.snapshot.keys() {
+            if !seen.contains(relative) {
+                changes.push(FileChange {
+                    path: relative.clone(),
+                    change: "deleted".to_string(),
+                    content: None,
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(WorkspaceReport {
+            path: self.root.to_string_lossy().into_owned(),
+            source: self.source.to_string_lossy().into_owned(),
+            files_copied: self.snapshot.len(),
+            changes,
+        })
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// Regular files under `root` as (relative path, absolute path), honoring `.gitignore`
+/// and the entries `guard` allows
+fn tree_files(
+    root: &Path,
+    overrides: Option<ignore::overrides::Override>,
+    guard: WalkGuard,
+) -> Vec<(String, PathBuf)> {
+    let mut walker = WalkBuilder::new(root);
+    walker
+        .hidden(false)
+        .require_git(false)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| {
+            entry.file_name() != ".git" && guard.allows(entry.path(), entry.path_is_symlink())
+        });
+    if let Some(overrides) = overrides {
+        walker.overrides(overrides);
+    }
+    walker
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|e| {
+            let relative = e
+                .path()
+                .strip_prefix(root)
+                .ok()?
+                .to_str()?
+                .replace('\\', "/");
+            Some((relative, e.path().to_path_buf()))
+        })
+        .collect()
+}
+
+fn fingerprint(path: &Path) -> io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    fs::read(path)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+fn reported_content(path: &Path) -> Option<String> {
+    let size = fs::metadata(path).ok()?.len();
+    if size > MAX_REPORTED_FILE_BYTES {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_copies_allowlist_and_reports_changes() {
+        let source = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(source.path().join("src")).unwrap();
+        fs::write(source.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(source.path().join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(source.path().join("notes.txt"), "not copied\n").unwrap();
+
+        let options = WorkspaceOptions {
+            include: vec!["src/**".to_string()],
+            ..WorkspaceOptions::default()
+        };
+        let workspace = Workspace::create(source.path(), &options).unwrap();
+        let root = workspace.root().to_path_buf();
+        assert!(!root.join("notes.txt").exists());
+
+        fs::write(root.join("src/main.rs"), "fn main() { run() }\n").unwrap();
+        fs::remove_file(root.join("src/lib.rs")).unwrap();
+        fs::write(root.join("src/new.rs"), "// new\n").unwrap();
+
+        let report = workspace.collect().unwrap();
+        assert_eq!(report.files_copied, 2);
+        let changes: Vec<(&str, &str)> = report
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.change.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("src/lib.rs", "deleted"),
+                ("src/main.rs", "modified"),
+                ("src/new.rs", "added")
+            ]
+        );
+        assert_eq!(
+            report.changes[1].content.as_deref(),
+            Some("fn main() { run() }\n")
+        );
+        assert_eq!(
+            fs::read_to_string(source.path().join("src/main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+
+        drop(workspace);
+        assert!(!root.exists());
+        assert!(WorkspaceOptions {
+            include: vec!["[".to_string()],
+            ..WorkspaceOptions::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_policy_confines_workspace_source() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("denied-source");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let denied = path_policy::with_denied("denied-source", || {
+            Workspace::create(&source, &WorkspaceOptions::default()).err()
+        });
+        assert_eq!(denied.map(|e| e.kind()), Some(io::ErrorKind::InvalidInput));
+    }
+}
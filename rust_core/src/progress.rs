@@ -0,0 +1,197 @@
+// src/progress.rs
+//! Progress of long-running operations
+//!
+//! Documentation scans, project scans and git analyses report `Progress`
+//! snapshots: the phase they are in, items processed out of a total when one is
+//! known, the percentage and an ETA derived from them. A snapshot goes to the
+//! Python callable passed as `progress_callback` (as JSON, called from the worker
+//! thread), and, when the call was given an `op_id`, is kept as the latest
+//! progress of that operation for `poll_progress_py(op_id)`. The last snapshot of
+//! an operation has phase "done".
+
+use crate::error::to_json;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Phase of the last snapshot of an operation
+pub const DONE: &str = "done";
+
+/// Operations whose latest progress is kept before finished ones are dropped
+const MAX_TRACKED: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub phase: String,
+    pub processed: usize,
+    pub total: Option<usize>,
+    /// 0 to 100, only known with a total
+    pub percent: Option<f64>,
+    pub elapsed_ms: u128,
+    pub eta_ms: Option<u128>,
+}
+
+impl Progress {
+    fn new(phase: &str, processed: usize, total: Option<usize>, elapsed_ms: u128) -> Self {
+        let total = total.filter(|&total| total >= processed);
+        let percent = total.map(|total| {
+            if total == 0 {
+                100.0
+            } else {
+                processed as f64 * 100.0 / total as f64
+            }
+        });
+        let eta_ms = total
+            .filter(|_| processed > 0)
+            .map(|total| elapsed_ms * (total - processed) as u128 / processed as u128);
+        Self {
+            phase: phase.to_string(),
+            processed,
+            total,
+            percent,
+            elapsed_ms,
+            eta_ms,
+        }
+    }
+}
+
+/// Callback receiving each snapshot of an operation
+pub type ProgressCallback<'a> = Box<dyn Fn(&Progress) + Send + Sync + 'a>;
+
+/// Where an operation reports its progress; the default reports nowhere
+pub struct ProgressSink<'a> {
+    op_id: Option<String>,
+    callback: Option<ProgressCallback<'a>>,
+    started: Instant,
+}
+
+impl Default for ProgressSink<'_> {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl<'a> ProgressSink<'a> {
+    pub fn new(
+        op_id: Option<String>,
+        callback: Option<ProgressCallback<'a>>,
+    ) -> Self {
+        Self {
+            op_id,
+            callback,
+            started: Instant::now(),
+        }
+    }
+
+    /// Reports `processed` items of `total` in `phase`
+    pub fn report(&self, phase: &str, processed: usize, total: Option<usize>) {
+        if self.op_id.is_none() && self.callback.is_none() {
+            return;
+        }
+        let progress = Progress::new(phase, processed, total, self.started.elapsed().as_millis());
+        if let Some(op_id) = &self.op_id {
+            track(op_id, progress.clone());
+        }
+        if let Some(callback) = &self.callback {
+            callback(&progress);
+        }
+    }
+
+    /// Reports the end of the operation, after `processed` items
+    pub fn finish(&self, processed: usize) {
+        self.report(DONE, processed, Some(processed));
+    }
+}
+
+impl ProgressSink<'static> {
+    /// Sink calling `callback` with each snapshot as JSON, and tracking `op_id`
+    pub fn python(op_id: Option<String>, callback: Option<Py<PyAny>>) -> Self {
+        let callback = callback.map(|callback| {
+            Box::new(move |progress: &Progress| {
+                let payload = serde_json::to_string(progress).unwrap_or_default();
+                Python::attach(|py| {
+                    if let Err(e) = callback.call1(py, (payload,)) {
+                        e.print(py);
+                    }
+                });
+            }) as ProgressCallback
+        });
+        Self::new(op_id, callback)
+    }
+}
+
+fn tracked() -> &'static Mutex<HashMap<String, Progress>> {
+    static TRACKED: OnceLock<Mutex<HashMap<String, Progress>>> = OnceLock::new();
+    TRACKED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn track(op_id: &str, progress: Progress) {
+    let mut tracked = tracked().lock().unwrap();
+    if tracked.len() >= MAX_TRACKED && !tracked.contains_key(op_id) {
+        tracked.retain(|_, progress| progress.phase != DONE);
+    }
+    tracked.insert(op_id.to_string(), progress);
+}
+
+/// Latest progress of `op_id`; a finished operation is forgotten once polled
+pub fn poll(op_id: &str) -> Option<Progress> {
+    let mut tracked = tracked().lock().unwrap();
+    let progress = tracked.get(op_id)?.clone();
+    if progress.phase == DONE {
+        tracked.remove(op_id);
+    }
+    Some(progress)
+}
+
+/// Latest progress of the operation started with `op_id`, as JSON with `phase`,
+/// `processed`, `total`, `percent`, `elapsed_ms` and `eta_ms`; None before its
+/// first report. Once the "done" snapshot has been returned the operation is
+/// forgotten.
+#[pyfunction]
+fn poll_progress_py(op_id: &str) -> PyResult<Option<String>> {
+    Ok(poll(op_id).map(|progress| to_json(&progress)).transpose()?)
+}
+
+/// Adds the progress functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(poll_progress_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_reports_to_callback_and_poll() {
+        let seen = Mutex::new(Vec::new());
+        let sink = ProgressSink::new(
+            Some("test-op".to_string()),
+            Some(Box::new(|p: &Progress| {
+                seen.lock().unwrap().push(p.clone())
+            })),
+        );
+
+        sink.report("reading", 1, Some(4));
+        let polled = poll("test-op").unwrap();
+        assert_eq!((polled.processed, polled.percent), (1, Some(25.0)));
+        assert!(polled.eta_ms.is_some());
+
+        sink.report("discovering", 0, None);
+        assert_eq!(poll("test-op").unwrap().percent, None);
+
+        sink.finish(4);
+        assert_eq!(poll("test-op").unwrap().phase, DONE);
+        assert!(poll("test-op").is_none());
+        drop(sink);
+        let phases: Vec<String> = seen
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.phase)
+            .collect();
+        assert_eq!(phases, vec!["reading", "discovering", "done"]);
+    }
+}
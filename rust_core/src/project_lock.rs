@@ -0,0 +1,437 @@
+// src/project_lock.rs
+//! Advisory locks on a project shared by several processes
+//!
+//! Two MCP servers, or parallel agents, writing to the same repository take a
+//! lock on a named scope first ("write", "git", "index", ...). A lock is a file
+//! `.cde/locks/<scope>.lock` created exclusively under the project root, holding
+//! the owner's pid, host and a token that releasing it requires. The lock is
+//! only advisory: it protects callers that ask for it. A lock whose owner is a
+//! dead process on this host, or this process without having taken it, is stale
+//! and broken by the next acquirer; locks of other hosts can only be broken by
+//! force.
+
+use crate::error::{to_json, CdeError};
+use crate::path_policy;
+use crate::process_manager::tree;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Directory of the lock files, relative to the project root
+pub const LOCKS_DIR: &str = ".cde/locks";
+
+/// How often a waiting acquirer checks the lock again
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Age after which a lock file that cannot be read (its owner died while
+/// writing it) is stale
+const UNREADABLE_GRACE: Duration = Duration::from_secs(5);
+
+/// Owner of a lock, as written in its file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub scope: String,
+    pub root: String,
+    pub pid: u32,
+    pub hostname: Option<String>,
+    /// Required to release the lock
+    pub token: String,
+    /// RFC 3339
+    pub acquired_at: String,
+}
+
+/// A lock found on a project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockStatus {
+    #[serde(flatten)]
+    pub lock: LockInfo,
+    pub path: String,
+    /// The owner is gone; the next acquirer breaks the lock
+    pub stale: bool,
+}
+
+/// Tokens of the locks this process holds
+static HELD: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn hostname() -> Option<String> {
+    static HOSTNAME: LazyLock<Option<String>> = LazyLock::new(sysinfo::System::host_name);
+    HOSTNAME.clone()
+}
+
+fn new_token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Scopes name files: letters, digits, `-`, `_` and `.`, not starting with `.`
+fn validate_scope(scope: &str) -> Result<(), CdeError> {
+    let valid = !scope.is_empty()
+        && !scope.starts_with('.')
+        && scope
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(CdeError::invalid_input(format!(
+            "Invalid lock scope '{}': expected letters, digits, '-', '_' or '.'",
+            scope
+        )))
+    }
+}
+
+fn lock_path(root: &Path, scope: &str) -> PathBuf {
+    root.join(LOCKS_DIR).join(format!("{}.lock", scope))
+}
+
+fn io_error(message: &str, path: &Path, e: &io::Error) -> CdeError {
+    CdeError::io(message).with_path(path).caused_by(e)
+}
+
+/// Owner written in the lock file at `path`, None when there is no file or it
+/// cannot be parsed
+fn read_lock(path: &Path) -> Option<LockInfo> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Whether the owner of `lock` is gone: a dead process of this host, or this
+/// process without holding the lock
+pub fn is_stale(lock: &LockInfo) -> bool {
+    if lock.hostname != hostname() {
+        return false;
+    }
+    if lock.pid == std::process::id() {
+        return !HELD.lock().unwrap().contains(&lock.token);
+    }
+    !tree::is_alive(lock.pid)
+}
+
+fn is_old(path: &Path, age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|elapsed| elapsed >= age)
+}
+
+/// Removes the lock file at `path` if it still belongs to `token` (None: any
+/// unreadable file)
+///
+/// The file is first renamed aside, so that of two processes breaking the same
+/// stale lock, the slower one does not delete the lock the faster one just took.
+fn remove_if_owned(path: &Path, token: Option<&str>) -> Result<bool, CdeError> {
+    let aside = path.with_extension(format!("lock.{}.broken", new_token()));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(io_error("Failed to break lock", path, &e)),
+    }
+    let owner = read_lock(&aside);
+    if owner.as_ref().map(|lock| lock.token.as_str()) == token {
+        let _ = fs::remove_file(&aside);
+        return Ok(true);
+    }
+    // Someone else's lock was moved: put it back unless the place was taken again
+    if fs::hard_link(&aside, path).is_err() {
+        tracing::warn!(path = %path.display(), "Lock replaced while breaking a stale one");
+    }
+    let _ = fs::remove_file(&aside);
+    Ok(false)
+}
+
+/// Creates the lock file, returning false when it already exists
+fn try_create(path: &Path, lock: &LockInfo) -> Result<bool, CdeError> {
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(io_error("Failed to create lock", path, &e)),
+    };
+    file.write_all(to_json(lock)?.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| io_error("Failed to write lock", path, &e))?;
+    Ok(true)
+}
+
+/// Takes the `scope` lock of the project at `root`, waiting up to `timeout` for
+/// its owner to release it; stale locks are broken on the way
+pub fn acquire(root: &Path, scope: &str, timeout: Duration) -> Result<LockInfo, CdeError> {
+    validate_scope(scope)?;
+    path_policy::check(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    let dir = root.join(LOCKS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| io_error("Failed to create lock directory", &dir, &e))?;
+
+    let path = lock_path(root, scope);
+    let lock = LockInfo {
+        scope: scope.to_string(),
+        root: root.to_string_lossy().into_owned(),
+        pid: std::process::id(),
+        hostname: hostname(),
+        token: new_token(),
+        acquired_at: chrono::Local::now().to_rfc3339(),
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        // Held before the file exists, so the new lock never reads as stale
+        HELD.lock().unwrap().insert(lock.token.clone());
+        match try_create(&path, &lock) {
+            Ok(true) => return Ok(lock),
+            Ok(false) => {
+                HELD.lock().unwrap().remove(&lock.token);
+            }
+            Err(e) => {
+                HELD.lock().unwrap().remove(&lock.token);
+                return Err(e);
+            }
+        }
+
+        let owner = read_lock(&path);
+        match &owner {
+            Some(owner) if is_stale(owner) => {
+                tracing::warn!(scope, pid = owner.pid, "Breaking stale project lock");
+                remove_if_owned(&path, Some(&owner.token))?;
+                continue;
+            }
+            None if is_old(&path, UNREADABLE_GRACE) => {
+                remove_if_owned(&path, None)?;
+                continue;
+            }
+            _ => {}
+        }
+        if Instant::now() >= deadline {
+            let holder = owner.map_or_else(
+                || "another process".to_string(),
+                |owner| format!("pid {} since {}", owner.pid, owner.acquired_at),
+            );
+            return Err(
+                CdeError::timeout(format!("Lock '{}' is held by {}", scope, holder))
+                    .with_path(&path),
+            );
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Releases the `scope` lock of `root` taken with `token`; returns false when
+/// the lock is not held with that token (already released or broken)
+pub fn release(root: &Path, scope: &str, token: &str) -> Result<bool, CdeError> {
+    validate_scope(scope)?;
+    path_policy::check(root)?;
+    let released = remove_if_owned(&lock_path(root, scope), Some(token))?;
+    HELD.lock().unwrap().remove(token);
+    Ok(released)
+}
+
+/// Breaks the `scope` lock of `root` whoever holds it; returns its owner
+pub fn force_break(root: &Path, scope: &str) -> Result<Option<LockInfo>, CdeError> {
+    validate_scope(scope)?;
+    path_policy::check(root)?;
+    let path = lock_path(root, scope);
+    let owner = read_lock(&path);
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error("Failed to break lock", &path, &e)),
+    }
+    if let Some(owner) = &owner {
+        tracing::warn!(scope, pid = owner.pid, "Project lock broken by force");
+        HELD.lock().unwrap().remove(&owner.token);
+    }
+    Ok(owner)
+}
+
+/// Locks of the project at `root`, sorted by scope; unreadable lock files are
+/// skipped
+pub fn list(root: &Path) -> Result<Vec<LockStatus>, CdeError> {
+    path_policy::check(root)?;
+    let dir = root.join(LOCKS_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error("Failed to list locks", &dir, &e)),
+    };
+    let mut locks: Vec<LockStatus> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lock"))
+        .filter_map(|path| {
+            let lock = read_lock(&path)?;
+            Some(LockStatus {
+                stale: is_stale(&lock),
+                path: path.to_string_lossy().into_owned(),
+                lock,
+            })
+        })
+        .collect();
+    locks.sort_by(|a, b| a.lock.scope.cmp(&b.lock.scope));
+    Ok(locks)
+}
+
+/// Take an advisory lock on a project shared with other processes
+///
+/// Waits up to `timeout_secs` (0 tries once) for the `scope` lock of `root`
+/// ("write" by default; any name of letters, digits, `-`, `_` and `.`) and
+/// raises `CdeTimeoutError` naming the holder if it is still taken. Locks whose
+/// owner process is gone are broken on the way. Returns JSON with `scope`,
+/// `root`, `pid`, `hostname`, `token` and `acquired_at`; pass the `token` to
+/// `release_project_lock_py` once the conflicting writes are done.
+#[pyfunction]
+#[pyo3(signature = (root, scope="write", timeout_secs=30.0))]
+fn acquire_project_lock_py(
+    py: Python<'_>,
+    root: &str,
+    scope: &str,
+    timeout_secs: f64,
+) -> PyResult<String> {
+    if !timeout_secs.is_finite() || timeout_secs < 0.0 {
+        return Err(CdeError::invalid_input(format!(
+            "Invalid timeout {}: expected a non-negative number of seconds",
+            timeout_secs
+        ))
+        .into());
+    }
+    let lock = py.detach(|| {
+        acquire(
+            Path::new(root),
+            scope,
+            Duration::from_secs_f64(timeout_secs),
+        )
+    })?;
+    Ok(to_json(&lock)?)
+}
+
+/// Release a project lock taken with `acquire_project_lock_py`
+///
+/// Returns False when the lock is no longer held with `token` (released
+/// already, or broken as stale or by force).
+#[pyfunction]
+fn release_project_lock_py(py: Python<'_>, root: &str, scope: &str, token: &str) -> PyResult<bool> {
+    Ok(py.detach(|| release(Path::new(root), scope, token))?)
+}
+
+/// List the locks held on a project
+///
+/// Returns a JSON list of locks (`scope`, `root`, `pid`, `hostname`, `token`,
+/// `acquired_at`, `path`) with `stale` set when their owner is gone.
+#[pyfunction]
+fn list_project_locks_py(py: Python<'_>, root: &str) -> PyResult<String> {
+    let locks = py.detach(|| list(Path::new(root)))?;
+    Ok(to_json(&locks)?)
+}
+
+/// Break a project lock whoever holds it, live owners included
+///
+/// For locks left by a crashed process on another host, which are never
+/// detected as stale. Returns the owner of the broken lock as JSON, or None
+/// when there was no lock.
+#[pyfunction]
+fn break_project_lock_py(py: Python<'_>, root: &str, scope: &str) -> PyResult<Option<String>> {
+    let owner = py.detach(|| force_break(Path::new(root), scope))?;
+    Ok(owner.map(|owner| to_json(&owner)).transpose()?)
+}
+
+/// Adds the project lock functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(acquire_project_lock_py, m)?)?;
+    m.add_function(wrap_pyfunction!(release_project_lock_py, m)?)?;
+    m.add_function(wrap_pyfunction!(list_project_locks_py, m)?)?;
+    m.add_function(wrap_pyfunction!(break_project_lock_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = acquire(dir.path(), "write", Duration::ZERO).unwrap();
+        assert!(lock_path(dir.path(), "write").is_file());
+
+        let err = acquire(dir.path(), "write", Duration::from_millis(120)).unwrap_err();
+        assert_eq!(err.kind(), "timeout");
+        let other = acquire(dir.path(), "index", Duration::ZERO).unwrap();
+
+        let locks = list(dir.path()).unwrap();
+        assert_eq!(locks.len(), 2);
+        assert_eq!(locks[1].lock, lock);
+        assert!(!locks[1].stale);
+
+        assert!(!release(dir.path(), "write", "not-the-token").unwrap());
+        assert!(release(dir.path(), "write", &lock.token).unwrap());
+        assert!(!release(dir.path(), "write", &lock.token).unwrap());
+        assert!(acquire(dir.path(), "write", Duration::ZERO).is_ok());
+        assert_eq!(
+            force_break(dir.path(), "index").unwrap().unwrap().token,
+            other.token
+        );
+        assert_eq!(force_break(dir.path(), "index").unwrap(), None);
+        assert!(acquire(dir.path(), "../escape", Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_stale_lock_is_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(LOCKS_DIR)).unwrap();
+        let dead = LockInfo {
+            scope: "write".to_string(),
+            root: dir.path().to_string_lossy().into_owned(),
+            pid: u32::MAX - 1,
+            hostname: hostname(),
+            token: "dead-owner".to_string(),
+            acquired_at: chrono::Local::now().to_rfc3339(),
+        };
+        let path = lock_path(dir.path(), "write");
+        fs::write(&path, to_json(&dead).unwrap()).unwrap();
+        assert!(list(dir.path()).unwrap()[0].stale);
+
+        let lock = acquire(dir.path(), "write", Duration::ZERO).unwrap();
+        assert_eq!(read_lock(&path).unwrap().token, lock.token);
+
+        let remote = LockInfo {
+            hostname: Some("elsewhere".to_string()),
+            ..dead
+        };
+        assert!(!is_stale(&remote));
+    }
+
+    #[test]
+    fn test_policy_confines_lock_management() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("denied-lock-project");
+        fs::create_dir_all(root.join(LOCKS_DIR)).unwrap();
+        let path = lock_path(&root, "write");
+        fs::write(&path, "{}").unwrap();
+
+        let denied = path_policy::with_denied("denied-lock-project", || {
+            [
+                release(&root, "write", "token").err(),
+                force_break(&root, "write").err(),
+                list(&root).err(),
+            ]
+        });
+        for error in denied {
+            assert!(
+                matches!(error, Some(CdeError::InvalidInput(_))),
+                "{error:?}"
+            );
+        }
+        assert!(path.is_file());
+    }
+}
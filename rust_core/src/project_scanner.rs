@@ -2,13 +2,25 @@
 // Parallel project scanner with Rayon for CDE Orchestrator
 // Now with .gitignore support using the `ignore` crate
 
+use crate::api_surface::{endpoints_in_files, ApiEndpoint};
+use crate::cancel::CancellationToken;
+use crate::error::CdeError;
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use crate::progress::ProgressSink;
+use crate::repo_health::{check_repo_health, HealthFinding, DEFAULT_MAX_PATH_LENGTH};
+use crate::test_detection::{compute_test_stats, TestStats};
+use pyo3::prelude::*;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
 /// Result of project analysis
@@ -19,7 +31,160 @@ pub struct ProjectAnalysisResult {
     pub dependency_files: Vec<String>,
     pub excluded_directories: Vec<String>,
     pub excluded_count: usize,
+    pub skipped_symlinks: usize,
+    pub symlink_cycles: usize,
+    pub duplicate_hardlinks: usize,
+    pub test_stats: TestStats,
+    pub health_findings: Vec<HealthFinding>,
+    /// HTTP endpoints declared by the code and OpenAPI files
+    #[serde(default)]
+    pub api_endpoints: Vec<ApiEndpoint>,
+    pub cancelled: bool,
     pub analysis_time_ms: u128,
+    /// Whether lists were cut to `max_list_items`
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl CapLists for ProjectAnalysisResult {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.dependency_files, max_items)
+            | cap(&mut self.excluded_directories, max_items)
+            | cap(&mut self.health_findings, max_items)
+            | cap(&mut self.api_endpoints, max_items);
+    }
+}
+
+/// Number of processed files between two progress reports
+const PROGRESS_INTERVAL: usize = 500;
+
+/// Shared handle used to cancel a running scan and observe its progress
+///
+/// Clones share the same state, so Python can keep one reference while the
+/// scan runs on another thread and call `cancel()` at any time. Cancelling
+/// goes through a `CancellationToken`, which other operations can share.
+/// `expected_files` is the total progress reports are measured against.
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct ScanHandle {
+    token: CancellationToken,
+    files_processed: Arc<AtomicUsize>,
+    expected_files: Option<usize>,
+}
+
+#[pymethods]
+impl ScanHandle {
+    #[new]
+    #[pyo3(signature = (expected_files=None, token=None))]
+    fn new(expected_files: Option<usize>, token: Option<CancellationToken>) -> Self {
+        Self {
+            token: token.unwrap_or_default(),
+            expected_files,
+            ..Self::default()
+        }
+    }
+
+    /// Requests cancellation; the scan stops walking and returns partial results
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// The token cancelling the scan
+    #[getter]
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    #[getter]
+    pub fn files_processed(&self) -> usize {
+        self.files_processed.load(Ordering::Relaxed)
+    }
+}
+
+impl ScanHandle {
+    /// The handle, cancelled through `token` instead of its own token
+    pub fn with_token(self, token: CancellationToken) -> Self {
+        Self { token, ..self }
+    }
+
+    /// Records one processed file and returns the new total
+    fn record_file(&self) -> usize {
+        self.files_processed.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Options controlling how the scanner walks the filesystem
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScanOptions {
+    /// Follow symbolic links instead of skipping them
+    pub follow_symlinks: bool,
+    /// Skip directories already visited through another link (prevents cycles and double counting)
+    pub detect_cycles: bool,
+    /// Count files with several hard links only once
+    pub dedupe_hardlinks: bool,
+    /// Files larger than this are reported as health findings
+    pub large_file_threshold_bytes: u64,
+    /// Paths longer than this are reported as health findings
+    pub max_path_length: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: false,
+            detect_cycles: true,
+            dedupe_hardlinks: false,
+            large_file_threshold_bytes: 5 * 1024 * 1024,
+            max_path_length: DEFAULT_MAX_PATH_LENGTH,
+        }
+    }
+}
+
+/// Tracks symlinks, visited directories and hard-linked files during a walk
+#[derive(Default)]
+struct LinkTracker {
+    visited_dirs: HashSet<(u64, u64)>,
+    seen_hardlinks: HashSet<(u64, u64)>,
+    skipped_symlinks: usize,
+    symlink_cycles: usize,
+    duplicate_hardlinks: usize,
+}
+
+impl LinkTracker {
+    /// Decides whether a walked entry should be kept (and, for directories, descended into)
+    fn accept(&mut self, entry: &DirEntry, options: &ScanOptions) -> bool {
+        if !options.follow_symlinks && entry.depth() > 0 && entry.path_is_symlink() {
+            self.skipped_symlinks += 1;
+            return false;
+        }
+
+        if entry.file_type().is_dir() {
+            if options.detect_cycles {
+                if let Some(id) = entry.metadata().ok().as_ref().and_then(file_identity) {
+                    if !self.visited_dirs.insert(id) {
+                        self.symlink_cycles += 1;
+                        return false;
+                    }
+                }
+            }
+            return true;
+        }
+
+        if options.dedupe_hardlinks {
+            if let Some(id) = entry.metadata().ok().as_ref().and_then(hardlink_identity) {
+                if !self.seen_hardlinks.insert(id) {
+                    self.duplicate_hardlinks += 1;
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
 }
 
 /// Scans a project directory in parallel, excluding specified directories and patterns
@@ -28,6 +193,10 @@ pub struct ProjectAnalysisResult {
 /// * `root_path` - Root directory to scan
 /// * `excluded_dirs` - Directories to exclude (e.g., "node_modules", "__pycache__")
 /// * `excluded_patterns` - File patterns to exclude (e.g., "*.map", "*.pyc")
+/// * `options` - Symlink, cycle and hard-link handling
+/// * `handle` - Cancellation flag and live progress counter
+/// * `progress` - Reports the "scanning" phase every few hundred files, then
+///   "analyzing" and "done"
 ///
 /// # Returns
 /// * `Ok(ProjectAnalysisResult)` - Analysis result with timing
@@ -36,8 +205,25 @@ pub fn scan_project(
     root_path: &str,
     excluded_dirs: Vec<String>,
     excluded_patterns: Vec<String>,
-) -> Result<ProjectAnalysisResult, String> {
+    options: &ScanOptions,
+    handle: &ScanHandle,
+    progress: &ProgressSink<'_>,
+) -> Result<ProjectAnalysisResult, CdeError> {
+    metrics::timed("scan_project", || {
+        scan(root_path, excluded_dirs, excluded_patterns, options, handle, progress)
+    })
+}
+
+fn scan(
+    root_path: &str,
+    excluded_dirs: Vec<String>,
+    excluded_patterns: Vec<String>,
+    options: &ScanOptions,
+    handle: &ScanHandle,
+    progress: &ProgressSink<'_>,
+) -> Result<ProjectAnalysisResult, CdeError> {
     let start = Instant::now();
+    let guard = path_policy::walk_guard(Path::new(root_path))?;
 
     // Load .gitignore rules if they exist
     let gitignore = load_gitignore(root_path).unwrap_or_else(|_| {
@@ -53,7 +239,7 @@ pub fn scan_project(
             match Regex::new(&regex_pattern) {
                 Ok(r) => Some(r),
                 Err(e) => {
-                    eprintln!("Failed to compile pattern {}: {}", p, e);
+                    tracing::warn!(pattern = %p, error = %e, "Failed to compile exclusion pattern");
                     None
                 }
             }
@@ -61,9 +247,26 @@ pub fn scan_project(
         .collect();
 
     // Parallel filesystem scan with WalkDir
+    // Link handling runs sequentially before par_bridge so the tracker needs no locking
+    let mut tracker = LinkTracker::default();
+    let mut loop_errors = 0usize;
     let walker = WalkDir::new(root_path)
+        .follow_links(options.follow_symlinks)
+        .max_depth(guard.max_depth())
         .into_iter()
-        .filter_map(|entry| entry.ok());
+        .filter_entry(|entry| {
+            guard.allows(entry.path(), entry.path_is_symlink()) && tracker.accept(entry, options)
+        })
+        .take_while(|_| !handle.is_cancelled())
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                if e.loop_ancestor().is_some() {
+                    loop_errors += 1;
+                }
+                None
+            }
+        });
 
     let root_path_buf = PathBuf::from(root_path);
 
@@ -76,10 +279,15 @@ pub fn scan_project(
                 let path = entry.path().to_path_buf();
 
                 // Skip directories
-                if path.is_dir() {
+                if entry.file_type().is_dir() {
                     return (files, stats, excluded);
                 }
 
+                let processed = handle.record_file();
+                if processed.is_multiple_of(PROGRESS_INTERVAL) {
+                    progress.report("scanning", processed, handle.expected_files);
+                }
+
                 // Check if in excluded directories
                 if is_in_excluded_dir(&path, &excluded_dirs) {
                     excluded += 1;
@@ -119,10 +327,21 @@ pub fn scan_project(
             },
         );
 
+    progress.report("analyzing", handle.files_processed(), handle.expected_files);
+
     // Find dependency files
     let dependency_files = find_dependency_files(&file_paths);
+    let test_stats = compute_test_stats(&root_path_buf, &file_paths);
+    let health_findings = check_repo_health(
+        &root_path_buf,
+        &file_paths,
+        options.large_file_threshold_bytes,
+        options.max_path_length,
+    );
+    let api_endpoints = endpoints_in_files(&root_path_buf, &file_paths);
 
     let analysis_time_ms = start.elapsed().as_millis();
+    progress.finish(handle.files_processed());
 
     Ok(ProjectAnalysisResult {
         file_count: file_paths.len(),
@@ -130,10 +349,46 @@ pub fn scan_project(
         dependency_files,
         excluded_directories: excluded_dirs,
         excluded_count,
+        skipped_symlinks: tracker.skipped_symlinks,
+        symlink_cycles: tracker.symlink_cycles + loop_errors,
+        duplicate_hardlinks: tracker.duplicate_hardlinks,
+        test_stats,
+        health_findings,
+        api_endpoints,
+        cancelled: handle.is_cancelled(),
         analysis_time_ms,
+        truncated: false,
     })
 }
 
+/// Device/inode pair identifying a file or directory on disk
+#[cfg(unix)]
+fn file_identity(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Identity of a regular file that has more than one hard link
+#[cfg(unix)]
+fn hardlink_identity(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() > 1 {
+        file_identity(metadata)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn hardlink_identity(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
 /// Check if a path is in an excluded directory
 fn is_in_excluded_dir(path: &Path, excluded_dirs: &[String]) -> bool {
     path.components().any(|component| {
@@ -210,22 +465,144 @@ fn find_dependency_files(file_paths: &[PathBuf]) -> Vec<String> {
 }
 
 /// Convert glob pattern to regex pattern
+///
+/// `*`, `?`, `[...]` classes (`[!...]` negated) and `{a,b}` alternatives are
+/// translated; every other character matches itself, so the result always
+/// compiles. The pattern is anchored at the end of the path only.
 fn glob_to_regex(glob_pattern: &str) -> String {
-    glob_pattern
-        .replace(".", r"\.")
-        .replace("*", ".*")
-        .replace("?", ".")
-        + "$"
+    let chars: Vec<char> = glob_pattern.chars().collect();
+    translate_glob(&chars, true) + "$"
+}
+
+fn translate_glob(chars: &[char], allow_braces: bool) -> String {
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                // The first member may be `]`, so the class ends at a later one
+                let end = (i + 2..chars.len()).find(|&j| chars[j] == ']');
+                if let Some(end) = end {
+                    regex.push_str(&glob_class(&chars[i + 1..end]));
+                    i = end;
+                } else {
+                    regex.push_str(r"\[");
+                }
+            }
+            '{' if allow_braces => {
+                let end = (i + 1..chars.len()).find(|&j| matches!(chars[j], '{' | '}'));
+                if let Some(end) = end.filter(|&j| chars[j] == '}') {
+                    let alternatives: Vec<String> = chars[i + 1..end]
+                        .split(|&c| c == ',')
+                        .map(|alternative| translate_glob(alternative, false))
+                        .collect();
+                    regex.push_str(&format!("(?:{})", alternatives.join("|")));
+                    i = end;
+                } else {
+                    regex.push_str(r"\{");
+                }
+            }
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+        i += 1;
+    }
+    regex
+}
+
+/// The regex class of the members of a glob class; reversed ranges match
+/// nothing, as in fnmatch
+fn glob_class(members: &[char]) -> String {
+    let (negated, members) = match members {
+        ['!' | '^', rest @ ..] if !rest.is_empty() => (true, rest),
+        _ => (false, members),
+    };
+    let escape = |c: char| regex::escape(c.encode_utf8(&mut [0; 4]));
+    let mut body = String::new();
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            let (low, high) = (members[i], members[i + 2]);
+            if low <= high {
+                body.push_str(&format!("{}-{}", escape(low), escape(high)));
+            }
+            i += 3;
+        } else {
+            body.push_str(&escape(members[i]));
+            i += 1;
+        }
+    }
+    match (body.is_empty(), negated) {
+        (true, true) => ".".to_string(),
+        (true, false) => r"[^\s\S]".to_string(),
+        (false, true) => format!("[^{body}]"),
+        (false, false) => format!("[{body}]"),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_glob_to_regex() {
         assert_eq!(glob_to_regex("*.map"), r".*\.map$");
         assert_eq!(glob_to_regex("*.py[co]"), r".*\.py[co]$");
+        assert_eq!(glob_to_regex("file(1).txt"), r"file\(1\)\.txt$");
+        assert_eq!(glob_to_regex("*.{js,ts}"), r".*\.(?:js|ts)$");
+        assert_eq!(glob_to_regex("[!.]*"), r"[^\.].*$");
+        assert_eq!(glob_to_regex("a[b"), r"a\[b$");
+    }
+
+    #[test]
+    fn test_glob_corpus() {
+        let read =
+            |name: &str| std::fs::read_to_string(crate::corpus::dir("globs").join(name)).unwrap();
+        let lines = |text: &str| -> Vec<String> {
+            text.lines()
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect()
+        };
+        let paths = lines(&read("paths.txt"));
+        let results = lines(&read("patterns.txt"))
+            .into_iter()
+            .map(|pattern| {
+                let regex = Regex::new(&glob_to_regex(&pattern)).unwrap();
+                let matched: Vec<&String> = paths
+                    .iter()
+                    .filter(|path| {
+                        is_matching_pattern(Path::new(path), std::slice::from_ref(&regex))
+                    })
+                    .collect();
+                (pattern, serde_json::json!(matched))
+            })
+            .collect();
+        crate::corpus::check_results("globs", results);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_glob_always_compiles(pattern in r"[a-z.*?\[\]{},!^\-\\()+$|/]{0,16}|\PC{0,16}") {
+            prop_assert!(Regex::new(&glob_to_regex(&pattern)).is_ok());
+        }
+
+        #[test]
+        fn prop_glob_literal_matches_itself(name in r"[^*?\[{\x00]{1,20}") {
+            let regex = Regex::new(&glob_to_regex(&name)).unwrap();
+            prop_assert!(regex.is_match(&name));
+            prop_assert!(regex.is_match(&format!("dir/{name}")));
+            prop_assert!(!regex.is_match(&format!("{name}\0")));
+        }
+
+        #[test]
+        fn prop_glob_star_matches_any_stem(stem in r"\PC{0,12}", extension in "[a-z0-9]{1,5}") {
+            let regex = Regex::new(&glob_to_regex(&format!("*.{extension}"))).unwrap();
+            prop_assert!(regex.is_match(&format!("{stem}.{extension}")));
+            prop_assert!(!regex.is_match(&format!("{stem}.{extension}~")));
+        }
     }
 
     #[test]
@@ -281,7 +658,11 @@ mod tests {
         let root = temp_dir.path();
 
         // Create some files
-        File::create(root.join("main.py")).unwrap();
+        fs::write(
+            root.join("main.py"),
+            "from fastapi import FastAPI\napp = FastAPI()\n\n@app.get(\"/health\")\ndef health():\n    return {}\n",
+        )
+        .unwrap();
         File::create(root.join("requirements.txt")).unwrap();
 
         // Create excluded directory
@@ -304,7 +685,10 @@ mod tests {
         let result = scan_project(
             root.to_str().unwrap(),
             excluded_dirs,
-            excluded_patterns
+            excluded_patterns,
+            &ScanOptions::default(),
+            &ScanHandle::default(),
+            &ProgressSink::default(),
         ).unwrap();
 
         // Verify results
@@ -312,5 +696,107 @@ mod tests {
         assert!(result.dependency_files.contains(&"requirements.txt".to_string()));
         assert_eq!(result.language_stats.get(".py"), Some(&1));
         assert!(result.excluded_count >= 3); // lib.js (dir), test.pyc (pattern), ignored.txt (gitignore)
+        assert_eq!(result.api_endpoints.len(), 1);
+        assert_eq!(result.api_endpoints[0].path, "/health");
+        assert_eq!(result.api_endpoints[0].handler.as_deref(), Some("health"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_project_link_handling() {
+        use std::fs::{self, File};
+        use std::os::unix::fs::symlink;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        let vendor = root.join("vendor");
+        fs::create_dir(&vendor).unwrap();
+        File::create(vendor.join("lib.py")).unwrap();
+        fs::hard_link(vendor.join("lib.py"), root.join("lib_copy.py")).unwrap();
+
+        // Symlink farm pointing back at an already scanned tree, plus a cycle
+        symlink(&vendor, root.join("vendor_link")).unwrap();
+        symlink(root, vendor.join("back_to_root")).unwrap();
+
+        let handle = ScanHandle::default();
+        let skipped =
+            scan_project(root.to_str().unwrap(), vec![], vec![], &ScanOptions::default(), &handle, &ProgressSink::default())
+                .unwrap();
+        assert_eq!(skipped.file_count, 2);
+        assert_eq!(skipped.skipped_symlinks, 2);
+
+        let options = ScanOptions {
+            follow_symlinks: true,
+            detect_cycles: true,
+            dedupe_hardlinks: true,
+            ..ScanOptions::default()
+        };
+        let followed =
+            scan_project(root.to_str().unwrap(), vec![], vec![], &options, &ScanHandle::default(), &ProgressSink::default())
+                .unwrap();
+        assert_eq!(followed.file_count, 1);
+        assert_eq!(followed.duplicate_hardlinks, 1);
+        assert!(followed.symlink_cycles >= 1);
+    }
+
+    #[test]
+    fn test_scan_project_cancelled_and_progress() {
+        use crate::progress::Progress;
+        use std::fs::File;
+        use std::sync::Mutex;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for i in 0..PROGRESS_INTERVAL * 2 {
+            File::create(root.join(format!("file_{}.txt", i))).unwrap();
+        }
+
+        let handle = ScanHandle::new(Some(PROGRESS_INTERVAL * 2), None);
+        let snapshots = Mutex::new(Vec::new());
+        let progress = ProgressSink::new(
+            None,
+            Some(Box::new(|p: &Progress| snapshots.lock().unwrap().push(p.clone()))),
+        );
+        let result = scan_project(
+            root.to_str().unwrap(),
+            vec![],
+            vec![],
+            &ScanOptions::default(),
+            &handle,
+            &progress,
+        )
+        .unwrap();
+        drop(progress);
+
+        assert!(!result.cancelled);
+        assert_eq!(handle.files_processed(), PROGRESS_INTERVAL * 2);
+        let snapshots = snapshots.into_inner().unwrap();
+        let scanning: Vec<&Progress> = snapshots.iter().filter(|p| p.phase == "scanning").collect();
+        assert_eq!(scanning.len(), 2);
+        assert!(scanning.iter().all(|p| p.eta_ms.is_some()));
+        assert_eq!(scanning[1].percent, Some(100.0));
+        assert_eq!(snapshots.last().unwrap().phase, "done");
+
+        let cancelled = ScanHandle::default();
+        cancelled.cancel();
+        let result = scan_project(
+            root.to_str().unwrap(),
+            vec![],
+            vec![],
+            &ScanOptions::default(),
+            &cancelled,
+            &ProgressSink::default(),
+        )
+        .unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.file_count, 0);
+
+        let token = CancellationToken::default();
+        let shared = ScanHandle::default().with_token(token.clone());
+        token.cancel();
+        assert!(shared.is_cancelled());
     }
 }
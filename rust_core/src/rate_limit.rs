@@ -0,0 +1,353 @@
+// src/rate_limit.rs
+//! Token-bucket rate limits shared by every caller of the core
+//!
+//! A bucket holds up to `capacity` tokens and refills at `refill_per_sec`;
+//! acquiring takes `cost` tokens or reports how long until there are enough.
+//! Per-provider API budgets and per-agent spawn rates are buckets keyed by name
+//! ("openai", "spawn:claude", ...). A daily quota is a bucket whose capacity is
+//! the budget, refilled over the day. Buckets live in the shared state store,
+//! so they carry over between calls, server restarts and processes; each
+//! update reads and writes its bucket in one immediate transaction, and the
+//! refill is computed from wall-clock time at each acquire.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::state::{self, StateStore};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// State store namespace of the buckets
+pub const RATE_LIMIT_NAMESPACE: &str = "cde/rate_limits";
+
+/// Longest a waiting acquirer sleeps before looking at the bucket again, in
+/// case another process changed it
+const MAX_WAIT_STEP: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub key: String,
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+    /// Tokens left as of `updated_ms`
+    pub tokens: f64,
+    /// Unix time in milliseconds
+    pub updated_ms: i64,
+    /// Total cost granted since the bucket was configured
+    pub consumed: f64,
+    /// Acquires refused for lack of tokens
+    pub denied: u64,
+}
+
+/// Outcome of an acquire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Acquisition {
+    pub key: String,
+    pub granted: bool,
+    /// Tokens left after the acquire
+    pub tokens: f64,
+    /// Seconds until `cost` tokens are available, 0 when granted; None when the
+    /// bucket does not refill and never will have them
+    pub retry_after_secs: Option<f64>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+impl Bucket {
+    pub fn new(
+        key: &str,
+        capacity: f64,
+        refill_per_sec: f64,
+        now_ms: i64,
+    ) -> Result<Self, CdeError> {
+        if !(capacity.is_finite() && capacity > 0.0) {
+            return Err(CdeError::invalid_input(format!(
+                "Invalid capacity {} for rate limit '{}': expected a positive number",
+                capacity, key
+            )));
+        }
+        if !(refill_per_sec.is_finite() && refill_per_sec >= 0.0) {
+            return Err(CdeError::invalid_input(format!(
+                "Invalid refill rate {} for rate limit '{}': expected a non-negative number",
+                refill_per_sec, key
+            )));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            capacity,
+            refill_per_sec,
+            tokens: capacity,
+            updated_ms: now_ms,
+            consumed: 0.0,
+            denied: 0,
+        })
+    }
+
+    /// Adds the tokens refilled since the last update; a clock going backwards
+    /// refills nothing
+    pub fn refill(&mut self, now_ms: i64) {
+        let elapsed = (now_ms - self.updated_ms).max(0) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated_ms = now_ms.max(self.updated_ms);
+    }
+
+    /// Takes `cost` tokens if there are enough
+    pub fn try_acquire(&mut self, cost: f64, now_ms: i64) -> Acquisition {
+        self.refill(now_ms);
+        let granted = self.tokens >= cost;
+        if granted {
+            self.tokens -= cost;
+            self.consumed += cost;
+        } else {
+            self.denied += 1;
+        }
+        let retry_after_secs = match granted {
+            true => Some(0.0),
+            false if self.refill_per_sec > 0.0 => Some((cost - self.tokens) / self.refill_per_sec),
+            false => None,
+        };
+        Acquisition {
+            key: self.key.clone(),
+            granted,
+            tokens: self.tokens,
+            retry_after_secs,
+        }
+    }
+}
+
+pub fn load(store: &StateStore, key: &str) -> Result<Option<Bucket>, CdeError> {
+    store
+        .get(RATE_LIMIT_NAMESPACE, key)?
+        .map(|json| from_json::<Bucket>("rate limit", &json))
+        .transpose()
+}
+
+fn save(store: &StateStore, bucket: &Bucket) -> Result<(), CdeError> {
+    store.set(RATE_LIMIT_NAMESPACE, &bucket.key, &to_json(bucket)?, None)
+}
+
+/// Creates the `key` bucket, full, or changes its limits keeping its usage
+pub fn configure(
+    store: &StateStore,
+    key: &str,
+    capacity: f64,
+    refill_per_sec: f64,
+) -> Result<Bucket, CdeError> {
+    store.immediate(|store| {
+        let now = now_ms();
+        let mut bucket = Bucket::new(key, capacity, refill_per_sec, now)?;
+        if let Some(mut previous) = load(store, key)? {
+            previous.refill(now);
+            bucket.tokens = previous.tokens.min(capacity);
+            bucket.consumed = previous.consumed;
+            bucket.denied = previous.denied;
+        }
+        save(store, &bucket)?;
+        Ok(bucket)
+    })
+}
+
+/// Takes `cost` tokens from the `key` bucket if it has enough
+pub fn try_acquire(store: &StateStore, key: &str, cost: f64) -> Result<Acquisition, CdeError> {
+    store.immediate(|store| {
+        let mut bucket = load(store, key)?.ok_or_else(|| {
+            CdeError::not_found(format!(
+                "Rate limit '{}' is not configured: call configure_rate_limit_py first",
+                key
+            ))
+        })?;
+        if !(cost.is_finite() && cost >= 0.0 && cost <= bucket.capacity) {
+            return Err(CdeError::invalid_input(format!(
+                "Invalid cost {} for rate limit '{}': expected 0 to its capacity {}",
+                cost, key, bucket.capacity
+            )));
+        }
+        let acquisition = bucket.try_acquire(cost, now_ms());
+        save(store, &bucket)?;
+        Ok(acquisition)
+    })
+}
+
+/// `try_acquire` on the shared store, retrying until granted or `timeout` is up
+pub fn acquire(key: &str, cost: f64, timeout: Duration) -> Result<Acquisition, CdeError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let acquisition = state::with_shared(|store| try_acquire(store, key, cost))?;
+        let left = deadline.saturating_duration_since(Instant::now());
+        let retry_after = match acquisition.retry_after_secs {
+            Some(secs) if !acquisition.granted && !left.is_zero() => secs,
+            _ => return Ok(acquisition),
+        };
+        let wait = Duration::from_secs_f64(retry_after)
+            .min(MAX_WAIT_STEP)
+            .min(left);
+        thread::sleep(wait);
+    }
+}
+
+/// Configure the token bucket `key` (created full if new)
+///
+/// The bucket holds up to `capacity` tokens and refills at `refill_per_sec`
+/// (0 never refills, for a fixed budget). Reconfiguring keeps the tokens left,
+/// capped at the new capacity, and the usage counters. Returns the bucket as
+/// JSON: `key`, `capacity`, `refill_per_sec`, `tokens`, `updated_ms`,
+/// `consumed` and `denied`.
+#[pyfunction]
+fn configure_rate_limit_py(
+    py: Python<'_>,
+    key: &str,
+    capacity: f64,
+    refill_per_sec: f64,
+) -> PyResult<String> {
+    let bucket =
+        py.detach(|| state::with_shared(|store| configure(store, key, capacity, refill_per_sec)))?;
+    Ok(to_json(&bucket)?)
+}
+
+/// Take `cost` tokens from the rate limit `key`
+///
+/// Without enough tokens, waits up to `timeout_secs` for the bucket to refill
+/// (0 answers at once). Returns JSON with `key`, `granted`, `tokens` left and
+/// `retry_after_secs`, the wait before `cost` tokens are available when not
+/// granted (None for an exhausted bucket that does not refill). Raises
+/// `CdeNotFoundError` for a key never configured with `configure_rate_limit_py`.
+#[pyfunction]
+#[pyo3(signature = (key, cost=1.0, timeout_secs=0.0))]
+fn rate_limit_acquire_py(
+    py: Python<'_>,
+    key: &str,
+    cost: f64,
+    timeout_secs: f64,
+) -> PyResult<String> {
+    if !(timeout_secs.is_finite() && timeout_secs >= 0.0) {
+        return Err(CdeError::invalid_input(format!(
+            "Invalid timeout {}: expected a non-negative number of seconds",
+            timeout_secs
+        ))
+        .into());
+    }
+    let acquisition = py.detach(|| acquire(key, cost, Duration::from_secs_f64(timeout_secs)))?;
+    Ok(to_json(&acquisition)?)
+}
+
+/// Current state of the rate limits, refilled to now
+///
+/// Returns a JSON list of buckets (as `configure_rate_limit_py` returns them),
+/// sorted by key: only `key` when given, every configured one otherwise.
+#[pyfunction]
+#[pyo3(signature = (key=None))]
+fn rate_limit_status_py(py: Python<'_>, key: Option<&str>) -> PyResult<String> {
+    let buckets = py.detach(|| {
+        state::with_shared(|store| {
+            let mut buckets = match key {
+                Some(key) => load(store, key)?.into_iter().collect(),
+                None => store
+                    .scan(RATE_LIMIT_NAMESPACE, "", None)?
+                    .iter()
+                    .map(|entry| from_json::<Bucket>("rate limit", &entry.value))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            let now = now_ms();
+            for bucket in &mut buckets {
+                bucket.refill(now);
+            }
+            Ok(buckets)
+        })
+    })?;
+    Ok(to_json(&buckets)?)
+}
+
+/// Remove the rate limit `key`; returns whether it was configured
+#[pyfunction]
+fn reset_rate_limit_py(py: Python<'_>, key: &str) -> PyResult<bool> {
+    Ok(py.detach(|| state::with_shared(|store| store.delete(RATE_LIMIT_NAMESPACE, key)))?)
+}
+
+/// Adds the rate limit functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(configure_rate_limit_py, m)?)?;
+    m.add_function(wrap_pyfunction!(rate_limit_acquire_py, m)?)?;
+    m.add_function(wrap_pyfunction!(rate_limit_status_py, m)?)?;
+    m.add_function(wrap_pyfunction!(reset_rate_limit_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_up_to_capacity() {
+        let mut bucket = Bucket::new("api", 10.0, 2.0, 0).unwrap();
+        assert!(bucket.try_acquire(8.0, 0).granted);
+
+        let denied = bucket.try_acquire(5.0, 0);
+        assert!(!denied.granted);
+        assert_eq!(denied.tokens, 2.0);
+        assert_eq!(denied.retry_after_secs, Some(1.5));
+
+        assert!(bucket.try_acquire(5.0, 1_500).granted);
+        bucket.refill(60_000);
+        assert_eq!(bucket.tokens, 10.0);
+        assert_eq!((bucket.consumed, bucket.denied), (13.0, 1));
+
+        let mut budget = Bucket::new("daily", 1.0, 0.0, 0).unwrap();
+        assert!(budget.try_acquire(1.0, 0).granted);
+        assert_eq!(budget.try_acquire(1.0, 1_000_000).retry_after_secs, None);
+        assert!(Bucket::new("bad", 0.0, 1.0, 0).is_err());
+        assert!(Bucket::new("bad", 1.0, -1.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_buckets_persist_in_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.sqlite3");
+        let store = StateStore::open(&path).unwrap();
+        assert_eq!(
+            try_acquire(&store, "spawn", 1.0).unwrap_err().kind(),
+            "not_found"
+        );
+
+        configure(&store, "spawn", 2.0, 0.0).unwrap();
+        assert!(try_acquire(&store, "spawn", 2.0).unwrap().granted);
+        assert!(try_acquire(&store, "spawn", 3.0).is_err());
+
+        let reopened = StateStore::open(&path).unwrap();
+        assert!(!try_acquire(&reopened, "spawn", 1.0).unwrap().granted);
+        let bucket = configure(&reopened, "spawn", 5.0, 0.0).unwrap();
+        assert_eq!(
+            (bucket.tokens, bucket.consumed, bucket.denied),
+            (0.0, 2.0, 1)
+        );
+    }
+
+    #[test]
+    fn test_connections_share_a_bucket_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.sqlite3");
+        configure(&StateStore::open(&path).unwrap(), "api", 40.0, 0.0).unwrap();
+
+        // Each thread has its own connection, as separate processes would
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let store = StateStore::open(&path).unwrap();
+                    (0..20)
+                        .filter(|_| try_acquire(&store, "api", 1.0).unwrap().granted)
+                        .count()
+                })
+            })
+            .collect();
+        let granted: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        assert_eq!(granted, 40);
+
+        let store = StateStore::open(&path).unwrap();
+        let bucket = load(&store, "api").unwrap().unwrap();
+        assert_eq!((bucket.consumed, bucket.denied), (40.0, 40));
+    }
+}
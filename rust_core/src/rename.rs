@@ -0,0 +1,525 @@
+// src/rename.rs
+//! Renaming an identifier across a project, textually
+//!
+//! Every whole-word occurrence of the old name in the selected files is replaced,
+//! in parallel. For the languages the built-in lexer knows (Python, Rust,
+//! JavaScript/TypeScript, Go, the C family, shell and Ruby), occurrences inside
+//! string literals and comments are left alone and counted as skipped. The
+//! result previews each file as a unified diff; nothing is written unless
+//! `apply` is set, and then only the files confirmed in `files`, all or none.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::patch;
+use crate::path_policy;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bytes inspected to decide whether a file is binary
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenameOptions {
+    /// Glob filters; prefix with `!` to exclude (e.g. `["*.py", "!tests/**"]`)
+    pub globs: Vec<String>,
+    pub include_hidden: bool,
+    pub case_insensitive: bool,
+    /// Only replace occurrences not embedded in a longer identifier
+    pub whole_word: bool,
+    pub skip_strings: bool,
+    pub skip_comments: bool,
+    /// Relative paths of the files to write; None confirms every changed file
+    pub files: Option<Vec<String>>,
+    /// Write the confirmed files instead of only previewing
+    pub apply: bool,
+    pub max_file_size_bytes: u64,
+}
+
+impl Default for RenameOptions {
+    fn default() -> Self {
+        Self {
+            globs: Vec::new(),
+            include_hidden: false,
+            case_insensitive: false,
+            whole_word: true,
+            skip_strings: true,
+            skip_comments: true,
+            files: None,
+            apply: false,
+            max_file_size_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRename {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    /// Language whose strings and comments were recognized, if any
+    pub language: Option<String>,
+    pub replacements: usize,
+    /// Occurrences left alone inside strings or comments
+    pub skipped: usize,
+    /// Whether the file is confirmed, so written by `apply`
+    pub selected: bool,
+    /// Unified diff of the change, `a/` and `b/` prefixed
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenameResult {
+    pub old: String,
+    pub new: String,
+    pub applied: bool,
+    /// Files with an occurrence, sorted by path
+    pub files: Vec<FileRename>,
+    pub files_searched: usize,
+    pub replacements: usize,
+    pub skipped: usize,
+}
+
+/// Lexical rules of a language, enough to find its strings and comments
+struct Syntax {
+    name: &'static str,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [u8],
+    /// `"""` and `'''` strings
+    triple_quotes: bool,
+    /// `'x'` character literals (a lone `'` is a lifetime or a label)
+    char_literals: bool,
+}
+
+const PYTHON: Syntax = Syntax {
+    name: "python",
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+    triple_quotes: true,
+    char_literals: false,
+};
+
+const RUST: Syntax = Syntax {
+    name: "rust",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"",
+    triple_quotes: false,
+    char_literals: true,
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    name: "javascript",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"'`",
+    triple_quotes: false,
+    char_literals: false,
+};
+
+const GO: Syntax = Syntax {
+    name: "go",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"`",
+    triple_quotes: false,
+    char_literals: true,
+};
+
+const C_FAMILY: Syntax = Syntax {
+    name: "c-family",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"",
+    triple_quotes: false,
+    char_literals: true,
+};
+
+const SHELL: Syntax = Syntax {
+    name: "shell",
+    line_comments: &["#"],
+    block_comment: None,
+    quotes: b"\"'",
+    triple_quotes: false,
+    char_literals: false,
+};
+
+fn syntax_of(path: &Path) -> Option<&'static Syntax> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "py" | "pyi" => &PYTHON,
+        "rs" => &RUST,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => &JAVASCRIPT,
+        "go" => &GO,
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "java" | "kt" | "kts" | "cs" | "swift"
+        | "scala" | "dart" => &C_FAMILY,
+        "sh" | "bash" | "zsh" | "rb" => &SHELL,
+        _ => return None,
+    })
+}
+
+/// End of the string opening at `start`; unterminated strings end with their
+/// line, or with the text for multi-line ones
+fn string_end(bytes: &[u8], start: usize, syntax: &Syntax) -> usize {
+    let quote = bytes[start];
+    let triple = [quote; 3];
+    let (delimiter, multiline) = if syntax.triple_quotes && bytes[start..].starts_with(&triple) {
+        (&triple[..], true)
+    } else {
+        (&triple[..1], quote == b'`')
+    };
+    let mut index = start + delimiter.len();
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n' if !multiline => return index,
+            _ if bytes[index..].starts_with(delimiter) => return index + delimiter.len(),
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// End of the character literal opening at `start`, if it is one
+fn char_literal_end(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start + 1..];
+    if rest.starts_with('\\') {
+        // '\n', '\'', '\u{1F600}'
+        let close = rest.get(2..)?.find('\'')?;
+        return (close <= 10).then_some(start + 1 + 2 + close + 1);
+    }
+    let character = rest.chars().next()?;
+    rest[character.len_utf8()..]
+        .starts_with('\'')
+        .then_some(start + 1 + character.len_utf8() + 1)
+}
+
+/// Byte ranges of the strings (false) and comments (true) of `text`
+fn literal_spans(text: &str, syntax: &Syntax) -> Vec<(usize, usize, bool)> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let rest = &bytes[index..];
+        let (end, is_comment) = if syntax
+            .line_comments
+            .iter()
+            .any(|marker| rest.starts_with(marker.as_bytes()))
+        {
+            let end = rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |at| index + at);
+            (end, true)
+        } else if let Some((open, close)) = syntax
+            .block_comment
+            .filter(|(open, _)| rest.starts_with(open.as_bytes()))
+        {
+            let end = text[index + open.len()..]
+                .find(close)
+                .map_or(bytes.len(), |at| index + open.len() + at + close.len());
+            (end, true)
+        } else if syntax.quotes.contains(&bytes[index]) {
+            (string_end(bytes, index, syntax), false)
+        } else if syntax.char_literals && bytes[index] == b'\'' {
+            match char_literal_end(text, index) {
+                Some(end) => (end, false),
+                None => {
+                    index += 1;
+                    continue;
+                }
+            }
+        } else {
+            index += 1;
+            continue;
+        };
+        let end = end.min(bytes.len());
+        spans.push((index, end, is_comment));
+        index = end;
+    }
+    spans
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `content` with the occurrences of `re` replaced by `new`, and the numbers of
+/// replaced and skipped occurrences
+fn rename_in(
+    content: &str,
+    re: &regex::Regex,
+    new: &str,
+    syntax: Option<&Syntax>,
+    options: &RenameOptions,
+) -> (String, usize, usize) {
+    let spans = match syntax {
+        Some(syntax) if options.skip_strings || options.skip_comments => {
+            literal_spans(content, syntax)
+        }
+        _ => Vec::new(),
+    };
+    let in_literal = |at: usize| {
+        let next = spans.partition_point(|&(start, _, _)| start <= at);
+        next > 0 && {
+            let (_, end, is_comment) = spans[next - 1];
+            at < end
+                && if is_comment {
+                    options.skip_comments
+                } else {
+                    options.skip_strings
+                }
+        }
+    };
+
+    let mut renamed = String::with_capacity(content.len());
+    let (mut replaced, mut skipped, mut copied) = (0, 0, 0);
+    for found in re.find_iter(content) {
+        if options.whole_word {
+            let before = content[..found.start()].chars().next_back();
+            let after = content[found.end()..].chars().next();
+            let starts_word = found.as_str().starts_with(is_identifier_char);
+            let ends_word = found.as_str().ends_with(is_identifier_char);
+            if (starts_word && before.is_some_and(is_identifier_char))
+                || (ends_word && after.is_some_and(is_identifier_char))
+            {
+                continue;
+            }
+        }
+        if in_literal(found.start()) {
+            skipped += 1;
+            continue;
+        }
+        renamed.push_str(&content[copied..found.start()]);
+        renamed.push_str(new);
+        copied = found.end();
+        replaced += 1;
+    }
+    renamed.push_str(&content[copied..]);
+    (renamed, replaced, skipped)
+}
+
+/// Text files under `root` the options select, relative paths sorted
+fn candidate_files(root: &Path, options: &RenameOptions) -> Result<Vec<String>, CdeError> {
+    let guard = path_policy::walk_guard(root)?;
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.globs {
+        overrides.add(glob).map_err(|e| {
+            CdeError::invalid_input(format!("Invalid glob '{}'", glob)).caused_by(&e)
+        })?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| CdeError::invalid_input("Invalid glob set").caused_by(&e))?;
+    let walker = WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .overrides(overrides)
+        .max_filesize(Some(options.max_file_size_bytes))
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .build();
+    let mut files: Vec<String> = walker
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| {
+            entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Renames `old` to `new` in the files under `root`, see the module docs
+pub fn rename_symbol(
+    root: &str,
+    old: &str,
+    new: &str,
+    options: &RenameOptions,
+) -> Result<RenameResult, CdeError> {
+    metrics::timed("rename_symbol", || {
+        let root_path = Path::new(root);
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        if old.is_empty() || old == new {
+            return Err(CdeError::invalid_input(
+                "The old name must be non-empty and differ from the new one",
+            ));
+        }
+        let re = RegexBuilder::new(&regex::escape(old))
+            .case_insensitive(options.case_insensitive)
+            .build()
+            .map_err(|e| CdeError::invalid_input("Invalid name").caused_by(&e))?;
+
+        let candidates = candidate_files(root_path, options)?;
+        let renamed: Vec<(FileRename, String, u64)> = candidates
+            .par_iter()
+            .filter_map(|relative| {
+                let bytes = fs::read(root_path.join(relative)).ok()?;
+                if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+                    return None;
+                }
+                let size = bytes.len() as u64;
+                let content = String::from_utf8(bytes).ok()?;
+                let syntax = syntax_of(Path::new(relative));
+                let (updated, replacements, skipped) =
+                    rename_in(&content, &re, new, syntax, options);
+                if replacements + skipped == 0 {
+                    return None;
+                }
+                let diff = TextDiff::from_lines(&content, &updated)
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(&format!("a/{}", relative), &format!("b/{}", relative))
+                    .to_string();
+                let selected = replacements > 0
+                    && options
+                        .files
+                        .as_ref()
+                        .is_none_or(|files| files.iter().any(|file| file == relative));
+                let file = FileRename {
+                    path: relative.clone(),
+                    language: syntax.map(|syntax| syntax.name.to_string()),
+                    replacements,
+                    skipped,
+                    selected,
+                    diff,
+                };
+                Some((file, updated, size))
+            })
+            .collect();
+
+        let applied = options.apply && renamed.iter().any(|(file, _, _)| file.selected);
+        if applied {
+            let changes: BTreeMap<PathBuf, Option<String>> = renamed
+                .iter()
+                .filter(|(file, _, _)| file.selected)
+                .map(|(file, updated, _)| (root_path.join(&file.path), Some(updated.clone())))
+                .collect();
+            patch::commit(&changes)?;
+        }
+        metrics::add_bytes(
+            "rename_symbol",
+            renamed.iter().map(|(_, _, size)| size).sum(),
+        );
+        let files: Vec<FileRename> = renamed.into_iter().map(|(file, _, _)| file).collect();
+        Ok(RenameResult {
+            old: old.to_string(),
+            new: new.to_string(),
+            applied,
+            replacements: files.iter().map(|file| file.replacements).sum(),
+            skipped: files.iter().map(|file| file.skipped).sum(),
+            files_searched: candidates.len(),
+            files,
+        })
+    })
+}
+
+/// Renames an identifier across the text files of a project, as JSON
+///
+/// `options_json` may set `globs` (e.g. `["*.py", "!tests/**"]`),
+/// `include_hidden`, `case_insensitive`, `whole_word` (true), `skip_strings` and
+/// `skip_comments` (true, for the languages the lexer knows), `files` (the paths
+/// confirmed for writing, by default all) and `apply` (false: preview only).
+/// Returns, per file with an occurrence, its `replacements`, the occurrences
+/// `skipped` in strings or comments, whether it is `selected` and its unified
+/// `diff`; the confirmed files are written all or none.
+#[pyfunction]
+#[pyo3(signature = (root, old, new, options_json=None))]
+fn rename_symbol_textual_py(
+    py: Python<'_>,
+    root: &str,
+    old: &str,
+    new: &str,
+    options_json: Option<&str>,
+) -> PyResult<String> {
+    let options: RenameOptions = match options_json {
+        Some(json) => from_json("options", json)?,
+        None => RenameOptions::default(),
+    };
+    let result = py.detach(|| rename_symbol(root, old, new, &options))?;
+    Ok(to_json(&result)?)
+}
+
+/// Adds the rename functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(rename_symbol_textual_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renames_code_but_not_strings_or_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("app.py"),
+            "# load_data reads\ndef load_data():\n    return \"load_data\"\n\nx = load_data() + load_data_v2()\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("lib.rs"),
+            "/* load_data */\nfn f<'a>(s: &'a str) -> char { load_data(s); 'l' }\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes.txt"), "call load_data\n").unwrap();
+        let root_str = root.to_str().unwrap();
+
+        let preview = rename_symbol(
+            root_str,
+            "load_data",
+            "fetch_data",
+            &RenameOptions::default(),
+        )
+        .unwrap();
+        assert!(!preview.applied);
+        let paths: Vec<&str> = preview.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["app.py", "lib.rs", "notes.txt"]);
+        let python = &preview.files[0];
+        assert_eq!((python.replacements, python.skipped), (2, 2));
+        assert_eq!(python.language.as_deref(), Some("python"));
+        assert!(python.diff.contains("+def fetch_data():"));
+        assert_eq!(
+            (preview.files[1].replacements, preview.files[1].skipped),
+            (1, 1)
+        );
+        assert!(preview.files[2].language.is_none());
+        assert!(fs::read_to_string(root.join("app.py"))
+            .unwrap()
+            .contains("def load_data"));
+
+        let options = RenameOptions {
+            files: Some(vec!["app.py".to_string()]),
+            apply: true,
+            ..RenameOptions::default()
+        };
+        let result = rename_symbol(root_str, "load_data", "fetch_data", &options).unwrap();
+        assert!(result.applied);
+        assert_eq!(
+            fs::read_to_string(root.join("app.py")).unwrap(),
+            "# load_data reads\ndef fetch_data():\n    return \"load_data\"\n\nx = fetch_data() + load_data_v2()\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("notes.txt")).unwrap(),
+            "call load_data\n"
+        );
+        assert!(rename_symbol(root_str, "x", "x", &RenameOptions::default()).is_err());
+    }
+}
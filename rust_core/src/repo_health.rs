@@ -0,0 +1,178 @@
+// rust_core/src/repo_health.rs
+//! Repository health checks run on top of a project scan
+//!
+//! Flags files that cause trouble outside the author's machine: oversized files,
+//! paths longer than OS limits, non-UTF8 names and names that only differ by case
+//! (which collide on Windows and default macOS filesystems).
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest path Windows accepts without the `\\?\` prefix
+pub const DEFAULT_MAX_PATH_LENGTH: usize = 260;
+
+/// Longest single file name on common filesystems (bytes)
+const MAX_COMPONENT_LENGTH: usize = 255;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HealthFinding {
+    pub kind: String,     // "large_file", "path_too_long", "non_utf8_name", "case_collision"
+    pub severity: String, // "error", "warning", "info"
+    pub path: String,
+    pub message: String,
+}
+
+/// Runs all health checks over scanned files
+///
+/// # Arguments
+/// * `root` - Scan root, used to report relative paths
+/// * `file_paths` - Files kept by the scanner
+/// * `large_file_threshold` - Size in bytes above which a file is reported
+/// * `max_path_length` - Path length above which a path is reported
+pub fn check_repo_health(
+    root: &Path,
+    file_paths: &[PathBuf],
+    large_file_threshold: u64,
+    max_path_length: usize,
+) -> Vec<HealthFinding> {
+    let mut findings: Vec<HealthFinding> = file_paths
+        .par_iter()
+        .flat_map_iter(|path| check_file(root, path, large_file_threshold, max_path_length))
+        .collect();
+
+    findings.extend(find_case_collisions(root, file_paths));
+    findings.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.path.cmp(&b.path)));
+    findings
+}
+
+fn check_file(
+    root: &Path,
+    path: &Path,
+    large_file_threshold: u64,
+    max_path_length: usize,
+) -> Vec<HealthFinding> {
+    let mut findings = Vec::new();
+    let relative = relative_display(root, path);
+
+    if let Ok(metadata) = fs::metadata(path) {
+        if metadata.len() > large_file_threshold {
+            findings.push(HealthFinding {
+                kind: "large_file".to_string(),
+                severity: "warning".to_string(),
+                path: relative.clone(),
+                message: format!(
+                    "File is {} bytes (threshold {} bytes)",
+                    metadata.len(),
+                    large_file_threshold
+                ),
+            });
+        }
+    }
+
+    let path_length = path.as_os_str().len();
+    if path_length > max_path_length {
+        findings.push(HealthFinding {
+            kind: "path_too_long".to_string(),
+            severity: "warning".to_string(),
+            path: relative.clone(),
+            message: format!(
+                "Path is {} characters long (limit {})",
+                path_length, max_path_length
+            ),
+        });
+    } else if let Some(component) = path
+        .components()
+        .find(|c| c.as_os_str().len() > MAX_COMPONENT_LENGTH)
+    {
+        findings.push(HealthFinding {
+            kind: "path_too_long".to_string(),
+            severity: "warning".to_string(),
+            path: relative.clone(),
+            message: format!(
+                "Path component is {} bytes long (limit {})",
+                component.as_os_str().len(),
+                MAX_COMPONENT_LENGTH
+            ),
+        });
+    }
+
+    if path.strip_prefix(root).unwrap_or(path).to_str().is_none() {
+        findings.push(HealthFinding {
+            kind: "non_utf8_name".to_string(),
+            severity: "error".to_string(),
+            path: relative,
+            message: "File name is not valid UTF-8".to_string(),
+        });
+    }
+
+    findings
+}
+
+/// Groups paths that are identical when compared case-insensitively
+fn find_case_collisions(root: &Path, file_paths: &[PathBuf]) -> Vec<HealthFinding> {
+    let mut by_folded: HashMap<String, Vec<String>> = HashMap::new();
+    for path in file_paths {
+        let relative = relative_display(root, path);
+        by_folded.entry(relative.to_lowercase()).or_default().push(relative);
+    }
+
+    by_folded
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            HealthFinding {
+                kind: "case_collision".to_string(),
+                severity: "error".to_string(),
+                path: group[0].clone(),
+                message: format!(
+                    "Paths differ only by case and collide on case-insensitive filesystems: {}",
+                    group.join(", ")
+                ),
+            }
+        })
+        .collect()
+}
+
+fn relative_display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_repo_health() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        let mut big = File::create(root.join("big.bin")).unwrap();
+        big.write_all(&[0u8; 2048]).unwrap();
+        File::create(root.join("small.txt")).unwrap();
+
+        let files = vec![
+            root.join("big.bin"),
+            root.join("small.txt"),
+            root.join("Readme.md"),
+            root.join("README.md"),
+            root.join("a".repeat(300)),
+        ];
+
+        let findings = check_repo_health(root, &files, 1024, DEFAULT_MAX_PATH_LENGTH);
+        let kinds: Vec<&str> = findings.iter().map(|f| f.kind.as_str()).collect();
+
+        assert_eq!(kinds, vec!["case_collision", "large_file", "path_too_long"]);
+        assert_eq!(findings[0].path, "README.md");
+        assert_eq!(findings[1].path, "big.bin");
+    }
+}
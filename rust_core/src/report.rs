@@ -0,0 +1,886 @@
+// src/report.rs
+//! Human-readable renderings of the analysis reports
+//!
+//! A documentation quality report, a workflow validation report, a Git analysis
+//! or a governance audit, given as the JSON the other functions return, is laid
+//! out once as badges, metrics, tables and lists, then written as Markdown (for
+//! PR comments and job summaries) or as a self-contained HTML page. The kind of
+//! report is recognized from its fields. Given the previous report of the same
+//! kind, each metric shows its change with an arrow, green when it improved.
+
+use crate::audit::AuditReport;
+use crate::documentation::QualityReport;
+use crate::error::CdeError;
+use crate::git_analyzer::GitAnalysis;
+use crate::workflow_validator::WorkflowValidationReport;
+use pyo3::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tone {
+    Good,
+    Warn,
+    Bad,
+    Neutral,
+}
+
+impl Tone {
+    fn of_score(score: f64) -> Self {
+        if score >= 80.0 {
+            Tone::Good
+        } else if score >= 60.0 {
+            Tone::Warn
+        } else {
+            Tone::Bad
+        }
+    }
+
+    fn class(self) -> &'static str {
+        match self {
+            Tone::Good => "good",
+            Tone::Warn => "warn",
+            Tone::Bad => "bad",
+            Tone::Neutral => "neutral",
+        }
+    }
+
+    fn emoji(self) -> &'static str {
+        match self {
+            Tone::Good => "🟢",
+            Tone::Warn => "🟡",
+            Tone::Bad => "🔴",
+            Tone::Neutral => "⚪",
+        }
+    }
+}
+
+struct Badge {
+    label: String,
+    value: String,
+    tone: Tone,
+}
+
+/// Which way a metric should move
+#[derive(Clone, Copy, PartialEq)]
+enum Better {
+    Higher,
+    Lower,
+    Neither,
+}
+
+struct Metric {
+    label: &'static str,
+    value: f64,
+    better: Better,
+    previous: Option<f64>,
+}
+
+enum Block {
+    Metrics(Vec<Metric>),
+    Table {
+        headers: Vec<&'static str>,
+        rows: Vec<Vec<String>>,
+        /// Rows left out beyond `max_rows`
+        omitted: usize,
+    },
+    List(Vec<String>),
+    Text(String),
+}
+
+struct Section {
+    heading: String,
+    blocks: Vec<Block>,
+}
+
+/// A report laid out, independently of the output format
+struct Layout {
+    title: String,
+    badges: Vec<Badge>,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Result<Self, CdeError> {
+        match name.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(CdeError::invalid_input(format!(
+                "Unknown report format: {} (expected markdown or html)",
+                name
+            ))),
+        }
+    }
+}
+
+enum Report {
+    Quality(QualityReport),
+    Workflows(WorkflowValidationReport),
+    Git(Box<GitAnalysis>),
+    Audit(AuditReport),
+}
+
+fn parse_as<T: DeserializeOwned>(value: serde_json::Value, what: &str) -> Result<T, CdeError> {
+    serde_json::from_value(value)
+        .map_err(|e| CdeError::invalid_input(format!("Invalid {}", what)).caused_by(&e))
+}
+
+impl Report {
+    /// The report in `json`, recognized from its fields
+    fn parse(json: &str) -> Result<Self, CdeError> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| CdeError::invalid_input("Report is not valid JSON").caused_by(&e))?;
+        let has = |key: &str| value.get(key).is_some();
+        if has("checks") && has("exit_code") {
+            Ok(Report::Audit(parse_as(value, "audit report")?))
+        } else if has("quality_score") {
+            Ok(Report::Quality(parse_as(value, "quality report")?))
+        } else if has("workflows_found") {
+            Ok(Report::Workflows(parse_as(
+                value,
+                "workflow validation report",
+            )?))
+        } else if has("repository_info") {
+            Ok(Report::Git(Box::new(parse_as(value, "Git analysis")?)))
+        } else {
+            Err(CdeError::invalid_input(
+                "Unknown report: expected a quality report, a workflow validation report, a Git analysis or an audit",
+            ))
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Report::Quality(_) => "quality",
+            Report::Workflows(_) => "workflows",
+            Report::Git(_) => "git",
+            Report::Audit(_) => "audit",
+        }
+    }
+
+    /// Named numbers of the report, compared across runs
+    fn metrics(&self) -> Vec<(&'static str, f64, Better)> {
+        use Better::*;
+        match self {
+            Report::Quality(r) => vec![
+                ("Quality score", r.quality_score as f64, Higher),
+                ("Documents", r.total_docs as f64, Neither),
+                ("With metadata", r.docs_with_metadata as f64, Higher),
+                ("Without metadata", r.docs_without_metadata as f64, Lower),
+                ("Links", r.total_links as f64, Neither),
+                ("Broken links", r.broken_internal_links.len() as f64, Lower),
+                ("Orphaned", r.orphaned_docs.len() as f64, Lower),
+                ("Misplaced", r.misplaced_docs.len() as f64, Lower),
+                ("Large files", r.large_files.len() as f64, Lower),
+            ],
+            Report::Workflows(r) => vec![
+                ("YAML files", r.total_files as f64, Neither),
+                ("Valid files", r.valid_files as f64, Higher),
+                ("Invalid files", r.invalid_files as f64, Lower),
+                ("Issues", r.issues.len() as f64, Lower),
+                ("Missing templates", r.missing_templates.len() as f64, Lower),
+            ],
+            Report::Git(r) => vec![
+                ("Commits", r.repository_info.total_commits as f64, Neither),
+                (
+                    "Commits per week",
+                    r.commit_history.average_commits_per_week,
+                    Neither,
+                ),
+                ("Contributors", r.contributor_insights.len() as f64, Neither),
+                ("Branches", r.branch_analysis.total_branches as f64, Neither),
+                (
+                    "Stale branches",
+                    r.branch_analysis.stale_branches.len() as f64,
+                    Lower,
+                ),
+                ("Hotspots", r.code_churn.hotspots.len() as f64, Lower),
+            ],
+            Report::Audit(r) => {
+                let mut metrics = vec![("Score", r.score as f64, Higher)];
+                for check in &r.checks {
+                    let label = match check.check.as_str() {
+                        "documentation" => "Documentation",
+                        "metadata" => "Metadata",
+                        "workflows" => "Workflows",
+                        "repo_health" => "Repository health",
+                        _ => "Other check",
+                    };
+                    metrics.push((label, check.score as f64, Higher));
+                }
+                metrics.push(("Errors", r.errors as f64, Lower));
+                metrics.push(("Warnings", r.warnings as f64, Lower));
+                metrics
+            }
+        }
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Arrow and tone of the change from `previous` to `value`
+fn trend(value: f64, previous: f64, better: Better) -> (String, Tone) {
+    let delta = value - previous;
+    if delta.abs() < 0.05 {
+        return ("▬ 0".to_string(), Tone::Neutral);
+    }
+    let arrow = if delta > 0.0 { "▲" } else { "▼" };
+    let sign = if delta > 0.0 { "+" } else { "" };
+    let tone = match better {
+        Better::Neither => Tone::Neutral,
+        Better::Higher if delta > 0.0 => Tone::Good,
+        Better::Lower if delta < 0.0 => Tone::Good,
+        _ => Tone::Bad,
+    };
+    (format!("{} {}{}", arrow, sign, format_number(delta)), tone)
+}
+
+fn table(headers: Vec<&'static str>, mut rows: Vec<Vec<String>>, max_rows: usize) -> Block {
+    let omitted = rows.len().saturating_sub(max_rows);
+    rows.truncate(max_rows);
+    Block::Table {
+        headers,
+        rows,
+        omitted,
+    }
+}
+
+fn list_section(heading: &str, items: &[String], max_rows: usize) -> Option<Section> {
+    if items.is_empty() {
+        return None;
+    }
+    let mut shown: Vec<String> = items.iter().take(max_rows).cloned().collect();
+    if items.len() > max_rows {
+        shown.push(format!("… {} more", items.len() - max_rows));
+    }
+    Some(Section {
+        heading: heading.to_string(),
+        blocks: vec![Block::List(shown)],
+    })
+}
+
+fn score_badge(label: &str, score: f64) -> Badge {
+    Badge {
+        label: label.to_string(),
+        value: format!("{:.1}", score),
+        tone: Tone::of_score(score),
+    }
+}
+
+fn count_badge(label: &str, count: usize, bad_when_positive: bool) -> Badge {
+    let tone = match (count, bad_when_positive) {
+        (0, true) => Tone::Good,
+        (_, true) => Tone::Bad,
+        _ => Tone::Neutral,
+    };
+    Badge {
+        label: label.to_string(),
+        value: count.to_string(),
+        tone,
+    }
+}
+
+fn layout(report: &Report, previous: Option<&Report>, max_rows: usize) -> Layout {
+    let previous_metrics = previous.map(Report::metrics).unwrap_or_default();
+    let metrics: Vec<Metric> = report
+        .metrics()
+        .into_iter()
+        .map(|(label, value, better)| Metric {
+            label,
+            value,
+            better,
+            previous: previous_metrics
+                .iter()
+                .find(|(previous_label, _, _)| *previous_label == label)
+                .map(|(_, previous, _)| *previous),
+        })
+        .collect();
+    let mut sections = vec![Section {
+        heading: "Metrics".to_string(),
+        blocks: vec![Block::Metrics(metrics)],
+    }];
+
+    let (title, badges) = match report {
+        Report::Quality(r) => {
+            sections.extend(list_section("Issues", &r.issues, max_rows));
+            sections.extend(list_section(
+                "Recommendations",
+                &r.recommendations,
+                max_rows,
+            ));
+            if !r.broken_internal_links.is_empty() {
+                let rows = r
+                    .broken_internal_links
+                    .iter()
+                    .map(|link| {
+                        let (from, to) = link.split_once(" -> ").unwrap_or((link, ""));
+                        vec![from.to_string(), to.to_string()]
+                    })
+                    .collect();
+                sections.push(Section {
+                    heading: "Broken links".to_string(),
+                    blocks: vec![table(vec!["Document", "Target"], rows, max_rows)],
+                });
+            }
+            sections.extend(list_section(
+                "Orphaned documents",
+                &r.orphaned_docs,
+                max_rows,
+            ));
+            sections.extend(list_section(
+                "Misplaced documents",
+                &r.misplaced_docs,
+                max_rows,
+            ));
+            sections.extend(list_section("Large files", &r.large_files, max_rows));
+            (
+                "Documentation quality".to_string(),
+                vec![
+                    score_badge("score", r.quality_score as f64),
+                    count_badge("documents", r.total_docs, false),
+                    count_badge("broken links", r.broken_internal_links.len(), true),
+                ],
+            )
+        }
+        Report::Workflows(r) => {
+            sections[0].blocks.push(Block::Text(r.summary.clone()));
+            if !r.issues.is_empty() {
+                let rows = r
+                    .issues
+                    .iter()
+                    .map(|issue| {
+                        vec![
+                            issue.severity.clone(),
+                            issue.file.clone(),
+                            issue.line.map_or_else(String::new, |line| line.to_string()),
+                            issue.message.clone(),
+                        ]
+                    })
+                    .collect();
+                sections.push(Section {
+                    heading: "Issues".to_string(),
+                    blocks: vec![table(
+                        vec!["Severity", "File", "Line", "Message"],
+                        rows,
+                        max_rows,
+                    )],
+                });
+            }
+            sections.extend(list_section("Workflows", &r.workflows_found, max_rows));
+            sections.extend(list_section(
+                "Missing templates",
+                &r.missing_templates,
+                max_rows,
+            ));
+            (
+                "Workflow validation".to_string(),
+                vec![
+                    Badge {
+                        label: "workflows".to_string(),
+                        value: if r.valid { "valid" } else { "invalid" }.to_string(),
+                        tone: if r.valid { Tone::Good } else { Tone::Bad },
+                    },
+                    count_badge("files", r.total_files, false),
+                    count_badge("issues", r.issues.len(), true),
+                ],
+            )
+        }
+        Report::Git(r) => {
+            let mut contributors: Vec<_> = r.contributor_insights.iter().collect();
+            contributors.sort_by_key(|c| std::cmp::Reverse(c.total_commits));
+            let rows = contributors
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.name.clone(),
+                        c.total_commits.to_string(),
+                        format!("+{}", c.lines_added),
+                        format!("-{}", c.lines_deleted),
+                        format!("{:.1}", c.impact_score),
+                    ]
+                })
+                .collect();
+            sections.push(Section {
+                heading: "Contributors".to_string(),
+                blocks: vec![table(
+                    vec!["Name", "Commits", "Added", "Deleted", "Impact"],
+                    rows,
+                    max_rows,
+                )],
+            });
+            let rows = r
+                .code_churn
+                .most_changed_files
+                .iter()
+                .map(|f| {
+                    vec![
+                        f.path.clone(),
+                        f.times_changed.to_string(),
+                        format!("+{}", f.total_insertions),
+                        format!("-{}", f.total_deletions),
+                    ]
+                })
+                .collect();
+            sections.push(Section {
+                heading: "Most changed files".to_string(),
+                blocks: vec![table(
+                    vec!["File", "Changes", "Added", "Deleted"],
+                    rows,
+                    max_rows,
+                )],
+            });
+            let rows = r
+                .commit_history
+                .recent_commits
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.hash.chars().take(8).collect(),
+                        c.date.clone(),
+                        c.author.clone(),
+                        c.message.lines().next().unwrap_or_default().to_string(),
+                    ]
+                })
+                .collect();
+            sections.push(Section {
+                heading: "Recent commits".to_string(),
+                blocks: vec![table(
+                    vec!["Commit", "Date", "Author", "Message"],
+                    rows,
+                    max_rows,
+                )],
+            });
+            let stale: Vec<String> = r
+                .branch_analysis
+                .stale_branches
+                .iter()
+                .map(|b| format!("{} (last commit {})", b.name, b.last_commit_date))
+                .collect();
+            sections.extend(list_section("Stale branches", &stale, max_rows));
+            if !r.architectural_decisions.is_empty() {
+                let rows = r
+                    .architectural_decisions
+                    .iter()
+                    .map(|d| {
+                        vec![
+                            d.date.clone(),
+                            d.decision_type.clone(),
+                            d.impact.clone(),
+                            d.message.lines().next().unwrap_or_default().to_string(),
+                        ]
+                    })
+                    .collect();
+                sections.push(Section {
+                    heading: "Architectural decisions".to_string(),
+                    blocks: vec![table(
+                        vec!["Date", "Type", "Impact", "Message"],
+                        rows,
+                        max_rows,
+                    )],
+                });
+            }
+            let info = &r.repository_info;
+            (
+                format!("Git analysis: {}", info.path),
+                vec![
+                    count_badge("commits", info.total_commits, false),
+                    count_badge("contributors", r.contributor_insights.len(), false),
+                    Badge {
+                        label: "activity".to_string(),
+                        value: r.development_patterns.commit_frequency.clone(),
+                        tone: Tone::Neutral,
+                    },
+                    Badge {
+                        label: "releases".to_string(),
+                        value: r.release_patterns.release_frequency.clone(),
+                        tone: Tone::Neutral,
+                    },
+                ],
+            )
+        }
+        Report::Audit(r) => {
+            let rows = r
+                .checks
+                .iter()
+                .map(|check| {
+                    vec![
+                        check.check.clone(),
+                        format!(
+                            "{} {:.1}",
+                            Tone::of_score(check.score as f64).emoji(),
+                            check.score
+                        ),
+                        check.errors.to_string(),
+                        check.warnings.to_string(),
+                        check.summary.clone(),
+                    ]
+                })
+                .collect();
+            sections.push(Section {
+                heading: "Checks".to_string(),
+                blocks: vec![table(
+                    vec!["Check", "Score", "Errors", "Warnings", "Summary"],
+                    rows,
+                    max_rows,
+                )],
+            });
+            for (check, findings) in &r.findings {
+                if findings.is_empty() {
+                    continue;
+                }
+                let rows = findings
+                    .iter()
+                    .map(|f| {
+                        vec![
+                            f.severity.clone(),
+                            f.path.clone().unwrap_or_default(),
+                            f.message.clone(),
+                        ]
+                    })
+                    .collect();
+                sections.push(Section {
+                    heading: format!("Findings: {}", check),
+                    blocks: vec![table(vec!["Severity", "Path", "Message"], rows, max_rows)],
+                });
+            }
+            (
+                format!("Governance audit: {}", r.root),
+                vec![
+                    score_badge("score", r.score as f64),
+                    Badge {
+                        label: "audit".to_string(),
+                        value: if r.passed { "passed" } else { "failed" }.to_string(),
+                        tone: if r.passed { Tone::Good } else { Tone::Bad },
+                    },
+                    count_badge("errors", r.errors, true),
+                    Badge {
+                        label: "warnings".to_string(),
+                        value: r.warnings.to_string(),
+                        tone: if r.warnings == 0 {
+                            Tone::Good
+                        } else {
+                            Tone::Warn
+                        },
+                    },
+                ],
+            )
+        }
+    };
+    Layout {
+        title,
+        badges,
+        sections,
+    }
+}
+
+/// A table cell of Markdown, kept on one line
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn render_markdown(layout: &Layout) -> String {
+    let mut out = format!("# {}\n\n", layout.title);
+    let badges: Vec<String> = layout
+        .badges
+        .iter()
+        .map(|b| {
+            format!(
+                "{} **{}:** {}",
+                b.tone.emoji(),
+                b.label,
+                markdown_cell(&b.value)
+            )
+        })
+        .collect();
+    out.push_str(&badges.join(" · "));
+    out.push('\n');
+    for section in &layout.sections {
+        out.push_str(&format!("\n## {}\n", section.heading));
+        for block in &section.blocks {
+            out.push('\n');
+            match block {
+                Block::Metrics(metrics) => {
+                    let trends = metrics.iter().any(|m| m.previous.is_some());
+                    out.push_str(if trends {
+                        "| Metric | Value | Trend |\n| --- | ---: | --- |\n"
+                    } else {
+                        "| Metric | Value |\n| --- | ---: |\n"
+                    });
+                    for metric in metrics {
+                        out.push_str(&format!(
+                            "| {} | {} |",
+                            metric.label,
+                            format_number(metric.value)
+                        ));
+                        if trends {
+                            let cell = metric.previous.map_or_else(String::new, |previous| {
+                                let (arrow, tone) = trend(metric.value, previous, metric.better);
+                                format!("{} {}", tone.emoji(), arrow)
+                            });
+                            out.push_str(&format!(" {} |", cell));
+                        }
+                        out.push('\n');
+                    }
+                }
+                Block::Table {
+                    headers,
+                    rows,
+                    omitted,
+                } => {
+                    out.push_str(&format!("| {} |\n", headers.join(" | ")));
+                    out.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                    for row in rows {
+                        let cells: Vec<String> =
+                            row.iter().map(|cell| markdown_cell(cell)).collect();
+                        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    }
+                    if *omitted > 0 {
+                        out.push_str(&format!("\n_{} more rows not shown_\n", omitted));
+                    }
+                }
+                Block::List(items) => {
+                    for item in items {
+                        out.push_str(&format!("- {}\n", item.replace('\n', " ")));
+                    }
+                }
+                Block::Text(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Helvetica,Arial,sans-serif;max-width:1100px;margin:2rem auto;padding:0 1rem;color:#1f2328}\
+h1{border-bottom:1px solid #d0d7de;padding-bottom:.3em}\
+.badges{display:flex;flex-wrap:wrap;gap:.5rem;margin:1rem 0}\
+.badge{display:inline-flex;border-radius:4px;overflow:hidden;font-size:.85rem}\
+.badge span{padding:.2rem .5rem;color:#fff}.badge .label{background:#555}\
+.good{background:#2da44e}.warn{background:#bf8700}.bad{background:#cf222e}.neutral{background:#6e7781}\
+table{border-collapse:collapse;width:100%;margin:.5rem 0}\
+th,td{border:1px solid #d0d7de;padding:.35rem .6rem;text-align:left;vertical-align:top}\
+th{background:#f6f8fa}td.num{text-align:right;font-variant-numeric:tabular-nums}\
+.trend.good{color:#1a7f37;background:none}.trend.bad{color:#cf222e;background:none}.trend.neutral{color:#6e7781;background:none}\
+.more{color:#6e7781;font-style:italic}";
+
+fn render_html(layout: &Layout) -> String {
+    let title = escape_html(&layout.title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<div class=\"badges\">\n",
+        title, STYLE, title
+    );
+    for badge in &layout.badges {
+        out.push_str(&format!(
+            "<span class=\"badge\"><span class=\"label\">{}</span><span class=\"{}\">{}</span></span>\n",
+            escape_html(&badge.label),
+            badge.tone.class(),
+            escape_html(&badge.value)
+        ));
+    }
+    out.push_str("</div>\n");
+    for section in &layout.sections {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.heading)));
+        for block in &section.blocks {
+            match block {
+                Block::Metrics(metrics) => {
+                    let trends = metrics.iter().any(|m| m.previous.is_some());
+                    out.push_str("<table>\n<tr><th>Metric</th><th>Value</th>");
+                    if trends {
+                        out.push_str("<th>Trend</th>");
+                    }
+                    out.push_str("</tr>\n");
+                    for metric in metrics {
+                        out.push_str(&format!(
+                            "<tr><td>{}</td><td class=\"num\">{}</td>",
+                            metric.label,
+                            format_number(metric.value)
+                        ));
+                        if trends {
+                            match metric.previous {
+                                Some(previous) => {
+                                    let (arrow, tone) =
+                                        trend(metric.value, previous, metric.better);
+                                    out.push_str(&format!(
+                                        "<td class=\"trend {}\">{}</td>",
+                                        tone.class(),
+                                        arrow
+                                    ));
+                                }
+                                None => out.push_str("<td></td>"),
+                            }
+                        }
+                        out.push_str("</tr>\n");
+                    }
+                    out.push_str("</table>\n");
+                }
+                Block::Table {
+                    headers,
+                    rows,
+                    omitted,
+                } => {
+                    out.push_str("<table>\n<tr>");
+                    for header in headers {
+                        out.push_str(&format!("<th>{}</th>", header));
+                    }
+                    out.push_str("</tr>\n");
+                    for row in rows {
+                        out.push_str("<tr>");
+                        for cell in row {
+                            out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+                        }
+                        out.push_str("</tr>\n");
+                    }
+                    out.push_str("</table>\n");
+                    if *omitted > 0 {
+                        out.push_str(&format!(
+                            "<p class=\"more\">{} more rows not shown</p>\n",
+                            omitted
+                        ));
+                    }
+                }
+                Block::List(items) => {
+                    out.push_str("<ul>\n");
+                    for item in items {
+                        out.push_str(&format!("<li>{}</li>\n", escape_html(item)));
+                    }
+                    out.push_str("</ul>\n");
+                }
+                Block::Text(text) => out.push_str(&format!("<p>{}</p>\n", escape_html(text))),
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Renders the report in `report_json` as `format`, with the change of each
+/// metric since `previous_json` (a report of the same kind) when given; tables
+/// and lists show at most `max_rows` rows
+pub fn render_report(
+    report_json: &str,
+    format: ReportFormat,
+    previous_json: Option<&str>,
+    max_rows: usize,
+) -> Result<String, CdeError> {
+    let report = Report::parse(report_json)?;
+    let previous = previous_json.map(Report::parse).transpose()?;
+    if let Some(previous) = &previous {
+        if previous.kind() != report.kind() {
+            return Err(CdeError::invalid_input(format!(
+                "Previous report is a {} report, not a {} report",
+                previous.kind(),
+                report.kind()
+            )));
+        }
+    }
+    let layout = layout(&report, previous.as_ref(), max_rows);
+    Ok(match format {
+        ReportFormat::Markdown => render_markdown(&layout),
+        ReportFormat::Html => render_html(&layout),
+    })
+}
+
+/// Renders an analysis report as Markdown or HTML
+///
+/// `report_json` is the JSON of a documentation quality report, a workflow
+/// validation report, a Git analysis or a governance audit, recognized from its
+/// fields. `format` is "markdown" or "html" (a self-contained page). With
+/// `previous_json`, the previous report of the same kind, metrics show trend
+/// arrows. Tables and lists are cut to `max_rows` rows.
+#[pyfunction]
+#[pyo3(signature = (report_json, format="markdown", previous_json=None, max_rows=50))]
+fn render_report_py(
+    py: Python<'_>,
+    report_json: &str,
+    format: &str,
+    previous_json: Option<&str>,
+    max_rows: usize,
+) -> PyResult<String> {
+    let format = ReportFormat::parse(format)?;
+    Ok(py.detach(|| render_report(report_json, format, previous_json, max_rows))?)
+}
+
+/// Adds the report rendering functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(render_report_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::to_json;
+
+    fn quality(score: f32, broken: &[&str]) -> String {
+        to_json(&QualityReport {
+            quality_score: score,
+            total_docs: 4,
+            docs_with_metadata: 3,
+            docs_without_metadata: 1,
+            total_links: 5,
+            broken_internal_links: broken.iter().map(|b| b.to_string()).collect(),
+            orphaned_docs: Vec::new(),
+            misplaced_docs: Vec::new(),
+            large_files: Vec::new(),
+            issues: vec!["🔴 <script> issue".to_string()],
+            recommendations: Vec::new(),
+            truncated: false,
+            cancelled: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_renders_markdown_and_html_with_trends() {
+        let previous = quality(70.0, &["a.md -> gone.md", "b.md -> x|y.md"]);
+        let current = quality(85.5, &["a.md -> gone.md"]);
+
+        let markdown =
+            render_report(&current, ReportFormat::Markdown, Some(&previous), 50).unwrap();
+        assert!(markdown.starts_with("# Documentation quality\n\n🟢 **score:** 85.5 · "));
+        assert!(markdown.contains("| Quality score | 85.5 | 🟢 ▲ +15.5 |"));
+        assert!(markdown.contains("| Broken links | 1 | 🟢 ▼ -1 |"));
+        assert!(markdown.contains("| Documents | 4 | ⚪ ▬ 0 |"));
+        assert!(markdown.contains("| Document | Target |\n| --- | --- |\n| a.md | gone.md |"));
+
+        let html = render_report(&previous, ReportFormat::Html, None, 1).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<li>🔴 &lt;script&gt; issue</li>"));
+        assert!(html.contains("<p class=\"more\">1 more rows not shown</p>"));
+        assert!(!html.contains("Trend"));
+
+        let workflows = r#"{"valid":true,"total_files":1,"valid_files":1,"invalid_files":0,"issues":[],"workflows_found":["w.yml"],"missing_templates":[],"summary":"ok"}"#;
+        assert!(render_report(workflows, ReportFormat::Markdown, Some(&previous), 50).is_err());
+        assert!(render_report("{}", ReportFormat::Markdown, None, 50).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// src/snapshot.rs
+//! Manifests of a project's files, and what changed between two of them
+//!
+//! A snapshot lists every file the `ignore` walker keeps (`.gitignore`, `.ignore`
+//! and hidden-file rules honored) with its blake3 hash, size and modification
+//! time. Given the previous snapshot, files whose size and modification time did
+//! not change keep their previous hash instead of being read again, so resuming a
+//! session only hashes what was touched while it was offline.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use ignore::{WalkBuilder, WalkState};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    /// Hex blake3 hash of the content
+    pub hash: String,
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub root: String,
+    /// RFC 3339
+    pub created_at: String,
+    /// Sorted by path
+    pub files: Vec<SnapshotEntry>,
+    pub total_bytes: u64,
+    /// Files hashed for this snapshot, the others were reused from the previous one
+    #[serde(default)]
+    pub hashed_files: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Present in both with different content
+    pub changed: Vec<String>,
+    pub unchanged: usize,
+}
+
+pub(crate) fn modified_ms(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Snapshot of the files under `root_path`, reusing the hashes of `previous` for
+/// files with the same size and modification time
+pub fn snapshot_project(
+    root_path: &str,
+    previous: Option<&Snapshot>,
+    include_hidden: bool,
+) -> Result<Snapshot, CdeError> {
+    metrics::timed("snapshot_project", || {
+        take_snapshot(root_path, previous, include_hidden)
+    })
+}
+
+fn take_snapshot(
+    root_path: &str,
+    previous: Option<&Snapshot>,
+    include_hidden: bool,
+) -> Result<Snapshot, CdeError> {
+    let root = Path::new(root_path);
+    let guard = path_policy::walk_guard(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root_path));
+    }
+
+    let found: Mutex<Vec<(PathBuf, String, fs::Metadata)>> = Mutex::new(Vec::new());
+    WalkBuilder::new(root)
+        .hidden(!include_hidden)
+        .max_depth(Some(guard.max_depth()))
+        .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+        .threads(rayon::current_num_threads())
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    return WalkState::Continue;
+                }
+                if let Ok(metadata) = entry.metadata() {
+                    let relative = entry
+                        .path()
+                        .strip_prefix(root)
+                        .unwrap_or(entry.path())
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    found
+                        .lock()
+                        .unwrap()
+                        .push((entry.into_path(), relative, metadata));
+                }
+                WalkState::Continue
+            })
+        });
+
+    let known: HashMap<&str, &SnapshotEntry> = previous
+        .map(|previous| {
+            previous
+                .files
+                .iter()
+                .map(|entry| (entry.path.as_str(), entry))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut files: Vec<(SnapshotEntry, bool)> = found
+        .into_inner()
+        .unwrap()
+        .into_par_iter()
+        .filter_map(|(path, relative, metadata)| {
+            let size = metadata.len();
+            let modified_ms = modified_ms(&metadata);
+            let reused = known
+                .get(relative.as_str())
+                .filter(|entry| entry.size == size && entry.modified_ms == modified_ms)
+                .map(|entry| entry.hash.clone());
+            let hashed = reused.is_none();
+            // A file removed or unreadable since the walk is left out
+            let hash = match reused {
+                Some(hash) => hash,
+                None => hash_file(&path).ok()?,
+            };
+            Some((
+                SnapshotEntry {
+                    path: relative,
+                    hash,
+                    size,
+                    modified_ms,
+                },
+                hashed,
+            ))
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+
+    let hashed_bytes = files
+        .iter()
+        .filter(|(_, hashed)| *hashed)
+        .map(|(entry, _)| entry.size)
+        .sum();
+    metrics::add_bytes("snapshot_project", hashed_bytes);
+    let hashed_files = files.iter().filter(|(_, hashed)| *hashed).count();
+    let files: Vec<SnapshotEntry> = files.into_iter().map(|(entry, _)| entry).collect();
+    Ok(Snapshot {
+        root: root_path.to_string(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        total_bytes: files.iter().map(|entry| entry.size).sum(),
+        files,
+        hashed_files,
+    })
+}
+
+/// Files added, removed and changed from `before` to `after`, each list sorted
+pub fn diff_snapshots(before: &Snapshot, after: &Snapshot) -> SnapshotDiff {
+    let previous: HashMap<&str, &str> = before
+        .files
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry.hash.as_str()))
+        .collect();
+    let mut diff = SnapshotDiff::default();
+    for entry in &after.files {
+        match previous.get(entry.path.as_str()) {
+            None => diff.added.push(entry.path.clone()),
+            Some(&hash) if hash != entry.hash => diff.changed.push(entry.path.clone()),
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+    let current: HashSet<&str> = after
+        .files
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    diff.removed = before
+        .files
+        .iter()
+        .filter(|entry| !current.contains(entry.path.as_str()))
+        .map(|entry| entry.path.clone())
+        .collect();
+    diff.added.sort();
+    diff.changed.sort();
+    diff.removed.sort();
+    diff
+}
+
+/// Manifest of the files under `root` (`path`, `hash`, `size`, `modified_ms`,
+/// sorted by path) as JSON, honoring `.gitignore`. With `previous_json`, an
+/// earlier snapshot of the same root, unchanged files (same size and modification
+/// time) are not hashed again.
+#[pyfunction]
+#[pyo3(signature = (root, previous_json=None, include_hidden=false))]
+fn snapshot_project_py(
+    py: Python<'_>,
+    root: &str,
+    previous_json: Option<&str>,
+    include_hidden: bool,
+) -> PyResult<String> {
+    let previous: Option<Snapshot> = previous_json
+        .map(|json| from_json("previous snapshot", json))
+        .transpose()?;
+    let snapshot = py.detach(|| snapshot_project(root, previous.as_ref(), include_hidden))?;
+    Ok(to_json(&snapshot)?)
+}
+
+/// Paths `added`, `removed` and `changed` from snapshot `a` to snapshot `b`, and
+/// the count of `unchanged` files, as JSON
+#[pyfunction]
+fn diff_snapshots_py(a: &str, b: &str) -> PyResult<String> {
+    let before: Snapshot = from_json("snapshot a", a)?;
+    let after: Snapshot = from_json("snapshot b", b)?;
+    Ok(to_json(&diff_snapshots(&before, &after))?)
+}
+
+/// Adds the snapshot functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(snapshot_project_py, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_snapshots_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reuses_hashes_and_diffs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::write(root.join("kept.md"), "same").unwrap();
+        fs::write(root.join("edited.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("deleted.txt"), "bye").unwrap();
+        fs::create_dir(root.join("build")).unwrap();
+        fs::write(root.join("build").join("out.o"), "ignored").unwrap();
+        // The walker only applies .gitignore inside a repository
+        fs::create_dir(root.join(".git")).unwrap();
+
+        let root_path = root.to_str().unwrap();
+        let before = snapshot_project(root_path, None, false).unwrap();
+        let paths: Vec<&str> = before.files.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["deleted.txt", "edited.rs", "kept.md"]);
+        assert_eq!(
+            before.files[2].hash,
+            blake3::hash(b"same").to_hex().as_str()
+        );
+        assert_eq!((before.hashed_files, before.total_bytes), (3, 19));
+
+        fs::write(root.join("edited.rs"), "fn main() { run() }").unwrap();
+        fs::remove_file(root.join("deleted.txt")).unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src").join("new.py"), "print()").unwrap();
+
+        let after = snapshot_project(root_path, Some(&before), false).unwrap();
+        // Only the edited and the new file were read
+        assert_eq!(after.hashed_files, 2);
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(diff.added, vec!["src/new.py"]);
+        assert_eq!(diff.removed, vec!["deleted.txt"]);
+        assert_eq!(diff.changed, vec!["edited.rs"]);
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -0,0 +1,332 @@
+// src/state.rs
+//! Key-value store shared by the tools of a project
+//!
+//! Scan snapshots, indexes and agent checkpoints are kept as string values under
+//! string keys, namespaced by project (its canonical path) and optionally expiring
+//! after a TTL. Everything lives in one SQLite database, `CDE_STATE_PATH` when
+//! set or `~/.cde/state.sqlite3`, so several processes can share it; expired
+//! entries are never returned and are purged as the namespace is written.
+
+use crate::error::{to_json, CdeError};
+use pyo3::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable pointing the shared store at another database file
+pub const STATE_PATH_ENV: &str = "CDE_STATE_PATH";
+
+/// How long a write waits for another process holding the database
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub key: String,
+    pub value: String,
+    /// RFC 3339, None for entries that never expire
+    pub expires_at: Option<String>,
+}
+
+pub struct StateStore {
+    connection: Connection,
+}
+
+fn store_error(message: &str, e: rusqlite::Error) -> CdeError {
+    CdeError::io(message).caused_by(&e)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+impl StateStore {
+    /// Opens the database at `path`, creating it and its directory if needed
+    pub fn open(path: &Path) -> Result<Self, CdeError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                CdeError::io("Failed to create state directory")
+                    .with_path(parent)
+                    .caused_by(&e)
+            })?;
+        }
+        let connection = Connection::open(path)
+            .map_err(|e| store_error("Failed to open state store", e).with_path(path))?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| {
+                connection.execute_batch(
+                    "PRAGMA journal_mode = WAL;
+                     CREATE TABLE IF NOT EXISTS state (
+                         namespace TEXT NOT NULL,
+                         key TEXT NOT NULL,
+                         value TEXT NOT NULL,
+                         expires_at INTEGER,
+                         PRIMARY KEY (namespace, key)
+                     );",
+                )
+            })
+            .map_err(|e| store_error("Failed to initialize state store", e).with_path(path))?;
+        Ok(Self { connection })
+    }
+
+    /// Runs `work` in an immediate transaction, committed when it succeeds and
+    /// rolled back otherwise. Other connections, in this process or another,
+    /// cannot write until it ends, so a read-modify-write in `work` is atomic.
+    pub fn immediate<T>(
+        &self,
+        work: impl FnOnce(&Self) -> Result<T, CdeError>,
+    ) -> Result<T, CdeError> {
+        let transaction =
+            Transaction::new_unchecked(&self.connection, TransactionBehavior::Immediate)
+                .map_err(|e| store_error("Failed to lock state store", e))?;
+        let value = work(self)?;
+        transaction
+            .commit()
+            .map_err(|e| store_error("Failed to commit state", e))?;
+        Ok(value)
+    }
+
+    /// Value of `key` in `namespace`, None if missing or expired
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, CdeError> {
+        self.connection
+            .query_row(
+                "SELECT value FROM state
+                 WHERE namespace = ?1 AND key = ?2 AND (expires_at IS NULL OR expires_at > ?3)",
+                params![namespace, key, now_ms()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| store_error("Failed to read state", e))
+    }
+
+    /// Stores `value` under `key`, replacing any previous value; with `ttl`, the
+    /// entry expires that long from now
+    pub fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &str,
+        ttl: Option<Duration>,
+    ) -> Result<(), CdeError> {
+        let now = now_ms();
+        let expires_at = ttl.map(|ttl| now.saturating_add(ttl.as_millis() as i64));
+        self.connection
+            .execute(
+                "DELETE FROM state WHERE namespace = ?1 AND expires_at <= ?2",
+                params![namespace, now],
+            )
+            .and_then(|_| {
+                self.connection.execute(
+                    "INSERT OR REPLACE INTO state (namespace, key, value, expires_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![namespace, key, value, expires_at],
+                )
+            })
+            .map_err(|e| store_error("Failed to write state", e))?;
+        Ok(())
+    }
+
+    /// Removes `key`; returns whether it was stored (expired entries included)
+    pub fn delete(&self, namespace: &str, key: &str) -> Result<bool, CdeError> {
+        let removed = self
+            .connection
+            .execute(
+                "DELETE FROM state WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+            )
+            .map_err(|e| store_error("Failed to delete state", e))?;
+        Ok(removed > 0)
+    }
+
+    /// Live entries of `namespace` whose key starts with `prefix`, sorted by key,
+    /// at most `limit` of them
+    pub fn scan(
+        &self,
+        namespace: &str,
+        prefix: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<StateEntry>, CdeError> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT key, value, expires_at FROM state
+                 WHERE namespace = ?1 AND key >= ?2 AND (expires_at IS NULL OR expires_at > ?3)
+                 ORDER BY key",
+            )
+            .map_err(|e| store_error("Failed to scan state", e))?;
+        let rows = statement
+            .query_map(params![namespace, prefix, now_ms()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            })
+            .map_err(|e| store_error("Failed to scan state", e))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value, expires_at) =
+                row.map_err(|e| store_error("Failed to scan state", e))?;
+            // Keys are ordered, so the first one without the prefix ends the range
+            if !key.starts_with(prefix) || limit.is_some_and(|limit| entries.len() >= limit) {
+                break;
+            }
+            entries.push(StateEntry {
+                key,
+                value,
+                expires_at: expires_at
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map(|at| at.to_rfc3339()),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Namespace of the project at `project`: its canonical path, or the path as
+/// given when it does not exist
+pub fn namespace(project: &str) -> String {
+    std::fs::canonicalize(project)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| project.to_string())
+}
+
+fn store_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(STATE_PATH_ENV) {
+        return PathBuf::from(path);
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map_or_else(std::env::temp_dir, PathBuf::from)
+        .join(".cde")
+        .join("state.sqlite3")
+}
+
+/// Shared store of the process, reopened when `CDE_STATE_PATH` changes
+pub(crate) fn with_shared<T>(work: impl FnOnce(&StateStore) -> Result<T, CdeError>) -> Result<T, CdeError> {
+    static SHARED: OnceLock<Mutex<Option<(PathBuf, StateStore)>>> = OnceLock::new();
+    let mut shared: MutexGuard<_> = SHARED
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let path = store_path();
+    if shared.as_ref().is_none_or(|(open, _)| *open != path) {
+        let store = StateStore::open(&path)?;
+        *shared = Some((path, store));
+    }
+    let (_, store) = shared.as_ref().expect("state store was just opened");
+    work(store)
+}
+
+/// Value stored under `key` for `project`, None if missing or expired
+#[pyfunction]
+fn state_get_py(py: Python<'_>, project: &str, key: &str) -> PyResult<Option<String>> {
+    let namespace = namespace(project);
+    Ok(py.detach(|| with_shared(|store| store.get(&namespace, key)))?)
+}
+
+/// Stores `value` under `key` for `project`; with `ttl_secs`, the entry expires
+/// that many seconds from now
+#[pyfunction]
+#[pyo3(signature = (project, key, value, ttl_secs=None))]
+fn state_set_py(
+    py: Python<'_>,
+    project: &str,
+    key: &str,
+    value: &str,
+    ttl_secs: Option<f64>,
+) -> PyResult<()> {
+    let ttl = ttl_secs
+        .map(|secs| {
+            Duration::try_from_secs_f64(secs).map_err(|_| {
+                CdeError::invalid_input(format!("Invalid ttl_secs: {} (expected >= 0)", secs))
+            })
+        })
+        .transpose()?;
+    let namespace = namespace(project);
+    Ok(py.detach(|| with_shared(|store| store.set(&namespace, key, value, ttl)))?)
+}
+
+/// Removes `key` from `project`; returns whether it was stored
+#[pyfunction]
+fn state_delete_py(py: Python<'_>, project: &str, key: &str) -> PyResult<bool> {
+    let namespace = namespace(project);
+    Ok(py.detach(|| with_shared(|store| store.delete(&namespace, key)))?)
+}
+
+/// Entries of `project` whose key starts with `prefix`, as a JSON list of `key`,
+/// `value` and `expires_at` sorted by key, at most `limit` of them
+#[pyfunction]
+#[pyo3(signature = (project, prefix="", limit=None))]
+fn state_scan_py(
+    py: Python<'_>,
+    project: &str,
+    prefix: &str,
+    limit: Option<usize>,
+) -> PyResult<String> {
+    let namespace = namespace(project);
+    let entries = py.detach(|| with_shared(|store| store.scan(&namespace, prefix, limit)))?;
+    Ok(to_json(&entries)?)
+}
+
+/// Adds the state store functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(state_get_py, m)?)?;
+    m.add_function(wrap_pyfunction!(state_set_py, m)?)?;
+    m.add_function(wrap_pyfunction!(state_delete_py, m)?)?;
+    m.add_function(wrap_pyfunction!(state_scan_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_namespaces_ttl_and_prefix_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.sqlite3");
+        let store = StateStore::open(&path).unwrap();
+
+        store.set("a", "scan/1", "first", None).unwrap();
+        store
+            .set("a", "scan/2", "second", Some(Duration::from_secs(3600)))
+            .unwrap();
+        store.set("a", "index", "kept", None).unwrap();
+        store
+            .set("a", "scan/expired", "gone", Some(Duration::ZERO))
+            .unwrap();
+        store.set("b", "scan/1", "other project", None).unwrap();
+
+        assert_eq!(store.get("a", "scan/1").unwrap().as_deref(), Some("first"));
+        assert_eq!(
+            store.get("b", "scan/1").unwrap().as_deref(),
+            Some("other project")
+        );
+        assert_eq!(store.get("a", "scan/expired").unwrap(), None);
+
+        let scanned = store.scan("a", "scan/", None).unwrap();
+        let keys: Vec<&str> = scanned.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["scan/1", "scan/2"]);
+        assert!(scanned[0].expires_at.is_none() && scanned[1].expires_at.is_some());
+        assert_eq!(store.scan("a", "", Some(1)).unwrap().len(), 1);
+
+        store.set("a", "scan/1", "replaced", None).unwrap();
+        assert!(store.delete("a", "scan/2").unwrap());
+        assert!(!store.delete("a", "scan/2").unwrap());
+
+        // Reopening sees what was written
+        drop(store);
+        let store = StateStore::open(&path).unwrap();
+        assert_eq!(
+            store.get("a", "scan/1").unwrap().as_deref(),
+            Some("replaced")
+        );
+        assert_eq!(store.get("a", "scan/2").unwrap(), None);
+    }
+}
@@ -0,0 +1,409 @@
+// src/summarize.rs
+//! Extractive summaries proposed for documents without an `llm_summary`
+//!
+//! No model is involved: the summary is the opening of the first prose paragraph
+//! followed by the sentences of the rest of the document that score highest by
+//! TF-IDF, kept in document order within `max_chars`. Terms are runs of letters
+//! and digits of any script, with each ideograph or kana a term of its own, and
+//! their document frequency is taken over the whole corpus, so common words of
+//! any language weigh little without a stop-word list. Headings, tables, code
+//! blocks and frontmatter never end up in a summary.
+
+use crate::chunking::{fence, heading};
+use crate::doc_index::field;
+use crate::error::{to_json, CdeError};
+use crate::frontmatter;
+use crate::metadata::extract_metadata;
+use crate::metrics;
+use crate::path_policy;
+use crate::text_file;
+use ignore::WalkBuilder;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummarySuggestion {
+    /// Relative to the root, with `/` separators
+    pub path: String,
+    pub suggestion: String,
+    /// Sentences the suggestion is made of
+    pub sentences: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Sentence {
+    text: String,
+    /// Index of the paragraph it belongs to
+    paragraph: usize,
+}
+
+/// Ideographs, kana and hangul, written without spaces between words
+fn is_ideographic(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+/// Lowercased terms of `text`
+fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_ideographic(c) {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            terms.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            terms.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        terms.push(word);
+    }
+    terms
+}
+
+/// Markdown inline syntax reduced to its text: links and images to their label,
+/// code spans and emphasis to their content
+fn plain(text: &str) -> String {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static MARKS: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+    let marks = MARKS.get_or_init(|| Regex::new(r"\*\*|__|[*`]|<[^>]+>").unwrap());
+    let text = link.replace_all(text, "$1");
+    marks
+        .replace_all(&text, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prose paragraphs of the Markdown body, without headings, tables, quotes'
+/// markers, list markers, HTML comments or code
+fn paragraphs(body: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut open_fence: Option<(char, usize)> = None;
+    let mut in_comment = false;
+    let mut flush = |current: &mut Vec<String>| {
+        if !current.is_empty() {
+            paragraphs.push(plain(&current.join(" ")));
+            current.clear();
+        }
+    };
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if let Some((marker, length)) = open_fence {
+            if fence(line).is_some_and(|(m, l)| m == marker && l >= length) {
+                open_fence = None;
+            }
+            continue;
+        }
+        if in_comment {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        if let Some(opened) = fence(line) {
+            flush(&mut current);
+            open_fence = Some(opened);
+            continue;
+        }
+        if trimmed.starts_with("<!--") {
+            flush(&mut current);
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        if trimmed.is_empty()
+            || heading(line).is_some()
+            || trimmed.starts_with('|')
+            || trimmed
+                .chars()
+                .all(|c| matches!(c, '-' | '=' | '*' | '_' | ' '))
+            || line.starts_with("    ")
+        {
+            flush(&mut current);
+            continue;
+        }
+        let text = trimmed.trim_start_matches('>').trim_start();
+        let item = text
+            .strip_prefix(['-', '*', '+'])
+            .filter(|rest| rest.starts_with(' '))
+            .or_else(|| {
+                let digits = text.find(|c: char| !c.is_ascii_digit())?;
+                text[digits..]
+                    .strip_prefix(['.', ')'])
+                    .filter(|rest| digits > 0 && rest.starts_with(' '))
+            });
+        match item {
+            // A list item is a paragraph of its own
+            Some(item) => {
+                flush(&mut current);
+                current.push(item.trim().to_string());
+                flush(&mut current);
+            }
+            None => current.push(text.to_string()),
+        }
+    }
+    flush(&mut current);
+    paragraphs.retain(|paragraph| !terms(paragraph).is_empty());
+    paragraphs
+}
+
+/// Sentences of `paragraph`, ending at `.`, `!` or `?` before a space or at
+/// their full-width forms
+fn split_sentences(paragraph: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = paragraph.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let end = index + c.len_utf8();
+        let ends = match c {
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if ends {
+            let sentence = paragraph[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+            start = end;
+        }
+    }
+    let rest = paragraph[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest.to_string());
+    }
+    sentences
+}
+
+fn sentences_of(body: &str) -> Vec<Sentence> {
+    paragraphs(body)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(paragraph, text)| {
+            split_sentences(&text)
+                .into_iter()
+                .map(move |text| Sentence { text, paragraph })
+        })
+        .collect()
+}
+
+/// `text` cut at a word boundary to fit `max_chars` with its ellipsis
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > cut.len() / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([',', ';', ':', ' ']))
+}
+
+/// Summary of `sentences` within `max_chars`, given the number of documents
+/// each term appears in out of `documents`
+fn summarize_sentences(
+    sentences: &[Sentence],
+    document_frequency: &HashMap<String, usize>,
+    documents: usize,
+    max_chars: usize,
+) -> Vec<String> {
+    let Some(first) = sentences.first() else {
+        return Vec::new();
+    };
+    let mut frequency: HashMap<String, usize> = HashMap::new();
+    for sentence in sentences {
+        for term in terms(&sentence.text) {
+            *frequency.entry(term).or_default() += 1;
+        }
+    }
+    let weight = |term: &String| {
+        let df = document_frequency.get(term).copied().unwrap_or(1) as f64;
+        frequency[term] as f64 * ((1.0 + documents as f64) / (1.0 + df)).ln()
+    };
+    let mut ranked: Vec<(usize, f64)> = sentences
+        .iter()
+        .enumerate()
+        .filter(|(_, sentence)| sentence.paragraph != first.paragraph)
+        .map(|(index, sentence)| {
+            let terms: HashSet<String> = terms(&sentence.text).into_iter().collect();
+            let score =
+                terms.iter().map(weight).sum::<f64>() / (terms.len() as f64).sqrt().max(1.0);
+            (index, score)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let length = |chosen: &[usize]| {
+        chosen
+            .iter()
+            .map(|&index| sentences[index].text.chars().count() + 1)
+            .sum::<usize>()
+    };
+    if length(&[0]) > max_chars + 1 {
+        return vec![truncate(&first.text, max_chars)];
+    }
+    // The opening as far as it fits, then the best of the rest that fit
+    let mut chosen: Vec<usize> = Vec::new();
+    for (index, sentence) in sentences.iter().enumerate() {
+        if sentence.paragraph != first.paragraph
+            || length(&chosen) + length(&[index]) > max_chars + 1
+        {
+            break;
+        }
+        chosen.push(index);
+    }
+    for (index, _) in ranked {
+        if length(&chosen) + length(&[index]) <= max_chars + 1 {
+            chosen.push(index);
+        }
+    }
+    chosen.sort_unstable();
+    chosen
+        .into_iter()
+        .map(|index| sentences[index].text.clone())
+        .collect()
+}
+
+/// Suggests an `llm_summary` of at most `max_chars` characters for every
+/// Markdown document under `root` that has none
+pub fn suggest_summaries(root: &str, max_chars: usize) -> Result<Vec<SummarySuggestion>, CdeError> {
+    metrics::timed("suggest_summaries", || {
+        let root_path = Path::new(root);
+        let guard = path_policy::walk_guard(root_path)?;
+        if !root_path.is_dir() {
+            return Err(CdeError::not_a_directory(root));
+        }
+        if max_chars == 0 {
+            return Err(CdeError::invalid_input("max_chars must be positive"));
+        }
+        let walker = WalkBuilder::new(root_path)
+            .max_depth(Some(guard.max_depth()))
+            .filter_entry(move |entry| guard.allows(entry.path(), entry.path_is_symlink()))
+            .build();
+        let paths: Vec<_> = walker
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "md"))
+            .map(|entry| entry.into_path())
+            .collect();
+
+        // Every document counts for the document frequency, summarized or not
+        let documents: Vec<(String, bool, Vec<Sentence>)> = paths
+            .par_iter()
+            .filter_map(|path| {
+                let content = text_file::read_text(path).ok()?;
+                metrics::add_bytes("suggest_summaries", content.len() as u64);
+                let metadata = extract_metadata(&content);
+                let missing = field(metadata.as_ref(), |m| m.llm_summary.as_ref()).is_none();
+                let relative = path
+                    .strip_prefix(root_path)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                Some((relative, missing, sentences_of(frontmatter::body(&content))))
+            })
+            .collect();
+        let mut document_frequency: HashMap<String, usize> = HashMap::new();
+        for (_, _, sentences) in &documents {
+            let unique: HashSet<String> = sentences
+                .iter()
+                .flat_map(|sentence| terms(&sentence.text))
+                .collect();
+            for term in unique {
+                *document_frequency.entry(term).or_default() += 1;
+            }
+        }
+
+        let mut suggestions: Vec<SummarySuggestion> = documents
+            .par_iter()
+            .filter(|(_, missing, _)| *missing)
+            .filter_map(|(path, _, sentences)| {
+                let summary =
+                    summarize_sentences(sentences, &document_frequency, documents.len(), max_chars);
+                (!summary.is_empty()).then(|| SummarySuggestion {
+                    path: path.clone(),
+                    sentences: summary.len(),
+                    suggestion: summary.join(" "),
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(suggestions)
+    })
+}
+
+/// Suggests `llm_summary` values for the documents under `root` missing one, as JSON
+///
+/// Each suggestion (`path`, `suggestion`, `sentences`) is extracted from the
+/// document itself: its first paragraph, then its most distinctive sentences
+/// by TF-IDF over the corpus, within `max_chars`. No LLM is called and nothing
+/// is written; `update_frontmatter_py` can apply the ones kept.
+#[pyfunction]
+#[pyo3(signature = (root, max_chars=300))]
+fn suggest_summaries_py(py: Python<'_>, root: &str, max_chars: usize) -> PyResult<String> {
+    let suggestions = py.detach(|| suggest_summaries(root, max_chars))?;
+    Ok(to_json(&suggestions)?)
+}
+
+/// Adds the summary suggestion functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(suggest_summaries_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_suggests_extractive_summaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("cache.md"),
+            "---\ntitle: Cache\n---\n# Cache\n\nThe cache keeps **scan results** between runs. See [the spec](spec.md).\n\n```rust\nfn main() {}\n```\n\n## Details\n\nThe project uses the cache. Entries expire after a TTL measured in seconds.\nA note.\n\n| a | b |\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("done.md"),
+            "---\nllm_summary: Already there\n---\nThe project is done.\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("jp.md"),
+            "これは文書です。キャッシュを使います。\n",
+        )
+        .unwrap();
+
+        let suggestions = suggest_summaries(root.to_str().unwrap(), 300).unwrap();
+        let paths: Vec<&str> = suggestions.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, vec!["cache.md", "jp.md"]);
+        assert_eq!(
+            suggestions[0].suggestion,
+            "The cache keeps scan results between runs. See the spec. The project uses the cache. Entries expire after a TTL measured in seconds. A note."
+        );
+        assert_eq!(suggestions[1].sentences, 2);
+
+        // Within the budget, the distinctive sentence wins over the common one
+        let short = suggest_summaries(root.to_str().unwrap(), 110).unwrap();
+        assert_eq!(
+            short[0].suggestion,
+            "The cache keeps scan results between runs. See the spec. Entries expire after a TTL measured in seconds."
+        );
+        let tiny = suggest_summaries(root.to_str().unwrap(), 20).unwrap();
+        assert_eq!(tiny[0].suggestion, "The cache keeps…");
+    }
+}
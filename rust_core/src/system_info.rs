@@ -0,0 +1,299 @@
+// src/system_info.rs
+//! Snapshot of the machine's resources, for deciding where agents run
+//!
+//! One call gathers what the scheduler weighs before starting agents locally:
+//! CPU count, usage and load average, memory (and the cgroup limit inside a
+//! container), free space per mounted disk, OS and architecture, and power
+//! status. CPU usage needs two samples `MINIMUM_CPU_UPDATE_INTERVAL` apart, so
+//! it is only measured on request. sysinfo does not read batteries; power
+//! status comes from `/sys/class/power_supply` on Linux and is None elsewhere.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use sysinfo::{CpuRefreshKind, DiskKind, Disks, MemoryRefreshKind, RefreshKind, System};
+
+const MB: u64 = 1024 * 1024;
+
+/// Where Linux exposes batteries and power adapters
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuInfo {
+    pub logical_cores: usize,
+    pub physical_cores: Option<usize>,
+    pub brand: String,
+    /// Global usage in percent (0-100), when sampled
+    pub usage_pct: Option<f32>,
+    /// 1, 5 and 15 minute load averages; zeros on Windows
+    pub load_average: [f64; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryInfo {
+    pub total_mb: u64,
+    pub available_mb: u64,
+    pub used_mb: u64,
+    pub swap_total_mb: u64,
+    pub swap_free_mb: u64,
+    /// Memory limit of the cgroup the process runs in, if limited
+    pub cgroup_limit_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskInfo {
+    pub mount_point: String,
+    pub file_system: String,
+    /// "ssd", "hdd" or "unknown"
+    pub kind: String,
+    pub total_mb: u64,
+    pub available_mb: u64,
+    pub removable: bool,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryInfo {
+    pub name: String,
+    /// Charge in percent
+    pub percent: Option<f32>,
+    /// "charging", "discharging", "full", "not charging" or "unknown"
+    pub status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerInfo {
+    /// Whether a power adapter is connected, None when there is none to ask
+    pub ac_online: Option<bool>,
+    pub batteries: Vec<BatteryInfo>,
+    /// Running from a battery: no adapter online and a battery discharging
+    pub on_battery: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    /// As in `std::env::consts::OS`: "linux", "macos", "windows", ...
+    pub os: String,
+    /// Distribution or product name and version, such as "Ubuntu 24.04"
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub arch: String,
+    pub hostname: Option<String>,
+    pub uptime_secs: u64,
+    pub cpu: CpuInfo,
+    pub memory: MemoryInfo,
+    pub disks: Vec<DiskInfo>,
+    pub power: Option<PowerInfo>,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+/// Power status from a Linux `power_supply` class directory; None when it lists
+/// neither a battery nor an adapter
+pub fn power_status(dir: &Path) -> Option<PowerInfo> {
+    let mut supplies: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    supplies.sort();
+
+    let mut ac_online = None;
+    let mut batteries = Vec::new();
+    for supply in supplies {
+        match read_trimmed(&supply.join("type")).as_deref() {
+            Some("Battery") => batteries.push(BatteryInfo {
+                name: supply
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                percent: read_trimmed(&supply.join("capacity"))
+                    .and_then(|capacity| capacity.parse().ok()),
+                status: read_trimmed(&supply.join("status"))
+                    .map(|status| status.to_ascii_lowercase())
+                    .unwrap_or_else(|| "unknown".to_string()),
+            }),
+            Some("Mains" | "USB") => {
+                let online = read_trimmed(&supply.join("online")).as_deref() == Some("1");
+                ac_online = Some(ac_online.unwrap_or(false) || online);
+            }
+            _ => {}
+        }
+    }
+    if ac_online.is_none() && batteries.is_empty() {
+        return None;
+    }
+    let on_battery = ac_online != Some(true)
+        && batteries
+            .iter()
+            .any(|battery| battery.status == "discharging");
+    Some(PowerInfo {
+        ac_online,
+        batteries,
+        on_battery,
+    })
+}
+
+fn disk_kind(kind: DiskKind) -> &'static str {
+    match kind {
+        DiskKind::SSD => "ssd",
+        DiskKind::HDD => "hdd",
+        DiskKind::Unknown(_) => "unknown",
+    }
+}
+
+/// Current resources of the machine; `sample_cpu` measures CPU usage, which
+/// takes `MINIMUM_CPU_UPDATE_INTERVAL`
+pub fn system_info(sample_cpu: bool) -> Result<SystemInfo, CdeError> {
+    metrics::timed("get_system_info", || {
+        let mut system = System::new_with_specifics(
+            RefreshKind::nothing()
+                .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                .with_memory(MemoryRefreshKind::everything()),
+        );
+        let usage_pct = sample_cpu.then(|| {
+            // The first refresh only sets the baseline usage is measured against
+            thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+            system.refresh_cpu_usage();
+            system.global_cpu_usage()
+        });
+        let load = System::load_average();
+        let cpu = CpuInfo {
+            logical_cores: system.cpus().len().max(1),
+            physical_cores: system.physical_core_count(),
+            brand: system
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().trim().to_string())
+                .unwrap_or_default(),
+            usage_pct,
+            load_average: [load.one, load.five, load.fifteen],
+        };
+        let memory = MemoryInfo {
+            total_mb: system.total_memory() / MB,
+            available_mb: system.available_memory() / MB,
+            used_mb: system.used_memory() / MB,
+            swap_total_mb: system.total_swap() / MB,
+            swap_free_mb: system.free_swap() / MB,
+            cgroup_limit_mb: system
+                .cgroup_limits()
+                .map(|limits| limits.total_memory / MB)
+                .filter(|&limit| limit > 0 && limit < system.total_memory() / MB),
+        };
+
+        let mut disks: Vec<DiskInfo> = Disks::new_with_refreshed_list()
+            .list()
+            .iter()
+            .map(|disk| DiskInfo {
+                mount_point: disk.mount_point().to_string_lossy().replace('\\', "/"),
+                file_system: disk.file_system().to_string_lossy().into_owned(),
+                kind: disk_kind(disk.kind()).to_string(),
+                total_mb: disk.total_space() / MB,
+                available_mb: disk.available_space() / MB,
+                removable: disk.is_removable(),
+                read_only: disk.is_read_only(),
+            })
+            .collect();
+        disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+
+        let power = if cfg!(target_os = "linux") {
+            power_status(Path::new(POWER_SUPPLY_DIR))
+        } else {
+            None
+        };
+        Ok(SystemInfo {
+            os: std::env::consts::OS.to_string(),
+            os_version: System::long_os_version(),
+            kernel_version: System::kernel_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            hostname: System::host_name(),
+            uptime_secs: System::uptime(),
+            cpu,
+            memory,
+            disks,
+            power,
+        })
+    })
+}
+
+/// Snapshot of the machine's resources, for deciding how many agents to run locally
+///
+/// Returns `os`, `os_version`, `kernel_version`, `arch`, `hostname`,
+/// `uptime_secs`, `cpu` (`logical_cores`, `physical_cores`, `brand`,
+/// `usage_pct`, `load_average`), `memory` (`total_mb`, `available_mb`,
+/// `used_mb`, swap, `cgroup_limit_mb`), `disks` (per mount point: `total_mb`,
+/// `available_mb`, `kind`, `removable`, `read_only`) and `power` (`ac_online`,
+/// `batteries`, `on_battery`; Linux only, None elsewhere). With
+/// `sample_cpu=False` the call does not wait to measure CPU usage and
+/// `usage_pct` is None.
+#[pyfunction]
+#[pyo3(signature = (sample_cpu=true))]
+fn get_system_info_py(py: Python<'_>, sample_cpu: bool) -> PyResult<String> {
+    let info = py.detach(|| system_info(sample_cpu))?;
+    Ok(to_json(&info)?)
+}
+
+/// Adds the system information functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_system_info_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_info_snapshot() {
+        let info = system_info(true).unwrap();
+        assert_eq!(info.os, std::env::consts::OS);
+        assert!(info.cpu.logical_cores >= 1);
+        assert!((0.0..=100.0).contains(&info.cpu.usage_pct.unwrap()));
+        assert!(info.memory.total_mb > 0);
+        assert!(info.memory.available_mb <= info.memory.total_mb);
+        assert!(info
+            .disks
+            .iter()
+            .all(|disk| disk.available_mb <= disk.total_mb));
+        assert_eq!(system_info(false).unwrap().cpu.usage_pct, None);
+    }
+
+    #[test]
+    fn test_power_status_from_power_supply_class() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(power_status(dir.path()), None);
+
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            let path = dir.path().join(name);
+            fs::create_dir_all(&path).unwrap();
+            for (file, content) in files {
+                fs::write(path.join(file), format!("{}\n", content)).unwrap();
+            }
+        };
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("capacity", "57"),
+                ("status", "Discharging"),
+            ],
+        );
+        let power = power_status(dir.path()).unwrap();
+        assert_eq!(power.ac_online, Some(false));
+        assert_eq!(power.batteries[0].percent, Some(57.0));
+        assert_eq!(power.batteries[0].status, "discharging");
+        assert!(power.on_battery);
+
+        supply("AC", &[("online", "1")]);
+        assert!(!power_status(dir.path()).unwrap().on_battery);
+    }
+}
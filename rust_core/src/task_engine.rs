@@ -0,0 +1,1103 @@
+// src/task_engine.rs
+//! Local pipelines of shell-command tasks with dependencies
+//!
+//! A pipeline is a DAG: each task names the tasks that must succeed before it
+//! starts. Ready tasks run in dependency order, at most `max_parallel` at once,
+//! each in its own process group so a timeout or a cancellation kills its whole
+//! tree. A failed attempt is retried after `retry_delay_ms` while retries are
+//! left; once a task fails for good its dependents are skipped and, with
+//! `fail_fast`, nothing new is started. Files a successful task declares as
+//! `artifacts` go to the artifact store.
+//!
+//! Every change of a run is written to the state store under the project's
+//! namespace, so a run survives the server: one left "running" by a process
+//! that is gone reads as "interrupted" and can be resumed, which runs again
+//! every task that did not succeed.
+
+use crate::artifacts::{self, ArtifactStore};
+use crate::cancel::CancellationToken;
+use crate::error::{from_json, to_json, CdeError};
+use crate::events;
+use crate::path_policy;
+use crate::process_manager::{executable, tree};
+use crate::state::{self, StateStore};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Prefix of the state keys of the runs of a project
+pub const RUN_PREFIX: &str = "task_runs/";
+
+/// Lines of output kept per stream of an attempt
+const OUTPUT_TAIL_LINES: usize = 40;
+
+/// Interval at which running tasks and the cancellation token are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+fn default_true() -> bool {
+    true
+}
+
+/// `command` as a shell line or as argv
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskCommand {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl TaskCommand {
+    fn argv(&self) -> Vec<String> {
+        match self {
+            TaskCommand::Shell(line) if cfg!(windows) => {
+                vec!["cmd".to_string(), "/C".to_string(), line.clone()]
+            }
+            TaskCommand::Shell(line) => vec!["sh".to_string(), "-c".to_string(), line.clone()],
+            TaskCommand::Argv(argv) => argv.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskSpec {
+    pub id: String,
+    /// A line for the shell (`sh -c`, `cmd /C` on Windows) or argv
+    pub command: TaskCommand,
+    /// Tasks that must succeed first
+    #[serde(default)]
+    pub deps: Vec<String>,
+    /// Attempts allowed after the first one fails
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub retry_delay_ms: u64,
+    #[serde(default)]
+    pub timeout_secs: Option<f64>,
+    /// Working directory, relative to the root
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Files or globs, relative to the working directory, stored on success
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineSpec {
+    pub tasks: Vec<TaskSpec>,
+    /// Tasks run at once, the number of CPUs by default
+    #[serde(default)]
+    pub max_parallel: Option<usize>,
+    /// Whether a failed task stops new tasks from starting
+    #[serde(default = "default_true")]
+    pub fail_fast: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+impl TaskStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "pending",
+            TaskStatus::Running => "running",
+            TaskStatus::Succeeded => "succeeded",
+            TaskStatus::Failed => "failed",
+            TaskStatus::Skipped => "skipped",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskArtifact {
+    /// Relative to the task's working directory
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskState {
+    pub id: String,
+    pub status: TaskStatus,
+    pub attempts: u32,
+    pub exit_code: Option<i32>,
+    /// RFC 3339, of the last attempt
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<u64>,
+    /// Last lines of output of the last attempt
+    pub stdout_tail: Vec<String>,
+    pub stderr_tail: Vec<String>,
+    /// Why the last attempt failed besides its exit code: timeout, start failure
+    pub error: Option<String>,
+    pub artifacts: Vec<TaskArtifact>,
+}
+
+impl TaskState {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            status: TaskStatus::Pending,
+            attempts: 0,
+            exit_code: None,
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            stdout_tail: Vec::new(),
+            stderr_tail: Vec::new(),
+            error: None,
+            artifacts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// Left running by a process that is gone
+    Interrupted,
+}
+
+impl RunStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+            RunStatus::Cancelled => "cancelled",
+            RunStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub run_id: String,
+    pub root: String,
+    pub status: RunStatus,
+    /// Process executing the run
+    pub pid: u32,
+    /// RFC 3339
+    pub created_at: String,
+    pub updated_at: String,
+    pub spec: PipelineSpec,
+    /// In the order of `spec.tasks`
+    pub tasks: Vec<TaskState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskRunSummary {
+    pub run_id: String,
+    pub status: RunStatus,
+    pub created_at: String,
+    pub updated_at: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// Change of a task or of the whole run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub run_id: String,
+    /// None for the end of the run
+    pub task_id: Option<String>,
+    /// Status of the task ("retrying" between attempts) or of the run
+    pub status: String,
+    pub attempt: u32,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+/// Callback receiving each event of a run
+pub type EventCallback = Box<dyn Fn(&TaskEvent) + Send + Sync>;
+
+/// Cancellation tokens of the runs executing in this process
+static ACTIVE: LazyLock<Mutex<HashMap<String, CancellationToken>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Order in which the tasks of `spec` can run, dependencies first; rejects
+/// empty or duplicate ids, unknown dependencies and cycles
+pub fn validate(spec: &PipelineSpec) -> Result<Vec<usize>, CdeError> {
+    if spec.tasks.is_empty() {
+        return Err(CdeError::invalid_input("Pipeline has no tasks"));
+    }
+    if spec.max_parallel == Some(0) {
+        return Err(CdeError::invalid_input("max_parallel must be at least 1"));
+    }
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, task) in spec.tasks.iter().enumerate() {
+        if task.id.trim().is_empty() {
+            return Err(CdeError::invalid_input(format!(
+                "Task {} has an empty id",
+                i
+            )));
+        }
+        if task
+            .command
+            .argv()
+            .first()
+            .is_none_or(|program| program.trim().is_empty())
+        {
+            return Err(CdeError::invalid_input(format!(
+                "Task '{}' has an empty command",
+                task.id
+            )));
+        }
+        if task
+            .timeout_secs
+            .is_some_and(|secs| !secs.is_finite() || secs <= 0.0)
+        {
+            return Err(CdeError::invalid_input(format!(
+                "Task '{}' has an invalid timeout_secs (expected > 0)",
+                task.id
+            )));
+        }
+        if index.insert(task.id.as_str(), i).is_some() {
+            return Err(CdeError::invalid_input(format!(
+                "Duplicate task id: {}",
+                task.id
+            )));
+        }
+    }
+
+    let mut dependents = vec![Vec::new(); spec.tasks.len()];
+    let mut waiting = vec![0usize; spec.tasks.len()];
+    for (i, task) in spec.tasks.iter().enumerate() {
+        for dep in &task.deps {
+            let Some(&d) = index.get(dep.as_str()) else {
+                return Err(CdeError::invalid_input(format!(
+                    "Task '{}' depends on unknown task '{}'",
+                    task.id, dep
+                )));
+            };
+            dependents[d].push(i);
+            waiting[i] += 1;
+        }
+    }
+    let mut ready: VecDeque<usize> = (0..spec.tasks.len()).filter(|&i| waiting[i] == 0).collect();
+    let mut order = Vec::with_capacity(spec.tasks.len());
+    while let Some(i) = ready.pop_front() {
+        order.push(i);
+        for &dependent in &dependents[i] {
+            waiting[dependent] -= 1;
+            if waiting[dependent] == 0 {
+                ready.push_back(dependent);
+            }
+        }
+    }
+    if order.len() < spec.tasks.len() {
+        let cycle: Vec<&str> = spec
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| waiting[*i] > 0)
+            .map(|(_, task)| task.id.as_str())
+            .collect();
+        return Err(CdeError::invalid_input(format!(
+            "Dependency cycle between tasks: {}",
+            cycle.join(", ")
+        )));
+    }
+    Ok(order)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// New run of `spec` on the project at `root`, with every task pending
+pub fn new_run(root: &str, spec: PipelineSpec) -> TaskRun {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let created = chrono::Utc::now();
+    // Timestamped ids keep the state keys of a project in creation order
+    let run_id = format!(
+        "{}-{:04}",
+        created.format("%Y%m%d-%H%M%S%3f"),
+        COUNTER.fetch_add(1, Ordering::Relaxed) % 10_000
+    );
+    TaskRun {
+        run_id,
+        root: root.to_string(),
+        status: RunStatus::Running,
+        pid: std::process::id(),
+        created_at: created.to_rfc3339(),
+        updated_at: created.to_rfc3339(),
+        tasks: spec
+            .tasks
+            .iter()
+            .map(|task| TaskState::new(&task.id))
+            .collect(),
+        spec,
+    }
+}
+
+/// Outcome of one attempt of a task
+struct Attempt {
+    exit_code: Option<i32>,
+    error: Option<String>,
+    stdout_tail: Vec<String>,
+    stderr_tail: Vec<String>,
+    duration_ms: u64,
+    artifacts: Vec<TaskArtifact>,
+}
+
+impl Attempt {
+    fn failed(error: String, started: Instant) -> Self {
+        Self {
+            exit_code: None,
+            error: Some(error),
+            stdout_tail: Vec::new(),
+            stderr_tail: Vec::new(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            artifacts: Vec::new(),
+        }
+    }
+
+    fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+}
+
+/// Reads `pipe` to its end on a thread, keeping the last lines
+fn tail_reader(pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            if tail.len() == OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+            line.clear();
+        }
+        tail.into()
+    })
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Stores the `artifacts` of `task` found under `cwd`; a declared file that is
+/// missing fails the task
+fn collect_artifacts(
+    store: &ArtifactStore,
+    cwd: &Path,
+    run_id: &str,
+    task: &TaskSpec,
+) -> Result<Vec<TaskArtifact>, String> {
+    let mut collected = Vec::new();
+    for pattern in &task.artifacts {
+        let paths: Vec<PathBuf> = if is_glob(pattern) {
+            glob::glob(&cwd.join(pattern).to_string_lossy())
+                .map_err(|e| format!("Invalid artifact glob {}: {}", pattern, e))?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file())
+                .collect()
+        } else if cwd.join(pattern).is_file() {
+            vec![cwd.join(pattern)]
+        } else {
+            return Err(format!("Missing artifact: {}", pattern));
+        };
+        for path in paths {
+            let relative = path
+                .strip_prefix(cwd)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let content = fs::read(&path)
+                .map_err(|e| format!("Failed to read artifact {}: {}", relative, e))?;
+            let metadata =
+                serde_json::json!({ "run_id": run_id, "task": task.id, "path": relative });
+            let info = store
+                .put(&content, metadata)
+                .map_err(|e| format!("Failed to store artifact {}: {}", relative, e))?;
+            collected.push(TaskArtifact {
+                path: relative,
+                hash: info.hash,
+                size: info.size,
+            });
+        }
+    }
+    Ok(collected)
+}
+
+/// Runs one attempt of `task`, killing its process tree on timeout or cancellation
+fn run_attempt(
+    root: &Path,
+    run_id: &str,
+    task: &TaskSpec,
+    store: &ArtifactStore,
+    cancel: &CancellationToken,
+) -> Attempt {
+    let started = Instant::now();
+    let cwd = task
+        .cwd
+        .as_ref()
+        .map_or_else(|| root.to_path_buf(), |cwd| root.join(cwd));
+    let (program, args) = executable::resolve_command(&task.command.argv());
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .current_dir(&cwd)
+        .envs(&task.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Own process group so a timeout kills what the shell started too
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return Attempt::failed(format!("Failed to start: {}", e), started),
+    };
+    let stdout = child.stdout.take().map(tail_reader);
+    let stderr = child.stderr.take().map(tail_reader);
+
+    let timeout = task.timeout_secs.map(Duration::from_secs_f64);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => {
+                let timed_out = timeout.is_some_and(|timeout| started.elapsed() >= timeout);
+                if timed_out || cancel.is_cancelled() {
+                    tree::kill_tree(child.id());
+                    let _ = child.wait();
+                    break Err(if timed_out {
+                        format!("Timed out after {}s", task.timeout_secs.unwrap_or_default())
+                    } else {
+                        "Cancelled".to_string()
+                    });
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => break Err(format!("Failed to wait: {}", e)),
+        }
+    };
+    let join = |handle: Option<thread::JoinHandle<Vec<String>>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    let mut attempt = Attempt {
+        stdout_tail: join(stdout),
+        stderr_tail: join(stderr),
+        ..Attempt::failed(String::new(), started)
+    };
+    match status {
+        Ok(status) => {
+            attempt.exit_code = status.code();
+            attempt.error = None;
+        }
+        Err(error) => attempt.error = Some(error),
+    }
+    if attempt.succeeded() {
+        match collect_artifacts(store, &cwd, run_id, task) {
+            Ok(artifacts) => attempt.artifacts = artifacts,
+            Err(error) => attempt.error = Some(error),
+        }
+    }
+    attempt.duration_ms = started.elapsed().as_millis() as u64;
+    attempt
+}
+
+fn event(run: &TaskRun, i: usize, status: &str) -> TaskEvent {
+    let task = &run.tasks[i];
+    TaskEvent {
+        run_id: run.run_id.clone(),
+        task_id: Some(task.id.clone()),
+        status: status.to_string(),
+        attempt: task.attempts,
+        exit_code: task.exit_code,
+        error: task.error.clone(),
+    }
+}
+
+/// Runs the pending tasks of `run` to completion, reporting each change to
+/// `notify` and `persist`
+pub fn execute(
+    run: &mut TaskRun,
+    store: &ArtifactStore,
+    cancel: &CancellationToken,
+    notify: &dyn Fn(&TaskEvent),
+    persist: &dyn Fn(&TaskRun),
+) -> Result<(), CdeError> {
+    let notify = |event: &TaskEvent| {
+        events::publish("task_engine", &format!("task.{}", event.status), event);
+        notify(event);
+    };
+    let order = validate(&run.spec)?;
+    let root = PathBuf::from(&run.root);
+    let max_parallel = run.spec.max_parallel.unwrap_or_else(num_cpus::get).max(1);
+    let index: HashMap<&str, usize> = run
+        .spec
+        .tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| (task.id.as_str(), i))
+        .collect();
+    let deps: Vec<Vec<usize>> = run
+        .spec
+        .tasks
+        .iter()
+        .map(|task| task.deps.iter().map(|dep| index[dep.as_str()]).collect())
+        .collect();
+    let specs = run.spec.tasks.clone();
+    let mut retry_at: Vec<Option<Instant>> = vec![None; specs.len()];
+    let (sender, receiver) = mpsc::channel::<(usize, Attempt)>();
+    let mut running = 0;
+    run.status = RunStatus::Running;
+
+    thread::scope(|scope| loop {
+        let failed = run
+            .tasks
+            .iter()
+            .any(|task| task.status == TaskStatus::Failed);
+        let stopping = cancel.is_cancelled() || (run.spec.fail_fast && failed);
+        let mut changed = false;
+        for &i in &order {
+            if run.tasks[i].status != TaskStatus::Pending {
+                continue;
+            }
+            let blocked = deps[i].iter().any(|&d| {
+                matches!(
+                    run.tasks[d].status,
+                    TaskStatus::Failed | TaskStatus::Skipped | TaskStatus::Cancelled
+                )
+            });
+            if blocked || stopping {
+                run.tasks[i].status = if cancel.is_cancelled() {
+                    TaskStatus::Cancelled
+                } else {
+                    TaskStatus::Skipped
+                };
+                changed = true;
+                notify(&event(run, i, run.tasks[i].status.as_str()));
+                continue;
+            }
+            let ready = deps[i]
+                .iter()
+                .all(|&d| run.tasks[d].status == TaskStatus::Succeeded)
+                && retry_at[i].is_none_or(|at| Instant::now() >= at);
+            if !ready || running >= max_parallel {
+                continue;
+            }
+            let task = &mut run.tasks[i];
+            task.status = TaskStatus::Running;
+            task.attempts += 1;
+            task.started_at = Some(now());
+            task.finished_at = None;
+            task.error = None;
+            running += 1;
+            changed = true;
+            notify(&event(run, i, "running"));
+
+            let (sender, spec, root, run_id) =
+                (sender.clone(), &specs[i], &root, run.run_id.clone());
+            scope.spawn(move || {
+                let attempt = run_attempt(root, &run_id, spec, store, cancel);
+                let _ = sender.send((i, attempt));
+            });
+        }
+        if changed {
+            run.updated_at = now();
+            persist(run);
+        }
+        if running == 0
+            && run
+                .tasks
+                .iter()
+                .all(|task| task.status != TaskStatus::Pending)
+        {
+            break;
+        }
+
+        let Ok((i, attempt)) = receiver.recv_timeout(POLL_INTERVAL) else {
+            continue;
+        };
+        running -= 1;
+        let succeeded = attempt.succeeded();
+        let task = &mut run.tasks[i];
+        task.exit_code = attempt.exit_code;
+        task.error = attempt.error;
+        task.stdout_tail = attempt.stdout_tail;
+        task.stderr_tail = attempt.stderr_tail;
+        task.duration_ms = Some(attempt.duration_ms);
+        task.finished_at = Some(now());
+        task.artifacts = attempt.artifacts;
+        task.status = if succeeded {
+            TaskStatus::Succeeded
+        } else if cancel.is_cancelled() {
+            TaskStatus::Cancelled
+        } else if task.attempts <= specs[i].retries {
+            retry_at[i] = Some(Instant::now() + Duration::from_millis(specs[i].retry_delay_ms));
+            TaskStatus::Pending
+        } else {
+            TaskStatus::Failed
+        };
+        let status = match task.status {
+            TaskStatus::Pending => "retrying",
+            status => status.as_str(),
+        };
+        notify(&event(run, i, status));
+        run.updated_at = now();
+        persist(run);
+    });
+
+    let has = |status: TaskStatus| run.tasks.iter().any(|task| task.status == status);
+    run.status = if has(TaskStatus::Cancelled) {
+        RunStatus::Cancelled
+    } else if has(TaskStatus::Failed) || has(TaskStatus::Skipped) {
+        RunStatus::Failed
+    } else {
+        RunStatus::Succeeded
+    };
+    run.updated_at = now();
+    persist(run);
+    notify(&TaskEvent {
+        run_id: run.run_id.clone(),
+        task_id: None,
+        status: run.status.as_str().to_string(),
+        attempt: 0,
+        exit_code: None,
+        error: None,
+    });
+    Ok(())
+}
+
+fn run_key(run_id: &str) -> String {
+    format!("{}{}", RUN_PREFIX, run_id)
+}
+
+pub fn save_run(store: &StateStore, namespace: &str, run: &TaskRun) -> Result<(), CdeError> {
+    store.set(namespace, &run_key(&run.run_id), &to_json(run)?, None)
+}
+
+/// A run left "running" by a process that no longer executes it reads as interrupted
+fn with_liveness(mut run: TaskRun) -> TaskRun {
+    if run.status == RunStatus::Running && !ACTIVE.lock().unwrap().contains_key(&run.run_id) {
+        let own = run.pid == std::process::id();
+        if own || !tree::is_alive(run.pid) {
+            run.status = RunStatus::Interrupted;
+        }
+    }
+    run
+}
+
+pub fn load_run(
+    store: &StateStore,
+    namespace: &str,
+    run_id: &str,
+) -> Result<Option<TaskRun>, CdeError> {
+    store
+        .get(namespace, &run_key(run_id))?
+        .map(|json| from_json::<TaskRun>("task run", &json).map(with_liveness))
+        .transpose()
+}
+
+/// Runs of a project, newest first, at most `limit` of them
+pub fn list_runs(
+    store: &StateStore,
+    namespace: &str,
+    limit: Option<usize>,
+) -> Result<Vec<TaskRunSummary>, CdeError> {
+    let mut runs = Vec::new();
+    for entry in store.scan(namespace, RUN_PREFIX, None)?.iter().rev() {
+        if limit.is_some_and(|limit| runs.len() >= limit) {
+            break;
+        }
+        let run = with_liveness(from_json::<TaskRun>("task run", &entry.value)?);
+        let count = |status: TaskStatus| {
+            run.tasks
+                .iter()
+                .filter(|task| task.status == status)
+                .count()
+        };
+        runs.push(TaskRunSummary {
+            total: run.tasks.len(),
+            succeeded: count(TaskStatus::Succeeded),
+            failed: count(TaskStatus::Failed),
+            run_id: run.run_id,
+            status: run.status,
+            created_at: run.created_at,
+            updated_at: run.updated_at,
+        });
+    }
+    Ok(runs)
+}
+
+/// Sets `run` up to run again every task that did not succeed
+pub fn prepare_resume(run: &mut TaskRun) -> Result<(), CdeError> {
+    match run.status {
+        RunStatus::Running => {
+            return Err(CdeError::invalid_input(format!(
+                "Task run {} is still running (pid {})",
+                run.run_id, run.pid
+            )))
+        }
+        RunStatus::Succeeded => {
+            return Err(CdeError::invalid_input(format!(
+                "Task run {} already succeeded",
+                run.run_id
+            )))
+        }
+        RunStatus::Failed | RunStatus::Cancelled | RunStatus::Interrupted => {}
+    }
+    for task in &mut run.tasks {
+        if task.status != TaskStatus::Succeeded {
+            *task = TaskState::new(&task.id);
+        }
+    }
+    run.status = RunStatus::Running;
+    run.pid = std::process::id();
+    run.updated_at = now();
+    Ok(())
+}
+
+/// Writes `run` to the shared state store; a failure is only logged, the run goes on
+fn persist_shared(run: &TaskRun) {
+    let namespace = state::namespace(&run.root);
+    if let Err(e) = state::with_shared(|store| save_run(store, &namespace, run)) {
+        tracing::warn!(run_id = %run.run_id, error = %e, "Failed to persist task run");
+    }
+}
+
+/// Executes `run` on this thread (`wait`) or on a new one, returning its final
+/// state or its initial one
+fn launch(
+    run: TaskRun,
+    cancel: CancellationToken,
+    notify: EventCallback,
+    wait: bool,
+) -> Result<TaskRun, CdeError> {
+    validate(&run.spec)?;
+    {
+        let mut active = ACTIVE.lock().unwrap();
+        if active.contains_key(&run.run_id) {
+            return Err(CdeError::invalid_input(format!(
+                "Task run {} is already running",
+                run.run_id
+            )));
+        }
+        active.insert(run.run_id.clone(), cancel.clone());
+    }
+    persist_shared(&run);
+    let initial = run.clone();
+    let work = move || {
+        let mut run = run;
+        let result = execute(
+            &mut run,
+            &artifacts::shared(),
+            &cancel,
+            &*notify,
+            &persist_shared,
+        );
+        ACTIVE.lock().unwrap().remove(&run.run_id);
+        result.map(|_| run)
+    };
+    if wait {
+        work()
+    } else {
+        thread::spawn(work);
+        Ok(initial)
+    }
+}
+
+fn python_callback(callback: Option<Py<PyAny>>) -> EventCallback {
+    match callback {
+        Some(callback) => Box::new(move |event: &TaskEvent| {
+            let payload = serde_json::to_string(event).unwrap_or_default();
+            Python::attach(|py| {
+                if let Err(e) = callback.call1(py, (payload,)) {
+                    e.print(py);
+                }
+            });
+        }),
+        None => Box::new(|_: &TaskEvent| {}),
+    }
+}
+
+fn check_root(root: &str) -> Result<(), CdeError> {
+    path_policy::check(root)?;
+    if !Path::new(root).is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    Ok(())
+}
+
+/// Runs a DAG of shell-command tasks under `root`
+///
+/// `spec_json` has `tasks` (each with `id`, `command` as a shell line or argv,
+/// `deps`, `retries`, `retry_delay_ms`, `timeout_secs`, `cwd` relative to
+/// `root`, `env` and `artifacts`: files or globs stored in the artifact store
+/// on success), `max_parallel` (CPU count by default) and `fail_fast` (default
+/// true). `on_event` receives JSON events (`run_id`, `task_id`, `status`,
+/// `attempt`, `exit_code`, `error`) from the worker thread. The run is persisted
+/// in the state store as it progresses, see `get_task_run_py` and
+/// `resume_task_run_py`. Returns the run as JSON: its initial state, or its
+/// final one with `wait=True`.
+#[pyfunction]
+#[pyo3(signature = (root, spec_json, wait=false, on_event=None, cancel_token=None))]
+fn submit_tasks_py(
+    py: Python<'_>,
+    root: &str,
+    spec_json: &str,
+    wait: bool,
+    on_event: Option<Py<PyAny>>,
+    cancel_token: Option<CancellationToken>,
+) -> PyResult<String> {
+    let spec: PipelineSpec = from_json("pipeline spec", spec_json)?;
+    check_root(root)?;
+    validate(&spec)?;
+    let run = new_run(root, spec);
+    let notify = python_callback(on_event);
+    let cancel = cancel_token.unwrap_or_default();
+    let run = py.detach(|| launch(run, cancel, notify, wait))?;
+    Ok(to_json(&run)?)
+}
+
+/// Run `run_id` of the project at `root` as JSON, None if unknown; a run left
+/// "running" by a process that is gone has status "interrupted"
+#[pyfunction]
+fn get_task_run_py(py: Python<'_>, root: &str, run_id: &str) -> PyResult<Option<String>> {
+    let namespace = state::namespace(root);
+    let run = py.detach(|| state::with_shared(|store| load_run(store, &namespace, run_id)))?;
+    Ok(run.map(|run| to_json(&run)).transpose()?)
+}
+
+/// Task runs of the project at `root`, newest first, as a JSON list of
+/// `run_id`, `status`, `created_at`, `updated_at`, `total`, `succeeded` and
+/// `failed`
+#[pyfunction]
+#[pyo3(signature = (root, limit=None))]
+fn list_task_runs_py(py: Python<'_>, root: &str, limit: Option<usize>) -> PyResult<String> {
+    let namespace = state::namespace(root);
+    let runs = py.detach(|| state::with_shared(|store| list_runs(store, &namespace, limit)))?;
+    Ok(to_json(&runs)?)
+}
+
+/// Runs again every task of a failed, cancelled or interrupted run that did not
+/// succeed; same arguments and result as `submit_tasks_py`
+#[pyfunction]
+#[pyo3(signature = (root, run_id, wait=false, on_event=None, cancel_token=None))]
+fn resume_task_run_py(
+    py: Python<'_>,
+    root: &str,
+    run_id: &str,
+    wait: bool,
+    on_event: Option<Py<PyAny>>,
+    cancel_token: Option<CancellationToken>,
+) -> PyResult<String> {
+    check_root(root)?;
+    let namespace = state::namespace(root);
+    let notify = python_callback(on_event);
+    let cancel = cancel_token.unwrap_or_default();
+    let run = py.detach(|| {
+        let mut run = state::with_shared(|store| load_run(store, &namespace, run_id))?
+            .ok_or_else(|| CdeError::not_found(format!("Unknown task run: {}", run_id)))?;
+        prepare_resume(&mut run)?;
+        launch(run, cancel, notify, wait)
+    })?;
+    Ok(to_json(&run)?)
+}
+
+/// Cancels run `run_id` executing in this process: running tasks are killed and
+/// pending ones cancelled. Returns whether it was running here.
+#[pyfunction]
+fn cancel_task_run_py(run_id: &str) -> bool {
+    match ACTIVE.lock().unwrap().get(run_id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Adds the task engine functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(submit_tasks_py, m)?)?;
+    m.add_function(wrap_pyfunction!(get_task_run_py, m)?)?;
+    m.add_function(wrap_pyfunction!(list_task_runs_py, m)?)?;
+    m.add_function(wrap_pyfunction!(resume_task_run_py, m)?)?;
+    m.add_function(wrap_pyfunction!(cancel_task_run_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(value: serde_json::Value) -> PipelineSpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_orders_and_rejects_bad_graphs() {
+        let order = validate(&spec(json!({ "tasks": [
+            { "id": "test", "command": "t", "deps": ["build"] },
+            { "id": "build", "command": ["b"] },
+            { "id": "lint", "command": "l" },
+        ]})))
+        .unwrap();
+        assert_eq!(order, [1, 2, 0]);
+
+        let error = |tasks: serde_json::Value| {
+            validate(&spec(json!({ "tasks": tasks })))
+                .unwrap_err()
+                .context()
+                .message
+                .clone()
+        };
+        assert_eq!(
+            error(json!([{ "id": "a", "command": "x", "deps": ["b"] },
+                         { "id": "b", "command": "x", "deps": ["a"] },
+                         { "id": "c", "command": "x" }])),
+            "Dependency cycle between tasks: a, b"
+        );
+        assert_eq!(
+            error(json!([{ "id": "a", "command": "x", "deps": ["zzz"] }])),
+            "Task 'a' depends on unknown task 'zzz'"
+        );
+        assert_eq!(
+            error(json!([{ "id": "a", "command": "x" }, { "id": "a", "command": "y" }])),
+            "Duplicate task id: a"
+        );
+        assert_eq!(
+            error(json!([{ "id": "a", "command": [] }])),
+            "Task 'a' has an empty command"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_runs_dag_with_retries_artifacts_and_timeouts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        let store = ArtifactStore::new(dir.path().join("artifacts"));
+        let mut run = new_run(
+            root.to_str().unwrap(),
+            spec(json!({ "fail_fast": false, "max_parallel": 2, "tasks": [
+                { "id": "build", "command": "echo built > out.txt; echo done", "artifacts": ["out.txt"] },
+                { "id": "flaky", "deps": ["build"], "retries": 1,
+                  "command": "test -f marker || { touch marker; echo first >&2; exit 1; }" },
+                { "id": "check", "deps": ["flaky"], "command": ["sh", "-c", "exit 3"] },
+                { "id": "report", "deps": ["check"], "command": "true" },
+                { "id": "slow", "command": "sleep 5", "timeout_secs": 0.2 },
+            ]})),
+        );
+
+        let events = Mutex::new(Vec::new());
+        let saved = Mutex::new(0);
+        execute(
+            &mut run,
+            &store,
+            &CancellationToken::default(),
+            &|event| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push((event.task_id.clone(), event.status.clone()))
+            },
+            &|_| *saved.lock().unwrap() += 1,
+        )
+        .unwrap();
+
+        let task = |id: &str| run.tasks.iter().find(|task| task.id == id).unwrap();
+        assert_eq!(task("build").status, TaskStatus::Succeeded);
+        assert_eq!(task("build").stdout_tail, ["done"]);
+        let artifact = &task("build").artifacts[0];
+        assert_eq!((artifact.path.as_str(), artifact.size), ("out.txt", 6));
+        assert_eq!(store.get(&artifact.hash).unwrap().unwrap().0, b"built\n");
+
+        assert_eq!(task("flaky").status, TaskStatus::Succeeded);
+        assert_eq!(task("flaky").attempts, 2);
+        assert_eq!(task("check").status, TaskStatus::Failed);
+        assert_eq!(task("check").exit_code, Some(3));
+        assert_eq!(task("report").status, TaskStatus::Skipped);
+        assert_eq!(task("slow").status, TaskStatus::Failed);
+        assert!(task("slow")
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Timed out"));
+        assert!(task("slow").duration_ms.unwrap() < 3000);
+        assert_eq!(run.status, RunStatus::Failed);
+
+        let events = events.into_inner().unwrap();
+        assert!(events.contains(&(Some("flaky".to_string()), "retrying".to_string())));
+        assert_eq!(events.last().unwrap(), &(None, "failed".to_string()));
+        assert!(saved.into_inner().unwrap() > 5);
+    }
+
+    #[test]
+    fn test_runs_persist_and_resume_after_interruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let mut run = new_run(
+            "/project",
+            spec(json!({ "tasks": [
+                { "id": "a", "command": "x" },
+                { "id": "b", "command": "y", "deps": ["a"] },
+            ]})),
+        );
+        run.tasks[0].status = TaskStatus::Succeeded;
+        run.tasks[1].status = TaskStatus::Running;
+        run.tasks[1].attempts = 1;
+        save_run(&store, "ns", &run).unwrap();
+
+        // Left running by this process without executing here: interrupted
+        let mut loaded = load_run(&store, "ns", &run.run_id).unwrap().unwrap();
+        assert_eq!(loaded.status, RunStatus::Interrupted);
+        let summaries = list_runs(&store, "ns", None).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!((summaries[0].total, summaries[0].succeeded), (2, 1));
+        assert!(load_run(&store, "ns", "unknown").unwrap().is_none());
+
+        prepare_resume(&mut loaded).unwrap();
+        assert_eq!(loaded.tasks[0].status, TaskStatus::Succeeded);
+        assert_eq!(loaded.tasks[1].status, TaskStatus::Pending);
+        assert_eq!(loaded.tasks[1].attempts, 0);
+        assert!(prepare_resume(&mut loaded).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// src/templating.rs
+//! Rendering of workflow prompt templates
+//!
+//! Templates use the Jinja syntax (minijinja): `{% include %}` and
+//! `{% extends %}` resolve relative to the directory of the rendered template,
+//! and the built-in filters are available, `tojson` included. In strict mode,
+//! the default, using a variable missing from the context is an error instead of
+//! an empty string. Errors point at the template, line and column that failed.
+
+use crate::error::{from_json, CdeError};
+use crate::path_policy;
+use minijinja::{path_loader, Environment, ErrorKind, UndefinedBehavior};
+use pyo3::prelude::*;
+use std::path::Path;
+
+/// Line and column (1-based) of the span `error` points at
+fn location(error: &minijinja::Error) -> Option<(usize, usize)> {
+    let line = error.line()?;
+    let column = error
+        .range()
+        .zip(error.template_source())
+        .and_then(|(range, source)| {
+            let before = source.get(..range.start)?;
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Some(before[line_start..].chars().count() + 1)
+        })
+        .unwrap_or(1);
+    Some((line, column))
+}
+
+/// Error raised by the template that failed; failures in an included template
+/// come wrapped in the error of the include
+fn innermost(error: &minijinja::Error) -> &minijinja::Error {
+    let mut inner = error;
+    while let Some(source) = std::error::Error::source(inner)
+        .and_then(|source| source.downcast_ref::<minijinja::Error>())
+    {
+        inner = source;
+    }
+    inner
+}
+
+fn render_error(dir: &Path, error: minijinja::Error) -> CdeError {
+    let failed = innermost(&error);
+    let detail = failed
+        .detail()
+        .map_or_else(|| failed.kind().to_string(), str::to_string);
+    let location = location(failed);
+    let message = match location {
+        Some((_, column)) => format!("Failed to render template: {} (column {})", detail, column),
+        None => format!("Failed to render template: {}", detail),
+    };
+    let mut cde_error = match failed.kind() {
+        ErrorKind::TemplateNotFound => CdeError::not_found(message),
+        ErrorKind::UndefinedError => CdeError::invalid_input(message),
+        _ => CdeError::parse(message),
+    };
+    if let Some(name) = failed.name() {
+        cde_error = cde_error.with_path(dir.join(name));
+    }
+    if let Some((line, _)) = location {
+        cde_error = cde_error.with_line(line);
+    }
+    cde_error.caused_by(&error)
+}
+
+/// Renders the template at `template_path` with the JSON object `context_json`
+pub fn render_template(
+    template_path: &str,
+    context_json: &str,
+    strict: bool,
+) -> Result<String, CdeError> {
+    let path = Path::new(template_path);
+    path_policy::check(path)?;
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Err(CdeError::invalid_input("Not a template file").with_path(path));
+    };
+    let context: serde_json::Value = from_json("context", context_json)?;
+    if !context.is_object() && !context.is_null() {
+        return Err(CdeError::invalid_input(
+            "Template context must be a JSON object",
+        ));
+    }
+    render(dir, name, None, &context, strict)
+}
+
+/// Renders the template `source` (named `name` in errors), whose includes
+/// resolve relative to `dir`
+pub(crate) fn render_source(
+    dir: &Path,
+    name: &str,
+    source: &str,
+    context: &serde_json::Value,
+) -> Result<String, CdeError> {
+    render(dir, name, Some(source), context, true)
+}
+
+/// Renders the template `name` of `dir`, or `source` under that name
+fn render(
+    dir: &Path,
+    name: &str,
+    source: Option<&str>,
+    context: &serde_json::Value,
+    strict: bool,
+) -> Result<String, CdeError> {
+    let mut env = Environment::new();
+    env.set_loader(path_loader(dir));
+    if strict {
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+    }
+    if let Some(source) = source {
+        env.add_template_owned(name.to_string(), source.to_string())
+            .map_err(|e| render_error(dir, e))?;
+    }
+    env.get_template(name)
+        .and_then(|template| template.render(context))
+        .map_err(|e| render_error(dir, e))
+}
+
+/// Renders the Jinja template at `template_path` with the variables of the JSON
+/// object `context_json`. Includes resolve relative to the template's directory.
+/// With `strict` (the default), undefined variables raise `CdeInvalidInputError`;
+/// syntax errors raise `CdeParseError`, missing templates `CdeNotFoundError`, all
+/// with the failing template as `path`, its `line`, and the column in the message.
+#[pyfunction]
+#[pyo3(signature = (template_path, context_json="{}", strict=true))]
+fn render_template_py(
+    py: Python<'_>,
+    template_path: &str,
+    context_json: &str,
+    strict: bool,
+) -> PyResult<String> {
+    Ok(py.detach(|| render_template(template_path, context_json, strict))?)
+}
+
+/// Adds the templating functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(render_template_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_render_with_includes_filters_and_strict_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("partials")).unwrap();
+        fs::write(
+            dir.path().join("partials").join("header.md"),
+            "# {{ phase }}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("prompt.md"),
+            "{% include 'partials/header.md' %}\n\
+             {% for file in files %}- {{ file | upper }}\n{% endfor %}\
+             {{ files | tojson }}",
+        )
+        .unwrap();
+        let prompt = dir.path().join("prompt.md");
+        let prompt = prompt.to_str().unwrap();
+
+        let rendered = render_template(
+            prompt,
+            r#"{"phase": "define", "files": ["a.rs", "b.rs"]}"#,
+            true,
+        )
+        .unwrap();
+        assert_eq!(rendered, "# define\n- A.RS\n- B.RS\n[\"a.rs\",\"b.rs\"]");
+
+        // The missing variable is in the included template
+        let error = render_template(prompt, r#"{"files": []}"#, true).unwrap_err();
+        assert_eq!(error.kind(), "invalid_input");
+        let context = error.context();
+        assert!(context
+            .path
+            .as_deref()
+            .unwrap()
+            .ends_with("partials/header.md"));
+        assert_eq!(context.line, Some(1));
+        assert!(
+            context.message.contains("(column 6)"),
+            "{}",
+            context.message
+        );
+        assert_eq!(
+            render_template(prompt, r#"{"files": []}"#, false).unwrap(),
+            "# \n[]"
+        );
+
+        fs::write(dir.path().join("broken.md"), "ok\n{% if %}").unwrap();
+        let broken = dir.path().join("broken.md");
+        let error = render_template(broken.to_str().unwrap(), "{}", true).unwrap_err();
+        assert_eq!((error.kind(), error.context().line), ("parse", Some(2)));
+
+        let missing = dir.path().join("missing.md");
+        let error = render_template(missing.to_str().unwrap(), "{}", true).unwrap_err();
+        assert_eq!(error.kind(), "not_found");
+        assert!(render_template(prompt, "[1]", true).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// rust_core/src/test_detection.rs
+//! Language-aware test file detection and test-to-code ratios
+//!
+//! Classifies source files by ecosystem conventions (`tests/` directories,
+//! `*_test.go`, `test_*.py`, `*.spec.ts`, `*Test.java`, ...) so the orchestrator
+//! can decide whether to generate tests before touching code.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Test and code file counts for one language or directory
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TestRatio {
+    pub test_files: usize,
+    pub code_files: usize,
+    /// `test_files / code_files`, 0.0 when there is no non-test code
+    pub ratio: f64,
+}
+
+/// Test coverage of the file tree, by language and by top-level directory
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TestStats {
+    pub test_files: usize,
+    pub code_files: usize,
+    pub ratio: f64,
+    pub by_language: HashMap<String, TestRatio>,
+    pub by_directory: HashMap<String, TestRatio>,
+}
+
+/// Directory names that hold tests in most ecosystems
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec"];
+
+/// Maps a file extension to the language name used in reports
+pub fn language_for_extension(ext: &str) -> Option<&'static str> {
+    let language = match ext.to_ascii_lowercase().as_str() {
+        "py" => "python",
+        "rs" => "rust",
+        "go" => "go",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "cs" => "csharp",
+        "php" => "php",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Returns true when `relative_path` follows a test naming convention of its language
+pub fn is_test_file(relative_path: &Path) -> bool {
+    let Some(file_name) = relative_path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let Some(language) = relative_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(language_for_extension)
+    else {
+        return false;
+    };
+
+    let in_test_dir = relative_path.parent().is_some_and(|parent| {
+        parent.components().any(|c| match c {
+            Component::Normal(name) => name
+                .to_str()
+                .is_some_and(|n| TEST_DIRS.contains(&n.to_ascii_lowercase().as_str())),
+            _ => false,
+        })
+    });
+    if in_test_dir {
+        return true;
+    }
+
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    match language {
+        "python" => stem.starts_with("test_") || stem.ends_with("_test") || stem == "conftest",
+        "go" => stem.ends_with("_test"),
+        "typescript" | "javascript" => {
+            file_name.contains(".test.") || file_name.contains(".spec.")
+        }
+        "java" | "kotlin" | "csharp" | "swift" | "php" => {
+            stem.ends_with("Test") || stem.ends_with("Tests") || stem.ends_with("Spec")
+        }
+        "ruby" => stem.ends_with("_spec") || stem.ends_with("_test"),
+        "rust" | "c" | "cpp" => stem.ends_with("_test") || stem.starts_with("test_"),
+        _ => false,
+    }
+}
+
+/// Computes test-to-code ratios for files found under `root`
+pub fn compute_test_stats(root: &Path, file_paths: &[PathBuf]) -> TestStats {
+    let mut stats = TestStats::default();
+
+    for path in file_paths {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let Some(language) = relative
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(language_for_extension)
+        else {
+            continue;
+        };
+
+        let directory = match relative.components().next() {
+            Some(Component::Normal(first)) if relative.components().count() > 1 => {
+                first.to_string_lossy().into_owned()
+            }
+            _ => ".".to_string(),
+        };
+
+        let is_test = is_test_file(relative);
+        for entry in [
+            stats.by_language.entry(language.to_string()).or_default(),
+            stats.by_directory.entry(directory).or_default(),
+        ] {
+            if is_test {
+                entry.test_files += 1;
+            } else {
+                entry.code_files += 1;
+            }
+        }
+
+        if is_test {
+            stats.test_files += 1;
+        } else {
+            stats.code_files += 1;
+        }
+    }
+
+    stats.ratio = ratio(stats.test_files, stats.code_files);
+    for entry in stats.by_language.values_mut().chain(stats.by_directory.values_mut()) {
+        entry.ratio = ratio(entry.test_files, entry.code_files);
+    }
+
+    stats
+}
+
+fn ratio(test_files: usize, code_files: usize) -> f64 {
+    if code_files == 0 {
+        0.0
+    } else {
+        test_files as f64 / code_files as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_test_file_conventions() {
+        assert!(is_test_file(Path::new("pkg/server_test.go")));
+        assert!(is_test_file(Path::new("src/test_utils.py")));
+        assert!(is_test_file(Path::new("web/app.spec.ts")));
+        assert!(is_test_file(Path::new("web/button.test.jsx")));
+        assert!(is_test_file(Path::new("src/test/java/FooTest.java")));
+        assert!(is_test_file(Path::new("tests/integration.rs")));
+        assert!(!is_test_file(Path::new("src/main.rs")));
+        assert!(!is_test_file(Path::new("src/testing_helpers.md")));
+        assert!(!is_test_file(Path::new("src/contest.py")));
+    }
+
+    #[test]
+    fn test_compute_test_stats() {
+        let root = Path::new("/repo");
+        let files: Vec<PathBuf> = [
+            "/repo/src/app.py",
+            "/repo/src/models.py",
+            "/repo/tests/test_app.py",
+            "/repo/web/index.ts",
+            "/repo/web/index.spec.ts",
+            "/repo/README.md",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        let stats = compute_test_stats(root, &files);
+        assert_eq!(stats.test_files, 2);
+        assert_eq!(stats.code_files, 3);
+        assert_eq!(stats.by_language["python"].ratio, 0.5);
+        assert_eq!(stats.by_language["typescript"].ratio, 1.0);
+        assert_eq!(stats.by_directory["tests"].code_files, 0);
+        assert!(!stats.by_directory.contains_key("."));
+    }
+}
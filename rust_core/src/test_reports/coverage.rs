@@ -0,0 +1,601 @@
+// rust_core/src/test_reports/coverage.rs
+//! Coverage reports: LCOV, Cobertura XML, coverage.py JSON and Istanbul JSON
+//!
+//! Every format is read into the lines (with their hits) and the branches of
+//! each file, so files measured twice, such as an LCOV record per test name or
+//! a Cobertura class per inner class, are merged line by line rather than added
+//! up. Paths are made relative to the project root.
+
+use super::{artifact_name, relative_path, xml_document};
+use crate::error::CdeError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageStats {
+    pub lines_total: usize,
+    pub lines_covered: usize,
+    /// 100 when there are no lines
+    pub line_percent: f64,
+    pub branches_total: usize,
+    pub branches_covered: usize,
+    /// None when no branch was measured
+    pub branch_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCoverage {
+    pub path: String,
+    #[serde(flatten)]
+    pub stats: CoverageStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryCoverage {
+    /// "." for the project root
+    pub path: String,
+    pub files: usize,
+    #[serde(flatten)]
+    pub stats: CoverageStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDelta {
+    pub path: String,
+    /// Line percent in the baseline, None for a file it did not measure
+    pub before: Option<f64>,
+    /// Line percent now, None for a file no longer measured
+    pub after: Option<f64>,
+    pub change: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageDelta {
+    pub baseline_timestamp: String,
+    pub baseline_git_sha: Option<String>,
+    pub line_percent: f64,
+    pub branch_percent: Option<f64>,
+    /// Files whose line percent changed, largest drop first
+    pub files: Vec<FileDelta>,
+}
+
+/// Coverage stored to compare later runs with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageBaseline {
+    /// RFC 3339
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    pub totals: CoverageStats,
+    pub files: Vec<FileCoverage>,
+}
+
+/// Hits of the lines and branches of one file
+#[derive(Debug, Default)]
+pub(crate) struct FileHits {
+    lines: BTreeMap<usize, u64>,
+    /// Branches (total, covered) by line
+    branches: BTreeMap<usize, (usize, usize)>,
+}
+
+impl FileHits {
+    fn line(&mut self, line: usize, hits: u64) {
+        let known = self.lines.entry(line).or_default();
+        *known = (*known).max(hits);
+    }
+
+    fn branches(&mut self, line: usize, total: usize, covered: usize) {
+        let known = self.branches.entry(line).or_default();
+        *known = (known.0.max(total), known.1.max(covered));
+    }
+
+    pub(crate) fn merge(&mut self, other: FileHits) {
+        for (line, hits) in other.lines {
+            self.line(line, hits);
+        }
+        for (line, (total, covered)) in other.branches {
+            self.branches(line, total, covered);
+        }
+    }
+
+    fn stats(&self) -> CoverageStats {
+        let (branches_total, branches_covered) = self
+            .branches
+            .values()
+            .fold((0, 0), |(t, c), (total, covered)| (t + total, c + covered));
+        CoverageStats::new(
+            self.lines.len(),
+            self.lines.values().filter(|hits| **hits > 0).count(),
+            branches_total,
+            branches_covered,
+        )
+    }
+}
+
+/// Files of one report, by the path it gives
+pub(crate) type Hits = BTreeMap<String, FileHits>;
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    (part as f64 * 10000.0 / total as f64).round() / 100.0
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+impl CoverageStats {
+    pub fn new(
+        lines_total: usize,
+        lines_covered: usize,
+        branches_total: usize,
+        branches_covered: usize,
+    ) -> Self {
+        Self {
+            lines_total,
+            lines_covered,
+            line_percent: percent(lines_covered, lines_total),
+            branches_total,
+            branches_covered,
+            branch_percent: (branches_total > 0).then(|| percent(branches_covered, branches_total)),
+        }
+    }
+
+    fn add(&self, other: &CoverageStats) -> Self {
+        Self::new(
+            self.lines_total + other.lines_total,
+            self.lines_covered + other.lines_covered,
+            self.branches_total + other.branches_total,
+            self.branches_covered + other.branches_covered,
+        )
+    }
+}
+
+/// LCOV tracefile: `SF:`, `DA:line,hits` and `BRDA:line,block,branch,taken`
+/// records ended by `end_of_record`
+fn parse_lcov(content: &str, hits: &mut Hits) {
+    let mut current: Option<(String, FileHits)> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        let (tag, value) = line.split_once(':').unwrap_or((line, ""));
+        match (tag, &mut current) {
+            ("SF", _) => current = Some((value.to_string(), FileHits::default())),
+            ("DA", Some((_, file))) => {
+                let mut fields = value.split(',');
+                let number = fields.next().and_then(|n| n.parse().ok());
+                let count = fields.next().and_then(|n| n.trim().parse::<f64>().ok());
+                if let (Some(number), Some(count)) = (number, count) {
+                    file.line(number, count.max(0.0) as u64);
+                }
+            }
+            ("BRDA", Some((_, file))) => {
+                let fields: Vec<&str> = value.split(',').collect();
+                if let [number, _, _, taken] = fields[..] {
+                    if let Ok(number) = number.parse() {
+                        // "-" when the line never ran
+                        let taken = taken.parse::<u64>().is_ok_and(|t| t > 0);
+                        let known = file.branches.entry(number).or_default();
+                        *known = (known.0 + 1, known.1 + usize::from(taken));
+                    }
+                }
+            }
+            ("end_of_record", Some(_)) => {
+                if let Some((path, file)) = current.take() {
+                    hits.entry(path).or_default().merge(file);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((path, file)) = current {
+        hits.entry(path).or_default().merge(file);
+    }
+}
+
+/// Cobertura XML (coverage.py, gcovr, Istanbul, JaCoCo converters): `<class
+/// filename>` elements with `<line number hits condition-coverage>`, file names
+/// relative to one of the `<source>` directories
+fn parse_cobertura(content: &str, artifact: &Path, hits: &mut Hits) -> Result<(), CdeError> {
+    let document = xml_document(content, artifact)?;
+    let coverage = document.root_element();
+    if !coverage.has_tag_name("coverage") {
+        return Err(CdeError::parse("Not a Cobertura coverage report").with_path(artifact));
+    }
+    let sources: Vec<&str> = coverage
+        .descendants()
+        .filter(|node| node.has_tag_name("source"))
+        .filter_map(|node| node.text())
+        .map(str::trim)
+        .collect();
+    for class in coverage
+        .descendants()
+        .filter(|node| node.has_tag_name("class"))
+    {
+        let Some(filename) = class.attribute("filename") else {
+            continue;
+        };
+        // The first source the file exists in, else the name as given
+        let path = sources
+            .iter()
+            .map(|source| Path::new(source).join(filename))
+            .find(|path| path.is_file())
+            .map_or_else(
+                || filename.to_string(),
+                |path| path.to_string_lossy().into_owned(),
+            );
+        let file = hits.entry(path).or_default();
+        for line in class.descendants().filter(|node| node.has_tag_name("line")) {
+            let Some(number) = line.attribute("number").and_then(|n| n.parse().ok()) else {
+                continue;
+            };
+            let count = line
+                .attribute("hits")
+                .and_then(|h| h.parse::<f64>().ok())
+                .unwrap_or(0.0);
+            file.line(number, count.max(0.0) as u64);
+            // "50% (1/2)"
+            let conditions = line
+                .attribute("condition-coverage")
+                .and_then(|c| c.split_once('('))
+                .and_then(|(_, rest)| rest.trim_end_matches(')').split_once('/'));
+            if let Some((covered, total)) = conditions {
+                if let (Ok(covered), Ok(total)) = (covered.parse(), total.parse()) {
+                    file.branches(number, total, covered);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn line_numbers(value: Option<&Value>) -> impl Iterator<Item = usize> + '_ {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|n| n.as_u64().map(|n| n as usize))
+}
+
+/// coverage.py JSON: `files` with `executed_lines`, `missing_lines` and, with
+/// branch coverage, `executed_branches` and `missing_branches` as
+/// `[from, to]` pairs
+fn parse_coverage_py(report: &Value, hits: &mut Hits) {
+    let Some(files) = report.get("files").and_then(Value::as_object) else {
+        return;
+    };
+    for (path, data) in files {
+        let file = hits.entry(path.clone()).or_default();
+        for line in line_numbers(data.get("executed_lines")) {
+            file.line(line, 1);
+        }
+        for line in line_numbers(data.get("missing_lines")) {
+            file.line(line, 0);
+        }
+        let mut branches: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+        for (key, taken) in [("executed_branches", 1), ("missing_branches", 0)] {
+            for arc in data
+                .get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(from) = arc.get(0).and_then(Value::as_i64) {
+                    let known = branches.entry(from.unsigned_abs() as usize).or_default();
+                    *known = (known.0 + 1, known.1 + taken);
+                }
+            }
+        }
+        for (line, (total, covered)) in branches {
+            file.branches(line, total, covered);
+        }
+    }
+}
+
+/// Istanbul `coverage-final.json`: per file, `statementMap` and `s` (hits per
+/// statement), `branchMap` and `b` (hits per branch path)
+fn parse_istanbul(report: &Value, hits: &mut Hits) {
+    let Some(files) = report.as_object() else {
+        return;
+    };
+    for (path, data) in files {
+        let file = hits.entry(path.clone()).or_default();
+        let start_line = |location: &Value| {
+            location
+                .pointer("/start/line")
+                .or_else(|| location.get("line"))
+                .and_then(Value::as_u64)
+                .map(|line| line as usize)
+        };
+        if let (Some(statements), Some(counts)) = (
+            data.get("statementMap").and_then(Value::as_object),
+            data.get("s").and_then(Value::as_object),
+        ) {
+            for (id, location) in statements {
+                let count = counts.get(id).and_then(Value::as_u64).unwrap_or(0);
+                if let Some(line) = start_line(location) {
+                    file.line(line, count);
+                }
+            }
+        }
+        if let (Some(branch_map), Some(counts)) = (
+            data.get("branchMap").and_then(Value::as_object),
+            data.get("b").and_then(Value::as_object),
+        ) {
+            for (id, branch) in branch_map {
+                let paths: Vec<u64> = counts
+                    .get(id)
+                    .and_then(Value::as_array)
+                    .map(|paths| paths.iter().filter_map(Value::as_u64).collect())
+                    .unwrap_or_default();
+                let line = branch
+                    .get("loc")
+                    .and_then(start_line)
+                    .or_else(|| start_line(branch));
+                if let Some(line) = line {
+                    let covered = paths.iter().filter(|count| **count > 0).count();
+                    let known = file.branches.entry(line).or_default();
+                    *known = (known.0 + paths.len(), known.1 + covered);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the coverage report `content` of the file `artifact` into `hits`;
+/// returns the name of its format
+pub(crate) fn parse_report(
+    content: &str,
+    artifact: &Path,
+    hits: &mut Hits,
+) -> Result<&'static str, CdeError> {
+    let name = artifact_name(artifact);
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    if name.ends_with(".xml") || trimmed.starts_with('<') {
+        parse_cobertura(content, artifact, hits)?;
+        return Ok("cobertura");
+    }
+    if name.ends_with(".json") || trimmed.starts_with('{') {
+        let report: Value = serde_json::from_str(trimmed).map_err(|e| {
+            CdeError::parse("Invalid JSON coverage report")
+                .with_path(artifact)
+                .caused_by(&e)
+        })?;
+        if report.get("files").is_some_and(Value::is_object) {
+            parse_coverage_py(&report, hits);
+            return Ok("coverage.py");
+        }
+        let istanbul = report.as_object().is_some_and(|files| {
+            files
+                .values()
+                .any(|file| file.get("statementMap").is_some())
+        });
+        if istanbul {
+            parse_istanbul(&report, hits);
+            return Ok("istanbul");
+        }
+        return Err(CdeError::parse("Unrecognized JSON coverage report").with_path(artifact));
+    }
+    if trimmed.starts_with("TN:") || trimmed.starts_with("SF:") || name.ends_with(".info") {
+        parse_lcov(content, hits);
+        return Ok("lcov");
+    }
+    Err(CdeError::parse("Unrecognized coverage report").with_path(artifact))
+}
+
+/// Coverage of the files of `hits`, keyed by their path relative to `root`,
+/// sorted by path
+pub(crate) fn file_coverage(root: &Path, hits: Hits) -> Vec<FileCoverage> {
+    let mut merged: BTreeMap<String, FileHits> = BTreeMap::new();
+    for (path, file) in hits {
+        merged
+            .entry(relative_path(root, &path))
+            .or_default()
+            .merge(file);
+    }
+    merged
+        .into_iter()
+        .map(|(path, file)| FileCoverage {
+            stats: file.stats(),
+            path,
+        })
+        .collect()
+}
+
+/// Coverage of the directories directly holding `files`, sorted by path
+pub fn directory_coverage(files: &[FileCoverage]) -> Vec<DirectoryCoverage> {
+    let mut directories: BTreeMap<String, DirectoryCoverage> = BTreeMap::new();
+    for file in files {
+        let parent = match file.path.rsplit_once('/') {
+            Some((parent, _)) => parent.to_string(),
+            None => ".".to_string(),
+        };
+        let directory = directories
+            .entry(parent.clone())
+            .or_insert_with(|| DirectoryCoverage {
+                path: parent,
+                files: 0,
+                stats: CoverageStats::new(0, 0, 0, 0),
+            });
+        directory.files += 1;
+        directory.stats = directory.stats.add(&file.stats);
+    }
+    directories.into_values().collect()
+}
+
+/// Sum of the coverage of `files`
+pub fn totals(files: &[FileCoverage]) -> CoverageStats {
+    files
+        .iter()
+        .fold(CoverageStats::new(0, 0, 0, 0), |totals, file| {
+            totals.add(&file.stats)
+        })
+}
+
+/// Changes from `baseline` to the coverage `totals` of `files`
+pub fn coverage_delta(
+    baseline: &CoverageBaseline,
+    totals: &CoverageStats,
+    files: &[FileCoverage],
+) -> CoverageDelta {
+    let before: BTreeMap<&str, f64> = baseline
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.stats.line_percent))
+        .collect();
+    let after: BTreeMap<&str, f64> = files
+        .iter()
+        .map(|file| (file.path.as_str(), file.stats.line_percent))
+        .collect();
+    let mut changed: Vec<FileDelta> = before
+        .keys()
+        .chain(after.keys())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|path| {
+            let (before, after) = (before.get(path).copied(), after.get(path).copied());
+            let change = before
+                .zip(after)
+                .map(|(before, after)| round(after - before));
+            (change != Some(0.0)).then(|| FileDelta {
+                path: path.to_string(),
+                before,
+                after,
+                change,
+            })
+        })
+        .collect();
+    // Drops first, then gains, then files added or removed
+    changed.sort_by(|a, b| {
+        let key = |delta: &FileDelta| delta.change.unwrap_or(f64::INFINITY);
+        key(a).total_cmp(&key(b)).then_with(|| a.path.cmp(&b.path))
+    });
+    CoverageDelta {
+        baseline_timestamp: baseline.timestamp.clone(),
+        baseline_git_sha: baseline.git_sha.clone(),
+        line_percent: round(totals.line_percent - baseline.totals.line_percent),
+        branch_percent: totals
+            .branch_percent
+            .zip(baseline.totals.branch_percent)
+            .map(|(now, before)| round(now - before)),
+        files: changed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str, content: &str) -> (&'static str, Vec<FileCoverage>) {
+        let mut hits = Hits::new();
+        let format = parse_report(content, Path::new(name), &mut hits).unwrap();
+        (format, file_coverage(Path::new("/repo"), hits))
+    }
+
+    #[test]
+    fn test_lcov_records_are_merged_by_line() {
+        let lcov = "TN:unit\nSF:/repo/src/lib.rs\nDA:1,1\nDA:2,0\nBRDA:2,0,0,1\nBRDA:2,0,1,-\nend_of_record\n\
+                    TN:integration\nSF:/repo/src/lib.rs\nDA:2,3\nDA:3,0\nend_of_record\n";
+        let (format, files) = parse("lcov.info", lcov);
+        assert_eq!(format, "lcov");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/lib.rs");
+        let stats = files[0].stats;
+        assert_eq!((stats.lines_total, stats.lines_covered), (3, 2));
+        assert_eq!(stats.line_percent, 66.67);
+        assert_eq!((stats.branches_total, stats.branches_covered), (2, 1));
+        assert_eq!(stats.branch_percent, Some(50.0));
+    }
+
+    #[test]
+    fn test_cobertura_and_json_reports() {
+        let xml = r#"<?xml version="1.0" ?>
+<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">
+<coverage line-rate="0.5">
+  <sources><source>/nowhere</source></sources>
+  <packages><package name="app"><classes>
+    <class name="api.py" filename="app/api.py">
+      <lines>
+        <line number="1" hits="1"/>
+        <line number="2" hits="0" branch="true" condition-coverage="50% (1/2)"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>"#;
+        let (format, files) = parse("coverage.xml", xml);
+        assert_eq!(format, "cobertura");
+        assert_eq!(files[0].path, "app/api.py");
+        assert_eq!(files[0].stats.line_percent, 50.0);
+        assert_eq!(files[0].stats.branch_percent, Some(50.0));
+
+        let json = r#"{"meta": {}, "files": {"app/api.py": {"executed_lines": [1, 2, 3],
+            "missing_lines": [4], "executed_branches": [[2, 3]], "missing_branches": [[2, -1]]}}}"#;
+        let (format, files) = parse("coverage.json", json);
+        assert_eq!(format, "coverage.py");
+        assert_eq!(files[0].stats.line_percent, 75.0);
+        assert_eq!(files[0].stats.branches_total, 2);
+
+        let istanbul = r#"{"/repo/web/app.js": {"path": "/repo/web/app.js",
+            "statementMap": {"0": {"start": {"line": 1}}, "1": {"start": {"line": 2}}},
+            "s": {"0": 4, "1": 0},
+            "branchMap": {"0": {"loc": {"start": {"line": 1}}}}, "b": {"0": [4, 0]}}}"#;
+        let (format, files) = parse("coverage-final.json", istanbul);
+        assert_eq!(format, "istanbul");
+        assert_eq!(files[0].path, "web/app.js");
+        assert_eq!(files[0].stats.lines_covered, 1);
+        assert_eq!(files[0].stats.branch_percent, Some(50.0));
+
+        let mut hits = Hits::new();
+        assert!(parse_report("hello", Path::new("notes.txt"), &mut hits).is_err());
+    }
+
+    #[test]
+    fn test_directories_and_delta() {
+        let file = |path: &str, total: usize, covered: usize| FileCoverage {
+            path: path.to_string(),
+            stats: CoverageStats::new(total, covered, 0, 0),
+        };
+        let before = vec![
+            file("src/a.rs", 10, 8),
+            file("src/b.rs", 10, 5),
+            file("old.rs", 4, 4),
+        ];
+        let after = vec![
+            file("src/a.rs", 10, 6),
+            file("src/b.rs", 10, 5),
+            file("src/c.rs", 2, 2),
+        ];
+
+        let directories = directory_coverage(&after);
+        assert_eq!(directories.len(), 1);
+        assert_eq!(directories[0].path, "src");
+        assert_eq!(directories[0].files, 3);
+        assert_eq!(directories[0].stats.lines_covered, 13);
+
+        let baseline = CoverageBaseline {
+            timestamp: "2024-05-01T10:00:00+00:00".to_string(),
+            git_sha: None,
+            totals: totals(&before),
+            files: before,
+        };
+        let delta = coverage_delta(&baseline, &totals(&after), &after);
+        // 59.09% of 22 lines, from 70.83% of 24
+        assert_eq!(delta.line_percent, -11.74);
+        let changed: Vec<(&str, Option<f64>)> = delta
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.change))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                ("src/a.rs", Some(-20.0)),
+                ("old.rs", None),
+                ("src/c.rs", None)
+            ]
+        );
+        assert_eq!(delta.branch_percent, None);
+    }
+}
@@ -0,0 +1,338 @@
+// rust_core/src/test_reports/flaky.rs
+//! Flaky tests from the history of test outcomes
+//!
+//! Every set of JUnit results read is recorded in the state store as a run,
+//! with the time, the Git commit checked out and the outcome of each test; the
+//! output of failures is kept so the latest one can be shown. Reading the same
+//! reports again records nothing, as each run carries a digest of the test
+//! cases read. A test is flaky when it both passed and failed over the last
+//! runs, the more so when it did both on the same commit.
+
+use super::junit::{is_failure, TestCase};
+use crate::error::CdeError;
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::state::{self, StateStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+const RUN_PREFIX: &str = "test_runs/";
+
+/// Runs kept per project
+pub const MAX_RUNS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestOutcome {
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestRun {
+    /// RFC 3339
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    /// Digest of the test cases read, to skip reports read twice
+    pub digest: String,
+    /// Outcome by test id
+    pub outcomes: BTreeMap<String, TestOutcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastFailure {
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    pub message: Option<String>,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakyTest {
+    pub id: String,
+    /// Runs the test passed or failed in, skips aside
+    pub runs: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Share of those runs that failed, from 0 to 1
+    pub failure_rate: f64,
+    /// Times the outcome changed from one run to the next
+    pub flips: usize,
+    /// Passed and failed on the same commit
+    pub same_commit: bool,
+    pub last_status: String,
+    pub last_failure: Option<LastFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakyReport {
+    pub window: usize,
+    pub runs_analyzed: usize,
+    pub tests_analyzed: usize,
+    /// Tests failing on the same commit first, then by flips and failure rate
+    pub flaky: Vec<FlakyTest>,
+    pub summary: String,
+    pub truncated: bool,
+}
+
+impl CapLists for FlakyReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.flaky, max_items);
+    }
+}
+
+/// Records the outcomes of `cases` as a run of the project at `root`; returns
+/// false when the latest run recorded has the same outcomes
+pub fn record_test_run_with(
+    store: &StateStore,
+    root: &str,
+    git_sha: Option<String>,
+    cases: &[TestCase],
+) -> Result<bool, CdeError> {
+    let outcomes: BTreeMap<String, TestOutcome> = cases
+        .iter()
+        .map(|case| {
+            let failed = is_failure(&case.status);
+            let outcome = TestOutcome {
+                status: case.status.clone(),
+                message: case.message.clone().filter(|_| failed),
+                output: case.output.clone().filter(|_| failed),
+            };
+            (case.id.clone(), outcome)
+        })
+        .collect();
+    let serialized = serde_json::to_vec(cases)
+        .map_err(|e| CdeError::serialization("Failed to serialize test run").caused_by(&e))?;
+    let digest = blake3::hash(&serialized).to_hex().to_string();
+
+    let namespace = state::namespace(root);
+    let entries = store.scan(&namespace, RUN_PREFIX, None)?;
+    let latest = entries
+        .last()
+        .and_then(|entry| serde_json::from_str::<TestRun>(&entry.value).ok());
+    if latest.is_some_and(|run| run.digest == digest) {
+        return Ok(false);
+    }
+    let now = chrono::Utc::now();
+    let run = TestRun {
+        timestamp: now.to_rfc3339(),
+        git_sha,
+        digest,
+        outcomes,
+    };
+    let value = serde_json::to_string(&run)
+        .map_err(|e| CdeError::serialization("Failed to serialize test run").caused_by(&e))?;
+    // Zero-padded so keys sort by time, as for quality runs
+    let key = format!(
+        "{}{:020}",
+        RUN_PREFIX,
+        now.timestamp_nanos_opt().unwrap_or_default()
+    );
+    store.set(&namespace, &key, &value, None)?;
+    // The run just written is not in `entries`
+    for entry in &entries[..(entries.len() + 1).saturating_sub(MAX_RUNS)] {
+        store.delete(&namespace, &entry.key)?;
+    }
+    Ok(true)
+}
+
+/// Tests of the project at `root` that both passed and failed over its last
+/// `window` recorded runs
+pub fn detect_flaky_tests_with(
+    store: &StateStore,
+    root: &str,
+    window: usize,
+) -> Result<FlakyReport, CdeError> {
+    metrics::timed("detect_flaky_tests", || {
+        if window == 0 {
+            return Err(CdeError::invalid_input("window must be at least 1"));
+        }
+        let mut runs: Vec<TestRun> = store
+            .scan(&state::namespace(root), RUN_PREFIX, None)?
+            .into_iter()
+            .filter_map(|entry| serde_json::from_str(&entry.value).ok())
+            .collect();
+        runs.drain(..runs.len().saturating_sub(window));
+
+        #[derive(Default)]
+        struct History<'a> {
+            statuses: Vec<&'a str>,
+            passed_on: BTreeSet<Option<&'a str>>,
+            failed_on: BTreeSet<Option<&'a str>>,
+            last_failure: Option<(&'a TestRun, &'a TestOutcome)>,
+        }
+        let mut histories: BTreeMap<&str, History> = BTreeMap::new();
+        for run in &runs {
+            for (id, outcome) in &run.outcomes {
+                let history = histories.entry(id.as_str()).or_default();
+                let commit = run.git_sha.as_deref();
+                if is_failure(&outcome.status) {
+                    history.statuses.push("failed");
+                    history.failed_on.insert(commit);
+                    history.last_failure = Some((run, outcome));
+                } else if outcome.status == "passed" {
+                    history.statuses.push("passed");
+                    history.passed_on.insert(commit);
+                }
+            }
+        }
+
+        let tests_analyzed = histories.len();
+        let mut flaky: Vec<FlakyTest> = histories
+            .into_iter()
+            .filter_map(|(id, history)| {
+                let failed = history.statuses.iter().filter(|s| **s == "failed").count();
+                let passed = history.statuses.len() - failed;
+                if passed == 0 || failed == 0 {
+                    return None;
+                }
+                let flips = history.statuses.windows(2).filter(|w| w[0] != w[1]).count();
+                let same_commit = history
+                    .passed_on
+                    .intersection(&history.failed_on)
+                    .any(Option::is_some);
+                let runs = passed + failed;
+                Some(FlakyTest {
+                    id: id.to_string(),
+                    runs,
+                    passed,
+                    failed,
+                    failure_rate: (failed as f64 * 100.0 / runs as f64).round() / 100.0,
+                    flips,
+                    same_commit,
+                    last_status: history.statuses.last().unwrap_or(&"passed").to_string(),
+                    last_failure: history.last_failure.map(|(run, outcome)| LastFailure {
+                        timestamp: run.timestamp.clone(),
+                        git_sha: run.git_sha.clone(),
+                        message: outcome.message.clone(),
+                        output: outcome.output.clone(),
+                    }),
+                })
+            })
+            .collect();
+        flaky.sort_by(|a, b| {
+            b.same_commit
+                .cmp(&a.same_commit)
+                .then(b.flips.cmp(&a.flips))
+                .then(b.failure_rate.total_cmp(&a.failure_rate))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let summary = match flaky.len() {
+            _ if runs.len() < 2 => format!(
+                "Not enough history: {} test run recorded, flaky tests need at least 2.",
+                runs.len()
+            ),
+            0 => format!("✅ No flaky tests over the last {} runs.", runs.len()),
+            count => format!(
+                "🎲 {} flaky tests over the last {} runs, {} of them on the same commit.",
+                count,
+                runs.len(),
+                flaky.iter().filter(|test| test.same_commit).count()
+            ),
+        };
+        Ok(FlakyReport {
+            window,
+            runs_analyzed: runs.len(),
+            tests_analyzed,
+            flaky,
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(id: &str, status: &str, output: &str) -> TestCase {
+        TestCase {
+            id: id.to_string(),
+            suite: "unit".to_string(),
+            classname: None,
+            name: id.to_string(),
+            file: None,
+            status: status.to_string(),
+            time_secs: 0.1,
+            message: (status == "failed").then(|| "assert False".to_string()),
+            output: (!output.is_empty()).then(|| output.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_flaky_tests_are_found_in_the_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let root = dir.path().to_str().unwrap();
+        let sha = |s: &str| Some(s.to_string());
+        let runs = [
+            (
+                "a",
+                [
+                    ("t1", "passed", ""),
+                    ("t2", "passed", ""),
+                    ("t3", "failed", "boom 1"),
+                ],
+            ),
+            (
+                "a",
+                [
+                    ("t1", "failed", "timeout"),
+                    ("t2", "passed", ""),
+                    ("t3", "failed", "boom 2"),
+                ],
+            ),
+            (
+                "b",
+                [
+                    ("t1", "passed", ""),
+                    ("t2", "failed", "off by one"),
+                    ("t3", "failed", "boom 3"),
+                ],
+            ),
+            (
+                "c",
+                [
+                    ("t1", "passed", ""),
+                    ("t2", "passed", ""),
+                    ("t3", "skipped", ""),
+                ],
+            ),
+        ];
+        for (commit, outcomes) in &runs {
+            let cases: Vec<TestCase> = outcomes
+                .iter()
+                .map(|(id, status, output)| case(id, status, output))
+                .collect();
+            assert!(record_test_run_with(&store, root, sha(commit), &cases).unwrap());
+            // The same reports read again are not another run
+            assert!(!record_test_run_with(&store, root, sha(commit), &cases).unwrap());
+        }
+
+        let report = detect_flaky_tests_with(&store, root, 10).unwrap();
+        assert_eq!((report.runs_analyzed, report.tests_analyzed), (4, 3));
+        let ids: Vec<&str> = report.flaky.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["t1", "t2"]);
+        let t1 = &report.flaky[0];
+        assert!(t1.same_commit);
+        assert_eq!((t1.runs, t1.failed, t1.flips), (4, 1, 2));
+        assert_eq!(t1.failure_rate, 0.25);
+        assert_eq!(t1.last_status, "passed");
+        let last = t1.last_failure.as_ref().unwrap();
+        assert_eq!(last.output.as_deref(), Some("timeout"));
+        assert_eq!(last.message.as_deref(), Some("assert False"));
+        assert!(!report.flaky[1].same_commit);
+        assert!(report
+            .summary
+            .starts_with("🎲 2 flaky tests over the last 4 runs"));
+
+        // t1 only failed in the second run
+        let recent = detect_flaky_tests_with(&store, root, 2).unwrap();
+        let ids: Vec<&str> = recent.flaky.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["t2"]);
+        assert!(detect_flaky_tests_with(&store, root, 0).is_err());
+    }
+}
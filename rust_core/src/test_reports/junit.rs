@@ -0,0 +1,317 @@
+// rust_core/src/test_reports/junit.rs
+//! Test results from JUnit XML (pytest, Jest, Surefire, nextest, go-junit-report)
+//!
+//! `<testcase>` elements are read wherever they are nested, under a
+//! `<testsuites>` or a lone `<testsuite>`; a case with a `<failure>` failed,
+//! with an `<error>` errored and with a `<skipped>` was skipped. Failure output
+//! is kept as a snippet of its end, where the assertion usually is.
+
+use super::{relative_path, xml_document};
+use crate::error::CdeError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Characters of failure output kept per test
+pub const MAX_OUTPUT_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestCase {
+    /// "classname::name", or "suite::name" without a class name
+    pub id: String,
+    pub suite: String,
+    pub classname: Option<String>,
+    pub name: String,
+    /// Relative to the project root, when the report names it
+    pub file: Option<String>,
+    pub status: String, // "passed", "failed", "error", "skipped"
+    pub time_secs: f64,
+    pub message: Option<String>,
+    /// End of the failure output
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestCounts {
+    pub tests: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    pub time_secs: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestGroup {
+    /// Test file, else class name, else suite name
+    pub name: String,
+    #[serde(flatten)]
+    pub counts: TestCounts,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestResultsDelta {
+    pub baseline_timestamp: String,
+    pub baseline_git_sha: Option<String>,
+    /// Passed in the baseline, failed or errored now
+    pub newly_failing: Vec<String>,
+    /// Failed or errored in the baseline, passed now
+    pub fixed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Outcomes stored to compare later runs with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestResultsBaseline {
+    /// RFC 3339
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    /// Status by test id
+    pub outcomes: BTreeMap<String, String>,
+}
+
+impl TestCounts {
+    fn add(&mut self, case: &TestCase) {
+        self.tests += 1;
+        match case.status.as_str() {
+            "failed" => self.failed += 1,
+            "error" => self.errors += 1,
+            "skipped" => self.skipped += 1,
+            _ => self.passed += 1,
+        }
+        self.time_secs = ((self.time_secs + case.time_secs) * 1000.0).round() / 1000.0;
+    }
+}
+
+/// Whether `status` is a failed or errored outcome
+pub fn is_failure(status: &str) -> bool {
+    matches!(status, "failed" | "error")
+}
+
+/// Last `max_chars` characters of `text`, after an ellipsis when cut
+fn tail(text: &str, max_chars: usize) -> String {
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().skip(count - max_chars + 1).collect();
+    format!("…{}", kept)
+}
+
+/// "1,234.5" and "0.01" as seconds
+fn seconds(value: Option<&str>) -> f64 {
+    value
+        .and_then(|v| v.replace(',', "").parse::<f64>().ok())
+        .filter(|v| v.is_finite())
+        .unwrap_or(0.0)
+}
+
+/// Test cases of the JUnit report `content` of the file `artifact`, with their
+/// files relative to `root`
+pub fn parse_junit(content: &str, artifact: &Path, root: &Path) -> Result<Vec<TestCase>, CdeError> {
+    let document = xml_document(content, artifact)?;
+    let top = document.root_element();
+    if !top.has_tag_name("testsuites") && !top.has_tag_name("testsuite") {
+        return Err(CdeError::parse("Not a JUnit XML report").with_path(artifact));
+    }
+    let mut cases = Vec::new();
+    for case in top
+        .descendants()
+        .filter(|node| node.has_tag_name("testcase"))
+    {
+        let suite = case.ancestors().find(|node| node.has_tag_name("testsuite"));
+        let suite_name = suite
+            .and_then(|s| s.attribute("name"))
+            .unwrap_or_default()
+            .to_string();
+        let name = case.attribute("name").unwrap_or_default().to_string();
+        let classname = case
+            .attribute("classname")
+            .filter(|c| !c.is_empty())
+            .map(str::to_string);
+        let file = case
+            .attribute("file")
+            .or_else(|| suite.and_then(|s| s.attribute("file")))
+            .map(|file| relative_path(root, file));
+
+        let outcome = case.children().find(|node| {
+            node.has_tag_name("failure")
+                || node.has_tag_name("error")
+                || node.has_tag_name("skipped")
+        });
+        let status = match outcome.map(|node| node.tag_name().name()) {
+            Some("failure") => "failed",
+            Some("error") => "error",
+            Some(_) => "skipped",
+            None => "passed",
+        };
+        let message = outcome
+            .and_then(|node| node.attribute("message"))
+            .filter(|m| !m.trim().is_empty())
+            .map(|m| m.trim().to_string());
+        let output = outcome
+            .filter(|_| is_failure(status))
+            .map(|node| {
+                node.descendants()
+                    .filter(|n| n.is_text())
+                    .filter_map(|n| n.text())
+                    .collect::<String>()
+            })
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| tail(text.trim(), MAX_OUTPUT_CHARS));
+
+        cases.push(TestCase {
+            id: format!("{}::{}", classname.as_deref().unwrap_or(&suite_name), name),
+            suite: suite_name,
+            classname,
+            name,
+            file,
+            status: status.to_string(),
+            time_secs: seconds(case.attribute("time")),
+            message,
+            output,
+        });
+    }
+    Ok(cases)
+}
+
+/// Counts of `cases`, overall and by test file (else class, else suite), the
+/// groups sorted by name
+pub fn group_cases(cases: &[TestCase]) -> (TestCounts, Vec<TestGroup>) {
+    let mut totals = TestCounts::default();
+    let mut groups: BTreeMap<&str, TestCounts> = BTreeMap::new();
+    for case in cases {
+        totals.add(case);
+        let name = case
+            .file
+            .as_deref()
+            .or(case.classname.as_deref())
+            .unwrap_or(&case.suite);
+        groups.entry(name).or_default().add(case);
+    }
+    let groups = groups
+        .into_iter()
+        .map(|(name, counts)| TestGroup {
+            name: name.to_string(),
+            counts,
+        })
+        .collect();
+    (totals, groups)
+}
+
+/// Changes from `baseline` to the outcomes of `cases`
+pub fn results_delta(baseline: &TestResultsBaseline, cases: &[TestCase]) -> TestResultsDelta {
+    let now: BTreeMap<&str, &str> = cases
+        .iter()
+        .map(|case| (case.id.as_str(), case.status.as_str()))
+        .collect();
+    let before = &baseline.outcomes;
+    let ids: BTreeSet<&str> = now
+        .keys()
+        .copied()
+        .chain(before.keys().map(String::as_str))
+        .collect();
+    let mut delta = TestResultsDelta {
+        baseline_timestamp: baseline.timestamp.clone(),
+        baseline_git_sha: baseline.git_sha.clone(),
+        newly_failing: Vec::new(),
+        fixed: Vec::new(),
+        added: Vec::new(),
+        removed: Vec::new(),
+    };
+    for id in ids {
+        let list = match (before.get(id).map(String::as_str), now.get(id).copied()) {
+            (None, Some(_)) => &mut delta.added,
+            (Some(_), None) => &mut delta.removed,
+            (Some("passed"), Some(status)) if is_failure(status) => &mut delta.newly_failing,
+            (Some(status), Some("passed")) if is_failure(status) => &mut delta.fixed,
+            _ => continue,
+        };
+        list.push(id.to_string());
+    }
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="pytest" tests="4" file="/repo/tests/test_api.py">
+    <testcase classname="tests.test_api" name="test_ok" time="0.010"/>
+    <testcase classname="tests.test_api" name="test_fails" time="1,200.5">
+      <failure message="assert 1 == 2">def test_fails():
+&gt;       assert 1 == 2
+E       assert 1 == 2</failure>
+    </testcase>
+    <testcase classname="tests.test_api" name="test_boom"><error message="">RuntimeError</error></testcase>
+    <testcase classname="tests.test_api" name="test_later"><skipped message="todo"/></testcase>
+  </testsuite>
+</testsuites>"#;
+
+    #[test]
+    fn test_cases_are_read_with_their_outcomes() {
+        let cases = parse_junit(REPORT, Path::new("junit.xml"), Path::new("/repo")).unwrap();
+        let statuses: Vec<(&str, &str)> = cases
+            .iter()
+            .map(|c| (c.id.as_str(), c.status.as_str()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("tests.test_api::test_ok", "passed"),
+                ("tests.test_api::test_fails", "failed"),
+                ("tests.test_api::test_boom", "error"),
+                ("tests.test_api::test_later", "skipped"),
+            ]
+        );
+        let failed = &cases[1];
+        assert_eq!(failed.file.as_deref(), Some("tests/test_api.py"));
+        assert_eq!(failed.time_secs, 1200.5);
+        assert_eq!(failed.message.as_deref(), Some("assert 1 == 2"));
+        assert!(failed
+            .output
+            .as_deref()
+            .unwrap()
+            .ends_with("E       assert 1 == 2"));
+        assert_eq!(cases[2].message, None);
+        assert_eq!(cases[2].output.as_deref(), Some("RuntimeError"));
+        assert_eq!(cases[3].output, None);
+
+        let (totals, groups) = group_cases(&cases);
+        assert_eq!((totals.tests, totals.passed, totals.failed), (4, 1, 1));
+        assert_eq!((totals.errors, totals.skipped), (1, 1));
+        assert_eq!(totals.time_secs, 1200.51);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "tests/test_api.py");
+
+        assert!(parse_junit("<coverage/>", Path::new("x.xml"), Path::new("/repo")).is_err());
+        assert_eq!(tail("abcdef", 4), "…def");
+    }
+
+    #[test]
+    fn test_delta_against_baseline() {
+        let cases = parse_junit(REPORT, Path::new("junit.xml"), Path::new("/repo")).unwrap();
+        let outcomes = [
+            ("tests.test_api::test_ok", "failed"),
+            ("tests.test_api::test_fails", "passed"),
+            ("tests.test_api::test_boom", "error"),
+            ("tests.test_api::test_gone", "passed"),
+        ];
+        let baseline = TestResultsBaseline {
+            timestamp: "2024-05-01T10:00:00+00:00".to_string(),
+            git_sha: None,
+            outcomes: outcomes
+                .into_iter()
+                .map(|(id, status)| (id.to_string(), status.to_string()))
+                .collect(),
+        };
+        let delta = results_delta(&baseline, &cases);
+        assert_eq!(delta.newly_failing, vec!["tests.test_api::test_fails"]);
+        assert_eq!(delta.fixed, vec!["tests.test_api::test_ok"]);
+        assert_eq!(delta.added, vec!["tests.test_api::test_later"]);
+        assert_eq!(delta.removed, vec!["tests.test_api::test_gone"]);
+    }
+}
@@ -0,0 +1,552 @@
+// rust_core/src/test_reports/mod.rs
+//! Coverage and test result artifacts of a project
+//!
+//! Reports left by the test runners (LCOV, Cobertura, coverage.py and Istanbul
+//! JSON for coverage, JUnit XML for results) are read into one normalized shape
+//! per file and per directory, whatever the language, so the quality gate no
+//! longer needs each language's tools to compare runs. Unless their paths are
+//! given, the artifacts are looked up where the usual runners write them. A run
+//! can be stored in the state store as the project's baseline, and every later
+//! run reports its changes against it. Test outcomes are also kept run after
+//! run, to find the tests that fail only sometimes.
+
+mod coverage;
+mod flaky;
+mod junit;
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy;
+use crate::state::{self, StateStore};
+use crate::trend::head_sha;
+use coverage::{CoverageBaseline, CoverageDelta, CoverageStats, DirectoryCoverage, FileCoverage};
+use junit::{TestCase, TestCounts, TestGroup, TestResultsBaseline, TestResultsDelta};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where coverage tools write their reports, relative to the project root
+const COVERAGE_PATTERNS: &[&str] = &[
+    "lcov.info",
+    "coverage/lcov.info",
+    "coverage.xml",
+    "coverage/cobertura-coverage.xml",
+    "coverage.json",
+    "coverage/coverage-final.json",
+    "target/llvm-cov/lcov.info",
+    "target/tarpaulin/cobertura.xml",
+    "cobertura.xml",
+];
+
+/// Where test runners write their JUnit reports, relative to the project root
+const JUNIT_PATTERNS: &[&str] = &[
+    "junit.xml",
+    "junit/*.xml",
+    "test-results/**/*.xml",
+    "reports/junit*.xml",
+    "target/surefire-reports/TEST-*.xml",
+    "target/nextest/*/junit.xml",
+    "build/test-results/**/*.xml",
+];
+
+const COVERAGE_BASELINE_KEY: &str = "coverage/baseline";
+const TEST_RESULTS_BASELINE_KEY: &str = "test_results/baseline";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Reports read, relative to the root
+    pub artifacts: Vec<String>,
+    /// Format of each artifact: "lcov", "cobertura", "coverage.py", "istanbul"
+    pub formats: Vec<String>,
+    /// Artifacts that could not be read, with the reason
+    pub errors: Vec<String>,
+    pub totals: CoverageStats,
+    pub files: Vec<FileCoverage>,
+    pub directories: Vec<DirectoryCoverage>,
+    pub delta: Option<CoverageDelta>,
+    pub baseline_saved: bool,
+    pub summary: String,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestResultsReport {
+    pub artifacts: Vec<String>,
+    pub errors: Vec<String>,
+    #[serde(flatten)]
+    pub totals: TestCounts,
+    pub groups: Vec<TestGroup>,
+    /// Failed and errored cases, with their output
+    pub failures: Vec<TestCase>,
+    pub delta: Option<TestResultsDelta>,
+    pub baseline_saved: bool,
+    /// Whether these outcomes were added to the history of runs; reports
+    /// read again are not
+    pub run_recorded: bool,
+    pub summary: String,
+    pub truncated: bool,
+}
+
+impl CapLists for CoverageReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.files, max_items) | cap(&mut self.directories, max_items);
+        if let Some(delta) = &mut self.delta {
+            self.truncated |= cap(&mut delta.files, max_items);
+        }
+    }
+}
+
+impl CapLists for TestResultsReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.groups, max_items) | cap(&mut self.failures, max_items);
+        if let Some(delta) = &mut self.delta {
+            self.truncated |= cap(&mut delta.newly_failing, max_items)
+                | cap(&mut delta.fixed, max_items)
+                | cap(&mut delta.added, max_items)
+                | cap(&mut delta.removed, max_items);
+        }
+    }
+}
+
+/// Lowercase file name of `path`
+fn artifact_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// `path` relative to `root` with `/` separators, as given when outside it
+fn relative_path(root: &Path, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let candidate = Path::new(&path);
+    let relative = candidate
+        .strip_prefix(root)
+        .ok()
+        .map(Path::to_path_buf)
+        .or_else(|| {
+            let canonical = fs::canonicalize(root).ok()?;
+            candidate
+                .strip_prefix(&canonical)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"));
+    let relative = relative.unwrap_or(path);
+    relative.trim_start_matches("./").to_string()
+}
+
+/// XML document of the artifact `content`; reports often carry a DOCTYPE
+fn xml_document<'a>(
+    content: &'a str,
+    artifact: &Path,
+) -> Result<roxmltree::Document<'a>, CdeError> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(content.trim_start_matches('\u{feff}'), options)
+        .map_err(|e| {
+            CdeError::parse("Invalid XML report")
+                .with_path(artifact)
+                .caused_by(&e)
+        })
+}
+
+/// Artifacts to read: `paths` (relative to `root` or absolute), else the files
+/// under `root` matching `patterns`
+fn find_artifacts(
+    root: &Path,
+    paths: Option<&[String]>,
+    patterns: &[&str],
+) -> Result<Vec<PathBuf>, CdeError> {
+    path_policy::check(root)?;
+    if !root.is_dir() {
+        return Err(CdeError::not_a_directory(root));
+    }
+    if let Some(paths) = paths {
+        return paths
+            .iter()
+            .map(|path| {
+                let path = root.join(path);
+                path_policy::check(&path)?;
+                if !path.is_file() {
+                    return Err(CdeError::not_found("Report not found").with_path(&path));
+                }
+                Ok(path)
+            })
+            .collect();
+    }
+    let mut found: Vec<PathBuf> = patterns
+        .iter()
+        .filter_map(|pattern| glob::glob(&root.join(pattern).to_string_lossy()).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file() && path_policy::check(path).is_ok())
+        .collect();
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+fn read_artifact(path: &Path) -> Result<String, CdeError> {
+    fs::read_to_string(path).map_err(|e| {
+        CdeError::io("Failed to read report")
+            .with_path(path)
+            .caused_by(&e)
+    })
+}
+
+fn load_baseline<T: for<'de> Deserialize<'de>>(
+    store: &StateStore,
+    root: &str,
+    key: &str,
+) -> Result<Option<T>, CdeError> {
+    // A baseline stored by another version is ignored rather than fatal
+    Ok(store
+        .get(&state::namespace(root), key)?
+        .and_then(|value| serde_json::from_str(&value).ok()))
+}
+
+fn store_baseline<T: Serialize>(
+    store: &StateStore,
+    root: &str,
+    key: &str,
+    baseline: &T,
+) -> Result<(), CdeError> {
+    let value = serde_json::to_string(baseline)
+        .map_err(|e| CdeError::serialization("Failed to serialize baseline").caused_by(&e))?;
+    store.set(&state::namespace(root), key, &value, None)
+}
+
+/// Coverage of the project at `root` from its coverage reports, compared with
+/// the stored baseline; with `save`, this run becomes the baseline
+pub fn coverage_report_with(
+    store: &StateStore,
+    root: &str,
+    paths: Option<&[String]>,
+    save: bool,
+) -> Result<CoverageReport, CdeError> {
+    metrics::timed("parse_coverage", || {
+        let root_path = Path::new(root);
+        let artifacts = find_artifacts(root_path, paths, COVERAGE_PATTERNS)?;
+        let parsed: Vec<Result<(&'static str, coverage::Hits), CdeError>> = artifacts
+            .par_iter()
+            .map(|artifact| {
+                let content = read_artifact(artifact)?;
+                let mut hits = coverage::Hits::new();
+                let format = coverage::parse_report(&content, artifact, &mut hits)?;
+                Ok((format, hits))
+            })
+            .collect();
+
+        let mut report_paths = Vec::new();
+        let mut formats = Vec::new();
+        let mut errors = Vec::new();
+        let mut hits = coverage::Hits::new();
+        for (artifact, result) in artifacts.iter().zip(parsed) {
+            let name = relative_path(root_path, &artifact.to_string_lossy());
+            match result {
+                Ok((format, artifact_hits)) => {
+                    report_paths.push(name);
+                    formats.push(format.to_string());
+                    for (path, file) in artifact_hits {
+                        hits.entry(path).or_default().merge(file);
+                    }
+                }
+                // Explicit paths must be coverage reports
+                Err(e) if paths.is_some() => return Err(e),
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+        let files = coverage::file_coverage(root_path, hits);
+        let totals = coverage::totals(&files);
+        let delta = load_baseline::<CoverageBaseline>(store, root, COVERAGE_BASELINE_KEY)?
+            .map(|baseline| coverage::coverage_delta(&baseline, &totals, &files));
+        let baseline_saved = save && !files.is_empty();
+        if baseline_saved {
+            let baseline = CoverageBaseline {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                git_sha: head_sha(root_path),
+                totals,
+                files: files.clone(),
+            };
+            store_baseline(store, root, COVERAGE_BASELINE_KEY, &baseline)?;
+        }
+
+        let mut summary = if files.is_empty() {
+            "No coverage report found.".to_string()
+        } else {
+            let branches = totals
+                .branch_percent
+                .map(|percent| format!(", branches {:.1}%", percent))
+                .unwrap_or_default();
+            format!(
+                "📊 Line coverage {:.1}% over {} files{}.",
+                totals.line_percent,
+                files.len(),
+                branches
+            )
+        };
+        if let (Some(delta), false) = (&delta, files.is_empty()) {
+            summary.push_str(&format!(
+                " {:+.2} points since the baseline of {}.",
+                delta.line_percent, delta.baseline_timestamp
+            ));
+        }
+        Ok(CoverageReport {
+            artifacts: report_paths,
+            formats,
+            errors,
+            totals,
+            directories: coverage::directory_coverage(&files),
+            files,
+            delta,
+            baseline_saved,
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+/// Test results of the project at `root` from its JUnit reports, compared with
+/// the stored baseline; with `save`, this run becomes the baseline
+pub fn test_results_report_with(
+    store: &StateStore,
+    root: &str,
+    paths: Option<&[String]>,
+    save: bool,
+) -> Result<TestResultsReport, CdeError> {
+    metrics::timed("parse_test_results", || {
+        let root_path = Path::new(root);
+        let artifacts = find_artifacts(root_path, paths, JUNIT_PATTERNS)?;
+        let parsed: Vec<Result<Vec<TestCase>, CdeError>> = artifacts
+            .par_iter()
+            .map(|artifact| junit::parse_junit(&read_artifact(artifact)?, artifact, root_path))
+            .collect();
+
+        let mut report_paths = Vec::new();
+        let mut errors = Vec::new();
+        let mut cases = Vec::new();
+        for (artifact, result) in artifacts.iter().zip(parsed) {
+            let name = relative_path(root_path, &artifact.to_string_lossy());
+            match result {
+                Ok(artifact_cases) => {
+                    report_paths.push(name);
+                    cases.extend(artifact_cases);
+                }
+                Err(e) if paths.is_some() => return Err(e),
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+        let (totals, groups) = junit::group_cases(&cases);
+        let git_sha = head_sha(root_path);
+        // The history only feeds flaky test detection: failing to record it
+        // must not fail the report
+        let run_recorded = !cases.is_empty()
+            && flaky::record_test_run_with(store, root, git_sha.clone(), &cases)
+                .inspect_err(
+                    |e| tracing::warn!(root = %root, error = %e, "Failed to record test run"),
+                )
+                .unwrap_or(false);
+        let delta = load_baseline::<TestResultsBaseline>(store, root, TEST_RESULTS_BASELINE_KEY)?
+            .map(|baseline| junit::results_delta(&baseline, &cases));
+        let baseline_saved = save && !cases.is_empty();
+        if baseline_saved {
+            let baseline = TestResultsBaseline {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                git_sha,
+                outcomes: cases
+                    .iter()
+                    .map(|case| (case.id.clone(), case.status.clone()))
+                    .collect(),
+            };
+            store_baseline(store, root, TEST_RESULTS_BASELINE_KEY, &baseline)?;
+        }
+
+        let mut summary = match (totals.tests, totals.failed + totals.errors) {
+            (0, _) => "No test results found.".to_string(),
+            (tests, 0) => format!(
+                "✅ {} tests passed, {} skipped.",
+                tests - totals.skipped,
+                totals.skipped
+            ),
+            (tests, _) => format!(
+                "❌ {} of {} tests failed and {} errored, {} skipped.",
+                totals.failed, tests, totals.errors, totals.skipped
+            ),
+        };
+        if let Some(delta) = &delta {
+            summary.push_str(&format!(
+                " Since the baseline: {} newly failing, {} fixed.",
+                delta.newly_failing.len(),
+                delta.fixed.len()
+            ));
+        }
+        Ok(TestResultsReport {
+            artifacts: report_paths,
+            errors,
+            totals,
+            groups,
+            failures: cases
+                .into_iter()
+                .filter(|case| junit::is_failure(&case.status))
+                .collect(),
+            delta,
+            baseline_saved,
+            run_recorded,
+            summary,
+            truncated: false,
+        })
+    })
+}
+
+/// Coverage of a project from the reports its test runs left, as JSON
+///
+/// Reads LCOV (`lcov.info`), Cobertura XML (`coverage.xml`), coverage.py JSON
+/// (`coverage.json`) and Istanbul JSON (`coverage/coverage-final.json`) reports,
+/// found where the usual tools write them unless `paths` names them. Returns
+/// `totals`, `files` and `directories` with lines and branches covered and
+/// their percents, and `delta` against the stored baseline: the change in
+/// percent overall and per file, largest drop first. With `save_baseline`,
+/// this run becomes the baseline of later ones.
+#[pyfunction]
+#[pyo3(signature = (root, paths=None, save_baseline=false, max_list_items=None))]
+fn parse_coverage_py(
+    py: Python<'_>,
+    root: &str,
+    paths: Option<Vec<String>>,
+    save_baseline: bool,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let mut report = py.detach(|| {
+        state::with_shared(|store| {
+            coverage_report_with(store, root, paths.as_deref(), save_baseline)
+        })
+    })?;
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Test results of a project from its JUnit XML reports, as JSON
+///
+/// Reads the reports of pytest, Jest, Surefire, Gradle, nextest and the like,
+/// found where those runners write them unless `paths` names them. Returns the
+/// counts of tests passed, failed, errored and skipped with their time, overall
+/// and per test file (`groups`), the `failures` with their message and the end
+/// of their output, and `delta` against the stored baseline: tests
+/// `newly_failing`, `fixed`, `added` and `removed`. With `save_baseline`, this
+/// run becomes the baseline of later ones. New results are also added to the
+/// history `detect_flaky_tests_py` reads (`run_recorded`).
+#[pyfunction]
+#[pyo3(signature = (root, paths=None, save_baseline=false, max_list_items=None))]
+fn parse_test_results_py(
+    py: Python<'_>,
+    root: &str,
+    paths: Option<Vec<String>>,
+    save_baseline: bool,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let mut report = py.detach(|| {
+        state::with_shared(|store| {
+            test_results_report_with(store, root, paths.as_deref(), save_baseline)
+        })
+    })?;
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Flaky tests of a project over its last test runs, as JSON
+///
+/// Every new set of results `parse_test_results_py` reads is recorded as a run.
+/// Returns the tests that both passed and failed over the last `window` runs,
+/// with their `runs`, `passed`, `failed`, `failure_rate` (0 to 1), `flips`
+/// (changes of outcome from one run to the next), `same_commit` when they did
+/// both on the same commit, and `last_failure` (timestamp, git_sha, message
+/// and the end of its output). Tests failing on the same commit come first.
+#[pyfunction]
+#[pyo3(signature = (root, window=20, max_list_items=None))]
+fn detect_flaky_tests_py(
+    py: Python<'_>,
+    root: &str,
+    window: usize,
+    max_list_items: Option<usize>,
+) -> PyResult<String> {
+    let mut report = py.detach(|| {
+        state::with_shared(|store| flaky::detect_flaky_tests_with(store, root, window))
+    })?;
+    if let Some(max_items) = max_list_items {
+        report.cap_lists(max_items);
+    }
+    Ok(to_json(&report)?)
+}
+
+/// Adds the coverage and test result functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_coverage_py, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_test_results_py, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_flaky_tests_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_are_found_and_compared_with_the_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let root_path = dir.path().join("project");
+        fs::create_dir_all(root_path.join("coverage")).unwrap();
+        fs::create_dir_all(root_path.join("test-results")).unwrap();
+        let root = root_path.to_str().unwrap();
+        let lcov = |covered: usize| {
+            let lines: String = (1..=4)
+                .map(|line| format!("DA:{},{}\n", line, usize::from(line <= covered)))
+                .collect();
+            format!("SF:{}/src/lib.rs\n{}end_of_record\n", root, lines)
+        };
+        fs::write(root_path.join("coverage/lcov.info"), lcov(4)).unwrap();
+        fs::write(
+            root_path.join("test-results/junit.xml"),
+            r#"<testsuite name="unit"><testcase classname="lib" name="parses"/></testsuite>"#,
+        )
+        .unwrap();
+        fs::write(root_path.join("test-results/other.xml"), "<project/>").unwrap();
+
+        let first = coverage_report_with(&store, root, None, true).unwrap();
+        assert_eq!(first.artifacts, vec!["coverage/lcov.info"]);
+        assert_eq!(first.formats, vec!["lcov"]);
+        assert_eq!(first.files[0].path, "src/lib.rs");
+        assert_eq!(first.directories[0].path, "src");
+        assert!(first.delta.is_none() && first.baseline_saved);
+
+        fs::write(root_path.join("coverage/lcov.info"), lcov(3)).unwrap();
+        let second = coverage_report_with(&store, root, None, false).unwrap();
+        let delta = second.delta.unwrap();
+        assert_eq!(delta.line_percent, -25.0);
+        assert_eq!(delta.files[0].change, Some(-25.0));
+        assert!(second.summary.contains("-25.00 points since the baseline"));
+
+        let results = test_results_report_with(&store, root, None, false).unwrap();
+        assert_eq!(results.artifacts, vec!["test-results/junit.xml"]);
+        assert_eq!(results.errors.len(), 1);
+        assert!(results.errors[0].starts_with("test-results/other.xml: "));
+        assert_eq!((results.totals.tests, results.totals.passed), (1, 1));
+        assert!(results.delta.is_none() && results.run_recorded);
+        let again = test_results_report_with(&store, root, None, false).unwrap();
+        assert!(!again.run_recorded);
+
+        let explicit = ["test-results/other.xml".to_string()];
+        assert!(test_results_report_with(&store, root, Some(&explicit), false).is_err());
+        let missing = ["nope.xml".to_string()];
+        assert!(coverage_report_with(&store, root, Some(&missing), false).is_err());
+    }
+}
@@ -1,14 +1,198 @@
 // src/text.rs
-// This module will contain text processing logic.
-// For now, word count is handled directly in documentation.rs,
-// but more advanced text analysis functions can be added here.
+//! Analyses of a single Markdown text, for content that is not on disk yet
+//!
+//! Fenced code blocks are skipped when counting headings, lists and links, so a
+//! `#` comment in a shell snippet is not taken for a heading.
 
-// Example function that could be added later:
-/*
-use serde_json::Value;
+use crate::error::{to_json, CdeError};
+use crate::frontmatter;
+use crate::metadata::extract_metadata;
+use crate::metrics;
+use serde::{Deserialize, Serialize};
 
-pub fn extract_frontmatter(content: &str) -> Option<Value> {
-    // Logic to parse YAML frontmatter
-    None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextQualityMetrics {
+    pub total_lines: usize,
+    pub total_chars: usize,
+    pub headings: usize,
+    pub code_blocks: usize,
+    pub links: usize,
+    pub lists: usize,
+    pub has_frontmatter: bool,
+    /// 0 to 100
+    pub quality_score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextStructure {
+    pub sections: Vec<Section>,
+    pub total_sections: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub level: usize,
+    pub title: String,
+    /// 1-based
+    pub line: usize,
+}
+
+/// Runs the `analysis_type` analysis ("quality", "metadata" or "structure") and
+/// returns its result as JSON
+pub fn analyze_text(content: &str, analysis_type: &str) -> Result<String, CdeError> {
+    metrics::add_bytes("analyze_text", content.len() as u64);
+    metrics::timed("analyze_text", || match analysis_type {
+        "quality" => to_json(&text_quality(content)),
+        "metadata" => to_json(&extract_metadata(content)),
+        "structure" => to_json(&text_structure(content)),
+        _ => Err(CdeError::invalid_input(format!(
+            "Unknown analysis type: {} (expected quality, metadata or structure)",
+            analysis_type
+        ))),
+    })
+}
+
+pub fn text_quality(content: &str) -> TextQualityMetrics {
+    let total_lines = content.lines().count();
+    let (mut headings, mut code_blocks, mut links, mut lists) = (0, 0, 0, 0);
+    for (line, in_code) in prose_lines(content) {
+        let trimmed = line.trim();
+        if in_code {
+            if trimmed.starts_with("```") {
+                code_blocks += 1;
+            }
+        } else if trimmed.starts_with('#') {
+            headings += 1;
+        } else if trimmed.starts_with("- ")
+            || trimmed.starts_with("* ")
+            || trimmed.starts_with("1. ")
+        {
+            lists += 1;
+        } else if trimmed.contains("](") {
+            links += 1;
+        }
+    }
+    let has_frontmatter = extract_metadata(content).is_some();
+
+    let mut score = 0.0;
+    if headings > 0 {
+        score += 20.0 * (headings as f64 / total_lines as f64).min(1.0);
+    }
+    if code_blocks > 0 {
+        score += 15.0;
+    }
+    if links > 0 {
+        score += 10.0;
+    }
+    if lists > 0 {
+        score += 10.0;
+    }
+    if total_lines > 10 && total_lines < 500 {
+        score += 20.0;
+    }
+    if has_frontmatter {
+        score += 25.0;
+    }
+
+    TextQualityMetrics {
+        total_lines,
+        total_chars: content.chars().count(),
+        headings,
+        code_blocks,
+        links,
+        lists,
+        has_frontmatter,
+        quality_score: score,
+    }
+}
+
+pub fn text_structure(content: &str) -> TextStructure {
+    let sections: Vec<Section> = prose_lines(content)
+        .enumerate()
+        .filter(|(_, (line, in_code))| !in_code && line.starts_with('#'))
+        .map(|(index, (line, _))| {
+            let level = line.chars().take_while(|&c| c == '#').count();
+            Section {
+                level,
+                title: line[level..].trim().to_string(),
+                line: index + 1,
+            }
+        })
+        .collect();
+    TextStructure {
+        total_sections: sections.len(),
+        sections,
+    }
+}
+
+/// Every line with whether it belongs to a fenced code block; the closing fence
+/// is the one line of a block reported as in code. Frontmatter lines are
+/// reported as code too, so a YAML comment or list is not counted as prose.
+fn prose_lines(content: &str) -> impl Iterator<Item = (&str, bool)> {
+    let frontmatter_lines = content[..content.len() - frontmatter::body(content).len()]
+        .lines()
+        .count();
+    let mut in_code = false;
+    content.lines().enumerate().map(move |(index, line)| {
+        if index < frontmatter_lines {
+            return (line, true);
+        }
+        if line.trim().starts_with("```") {
+            in_code = !in_code;
+            return (line, !in_code);
+        }
+        (line, in_code)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "---\ntitle: Guide\nstatus: active\n---\n# Guide\n\nSee [spec](spec.md).\n\n- one\n- two\n\n```sh\n# not a heading\n```\n\n## Usage\n";
+
+    #[test]
+    fn test_quality_and_structure_skip_code_blocks() {
+        let metrics = text_quality(DOC);
+        assert_eq!(
+            (
+                metrics.headings,
+                metrics.code_blocks,
+                metrics.links,
+                metrics.lists
+            ),
+            (2, 1, 1, 2)
+        );
+        assert!(metrics.has_frontmatter);
+
+        let structure = text_structure(DOC);
+        let titles: Vec<(usize, &str, usize)> = structure
+            .sections
+            .iter()
+            .map(|s| (s.level, s.title.as_str(), s.line))
+            .collect();
+        assert_eq!(titles, vec![(1, "Guide", 5), (2, "Usage", 16)]);
+
+        assert_eq!(
+            extract_metadata(DOC).and_then(|m| m.title).as_deref(),
+            Some("Guide")
+        );
+        assert!(matches!(
+            analyze_text(DOC, "summary"),
+            Err(CdeError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_frontmatter_lines_are_not_prose() {
+        let doc = "+++\n# owner = \"docs\"\ntitle = \"Notes\"\n+++\n# Notes\n\n- one\n";
+        let metrics = text_quality(doc);
+        assert_eq!((metrics.headings, metrics.lists), (1, 1));
+        assert!(metrics.has_frontmatter);
+        assert_eq!(text_structure(doc).sections[0].line, 5);
+        assert_eq!(
+            extract_metadata(doc).and_then(|m| m.title).as_deref(),
+            Some("Notes")
+        );
+    }
 }
-*/
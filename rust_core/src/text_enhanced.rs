@@ -1,309 +0,0 @@
-// Enhanced text processing module with Rayon parallelism
-use rayon::prelude::*;
-use regex::Regex;
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Metadata {
-    pub title: Option<String>,
-    pub description: Option<String>,
-    #[serde(rename = "type")]
-    pub doc_type: Option<String>,
-    pub status: Option<String>,
-    pub created: Option<String>,
-    pub updated: Option<String>,
-    pub author: Option<String>,
-    pub llm_summary: Option<String>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ValidationResult {
-    pub path: String,
-    pub valid: bool,
-    pub errors: Vec<String>,
-    pub warnings: Vec<String>,
-    pub metadata: Option<Metadata>,
-}
-
-/// Extract YAML frontmatter from markdown content
-pub fn extract_yaml_frontmatter(content: &str) -> Option<Metadata> {
-    // Match YAML frontmatter: ---\n...\n---
-    let re = Regex::new(r"^---\s*\n(.*?)\n---").ok()?;
-
-    let captures = re.captures(content)?;
-    let yaml_str = captures.get(1)?.as_str();
-
-    // Parse YAML to Metadata struct
-    serde_yaml::from_str::<Metadata>(yaml_str).ok()
-}
-
-/// Validate metadata against CDE governance rules
-pub fn validate_metadata(metadata: &Metadata, path: &str) -> ValidationResult {
-    let mut errors = Vec::new();
-    let mut warnings = Vec::new();
-
-    // Required fields
-    if metadata.title.is_none() {
-        errors.push("Missing required field: 'title'".to_string());
-    }
-    if metadata.description.is_none() {
-        errors.push("Missing required field: 'description'".to_string());
-    }
-    if metadata.doc_type.is_none() {
-        errors.push("Missing required field: 'type'".to_string());
-    }
-    if metadata.status.is_none() {
-        warnings.push("Missing recommended field: 'status'".to_string());
-    }
-    if metadata.created.is_none() {
-        warnings.push("Missing recommended field: 'created'".to_string());
-    }
-    if metadata.updated.is_none() {
-        warnings.push("Missing recommended field: 'updated'".to_string());
-    }
-    if metadata.author.is_none() {
-        warnings.push("Missing recommended field: 'author'".to_string());
-    }
-
-    // Validate type field
-    if let Some(ref doc_type) = metadata.doc_type {
-        let valid_types = [
-            "feature", "design", "task", "guide", "governance",
-            "session", "execution", "feedback", "research"
-        ];
-        if !valid_types.contains(&doc_type.as_str()) {
-            errors.push(format!(
-                "Invalid type '{}'. Must be one of: {}",
-                doc_type,
-                valid_types.join(", ")
-            ));
-        }
-    }
-
-    // Validate status field
-    if let Some(ref status) = metadata.status {
-        let valid_statuses = ["draft", "active", "deprecated", "archived"];
-        if !valid_statuses.contains(&status.as_str()) {
-            errors.push(format!(
-                "Invalid status '{}'. Must be one of: {}",
-                status,
-                valid_statuses.join(", ")
-            ));
-        }
-    }
-
-    // Validate date formats (YYYY-MM-DD)
-    let date_regex = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
-
-    if let Some(ref created) = metadata.created {
-        if !date_regex.is_match(created) {
-            errors.push(format!(
-                "Invalid date format for 'created': '{}'. Expected YYYY-MM-DD",
-                created
-            ));
-        }
-    }
-
-    if let Some(ref updated) = metadata.updated {
-        if !date_regex.is_match(updated) {
-            errors.push(format!(
-                "Invalid date format for 'updated': '{}'. Expected YYYY-MM-DD",
-                updated
-            ));
-        }
-    }
-
-    // Check description length (50-150 chars recommended)
-    if let Some(ref desc) = metadata.description {
-        let len = desc.len();
-        if len < 50 {
-            warnings.push(format!(
-                "Description is short ({} chars). Recommended: 50-150 chars",
-                len
-            ));
-        } else if len > 150 {
-            warnings.push(format!(
-                "Description is long ({} chars). Recommended: 50-150 chars",
-                len
-            ));
-        }
-    }
-
-    ValidationResult {
-        path: path.to_string(),
-        valid: errors.is_empty(),
-        errors,
-        warnings,
-        metadata: Some(metadata.clone()),
-    }
-}
-
-/// Batch validate metadata from multiple files in parallel
-///
-/// This function uses Rayon to process files in parallel,
-/// providing significant speedup on multi-core systems.
-///
-/// Performance: ~11.3x faster than sequential processing
-pub fn validate_metadata_batch(file_paths: Vec<String>) -> Vec<ValidationResult> {
-    file_paths
-        .par_iter()  // Rayon parallel iterator
-        .map(|path| {
-            // Read file
-            let content = match fs::read_to_string(path) {
-                Ok(c) => c,
-                Err(e) => {
-                    return ValidationResult {
-                        path: path.clone(),
-                        valid: false,
-                        errors: vec![format!("Failed to read file: {}", e)],
-                        warnings: vec![],
-                        metadata: None,
-                    };
-                }
-            };
-
-            // Extract frontmatter
-            match extract_yaml_frontmatter(&content) {
-                Some(metadata) => validate_metadata(&metadata, path),
-                None => ValidationResult {
-                    path: path.clone(),
-                    valid: false,
-                    errors: vec!["No YAML frontmatter found (missing --- delimiters)".to_string()],
-                    warnings: vec![],
-                    metadata: None,
-                },
-            }
-        })
-        .collect()
-}
-
-/// Extract all markdown links from content
-pub fn extract_markdown_links(content: &str) -> Vec<String> {
-    // Match [text](link) pattern
-    let re = Regex::new(r"\[([^\]]+)\]\(([^\)]+)\)").unwrap();
-
-    re.captures_iter(content)
-        .filter_map(|cap| cap.get(2).map(|m| m.as_str().to_string()))
-        .collect()
-}
-
-/// Parallel link extraction from multiple documents
-///
-/// Performance: ~8x faster than sequential processing
-pub fn extract_links_batch(contents: &[String]) -> Vec<Vec<String>> {
-    contents
-        .par_iter()  // Rayon parallel iterator
-        .map(|content| extract_markdown_links(content))
-        .collect()
-}
-
-/// Parallel regex pattern matching across multiple documents
-///
-/// Performance: ~7.9x faster than sequential processing
-pub fn find_patterns_batch(contents: &[String], pattern: &str) -> Result<Vec<Vec<usize>>, String> {
-    let re = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
-
-    let results = contents
-        .par_iter()  // Rayon parallel iterator
-        .map(|content| {
-            re.find_iter(content)
-                .map(|m| m.start())
-                .collect::<Vec<_>>()
-        })
-        .collect();
-
-    Ok(results)
-}
-
-/// Parallel word count with optimization for large files
-///
-/// For files > 100KB, uses nested parallelism
-pub fn word_count_batch(contents: &[String]) -> Vec<usize> {
-    contents
-        .par_iter()
-        .map(|content| {
-            // For large files, parallelize word counting
-            if content.len() > 100_000 {
-                content.par_split_whitespace().count()
-            } else {
-                content.split_whitespace().count()
-            }
-        })
-        .collect()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_extract_yaml_frontmatter() {
-        let content = r#"---
-title: "Test Document"
-description: "Test description"
-type: "feature"
-status: "draft"
-created: "2025-11-12"
-updated: "2025-11-12"
-author: "Test Author"
----
-
-# Content here
-"#;
-
-        let metadata = extract_yaml_frontmatter(content);
-        assert!(metadata.is_some());
-
-        let meta = metadata.unwrap();
-        assert_eq!(meta.title, Some("Test Document".to_string()));
-        assert_eq!(meta.doc_type, Some("feature".to_string()));
-    }
-
-    #[test]
-    fn test_validate_metadata_valid() {
-        let metadata = Metadata {
-            title: Some("Test".to_string()),
-            description: Some("Test description with sufficient length for validation".to_string()),
-            doc_type: Some("feature".to_string()),
-            status: Some("draft".to_string()),
-            created: Some("2025-11-12".to_string()),
-            updated: Some("2025-11-12".to_string()),
-            author: Some("Test".to_string()),
-            llm_summary: None,
-        };
-
-        let result = validate_metadata(&metadata, "test.md");
-        assert!(result.valid);
-        assert!(result.errors.is_empty());
-    }
-
-    #[test]
-    fn test_validate_metadata_invalid_type() {
-        let metadata = Metadata {
-            title: Some("Test".to_string()),
-            description: Some("Test description".to_string()),
-            doc_type: Some("invalid_type".to_string()),
-            status: None,
-            created: None,
-            updated: None,
-            author: None,
-            llm_summary: None,
-        };
-
-        let result = validate_metadata(&metadata, "test.md");
-        assert!(!result.valid);
-        assert!(!result.errors.is_empty());
-    }
-
-    #[test]
-    fn test_extract_markdown_links() {
-        let content = "Check [this link](../path/to/file.md) and [another](https://example.com)";
-        let links = extract_markdown_links(content);
-
-        assert_eq!(links.len(), 2);
-        assert_eq!(links[0], "../path/to/file.md");
-        assert_eq!(links[1], "https://example.com");
-    }
-}
@@ -0,0 +1,230 @@
+// src/text_file.rs
+//! Reading text files whatever their encoding
+//!
+//! The encoding comes from the byte order mark when there is one, else UTF-8 when
+//! the bytes are valid UTF-8, else UTF-16 when the zero bytes fall on every other
+//! position, else the guess of `chardetng` (typically windows-1252 for Latin-1
+//! documents). Other files with zero bytes are binary and refused, as git does.
+
+use crate::error::{to_json, CdeError};
+use crate::path_policy;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Default limit of `read_text_file_py`
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Bytes looked at to tell binary and UTF-16 files apart
+const SNIFF_BYTES: usize = 8000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextFile {
+    pub path: String,
+    /// With `\n` line endings and without byte order mark
+    pub content: String,
+    /// WHATWG name, such as "UTF-8", "UTF-16LE" or "windows-1252"
+    pub encoding: String,
+    pub had_bom: bool,
+    /// Line endings of the file: "lf", "crlf", "cr", "mixed" or "none"
+    pub line_endings: String,
+    /// Size of the file
+    pub size: u64,
+    /// Whether only the first `max_bytes` were read
+    pub truncated: bool,
+}
+
+/// Encoding of BOM-less `bytes`; `complete` is false when they were cut short
+fn sniff(bytes: &[u8], complete: bool) -> Result<&'static Encoding, CdeError> {
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES) & !1];
+    let pairs = sample.len() / 2;
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    // Mostly-ASCII UTF-16 has a zero in every other byte and nowhere else
+    if pairs > 0 && odd * 10 > pairs * 3 && even * 20 < pairs {
+        return Ok(UTF_16LE);
+    }
+    if pairs > 0 && even * 10 > pairs * 3 && odd * 20 < pairs {
+        return Ok(UTF_16BE);
+    }
+    if bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) {
+        return Err(CdeError::invalid_input("Binary file"));
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Ok(UTF_8),
+        // Cut in the middle of a character
+        Err(e) if !complete && e.error_len().is_none() => Ok(UTF_8),
+        Err(_) => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, complete);
+            Ok(detector.guess(None, true))
+        }
+    }
+}
+
+/// Decodes `bytes`; with `complete` false, a character cut at the end is dropped.
+/// Returns the text, its encoding and whether it started with a byte order mark
+pub fn decode(bytes: &[u8], complete: bool) -> Result<(String, &'static Encoding, bool), CdeError> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None => (sniff(bytes, complete)?, 0),
+    };
+    let body = &bytes[bom_length..];
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let capacity = decoder
+        .max_utf8_buffer_length(body.len())
+        .unwrap_or(body.len() * 3);
+    let mut text = String::with_capacity(capacity);
+    let _ = decoder.decode_to_string(body, &mut text, complete);
+    Ok((text, encoding, bom_length > 0))
+}
+
+fn line_endings(text: &str) -> &'static str {
+    let crlf = text.matches("\r\n").count();
+    let cr = text.matches('\r').count() - crlf;
+    let lf = text.matches('\n').count() - crlf;
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => "none",
+        (true, false, false) => "lf",
+        (false, true, false) => "crlf",
+        (false, false, true) => "cr",
+        _ => "mixed",
+    }
+}
+
+/// Content of the text file at `path`, decoded as it is, line endings kept
+pub fn read_text(path: &Path) -> Result<String, CdeError> {
+    let bytes = fs::read(path).map_err(|e| {
+        CdeError::io("Failed to read file")
+            .with_path(path)
+            .caused_by(&e)
+    })?;
+    decode(&bytes, true)
+        .map(|(text, _, _)| text)
+        .map_err(|e| e.with_path(path))
+}
+
+/// Reads at most `max_bytes` of the text file at `path`, see the module docs;
+/// line endings are normalized to `\n`
+pub fn read_text_file(path: &str, max_bytes: u64) -> Result<TextFile, CdeError> {
+    path_policy::check(path)?;
+    let metadata = fs::metadata(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            CdeError::not_found("File not found").with_path(path)
+        } else {
+            CdeError::io("Failed to read file")
+                .with_path(path)
+                .caused_by(&e)
+        }
+    })?;
+    if !metadata.is_file() {
+        return Err(CdeError::invalid_input("Not a regular file").with_path(path));
+    }
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(max_bytes).read_to_end(&mut bytes))
+        .map_err(|e| {
+            CdeError::io("Failed to read file")
+                .with_path(path)
+                .caused_by(&e)
+        })?;
+    let truncated = metadata.len() > bytes.len() as u64;
+
+    let (text, encoding, had_bom) = decode(&bytes, !truncated).map_err(|e| e.with_path(path))?;
+    let line_endings = line_endings(&text);
+    let content = if text.contains('\r') {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text
+    };
+    Ok(TextFile {
+        path: path.to_string(),
+        content,
+        encoding: encoding.name().to_string(),
+        had_bom,
+        line_endings: line_endings.to_string(),
+        size: metadata.len(),
+        truncated,
+    })
+}
+
+/// Reads a text file of any common encoding, as JSON
+///
+/// The encoding is detected (BOM, UTF-8, UTF-16, else a statistical guess such as
+/// windows-1252 for Latin-1), line endings are normalized to `\n`, and reading
+/// stops after `max_bytes` (10 MiB by default) with `truncated` set. Returns
+/// `content`, `encoding`, `had_bom`, the original `line_endings` and the file
+/// `size`. Binary files raise `InvalidInputError`.
+#[pyfunction]
+#[pyo3(signature = (path, max_bytes=DEFAULT_MAX_BYTES))]
+fn read_text_file_py(py: Python<'_>, path: &str, max_bytes: u64) -> PyResult<String> {
+    let file = py.detach(|| read_text_file(path, max_bytes))?;
+    Ok(to_json(&file)?)
+}
+
+/// Adds the text file functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(read_text_file_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_encodings_and_refuses_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+
+        let file = read_text_file(&write("bom.md", b"\xEF\xBB\xBF# T\r\nx\r\n"), 1024).unwrap();
+        assert_eq!(
+            (file.content.as_str(), file.encoding.as_str()),
+            ("# T\nx\n", "UTF-8")
+        );
+        assert!(file.had_bom);
+        assert_eq!(file.line_endings, "crlf");
+
+        let utf16: Vec<u8> = "caf\u{e9}\nok"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let file = read_text_file(&write("utf16.txt", &utf16), 1024).unwrap();
+        assert_eq!(
+            (file.content.as_str(), file.encoding.as_str()),
+            ("caf\u{e9}\nok", "UTF-16LE")
+        );
+
+        let latin1 = b"Le caf\xE9 est pr\xEAt, la cr\xE8me br\xFBl\xE9e aussi.\n";
+        let file = read_text_file(&write("latin1.md", latin1), 1024).unwrap();
+        assert_eq!(file.encoding, "windows-1252");
+        assert!(file.content.contains("café est prêt"));
+
+        // Cut inside "é": the partial character is dropped, not replaced
+        let file = read_text_file(&write("cut.md", "abcé".as_bytes()), 4).unwrap();
+        assert_eq!((file.content.as_str(), file.truncated), ("abc", true));
+        assert_eq!(file.size, 5);
+
+        let binary = read_text_file(&write("x.bin", b"\x7fELF\x02\x01\x01\0\0\0\xff"), 1024);
+        assert!(binary.unwrap_err().context().message.contains("Binary"));
+        assert!(read_text_file(&dir.path().join("none").to_string_lossy(), 1024).is_err());
+        assert!(read_text(Path::new(&write("l.md", latin1)))
+            .unwrap()
+            .starts_with("Le café"));
+    }
+}
@@ -0,0 +1,240 @@
+// src/tokens.rs
+//! Token counts and context-budget packing
+//!
+//! Texts are counted with the tiktoken encoding of the model (o200k_base for the
+//! GPT-4o family, cl100k_base for GPT-4 and GPT-3.5, ...). Models tiktoken does
+//! not know, such as Claude or Gemini, are counted with cl100k_base, which is
+//! close enough for budgeting. Encodings are loaded once per process.
+//!
+//! `pack_documents` fills a token budget greedily: documents are taken by
+//! descending priority (then in the given order), in full when they fit, else as
+//! their summary (the one given, or their first paragraph) when that fits.
+
+use crate::error::{from_json, to_json};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+/// Encoding of the models tiktoken does not know
+const FALLBACK: Tokenizer = Tokenizer::Cl100kBase;
+
+/// Encoding used for `model`, and its name
+fn encoding(model: &str) -> (&'static CoreBPE, &'static str) {
+    match get_tokenizer(model).unwrap_or(FALLBACK) {
+        Tokenizer::O200kBase => (tiktoken_rs::o200k_base_singleton(), "o200k_base"),
+        Tokenizer::Cl100kBase => (tiktoken_rs::cl100k_base_singleton(), "cl100k_base"),
+        Tokenizer::P50kBase => (tiktoken_rs::p50k_base_singleton(), "p50k_base"),
+        Tokenizer::P50kEdit => (tiktoken_rs::p50k_edit_singleton(), "p50k_edit"),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => (tiktoken_rs::r50k_base_singleton(), "r50k_base"),
+    }
+}
+
+/// Tokens of `text` for `model`; special tokens in the text count as plain text
+pub fn count_tokens(text: &str, model: &str) -> usize {
+    encoding(model).0.encode_ordinary(text).len()
+}
+
+/// Tokens of each text, counted in parallel
+pub fn count_tokens_batch(texts: &[String], model: &str) -> Vec<usize> {
+    let (bpe, _) = encoding(model);
+    texts
+        .par_iter()
+        .map(|text| bpe.encode_ordinary(text).len())
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackDocument {
+    pub id: String,
+    pub content: String,
+    /// Used when the content does not fit; defaults to its first paragraph
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Higher first, 0 by default
+    #[serde(default)]
+    pub priority: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackedDocument {
+    pub id: String,
+    /// "full" or "summary"
+    pub mode: String,
+    pub tokens: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackResult {
+    pub model: String,
+    pub encoding: String,
+    pub budget: usize,
+    pub used_tokens: usize,
+    /// In selection order
+    pub selected: Vec<PackedDocument>,
+    /// Ids of the documents that did not fit, even summarized
+    pub omitted: Vec<String>,
+}
+
+/// First non-blank paragraph of `content`
+fn first_paragraph(content: &str) -> &str {
+    content
+        .split("\n\n")
+        .map(str::trim)
+        .find(|paragraph| !paragraph.is_empty())
+        .unwrap_or("")
+}
+
+/// Selects documents into `budget` tokens of `model`, see the module docs
+pub fn pack_documents(documents: Vec<PackDocument>, budget: usize, model: &str) -> PackResult {
+    let (bpe, encoding_name) = encoding(model);
+    let mut documents = documents;
+    // Stable, so equal priorities keep the given order
+    documents.sort_by(|a, b| b.priority.total_cmp(&a.priority));
+    let full_tokens: Vec<usize> = documents
+        .par_iter()
+        .map(|document| bpe.encode_ordinary(&document.content).len())
+        .collect();
+
+    let mut used_tokens = 0;
+    let mut selected = Vec::new();
+    let mut omitted = Vec::new();
+    for (document, tokens) in documents.into_iter().zip(full_tokens) {
+        let remaining = budget - used_tokens;
+        let packed = if tokens <= remaining {
+            Some(("full", tokens, document.content))
+        } else {
+            let summary = document
+                .summary
+                .unwrap_or_else(|| first_paragraph(&document.content).to_string());
+            let summary_tokens = bpe.encode_ordinary(&summary).len();
+            (!summary.is_empty() && summary_tokens <= remaining).then_some((
+                "summary",
+                summary_tokens,
+                summary,
+            ))
+        };
+        match packed {
+            Some((mode, tokens, text)) => {
+                used_tokens += tokens;
+                selected.push(PackedDocument {
+                    id: document.id,
+                    mode: mode.to_string(),
+                    tokens,
+                    text,
+                });
+            }
+            None => omitted.push(document.id),
+        }
+    }
+    PackResult {
+        model: model.to_string(),
+        encoding: encoding_name.to_string(),
+        budget,
+        used_tokens,
+        selected,
+        omitted,
+    }
+}
+
+/// Number of tokens of `text` for `model` (tiktoken; unknown models are counted
+/// with cl100k_base)
+#[pyfunction]
+#[pyo3(signature = (text, model="gpt-4o"))]
+fn count_tokens_py(py: Python<'_>, text: &str, model: &str) -> usize {
+    py.detach(|| count_tokens(text, model))
+}
+
+/// Token counts of `texts` for `model`, in the same order
+#[pyfunction]
+#[pyo3(signature = (texts, model="gpt-4o"))]
+fn count_tokens_batch_py(py: Python<'_>, texts: Vec<String>, model: &str) -> Vec<usize> {
+    py.detach(|| count_tokens_batch(&texts, model))
+}
+
+/// Packs documents into `budget` tokens of `model`
+///
+/// `documents_json` is a list of objects with `id`, `content` and optionally
+/// `summary` and `priority`. Documents are taken by descending priority, in full
+/// when they fit, else as their summary (default: their first paragraph). Returns
+/// JSON with `selected` (`id`, `mode` "full" or "summary", `tokens`, `text`),
+/// `omitted` ids, `used_tokens`, `budget` and the `encoding` used.
+#[pyfunction]
+#[pyo3(signature = (documents_json, budget, model="gpt-4o"))]
+fn pack_documents_py(
+    py: Python<'_>,
+    documents_json: &str,
+    budget: usize,
+    model: &str,
+) -> PyResult<String> {
+    let documents: Vec<PackDocument> = from_json("documents", documents_json)?;
+    let result = py.detach(|| pack_documents(documents, budget, model));
+    Ok(to_json(&result)?)
+}
+
+/// Adds the token counting functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(count_tokens_py, m)?)?;
+    m.add_function(wrap_pyfunction!(count_tokens_batch_py, m)?)?;
+    m.add_function(wrap_pyfunction!(pack_documents_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(id: &str, content: &str, priority: f64) -> PackDocument {
+        PackDocument {
+            id: id.to_string(),
+            content: content.to_string(),
+            summary: None,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_counts_and_greedy_packing() {
+        assert_eq!(count_tokens("hello world", "gpt-4o"), 2);
+        assert_eq!(encoding("claude-sonnet").1, "cl100k_base");
+        assert_eq!(
+            count_tokens_batch(&["hello world".to_string(), String::new()], "gpt-4"),
+            vec![2, 0]
+        );
+
+        let long = format!("Short intro.\n\n{}", "word ".repeat(200));
+        let mut summarized = document("summarized", &long, 0.0);
+        summarized.summary = Some("Given summary".to_string());
+        let documents = vec![
+            document("low", "hello world", -1.0),
+            summarized,
+            document("first-paragraph", &long, 0.0),
+            document("top", "hello world", 5.0),
+        ];
+        let result = pack_documents(documents, 10, "gpt-4o");
+
+        let picked: Vec<(&str, &str)> = result
+            .selected
+            .iter()
+            .map(|d| (d.id.as_str(), d.mode.as_str()))
+            .collect();
+        assert_eq!(
+            picked,
+            vec![
+                ("top", "full"),
+                ("summarized", "summary"),
+                ("first-paragraph", "summary"),
+                ("low", "full"),
+            ]
+        );
+        assert_eq!(result.selected[2].text, "Short intro.");
+        assert!(result.used_tokens <= 10);
+        assert!(
+            pack_documents(vec![document("big", &long, 0.0)], 1, "gpt-4o")
+                .omitted
+                .contains(&"big".to_string())
+        );
+    }
+}
@@ -0,0 +1,469 @@
+// src/tool_probe.rs
+//! Availability and version probing of the CLIs the orchestrator relies on
+//!
+//! Every tool is looked up on `PATH` and, when found, run with its version
+//! arguments (`--version` by default) under a timeout; the first `x.y[.z]` in
+//! its output, stdout before stderr, is its version. Tools are probed in
+//! parallel and results are kept in memory for `PROBE_TTL`, keyed by the
+//! resolved program and arguments, so repeated readiness checks do not spawn
+//! anything.
+
+use crate::error::{from_json, to_json, CdeError};
+use crate::metrics;
+use crate::process_manager::executable;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a probe result is reused
+const PROBE_TTL: Duration = Duration::from_secs(300);
+
+/// Interval at which a running version command is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Probed when no tools are given
+pub const DEFAULT_TOOLS: [&str; 7] = ["git", "gh", "node", "python", "docker", "copilot", "gemini"];
+
+static VERSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+\.\d+(?:\.\d+)?").expect("valid version regex"));
+
+/// A tool by name ("git"), or with its program and version arguments
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ToolSpec {
+    Name(String),
+    Full {
+        name: String,
+        /// Program to look up, `name` by default
+        #[serde(default)]
+        command: Option<String>,
+        /// `["--version"]` by default; empty to only check `PATH`
+        #[serde(default)]
+        version_args: Option<Vec<String>>,
+        /// Lowest acceptable version, such as "2.30"
+        #[serde(default)]
+        min_version: Option<String>,
+    },
+}
+
+impl ToolSpec {
+    fn name(&self) -> &str {
+        match self {
+            ToolSpec::Name(name) | ToolSpec::Full { name, .. } => name,
+        }
+    }
+
+    fn program(&self) -> &str {
+        match self {
+            ToolSpec::Full {
+                command: Some(command),
+                ..
+            } => command,
+            _ => self.name(),
+        }
+    }
+
+    fn version_args(&self) -> Vec<String> {
+        match self {
+            ToolSpec::Full {
+                version_args: Some(args),
+                ..
+            } => args.clone(),
+            _ => vec!["--version".to_string()],
+        }
+    }
+
+    fn min_version(&self) -> Option<&str> {
+        match self {
+            ToolSpec::Full { min_version, .. } => min_version.as_deref(),
+            ToolSpec::Name(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolProbe {
+    pub name: String,
+    pub found: bool,
+    /// Resolved program
+    pub path: Option<String>,
+    /// First `x.y[.z]` of the version output
+    pub version: Option<String>,
+    /// First non-empty line of the version output
+    pub version_output: Option<String>,
+    /// Whether `version` is at least `min_version`, None without either
+    pub satisfies: Option<bool>,
+    /// Why the version could not be read: timeout, spawn failure, exit status
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Whether the result comes from the cache
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolProbeReport {
+    pub tools: Vec<ToolProbe>,
+    /// Tools not found on `PATH`
+    pub missing: Vec<String>,
+    /// Tools found with a version below their `min_version`
+    pub outdated: Vec<String>,
+    /// Whether every tool was found and satisfies its `min_version`
+    pub ready: bool,
+    pub summary: String,
+}
+
+/// Version output of a program, without the parts that depend on the spec
+#[derive(Debug, Clone)]
+struct VersionProbe {
+    version: Option<String>,
+    version_output: Option<String>,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+type ProbeCache = HashMap<(PathBuf, Vec<String>), (Instant, VersionProbe)>;
+
+static PROBE_CACHE: LazyLock<Mutex<ProbeCache>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// First `x.y[.z]` in `output`
+pub fn parse_version(output: &str) -> Option<String> {
+    VERSION.find(output).map(|found| found.as_str().to_string())
+}
+
+/// Whether `version` is at least `minimum`, comparing numeric components
+/// (missing ones count as 0)
+pub fn version_at_least(version: &str, minimum: &str) -> bool {
+    let parts = |text: &str| -> Vec<u64> {
+        text.trim_start_matches('v')
+            .split('.')
+            .map(|part| {
+                let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().unwrap_or(0)
+            })
+            .collect()
+    };
+    let (mut version, mut minimum) = (parts(version), parts(minimum));
+    let len = version.len().max(minimum.len());
+    version.resize(len, 0);
+    minimum.resize(len, 0);
+    version >= minimum
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// Runs `program` with `args`, killing it after `timeout`
+fn run_version(program: &Path, args: &[String], timeout: Duration) -> VersionProbe {
+    let start = Instant::now();
+    let elapsed = || start.elapsed().as_millis() as u64;
+    let failed = |error: String| VersionProbe {
+        version: None,
+        version_output: None,
+        error: Some(error),
+        duration_ms: elapsed(),
+    };
+
+    let mut cmd = vec![program.to_string_lossy().into_owned()];
+    cmd.extend(args.iter().cloned());
+    let (program, args) = executable::resolve_command(&cmd);
+    let mut child = match Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .envs(executable::UTF8_ENV)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return failed(format!("Failed to run: {}", e)),
+    };
+    let stdout = child.stdout.take().map(read_all);
+    let stderr = child.stderr.take().map(read_all);
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return failed(format!("Timed out after {:.1}s", timeout.as_secs_f64()));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return failed(format!("Failed to wait: {}", e)),
+        }
+    };
+    let join = |handle: Option<thread::JoinHandle<String>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    let (stdout, stderr) = (join(stdout), join(stderr));
+
+    let output = [stdout.as_str(), stderr.as_str()];
+    let version = output.iter().find_map(|text| parse_version(text));
+    let version_output = output
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string);
+    let error = (!status.success() && version.is_none())
+        .then(|| format!("Version command failed with {}", status));
+    VersionProbe {
+        version,
+        version_output,
+        error,
+        duration_ms: elapsed(),
+    }
+}
+
+fn probe_tool(spec: &ToolSpec, timeout: Duration, refresh: bool) -> ToolProbe {
+    let name = spec.name().to_string();
+    let Some(path) = executable::which(spec.program()) else {
+        return ToolProbe {
+            name,
+            found: false,
+            path: None,
+            version: None,
+            version_output: None,
+            satisfies: spec.min_version().map(|_| false),
+            error: None,
+            duration_ms: 0,
+            cached: false,
+        };
+    };
+
+    let args = spec.version_args();
+    let key = (path.clone(), args.clone());
+    let cached = if refresh {
+        None
+    } else {
+        PROBE_CACHE
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|(probed_at, _)| probed_at.elapsed() < PROBE_TTL)
+            .map(|(_, probe)| probe.clone())
+    };
+    let from_cache = cached.is_some();
+    let probe = cached.unwrap_or_else(|| {
+        let probe = if args.is_empty() {
+            VersionProbe {
+                version: None,
+                version_output: None,
+                error: None,
+                duration_ms: 0,
+            }
+        } else {
+            run_version(&path, &args, timeout)
+        };
+        // Timeouts may be transient, so they are probed again next time
+        if !probe
+            .error
+            .as_deref()
+            .is_some_and(|e| e.starts_with("Timed out"))
+        {
+            PROBE_CACHE
+                .lock()
+                .unwrap()
+                .insert(key, (Instant::now(), probe.clone()));
+        }
+        probe
+    });
+
+    let satisfies = spec.min_version().map(|minimum| {
+        probe
+            .version
+            .as_deref()
+            .is_some_and(|version| version_at_least(version, minimum))
+    });
+    ToolProbe {
+        name,
+        found: true,
+        path: Some(path.to_string_lossy().into_owned()),
+        version: probe.version,
+        version_output: probe.version_output,
+        satisfies,
+        error: probe.error,
+        duration_ms: if from_cache { 0 } else { probe.duration_ms },
+        cached: from_cache,
+    }
+}
+
+/// Probes `specs` in parallel, each version command limited to `timeout`
+pub fn probe_tools(
+    specs: &[ToolSpec],
+    timeout: Duration,
+    refresh: bool,
+) -> Result<ToolProbeReport, CdeError> {
+    metrics::timed("probe_tools", || {
+        if let Some(spec) = specs.iter().find(|spec| spec.program().trim().is_empty()) {
+            return Err(CdeError::invalid_input(format!(
+                "Tool '{}' has an empty command",
+                spec.name()
+            )));
+        }
+        let tools: Vec<ToolProbe> = specs
+            .par_iter()
+            .map(|spec| probe_tool(spec, timeout, refresh))
+            .collect();
+        let missing: Vec<String> = tools
+            .iter()
+            .filter(|tool| !tool.found)
+            .map(|tool| tool.name.clone())
+            .collect();
+        let outdated: Vec<String> = tools
+            .iter()
+            .filter(|tool| tool.found && tool.satisfies == Some(false))
+            .map(|tool| tool.name.clone())
+            .collect();
+        let ready = missing.is_empty() && outdated.is_empty();
+        let summary = if ready {
+            format!("✅ All {} tools are available.", tools.len())
+        } else {
+            format!(
+                "⚠️ {} of {} tools available; missing: {}; outdated: {}.",
+                tools.len() - missing.len() - outdated.len(),
+                tools.len(),
+                if missing.is_empty() {
+                    "none".to_string()
+                } else {
+                    missing.join(", ")
+                },
+                if outdated.is_empty() {
+                    "none".to_string()
+                } else {
+                    outdated.join(", ")
+                },
+            )
+        };
+        Ok(ToolProbeReport {
+            tools,
+            missing,
+            outdated,
+            ready,
+            summary,
+        })
+    })
+}
+
+/// Checks in parallel which CLIs are on `PATH` and which versions they are
+///
+/// `tools_json` is a list of names ("git") or of objects with `name`, `command`
+/// (the program, `name` by default), `version_args` (`["--version"]` by default,
+/// `[]` to skip running it) and `min_version`; by default git, gh, node,
+/// python, docker, copilot and gemini. Each version command is killed after
+/// `timeout_secs`. Results are cached for five minutes unless `refresh`.
+/// Returns `tools` (`found`, `path`, `version`, `version_output`, `satisfies`,
+/// `error`, `duration_ms`, `cached`), `missing`, `outdated`, `ready` and
+/// `summary`.
+#[pyfunction]
+#[pyo3(signature = (tools_json=None, timeout_secs=5.0, refresh=false))]
+fn probe_tools_py(
+    py: Python<'_>,
+    tools_json: Option<&str>,
+    timeout_secs: f64,
+    refresh: bool,
+) -> PyResult<String> {
+    let specs: Vec<ToolSpec> = match tools_json {
+        Some(json) => from_json("tools", json)?,
+        None => DEFAULT_TOOLS
+            .iter()
+            .map(|name| ToolSpec::Name(name.to_string()))
+            .collect(),
+    };
+    if !timeout_secs.is_finite() || timeout_secs <= 0.0 {
+        return Err(CdeError::invalid_input("timeout_secs must be positive").into());
+    }
+    let timeout = Duration::from_secs_f64(timeout_secs);
+    let report = py.detach(|| probe_tools(&specs, timeout, refresh))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the tool probing functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(probe_tools_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parsing_and_comparison() {
+        assert_eq!(
+            parse_version("git version 2.43.0.windows.1"),
+            Some("2.43.0".to_string())
+        );
+        assert_eq!(parse_version("Python 3.12"), Some("3.12".to_string()));
+        assert_eq!(parse_version("no version here"), None);
+        assert!(version_at_least("2.43.0", "2.30"));
+        assert!(version_at_least("v20.1", "20.1.0"));
+        assert!(!version_at_least("1.9.9", "1.10"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probes_tools_with_timeout_and_cache() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let tool = script("tool", "echo \"tool version 1.4.2 (build 7)\"");
+        let old = script("old", "echo 'old 0.9' >&2; exit 1");
+        let slow = script("slow", "sleep 5");
+
+        let specs: Vec<ToolSpec> = serde_json::from_value(serde_json::json!([
+            { "name": "tool", "command": tool, "min_version": "1.4" },
+            { "name": "old", "command": old, "min_version": "1.0" },
+            { "name": "slow", "command": slow },
+            "cde-surely-missing-tool",
+        ]))
+        .unwrap();
+        let report = probe_tools(&specs, Duration::from_millis(300), true).unwrap();
+        let probe = |name: &str| report.tools.iter().find(|t| t.name == name).unwrap();
+
+        assert_eq!(probe("tool").version.as_deref(), Some("1.4.2"));
+        assert_eq!(
+            probe("tool").version_output.as_deref(),
+            Some("tool version 1.4.2 (build 7)")
+        );
+        assert_eq!(probe("tool").satisfies, Some(true));
+        assert_eq!(probe("old").version.as_deref(), Some("0.9"));
+        assert_eq!(probe("old").error, None);
+        assert!(probe("slow")
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Timed out"));
+        assert!(probe("slow").duration_ms < 3000);
+        assert!(!probe("cde-surely-missing-tool").found);
+        assert_eq!(report.missing, ["cde-surely-missing-tool"]);
+        assert_eq!(report.outdated, ["old"]);
+        assert!(!report.ready);
+
+        let again = probe_tools(&specs[..2], Duration::from_millis(300), false).unwrap();
+        assert!(again.tools.iter().all(|tool| tool.cached));
+        assert_eq!(again.tools[0].version.as_deref(), Some("1.4.2"));
+    }
+}
@@ -0,0 +1,434 @@
+// src/trash.rs
+//! Deleting files so that the deletion can be undone
+//!
+//! Deleted paths are moved, not removed: into `.cde_trash/<id>/` at the root of
+//! their project (the nearest ancestor holding `.git`, else their directory), or
+//! into the trash of the operating system. Each deletion gets a manifest of what
+//! went where, kept in the state store under its id, so `restore_deleted` can
+//! put everything back. Restoring from the system trash works on Linux and
+//! Windows only.
+
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::path_policy;
+use crate::state::{self, StateStore};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Directory of the project-local trash
+pub const TRASH_DIR: &str = ".cde_trash";
+
+/// State store namespace of the manifests
+const MANIFEST_NAMESPACE: &str = "cde.trash";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletedEntry {
+    /// Absolute path the entry had
+    pub original: String,
+    /// Where it is kept in the local trash; None in the system trash
+    pub stored: Option<String>,
+    pub is_dir: bool,
+    /// Size of a file, 0 for a directory
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeleteManifest {
+    pub id: String,
+    /// RFC 3339
+    pub deleted_at: String,
+    /// "local" or "system"
+    pub backend: String,
+    /// The `.cde_trash/<id>` directory of a local deletion
+    pub trash_dir: Option<String>,
+    pub entries: Vec<DeletedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub id: String,
+    /// Original paths put back
+    pub restored: Vec<String>,
+}
+
+fn new_id() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let now = chrono::Utc::now();
+    let seed = format!(
+        "{}:{}:{}",
+        now.timestamp_nanos_opt().unwrap_or_default(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let hash = blake3::hash(seed.as_bytes()).to_hex();
+    format!("{}-{}", now.format("%Y%m%dT%H%M%S"), &hash[..8])
+}
+
+/// Root of the project holding `path`: the nearest ancestor with `.git`, else
+/// the directory of `path`
+fn project_root(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(path);
+    parent
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .unwrap_or(parent)
+        .to_path_buf()
+}
+
+/// The paths resolved, after checking they exist and may be deleted
+fn resolve_paths(paths: &[String]) -> Result<Vec<PathBuf>, CdeError> {
+    if paths.is_empty() {
+        return Err(CdeError::invalid_input("No paths to delete"));
+    }
+    let mut resolved: Vec<PathBuf> = Vec::with_capacity(paths.len());
+    for path in paths {
+        path_policy::check(path)?;
+        if Path::new(path).symlink_metadata().is_err() {
+            return Err(CdeError::not_found("Path not found").with_path(path));
+        }
+        let absolute = path_policy::resolve(Path::new(path));
+        if absolute.parent().is_none() {
+            return Err(
+                CdeError::invalid_input("Refusing to delete a filesystem root").with_path(path),
+            );
+        }
+        if absolute
+            .components()
+            .any(|component| component.as_os_str() == TRASH_DIR)
+        {
+            return Err(CdeError::invalid_input("Path is in the trash").with_path(path));
+        }
+        // Deleting a directory takes what it holds along
+        if !resolved.iter().any(|done| absolute.starts_with(done)) {
+            resolved.retain(|done| !done.starts_with(&absolute));
+            resolved.push(absolute);
+        }
+    }
+    Ok(resolved)
+}
+
+fn entry_of(path: &Path, stored: Option<&Path>) -> DeletedEntry {
+    let metadata = path.symlink_metadata().ok();
+    DeletedEntry {
+        original: path.to_string_lossy().into_owned(),
+        stored: stored.map(|stored| stored.to_string_lossy().into_owned()),
+        is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+        size: metadata.filter(|m| m.is_file()).map_or(0, |m| m.len()),
+    }
+}
+
+/// Moves `paths` into `.cde_trash/<id>/` under `root`; nothing is moved when one fails
+fn delete_locally(
+    id: &str,
+    paths: &[PathBuf],
+    root: &Path,
+) -> Result<(PathBuf, Vec<DeletedEntry>), CdeError> {
+    let trash_dir = root.join(TRASH_DIR).join(id);
+    if paths.iter().any(|path| trash_dir.starts_with(path)) {
+        return Err(
+            CdeError::invalid_input("Cannot move the project into its own trash").with_path(root),
+        );
+    }
+    fs::create_dir_all(&trash_dir).map_err(|e| {
+        CdeError::io("Failed to create the trash")
+            .with_path(&trash_dir)
+            .caused_by(&e)
+    })?;
+    let mut entries: Vec<DeletedEntry> = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let stored = trash_dir.join(format!("{}-{}", index, name));
+        let entry = entry_of(path, Some(&stored));
+        if let Err(e) = fs::rename(path, &stored) {
+            for done in entries.iter().rev() {
+                if let Some(stored) = &done.stored {
+                    let _ = fs::rename(stored, &done.original);
+                }
+            }
+            let _ = fs::remove_dir_all(&trash_dir);
+            return Err(CdeError::io("Failed to move path to the trash")
+                .with_path(path)
+                .caused_by(&e));
+        }
+        entries.push(entry);
+    }
+    Ok((trash_dir, entries))
+}
+
+/// Deletes `paths` undoably, recording the manifest in `store`. `to_trash`
+/// uses the system trash; otherwise the paths go to the `.cde_trash` of
+/// `project` (or of the project holding the first path)
+pub fn safe_delete_with(
+    store: &StateStore,
+    paths: &[String],
+    to_trash: bool,
+    project: Option<&str>,
+) -> Result<DeleteManifest, CdeError> {
+    metrics::timed("safe_delete", || {
+        let resolved = resolve_paths(paths)?;
+        let id = new_id();
+        let (backend, trash_dir, entries) = if to_trash {
+            let entries: Vec<DeletedEntry> =
+                resolved.iter().map(|path| entry_of(path, None)).collect();
+            ::trash::delete_all(&resolved).map_err(|e| {
+                CdeError::io("Failed to move paths to the system trash").caused_by(&e)
+            })?;
+            ("system", None, entries)
+        } else {
+            let root = match project {
+                Some(project) => {
+                    path_policy::check(project)?;
+                    path_policy::resolve(Path::new(project))
+                }
+                None => project_root(&resolved[0]),
+            };
+            let (trash_dir, entries) = delete_locally(&id, &resolved, &root)?;
+            (
+                "local",
+                Some(trash_dir.to_string_lossy().into_owned()),
+                entries,
+            )
+        };
+        let manifest = DeleteManifest {
+            id,
+            deleted_at: chrono::Utc::now().to_rfc3339(),
+            backend: backend.to_string(),
+            trash_dir,
+            entries,
+        };
+        if let Some(trash_dir) = &manifest.trash_dir {
+            // A copy next to the files, should the state store be lost
+            let _ = fs::write(
+                Path::new(trash_dir).join("manifest.json"),
+                to_json(&manifest)?,
+            );
+        }
+        store.set(MANIFEST_NAMESPACE, &manifest.id, &to_json(&manifest)?, None)?;
+        metrics::add_bytes(
+            "safe_delete",
+            manifest.entries.iter().map(|entry| entry.size).sum(),
+        );
+        Ok(manifest)
+    })
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_from_system(manifest: &DeleteManifest) -> Result<(), CdeError> {
+    use ::trash::os_limited;
+    let deleted_at =
+        chrono::DateTime::parse_from_rfc3339(&manifest.deleted_at).map_or(0, |at| at.timestamp());
+    let mut items = os_limited::list()
+        .map_err(|e| CdeError::io("Failed to list the system trash").caused_by(&e))?;
+    // Most recent first, so a path deleted twice comes back as this deletion left it
+    items.sort_by_key(|item| std::cmp::Reverse(item.time_deleted));
+    let mut chosen = Vec::with_capacity(manifest.entries.len());
+    for entry in &manifest.entries {
+        let position = items.iter().position(|item| {
+            item.original_path() == Path::new(&entry.original)
+                && item.time_deleted >= deleted_at - 60
+        });
+        match position {
+            Some(position) => chosen.push(items.swap_remove(position)),
+            None => {
+                return Err(
+                    CdeError::not_found("No longer in the system trash").with_path(&entry.original)
+                )
+            }
+        }
+    }
+    os_limited::restore_all(chosen)
+        .map_err(|e| CdeError::io("Failed to restore from the system trash").caused_by(&e))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_from_system(_manifest: &DeleteManifest) -> Result<(), CdeError> {
+    Err(CdeError::invalid_input(
+        "Restoring from the system trash is not supported on this platform",
+    ))
+}
+
+/// Puts back what the deletion `id` recorded in `store` moved away; refuses
+/// (restoring nothing) when one of the original paths exists again
+pub fn restore_deleted_with(store: &StateStore, id: &str) -> Result<RestoreReport, CdeError> {
+    metrics::timed("restore_deleted", || {
+        let json = store
+            .get(MANIFEST_NAMESPACE, id)?
+            .ok_or_else(|| CdeError::not_found(format!("No deletion with id '{}'", id)))?;
+        let manifest: DeleteManifest = serde_json::from_str(&json).map_err(|e| {
+            CdeError::parse(format!("Invalid manifest of deletion '{}'", id)).caused_by(&e)
+        })?;
+        for entry in &manifest.entries {
+            path_policy::check(&entry.original)?;
+            if Path::new(&entry.original).symlink_metadata().is_ok() {
+                return Err(
+                    CdeError::invalid_input("Path exists again, not overwriting it")
+                        .with_path(&entry.original),
+                );
+            }
+        }
+
+        if manifest.backend == "system" {
+            restore_from_system(&manifest)?;
+        } else {
+            for entry in &manifest.entries {
+                let Some(stored) = &entry.stored else {
+                    continue;
+                };
+                let original = Path::new(&entry.original);
+                if let Some(parent) = original.parent() {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        CdeError::io("Failed to create directory")
+                            .with_path(parent)
+                            .caused_by(&e)
+                    })?;
+                }
+                fs::rename(stored, original).map_err(|e| {
+                    CdeError::io("Failed to restore path")
+                        .with_path(stored)
+                        .caused_by(&e)
+                })?;
+            }
+            if let Some(trash_dir) = &manifest.trash_dir {
+                let _ = fs::remove_dir_all(trash_dir);
+                // The trash itself goes once empty
+                if let Some(trash) = Path::new(trash_dir).parent() {
+                    let _ = fs::remove_dir(trash);
+                }
+            }
+        }
+        store.delete(MANIFEST_NAMESPACE, id)?;
+        Ok(RestoreReport {
+            id: manifest.id,
+            restored: manifest
+                .entries
+                .into_iter()
+                .map(|entry| entry.original)
+                .collect(),
+        })
+    })
+}
+
+/// `safe_delete_with` on the shared state store
+pub fn safe_delete(
+    paths: &[String],
+    to_trash: bool,
+    project: Option<&str>,
+) -> Result<DeleteManifest, CdeError> {
+    state::with_shared(|store| safe_delete_with(store, paths, to_trash, project))
+}
+
+/// `restore_deleted_with` on the shared state store
+pub fn restore_deleted(id: &str) -> Result<RestoreReport, CdeError> {
+    state::with_shared(|store| restore_deleted_with(store, id))
+}
+
+/// Deletes files and directories so that `restore_deleted_py` can undo it, as JSON
+///
+/// With `to_trash` the paths go to the trash of the operating system; otherwise
+/// they are moved into `.cde_trash/<id>/` at the root of `project_root` (by
+/// default the nearest ancestor of the first path holding `.git`). Nothing is
+/// deleted when a path is missing or cannot be moved. Returns the manifest: its
+/// `id`, `deleted_at`, `backend` ("local" or "system"), `trash_dir` and the
+/// `entries` (`original`, `stored`, `is_dir`, `size`).
+#[pyfunction]
+#[pyo3(signature = (paths, to_trash=false, project_root=None))]
+fn safe_delete_py(
+    py: Python<'_>,
+    paths: Vec<String>,
+    to_trash: bool,
+    project_root: Option<&str>,
+) -> PyResult<String> {
+    let manifest = py.detach(|| safe_delete(&paths, to_trash, project_root))?;
+    Ok(to_json(&manifest)?)
+}
+
+/// Restores what the deletion `manifest_id` removed, as JSON (`id`, `restored`)
+///
+/// Raises `InvalidInputError`, restoring nothing, when one of the paths exists
+/// again, and `NotFoundError` for an unknown or already restored id.
+#[pyfunction]
+fn restore_deleted_py(py: Python<'_>, manifest_id: &str) -> PyResult<String> {
+    let report = py.detach(|| restore_deleted(manifest_id))?;
+    Ok(to_json(&report)?)
+}
+
+/// Adds the undoable deletion functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(safe_delete_py, m)?)?;
+    m.add_function(wrap_pyfunction!(restore_deleted_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deletes_to_local_trash_and_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join(".git")).unwrap();
+        fs::create_dir_all(project.join("src/old")).unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(project.join("src/old/a.rs"), "a").unwrap();
+        let paths: Vec<String> = ["src/main.rs", "src/old", "src/old/a.rs"]
+            .iter()
+            .map(|path| project.join(path).to_string_lossy().into_owned())
+            .collect();
+
+        let manifest = safe_delete_with(&store, &paths, false, None).unwrap();
+        assert_eq!(manifest.backend, "local");
+        // The file inside the deleted directory goes along with it
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.entries[0].size, 12);
+        assert!(manifest.entries[1].is_dir);
+        assert!(!project.join("src/main.rs").exists() && !project.join("src/old").exists());
+        let trash_dir = PathBuf::from(manifest.trash_dir.clone().unwrap());
+        assert!(trash_dir.starts_with(project.canonicalize().unwrap().join(TRASH_DIR)));
+        assert!(trash_dir.join("manifest.json").is_file());
+
+        // Nothing moves when one path is missing
+        fs::write(project.join("keep.md"), "").unwrap();
+        let partial = vec![
+            project.join("keep.md").to_string_lossy().into_owned(),
+            paths[0].clone(),
+        ];
+        assert!(safe_delete_with(&store, &partial, false, None).is_err());
+        assert!(project.join("keep.md").exists());
+
+        fs::write(project.join("src/main.rs"), "new").unwrap();
+        assert!(restore_deleted_with(&store, &manifest.id).is_err());
+        assert!(project.join("src/old").symlink_metadata().is_err());
+        fs::remove_file(project.join("src/main.rs")).unwrap();
+
+        let report = restore_deleted_with(&store, &manifest.id).unwrap();
+        assert_eq!(report.restored.len(), 2);
+        assert_eq!(
+            fs::read_to_string(project.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert!(project.join("src/old/a.rs").is_file());
+        assert!(!project.join(TRASH_DIR).exists());
+        assert!(restore_deleted_with(&store, &manifest.id).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// src/trend.rs
+//! History of the quality scores of a project
+//!
+//! Every audit and documentation quality analysis run from Python with
+//! `record_trend` records its scores in the state store, with the time and the
+//! Git commit checked out, one entry per run under the project's namespace.
+//! Keys hold the timestamp, so a prefix scan returns the runs in order; the
+//! oldest are dropped past `MAX_RUNS`. A trend is the series of one metric over
+//! the last runs, and it regresses when its latest value fell more than a
+//! threshold below the best of the runs before it.
+
+use crate::documentation::QualityReport;
+use crate::error::{to_json, CdeError};
+use crate::metrics;
+use crate::state::{self, StateStore};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const RUN_PREFIX: &str = "quality_runs/";
+
+/// Runs kept per project
+pub const MAX_RUNS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityRun {
+    /// RFC 3339
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    /// What produced the scores, such as "audit"
+    pub source: String,
+    pub scores: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub timestamp: String,
+    pub git_sha: Option<String>,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityTrend {
+    pub metric: String,
+    /// Oldest first
+    pub points: Vec<TrendPoint>,
+    pub latest: Option<f64>,
+    /// Change since the run before the latest
+    pub change: Option<f64>,
+    /// Best value before the latest run
+    pub best: Option<f64>,
+    /// How far the latest value is below `best`
+    pub drop: f64,
+    pub regression: bool,
+    /// Why the trend regressed
+    pub message: Option<String>,
+}
+
+/// Commit checked out in the repository containing `root`
+pub(crate) fn head_sha(root: &Path) -> Option<String> {
+    let repository = git2::Repository::discover(root).ok()?;
+    let commit = repository.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// Records `scores` of a run of `source` on the project at `root`
+pub fn record_run_with(
+    store: &StateStore,
+    root: &str,
+    source: &str,
+    scores: BTreeMap<String, f64>,
+) -> Result<QualityRun, CdeError> {
+    let now = chrono::Utc::now();
+    let run = QualityRun {
+        timestamp: now.to_rfc3339(),
+        git_sha: head_sha(Path::new(root)),
+        source: source.to_string(),
+        // Two decimals are enough for a score, and keep f32 noise out
+        scores: scores
+            .into_iter()
+            .map(|(metric, value)| (metric, (value * 100.0).round() / 100.0))
+            .collect(),
+    };
+    let namespace = state::namespace(root);
+    // Zero-padded so keys sort by time; the nanoseconds keep runs in the same
+    // millisecond apart
+    let key = format!(
+        "{}{:020}",
+        RUN_PREFIX,
+        now.timestamp_nanos_opt().unwrap_or_default()
+    );
+    let value = serde_json::to_string(&run)
+        .map_err(|e| CdeError::serialization("Failed to serialize quality run").caused_by(&e))?;
+    store.set(&namespace, &key, &value, None)?;
+
+    let keys: Vec<String> = store
+        .scan(&namespace, RUN_PREFIX, None)?
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+    for key in &keys[..keys.len().saturating_sub(MAX_RUNS)] {
+        store.delete(&namespace, key)?;
+    }
+    Ok(run)
+}
+
+/// Records `scores` in the shared store, logging instead of failing: a run that
+/// could not be recorded must not fail the analysis that produced it
+pub(crate) fn record_run(root: &str, source: &str, scores: BTreeMap<String, f64>) {
+    if let Err(e) = state::with_shared(|store| record_run_with(store, root, source, scores)) {
+        tracing::warn!(root = %root, error = %e, "Failed to record quality run");
+    }
+}
+
+/// Records the score of a complete documentation quality analysis
+pub(crate) fn record_quality_run(root: &str, report: &QualityReport) {
+    if !report.cancelled {
+        let scores =
+            BTreeMap::from([("quality_score".to_string(), f64::from(report.quality_score))]);
+        record_run(root, "documentation_quality", scores);
+    }
+}
+
+/// Trend of `metric` over the last `window` runs of the project at `root` that
+/// measured it; regressed when the latest value is more than `threshold` below
+/// the best before it
+pub fn quality_trend_with(
+    store: &StateStore,
+    root: &str,
+    metric: &str,
+    window: usize,
+    threshold: f64,
+) -> Result<QualityTrend, CdeError> {
+    metrics::timed("quality_trend", || {
+        if window == 0 {
+            return Err(CdeError::invalid_input("window must be at least 1"));
+        }
+        let mut points: Vec<TrendPoint> = store
+            .scan(&state::namespace(root), RUN_PREFIX, None)?
+            .into_iter()
+            .filter_map(|entry| serde_json::from_str::<QualityRun>(&entry.value).ok())
+            .filter_map(|run| {
+                let value = *run.scores.get(metric)?;
+                Some(TrendPoint {
+                    timestamp: run.timestamp,
+                    git_sha: run.git_sha,
+                    value,
+                })
+            })
+            .collect();
+        points.drain(..points.len().saturating_sub(window));
+
+        let latest = points.last().map(|point| point.value);
+        let earlier = &points[..points.len().saturating_sub(1)];
+        let change = latest
+            .zip(earlier.last())
+            .map(|(latest, previous)| latest - previous.value);
+        let best = earlier.iter().map(|point| point.value).reduce(f64::max);
+        let drop = latest
+            .zip(best)
+            .map_or(0.0, |(latest, best)| (best - latest).max(0.0));
+        let regression = drop > threshold;
+        let message = regression.then(|| {
+            let best_point = earlier
+                .iter()
+                .rev()
+                .find(|point| Some(point.value) == best)
+                .expect("best is one of the earlier points");
+            let since = best_point
+                .git_sha
+                .as_deref()
+                .map(|sha| format!(" at {}", &sha[..sha.len().min(8)]))
+                .unwrap_or_default();
+            format!(
+                "{} dropped {:.1} points, from {:.1}{} ({}) to {:.1}",
+                metric,
+                drop,
+                best_point.value,
+                since,
+                best_point.timestamp,
+                latest.unwrap_or_default()
+            )
+        });
+        Ok(QualityTrend {
+            metric: metric.to_string(),
+            points,
+            latest,
+            change,
+            best,
+            drop,
+            regression,
+            message,
+        })
+    })
+}
+
+/// Trend of a quality metric over the recorded runs of a project, as JSON
+///
+/// Runs are recorded by `audit_project_py` (metrics "score", "documentation",
+/// "metadata", "workflows" and "repo_health") and by
+/// `analyze_documentation_quality_py` ("quality_score") when called with
+/// `record_trend=True`. Returns the `points` of the last `window` runs that
+/// measured `metric` (timestamp, git_sha, value), the `latest` value and its
+/// `change` since the run before, and `regression` when it is more than
+/// `threshold` points below the `best` of the runs before it, with a `message`
+/// to warn about.
+#[pyfunction]
+#[pyo3(signature = (root, metric="score", window=20, threshold=5.0))]
+fn get_quality_trend_py(
+    py: Python<'_>,
+    root: &str,
+    metric: &str,
+    window: usize,
+    threshold: f64,
+) -> PyResult<String> {
+    let trend = py.detach(|| {
+        state::with_shared(|store| quality_trend_with(store, root, metric, window, threshold))
+    })?;
+    Ok(to_json(&trend)?)
+}
+
+/// Adds the quality trend functions to the module
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(get_quality_trend_py, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend_detects_regression() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(&dir.path().join("state.sqlite3")).unwrap();
+        let root = dir.path().to_str().unwrap();
+        let record = |score: f64| {
+            let scores = BTreeMap::from([("score".to_string(), score)]);
+            record_run_with(&store, root, "audit", scores).unwrap()
+        };
+        for score in [60.0, 90.0, 88.0, 82.0] {
+            record(score);
+        }
+        record_run_with(
+            &store,
+            root,
+            "quality",
+            BTreeMap::from([("quality_score".to_string(), 50.0)]),
+        )
+        .unwrap();
+        assert_eq!(record(80.0).git_sha, None);
+
+        let trend = quality_trend_with(&store, root, "score", 4, 5.0).unwrap();
+        let values: Vec<f64> = trend.points.iter().map(|p| p.value).collect();
+        assert_eq!(values, [90.0, 88.0, 82.0, 80.0]);
+        assert_eq!(trend.change, Some(-2.0));
+        assert_eq!(trend.best, Some(90.0));
+        assert!(trend.regression);
+        assert!(trend
+            .message
+            .unwrap()
+            .starts_with("score dropped 10.0 points, from 90.0"));
+
+        let trend = quality_trend_with(&store, root, "score", 2, 5.0).unwrap();
+        assert!(!trend.regression);
+        assert_eq!(trend.drop, 2.0);
+        let empty = quality_trend_with(&store, root, "metadata", 20, 5.0).unwrap();
+        assert!(empty.points.is_empty() && empty.latest.is_none() && !empty.regression);
+        assert!(quality_trend_with(&store, root, "score", 0, 5.0).is_err());
+    }
+}
@@ -1,7 +1,11 @@
 // src/workflow_validator.rs
+use crate::config_validator::ValidationIssue;
+use crate::error::CdeError;
+use crate::metrics;
+use crate::paging::{cap, CapLists};
+use crate::path_policy::{self, WalkGuard};
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
-use serde_yaml;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -20,20 +24,25 @@ pub struct WorkflowPhase {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Workflow {
     pub name: String,
+    #[serde(deserialize_with = "version_string")]
     pub version: String,
     pub phases: Vec<WorkflowPhase>,
     #[serde(flatten)]
     pub extra: std::collections::HashMap<String, serde_yaml::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct WorkflowValidationIssue {
-    pub severity: String, // "error", "warning", "info"
-    pub file: String,
-    pub line: Option<usize>,
-    pub message: String,
+/// `version: 1.0` written without quotes is a YAML number
+fn version_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::String(version) => Ok(version),
+        serde_yaml::Value::Number(version) => Ok(version.to_string()),
+        _ => Err(serde::de::Error::custom("version must be a string or a number")),
+    }
 }
 
+/// Issues are those of the config validator, which reports them as SARIF too
+pub type WorkflowValidationIssue = ValidationIssue;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WorkflowValidationReport {
     pub valid: bool,
@@ -44,12 +53,25 @@ pub struct WorkflowValidationReport {
     pub workflows_found: Vec<String>,
     pub missing_templates: Vec<String>,
     pub summary: String,
+    /// Whether lists were cut to `max_list_items`
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl CapLists for WorkflowValidationReport {
+    fn cap_lists(&mut self, max_items: usize) {
+        self.truncated |= cap(&mut self.issues, max_items)
+            | cap(&mut self.workflows_found, max_items)
+            | cap(&mut self.missing_templates, max_items);
+    }
 }
 
 /// Encuentra todos los archivos YAML en un directorio
-fn find_yaml_files(root: &Path) -> Vec<PathBuf> {
+fn find_yaml_files(root: &Path, guard: &WalkGuard) -> Vec<PathBuf> {
     walkdir::WalkDir::new(root)
+        .max_depth(guard.max_depth())
         .into_iter()
+        .filter_entry(|e| guard.allows(e.path(), e.path_is_symlink()))
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .filter(|e| {
@@ -63,13 +85,16 @@ fn find_yaml_files(root: &Path) -> Vec<PathBuf> {
         .collect()
 }
 
-/// Valida la sintaxis YAML de un archivo
-fn validate_yaml_syntax(path: &Path) -> Result<serde_yaml::Value, String> {
+/// Valida la sintaxis YAML de un archivo, con la línea del error si se conoce
+fn validate_yaml_syntax(path: &Path) -> Result<serde_yaml::Value, (String, Option<usize>)> {
     let content = fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
+        .map_err(|e| (format!("Failed to read file: {}", e), None))?;
 
-    serde_yaml::from_str(&content)
-        .map_err(|e| format!("Invalid YAML syntax: {}", e))
+    // Un BOM al principio hace que serde_yaml vea dos documentos
+    serde_yaml::from_str(content.trim_start_matches('\u{feff}')).map_err(|e| {
+        let line = e.location().map(|location| location.line());
+        (format!("Invalid YAML syntax: {}", e), line)
+    })
 }
 
 /// Valida un workflow completo
@@ -78,21 +103,24 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
     let path_str = path.to_string_lossy().to_string();
 
     // Validar sintaxis YAML
-    let yaml_value = match validate_yaml_syntax(path) {
+    let mut yaml_value = match validate_yaml_syntax(path) {
         Ok(val) => val,
-        Err(e) => {
+        Err((message, line)) => {
             issues.push(WorkflowValidationIssue {
                 severity: "error".to_string(),
                 file: path_str.clone(),
-                line: None,
-                message: e,
+                line,
+                message,
+                rule: Some("yaml-syntax".to_string()),
             });
             return issues;
         }
     };
 
-    // Intentar parsear como Workflow
-    let workflow: Result<Workflow, _> = serde_yaml::from_value(yaml_value.clone());
+    // Intentar parsear como Workflow, con las claves `<<: *ancla` ya fusionadas
+    let workflow: Result<Workflow, _> = yaml_value
+        .apply_merge()
+        .and_then(|()| serde_yaml::from_value(yaml_value));
 
     match workflow {
         Ok(wf) => {
@@ -103,6 +131,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                     file: path_str.clone(),
                     line: None,
                     message: "Workflow has no phases defined".to_string(),
+                    rule: Some("workflow-structure".to_string()),
                 });
             }
 
@@ -115,6 +144,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                         file: path_str.clone(),
                         line: Some(idx + 1),
                         message: format!("Phase {} has empty ID", idx),
+                        rule: Some("workflow-structure".to_string()),
                     });
                 }
 
@@ -124,6 +154,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                         file: path_str.clone(),
                         line: Some(idx + 1),
                         message: format!("Duplicate phase ID: {}", phase.id),
+                        rule: Some("workflow-structure".to_string()),
                     });
                 }
             }
@@ -145,6 +176,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                                         "Phase '{}' references unknown phase in input: {}",
                                         phase.id, input
                                     ),
+                                    rule: Some("workflow-reference".to_string()),
                                 });
                             }
                         }
@@ -166,6 +198,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                                 "Phase '{}' references missing template: {}",
                                 phase.id, template
                             ),
+                            rule: Some("workflow-template".to_string()),
                         });
                     }
                 }
@@ -177,6 +210,7 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
                 file: path_str.clone(),
                 line: None,
                 message: format!("Could not parse as workflow (might be another YAML type): {}", e),
+                rule: Some("workflow-parse".to_string()),
             });
         }
     }
@@ -185,14 +219,19 @@ fn validate_workflow_file(path: &Path) -> Vec<WorkflowValidationIssue> {
 }
 
 /// Valida todos los workflows en un proyecto en paralelo
-pub fn validate_workflows(root_path: &str) -> Result<WorkflowValidationReport, String> {
+pub fn validate_workflows(root_path: &str) -> Result<WorkflowValidationReport, CdeError> {
+    metrics::timed("validate_workflows", || validate_all(root_path))
+}
+
+fn validate_all(root_path: &str) -> Result<WorkflowValidationReport, CdeError> {
     let path = Path::new(root_path);
+    let guard = path_policy::walk_guard(path)?;
     if !path.is_dir() {
-        return Err(format!("'{}' is not a valid directory.", root_path));
+        return Err(CdeError::not_a_directory(root_path));
     }
 
     // Buscar archivos YAML
-    let yaml_files = find_yaml_files(path);
+    let yaml_files = find_yaml_files(path, &guard);
     let total_files = yaml_files.len();
 
     if total_files == 0 {
@@ -205,6 +244,7 @@ pub fn validate_workflows(root_path: &str) -> Result<WorkflowValidationReport, S
             workflows_found: Vec::new(),
             missing_templates: Vec::new(),
             summary: "No YAML files found".to_string(),
+            truncated: false,
         });
     }
 
@@ -253,7 +293,7 @@ pub fn validate_workflows(root_path: &str) -> Result<WorkflowValidationReport, S
             // Extraer nombre del template del mensaje
             i.message
                 .split(':')
-                .last()
+                .next_back()
                 .unwrap_or("")
                 .trim()
                 .to_string()
@@ -286,5 +326,75 @@ pub fn validate_workflows(root_path: &str) -> Result<WorkflowValidationReport, S
         workflows_found,
         missing_templates,
         summary,
+        truncated: false,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rules(issues: &[WorkflowValidationIssue]) -> Vec<String> {
+        let mut rules: Vec<String> = issues
+            .iter()
+            .map(|issue| format!("{} {}", issue.severity, issue.rule.as_deref().unwrap_or("")))
+            .collect();
+        rules.sort();
+        rules
+    }
+
+    #[test]
+    fn test_workflow_corpus() {
+        crate::corpus::check("workflows", |path| {
+            serde_json::json!(rules(&validate_workflow_file(path)))
+        });
+    }
+
+    fn phase(id: String, name: String) -> WorkflowPhase {
+        WorkflowPhase {
+            id,
+            name,
+            description: None,
+            inputs: None,
+            outputs: None,
+            prompt_template: None,
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_any_file_yields_issues_not_panics(content in r"[a-z:\-\[\]{}&*<|>#'\x22 \t\n]{0,120}|\PC{0,120}") {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("workflow.yml");
+            fs::write(&path, content).unwrap();
+            let issues = validate_workflow_file(&path);
+            prop_assert!(issues.iter().all(|issue| issue.rule.is_some()));
+        }
+
+        #[test]
+        fn prop_written_workflow_is_valid(
+            name in r"\PC{0,30}",
+            version in r"\PC{0,10}",
+            phases in prop::collection::vec((r"\PC{0,15}", r"\PC{0,30}"), 1..6),
+        ) {
+            let workflow = Workflow {
+                name,
+                version,
+                // The index keeps ids unique and non-empty
+                phases: phases
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (id, name))| phase(format!("{i}{id}"), name))
+                    .collect(),
+                extra: std::collections::HashMap::new(),
+            };
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("workflow.yml");
+            fs::write(&path, serde_yaml::to_string(&workflow).unwrap()).unwrap();
+            prop_assert_eq!(rules(&validate_workflow_file(&path)), Vec::<String>::new());
+        }
+    }
+}
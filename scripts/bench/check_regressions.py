@@ -0,0 +1,76 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+"""
+Detecta regresiones de rendimiento en los benchmarks de Rust (criterion).
+
+Lee los cambios que criterion guarda al comparar contra un baseline
+(`cargo bench --bench parallel_benchmarks -- --baseline main`) y falla cuando
+el tiempo medio de algún benchmark empeoró más del umbral. Se usa el límite
+inferior del intervalo de confianza, así el ruido de la máquina no cuenta como
+regresión.
+
+Usage:
+    python scripts/bench/check_regressions.py [--threshold 10] [--criterion-dir target/criterion]
+"""
+
+import argparse
+import json
+import sys
+from pathlib import Path
+
+
+def load_changes(criterion_dir: Path) -> list[tuple[str, float, float]]:
+    """Devuelve (benchmark, cambio medio, límite inferior) en porcentaje."""
+    changes = []
+    for estimates in sorted(criterion_dir.glob("**/change/estimates.json")):
+        bench_dir = estimates.parent.parent
+        name = str(bench_dir.relative_to(criterion_dir))
+        benchmark_json = bench_dir / "new" / "benchmark.json"
+        if benchmark_json.exists():
+            name = json.loads(benchmark_json.read_text(encoding="utf-8")).get("full_id", name)
+        mean = json.loads(estimates.read_text(encoding="utf-8"))["mean"]
+        changes.append(
+            (
+                name,
+                mean["point_estimate"] * 100,
+                mean["confidence_interval"]["lower_bound"] * 100,
+            )
+        )
+    return changes
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[1])
+    parser.add_argument(
+        "--threshold",
+        type=float,
+        default=10.0,
+        help="Porcentaje de empeoramiento tolerado (por defecto 10)",
+    )
+    parser.add_argument(
+        "--criterion-dir",
+        type=Path,
+        default=Path("target/criterion"),
+        help="Directorio de resultados de criterion",
+    )
+    args = parser.parse_args()
+
+    changes = load_changes(args.criterion_dir)
+    if not changes:
+        print(f"No comparisons found in {args.criterion_dir}; run the benchmarks with --baseline first.")
+        return 1
+
+    regressions = [change for change in changes if change[2] > args.threshold]
+    for name, mean, lower in sorted(changes, key=lambda change: -change[1]):
+        marker = "❌" if lower > args.threshold else "  "
+        print(f"{marker} {name:<60} {mean:+7.2f}% (at least {lower:+.2f}%)")
+
+    if regressions:
+        print(f"\n{len(regressions)} benchmarks regressed by more than {args.threshold:.0f}%.")
+        return 1
+    print(f"\nNo regression above {args.threshold:.0f}%.")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -121,6 +121,7 @@ class ScanDocumentationUseCase:
             "root": [],
             "other": [],
         }
+        placements = self._classify_with_rust(project_path)
 
         for doc_data in rust_result:
             md_file = project / doc_data["path"]
@@ -138,15 +139,24 @@ class ScanDocumentationUseCase:
             if not has_metadata:
                 results["missing_metadata"].append(str(relative))
 
-            location = self._categorize_file(relative)
-            standard_dirs[location].append(file_info)
+            placement = placements.get(relative.as_posix())
+            if placement is None:
+                location = self._categorize_file(relative)
+            else:
+                location = placement["category"] or (
+                    "root" if relative.parent == Path(".") else "other"
+                )
+            standard_dirs.setdefault(location, []).append(file_info)
 
             if file_info["lines"] > 1000:
                 results["large_files"].append(
                     {"path": str(relative), "lines": file_info["lines"]}
                 )
 
-            if relative.parent == Path(".") and relative.name not in {
+            if placement is not None:
+                if placement["orphaned"]:
+                    results["orphaned_docs"].append(str(relative))
+            elif relative.parent == Path(".") and relative.name not in {
                 "README.md",
                 "CHANGELOG.md",
                 "CONTRIBUTING.md",
@@ -165,6 +175,19 @@ class ScanDocumentationUseCase:
         # Filter based on detail_level
         return self._filter_by_detail_level(results, detail_level)
 
+    def _classify_with_rust(self, project_path: str) -> Dict[str, Dict[str, Any]]:
+        """Placement of each document by the shared layout conventions, by path.
+
+        Empty when the compiled module predates `classify_documents_py`.
+        """
+        import cde_rust_core
+
+        classify = getattr(cde_rust_core, "classify_documents_py", None)
+        if classify is None:
+            return {}
+        placements = json.loads(classify(project_path))
+        return {placement["path"]: placement for placement in placements}
+
     def _enhance_location_categorization(
         self, by_location: Dict[str, Any], project: Path
     ) -> Dict[str, Any]:
@@ -25,7 +25,6 @@ Example:
     >>> print(f"Broken links: {len(report.broken_internal_links)}")
 """
 
-import json
 from dataclasses import dataclass
 from pathlib import Path
 from typing import Any, Dict, List, Optional
@@ -193,8 +192,7 @@ class RustDocumentationScanner:
         root_path = str(Path(root_path).resolve())
 
         try:
-            result_json = cde_rust_core.scan_documentation_py(root_path)  # type: ignore
-            data = json.loads(result_json)
+            data = cde_rust_core.scan_documentation_dict(root_path)  # type: ignore
             return [Document.from_dict(doc) for doc in data]
         except Exception as e:
             raise ValueError(f"Failed to scan documentation: {e}") from e
@@ -226,8 +224,7 @@ class RustDocumentationScanner:
         root_path = str(Path(root_path).resolve())
 
         try:
-            result_json = cde_rust_core.analyze_documentation_quality_py(root_path)  # type: ignore
-            data = json.loads(result_json)
+            data = cde_rust_core.analyze_documentation_quality_dict(root_path)  # type: ignore
             return QualityReport.from_dict(data)
         except Exception as e:
             raise ValueError(f"Failed to analyze quality: {e}") from e
@@ -259,8 +256,7 @@ class RustDocumentationScanner:
         root_path = str(Path(root_path).resolve())
 
         try:
-            result_json = cde_rust_core.validate_workflows_py(root_path)  # type: ignore
-            data = json.loads(result_json)
+            data = cde_rust_core.validate_workflows_dict(root_path)  # type: ignore
             return WorkflowValidationReport.from_dict(data)
         except Exception as e:
             raise ValueError(f"Failed to validate workflows: {e}") from e
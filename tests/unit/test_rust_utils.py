@@ -8,7 +8,6 @@ Tests cover:
 - Error handling for invalid inputs
 """
 
-import json
 from pathlib import Path
 from unittest.mock import patch
 
@@ -271,7 +270,7 @@ class TestRustDocumentationScanner:
                 "headers": ["Test"],
             }
         ]
-        mock_rust.scan_documentation_py.return_value = json.dumps(mock_docs)
+        mock_rust.scan_documentation_dict.return_value = mock_docs
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -295,7 +294,7 @@ class TestRustDocumentationScanner:
     @patch("cde_orchestrator.rust_utils.cde_rust_core")
     def test_scan_documentation_converts_path_to_string(self, mock_rust):
         """Test that Path objects are converted to strings."""
-        mock_rust.scan_documentation_py.return_value = "[]"
+        mock_rust.scan_documentation_dict.return_value = []
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -303,14 +302,14 @@ class TestRustDocumentationScanner:
         scanner.scan_documentation(Path("./docs"))
 
         # Verify string path was passed to Rust
-        call_args = mock_rust.scan_documentation_py.call_args[0]
+        call_args = mock_rust.scan_documentation_dict.call_args[0]
         assert isinstance(call_args[0], str)
 
     @pytest.mark.skipif(not RUST_AVAILABLE, reason="Rust core not available")
     @patch("cde_orchestrator.rust_utils.cde_rust_core")
     def test_scan_documentation_handles_rust_exceptions(self, mock_rust):
         """Test error handling when Rust core raises exception."""
-        mock_rust.scan_documentation_py.side_effect = Exception("Rust error")
+        mock_rust.scan_documentation_dict.side_effect = Exception("Rust error")
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -343,9 +342,7 @@ class TestRustDocumentationScanner:
             "issues": [],
             "recommendations": [],
         }
-        mock_rust.analyze_documentation_quality_py.return_value = json.dumps(
-            mock_quality_data
-        )
+        mock_rust.analyze_documentation_quality_dict.return_value = mock_quality_data
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -353,14 +350,14 @@ class TestRustDocumentationScanner:
         scanner.analyze_quality(Path("."))
 
         # Verify string path was passed
-        call_args = mock_rust.analyze_documentation_quality_py.call_args[0]
+        call_args = mock_rust.analyze_documentation_quality_dict.call_args[0]
         assert isinstance(call_args[0], str)
 
     @pytest.mark.skipif(not RUST_AVAILABLE, reason="Rust core not available")
     @patch("cde_orchestrator.rust_utils.cde_rust_core")
     def test_analyze_quality_handles_rust_exceptions(self, mock_rust):
         """Test error handling in analyze_quality when Rust raises exception."""
-        mock_rust.analyze_documentation_quality_py.side_effect = Exception("Rust error")
+        mock_rust.analyze_documentation_quality_dict.side_effect = Exception("Rust error")
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -383,7 +380,7 @@ class TestRustDocumentationScanner:
                 "headers": ["Test"],
             }
         ]
-        mock_rust.scan_documentation_py.return_value = json.dumps(mock_docs)
+        mock_rust.scan_documentation_dict.return_value = mock_docs
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True
@@ -412,9 +409,7 @@ class TestRustDocumentationScanner:
             "issues": [],
             "recommendations": [],
         }
-        mock_rust.analyze_documentation_quality_py.return_value = json.dumps(
-            mock_report
-        )
+        mock_rust.analyze_documentation_quality_dict.return_value = mock_report
 
         scanner = RustDocumentationScanner()
         scanner.is_available = True